@@ -5,6 +5,8 @@ use std::{env, error::Error, path::Path};
 
 #[derive(Default)]
 struct Parameters {
+    fold_constants: bool,
+    inline: bool,
     #[cfg(feature = "spv-in")]
     spv_flow_dump_prefix: Option<String>,
     #[cfg(feature = "spv-out")]
@@ -13,6 +15,8 @@ struct Parameters {
     msl: naga::back::msl::Options,
     #[cfg(feature = "glsl-out")]
     glsl: naga::back::glsl::Options,
+    #[cfg(feature = "hlsl-out")]
+    hlsl: naga::back::hlsl::Options,
 }
 
 trait PrettyResult {
@@ -54,6 +58,8 @@ fn main() {
         //TODO: use `strip_prefix` when MSRV reaches 1.45.0
         if arg.starts_with("--") {
             match &arg[2..] {
+                "fold-constants" => params.fold_constants = true,
+                "inline" => params.inline = true,
                 #[cfg(feature = "spv-in")]
                 "flow-dir" => params.spv_flow_dump_prefix = args.next(),
                 #[cfg(feature = "glsl-out")]
@@ -71,6 +77,22 @@ fn main() {
                         panic!("Unknown profile: {}", string)
                     };
                 }
+                #[cfg(feature = "hlsl-out")]
+                "shader-model" => {
+                    use naga::back::hlsl::ShaderModel;
+                    let string = args.next().unwrap();
+                    params.hlsl.shader_model = match string.as_str() {
+                        "50" => ShaderModel::V5_0,
+                        "51" => ShaderModel::V5_1,
+                        "60" => ShaderModel::V6_0,
+                        "61" => ShaderModel::V6_1,
+                        "62" => ShaderModel::V6_2,
+                        "63" => ShaderModel::V6_3,
+                        "64" => ShaderModel::V6_4,
+                        "65" => ShaderModel::V6_5,
+                        _ => panic!("Unknown shader model: {}", string),
+                    };
+                }
                 other => log::warn!("Unknown parameter: {}", other),
             }
         } else if input_path.is_none() {
@@ -89,7 +111,7 @@ fn main() {
             return;
         }
     };
-    let module = match Path::new(input_path)
+    let mut module = match Path::new(input_path)
         .extension()
         .expect("Input has no extension?")
         .to_str()
@@ -103,6 +125,14 @@ fn main() {
             let input = fs::read(input_path).unwrap();
             naga::front::spv::parse_u8_slice(&input, &options).unwrap()
         }
+        #[cfg(feature = "spv-in")]
+        "spvasm" => {
+            let options = naga::front::spv::Options {
+                flow_graph_dump_prefix: params.spv_flow_dump_prefix.map(std::path::PathBuf::from),
+            };
+            let input = fs::read_to_string(input_path).unwrap();
+            naga::front::spv::parse_str(&input, &options).unwrap()
+        }
         #[cfg(feature = "wgsl-in")]
         "wgsl" => {
             let input = fs::read_to_string(input_path).unwrap();
@@ -115,6 +145,17 @@ fn main() {
                 }
             }
         }
+        #[cfg(feature = "ir-in")]
+        "ir" => {
+            let input = fs::read_to_string(input_path).unwrap();
+            match naga::front::ir::parse_str(&input) {
+                Ok(module) => module,
+                Err(error) => {
+                    let (line, column) = error.span.location(&input);
+                    panic!("{}:{}:{}: {}", input_path, line, column, error);
+                }
+            }
+        }
         #[cfg(feature = "glsl-in")]
         "vert" => {
             let input = fs::read_to_string(input_path).unwrap();
@@ -157,6 +198,40 @@ fn main() {
             )
             .unwrap_pretty()
         }
+        #[cfg(feature = "glsl-in")]
+        stage @ "geom"
+        | stage @ "tesc"
+        | stage @ "tese"
+        | stage @ "rgen"
+        | stage @ "rint"
+        | stage @ "rahit"
+        | stage @ "rchit"
+        | stage @ "rmiss"
+        | stage @ "rcall" => {
+            let input = fs::read_to_string(input_path).unwrap();
+            let mut entry_points = naga::FastHashMap::default();
+            let stage = match stage {
+                "geom" => naga::ShaderStage::Geometry,
+                "tesc" => naga::ShaderStage::TessellationControl,
+                "tese" => naga::ShaderStage::TessellationEvaluation,
+                "rgen" => naga::ShaderStage::RayGeneration,
+                "rint" => naga::ShaderStage::Intersection,
+                "rahit" => naga::ShaderStage::AnyHit,
+                "rchit" => naga::ShaderStage::ClosestHit,
+                "rmiss" => naga::ShaderStage::Miss,
+                "rcall" => naga::ShaderStage::Callable,
+                _ => unreachable!(),
+            };
+            entry_points.insert("main".to_string(), stage);
+            naga::front::glsl::parse_str(
+                &input,
+                &naga::front::glsl::Options {
+                    entry_points,
+                    defines: Default::default(),
+                },
+            )
+            .unwrap_pretty()
+        }
         other => {
             if true {
                 // prevent "unreachable_code" warnings
@@ -166,6 +241,17 @@ fn main() {
         }
     };
 
+    if params.inline {
+        naga::proc::inline_calls(&mut module, &Default::default()).unwrap_pretty();
+        // the functions are only removed if something still uses the module
+        if !module.entry_points.is_empty() {
+            naga::proc::compact(&mut module, |_| true);
+        }
+    }
+    if params.fold_constants {
+        naga::proc::fold_constants(&mut module).unwrap_pretty();
+    }
+
     let output_path = match output_path {
         Some(ref string) => string,
         None => {
@@ -176,9 +262,25 @@ fn main() {
 
     // validate the IR
     #[allow(unused_variables)]
-    let analysis = naga::proc::Validator::new()
-        .validate(&module)
-        .unwrap_pretty();
+    let analysis = match naga::proc::Validator::new().validate(&module) {
+        Ok(analysis) => analysis,
+        Err(error) => {
+            // point at the offending code, if the front-end recorded where it came from
+            if error.span().is_defined() {
+                if let Ok(source) = fs::read_to_string(input_path) {
+                    let (line, column) = error.span().location(&source);
+                    print!("{}:{}:{}: ", input_path, line, column);
+                    // explain where the non-uniformity comes from
+                    if let naga::proc::ValidationError::Analysis(ref analysis_error) = error {
+                        println!("{}", analysis_error.emit_to_string(&module, &source));
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Err::<(), _>(error).unwrap_pretty();
+            return;
+        }
+    };
 
     match Path::new(output_path)
         .extension()
@@ -206,14 +308,42 @@ fn main() {
 
             fs::write(output_path, bytes.as_slice()).unwrap();
         }
+        #[cfg(feature = "spv-out")]
+        "spvasm" => {
+            use naga::back::spv;
+
+            let spv = spv::write_vec(&module, &analysis, &params.spv).unwrap_pretty();
+            let text = spv::disassemble(&spv).unwrap();
+            fs::write(output_path, text).unwrap();
+        }
         #[cfg(feature = "glsl-out")]
-        stage @ "vert" | stage @ "frag" | stage @ "comp" => {
+        stage @ "vert"
+        | stage @ "frag"
+        | stage @ "comp"
+        | stage @ "geom"
+        | stage @ "tesc"
+        | stage @ "tese"
+        | stage @ "rgen"
+        | stage @ "rint"
+        | stage @ "rahit"
+        | stage @ "rchit"
+        | stage @ "rmiss"
+        | stage @ "rcall" => {
             use naga::back::glsl;
 
             params.glsl.shader_stage = match stage {
                 "vert" => naga::ShaderStage::Vertex,
                 "frag" => naga::ShaderStage::Fragment,
                 "comp" => naga::ShaderStage::Compute,
+                "geom" => naga::ShaderStage::Geometry,
+                "tesc" => naga::ShaderStage::TessellationControl,
+                "tese" => naga::ShaderStage::TessellationEvaluation,
+                "rgen" => naga::ShaderStage::RayGeneration,
+                "rint" => naga::ShaderStage::Intersection,
+                "rahit" => naga::ShaderStage::AnyHit,
+                "rchit" => naga::ShaderStage::ClosestHit,
+                "rmiss" => naga::ShaderStage::Miss,
+                "rcall" => naga::ShaderStage::Callable,
                 _ => unreachable!(),
             };
 
@@ -235,6 +365,24 @@ fn main() {
                 })
                 .unwrap();
         }
+        #[cfg(feature = "wgsl-out")]
+        "wgsl" => {
+            use naga::back::wgsl;
+            let (wgsl, _) =
+                wgsl::write_string(&module, &analysis, &wgsl::Options::default()).unwrap_pretty();
+            fs::write(output_path, wgsl).unwrap();
+        }
+        #[cfg(feature = "hlsl-out")]
+        "hlsl" => {
+            use naga::back::hlsl;
+            let (hlsl, _) = hlsl::write_string(&module, &analysis, &params.hlsl).unwrap_pretty();
+            fs::write(output_path, hlsl).unwrap();
+        }
+        #[cfg(feature = "ir-out")]
+        "ir" => {
+            let output = naga::back::ir::write_string(&module).unwrap();
+            fs::write(output_path, output).unwrap();
+        }
         #[cfg(feature = "dot-out")]
         "dot" => {
             use naga::back::dot;
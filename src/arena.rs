@@ -1,3 +1,4 @@
+use crate::Span;
 use std::{cmp::Ordering, fmt, hash, marker::PhantomData, num::NonZeroU32, ops};
 
 /// An unique index in the arena array that a handle points to.
@@ -73,6 +74,12 @@ impl<T> Handle<T> {
         let index = self.index.get() - 1;
         index as usize
     }
+
+    /// Creates a handle from a zero-based index.
+    pub(crate) fn from_usize(index: usize) -> Self {
+        let handle_index = NonZeroU32::new(index as u32 + 1).unwrap();
+        Handle::new(handle_index)
+    }
 }
 
 /// A strongly typed range of handles.
@@ -101,6 +108,21 @@ impl<T> fmt::Debug for Range<T> {
         write!(formatter, "{}..{}", self.inner.start, self.inner.end)
     }
 }
+impl<T> Range<T> {
+    /// Creates a range of handles from a range of zero-based indices.
+    pub(crate) fn from_index_range(inner: ops::Range<u32>) -> Self {
+        Range {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the zero-based indices of the handles in this range.
+    pub(crate) fn index_range(&self) -> ops::Range<u32> {
+        self.inner.clone()
+    }
+}
+
 impl<T> Iterator for Range<T> {
     type Item = Handle<T>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -128,10 +150,14 @@ impl<T> Iterator for Range<T> {
     any(feature = "serialize", feature = "deserialize"),
     serde(transparent)
 )]
+#[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Arena<T> {
     /// Values of this arena.
     data: Vec<T>,
+    /// Source spans of the values, indexed the same way.
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(skip))]
+    span_info: Vec<Span>,
 }
 
 impl<T> Default for Arena<T> {
@@ -148,7 +174,10 @@ impl<T: fmt::Debug> fmt::Debug for Arena<T> {
 impl<T> Arena<T> {
     /// Create a new arena with no initial capacity allocated.
     pub fn new() -> Self {
-        Arena { data: Vec::new() }
+        Arena {
+            data: Vec::new(),
+            span_info: Vec::new(),
+        }
     }
 
     /// Returns the current number of items stored in this arena.
@@ -183,12 +212,55 @@ impl<T> Arena<T> {
 
     /// Adds a new value to the arena, returning a typed handle.
     pub fn append(&mut self, value: T) -> Handle<T> {
+        self.append_with_span(value, Span::UNDEFINED)
+    }
+
+    /// Adds a new value with a source span to the arena, returning a typed handle.
+    pub fn append_with_span(&mut self, value: T, span: Span) -> Handle<T> {
         let position = self.data.len() + 1;
         let index = unsafe { Index::new_unchecked(position as u32) };
         self.data.push(value);
+        self.span_info.resize(self.data.len() - 1, Span::UNDEFINED);
+        self.span_info.push(span);
         Handle::new(index)
     }
 
+    /// Get the source span of an element in the arena.
+    pub fn get_span(&self, handle: Handle<T>) -> Span {
+        self.span_info
+            .get(handle.index())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Set the source span of an element in the arena.
+    pub fn set_span(&mut self, handle: Handle<T>, span: Span) {
+        if self.span_info.len() < self.data.len() {
+            self.span_info.resize(self.data.len(), Span::UNDEFINED);
+        }
+        self.span_info[handle.index()] = span;
+    }
+
+    /// Assign a span to all the elements starting from `old_length`,
+    /// which don't have one yet.
+    pub fn fill_spans_from(&mut self, old_length: usize, span: Span) {
+        if self.span_info.len() < self.data.len() {
+            self.span_info.resize(self.data.len(), Span::UNDEFINED);
+        }
+        for info in self.span_info[old_length..].iter_mut() {
+            if !info.is_defined() {
+                *info = span;
+            }
+        }
+    }
+
+    /// Consumes the arena, returning the values in order together with their spans.
+    pub(crate) fn into_values_with_spans(self) -> impl Iterator<Item = (T, Span)> {
+        let mut span_info = self.span_info;
+        span_info.resize(self.data.len(), Span::UNDEFINED);
+        self.data.into_iter().zip(span_info)
+    }
+
     /// Fetch a handle to an existing type.
     pub fn fetch_if<F: Fn(&T) -> bool>(&self, fun: F) -> Option<Handle<T>> {
         self.data
@@ -218,6 +290,25 @@ impl<T> Arena<T> {
         self.fetch_if_or_append(value, T::eq)
     }
 
+    /// Keep only the elements for which `predicate` returns `true`,
+    /// passing it the original handle of each element.
+    ///
+    /// The handles of the retained elements are shifted down to fill the gaps.
+    pub(crate) fn retain_mut<P>(&mut self, mut predicate: P)
+    where
+        P: FnMut(Handle<T>, &mut T) -> bool,
+    {
+        let data = std::mem::take(&mut self.data);
+        let mut span_info = std::mem::take(&mut self.span_info);
+        span_info.resize(data.len(), Span::UNDEFINED);
+        for (index, (mut value, span)) in data.into_iter().zip(span_info).enumerate() {
+            if predicate(Handle::from_usize(index), &mut value) {
+                self.data.push(value);
+                self.span_info.push(span);
+            }
+        }
+    }
+
     pub fn try_get(&self, handle: Handle<T>) -> Option<&T> {
         self.data.get(handle.index.get() as usize - 1)
     }
@@ -118,6 +118,90 @@ impl StatementGraph {
                     //TODO: link to function
                     "Call"
                 }
+                S::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    self.emits.push((id, result));
+                    self.dependencies.push((id, pointer, "pointer"));
+                    self.dependencies.push((id, value, "value"));
+                    if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
+                        self.dependencies.push((id, cmp, "cmp"));
+                    }
+                    "Atomic"
+                }
+                S::Barrier { .. } => "Barrier",
+                S::EmitVertex => "EmitVertex",
+                S::EndPrimitive => "EndPrimitive",
+                S::Subgroup { ref fun, result } => {
+                    self.emits.push((id, result));
+                    match *fun {
+                        crate::SubgroupFunction::Elect => {}
+                        crate::SubgroupFunction::Ballot { predicate } => {
+                            if let Some(predicate) = predicate {
+                                self.dependencies.push((id, predicate, "predicate"));
+                            }
+                        }
+                        crate::SubgroupFunction::Gather { mode, argument } => {
+                            match mode {
+                                crate::GatherMode::BroadcastFirst => {}
+                                crate::GatherMode::Broadcast(index)
+                                | crate::GatherMode::Shuffle(index)
+                                | crate::GatherMode::ShuffleDown(index)
+                                | crate::GatherMode::ShuffleUp(index)
+                                | crate::GatherMode::ShuffleXor(index) => {
+                                    self.dependencies.push((id, index, "index"));
+                                }
+                            }
+                            self.dependencies.push((id, argument, "arg"));
+                        }
+                        crate::SubgroupFunction::Collective { argument, .. } => {
+                            self.dependencies.push((id, argument, "arg"));
+                        }
+                    }
+                    "Subgroup"
+                }
+                S::TraceRay {
+                    acceleration_structure,
+                    flags,
+                    cull_mask,
+                    sbt_offset,
+                    sbt_stride,
+                    miss_index,
+                    origin,
+                    t_min,
+                    direction,
+                    t_max,
+                    payload,
+                } => {
+                    self.dependencies
+                        .push((id, acceleration_structure, "acceleration_structure"));
+                    self.dependencies.push((id, flags, "flags"));
+                    self.dependencies.push((id, cull_mask, "cull_mask"));
+                    self.dependencies.push((id, sbt_offset, "sbt_offset"));
+                    self.dependencies.push((id, sbt_stride, "sbt_stride"));
+                    self.dependencies.push((id, miss_index, "miss_index"));
+                    self.dependencies.push((id, origin, "origin"));
+                    self.dependencies.push((id, t_min, "t_min"));
+                    self.dependencies.push((id, direction, "direction"));
+                    self.dependencies.push((id, t_max, "t_max"));
+                    self.dependencies.push((id, payload, "payload"));
+                    "TraceRay"
+                }
+                S::ReportIntersection {
+                    hit_t,
+                    hit_kind,
+                    result,
+                } => {
+                    self.emits.push((id, result));
+                    self.dependencies.push((id, hit_t, "hit_t"));
+                    self.dependencies.push((id, hit_kind, "hit_kind"));
+                    "ReportIntersection"
+                }
+                S::IgnoreIntersection => "IgnoreIntersection",
+                S::TerminateRay => "TerminateRay",
             };
         }
         root
@@ -310,6 +394,17 @@ fn write_fun(output: &mut String, prefix: String, fun: &crate::Function) -> Resu
                 edges.insert("", expr);
                 (Cow::Borrowed("ArrayLength"), 7)
             }
+            E::AtomicResult { kind, width, .. } => {
+                (Cow::Owned(format!("AtomicResult<{:?},{}>", kind, width)), 4)
+            }
+            E::SubgroupResult { kind, width, size } => {
+                let label = match size {
+                    Some(size) => format!("SubgroupResult<{:?},{:?},{}>", size, kind, width),
+                    None => format!("SubgroupResult<{:?},{}>", kind, width),
+                };
+                (Cow::Owned(label), 4)
+            }
+            E::ReportIntersectionResult => (Cow::Borrowed("ReportIntersectionResult"), 4),
         };
         writeln!(
             output,
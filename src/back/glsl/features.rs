@@ -1,7 +1,7 @@
 use super::{BackendResult, Error, Version, Writer};
 use crate::{
-    Bytes, ImageClass, ImageDimension, ScalarKind, ShaderStage, StorageClass, StorageFormat,
-    TypeInner,
+    Binding, BuiltIn, Bytes, ImageClass, ImageDimension, ScalarKind, Scope, ShaderStage, Statement,
+    StorageClass, StorageFormat, TypeInner,
 };
 use std::io::Write;
 
@@ -24,6 +24,16 @@ bitflags::bitflags! {
         const CONSERVATIVE_DEPTH = 1 << 9;
         /// Isn't supported in ES
         const TEXTURE_1D = 1 << 10;
+        /// Subgroup built-ins, barriers and operations
+        const SUBGROUP_OPERATIONS = 1 << 11;
+        const GEOMETRY_SHADER = 1 << 12;
+        const TESSELLATION_SHADER = 1 << 13;
+        /// Ray tracing stages, acceleration structures and payloads
+        const RAY_TRACING = 1 << 14;
+        /// 2 byte floats
+        const HALF_FLOAT = 1 << 15;
+        /// 8 byte integers
+        const INT64_TYPE = 1 << 16;
     }
 }
 
@@ -84,6 +94,12 @@ impl FeaturesManager {
         // 1D textures are supported by all core versions and aren't supported by an es versions
         // so use 0 that way the check will always be false and can be optimized away
         check_feature!(TEXTURE_1D, 0);
+        check_feature!(SUBGROUP_OPERATIONS, 430, 310);
+        check_feature!(GEOMETRY_SHADER, 150, 310);
+        check_feature!(TESSELLATION_SHADER, 400, 310);
+        check_feature!(RAY_TRACING, 460);
+        check_feature!(HALF_FLOAT, 450, 310);
+        check_feature!(INT64_TYPE, 400);
 
         // Return an error if there are missing features
         if missing.is_empty() {
@@ -117,6 +133,11 @@ impl FeaturesManager {
             writeln!(out, "#extension GL_ARB_gpu_shader_fp64 : require")?;
         }
 
+        if self.0.contains(Features::INT64_TYPE) {
+            // https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_gpu_shader_int64.txt
+            writeln!(out, "#extension GL_ARB_gpu_shader_int64 : require")?;
+        }
+
         if self.0.contains(Features::CUBE_TEXTURES_ARRAY) {
             if version.is_es() {
                 // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_cube_map_array.txt
@@ -164,6 +185,45 @@ impl FeaturesManager {
             }
         }
 
+        if self.0.contains(Features::GEOMETRY_SHADER) && version < Version::Embedded(320) {
+            // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_geometry_shader.txt
+            writeln!(out, "#extension GL_EXT_geometry_shader : require")?;
+        }
+
+        if self.0.contains(Features::TESSELLATION_SHADER) && version < Version::Embedded(320) {
+            // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_tessellation_shader.txt
+            writeln!(out, "#extension GL_EXT_tessellation_shader : require")?;
+        }
+
+        if self.0.contains(Features::SUBGROUP_OPERATIONS) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/khr/GL_KHR_shader_subgroup.txt
+            writeln!(out, "#extension GL_KHR_shader_subgroup_basic : require")?;
+            writeln!(out, "#extension GL_KHR_shader_subgroup_vote : require")?;
+            writeln!(out, "#extension GL_KHR_shader_subgroup_ballot : require")?;
+            writeln!(
+                out,
+                "#extension GL_KHR_shader_subgroup_arithmetic : require"
+            )?;
+            writeln!(out, "#extension GL_KHR_shader_subgroup_shuffle : require")?;
+            writeln!(
+                out,
+                "#extension GL_KHR_shader_subgroup_shuffle_relative : require"
+            )?;
+        }
+
+        if self.0.contains(Features::RAY_TRACING) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GLSL_EXT_ray_tracing.txt
+            writeln!(out, "#extension GL_EXT_ray_tracing : require")?;
+        }
+
+        if self.0.contains(Features::HALF_FLOAT) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GL_EXT_shader_explicit_arithmetic_types.txt
+            writeln!(
+                out,
+                "#extension GL_EXT_shader_explicit_arithmetic_types_float16 : require"
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -183,8 +243,19 @@ impl<'a, W> Writer<'a, W> {
             }
         }
 
-        if let ShaderStage::Compute = self.options.shader_stage {
-            self.features.request(Features::COMPUTE_SHADER)
+        match self.options.shader_stage {
+            ShaderStage::Compute => self.features.request(Features::COMPUTE_SHADER),
+            ShaderStage::Geometry => self.features.request(Features::GEOMETRY_SHADER),
+            ShaderStage::TessellationControl | ShaderStage::TessellationEvaluation => {
+                self.features.request(Features::TESSELLATION_SHADER)
+            }
+            ShaderStage::RayGeneration
+            | ShaderStage::Intersection
+            | ShaderStage::AnyHit
+            | ShaderStage::ClosestHit
+            | ShaderStage::Miss
+            | ShaderStage::Callable => self.features.request(Features::RAY_TRACING),
+            _ => {}
         }
 
         for (_, ty) in self.module.types.iter() {
@@ -194,6 +265,7 @@ impl<'a, W> Writer<'a, W> {
                 TypeInner::Matrix { width, .. } => {
                     self.scalar_required_features(ScalarKind::Float, width)
                 }
+                TypeInner::AccelerationStructure => self.features.request(Features::RAY_TRACING),
                 TypeInner::Array { base, .. } => {
                     if let TypeInner::Array { .. } = self.module.types[base].inner {
                         self.features.request(Features::ARRAY_OF_ARRAYS)
@@ -253,17 +325,80 @@ impl<'a, W> Writer<'a, W> {
                 StorageClass::WorkGroup => self.features.request(Features::COMPUTE_SHADER),
                 StorageClass::Storage => self.features.request(Features::BUFFER_STORAGE),
                 StorageClass::PushConstant => return Err(Error::PushConstantNotSupported),
+                StorageClass::RayPayload
+                | StorageClass::IncomingRayPayload
+                | StorageClass::HitAttribute => self.features.request(Features::RAY_TRACING),
                 _ => {}
             }
+            if let Some(Binding::BuiltIn(BuiltIn::SubgroupSize))
+            | Some(Binding::BuiltIn(BuiltIn::SubgroupInvocationId)) = global.binding
+            {
+                self.features.request(Features::SUBGROUP_OPERATIONS);
+            }
+        }
+
+        for (_, function) in self.module.functions.iter() {
+            self.block_required_features(&function.body);
         }
+        self.block_required_features(&self.entry_point.function.body);
 
         self.features.check_availability(self.options.version)
     }
 
+    /// Helper method that checks the [`Features`](Features) needed by the statements of a block
+    fn block_required_features(&mut self, block: &[Statement]) {
+        for statement in block {
+            match *statement {
+                Statement::Block(ref block) => self.block_required_features(block),
+                Statement::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => {
+                    self.block_required_features(accept);
+                    self.block_required_features(reject);
+                }
+                Statement::Switch {
+                    ref cases,
+                    ref default,
+                    ..
+                } => {
+                    for case in cases {
+                        self.block_required_features(&case.body);
+                    }
+                    self.block_required_features(default);
+                }
+                Statement::Loop {
+                    ref body,
+                    ref continuing,
+                } => {
+                    self.block_required_features(body);
+                    self.block_required_features(continuing);
+                }
+                Statement::Barrier {
+                    execution,
+                    memory_scope,
+                    ..
+                } if execution == Some(Scope::SubGroup) || memory_scope == Scope::SubGroup => {
+                    self.features.request(Features::SUBGROUP_OPERATIONS);
+                }
+                Statement::Subgroup { .. } => {
+                    self.features.request(Features::SUBGROUP_OPERATIONS);
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Helper method that checks the [`Features`](Features) needed by a scalar
     fn scalar_required_features(&mut self, kind: ScalarKind, width: Bytes) {
-        if kind == ScalarKind::Float && width == 8 {
-            self.features.request(Features::DOUBLE_TYPE);
+        match (kind, width) {
+            (ScalarKind::Float, 2) => self.features.request(Features::HALF_FLOAT),
+            (ScalarKind::Float, 8) => self.features.request(Features::DOUBLE_TYPE),
+            (ScalarKind::Sint, 8) | (ScalarKind::Uint, 8) => {
+                self.features.request(Features::INT64_TYPE)
+            }
+            _ => {}
         }
     }
 }
@@ -49,10 +49,11 @@ use crate::{
         EntryPointIndex, NameKey, Namer, ResolveContext, Typifier, TypifyError,
     },
     Arena, ArraySize, BinaryOperator, Binding, BuiltIn, Bytes, ConservativeDepth, Constant,
-    ConstantInner, DerivativeAxis, Expression, FastHashMap, Function, GlobalVariable, Handle,
-    ImageClass, Interpolation, LocalVariable, Module, RelationalFunction, ScalarKind, ScalarValue,
-    ShaderStage, Statement, StorageAccess, StorageClass, StorageFormat, StructMember, Type,
-    TypeInner, UnaryOperator,
+    ConstantInner, DerivativeAxis, Expression, FastHashMap, Function, GeometryInput,
+    GeometryOutput, GlobalVariable, Handle, ImageClass, Interpolation, LocalVariable, Module,
+    RelationalFunction, ScalarKind, ScalarValue, ShaderStage, Statement, StorageAccess,
+    StorageClass, StorageFormat, StructMember, TessellationInfo, TessellationPrimitive,
+    TessellationSpacing, Type, TypeInner, UnaryOperator, VertexOrder,
 };
 use features::FeaturesManager;
 use std::{
@@ -68,7 +69,7 @@ mod features;
 mod keywords;
 
 /// List of supported core glsl versions
-pub const SUPPORTED_CORE_VERSIONS: &[u16] = &[330, 400, 410, 420, 430, 440, 450];
+pub const SUPPORTED_CORE_VERSIONS: &[u16] = &[330, 400, 410, 420, 430, 440, 450, 460];
 /// List of supported es glsl versions
 pub const SUPPORTED_ES_VERSIONS: &[u16] = &[300, 310, 320];
 const INDENT: &str = "    ";
@@ -136,6 +137,10 @@ pub struct Options {
     /// If no entry point that matches is found a error will be thrown while creating a new instance
     /// of [`Writer`](struct.Writer.html)
     pub entry_point: String,
+    /// Write specializable constants as `layout(constant_id = N)` constants
+    ///
+    /// These are only supported by glsl targeting vulkan, otherwise the default values are used
+    pub specialization_constants: bool,
 }
 
 impl Default for Options {
@@ -144,6 +149,7 @@ impl Default for Options {
             version: Version::Embedded(320),
             shader_stage: ShaderStage::Compute,
             entry_point: "main".to_string(),
+            specialization_constants: false,
         }
     }
 }
@@ -199,13 +205,12 @@ impl<'a, 'b> FunctionCtx<'a, 'b> {
     /// Helper method that retrieves the name of the argument in the current function
     ///
     /// # Panics
-    /// - If the function is an entry point
     /// - If the function arguments are less or equal to `arg`
     /// - If `names` hasn't been filled properly
     fn get_arg<'c>(&self, arg: u32, names: &'c FastHashMap<NameKey, String>) -> &'c str {
         match self.func {
             FunctionType::Function(handle) => &names[&NameKey::FunctionArgument(handle, arg)],
-            FunctionType::EntryPoint(_) => unreachable!(),
+            FunctionType::EntryPoint(idx) => &names[&NameKey::EntryPointArgument(idx, arg)],
         }
     }
 }
@@ -257,7 +262,8 @@ pub enum Error {
     /// A scalar with an unsupported width was requested
     #[error("A scalar with an unsupported width was requested: {0:?} {1:?}")]
     UnsupportedScalar(ScalarKind, Bytes),
-    /// [`Interpolation::Patch`](crate::Interpolation::Patch) isn't supported
+    /// [`Interpolation::Patch`](crate::Interpolation::Patch) is only supported between the
+    /// tessellation stages
     #[error("Patch interpolation isn't supported")]
     PatchInterpolationNotSupported,
     /// A image was used with multiple samplers, this isn't supported
@@ -388,6 +394,62 @@ impl<'a, W: Write> Writer<'a, W> {
             writeln!(self.out)?;
         }
 
+        if let Some(geometry) = self.entry_point.geometry {
+            let input = match geometry.input {
+                GeometryInput::Points => "points",
+                GeometryInput::Lines => "lines",
+                GeometryInput::LinesAdjacency => "lines_adjacency",
+                GeometryInput::Triangles => "triangles",
+                GeometryInput::TrianglesAdjacency => "triangles_adjacency",
+            };
+            let output = match geometry.output {
+                GeometryOutput::Points => "points",
+                GeometryOutput::LineStrip => "line_strip",
+                GeometryOutput::TriangleStrip => "triangle_strip",
+            };
+            writeln!(self.out, "layout({}) in;", input)?;
+            writeln!(
+                self.out,
+                "layout({}, max_vertices = {}) out;",
+                output, geometry.max_vertices
+            )?;
+            writeln!(self.out)?;
+        }
+
+        match self.entry_point.tessellation {
+            Some(TessellationInfo::Control { output_vertices }) => {
+                writeln!(self.out, "layout(vertices = {}) out;", output_vertices)?;
+                writeln!(self.out)?;
+            }
+            Some(TessellationInfo::Evaluation {
+                primitive,
+                spacing,
+                vertex_order,
+            }) => {
+                let primitive = match primitive {
+                    TessellationPrimitive::Triangles => "triangles",
+                    TessellationPrimitive::Quads => "quads",
+                    TessellationPrimitive::Isolines => "isolines",
+                };
+                let spacing = match spacing {
+                    TessellationSpacing::Equal => "equal_spacing",
+                    TessellationSpacing::FractionalEven => "fractional_even_spacing",
+                    TessellationSpacing::FractionalOdd => "fractional_odd_spacing",
+                };
+                let vertex_order = match vertex_order {
+                    VertexOrder::Clockwise => "cw",
+                    VertexOrder::CounterClockwise => "ccw",
+                };
+                writeln!(
+                    self.out,
+                    "layout({}, {}, {}) in;",
+                    primitive, spacing, vertex_order
+                )?;
+                writeln!(self.out)?;
+            }
+            None => {}
+        }
+
         // Enable early depth tests if needed
         if let Some(depth_test) = self.entry_point.early_depth_test {
             writeln!(self.out, "layout(early_fragment_tests) in;")?;
@@ -406,6 +468,33 @@ impl<'a, W: Write> Writer<'a, W> {
             writeln!(self.out)?;
         }
 
+        // Write the specialization constants
+        //
+        // `layout(constant_id = N) const type name = value;`
+        // They are written first because struct members might use them as array sizes
+        if self.options.specialization_constants {
+            let mut any_written = false;
+            for (handle, constant) in self.module.constants.iter() {
+                if let (Some(id), &ConstantInner::Scalar { width, ref value }) =
+                    (constant.specialization, &constant.inner)
+                {
+                    write!(
+                        self.out,
+                        "layout(constant_id = {}) const {} {} = ",
+                        id,
+                        glsl_scalar(value.scalar_kind(), width)?.full,
+                        self.names[&NameKey::Constant(handle)]
+                    )?;
+                    self.write_scalar_value(width, value)?;
+                    writeln!(self.out, ";")?;
+                    any_written = true;
+                }
+            }
+            if any_written {
+                writeln!(self.out)?;
+            }
+        }
+
         // Write all structs
         //
         // This are always ordered because of the IR is structured in a way that you can't make a
@@ -488,6 +577,27 @@ impl<'a, W: Write> Writer<'a, W> {
                 _ => self.write_global(handle, global)?,
             }
         }
+
+        // Write the entry point inputs and outputs
+        //
+        // Arguments and results with location bindings are written as `in` and `out` globals,
+        // the built-in ones are read and written directly
+        for arg in self.entry_point.function.arguments.iter() {
+            self.write_varyings(
+                arg.ty,
+                arg.binding.as_ref(),
+                arg.interpolation,
+                StorageClass::Input,
+            )?;
+        }
+        if let Some(ref result) = self.entry_point.function.result {
+            self.write_varyings(
+                result.ty,
+                result.binding.as_ref(),
+                result.interpolation,
+                StorageClass::Output,
+            )?;
+        }
         // Write all regular functions
         for (handle, function) in self.module.functions.iter() {
             // Check that the function doesn't use globals that aren't supported
@@ -533,7 +643,9 @@ impl<'a, W: Write> Writer<'a, W> {
     fn write_type(&mut self, ty: Handle<Type>) -> BackendResult {
         match self.module.types[ty].inner {
             // Scalars are simple we just get the full name from `glsl_scalar`
+            // Atomics are just scalars in glsl, only the operations on them differ
             TypeInner::Scalar { kind, width }
+            | TypeInner::Atomic { kind, width }
             | TypeInner::ValuePointer {
                 size: None,
                 kind,
@@ -573,15 +685,19 @@ impl<'a, W: Write> Writer<'a, W> {
             TypeInner::Pointer { base, .. } => self.write_type(base)?,
             // Arrays are written as `base[size]`
             TypeInner::Array { base, size, .. } => {
-                let ty_name = &self.names[&NameKey::Type(base)];
-
-                write!(self.out, "{}", ty_name)?;
+                self.write_type(base)?;
                 write!(self.out, "[")?;
 
                 // Write the array size
                 // Writes nothing if `ArraySize::Dynamic`
                 // Panics if `ArraySize::Constant` has a constant that isn't an uint
                 match size {
+                    ArraySize::Constant(const_handle)
+                        if self.options.specialization_constants
+                            && self.module.constants[const_handle].specialization.is_some() =>
+                    {
+                        write!(self.out, "{}", self.names[&NameKey::Constant(const_handle)])?
+                    }
                     ArraySize::Constant(const_handle) => {
                         match self.module.constants[const_handle].inner {
                             ConstantInner::Scalar {
@@ -637,6 +753,7 @@ impl<'a, W: Write> Writer<'a, W> {
             // Write all variants instead of `_` so that if new variants are added a
             // no exhaustiveness error is thrown
             TypeInner::Image { .. } | TypeInner::Sampler { .. } => unreachable!(),
+            TypeInner::AccelerationStructure => write!(self.out, "accelerationStructureEXT")?,
         }
 
         Ok(())
@@ -712,30 +829,53 @@ impl<'a, W: Write> Writer<'a, W> {
         // Write the interpolation modifier if needed
         //
         // We ignore all interpolation modifiers that aren't used in input globals in fragment
-        // shaders or output globals in vertex shaders
+        // shaders or output globals in vertex, geometry and tessellation evaluation shaders
+        //
+        // The exception is `patch`, which is used by the tessellation stages
         //
         // TODO: Should this throw an error?
         if let Some(interpolation) = global.interpolation {
             match (self.options.shader_stage, global.class) {
+                (ShaderStage::TessellationControl, StorageClass::Output)
+                | (ShaderStage::TessellationEvaluation, StorageClass::Input)
+                    if interpolation == Interpolation::Patch =>
+                {
+                    write!(self.out, "patch ")?;
+                }
                 (ShaderStage::Fragment, StorageClass::Input)
-                | (ShaderStage::Vertex, StorageClass::Output) => {
+                | (ShaderStage::Vertex, StorageClass::Output)
+                | (ShaderStage::Geometry, StorageClass::Output)
+                | (ShaderStage::TessellationEvaluation, StorageClass::Output) => {
                     write!(self.out, "{} ", glsl_interpolation(interpolation)?)?;
                 }
                 _ => (),
             };
         }
 
+        // Ray payloads are matched between the stages by location, which is also
+        // the payload argument of `traceRayEXT`
+        if let Some(location) = self.payload_location(handle, global) {
+            write!(self.out, "layout(location = {}) ", location)?;
+        }
+
         // Write the storage class
         // Trailing space is important
         write!(self.out, "{} ", glsl_storage_class(global.class))?;
 
         // Write the type
         // `write_type` adds no leading or trailing spaces
-        self.write_type(global.ty)?;
+        //
+        // Per-vertex arrays are left unsized, their size is implied by the primitive
+        let per_vertex_base = self.per_vertex_array_base(global);
+        self.write_type(per_vertex_base.unwrap_or(global.ty))?;
 
         // Finally write the global name and end the global with a `;` and a newline
         // Leading space is important
-        writeln!(self.out, " {};", self.get_global_name(handle, global))?;
+        write!(self.out, " {}", self.get_global_name(handle, global))?;
+        if per_vertex_base.is_some() {
+            write!(self.out, "[]")?;
+        }
+        writeln!(self.out, ";")?;
         writeln!(self.out)?;
 
         Ok(())
@@ -746,22 +886,13 @@ impl<'a, W: Write> Writer<'a, W> {
     /// Globals have different naming schemes depending on their binding:
     /// - Globals without bindings use the name from the [`Namer`](crate::proc::Namer)
     /// - Globals with builtin bindings get the from [`glsl_built_in`](glsl_built_in)
-    /// - Globals with location bindings are named `_location_X` where `X` is the location
+    /// - Globals with location bindings are named `_location_X` where `X` is the location,
+    ///   followed by a suffix for the stage that writes them
     /// - Globals with resource binding are named `_group_X_binding_Y` where `X`
     ///   is the group and `Y` is the binding
     fn get_global_name(&self, handle: Handle<GlobalVariable>, global: &GlobalVariable) -> String {
         match global.binding {
-            Some(Binding::Location(location)) => {
-                format!(
-                    "_location_{}{}",
-                    location,
-                    match (self.options.shader_stage, global.class) {
-                        (ShaderStage::Fragment, StorageClass::Input) => "_vs",
-                        (ShaderStage::Vertex, StorageClass::Output) => "_vs",
-                        _ => "",
-                    }
-                )
-            }
+            Some(Binding::Location(location)) => self.varying_name(location, global.class),
             Some(Binding::Resource { group, binding }) => {
                 format!("_group_{}_binding_{}", group, binding)
             }
@@ -770,6 +901,192 @@ impl<'a, W: Write> Writer<'a, W> {
         }
     }
 
+    /// Helper method used to get the name of the `in` or `out` global at `location`
+    ///
+    /// The name is `_location_X` where `X` is the location, followed by a suffix
+    /// for the stage that writes it, so that the outputs of a stage match the inputs of the next
+    fn varying_name(&self, location: u32, class: StorageClass) -> String {
+        format!(
+            "_location_{}{}",
+            location,
+            match (self.options.shader_stage, class) {
+                (ShaderStage::Fragment, StorageClass::Input) => "_vs",
+                (ShaderStage::Vertex, StorageClass::Output) => "_vs",
+                (ShaderStage::Geometry, StorageClass::Input) => "_vs",
+                (ShaderStage::Geometry, StorageClass::Output) => "_gs",
+                (ShaderStage::TessellationControl, StorageClass::Input) => "_vs",
+                (ShaderStage::TessellationControl, StorageClass::Output) => "_tcs",
+                (ShaderStage::TessellationEvaluation, StorageClass::Input) => "_tcs",
+                // the evaluation stage takes the place of the vertex stage
+                (ShaderStage::TessellationEvaluation, StorageClass::Output) => "_vs",
+                _ => "",
+            }
+        )
+    }
+
+    /// Helper method that returns the name an entry point argument or result
+    /// with the given binding is read from or written to
+    fn varying_source(&self, binding: &Binding, class: StorageClass) -> String {
+        match *binding {
+            Binding::Location(location) => self.varying_name(location, class),
+            Binding::BuiltIn(built_in) => glsl_built_in(built_in).to_string(),
+            Binding::Resource { .. } => unreachable!(),
+        }
+    }
+
+    /// Helper method used to write the `in` or `out` globals of an entry point argument or
+    /// result, which is either bound itself or is a struct with all of the members bound
+    fn write_varyings(
+        &mut self,
+        ty: Handle<Type>,
+        binding: Option<&Binding>,
+        interpolation: Option<Interpolation>,
+        class: StorageClass,
+    ) -> BackendResult {
+        match binding {
+            Some(binding) => self.write_varying(ty, binding, interpolation, class),
+            None => {
+                if let TypeInner::Struct { ref members, .. } = self.module.types[ty].inner {
+                    for member in members.iter() {
+                        if let Some(ref binding) = member.binding {
+                            self.write_varying(member.ty, binding, member.interpolation, class)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Helper method used to write a single `in` or `out` global
+    ///
+    /// # Notes
+    /// Built-ins are skipped, as they are already declared
+    fn write_varying(
+        &mut self,
+        ty: Handle<Type>,
+        binding: &Binding,
+        interpolation: Option<Interpolation>,
+        class: StorageClass,
+    ) -> BackendResult {
+        let location = match *binding {
+            Binding::Location(location) => location,
+            _ => return Ok(()),
+        };
+
+        // Write the interpolation modifier, with the same rules as for the globals
+        if let Some(interpolation) = interpolation {
+            match (self.options.shader_stage, class) {
+                (ShaderStage::Fragment, StorageClass::Input)
+                | (ShaderStage::Vertex, StorageClass::Output) => {
+                    write!(self.out, "{} ", glsl_interpolation(interpolation)?)?;
+                }
+                _ => (),
+            };
+        }
+
+        write!(self.out, "{} ", glsl_storage_class(class))?;
+        self.write_type(ty)?;
+        writeln!(self.out, " {};", self.varying_name(location, class))?;
+        writeln!(self.out)?;
+
+        Ok(())
+    }
+
+    /// Helper method used to write the value of an entry point argument with the given binding
+    ///
+    /// Built-ins are converted to the argument type, since their types in glsl might differ
+    /// (i.e. `gl_VertexID` is an `int`)
+    fn write_varying_value(&mut self, ty: Handle<Type>, binding: &Binding) -> BackendResult {
+        let source = self.varying_source(binding, StorageClass::Input);
+        if let Binding::BuiltIn(_) = *binding {
+            self.write_type(ty)?;
+            write!(self.out, "({})", source)?;
+        } else {
+            write!(self.out, "{}", source)?;
+        }
+        Ok(())
+    }
+
+    /// Helper method that returns the location of a ray payload global
+    ///
+    /// Payloads have no bindings in the IR, so they are numbered in the order of
+    /// declaration among the globals of the same storage class
+    fn payload_location(
+        &self,
+        handle: Handle<GlobalVariable>,
+        global: &GlobalVariable,
+    ) -> Option<usize> {
+        match global.class {
+            StorageClass::RayPayload | StorageClass::IncomingRayPayload => self
+                .module
+                .global_variables
+                .iter()
+                .filter(|&(_, other)| other.class == global.class)
+                .position(|(other, _)| other == handle),
+            _ => None,
+        }
+    }
+
+    /// Helper method that checks if `global` is a per-vertex array of a geometry or
+    /// tessellation shader, returning the type of its elements
+    fn per_vertex_array_base(&self, global: &GlobalVariable) -> Option<Handle<Type>> {
+        let arrayed = match (self.options.shader_stage, global.class) {
+            (ShaderStage::Geometry, StorageClass::Input)
+            | (ShaderStage::TessellationControl, StorageClass::Input)
+            | (ShaderStage::TessellationControl, StorageClass::Output)
+            | (ShaderStage::TessellationEvaluation, StorageClass::Input) => true,
+            _ => false,
+        };
+        let per_vertex = match global.binding {
+            Some(Binding::Location(_)) => global.interpolation != Some(Interpolation::Patch),
+            Some(Binding::BuiltIn(built_in)) => built_in.is_per_vertex(),
+            _ => false,
+        };
+        match self.module.types[global.ty].inner {
+            TypeInner::Array { base, .. } if arrayed && per_vertex => {
+                match (&global.binding, &self.module.types[base].inner) {
+                    // clip distances are arrays on their own
+                    (&Some(Binding::BuiltIn(BuiltIn::ClipDistance)), &TypeInner::Array { .. }) => {
+                        Some(base)
+                    }
+                    (&Some(Binding::BuiltIn(BuiltIn::ClipDistance)), _) => None,
+                    _ => Some(base),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Helper method that checks if `expr` is a per-vertex built-in array of a geometry or
+    /// tessellation shader, returning the name of the array of blocks it lives in
+    ///
+    /// Those are written as `gl_in[i].gl_Position` instead of `gl_Position[i]`
+    fn per_vertex_built_in(
+        &self,
+        expr: Handle<Expression>,
+        ctx: &FunctionCtx<'_, '_>,
+    ) -> Option<(&'static str, BuiltIn)> {
+        match ctx.expressions[expr] {
+            Expression::GlobalVariable(handle) => {
+                let global = &self.module.global_variables[handle];
+                match global.binding {
+                    Some(Binding::BuiltIn(built_in))
+                        if self.per_vertex_array_base(global).is_some() =>
+                    {
+                        let block = match global.class {
+                            StorageClass::Output => "gl_out",
+                            _ => "gl_in",
+                        };
+                        Some((block, built_in))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     /// Helper method used to write functions (both entry points and regular functions)
     ///
     /// # Notes
@@ -816,10 +1133,11 @@ impl<'a, W: Write> Writer<'a, W> {
         // Start by writing the return type if any otherwise write void
         // This is the only place where `void` is a valid type
         // (though it's more a keyword than a type)
-        if let Some(ty) = func.return_type {
-            self.write_type(ty)?;
-        } else {
-            write!(self.out, "void")?;
+        //
+        // Entry points return `void` too, their results are written into the `out` globals
+        match (&ctx.func, func.result.as_ref()) {
+            (&FunctionType::Function(_), Some(result)) => self.write_type(result.ty)?,
+            _ => write!(self.out, "void")?,
         }
 
         // Write the function name and open parentheses for the argument list
@@ -829,7 +1147,13 @@ impl<'a, W: Write> Writer<'a, W> {
         //
         // We need access to `Self` here so we use the reference passed to the closure as an
         // argument instead of capturing as that would cause a borrow checker error
-        self.write_slice(&func.arguments, |this, i, arg| {
+        //
+        // Entry points take no arguments, they are read from the `in` globals instead
+        let arguments = match ctx.func {
+            FunctionType::Function(_) => &func.arguments[..],
+            FunctionType::EntryPoint(_) => &[],
+        };
+        self.write_slice(arguments, |this, i, arg| {
             // Write the argument type
             // `write_type` adds no trailing spaces
             this.write_type(arg.ty)?;
@@ -866,13 +1190,43 @@ impl<'a, W: Write> Writer<'a, W> {
 
                 // Write the constant
                 // `write_constant` adds no trailing or leading space/newline
-                self.write_constant(&self.module.constants[init])?;
+                self.write_constant(init)?;
             }
 
             // Finish the local with `;` and add a newline (only for readability)
             writeln!(self.out, ";")?
         }
 
+        // Declare the entry point arguments as locals initialized from the inputs
+        //
+        // Struct arguments are built from the inputs of all their members
+        if let FunctionType::EntryPoint(_) = ctx.func {
+            for (index, arg) in func.arguments.iter().enumerate() {
+                write!(self.out, "{}", INDENT)?;
+                self.write_type(arg.ty)?;
+                write!(self.out, " {} = ", ctx.get_arg(index as u32, &self.names))?;
+                match arg.binding {
+                    Some(ref binding) => self.write_varying_value(arg.ty, binding)?,
+                    None => {
+                        if let TypeInner::Struct { ref members, .. } =
+                            self.module.types[arg.ty].inner
+                        {
+                            self.write_type(arg.ty)?;
+                            write!(self.out, "(")?;
+                            self.write_slice(members, |this, _, member| match member.binding {
+                                Some(ref binding) => this.write_varying_value(member.ty, binding),
+                                None => Err(Error::Custom(
+                                    "Entry point argument member without a binding".to_string(),
+                                )),
+                            })?;
+                            write!(self.out, ")")?;
+                        }
+                    }
+                }
+                writeln!(self.out, ";")?;
+            }
+        }
+
         // Write the function body (statement list)
         for sta in func.body.iter() {
             // Write a statement, the indentation should always be 1 when writing the function body
@@ -919,25 +1273,17 @@ impl<'a, W: Write> Writer<'a, W> {
     ///
     /// # Notes
     /// Adds no newlines or leading/trailing whitespace
-    fn write_constant(&mut self, constant: &Constant) -> BackendResult {
+    fn write_constant(&mut self, handle: Handle<Constant>) -> BackendResult {
+        let constant = &self.module.constants[handle];
+
+        // Specialization constants are declared at the top and referenced by name
+        if self.options.specialization_constants && constant.specialization.is_some() {
+            write!(self.out, "{}", self.names[&NameKey::Constant(handle)])?;
+            return Ok(());
+        }
+
         match constant.inner {
-            ConstantInner::Scalar {
-                width: _,
-                ref value,
-            } => match *value {
-                // Signed integers don't need anything special
-                ScalarValue::Sint(int) => write!(self.out, "{}", int)?,
-                // Unsigned integers need a `u` at the end
-                //
-                // While `core` doesn't necessarily need it, it's allowed and since `es` needs it we
-                // always write it as the extra branch wouldn't have any benefit in readability
-                ScalarValue::Uint(int) => write!(self.out, "{}u", int)?,
-                // Floats are written using `Debug` instead of `Display` because it always appends the
-                // decimal part even it's zero which is needed for a valid glsl float constant
-                ScalarValue::Float(float) => write!(self.out, "{:?}", float)?,
-                // Booleans are either `true` or `false` so nothing special needs to be done
-                ScalarValue::Bool(boolean) => write!(self.out, "{}", boolean)?,
-            },
+            ConstantInner::Scalar { width, ref value } => self.write_scalar_value(width, value)?,
             // Composite constant are created using the same syntax as compose
             // `type(components)` where `components` is a comma separated list of constants
             ConstantInner::Composite { ty, ref components } => {
@@ -945,9 +1291,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, "(")?;
 
                 // Write the comma separated constants
-                self.write_slice(components, |this, _, arg| {
-                    this.write_constant(&this.module.constants[*arg])
-                })?;
+                self.write_slice(components, |this, _, arg| this.write_constant(*arg))?;
 
                 write!(self.out, ")")?
             }
@@ -956,6 +1300,38 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
+    /// Helper method used to write scalar constant values
+    ///
+    /// # Notes
+    /// Adds no newlines or leading/trailing whitespace
+    fn write_scalar_value(&mut self, width: Bytes, value: &ScalarValue) -> BackendResult {
+        match *value {
+            // Signed integers don't need anything special
+            //
+            // 64 bit integers need the `l` suffix
+            ScalarValue::Sint(int) if width == 8 => write!(self.out, "{}l", int)?,
+            ScalarValue::Sint(int) => write!(self.out, "{}", int)?,
+            // Unsigned integers need a `u` at the end
+            //
+            // While `core` doesn't necessarily need it, it's allowed and since `es` needs it we
+            // always write it as the extra branch wouldn't have any benefit in readability
+            ScalarValue::Uint(int) if width == 8 => write!(self.out, "{}ul", int)?,
+            ScalarValue::Uint(int) => write!(self.out, "{}u", int)?,
+            // Floats are written using `Debug` instead of `Display` because it always appends the
+            // decimal part even it's zero which is needed for a valid glsl float constant
+            //
+            // Half floats need the `hf` suffix and doubles the `lf` suffix, otherwise they would
+            // be parsed as `float`
+            ScalarValue::Float(float) if width == 2 => write!(self.out, "{:?}hf", float)?,
+            ScalarValue::Float(float) if width == 8 => write!(self.out, "{:?}lf", float)?,
+            ScalarValue::Float(float) => write!(self.out, "{:?}", float)?,
+            // Booleans are either `true` or `false` so nothing special needs to be done
+            ScalarValue::Bool(boolean) => write!(self.out, "{}", boolean)?,
+        }
+
+        Ok(())
+    }
+
     /// Helper method used to write structs
     ///
     /// # Notes
@@ -1169,6 +1545,51 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, "{}", INDENT.repeat(indent))?;
                 writeln!(self.out, "continue;")?
             }
+            // Entry points write their result into the `out` globals instead of returning it
+            Statement::Return { value: Some(expr) }
+                if matches!(ctx.func, FunctionType::EntryPoint(_)) =>
+            {
+                let result = self.entry_point.function.result.as_ref().unwrap();
+                match result.binding {
+                    Some(ref binding) => {
+                        write!(self.out, "{}", INDENT.repeat(indent))?;
+                        let name = self.varying_source(binding, StorageClass::Output);
+                        write!(self.out, "{} = ", name)?;
+                        self.write_expr(expr, ctx)?;
+                        writeln!(self.out, ";")?;
+                    }
+                    None => {
+                        // Store the result in a temporary and then write each member
+                        let temp_name = format!("_result{}", self.block_id.generate());
+                        write!(self.out, "{}", INDENT.repeat(indent))?;
+                        self.write_type(result.ty)?;
+                        write!(self.out, " {} = ", temp_name)?;
+                        self.write_expr(expr, ctx)?;
+                        writeln!(self.out, ";")?;
+                        if let TypeInner::Struct { ref members, .. } =
+                            self.module.types[result.ty].inner
+                        {
+                            for (index, member) in members.iter().enumerate() {
+                                if let Some(ref binding) = member.binding {
+                                    let name = self.varying_source(binding, StorageClass::Output);
+                                    let member_name = &self.names
+                                        [&NameKey::StructMember(result.ty, index as u32)];
+                                    writeln!(
+                                        self.out,
+                                        "{}{} = {}.{};",
+                                        INDENT.repeat(indent),
+                                        name,
+                                        temp_name,
+                                        member_name
+                                    )?;
+                                }
+                            }
+                        }
+                    }
+                }
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                writeln!(self.out, "return;")?;
+            }
             // `return expr;`, `expr` is optional
             Statement::Return { value } => {
                 write!(self.out, "{}", INDENT.repeat(indent))?;
@@ -1187,6 +1608,14 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, "{}", INDENT.repeat(indent))?;
                 writeln!(self.out, "discard;")?
             }
+            Statement::EmitVertex => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                writeln!(self.out, "EmitVertex();")?
+            }
+            Statement::EndPrimitive => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                writeln!(self.out, "EndPrimitive();")?
+            }
             // Stores in glsl are just variable assignments written as `pointer = value;`
             Statement::Store { pointer, value } => {
                 write!(self.out, "{}", INDENT.repeat(indent))?;
@@ -1226,7 +1655,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, "{}", INDENT.repeat(indent))?;
                 if let Some(expr) = result {
                     let name = format!("_expr{}", expr.index());
-                    let ty = self.module.functions[function].return_type.unwrap();
+                    let ty = self.module.functions[function].result.as_ref().unwrap().ty;
                     self.write_type(ty)?;
                     write!(self.out, " {} = ", name)?;
                     self.cached_expressions.insert(expr, name);
@@ -1235,6 +1664,251 @@ impl<'a, W: Write> Writer<'a, W> {
                 self.write_slice(arguments, |this, _, arg| this.write_expr(*arg, ctx))?;
                 writeln!(self.out, ");")?
             }
+            // Atomic operations are written as the `atomicOp(pointer, value)` function calls,
+            // the result is stored in a variable like for the `Call` statement
+            Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                let (kind, width) = match ctx.expressions[result] {
+                    Expression::AtomicResult { kind, width, .. } => (kind, width),
+                    _ => unreachable!(),
+                };
+                let scalar = glsl_scalar(kind, width)?;
+                let name = format!("_expr{}", result.index());
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+
+                match *fun {
+                    // glsl only returns the original value, so we have to
+                    // compare it ourselves to get the exchange flag
+                    crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                        write!(
+                            self.out,
+                            "{}vec2 {} = {}vec2(atomicCompSwap(",
+                            scalar.prefix, name, scalar.prefix
+                        )?;
+                        self.write_expr(pointer, ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(cmp, ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(value, ctx)?;
+                        writeln!(self.out, "), 0);")?;
+                        write!(
+                            self.out,
+                            "{}{}.y = {}({}.x == ",
+                            INDENT.repeat(indent),
+                            name,
+                            scalar.full,
+                            name
+                        )?;
+                        self.write_expr(cmp, ctx)?;
+                        writeln!(self.out, ");")?;
+                    }
+                    _ => {
+                        let fun_name = match *fun {
+                            // there is no `atomicSub`, so we add the negated value instead
+                            crate::AtomicFunction::Add | crate::AtomicFunction::Subtract => {
+                                "atomicAdd"
+                            }
+                            crate::AtomicFunction::And => "atomicAnd",
+                            crate::AtomicFunction::ExclusiveOr => "atomicXor",
+                            crate::AtomicFunction::InclusiveOr => "atomicOr",
+                            crate::AtomicFunction::Min => "atomicMin",
+                            crate::AtomicFunction::Max => "atomicMax",
+                            crate::AtomicFunction::Exchange { .. } => "atomicExchange",
+                        };
+                        write!(self.out, "{} {} = {}(", scalar.full, name, fun_name)?;
+                        self.write_expr(pointer, ctx)?;
+                        write!(self.out, ", ")?;
+                        if let crate::AtomicFunction::Subtract = *fun {
+                            write!(self.out, "-(")?;
+                            self.write_expr(value, ctx)?;
+                            write!(self.out, ")")?;
+                        } else {
+                            self.write_expr(value, ctx)?;
+                        }
+                        writeln!(self.out, ");")?;
+                    }
+                }
+                self.cached_expressions.insert(result, name);
+            }
+            // Memory barriers are written per kind of memory as `memoryBarrierX()` calls,
+            // and are followed by `barrier()` if this is also a control barrier.
+            // Subgroup scoped barriers use the `subgroup` prefixed versions instead
+            Statement::Barrier {
+                execution,
+                memory_scope,
+                memory,
+            } => {
+                let prefix = match memory_scope {
+                    crate::Scope::SubGroup => "subgroupMemoryBarrier",
+                    crate::Scope::WorkGroup | crate::Scope::Device => "memoryBarrier",
+                };
+                if memory.contains(crate::Barrier::STORAGE) {
+                    writeln!(self.out, "{}{}Buffer();", INDENT.repeat(indent), prefix)?;
+                }
+                if memory.contains(crate::Barrier::WORK_GROUP) {
+                    writeln!(self.out, "{}{}Shared();", INDENT.repeat(indent), prefix)?;
+                }
+                if memory.contains(crate::Barrier::IMAGE) {
+                    writeln!(self.out, "{}{}Image();", INDENT.repeat(indent), prefix)?;
+                }
+                match execution {
+                    Some(crate::Scope::SubGroup) => {
+                        writeln!(self.out, "{}subgroupBarrier();", INDENT.repeat(indent))?
+                    }
+                    Some(_) => writeln!(self.out, "{}barrier();", INDENT.repeat(indent))?,
+                    None => {}
+                }
+            }
+            // Subgroup operations map directly to the `GL_KHR_shader_subgroup` functions,
+            // their result is stored in a new variable like the other baked expressions
+            Statement::Subgroup { ref fun, result } => {
+                let (kind, width, size) = match ctx.expressions[result] {
+                    Expression::SubgroupResult { kind, width, size } => (kind, width, size),
+                    _ => unreachable!(),
+                };
+                let scalar = glsl_scalar(kind, width)?;
+                let name = format!("_expr{}", result.index());
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                match size {
+                    Some(size) => write!(self.out, "{}vec{}", scalar.prefix, size as u8)?,
+                    None => write!(self.out, "{}", scalar.full)?,
+                }
+                write!(self.out, " {} = ", name)?;
+
+                match *fun {
+                    crate::SubgroupFunction::Elect => write!(self.out, "subgroupElect()")?,
+                    crate::SubgroupFunction::Ballot { predicate } => {
+                        write!(self.out, "subgroupBallot(")?;
+                        match predicate {
+                            Some(predicate) => self.write_expr(predicate, ctx)?,
+                            None => write!(self.out, "true")?,
+                        }
+                        write!(self.out, ")")?;
+                    }
+                    crate::SubgroupFunction::Gather { mode, argument } => {
+                        let (fun_name, index) = match mode {
+                            crate::GatherMode::BroadcastFirst => ("subgroupBroadcastFirst", None),
+                            crate::GatherMode::Broadcast(index) => {
+                                ("subgroupBroadcast", Some(index))
+                            }
+                            crate::GatherMode::Shuffle(index) => ("subgroupShuffle", Some(index)),
+                            crate::GatherMode::ShuffleDown(delta) => {
+                                ("subgroupShuffleDown", Some(delta))
+                            }
+                            crate::GatherMode::ShuffleUp(delta) => {
+                                ("subgroupShuffleUp", Some(delta))
+                            }
+                            crate::GatherMode::ShuffleXor(mask) => {
+                                ("subgroupShuffleXor", Some(mask))
+                            }
+                        };
+                        write!(self.out, "{}(", fun_name)?;
+                        self.write_expr(argument, ctx)?;
+                        if let Some(index) = index {
+                            write!(self.out, ", ")?;
+                            self.write_expr(index, ctx)?;
+                        }
+                        write!(self.out, ")")?;
+                    }
+                    crate::SubgroupFunction::Collective {
+                        op,
+                        collective,
+                        argument,
+                    } => {
+                        let collective_name = match collective {
+                            crate::CollectiveOperation::Reduce => "",
+                            crate::CollectiveOperation::InclusiveScan => "Inclusive",
+                            crate::CollectiveOperation::ExclusiveScan => "Exclusive",
+                        };
+                        let op_name = match op {
+                            crate::SubgroupOperation::All => "All",
+                            crate::SubgroupOperation::Any => "Any",
+                            crate::SubgroupOperation::Add => "Add",
+                            crate::SubgroupOperation::Multiply => "Mul",
+                            crate::SubgroupOperation::Min => "Min",
+                            crate::SubgroupOperation::Max => "Max",
+                            crate::SubgroupOperation::And => "And",
+                            crate::SubgroupOperation::InclusiveOr => "Or",
+                            crate::SubgroupOperation::ExclusiveOr => "Xor",
+                        };
+                        write!(self.out, "subgroup{}{}(", collective_name, op_name)?;
+                        self.write_expr(argument, ctx)?;
+                        write!(self.out, ")")?;
+                    }
+                }
+                writeln!(self.out, ";")?;
+                self.cached_expressions.insert(result, name);
+            }
+            // `traceRayEXT` takes the location of the payload instead of the payload itself
+            Statement::TraceRay {
+                acceleration_structure,
+                flags,
+                cull_mask,
+                sbt_offset,
+                sbt_stride,
+                miss_index,
+                origin,
+                t_min,
+                direction,
+                t_max,
+                payload,
+            } => {
+                let location = match ctx.expressions[payload] {
+                    Expression::GlobalVariable(var) => {
+                        self.payload_location(var, &self.module.global_variables[var])
+                    }
+                    _ => None,
+                }
+                .ok_or_else(|| Error::Custom("Ray payload must be a global".to_string()))?;
+
+                write!(self.out, "{}traceRayEXT(", INDENT.repeat(indent))?;
+                for operand in [
+                    acceleration_structure,
+                    flags,
+                    cull_mask,
+                    sbt_offset,
+                    sbt_stride,
+                    miss_index,
+                    origin,
+                    t_min,
+                    direction,
+                    t_max,
+                ]
+                .iter()
+                {
+                    self.write_expr(*operand, ctx)?;
+                    write!(self.out, ", ")?;
+                }
+                writeln!(self.out, "{});", location)?;
+            }
+            Statement::ReportIntersection {
+                hit_t,
+                hit_kind,
+                result,
+            } => {
+                let name = format!("_expr{}", result.index());
+                write!(
+                    self.out,
+                    "{}bool {} = reportIntersectionEXT(",
+                    INDENT.repeat(indent),
+                    name
+                )?;
+                self.write_expr(hit_t, ctx)?;
+                write!(self.out, ", ")?;
+                self.write_expr(hit_kind, ctx)?;
+                writeln!(self.out, ");")?;
+                self.cached_expressions.insert(result, name);
+            }
+            Statement::IgnoreIntersection => {
+                writeln!(self.out, "{}ignoreIntersectionEXT;", INDENT.repeat(indent))?
+            }
+            Statement::TerminateRay => {
+                writeln!(self.out, "{}terminateRayEXT;", INDENT.repeat(indent))?
+            }
         }
 
         Ok(())
@@ -1252,6 +1926,12 @@ impl<'a, W: Write> Writer<'a, W> {
         match ctx.expressions[expr] {
             // `Access` is applied to arrays, vectors and matrices and is written as indexing
             Expression::Access { base, index } => {
+                if let Some((block, built_in)) = self.per_vertex_built_in(base, ctx) {
+                    write!(self.out, "{}[", block)?;
+                    self.write_expr(index, ctx)?;
+                    write!(self.out, "].{}", glsl_built_in(built_in))?;
+                    return Ok(());
+                }
                 self.write_expr(base, ctx)?;
                 write!(self.out, "[")?;
                 self.write_expr(index, ctx)?;
@@ -1261,6 +1941,10 @@ impl<'a, W: Write> Writer<'a, W> {
             // be applied to structs, in this case we need to find the name of the field at that
             // index and write `base.field_name`
             Expression::AccessIndex { base, index } => {
+                if let Some((block, built_in)) = self.per_vertex_built_in(base, ctx) {
+                    write!(self.out, "{}[{}].{}", block, index, glsl_built_in(built_in))?;
+                    return Ok(());
+                }
                 self.write_expr(base, ctx)?;
 
                 let mut resolved = ctx.typifier.get(base, &self.module.types);
@@ -1292,9 +1976,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 }
             }
             // Constants are delegated to `write_constant`
-            Expression::Constant(constant) => {
-                self.write_constant(&self.module.constants[constant])?
-            }
+            Expression::Constant(constant) => self.write_constant(constant)?,
             // `Compose` is pretty simple we just write `type(components)` where `components` is a
             // comma separated list of expressions
             Expression::Compose { ty, ref components } => {
@@ -1763,7 +2445,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, ")")?
             }
             // `As` is always a call.
-            // If `convert` is true the function name is the type
+            // If `convert` is true, or if both kinds are integers, the function name is the type
             // Else the function name is one of the glsl provided bitcast functions
             Expression::As {
                 expr,
@@ -1771,7 +2453,8 @@ impl<'a, W: Write> Writer<'a, W> {
                 convert,
             } => {
                 let inner = ctx.typifier.get(expr, &self.module.types);
-                if convert {
+                let is_integer = |kind| kind == ScalarKind::Sint || kind == ScalarKind::Uint;
+                if convert || (is_integer(kind) && inner.scalar_kind().map_or(false, is_integer)) {
                     // this is similar to `write_type`, but with the target kind
                     match *inner {
                         TypeInner::Scalar { kind: _, width } => {
@@ -1796,7 +2479,7 @@ impl<'a, W: Write> Writer<'a, W> {
                         "{}",
                         match (source_kind, kind) {
                             (ScalarKind::Float, ScalarKind::Sint) => "floatBitsToInt",
-                            (ScalarKind::Float, ScalarKind::Uint) => "floatBitsToUInt",
+                            (ScalarKind::Float, ScalarKind::Uint) => "floatBitsToUint",
                             (ScalarKind::Sint, ScalarKind::Float) => "intBitsToFloat",
                             (ScalarKind::Uint, ScalarKind::Float) => "uintBitsToFloat",
                             _ => {
@@ -1813,7 +2496,12 @@ impl<'a, W: Write> Writer<'a, W> {
                 self.write_expr(expr, ctx)?;
                 write!(self.out, ")")?
             }
-            Expression::Call(_function) => unreachable!(),
+            // `Call`, `AtomicResult`, `SubgroupResult` and `ReportIntersectionResult`
+            // are always cached by their statements
+            Expression::Call(_)
+            | Expression::AtomicResult { .. }
+            | Expression::SubgroupResult { .. }
+            | Expression::ReportIntersectionResult => unreachable!(),
             // `ArrayLength` is written as `expr.length()` and we convert it to a uint
             Expression::ArrayLength(expr) => {
                 write!(self.out, "uint(")?;
@@ -1921,15 +2609,33 @@ struct ScalarString<'a> {
 /// If a [`Float`](crate::ScalarKind::Float) with an width that isn't 4 or 8
 fn glsl_scalar(kind: ScalarKind, width: Bytes) -> Result<ScalarString<'static>, Error> {
     Ok(match kind {
-        ScalarKind::Sint => ScalarString {
-            prefix: "i",
-            full: "int",
+        ScalarKind::Sint => match width {
+            4 => ScalarString {
+                prefix: "i",
+                full: "int",
+            },
+            8 => ScalarString {
+                prefix: "i64",
+                full: "int64_t",
+            },
+            _ => return Err(Error::UnsupportedScalar(kind, width)),
         },
-        ScalarKind::Uint => ScalarString {
-            prefix: "u",
-            full: "uint",
+        ScalarKind::Uint => match width {
+            4 => ScalarString {
+                prefix: "u",
+                full: "uint",
+            },
+            8 => ScalarString {
+                prefix: "u64",
+                full: "uint64_t",
+            },
+            _ => return Err(Error::UnsupportedScalar(kind, width)),
         },
         ScalarKind::Float => match width {
+            2 => ScalarString {
+                prefix: "f16",
+                full: "float16_t",
+            },
             4 => ScalarString {
                 prefix: "",
                 full: "float",
@@ -1971,6 +2677,29 @@ fn glsl_built_in(built_in: BuiltIn) -> &'static str {
         BuiltIn::LocalInvocationIndex => "gl_LocalInvocationIndex",
         BuiltIn::WorkGroupId => "gl_WorkGroupID",
         BuiltIn::WorkGroupSize => "gl_WorkGroupSize",
+        // subgroup
+        BuiltIn::SubgroupSize => "gl_SubgroupSize",
+        BuiltIn::SubgroupInvocationId => "gl_SubgroupInvocationID",
+        // geometry and tessellation
+        BuiltIn::InvocationId => "gl_InvocationID",
+        BuiltIn::PatchVertices => "gl_PatchVerticesIn",
+        BuiltIn::TessCoord => "gl_TessCoord",
+        BuiltIn::TessLevelInner => "gl_TessLevelInner",
+        BuiltIn::TessLevelOuter => "gl_TessLevelOuter",
+        // ray tracing
+        BuiltIn::LaunchId => "gl_LaunchIDEXT",
+        BuiltIn::LaunchSize => "gl_LaunchSizeEXT",
+        BuiltIn::WorldRayOrigin => "gl_WorldRayOriginEXT",
+        BuiltIn::WorldRayDirection => "gl_WorldRayDirectionEXT",
+        BuiltIn::ObjectRayOrigin => "gl_ObjectRayOriginEXT",
+        BuiltIn::ObjectRayDirection => "gl_ObjectRayDirectionEXT",
+        BuiltIn::RayTmin => "gl_RayTminEXT",
+        BuiltIn::RayTmax => "gl_RayTmaxEXT",
+        BuiltIn::InstanceCustomIndex => "gl_InstanceCustomIndexEXT",
+        BuiltIn::ObjectToWorld => "gl_ObjectToWorldEXT",
+        BuiltIn::WorldToObject => "gl_WorldToObjectEXT",
+        BuiltIn::HitKind => "gl_HitKindEXT",
+        BuiltIn::IncomingRayFlags => "gl_IncomingRayFlagsEXT",
     }
 }
 
@@ -1986,13 +2715,17 @@ fn glsl_storage_class(class: StorageClass) -> &'static str {
         StorageClass::Handle => "uniform",
         StorageClass::WorkGroup => "shared",
         StorageClass::PushConstant => "",
+        StorageClass::RayPayload => "rayPayloadEXT",
+        StorageClass::IncomingRayPayload => "rayPayloadInEXT",
+        StorageClass::HitAttribute => "hitAttributeEXT",
     }
 }
 
 /// Helper function that returns the string corresponding to the glsl interpolation qualifier
 ///
 /// # Errors
-/// If [`Patch`](crate::Interpolation::Patch) is passed, as it isn't supported in glsl
+/// If [`Patch`](crate::Interpolation::Patch) is passed, as it isn't an interpolation qualifier in
+/// glsl but a storage qualifier of the tessellation stages
 fn glsl_interpolation(interpolation: Interpolation) -> Result<&'static str, Error> {
     Ok(match interpolation {
         Interpolation::Perspective => "smooth",
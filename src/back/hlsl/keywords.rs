@@ -0,0 +1,342 @@
+// https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-appendix-keywords
+// https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-intrinsic-functions
+pub const RESERVED: &[&str] = &[
+    // keywords
+    "AppendStructuredBuffer",
+    "asm",
+    "asm_fragment",
+    "BlendState",
+    "bool",
+    "break",
+    "Buffer",
+    "ByteAddressBuffer",
+    "case",
+    "cbuffer",
+    "centroid",
+    "class",
+    "column_major",
+    "compile",
+    "compile_fragment",
+    "CompileShader",
+    "const",
+    "continue",
+    "ComputeShader",
+    "ConsumeStructuredBuffer",
+    "default",
+    "DepthStencilState",
+    "DepthStencilView",
+    "discard",
+    "do",
+    "double",
+    "DomainShader",
+    "dword",
+    "else",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "fxgroup",
+    "GeometryShader",
+    "groupshared",
+    "half",
+    "Hullshader",
+    "if",
+    "in",
+    "inline",
+    "inout",
+    "InputPatch",
+    "int",
+    "interface",
+    "line",
+    "lineadj",
+    "linear",
+    "LineStream",
+    "matrix",
+    "min16float",
+    "min10float",
+    "min16int",
+    "min12int",
+    "min16uint",
+    "namespace",
+    "nointerpolation",
+    "noperspective",
+    "NULL",
+    "out",
+    "OutputPatch",
+    "packoffset",
+    "pass",
+    "pixelfragment",
+    "PixelShader",
+    "point",
+    "PointStream",
+    "precise",
+    "RasterizerState",
+    "RenderTargetView",
+    "return",
+    "register",
+    "row_major",
+    "RWBuffer",
+    "RWByteAddressBuffer",
+    "RWStructuredBuffer",
+    "RWTexture1D",
+    "RWTexture1DArray",
+    "RWTexture2D",
+    "RWTexture2DArray",
+    "RWTexture3D",
+    "sample",
+    "sampler",
+    "SamplerState",
+    "SamplerComparisonState",
+    "shared",
+    "snorm",
+    "stateblock",
+    "stateblock_state",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "StructuredBuffer",
+    "tbuffer",
+    "technique",
+    "technique10",
+    "technique11",
+    "texture",
+    "Texture1D",
+    "Texture1DArray",
+    "Texture2D",
+    "Texture2DArray",
+    "Texture2DMS",
+    "Texture2DMSArray",
+    "Texture3D",
+    "TextureCube",
+    "TextureCubeArray",
+    "true",
+    "typedef",
+    "triangle",
+    "triangleadj",
+    "TriangleStream",
+    "uint",
+    "uniform",
+    "unorm",
+    "unsigned",
+    "vector",
+    "vertexfragment",
+    "VertexShader",
+    "void",
+    "volatile",
+    "while",
+    // vector and matrix types
+    "bool2",
+    "bool3",
+    "bool4",
+    "int2",
+    "int3",
+    "int4",
+    "uint2",
+    "uint3",
+    "uint4",
+    "half2",
+    "half3",
+    "half4",
+    "float2",
+    "float3",
+    "float4",
+    "double2",
+    "double3",
+    "double4",
+    "half2x2",
+    "half2x3",
+    "half2x4",
+    "half3x2",
+    "half3x3",
+    "half3x4",
+    "half4x2",
+    "half4x3",
+    "half4x4",
+    "float2x2",
+    "float2x3",
+    "float2x4",
+    "float3x2",
+    "float3x3",
+    "float3x4",
+    "float4x2",
+    "float4x3",
+    "float4x4",
+    "double2x2",
+    "double2x3",
+    "double2x4",
+    "double3x2",
+    "double3x3",
+    "double3x4",
+    "double4x2",
+    "double4x3",
+    "double4x4",
+    // reserved words
+    "auto",
+    "catch",
+    "char",
+    "const_cast",
+    "delete",
+    "dynamic_cast",
+    "enum",
+    "explicit",
+    "friend",
+    "goto",
+    "long",
+    "mutable",
+    "new",
+    "operator",
+    "private",
+    "protected",
+    "public",
+    "reinterpret_cast",
+    "short",
+    "signed",
+    "sizeof",
+    "static_cast",
+    "template",
+    "this",
+    "throw",
+    "try",
+    "typename",
+    "union",
+    "using",
+    "virtual",
+    // intrinsic functions
+    "abort",
+    "abs",
+    "acos",
+    "all",
+    "AllMemoryBarrier",
+    "AllMemoryBarrierWithGroupSync",
+    "any",
+    "asdouble",
+    "asfloat",
+    "asin",
+    "asint",
+    "asuint",
+    "atan",
+    "atan2",
+    "ceil",
+    "CheckAccessFullyMapped",
+    "clamp",
+    "clip",
+    "cos",
+    "cosh",
+    "countbits",
+    "cross",
+    "ddx",
+    "ddx_coarse",
+    "ddx_fine",
+    "ddy",
+    "ddy_coarse",
+    "ddy_fine",
+    "degrees",
+    "determinant",
+    "DeviceMemoryBarrier",
+    "DeviceMemoryBarrierWithGroupSync",
+    "distance",
+    "dot",
+    "dst",
+    "errorf",
+    "EvaluateAttributeAtCentroid",
+    "EvaluateAttributeAtSample",
+    "EvaluateAttributeSnapped",
+    "exp",
+    "exp2",
+    "f16tof32",
+    "f32tof16",
+    "faceforward",
+    "firstbithigh",
+    "firstbitlow",
+    "floor",
+    "fma",
+    "fmod",
+    "frac",
+    "frexp",
+    "fwidth",
+    "GetRenderTargetSampleCount",
+    "GetRenderTargetSamplePosition",
+    "GroupMemoryBarrier",
+    "GroupMemoryBarrierWithGroupSync",
+    "InterlockedAdd",
+    "InterlockedAnd",
+    "InterlockedCompareExchange",
+    "InterlockedCompareStore",
+    "InterlockedExchange",
+    "InterlockedMax",
+    "InterlockedMin",
+    "InterlockedOr",
+    "InterlockedXor",
+    "isfinite",
+    "isinf",
+    "isnan",
+    "ldexp",
+    "length",
+    "lerp",
+    "lit",
+    "log",
+    "log10",
+    "log2",
+    "mad",
+    "max",
+    "min",
+    "modf",
+    "msad4",
+    "mul",
+    "noise",
+    "normalize",
+    "pow",
+    "printf",
+    "Process2DQuadTessFactorsAvg",
+    "Process2DQuadTessFactorsMax",
+    "Process2DQuadTessFactorsMin",
+    "ProcessIsolineTessFactors",
+    "ProcessQuadTessFactorsAvg",
+    "ProcessQuadTessFactorsMax",
+    "ProcessQuadTessFactorsMin",
+    "ProcessTriTessFactorsAvg",
+    "ProcessTriTessFactorsMax",
+    "ProcessTriTessFactorsMin",
+    "radians",
+    "rcp",
+    "reflect",
+    "refract",
+    "reversebits",
+    "round",
+    "rsqrt",
+    "saturate",
+    "sign",
+    "sin",
+    "sincos",
+    "sinh",
+    "smoothstep",
+    "sqrt",
+    "step",
+    "tan",
+    "tanh",
+    "transpose",
+    "trunc",
+    // wave intrinsics
+    "WaveActiveAllEqual",
+    "WaveActiveAllTrue",
+    "WaveActiveAnyTrue",
+    "WaveActiveBallot",
+    "WaveActiveBitAnd",
+    "WaveActiveBitOr",
+    "WaveActiveBitXor",
+    "WaveActiveCountBits",
+    "WaveActiveMax",
+    "WaveActiveMin",
+    "WaveActiveProduct",
+    "WaveActiveSum",
+    "WaveGetLaneCount",
+    "WaveGetLaneIndex",
+    "WaveIsFirstLane",
+    "WavePrefixCountBits",
+    "WavePrefixProduct",
+    "WavePrefixSum",
+    "WaveReadLaneAt",
+    "WaveReadLaneFirst",
+];
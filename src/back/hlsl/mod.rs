@@ -0,0 +1,191 @@
+/*! High Level Shading Language (HLSL) backend
+
+## Binding model
+
+HLSL resources live in registers of four kinds: `b` for constant buffers,
+`t` for shader resource views, `u` for unordered access views, and `s` for samplers.
+Shader model 5.1 added register spaces on top of that. The register and space
+of each resource are provided in the options, per group + binding pair.
+The kind of the register is derived from the resource type.
+
+Uniform buffers are declared as `cbuffer` blocks, and storage buffers as
+`[RW]StructuredBuffer`s. A storage buffer whose only member is a runtime-sized
+array becomes a structured buffer of the array elements; any other storage
+buffer becomes a structured buffer with a single element.
+
+## Matrices
+
+IR matrices are declared as `floatCxR`, so that the HLSL rows are the columns
+of the IR matrix. This keeps the memory layout of column-major matrices when
+they are declared with `row_major`, and the order of operands in products is
+swapped accordingly.
+
+## Entry points
+
+Global variables in the `Input` and `Output` storage classes become static
+variables, which are filled from an input struct at the start of the entry point,
+and are collected into an output struct when it returns.
+!*/
+
+use crate::{
+    proc::{analyzer::Analysis, TypifyError},
+    FastHashMap,
+};
+use std::{fmt, io::Error as IoError, string::FromUtf8Error};
+
+mod keywords;
+mod writer;
+
+pub use writer::Writer;
+
+/// Shader model of the generated HLSL.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+pub enum ShaderModel {
+    V5_0,
+    V5_1,
+    V6_0,
+    V6_1,
+    V6_2,
+    V6_3,
+    V6_4,
+    V6_5,
+}
+
+impl ShaderModel {
+    /// Target profile for compiling the given stage, e.g. `vs_5_1`.
+    pub fn profile(self, stage: crate::ShaderStage) -> Option<String> {
+        let prefix = match stage {
+            crate::ShaderStage::Vertex => "vs",
+            crate::ShaderStage::Fragment => "ps",
+            crate::ShaderStage::Compute => "cs",
+            crate::ShaderStage::Geometry => "gs",
+            crate::ShaderStage::TessellationControl => "hs",
+            crate::ShaderStage::TessellationEvaluation => "ds",
+            _ => return None,
+        };
+        Some(format!("{}_{}", prefix, self))
+    }
+
+    /// Register spaces are supported starting with shader model 5.1.
+    fn has_register_spaces(self) -> bool {
+        self >= ShaderModel::V5_1
+    }
+
+    /// Wave intrinsics and 64-bit integers need shader model 6.0.
+    fn has_wave_ops(self) -> bool {
+        self >= ShaderModel::V6_0
+    }
+
+    /// Native 16-bit types need shader model 6.2.
+    fn has_16bit_types(self) -> bool {
+        self >= ShaderModel::V6_2
+    }
+}
+
+impl fmt::Display for ShaderModel {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (major, minor) = match *self {
+            ShaderModel::V5_0 => (5, 0),
+            ShaderModel::V5_1 => (5, 1),
+            ShaderModel::V6_0 => (6, 0),
+            ShaderModel::V6_1 => (6, 1),
+            ShaderModel::V6_2 => (6, 2),
+            ShaderModel::V6_3 => (6, 3),
+            ShaderModel::V6_4 => (6, 4),
+            ShaderModel::V6_5 => (6, 5),
+        };
+        write!(formatter, "{}_{}", major, minor)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BindTarget {
+    pub space: u8,
+    pub register: u32,
+}
+
+#[derive(Clone, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+pub struct BindSource {
+    pub group: u32,
+    pub binding: u32,
+}
+
+pub type BindingMap = FastHashMap<BindSource, BindTarget>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    IO(#[from] IoError),
+    #[error(transparent)]
+    Utf8(#[from] FromUtf8Error),
+    #[error(transparent)]
+    Type(#[from] TypifyError),
+    #[error("bind source for {0:?} is missing from the map")]
+    MissingBindTarget(BindSource),
+    #[error("register space {0} requires shader model 5.1")]
+    UnsupportedRegisterSpace(u8),
+    #[error("scalar {0:?} with width {1} is not supported by shader model {2}")]
+    UnsupportedScalar(crate::ScalarKind, crate::Bytes, ShaderModel),
+    #[error("math function {0:?} is not supported by HLSL")]
+    UnsupportedMathFunction(crate::MathFunction),
+    #[error("{0} requires shader model 6.0")]
+    UnsupportedWaveOperation(String),
+    #[error("feature '{0}' is not implemented yet")]
+    FeatureNotImplemented(String),
+    #[error("module is not valid")]
+    Validation,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The shader model to target.
+    pub shader_model: ShaderModel,
+    /// Binding model mapping to HLSL registers.
+    pub binding_map: BindingMap,
+    /// Use the binding as the register and the group as the space
+    /// for resources that are missing from the map, instead of failing.
+    pub fake_missing_bindings: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            shader_model: ShaderModel::V5_1,
+            binding_map: BindingMap::default(),
+            fake_missing_bindings: true,
+        }
+    }
+}
+
+impl Options {
+    fn resolve_resource_binding(&self, group: u32, binding: u32) -> Result<BindTarget, Error> {
+        let source = BindSource { group, binding };
+        match self.binding_map.get(&source) {
+            Some(target) => Ok(target.clone()),
+            None if self.fake_missing_bindings => Ok(BindTarget {
+                space: group as u8,
+                register: binding,
+            }),
+            None => Err(Error::MissingBindTarget(source)),
+        }
+    }
+}
+
+/// Information about a translated module that is required
+/// for the use of the result.
+pub struct TranslationInfo {
+    /// Mapping of the entry point names. Each item in the array
+    /// corresponds to an entry point in `module.entry_points.iter()`.
+    pub entry_point_names: Vec<String>,
+}
+
+pub fn write_string(
+    module: &crate::Module,
+    analysis: &Analysis,
+    options: &Options,
+) -> Result<(String, TranslationInfo), Error> {
+    let mut w = Writer::new(Vec::new());
+    let info = w.write(module, analysis, options)?;
+    let string = String::from_utf8(w.finish())?;
+    Ok((string, info))
+}
@@ -0,0 +1,2266 @@
+use super::{keywords::RESERVED, Error, Options, ShaderModel, TranslationInfo};
+use crate::{
+    arena::Handle,
+    proc::{
+        analyzer::{Analysis, FunctionInfo, GlobalUse},
+        EntryPointIndex, NameKey, Namer, ResolveContext, Typifier,
+    },
+    FastHashMap, FastHashSet,
+};
+use bit_set::BitSet;
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    io::Write,
+};
+
+const INDENT: &str = "    ";
+const BAKE_PREFIX: &str = "_e";
+const COMPONENTS: &[char] = &['x', 'y', 'z', 'w'];
+
+#[derive(Clone)]
+struct Level(usize);
+impl Level {
+    fn next(&self) -> Self {
+        Level(self.0 + 1)
+    }
+}
+impl Display for Level {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), FmtError> {
+        (0..self.0).try_for_each(|_| formatter.write_str(INDENT))
+    }
+}
+
+fn scalar_string(
+    kind: crate::ScalarKind,
+    width: crate::Bytes,
+    model: ShaderModel,
+) -> Result<&'static str, Error> {
+    Ok(match (kind, width) {
+        (crate::ScalarKind::Float, 2) if model.has_16bit_types() => "half",
+        (crate::ScalarKind::Float, 4) => "float",
+        (crate::ScalarKind::Float, 8) => "double",
+        (crate::ScalarKind::Sint, 4) => "int",
+        (crate::ScalarKind::Uint, 4) => "uint",
+        (crate::ScalarKind::Sint, 8) if model.has_wave_ops() => "int64_t",
+        (crate::ScalarKind::Uint, 8) if model.has_wave_ops() => "uint64_t",
+        (crate::ScalarKind::Bool, crate::BOOL_WIDTH) => "bool",
+        _ => return Err(Error::UnsupportedScalar(kind, width, model)),
+    })
+}
+
+fn built_in_semantic(built_in: crate::BuiltIn) -> Result<&'static str, Error> {
+    use crate::BuiltIn as Bi;
+    Ok(match built_in {
+        // vertex
+        Bi::ClipDistance => "SV_ClipDistance",
+        Bi::InstanceIndex => "SV_InstanceID",
+        Bi::Position => "SV_Position",
+        Bi::VertexIndex => "SV_VertexID",
+        // fragment
+        Bi::FragCoord => "SV_Position",
+        Bi::FragDepth => "SV_Depth",
+        Bi::FrontFacing => "SV_IsFrontFace",
+        Bi::SampleIndex => "SV_SampleIndex",
+        Bi::SampleMaskIn | Bi::SampleMaskOut => "SV_Coverage",
+        // compute
+        Bi::GlobalInvocationId => "SV_DispatchThreadID",
+        Bi::LocalInvocationId => "SV_GroupThreadID",
+        Bi::LocalInvocationIndex => "SV_GroupIndex",
+        Bi::WorkGroupId => "SV_GroupID",
+        _ => {
+            return Err(Error::FeatureNotImplemented(format!(
+                "built-in {:?}",
+                built_in
+            )))
+        }
+    })
+}
+
+/// Built-ins that are provided by intrinsic functions instead of semantics.
+fn built_in_intrinsic(built_in: crate::BuiltIn) -> Option<&'static str> {
+    match built_in {
+        crate::BuiltIn::SubgroupSize => Some("WaveGetLaneCount()"),
+        crate::BuiltIn::SubgroupInvocationId => Some("WaveGetLaneIndex()"),
+        _ => None,
+    }
+}
+
+fn interpolation_string(interpolation: crate::Interpolation) -> Result<&'static str, Error> {
+    Ok(match interpolation {
+        crate::Interpolation::Perspective => "linear",
+        crate::Interpolation::Linear => "noperspective",
+        crate::Interpolation::Flat => "nointerpolation",
+        crate::Interpolation::Centroid => "centroid",
+        crate::Interpolation::Sample => "sample",
+        crate::Interpolation::Patch => {
+            return Err(Error::FeatureNotImplemented(
+                "patch interpolation".to_string(),
+            ))
+        }
+    })
+}
+
+fn math_function_string(fun: crate::MathFunction) -> Result<&'static str, Error> {
+    use crate::MathFunction as Mf;
+    Ok(match fun {
+        // comparison
+        Mf::Abs => "abs",
+        Mf::Min => "min",
+        Mf::Max => "max",
+        Mf::Clamp => "clamp",
+        // trigonometry
+        Mf::Cos => "cos",
+        Mf::Cosh => "cosh",
+        Mf::Sin => "sin",
+        Mf::Sinh => "sinh",
+        Mf::Tan => "tan",
+        Mf::Tanh => "tanh",
+        Mf::Acos => "acos",
+        Mf::Asin => "asin",
+        Mf::Atan => "atan",
+        Mf::Atan2 => "atan2",
+        // decomposition
+        Mf::Ceil => "ceil",
+        Mf::Floor => "floor",
+        Mf::Round => "round",
+        Mf::Fract => "frac",
+        Mf::Trunc => "trunc",
+        Mf::Modf => "modf",
+        Mf::Frexp => "frexp",
+        Mf::Ldexp => "ldexp",
+        // exponent
+        Mf::Exp => "exp",
+        Mf::Exp2 => "exp2",
+        Mf::Log => "log",
+        Mf::Log2 => "log2",
+        Mf::Pow => "pow",
+        // geometry
+        Mf::Dot => "dot",
+        Mf::Cross => "cross",
+        Mf::Distance => "distance",
+        Mf::Length => "length",
+        Mf::Normalize => "normalize",
+        Mf::FaceForward => "faceforward",
+        Mf::Reflect => "reflect",
+        // computational
+        Mf::Sign => "sign",
+        Mf::Fma => "mad",
+        Mf::Mix => "lerp",
+        Mf::Step => "step",
+        Mf::SmoothStep => "smoothstep",
+        Mf::Sqrt => "sqrt",
+        Mf::InverseSqrt => "rsqrt",
+        Mf::Transpose => "transpose",
+        Mf::Determinant => "determinant",
+        // bits
+        Mf::CountOneBits => "countbits",
+        Mf::ReverseBits => "reversebits",
+        Mf::Outer | Mf::Inverse => return Err(Error::UnsupportedMathFunction(fun)),
+    })
+}
+
+fn atomic_fun_name(fun: &crate::AtomicFunction) -> &'static str {
+    match *fun {
+        crate::AtomicFunction::Add | crate::AtomicFunction::Subtract => "InterlockedAdd",
+        crate::AtomicFunction::And => "InterlockedAnd",
+        crate::AtomicFunction::ExclusiveOr => "InterlockedXor",
+        crate::AtomicFunction::InclusiveOr => "InterlockedOr",
+        crate::AtomicFunction::Min => "InterlockedMin",
+        crate::AtomicFunction::Max => "InterlockedMax",
+        crate::AtomicFunction::Exchange { compare: None } => "InterlockedExchange",
+        crate::AtomicFunction::Exchange { compare: Some(_) } => "InterlockedCompareExchange",
+    }
+}
+
+fn dimension_count(dim: crate::ImageDimension) -> usize {
+    match dim {
+        crate::ImageDimension::D1 => 1,
+        crate::ImageDimension::D2 | crate::ImageDimension::Cube => 2,
+        crate::ImageDimension::D3 => 3,
+    }
+}
+
+/// Returns true if the storage buffer is read-only, and can be a shader resource view.
+fn is_read_only(access: crate::StorageAccess) -> bool {
+    !access.contains(crate::StorageAccess::STORE)
+}
+
+/// Kind of an image query, as far as the helper functions are concerned.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+enum QueryKind {
+    Size,
+    NumLevels,
+    NumLayers,
+    NumSamples,
+}
+
+impl From<crate::ImageQuery> for QueryKind {
+    fn from(query: crate::ImageQuery) -> Self {
+        match query {
+            crate::ImageQuery::Size { .. } => QueryKind::Size,
+            crate::ImageQuery::NumLevels => QueryKind::NumLevels,
+            crate::ImageQuery::NumLayers => QueryKind::NumLayers,
+            crate::ImageQuery::NumSamples => QueryKind::NumSamples,
+        }
+    }
+}
+
+/// Image type as it's declared in HLSL, which depends on the access
+/// of the global variable for storage images.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+struct ImageType {
+    dim: crate::ImageDimension,
+    arrayed: bool,
+    class: crate::ImageClass,
+    writable: bool,
+}
+
+impl ImageType {
+    /// Returns true if the texture has mipmaps that `GetDimensions` can be asked about.
+    fn has_levels(&self) -> bool {
+        match self.class {
+            crate::ImageClass::Sampled { multi, .. } => !multi,
+            crate::ImageClass::Depth => true,
+            crate::ImageClass::Storage(_) => !self.writable,
+        }
+    }
+}
+
+enum FunctionOrigin {
+    Handle(Handle<crate::Function>),
+    EntryPoint(EntryPointIndex),
+}
+
+/// Values collected into the output struct of an entry point when it returns.
+struct EntryPointOutput {
+    struct_name: String,
+    name: String,
+    members: Vec<String>,
+}
+
+struct FunctionContext<'a> {
+    function: &'a crate::Function,
+    origin: FunctionOrigin,
+    info: &'a FunctionInfo,
+    module: &'a crate::Module,
+    output: Option<EntryPointOutput>,
+}
+
+impl FunctionContext<'_> {
+    fn argument_key(&self, index: u32) -> NameKey {
+        match self.origin {
+            FunctionOrigin::Handle(handle) => NameKey::FunctionArgument(handle, index),
+            FunctionOrigin::EntryPoint(ep_index) => NameKey::EntryPointArgument(ep_index, index),
+        }
+    }
+
+    fn local_key(&self, local: Handle<crate::LocalVariable>) -> NameKey {
+        match self.origin {
+            FunctionOrigin::Handle(handle) => NameKey::FunctionLocal(handle, local),
+            FunctionOrigin::EntryPoint(ep_index) => NameKey::EntryPointLocal(ep_index, local),
+        }
+    }
+}
+
+pub struct Writer<W> {
+    out: W,
+    names: FastHashMap<NameKey, String>,
+    named_expressions: BitSet,
+    typifier: Typifier,
+    namer: Namer,
+    shader_model: ShaderModel,
+    /// Storage buffers declared as structured buffers of their only member.
+    element_buffers: FastHashSet<Handle<crate::GlobalVariable>>,
+    /// Structs with locations that are render targets.
+    fragment_outputs: FastHashSet<Handle<crate::Type>>,
+    /// Samplers that are used for depth comparisons.
+    comparison_samplers: FastHashSet<Handle<crate::GlobalVariable>>,
+    /// Helper functions for the queries of images.
+    image_queries: FastHashMap<(ImageType, QueryKind), String>,
+    /// Helper functions for the lengths of element buffers.
+    array_lengths: FastHashMap<Handle<crate::GlobalVariable>, String>,
+    /// Helper functions for the construction of structs.
+    constructors: FastHashMap<Handle<crate::Type>, String>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new `Writer` instance.
+    pub fn new(out: W) -> Self {
+        Writer {
+            out,
+            names: FastHashMap::default(),
+            named_expressions: BitSet::new(),
+            typifier: Typifier::new(),
+            namer: Namer::default(),
+            shader_model: ShaderModel::V5_1,
+            element_buffers: FastHashSet::default(),
+            fragment_outputs: FastHashSet::default(),
+            comparison_samplers: FastHashSet::default(),
+            image_queries: FastHashMap::default(),
+            array_lengths: FastHashMap::default(),
+            constructors: FastHashMap::default(),
+        }
+    }
+
+    /// Finishes writing and returns the output.
+    pub fn finish(self) -> W {
+        self.out
+    }
+
+    pub fn write(
+        &mut self,
+        module: &crate::Module,
+        analysis: &Analysis,
+        options: &Options,
+    ) -> Result<TranslationInfo, Error> {
+        self.names.clear();
+        self.namer.reset(module, RESERVED, &mut self.names);
+        self.shader_model = options.shader_model;
+        self.element_buffers.clear();
+        self.fragment_outputs.clear();
+        self.comparison_samplers.clear();
+        self.image_queries.clear();
+        self.array_lengths.clear();
+        self.constructors.clear();
+
+        self.collect_helpers(module)?;
+
+        self.write_constants(module, false)?;
+        self.write_structs(module)?;
+        self.write_constants(module, true)?;
+        self.write_global_variables(module, options)?;
+        self.write_helpers(module)?;
+
+        for (handle, function) in module.functions.iter() {
+            let context = FunctionContext {
+                function,
+                origin: FunctionOrigin::Handle(handle),
+                info: &analysis[handle],
+                module,
+                output: None,
+            };
+            let name = self.names[&NameKey::Function(handle)].clone();
+            self.write_function(&name, context)?;
+            writeln!(self.out)?;
+        }
+
+        let mut info = TranslationInfo {
+            entry_point_names: Vec::with_capacity(module.entry_points.len()),
+        };
+        for (index, ep) in module.entry_points.iter().enumerate() {
+            let name = self.names[&NameKey::EntryPoint(index as _)].clone();
+            self.write_entry_point(module, analysis, index, ep, &name)?;
+            writeln!(self.out)?;
+            info.entry_point_names.push(name);
+        }
+
+        Ok(info)
+    }
+
+    /// Finds out which helper functions need to be generated, and names them.
+    fn collect_helpers(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, var) in module.global_variables.iter() {
+            if var.class != crate::StorageClass::Storage {
+                continue;
+            }
+            if let crate::TypeInner::Struct { ref members, .. } = module.types[var.ty].inner {
+                if let [ref member] = members[..] {
+                    if let crate::TypeInner::Array {
+                        size: crate::ArraySize::Dynamic,
+                        ..
+                    } = module.types[member.ty].inner
+                    {
+                        self.element_buffers.insert(handle);
+                    }
+                }
+            }
+        }
+
+        for ep in module.entry_points.iter() {
+            if ep.stage != crate::ShaderStage::Fragment {
+                continue;
+            }
+            if let Some(ref result) = ep.function.result {
+                if let crate::TypeInner::Struct { .. } = module.types[result.ty].inner {
+                    self.fragment_outputs.insert(result.ty);
+                }
+            }
+        }
+
+        let functions = module
+            .functions
+            .iter()
+            .map(|(_, fun)| fun)
+            .chain(module.entry_points.iter().map(|ep| &ep.function));
+        for function in functions {
+            for (_, expression) in function.expressions.iter() {
+                match *expression {
+                    crate::Expression::ImageQuery { image, query } => {
+                        let image_type = self.image_type_of(module, function, image)?;
+                        let kind = QueryKind::from(query);
+                        if !self.image_queries.contains_key(&(image_type, kind)) {
+                            let label = match kind {
+                                QueryKind::Size => "NagaDimensions",
+                                QueryKind::NumLevels => "NagaNumLevels",
+                                QueryKind::NumLayers => "NagaNumLayers",
+                                QueryKind::NumSamples => "NagaNumSamples",
+                            };
+                            let name = self.namer.call(label);
+                            self.image_queries.insert((image_type, kind), name);
+                        }
+                    }
+                    crate::Expression::ImageSample {
+                        sampler,
+                        depth_ref: Some(_),
+                        ..
+                    } => {
+                        if let crate::Expression::GlobalVariable(handle) =
+                            function.expressions[sampler]
+                        {
+                            self.comparison_samplers.insert(handle);
+                        }
+                    }
+                    crate::Expression::ArrayLength(array) => {
+                        let handle = match function.expressions[array] {
+                            crate::Expression::AccessIndex { base, index: 0 } => {
+                                match function.expressions[base] {
+                                    crate::Expression::GlobalVariable(handle)
+                                        if self.element_buffers.contains(&handle) =>
+                                    {
+                                        handle
+                                    }
+                                    _ => {
+                                        return Err(Error::FeatureNotImplemented(
+                                            "length of an array within a buffer".to_string(),
+                                        ))
+                                    }
+                                }
+                            }
+                            _ => return Err(Error::Validation),
+                        };
+                        if !self.array_lengths.contains_key(&handle) {
+                            let label = format!(
+                                "NagaArrayLength_{}",
+                                self.names[&NameKey::GlobalVariable(handle)]
+                            );
+                            let name = self.namer.call(&label);
+                            self.array_lengths.insert(handle, name);
+                        }
+                    }
+                    crate::Expression::Compose { ty, .. } => {
+                        if let crate::TypeInner::Struct { .. } = module.types[ty].inner {
+                            if !self.constructors.contains_key(&ty) {
+                                let label = format!("Construct{}", self.names[&NameKey::Type(ty)]);
+                                let name = self.namer.call(&label);
+                                self.constructors.insert(ty, name);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Determines the declared type of an image expression, which is
+    /// either a global variable, or a function argument.
+    fn image_type_of(
+        &self,
+        module: &crate::Module,
+        function: &crate::Function,
+        image: Handle<crate::Expression>,
+    ) -> Result<ImageType, Error> {
+        let (ty, access) = match function.expressions[image] {
+            crate::Expression::GlobalVariable(handle) => {
+                let var = &module.global_variables[handle];
+                (var.ty, var.storage_access)
+            }
+            crate::Expression::FunctionArgument(index) => {
+                let arg = &function.arguments[index as usize];
+                (arg.ty, crate::StorageAccess::all())
+            }
+            _ => return Err(Error::Validation),
+        };
+        match module.types[ty].inner {
+            crate::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => Ok(ImageType {
+                dim,
+                arrayed,
+                class,
+                writable: match class {
+                    crate::ImageClass::Storage(_) => !is_read_only(access),
+                    _ => false,
+                },
+            }),
+            _ => Err(Error::Validation),
+        }
+    }
+
+    fn write_scalar_value(
+        &mut self,
+        width: crate::Bytes,
+        value: &crate::ScalarValue,
+    ) -> Result<(), Error> {
+        match *value {
+            crate::ScalarValue::Sint(value) => {
+                let suffix = if width == 8 { "L" } else { "" };
+                write!(self.out, "{}{}", value, suffix)?;
+            }
+            crate::ScalarValue::Uint(value) => {
+                let suffix = if width == 8 { "uL" } else { "u" };
+                write!(self.out, "{}{}", value, suffix)?;
+            }
+            crate::ScalarValue::Float(value) if !value.is_finite() => {
+                if width != 4 {
+                    return Err(Error::FeatureNotImplemented(format!(
+                        "float value {}",
+                        value
+                    )));
+                }
+                let bits = if value.is_nan() {
+                    0x7fc0_0000
+                } else if value.is_sign_negative() {
+                    0xff80_0000u32
+                } else {
+                    0x7f80_0000
+                };
+                write!(self.out, "asfloat({:#x}u)", bits)?;
+            }
+            crate::ScalarValue::Float(value) => {
+                let mut string = format!("{:?}", value);
+                if !string.contains('.') {
+                    let position = string.find('e').unwrap_or(string.len());
+                    string.insert_str(position, ".0");
+                }
+                let suffix = match width {
+                    2 => "h",
+                    8 => "L",
+                    _ => "",
+                };
+                write!(self.out, "{}{}", string, suffix)?;
+            }
+            crate::ScalarValue::Bool(value) => write!(self.out, "{}", value)?,
+        }
+        Ok(())
+    }
+
+    /// Returns true if the constant gets a declaration of its own,
+    /// as opposed to being written as a literal at the place of use.
+    fn is_declared(constant: &crate::Constant) -> bool {
+        match constant.inner {
+            crate::ConstantInner::Scalar { .. } => {
+                constant.name.is_some() || constant.specialization.is_some()
+            }
+            crate::ConstantInner::Composite { .. } => true,
+        }
+    }
+
+    /// Writes a reference to the constant: either its name, or a literal.
+    fn write_constant(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Constant>,
+    ) -> Result<(), Error> {
+        let constant = &module.constants[handle];
+        if Self::is_declared(constant) {
+            write!(self.out, "{}", self.names[&NameKey::Constant(handle)])?;
+            return Ok(());
+        }
+        match constant.inner {
+            crate::ConstantInner::Scalar { width, ref value } => {
+                self.write_scalar_value(width, value)
+            }
+            crate::ConstantInner::Composite { .. } => Err(Error::Validation),
+        }
+    }
+
+    /// Writes the module constants. HLSL has no specialization constants,
+    /// so the specializable ones get their default values.
+    fn write_constants(&mut self, module: &crate::Module, composite: bool) -> Result<(), Error> {
+        let mut any = false;
+        for (handle, constant) in module.constants.iter() {
+            if !Self::is_declared(constant) {
+                continue;
+            }
+            let name = self.names[&NameKey::Constant(handle)].clone();
+            match constant.inner {
+                crate::ConstantInner::Scalar { width, ref value } if !composite => {
+                    let kind = value.scalar_kind();
+                    write!(
+                        self.out,
+                        "static const {} {} = ",
+                        scalar_string(kind, width, self.shader_model)?,
+                        name
+                    )?;
+                    self.write_scalar_value(width, value)?;
+                    writeln!(self.out, ";")?;
+                }
+                crate::ConstantInner::Composite { ty, ref components } if composite => {
+                    write!(self.out, "static const ")?;
+                    self.write_typed_name(module, ty, &name)?;
+                    write!(self.out, " = ")?;
+                    let use_initializer_list = match module.types[ty].inner {
+                        crate::TypeInner::Struct { .. } | crate::TypeInner::Array { .. } => true,
+                        _ => false,
+                    };
+                    if use_initializer_list {
+                        write!(self.out, "{{ ")?;
+                    } else {
+                        self.write_type(module, ty)?;
+                        write!(self.out, "(")?;
+                    }
+                    for (index, &component) in components.iter().enumerate() {
+                        if index != 0 {
+                            write!(self.out, ", ")?;
+                        }
+                        self.write_constant(module, component)?;
+                    }
+                    if use_initializer_list {
+                        writeln!(self.out, " }};")?;
+                    } else {
+                        writeln!(self.out, ");")?;
+                    }
+                }
+                _ => continue,
+            }
+            any = true;
+        }
+        if any {
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the semantic of an entry point input or output.
+    fn write_semantic(
+        &mut self,
+        binding: Option<&crate::Binding>,
+        render_target: bool,
+    ) -> Result<(), Error> {
+        match binding {
+            Some(&crate::Binding::BuiltIn(built_in)) => {
+                write!(self.out, " : {}", built_in_semantic(built_in)?)?;
+            }
+            Some(&crate::Binding::Location(location)) => {
+                if render_target {
+                    write!(self.out, " : SV_Target{}", location)?;
+                } else {
+                    write!(self.out, " : LOC{}", location)?;
+                }
+            }
+            Some(&crate::Binding::Resource { .. }) => return Err(Error::Validation),
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn write_interpolation(
+        &mut self,
+        interpolation: Option<crate::Interpolation>,
+    ) -> Result<(), Error> {
+        if let Some(interpolation) = interpolation {
+            write!(self.out, "{} ", interpolation_string(interpolation)?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the struct is only used as a storage buffer,
+    /// since it can't be declared in HLSL with a runtime-sized array.
+    fn has_dynamic_array(module: &crate::Module, members: &[crate::StructMember]) -> bool {
+        members
+            .iter()
+            .any(|member| match module.types[member.ty].inner {
+                crate::TypeInner::Array {
+                    size: crate::ArraySize::Dynamic,
+                    ..
+                } => true,
+                _ => false,
+            })
+    }
+
+    fn write_structs(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, ty) in module.types.iter() {
+            let members = match ty.inner {
+                crate::TypeInner::Struct { ref members, .. } => members,
+                _ => continue,
+            };
+            if Self::has_dynamic_array(module, members) {
+                continue;
+            }
+            let render_target = self.fragment_outputs.contains(&handle);
+            writeln!(self.out, "struct {} {{", self.names[&NameKey::Type(handle)])?;
+            for (index, member) in members.iter().enumerate() {
+                write!(self.out, "{}", INDENT)?;
+                self.write_interpolation(member.interpolation)?;
+                let name = self.names[&NameKey::StructMember(handle, index as u32)].clone();
+                self.write_member_typed_name(module, member.ty, &name)?;
+                self.write_semantic(member.binding.as_ref(), render_target)?;
+                writeln!(self.out, ";")?;
+            }
+            writeln!(self.out, "}};")?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn write_register(
+        &mut self,
+        letter: char,
+        binding: Option<&crate::Binding>,
+        options: &Options,
+    ) -> Result<(), Error> {
+        let (group, binding) = match binding {
+            Some(&crate::Binding::Resource { group, binding }) => (group, binding),
+            _ => return Ok(()),
+        };
+        let target = options.resolve_resource_binding(group, binding)?;
+        write!(self.out, " : register({}{}", letter, target.register)?;
+        if self.shader_model.has_register_spaces() {
+            write!(self.out, ", space{}", target.space)?;
+        } else if target.space != 0 {
+            return Err(Error::UnsupportedRegisterSpace(target.space));
+        }
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
+    fn write_global_variables(
+        &mut self,
+        module: &crate::Module,
+        options: &Options,
+    ) -> Result<(), Error> {
+        for (handle, var) in module.global_variables.iter() {
+            let name = self.names[&NameKey::GlobalVariable(handle)].clone();
+            match var.class {
+                crate::StorageClass::Uniform | crate::StorageClass::PushConstant => {
+                    let block_name = self.namer.call(&format!("{}_block", name));
+                    write!(self.out, "cbuffer {}", block_name)?;
+                    self.write_register('b', var.binding.as_ref(), options)?;
+                    writeln!(self.out, " {{")?;
+                    write!(self.out, "{}", INDENT)?;
+                    self.write_member_typed_name(module, var.ty, &name)?;
+                    writeln!(self.out, ";")?;
+                    writeln!(self.out, "}}")?;
+                }
+                crate::StorageClass::Storage => {
+                    let read_only = is_read_only(var.storage_access);
+                    let (prefix, letter) = if read_only { ("", 't') } else { ("RW", 'u') };
+                    write!(self.out, "{}StructuredBuffer<", prefix)?;
+                    if self.element_buffers.contains(&handle) {
+                        let base = match module.types[var.ty].inner {
+                            crate::TypeInner::Struct { ref members, .. } => {
+                                match module.types[members[0].ty].inner {
+                                    crate::TypeInner::Array { base, .. } => base,
+                                    _ => return Err(Error::Validation),
+                                }
+                            }
+                            _ => return Err(Error::Validation),
+                        };
+                        self.write_element_type(module, base)?;
+                    } else {
+                        match module.types[var.ty].inner {
+                            crate::TypeInner::Struct { ref members, .. }
+                                if Self::has_dynamic_array(module, members) =>
+                            {
+                                return Err(Error::FeatureNotImplemented(
+                                    "storage buffers with runtime-sized arrays after other members"
+                                        .to_string(),
+                                ));
+                            }
+                            _ => {}
+                        }
+                        self.write_element_type(module, var.ty)?;
+                    }
+                    write!(self.out, "> {}", name)?;
+                    self.write_register(letter, var.binding.as_ref(), options)?;
+                    writeln!(self.out, ";")?;
+                }
+                crate::StorageClass::Handle => {
+                    let letter = match module.types[var.ty].inner {
+                        crate::TypeInner::Image {
+                            dim,
+                            arrayed,
+                            class,
+                        } => {
+                            let writable = match class {
+                                crate::ImageClass::Storage(_) => !is_read_only(var.storage_access),
+                                _ => false,
+                            };
+                            self.write_image_type(ImageType {
+                                dim,
+                                arrayed,
+                                class,
+                                writable,
+                            })?;
+                            if writable {
+                                'u'
+                            } else {
+                                't'
+                            }
+                        }
+                        crate::TypeInner::Sampler { comparison } => {
+                            // The IR doesn't require comparison samplers for depth
+                            // comparisons, but HLSL does.
+                            let comparison =
+                                comparison || self.comparison_samplers.contains(&handle);
+                            self.write_type_inner(&crate::TypeInner::Sampler { comparison })?;
+                            's'
+                        }
+                        _ => return Err(Error::Validation),
+                    };
+                    write!(self.out, " {}", name)?;
+                    self.write_register(letter, var.binding.as_ref(), options)?;
+                    writeln!(self.out, ";")?;
+                }
+                crate::StorageClass::Private
+                | crate::StorageClass::Input
+                | crate::StorageClass::Output => {
+                    write!(self.out, "static ")?;
+                    self.write_member_typed_name(module, var.ty, &name)?;
+                    if let Some(init) = var.init {
+                        write!(self.out, " = ")?;
+                        self.write_constant(module, init)?;
+                    }
+                    writeln!(self.out, ";")?;
+                }
+                crate::StorageClass::WorkGroup => {
+                    write!(self.out, "groupshared ")?;
+                    self.write_typed_name(module, var.ty, &name)?;
+                    writeln!(self.out, ";")?;
+                }
+                crate::StorageClass::Function => return Err(Error::Validation),
+                crate::StorageClass::RayPayload
+                | crate::StorageClass::IncomingRayPayload
+                | crate::StorageClass::HitAttribute => {
+                    return Err(Error::FeatureNotImplemented(format!(
+                        "storage class {:?}",
+                        var.class
+                    )))
+                }
+            }
+        }
+        if !module.global_variables.is_empty() {
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the element type of a structured buffer, which can't be an array.
+    fn write_element_type(
+        &mut self,
+        module: &crate::Module,
+        ty: Handle<crate::Type>,
+    ) -> Result<(), Error> {
+        if let crate::TypeInner::Array { .. } = module.types[ty].inner {
+            return Err(Error::FeatureNotImplemented(
+                "structured buffers of arrays".to_string(),
+            ));
+        }
+        self.write_type(module, ty)
+    }
+
+    fn write_image_type(&mut self, image: ImageType) -> Result<(), Error> {
+        let dim_str = match image.dim {
+            crate::ImageDimension::D1 => "1D",
+            crate::ImageDimension::D2 => "2D",
+            crate::ImageDimension::D3 => "3D",
+            crate::ImageDimension::Cube => "Cube",
+        };
+        let arrayed_str = if image.arrayed { "Array" } else { "" };
+        match image.class {
+            crate::ImageClass::Sampled { kind, multi } => {
+                let multi_str = if multi { "MS" } else { "" };
+                let scalar = scalar_string(kind, 4, self.shader_model)?;
+                write!(
+                    self.out,
+                    "Texture{}{}{}<{}4>",
+                    dim_str, multi_str, arrayed_str, scalar
+                )?;
+            }
+            crate::ImageClass::Depth => {
+                write!(self.out, "Texture{}{}<float>", dim_str, arrayed_str)?;
+            }
+            crate::ImageClass::Storage(format) => {
+                let prefix = if image.writable { "RW" } else { "" };
+                let scalar = scalar_string(format.into(), 4, self.shader_model)?;
+                write!(
+                    self.out,
+                    "{}Texture{}{}<{}4>",
+                    prefix, dim_str, arrayed_str, scalar
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the part of the type that goes before the name.
+    fn write_type(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Type>,
+    ) -> Result<(), Error> {
+        match module.types[handle].inner {
+            crate::TypeInner::Struct { .. } => {
+                write!(self.out, "{}", self.names[&NameKey::Type(handle)])?;
+                Ok(())
+            }
+            crate::TypeInner::Array { base, .. } => self.write_type(module, base),
+            crate::TypeInner::Pointer { base, .. } => self.write_type(module, base),
+            ref other => self.write_type_inner(other),
+        }
+    }
+
+    fn write_type_inner(&mut self, inner: &crate::TypeInner) -> Result<(), Error> {
+        let model = self.shader_model;
+        match *inner {
+            crate::TypeInner::Scalar { kind, width } | crate::TypeInner::Atomic { kind, width } => {
+                write!(self.out, "{}", scalar_string(kind, width, model)?)?;
+            }
+            crate::TypeInner::Vector { size, kind, width } => {
+                write!(
+                    self.out,
+                    "{}{}",
+                    scalar_string(kind, width, model)?,
+                    size as u8
+                )?;
+            }
+            crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => {
+                write!(
+                    self.out,
+                    "{}{}x{}",
+                    scalar_string(crate::ScalarKind::Float, width, model)?,
+                    columns as u8,
+                    rows as u8
+                )?;
+            }
+            crate::TypeInner::ValuePointer {
+                size,
+                kind,
+                width,
+                class: _,
+            } => {
+                write!(self.out, "{}", scalar_string(kind, width, model)?)?;
+                if let Some(size) = size {
+                    write!(self.out, "{}", size as u8)?;
+                }
+            }
+            crate::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                self.write_image_type(ImageType {
+                    dim,
+                    arrayed,
+                    class,
+                    writable: match class {
+                        crate::ImageClass::Storage(_) => true,
+                        _ => false,
+                    },
+                })?;
+            }
+            crate::TypeInner::Sampler { comparison } => {
+                let name = if comparison {
+                    "SamplerComparisonState"
+                } else {
+                    "SamplerState"
+                };
+                write!(self.out, "{}", name)?;
+            }
+            // these are always referred to by handle
+            crate::TypeInner::Pointer { .. }
+            | crate::TypeInner::Array { .. }
+            | crate::TypeInner::Struct { .. } => return Err(Error::Validation),
+            crate::TypeInner::AccelerationStructure => {
+                return Err(Error::FeatureNotImplemented(
+                    "acceleration structures".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the array sizes that go after the name.
+    fn write_array_size(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Type>,
+    ) -> Result<(), Error> {
+        if let crate::TypeInner::Array { base, size, .. } = module.types[handle].inner {
+            match size {
+                crate::ArraySize::Constant(constant) => {
+                    write!(self.out, "[")?;
+                    self.write_constant(module, constant)?;
+                    write!(self.out, "]")?;
+                }
+                crate::ArraySize::Dynamic => {
+                    return Err(Error::FeatureNotImplemented(
+                        "runtime-sized arrays outside of storage buffers".to_string(),
+                    ))
+                }
+            }
+            self.write_array_size(module, base)?;
+        }
+        Ok(())
+    }
+
+    fn write_typed_name(
+        &mut self,
+        module: &crate::Module,
+        ty: Handle<crate::Type>,
+        name: &str,
+    ) -> Result<(), Error> {
+        self.write_type(module, ty)?;
+        write!(self.out, " {}", name)?;
+        self.write_array_size(module, ty)
+    }
+
+    /// Writes a declaration of a struct member or a buffer, which needs
+    /// matrices in the row-major layout to match the IR.
+    fn write_member_typed_name(
+        &mut self,
+        module: &crate::Module,
+        ty: Handle<crate::Type>,
+        name: &str,
+    ) -> Result<(), Error> {
+        let mut inner = &module.types[ty].inner;
+        while let crate::TypeInner::Array { base, .. } = *inner {
+            inner = &module.types[base].inner;
+        }
+        if let crate::TypeInner::Matrix { .. } = *inner {
+            write!(self.out, "row_major ")?;
+        }
+        self.write_typed_name(module, ty, name)
+    }
+
+    /// Writes the type of an expression, as resolved by the typifier.
+    fn write_expression_type(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Expression>,
+        name: &str,
+    ) -> Result<(), Error> {
+        // The typifier is moved out while the resolved type is written.
+        let typifier = std::mem::replace(&mut self.typifier, Typifier::new());
+        let result = match typifier.get_handle(handle) {
+            Ok(ty) => self.write_typed_name(module, ty, name),
+            Err(inner) => self
+                .write_type_inner(inner)
+                .and_then(|()| Ok(write!(self.out, " {}", name)?)),
+        };
+        self.typifier = typifier;
+        result
+    }
+
+    fn write_helpers(&mut self, module: &crate::Module) -> Result<(), Error> {
+        let mut image_queries = self
+            .image_queries
+            .iter()
+            .map(|(&key, name)| (name.clone(), key))
+            .collect::<Vec<_>>();
+        image_queries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, (image, kind)) in image_queries {
+            let size_count = dimension_count(image.dim);
+            let mut fields = size_count;
+            let layers_field = fields;
+            if image.arrayed {
+                fields += 1;
+            }
+            let last_field = fields;
+            let has_levels = image.has_levels();
+            let is_multisampled = match image.class {
+                crate::ImageClass::Sampled { multi, .. } => multi,
+                _ => false,
+            };
+            if has_levels || is_multisampled {
+                fields += 1;
+            }
+
+            let return_type = match kind {
+                QueryKind::Size => match image.dim {
+                    crate::ImageDimension::D1 => "int",
+                    crate::ImageDimension::D2 => "int2",
+                    crate::ImageDimension::D3 | crate::ImageDimension::Cube => "int3",
+                },
+                QueryKind::NumLevels | QueryKind::NumLayers | QueryKind::NumSamples => "int",
+            };
+            write!(self.out, "{} {}(", return_type, name)?;
+            self.write_image_type(image)?;
+            write!(self.out, " image")?;
+            if has_levels {
+                write!(self.out, ", uint level")?;
+            }
+            writeln!(self.out, ") {{")?;
+            writeln!(self.out, "{}uint4 ret;", INDENT)?;
+            write!(self.out, "{}image.GetDimensions(", INDENT)?;
+            if has_levels {
+                write!(self.out, "level, ")?;
+            }
+            for (index, component) in COMPONENTS[..fields].iter().enumerate() {
+                if index != 0 {
+                    write!(self.out, ", ")?;
+                }
+                write!(self.out, "ret.{}", component)?;
+            }
+            writeln!(self.out, ");")?;
+            let components = COMPONENTS[..size_count].iter().collect::<String>();
+            match kind {
+                QueryKind::Size if image.dim == crate::ImageDimension::Cube => {
+                    writeln!(self.out, "{}return int3(ret.{}, 1);", INDENT, components)?;
+                }
+                QueryKind::Size => {
+                    writeln!(
+                        self.out,
+                        "{}return {}(ret.{});",
+                        INDENT, return_type, components
+                    )?;
+                }
+                QueryKind::NumLayers => {
+                    writeln!(
+                        self.out,
+                        "{}return int(ret.{});",
+                        INDENT, COMPONENTS[layers_field]
+                    )?;
+                }
+                QueryKind::NumLevels | QueryKind::NumSamples => {
+                    writeln!(
+                        self.out,
+                        "{}return int(ret.{});",
+                        INDENT, COMPONENTS[last_field]
+                    )?;
+                }
+            }
+            writeln!(self.out, "}}")?;
+            writeln!(self.out)?;
+        }
+
+        let mut array_lengths = self
+            .array_lengths
+            .iter()
+            .map(|(&handle, name)| (handle, name.clone()))
+            .collect::<Vec<_>>();
+        array_lengths.sort_by_key(|&(handle, _)| handle.index());
+        for (handle, name) in array_lengths {
+            writeln!(self.out, "uint {}() {{", name)?;
+            writeln!(self.out, "{}uint count, stride;", INDENT)?;
+            writeln!(
+                self.out,
+                "{}{}.GetDimensions(count, stride);",
+                INDENT,
+                self.names[&NameKey::GlobalVariable(handle)]
+            )?;
+            writeln!(self.out, "{}return count;", INDENT)?;
+            writeln!(self.out, "}}")?;
+            writeln!(self.out)?;
+        }
+
+        let mut constructors = self
+            .constructors
+            .iter()
+            .map(|(&handle, name)| (handle, name.clone()))
+            .collect::<Vec<_>>();
+        constructors.sort_by_key(|&(handle, _)| handle.index());
+        for (handle, name) in constructors {
+            let members = match module.types[handle].inner {
+                crate::TypeInner::Struct { ref members, .. } => members,
+                _ => return Err(Error::Validation),
+            };
+            let struct_name = self.names[&NameKey::Type(handle)].clone();
+            write!(self.out, "{} {}(", struct_name, name)?;
+            for (index, member) in members.iter().enumerate() {
+                if index != 0 {
+                    write!(self.out, ", ")?;
+                }
+                self.write_typed_name(module, member.ty, &format!("arg{}", index))?;
+            }
+            writeln!(self.out, ") {{")?;
+            writeln!(self.out, "{}{} ret;", INDENT, struct_name)?;
+            for index in 0..members.len() {
+                let member_name = &self.names[&NameKey::StructMember(handle, index as u32)];
+                writeln!(self.out, "{}ret.{} = arg{};", INDENT, member_name, index)?;
+            }
+            writeln!(self.out, "{}return ret;", INDENT)?;
+            writeln!(self.out, "}}")?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_function_types(
+        &mut self,
+        module: &crate::Module,
+        function: &crate::Function,
+    ) -> Result<(), Error> {
+        self.typifier.resolve_all(
+            &function.expressions,
+            &module.types,
+            &ResolveContext {
+                constants: &module.constants,
+                global_vars: &module.global_variables,
+                local_vars: &function.local_variables,
+                functions: &module.functions,
+                arguments: &function.arguments,
+            },
+        )?;
+        Ok(())
+    }
+
+    fn write_function(&mut self, name: &str, context: FunctionContext) -> Result<(), Error> {
+        let module = context.module;
+        let function = context.function;
+        self.resolve_function_types(module, function)?;
+
+        match function.result {
+            Some(ref result) => {
+                if let crate::TypeInner::Array { .. } = module.types[result.ty].inner {
+                    return Err(Error::FeatureNotImplemented(
+                        "functions returning arrays".to_string(),
+                    ));
+                }
+                self.write_type(module, result.ty)?;
+            }
+            None => write!(self.out, "void")?,
+        }
+        write!(self.out, " {}(", name)?;
+        for (index, arg) in function.arguments.iter().enumerate() {
+            if index != 0 {
+                write!(self.out, ", ")?;
+            }
+            match module.types[arg.ty].inner {
+                crate::TypeInner::Pointer { .. } | crate::TypeInner::ValuePointer { .. } => {
+                    write!(self.out, "inout ")?;
+                }
+                _ => {}
+            }
+            let arg_name = self.names[&context.argument_key(index as u32)].clone();
+            self.write_typed_name(module, arg.ty, &arg_name)?;
+        }
+        writeln!(self.out, ") {{")?;
+        self.write_function_body(&context, &[])?;
+        writeln!(self.out, "}}")?;
+        Ok(())
+    }
+
+    /// Writes the locals and the statements of a function,
+    /// after the given initialization statements.
+    fn write_function_body(
+        &mut self,
+        context: &FunctionContext,
+        prologue: &[String],
+    ) -> Result<(), Error> {
+        let module = context.module;
+        for line in prologue {
+            writeln!(self.out, "{}{}", INDENT, line)?;
+        }
+        for (handle, local) in context.function.local_variables.iter() {
+            let local_name = self.names[&context.local_key(handle)].clone();
+            write!(self.out, "{}", INDENT)?;
+            self.write_typed_name(module, local.ty, &local_name)?;
+            if let Some(init) = local.init {
+                write!(self.out, " = ")?;
+                self.write_constant(module, init)?;
+            }
+            writeln!(self.out, ";")?;
+        }
+        if !prologue.is_empty() || !context.function.local_variables.is_empty() {
+            writeln!(self.out)?;
+        }
+
+        self.named_expressions.clear();
+        self.write_block(Level(1), &context.function.body, context)?;
+
+        // Entry points that fall off the end still need to return their outputs.
+        if let Some(ref output) = context.output {
+            match context.function.body.last() {
+                Some(&crate::Statement::Return { .. }) => {}
+                _ => self.write_output_return(&Level(1), output)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_entry_point(
+        &mut self,
+        module: &crate::Module,
+        analysis: &Analysis,
+        index: usize,
+        ep: &crate::EntryPoint,
+        name: &str,
+    ) -> Result<(), Error> {
+        let fun_info = analysis.get_entry_point(index);
+        let function = &ep.function;
+        let (input_target, output_target) = match ep.stage {
+            crate::ShaderStage::Vertex | crate::ShaderStage::Compute => (false, false),
+            crate::ShaderStage::Fragment => (false, true),
+            _ => {
+                return Err(Error::FeatureNotImplemented(format!(
+                    "{:?} shaders",
+                    ep.stage
+                )))
+            }
+        };
+
+        // Input globals are copied from the input struct, or from intrinsics,
+        // and output globals are collected into the output struct.
+        let mut prologue = Vec::new();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (handle, var) in module.global_variables.iter() {
+            let usage = fun_info[handle];
+            match var.class {
+                crate::StorageClass::Input if usage.contains(GlobalUse::READ) => {
+                    let var_name = &self.names[&NameKey::GlobalVariable(handle)];
+                    if let Some(crate::Binding::BuiltIn(built_in)) = var.binding {
+                        if let Some(intrinsic) = built_in_intrinsic(built_in) {
+                            if !self.shader_model.has_wave_ops() {
+                                return Err(Error::UnsupportedWaveOperation(format!(
+                                    "built-in {:?}",
+                                    built_in
+                                )));
+                            }
+                            prologue.push(format!("{} = {};", var_name, intrinsic));
+                            continue;
+                        }
+                    }
+                    inputs.push(handle);
+                }
+                crate::StorageClass::Output if usage.contains(GlobalUse::WRITE) => {
+                    outputs.push(handle);
+                }
+                _ => {}
+            }
+        }
+
+        let input_struct = if inputs.is_empty() {
+            None
+        } else {
+            let struct_name = self.namer.call(&format!("{}Input", name));
+            let arg_name = self.namer.call("input");
+            self.write_io_struct(module, &struct_name, &inputs, input_target)?;
+            for &handle in inputs.iter() {
+                let var_name = &self.names[&NameKey::GlobalVariable(handle)];
+                prologue.push(format!("{} = {}.{};", var_name, arg_name, var_name));
+            }
+            Some((struct_name, arg_name))
+        };
+        let output = if outputs.is_empty() {
+            None
+        } else {
+            if function.result.is_some() {
+                return Err(Error::FeatureNotImplemented(
+                    "entry points with both results and output globals".to_string(),
+                ));
+            }
+            let struct_name = self.namer.call(&format!("{}Output", name));
+            self.write_io_struct(module, &struct_name, &outputs, output_target)?;
+            Some(EntryPointOutput {
+                struct_name,
+                name: self.namer.call("output"),
+                members: outputs
+                    .iter()
+                    .map(|&handle| self.names[&NameKey::GlobalVariable(handle)].clone())
+                    .collect(),
+            })
+        };
+
+        match ep.stage {
+            crate::ShaderStage::Compute => {
+                let [x, y, z] = ep.workgroup_size;
+                writeln!(self.out, "[numthreads({}, {}, {})]", x, y, z)?;
+            }
+            crate::ShaderStage::Fragment if ep.early_depth_test.is_some() => {
+                writeln!(self.out, "[earlydepthstencil]")?;
+            }
+            _ => {}
+        }
+
+        match (output.as_ref(), function.result.as_ref()) {
+            (Some(output), _) => write!(self.out, "{}", output.struct_name)?,
+            (None, Some(result)) => self.write_type(module, result.ty)?,
+            (None, None) => write!(self.out, "void")?,
+        }
+        write!(self.out, " {}(", name)?;
+        let mut separator = "";
+        if let Some((ref struct_name, ref arg_name)) = input_struct {
+            write!(self.out, "{} {}", struct_name, arg_name)?;
+            separator = ", ";
+        }
+        for (arg_index, arg) in function.arguments.iter().enumerate() {
+            let arg_name =
+                self.names[&NameKey::EntryPointArgument(index as _, arg_index as u32)].clone();
+            if let Some(crate::Binding::BuiltIn(built_in)) = arg.binding {
+                if let Some(intrinsic) = built_in_intrinsic(built_in) {
+                    if !self.shader_model.has_wave_ops() {
+                        return Err(Error::UnsupportedWaveOperation(format!(
+                            "built-in {:?}",
+                            built_in
+                        )));
+                    }
+                    let ty_name = match module.types[arg.ty].inner {
+                        crate::TypeInner::Scalar { kind, width } => {
+                            scalar_string(kind, width, self.shader_model)?
+                        }
+                        _ => return Err(Error::Validation),
+                    };
+                    prologue.insert(0, format!("{} {} = {};", ty_name, arg_name, intrinsic));
+                    continue;
+                }
+            }
+            write!(self.out, "{}", separator)?;
+            separator = ", ";
+            self.write_interpolation(arg.interpolation)?;
+            self.write_typed_name(module, arg.ty, &arg_name)?;
+            self.write_semantic(arg.binding.as_ref(), input_target)?;
+        }
+        write!(self.out, ")")?;
+        if let Some(ref result) = function.result {
+            self.write_semantic(result.binding.as_ref(), output_target)?;
+        }
+        writeln!(self.out, " {{")?;
+
+        self.resolve_function_types(module, function)?;
+        let context = FunctionContext {
+            function,
+            origin: FunctionOrigin::EntryPoint(index as _),
+            info: fun_info,
+            module,
+            output,
+        };
+        self.write_function_body(&context, &prologue)?;
+        writeln!(self.out, "}}")?;
+        Ok(())
+    }
+
+    fn write_io_struct(
+        &mut self,
+        module: &crate::Module,
+        struct_name: &str,
+        globals: &[Handle<crate::GlobalVariable>],
+        render_target: bool,
+    ) -> Result<(), Error> {
+        writeln!(self.out, "struct {} {{", struct_name)?;
+        for &handle in globals {
+            let var = &module.global_variables[handle];
+            write!(self.out, "{}", INDENT)?;
+            self.write_interpolation(var.interpolation)?;
+            let name = self.names[&NameKey::GlobalVariable(handle)].clone();
+            self.write_typed_name(module, var.ty, &name)?;
+            self.write_semantic(var.binding.as_ref(), render_target)?;
+            writeln!(self.out, ";")?;
+        }
+        writeln!(self.out, "}};")?;
+        writeln!(self.out)?;
+        Ok(())
+    }
+
+    fn write_output_return(
+        &mut self,
+        level: &Level,
+        output: &EntryPointOutput,
+    ) -> Result<(), Error> {
+        let output_name = &output.name;
+        writeln!(self.out, "{}{{", level)?;
+        let next = level.next();
+        writeln!(self.out, "{}{} {};", next, output.struct_name, output_name)?;
+        for member in output.members.iter() {
+            writeln!(self.out, "{}{}.{} = {};", next, output_name, member, member)?;
+        }
+        writeln!(self.out, "{}return {};", next, output_name)?;
+        writeln!(self.out, "{}}}", level)?;
+        Ok(())
+    }
+
+    /// Starts a declaration for the result of the expression.
+    /// The caller is responsible for marking the expression as named.
+    fn start_baking_expression(
+        &mut self,
+        level: &Level,
+        handle: Handle<crate::Expression>,
+        context: &FunctionContext,
+        is_const: bool,
+    ) -> Result<(), Error> {
+        write!(self.out, "{}", level)?;
+        if is_const {
+            write!(self.out, "const ")?;
+        }
+        let name = format!("{}{}", BAKE_PREFIX, handle.index());
+        self.write_expression_type(context.module, handle, &name)
+    }
+
+    fn can_bake(&self, handle: Handle<crate::Expression>, context: &FunctionContext) -> bool {
+        if context.function.expressions[handle].needs_pre_emit() {
+            return false;
+        }
+        match *self.typifier.get(handle, &context.module.types) {
+            crate::TypeInner::Pointer { .. }
+            | crate::TypeInner::ValuePointer { .. }
+            | crate::TypeInner::Image { .. }
+            | crate::TypeInner::Sampler { .. } => false,
+            _ => true,
+        }
+    }
+
+    fn write_block(
+        &mut self,
+        level: Level,
+        block: &[crate::Statement],
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        for statement in block {
+            self.write_statement(&level, statement, context)?;
+        }
+        Ok(())
+    }
+
+    fn write_statement(
+        &mut self,
+        level: &Level,
+        statement: &crate::Statement,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        log::trace!("statement[{}] {:?}", level.0, statement);
+        match *statement {
+            crate::Statement::Emit(ref range) => {
+                for handle in range.clone() {
+                    let min_ref_count = context.function.expressions[handle].bake_ref_count();
+                    if min_ref_count <= context.info[handle].ref_count
+                        && self.can_bake(handle, context)
+                    {
+                        self.start_baking_expression(level, handle, context, true)?;
+                        write!(self.out, " = ")?;
+                        self.put_expression(handle, context)?;
+                        writeln!(self.out, ";")?;
+                        self.named_expressions.insert(handle.index());
+                    }
+                }
+            }
+            crate::Statement::Block(ref block) => {
+                writeln!(self.out, "{}{{", level)?;
+                self.write_block(level.next(), block, context)?;
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                write!(self.out, "{}if (", level)?;
+                self.put_expression(condition, context)?;
+                writeln!(self.out, ") {{")?;
+                self.write_block(level.next(), accept, context)?;
+                if !reject.is_empty() {
+                    writeln!(self.out, "{}}} else {{", level)?;
+                    self.write_block(level.next(), reject, context)?;
+                }
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                write!(self.out, "{}switch(", level)?;
+                self.put_expression(selector, context)?;
+                writeln!(self.out, ") {{")?;
+                let lcase = level.next();
+                for case in cases.iter() {
+                    writeln!(self.out, "{}case {}: {{", lcase, case.value)?;
+                    self.write_block(lcase.next(), &case.body, context)?;
+                    if !case.fall_through {
+                        writeln!(self.out, "{}break;", lcase.next())?;
+                    }
+                    writeln!(self.out, "{}}}", lcase)?;
+                }
+                writeln!(self.out, "{}default: {{", lcase)?;
+                self.write_block(lcase.next(), default, context)?;
+                writeln!(self.out, "{}break;", lcase.next())?;
+                writeln!(self.out, "{}}}", lcase)?;
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                if continuing.is_empty() {
+                    writeln!(self.out, "{}while(true) {{", level)?;
+                    self.write_block(level.next(), body, context)?;
+                } else {
+                    // The continuing block runs before every iteration but the first,
+                    // so that `continue` statements in the body go through it.
+                    let gate_name = self.namer.call("loop_init");
+                    writeln!(self.out, "{}bool {} = true;", level, gate_name)?;
+                    writeln!(self.out, "{}while(true) {{", level)?;
+                    let lif = level.next();
+                    writeln!(self.out, "{}if (!{}) {{", lif, gate_name)?;
+                    self.write_block(lif.next(), continuing, context)?;
+                    writeln!(self.out, "{}}}", lif)?;
+                    writeln!(self.out, "{}{} = false;", lif, gate_name)?;
+                    self.write_block(lif, body, context)?;
+                }
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::Break => {
+                writeln!(self.out, "{}break;", level)?;
+            }
+            crate::Statement::Continue => {
+                writeln!(self.out, "{}continue;", level)?;
+            }
+            crate::Statement::Return { value } => match context.output {
+                Some(ref output) => self.write_output_return(level, output)?,
+                None => match value {
+                    Some(value) => {
+                        write!(self.out, "{}return ", level)?;
+                        self.put_expression(value, context)?;
+                        writeln!(self.out, ";")?;
+                    }
+                    None => writeln!(self.out, "{}return;", level)?,
+                },
+            },
+            crate::Statement::Kill => {
+                writeln!(self.out, "{}discard;", level)?;
+            }
+            crate::Statement::Store { pointer, value } => {
+                write!(self.out, "{}", level)?;
+                self.put_expression(pointer, context)?;
+                write!(self.out, " = ")?;
+                self.put_expression(value, context)?;
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                write!(self.out, "{}", level)?;
+                self.put_expression(image, context)?;
+                write!(self.out, "[")?;
+                self.put_coordinate(coordinate, array_index, None, "int", context)?;
+                write!(self.out, "] = ")?;
+                self.put_expression(value, context)?;
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                match result {
+                    Some(result) => {
+                        self.start_baking_expression(level, result, context, true)?;
+                        write!(self.out, " = ")?;
+                        self.named_expressions.insert(result.index());
+                    }
+                    None => write!(self.out, "{}", level)?,
+                }
+                let name = &self.names[&NameKey::Function(function)];
+                write!(self.out, "{}", name)?;
+                self.put_call_parameters(arguments.iter().cloned(), context)?;
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                // HLSL atomics write the original value into an output argument.
+                self.start_baking_expression(level, result, context, false)?;
+                writeln!(self.out, ";")?;
+                let result_name = format!("{}{}", BAKE_PREFIX, result.index());
+                write!(self.out, "{}{}(", level, atomic_fun_name(fun))?;
+                self.put_expression(pointer, context)?;
+                write!(self.out, ", ")?;
+                match *fun {
+                    crate::AtomicFunction::Exchange {
+                        compare: Some(compare),
+                    } => {
+                        self.put_expression(compare, context)?;
+                        write!(self.out, ", ")?;
+                        self.put_expression(value, context)?;
+                        writeln!(self.out, ", {}.x);", result_name)?;
+                        // the second component tells if the exchange took place
+                        let (kind, width) = match context.function.expressions[result] {
+                            crate::Expression::AtomicResult { kind, width, .. } => (kind, width),
+                            _ => return Err(Error::Validation),
+                        };
+                        write!(
+                            self.out,
+                            "{}{}.y = {}({}.x == ",
+                            level,
+                            result_name,
+                            scalar_string(kind, width, self.shader_model)?,
+                            result_name
+                        )?;
+                        self.put_expression(compare, context)?;
+                        writeln!(self.out, ");")?;
+                    }
+                    crate::AtomicFunction::Subtract => {
+                        write!(self.out, "-")?;
+                        self.put_expression(value, context)?;
+                        writeln!(self.out, ", {});", result_name)?;
+                    }
+                    _ => {
+                        self.put_expression(value, context)?;
+                        writeln!(self.out, ", {});", result_name)?;
+                    }
+                }
+                self.named_expressions.insert(result.index());
+            }
+            crate::Statement::Barrier {
+                execution,
+                memory_scope: _,
+                memory,
+            } => {
+                // Subgroups execute in lockstep, so there is only
+                // a memory barrier to place for them.
+                let suffix = match execution {
+                    Some(crate::Scope::SubGroup) | None => "",
+                    Some(_) => "WithGroupSync",
+                };
+                let device = memory.intersects(crate::Barrier::STORAGE | crate::Barrier::IMAGE);
+                let group = memory.contains(crate::Barrier::WORK_GROUP);
+                let name = match (device, group) {
+                    (true, true) => Some("AllMemoryBarrier"),
+                    (true, false) => Some("DeviceMemoryBarrier"),
+                    (false, true) => Some("GroupMemoryBarrier"),
+                    (false, false) if !suffix.is_empty() => Some("GroupMemoryBarrier"),
+                    (false, false) => None,
+                };
+                if let Some(name) = name {
+                    writeln!(self.out, "{}{}{}();", level, name, suffix)?;
+                }
+            }
+            crate::Statement::Subgroup { ref fun, result } => {
+                if !self.shader_model.has_wave_ops() {
+                    return Err(Error::UnsupportedWaveOperation(format!(
+                        "subgroup function {:?}",
+                        fun
+                    )));
+                }
+                self.start_baking_expression(level, result, context, true)?;
+                write!(self.out, " = ")?;
+                self.put_subgroup_function(fun, context)?;
+                writeln!(self.out, ";")?;
+                self.named_expressions.insert(result.index());
+            }
+            crate::Statement::EmitVertex
+            | crate::Statement::EndPrimitive
+            | crate::Statement::TraceRay { .. }
+            | crate::Statement::ReportIntersection { .. }
+            | crate::Statement::IgnoreIntersection
+            | crate::Statement::TerminateRay => {
+                return Err(Error::FeatureNotImplemented(format!(
+                    "statement {:?}",
+                    statement
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn put_subgroup_function(
+        &mut self,
+        fun: &crate::SubgroupFunction,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        match *fun {
+            crate::SubgroupFunction::Elect => {
+                write!(self.out, "WaveIsFirstLane()")?;
+            }
+            crate::SubgroupFunction::Ballot { predicate } => {
+                write!(self.out, "WaveActiveBallot(")?;
+                match predicate {
+                    Some(predicate) => self.put_expression(predicate, context)?,
+                    None => write!(self.out, "true")?,
+                }
+                write!(self.out, ")")?;
+            }
+            crate::SubgroupFunction::Gather { mode, argument } => {
+                let (index, op) = match mode {
+                    crate::GatherMode::BroadcastFirst => {
+                        write!(self.out, "WaveReadLaneFirst(")?;
+                        self.put_expression(argument, context)?;
+                        write!(self.out, ")")?;
+                        return Ok(());
+                    }
+                    crate::GatherMode::Broadcast(index) | crate::GatherMode::Shuffle(index) => {
+                        (index, None)
+                    }
+                    crate::GatherMode::ShuffleDown(delta) => (delta, Some("+")),
+                    crate::GatherMode::ShuffleUp(delta) => (delta, Some("-")),
+                    crate::GatherMode::ShuffleXor(mask) => (mask, Some("^")),
+                };
+                write!(self.out, "WaveReadLaneAt(")?;
+                self.put_expression(argument, context)?;
+                write!(self.out, ", ")?;
+                if let Some(op) = op {
+                    write!(self.out, "(WaveGetLaneIndex() {} ", op)?;
+                    self.put_expression(index, context)?;
+                    write!(self.out, ")")?;
+                } else {
+                    self.put_expression(index, context)?;
+                }
+                write!(self.out, ")")?;
+            }
+            crate::SubgroupFunction::Collective {
+                op,
+                collective,
+                argument,
+            } => {
+                use crate::{CollectiveOperation as Co, SubgroupOperation as So};
+                let (name, inclusive_op) = match (collective, op) {
+                    (Co::Reduce, So::All) => ("WaveActiveAllTrue", None),
+                    (Co::Reduce, So::Any) => ("WaveActiveAnyTrue", None),
+                    (Co::Reduce, So::Add) => ("WaveActiveSum", None),
+                    (Co::Reduce, So::Multiply) => ("WaveActiveProduct", None),
+                    (Co::Reduce, So::Min) => ("WaveActiveMin", None),
+                    (Co::Reduce, So::Max) => ("WaveActiveMax", None),
+                    (Co::Reduce, So::And) => ("WaveActiveBitAnd", None),
+                    (Co::Reduce, So::InclusiveOr) => ("WaveActiveBitOr", None),
+                    (Co::Reduce, So::ExclusiveOr) => ("WaveActiveBitXor", None),
+                    (Co::ExclusiveScan, So::Add) => ("WavePrefixSum", None),
+                    (Co::ExclusiveScan, So::Multiply) => ("WavePrefixProduct", None),
+                    // inclusive scans are derived from the exclusive ones
+                    (Co::InclusiveScan, So::Add) => ("WavePrefixSum", Some("+")),
+                    (Co::InclusiveScan, So::Multiply) => ("WavePrefixProduct", Some("*")),
+                    _ => {
+                        return Err(Error::FeatureNotImplemented(format!(
+                            "subgroup {:?} of {:?}",
+                            collective, op
+                        )))
+                    }
+                };
+                if inclusive_op.is_some() {
+                    write!(self.out, "(")?;
+                }
+                write!(self.out, "{}(", name)?;
+                self.put_expression(argument, context)?;
+                write!(self.out, ")")?;
+                if let Some(op) = inclusive_op {
+                    write!(self.out, " {} ", op)?;
+                    self.put_expression(argument, context)?;
+                    write!(self.out, ")")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn put_call_parameters(
+        &mut self,
+        parameters: impl Iterator<Item = Handle<crate::Expression>>,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        write!(self.out, "(")?;
+        for (i, handle) in parameters.enumerate() {
+            if i != 0 {
+                write!(self.out, ", ")?;
+            }
+            self.put_expression(handle, context)?;
+        }
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
+    /// Writes the coordinate of an image access, with the array index
+    /// and the extra value (level or sample) appended to it.
+    fn put_coordinate(
+        &mut self,
+        coordinate: Handle<crate::Expression>,
+        array_index: Option<Handle<crate::Expression>>,
+        extra: Option<Option<Handle<crate::Expression>>>,
+        scalar: &str,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        let extra_count = array_index.is_some() as usize + extra.is_some() as usize;
+        if extra_count == 0 {
+            return self.put_expression(coordinate, context);
+        }
+        let size = match *self.typifier.get(coordinate, &context.module.types) {
+            crate::TypeInner::Scalar { .. } => 1,
+            crate::TypeInner::Vector { size, .. } => size as usize,
+            _ => return Err(Error::Validation),
+        };
+        write!(self.out, "{}{}(", scalar, size + extra_count)?;
+        self.put_expression(coordinate, context)?;
+        if let Some(array_index) = array_index {
+            write!(self.out, ", ")?;
+            self.put_expression(array_index, context)?;
+        }
+        match extra {
+            Some(Some(expr)) => {
+                write!(self.out, ", ")?;
+                self.put_expression(expr, context)?;
+            }
+            Some(None) => write!(self.out, ", 0")?,
+            None => {}
+        }
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
+    fn put_expression(
+        &mut self,
+        expr_handle: Handle<crate::Expression>,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        if self.named_expressions.contains(expr_handle.index()) {
+            write!(self.out, "{}{}", BAKE_PREFIX, expr_handle.index())?;
+            return Ok(());
+        }
+
+        let module = context.module;
+        let expression = &context.function.expressions[expr_handle];
+        log::trace!("expression {:?} = {:?}", expr_handle, expression);
+        match *expression {
+            crate::Expression::Access { base, index } => {
+                self.put_expression(base, context)?;
+                write!(self.out, "[")?;
+                self.put_expression(index, context)?;
+                write!(self.out, "]")?;
+            }
+            crate::Expression::AccessIndex { base, index } => {
+                // the only member of an element buffer is the buffer itself
+                if let crate::Expression::GlobalVariable(handle) =
+                    context.function.expressions[base]
+                {
+                    if self.element_buffers.contains(&handle) {
+                        let name = &self.names[&NameKey::GlobalVariable(handle)];
+                        write!(self.out, "{}", name)?;
+                        return Ok(());
+                    }
+                }
+                self.put_expression(base, context)?;
+                let mut resolved = self.typifier.get(base, &module.types);
+                let base_ty_handle = match *resolved {
+                    crate::TypeInner::Pointer { base, class: _ } => {
+                        resolved = &module.types[base].inner;
+                        Ok(base)
+                    }
+                    _ => self.typifier.get_handle(base),
+                };
+                match *resolved {
+                    crate::TypeInner::Struct { .. } => {
+                        let base_ty = base_ty_handle.map_err(|_| Error::Validation)?;
+                        let name = &self.names[&NameKey::StructMember(base_ty, index)];
+                        write!(self.out, ".{}", name)?;
+                    }
+                    crate::TypeInner::ValuePointer { .. } | crate::TypeInner::Vector { .. } => {
+                        write!(self.out, ".{}", COMPONENTS[index as usize])?;
+                    }
+                    crate::TypeInner::Matrix { .. } | crate::TypeInner::Array { .. } => {
+                        write!(self.out, "[{}]", index)?;
+                    }
+                    _ => return Err(Error::Validation),
+                }
+            }
+            crate::Expression::Constant(handle) => {
+                self.write_constant(module, handle)?;
+            }
+            crate::Expression::Compose { ty, ref components } => {
+                match module.types[ty].inner {
+                    crate::TypeInner::Struct { .. } => {
+                        let name = &self.constructors[&ty];
+                        write!(self.out, "{}", name)?;
+                    }
+                    crate::TypeInner::Array { .. } => {
+                        return Err(Error::FeatureNotImplemented(
+                            "composing of arrays".to_string(),
+                        ))
+                    }
+                    _ => self.write_type(module, ty)?,
+                }
+                self.put_call_parameters(components.iter().cloned(), context)?;
+            }
+            crate::Expression::FunctionArgument(index) => {
+                let name = &self.names[&context.argument_key(index)];
+                write!(self.out, "{}", name)?;
+            }
+            crate::Expression::GlobalVariable(handle) => {
+                let name = &self.names[&NameKey::GlobalVariable(handle)];
+                write!(self.out, "{}", name)?;
+                // other storage buffers hold a single element
+                if module.global_variables[handle].class == crate::StorageClass::Storage {
+                    write!(self.out, "[0]")?;
+                }
+            }
+            crate::Expression::LocalVariable(handle) => {
+                let name = &self.names[&context.local_key(handle)];
+                write!(self.out, "{}", name)?;
+            }
+            crate::Expression::Load { pointer } => {
+                // loads are implicit
+                self.put_expression(pointer, context)?;
+            }
+            crate::Expression::ImageSample {
+                image,
+                sampler,
+                coordinate,
+                array_index,
+                offset,
+                level,
+                depth_ref,
+            } => {
+                let fun_name = match (depth_ref, level) {
+                    (Some(_), crate::SampleLevel::Auto) => "SampleCmp",
+                    (Some(_), crate::SampleLevel::Zero) => "SampleCmpLevelZero",
+                    (Some(_), _) => {
+                        return Err(Error::FeatureNotImplemented(format!(
+                            "depth comparison with sample level {:?}",
+                            level
+                        )))
+                    }
+                    (None, crate::SampleLevel::Auto) => "Sample",
+                    (None, crate::SampleLevel::Zero) | (None, crate::SampleLevel::Exact(_)) => {
+                        "SampleLevel"
+                    }
+                    (None, crate::SampleLevel::Bias(_)) => "SampleBias",
+                    (None, crate::SampleLevel::Gradient { .. }) => "SampleGrad",
+                };
+                self.put_expression(image, context)?;
+                write!(self.out, ".{}(", fun_name)?;
+                self.put_expression(sampler, context)?;
+                write!(self.out, ", ")?;
+                self.put_coordinate(coordinate, array_index, None, "float", context)?;
+                if let Some(depth_ref) = depth_ref {
+                    write!(self.out, ", ")?;
+                    self.put_expression(depth_ref, context)?;
+                }
+                match level {
+                    crate::SampleLevel::Auto => {}
+                    crate::SampleLevel::Zero => {
+                        if depth_ref.is_none() {
+                            write!(self.out, ", 0.0")?;
+                        }
+                    }
+                    crate::SampleLevel::Exact(expr) | crate::SampleLevel::Bias(expr) => {
+                        write!(self.out, ", ")?;
+                        self.put_expression(expr, context)?;
+                    }
+                    crate::SampleLevel::Gradient { x, y } => {
+                        write!(self.out, ", ")?;
+                        self.put_expression(x, context)?;
+                        write!(self.out, ", ")?;
+                        self.put_expression(y, context)?;
+                    }
+                }
+                if let Some(offset) = offset {
+                    write!(self.out, ", ")?;
+                    self.write_constant(module, offset)?;
+                }
+                write!(self.out, ")")?;
+            }
+            crate::Expression::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index,
+            } => {
+                let image_type = self.image_type_of(module, context.function, image)?;
+                self.put_expression(image, context)?;
+                write!(self.out, ".Load(")?;
+                match image_type.class {
+                    crate::ImageClass::Sampled { multi: true, .. } => {
+                        self.put_coordinate(coordinate, array_index, None, "int", context)?;
+                        write!(self.out, ", ")?;
+                        self.put_expression(index.ok_or(Error::Validation)?, context)?;
+                    }
+                    _ if image_type.has_levels() => {
+                        self.put_coordinate(coordinate, array_index, Some(index), "int", context)?;
+                    }
+                    _ => {
+                        self.put_coordinate(coordinate, array_index, None, "int", context)?;
+                    }
+                }
+                write!(self.out, ")")?;
+            }
+            crate::Expression::ImageQuery { image, query } => {
+                let image_type = self.image_type_of(module, context.function, image)?;
+                let name = &self.image_queries[&(image_type, QueryKind::from(query))];
+                write!(self.out, "{}(", name)?;
+                self.put_expression(image, context)?;
+                if image_type.has_levels() {
+                    write!(self.out, ", ")?;
+                    match query {
+                        crate::ImageQuery::Size { level: Some(level) } => {
+                            self.put_expression(level, context)?
+                        }
+                        _ => write!(self.out, "0")?,
+                    }
+                }
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Unary { op, expr } => {
+                let op_str = match op {
+                    crate::UnaryOperator::Negate => "-",
+                    crate::UnaryOperator::Not => {
+                        match self.typifier.get(expr, &module.types).scalar_kind() {
+                            Some(crate::ScalarKind::Bool) => "!",
+                            _ => "~",
+                        }
+                    }
+                };
+                write!(self.out, "{}", op_str)?;
+                self.put_expression(expr, context)?;
+            }
+            crate::Expression::Binary { op, left, right } => {
+                let is_matrix = |handle| match *self.typifier.get(handle, &module.types) {
+                    crate::TypeInner::Matrix { .. } => true,
+                    _ => false,
+                };
+                if op == crate::BinaryOperator::Multiply && (is_matrix(left) || is_matrix(right)) {
+                    // HLSL rows are the IR columns, so the operands are swapped
+                    write!(self.out, "mul(")?;
+                    self.put_expression(right, context)?;
+                    write!(self.out, ", ")?;
+                    self.put_expression(left, context)?;
+                    write!(self.out, ")")?;
+                    return Ok(());
+                }
+                let op_str = match op {
+                    crate::BinaryOperator::Add => "+",
+                    crate::BinaryOperator::Subtract => "-",
+                    crate::BinaryOperator::Multiply => "*",
+                    crate::BinaryOperator::Divide => "/",
+                    crate::BinaryOperator::Modulo => "%",
+                    crate::BinaryOperator::Equal => "==",
+                    crate::BinaryOperator::NotEqual => "!=",
+                    crate::BinaryOperator::Less => "<",
+                    crate::BinaryOperator::LessEqual => "<=",
+                    crate::BinaryOperator::Greater => ">",
+                    crate::BinaryOperator::GreaterEqual => ">=",
+                    crate::BinaryOperator::And => "&",
+                    crate::BinaryOperator::ExclusiveOr => "^",
+                    crate::BinaryOperator::InclusiveOr => "|",
+                    crate::BinaryOperator::LogicalAnd => "&&",
+                    crate::BinaryOperator::LogicalOr => "||",
+                    crate::BinaryOperator::ShiftLeft => "<<",
+                    crate::BinaryOperator::ShiftRight => ">>",
+                };
+                write!(self.out, "(")?;
+                self.put_expression(left, context)?;
+                write!(self.out, " {} ", op_str)?;
+                self.put_expression(right, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                write!(self.out, "(")?;
+                self.put_expression(condition, context)?;
+                write!(self.out, " ? ")?;
+                self.put_expression(accept, context)?;
+                write!(self.out, " : ")?;
+                self.put_expression(reject, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Derivative { axis, expr } => {
+                let fun_name = match axis {
+                    crate::DerivativeAxis::X => "ddx",
+                    crate::DerivativeAxis::Y => "ddy",
+                    crate::DerivativeAxis::Width => "fwidth",
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.put_expression(expr, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Relational { fun, argument } => {
+                let fun_name = match fun {
+                    crate::RelationalFunction::All => "all",
+                    crate::RelationalFunction::Any => "any",
+                    crate::RelationalFunction::IsNan => "isnan",
+                    crate::RelationalFunction::IsInf => "isinf",
+                    crate::RelationalFunction::IsFinite => "isfinite",
+                    crate::RelationalFunction::IsNormal => {
+                        return Err(Error::FeatureNotImplemented(format!(
+                            "relational function {:?}",
+                            fun
+                        )))
+                    }
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.put_expression(argument, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+            } => {
+                write!(self.out, "{}", math_function_string(fun)?)?;
+                let arguments = std::iter::once(arg).chain(arg1).chain(arg2);
+                self.put_call_parameters(arguments, context)?;
+            }
+            crate::Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let (size, width) = match *self.typifier.get(expr, &module.types) {
+                    crate::TypeInner::Scalar { kind: _, width } => (None, width),
+                    crate::TypeInner::Vector {
+                        size,
+                        kind: _,
+                        width,
+                    } => (Some(size), width),
+                    _ => return Err(Error::Validation),
+                };
+                if convert {
+                    let target = match size {
+                        Some(size) => crate::TypeInner::Vector { size, kind, width },
+                        None => crate::TypeInner::Scalar { kind, width },
+                    };
+                    self.write_type_inner(&target)?;
+                } else {
+                    let fun_name = match (kind, width) {
+                        (crate::ScalarKind::Float, 4) => "asfloat",
+                        (crate::ScalarKind::Sint, 4) => "asint",
+                        (crate::ScalarKind::Uint, 4) => "asuint",
+                        _ => {
+                            return Err(Error::FeatureNotImplemented(format!(
+                                "bitcast to {:?} with width {}",
+                                kind, width
+                            )))
+                        }
+                    };
+                    write!(self.out, "{}", fun_name)?;
+                }
+                write!(self.out, "(")?;
+                self.put_expression(expr, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::ArrayLength(expr) => {
+                let handle = match context.function.expressions[expr] {
+                    crate::Expression::AccessIndex { base, .. } => {
+                        match context.function.expressions[base] {
+                            crate::Expression::GlobalVariable(handle) => handle,
+                            _ => return Err(Error::Validation),
+                        }
+                    }
+                    _ => return Err(Error::Validation),
+                };
+                write!(self.out, "{}()", self.array_lengths[&handle])?;
+            }
+            // results of statements are always baked
+            crate::Expression::Call(_)
+            | crate::Expression::AtomicResult { .. }
+            | crate::Expression::SubgroupResult { .. } => return Err(Error::Validation),
+            crate::Expression::ReportIntersectionResult => {
+                return Err(Error::FeatureNotImplemented("ray tracing".to_string()))
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,860 @@
+/*! IR text backend.
+
+Prints a module in the text format that `front::ir` parses back.
+The items of each arena are listed in order, and referenced by their
+zero-based index with a prefix: `T` for types, `C` for constants, `G` for
+global variables, `F` for functions, `L` for local variables and `%` for
+expressions. For example:
+
+```text
+type T0 = u32
+const C0 = u32 1
+global G0 "counter" private T0 = C0
+
+fn F0 "increment"() -> T0 {
+    %0 = global G0
+    %1 = load %0
+    %2 = constant C0
+    %3 = binary add %1, %2
+    emit %1..%2
+    emit %3..%4
+    store %0, %3
+    return %3
+}
+```
+
+Optional properties are written as attributes, such as `@location(0)`.
+!*/
+
+use crate::{
+    arena::Handle,
+    front::ir::grammar::{self, Keywords},
+    Expression, ScalarValue, Statement, TypeInner,
+};
+use std::fmt::{Debug, Error as FmtError, Write as _};
+
+/// Error of the IR text backend.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Format(#[from] FmtError),
+    #[error("{0} has no keyword")]
+    MissingKeyword(String),
+}
+
+/// Returns the keyword of a value, which every value of the tables has.
+fn keyword<T: Copy + PartialEq + Debug>(
+    table: Keywords<T>,
+    value: T,
+) -> Result<&'static str, Error> {
+    grammar::keyword(table, value).ok_or_else(|| Error::MissingKeyword(format!("{:?}", value)))
+}
+
+const INDENT: &str = "    ";
+
+struct Writer {
+    out: String,
+    indent: usize,
+}
+
+fn ty(handle: Handle<crate::Type>) -> String {
+    format!("{}{}", grammar::TYPE_PREFIX, handle.index())
+}
+
+fn constant(handle: Handle<crate::Constant>) -> String {
+    format!("{}{}", grammar::CONSTANT_PREFIX, handle.index())
+}
+
+fn expr(handle: Handle<Expression>) -> String {
+    format!("{}{}", grammar::EXPRESSION_PREFIX, handle.index())
+}
+
+fn name(name: &Option<String>) -> String {
+    match *name {
+        Some(ref name) => format!("{:?}", name),
+        None => "_".to_string(),
+    }
+}
+
+fn expr_list(handles: &[Handle<Expression>]) -> String {
+    handles
+        .iter()
+        .map(|&h| expr(h))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the name of a scalar or a vector type.
+fn value_type(size: Option<crate::VectorSize>, kind: crate::ScalarKind, width: u8) -> String {
+    let scalar = grammar::scalar_name(kind, width);
+    match size {
+        Some(size) => format!("vec{}<{}>", size as u8, scalar),
+        None => scalar,
+    }
+}
+
+impl Writer {
+    fn line(&mut self, text: &str) -> Result<(), Error> {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        writeln!(self.out, "{}", text)?;
+        Ok(())
+    }
+
+    fn binding(binding: &Option<crate::Binding>) -> Result<String, Error> {
+        Ok(match *binding {
+            Some(crate::Binding::BuiltIn(built_in)) => {
+                format!(" @builtin({})", keyword(grammar::BUILT_INS, built_in)?)
+            }
+            Some(crate::Binding::Location(location)) => format!(" @location({})", location),
+            Some(crate::Binding::Resource { group, binding }) => {
+                format!(" @resource({}, {})", group, binding)
+            }
+            None => String::new(),
+        })
+    }
+
+    fn interpolation(interpolation: Option<crate::Interpolation>) -> Result<String, Error> {
+        Ok(match interpolation {
+            Some(interpolation) => format!(
+                " @interpolation({})",
+                keyword(grammar::INTERPOLATIONS, interpolation)?
+            ),
+            None => String::new(),
+        })
+    }
+
+    fn type_inner(&self, inner: &TypeInner) -> Result<String, Error> {
+        Ok(match *inner {
+            TypeInner::Scalar { kind, width } => grammar::scalar_name(kind, width),
+            TypeInner::Vector { size, kind, width } => value_type(Some(size), kind, width),
+            TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => format!(
+                "mat{}x{}<{}>",
+                columns as u8,
+                rows as u8,
+                grammar::scalar_name(crate::ScalarKind::Float, width)
+            ),
+            TypeInner::Atomic { kind, width } => {
+                format!("atomic<{}>", grammar::scalar_name(kind, width))
+            }
+            TypeInner::Pointer { base, class } => format!(
+                "ptr<{}, {}>",
+                keyword(grammar::STORAGE_CLASSES, class)?,
+                ty(base)
+            ),
+            TypeInner::ValuePointer {
+                size,
+                kind,
+                width,
+                class,
+            } => format!(
+                "ptr<{}, {}>",
+                keyword(grammar::STORAGE_CLASSES, class)?,
+                value_type(size, kind, width)
+            ),
+            TypeInner::Array { base, size, stride } => {
+                let mut string = match size {
+                    crate::ArraySize::Constant(size) => {
+                        format!("array<{}, {}>", ty(base), constant(size))
+                    }
+                    crate::ArraySize::Dynamic => format!("array<{}>", ty(base)),
+                };
+                if let Some(stride) = stride {
+                    string += &format!(" @stride({})", stride);
+                }
+                string
+            }
+            TypeInner::Struct { block, .. } => {
+                if block {
+                    "struct @block {".to_string()
+                } else {
+                    "struct {".to_string()
+                }
+            }
+            TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                let class = match class {
+                    crate::ImageClass::Sampled { kind, multi } => format!(
+                        "sampled<{}{}>",
+                        keyword(grammar::SCALAR_KINDS, kind)?,
+                        if multi { ", multi" } else { "" }
+                    ),
+                    crate::ImageClass::Depth => "depth".to_string(),
+                    crate::ImageClass::Storage(format) => {
+                        format!("storage<{}>", keyword(grammar::STORAGE_FORMATS, format)?)
+                    }
+                };
+                format!(
+                    "image<{}{}, {}>",
+                    keyword(grammar::IMAGE_DIMENSIONS, dim)?,
+                    if arrayed { ", arrayed" } else { "" },
+                    class
+                )
+            }
+            TypeInner::Sampler { comparison } => {
+                if comparison {
+                    "sampler @comparison".to_string()
+                } else {
+                    "sampler".to_string()
+                }
+            }
+            TypeInner::AccelerationStructure => "acceleration_structure".to_string(),
+        })
+    }
+
+    fn write_types(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, ty_) in module.types.iter() {
+            let label = match ty_.name {
+                Some(ref name) => format!("type {} {:?} = ", ty(handle), name),
+                None => format!("type {} = ", ty(handle)),
+            };
+            let inner = self.type_inner(&ty_.inner)?;
+            self.line(&(label + &inner))?;
+            if let TypeInner::Struct { ref members, .. } = ty_.inner {
+                self.indent += 1;
+                for member in members {
+                    let span = match member.span {
+                        Some(span) => format!(" @span({})", span),
+                        None => String::new(),
+                    };
+                    self.line(&format!(
+                        "{}: {}{}{}{},",
+                        name(&member.name),
+                        ty(member.ty),
+                        span,
+                        Self::binding(&member.binding)?,
+                        Self::interpolation(member.interpolation)?,
+                    ))?;
+                }
+                self.indent -= 1;
+                self.line("}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_constants(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, constant_) in module.constants.iter() {
+            let mut text = format!("const {}", constant(handle));
+            if let Some(ref name) = constant_.name {
+                write!(text, " {:?}", name)?;
+            }
+            match constant_.inner {
+                crate::ConstantInner::Scalar { width, ref value } => {
+                    let kind = match *value {
+                        ScalarValue::Sint(_) => crate::ScalarKind::Sint,
+                        ScalarValue::Uint(_) => crate::ScalarKind::Uint,
+                        ScalarValue::Float(_) => crate::ScalarKind::Float,
+                        ScalarValue::Bool(_) => crate::ScalarKind::Bool,
+                    };
+                    write!(text, " = {} ", grammar::scalar_name(kind, width))?;
+                    match *value {
+                        ScalarValue::Sint(v) => write!(text, "{}", v)?,
+                        ScalarValue::Uint(v) => write!(text, "{}", v)?,
+                        ScalarValue::Float(v) => write!(text, "{:?}", v)?,
+                        ScalarValue::Bool(v) => write!(text, "{}", v)?,
+                    }
+                }
+                crate::ConstantInner::Composite {
+                    ty: ty_,
+                    ref components,
+                } => {
+                    let components = components
+                        .iter()
+                        .map(|&c| constant(c))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    write!(text, " = composite {} ({})", ty(ty_), components)?;
+                }
+            }
+            if let Some(id) = constant_.specialization {
+                write!(text, " @specialization({})", id)?;
+            }
+            self.line(&text)?;
+        }
+        Ok(())
+    }
+
+    fn write_globals(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, var) in module.global_variables.iter() {
+            let mut text = format!("global {}{}", grammar::GLOBAL_PREFIX, handle.index());
+            if let Some(ref name) = var.name {
+                write!(text, " {:?}", name)?;
+            }
+            write!(
+                text,
+                " {} {}",
+                keyword(grammar::STORAGE_CLASSES, var.class)?,
+                ty(var.ty)
+            )?;
+            if let Some(init) = var.init {
+                write!(text, " = {}", constant(init))?;
+            }
+            text += &Self::binding(&var.binding)?;
+            text += &Self::interpolation(var.interpolation)?;
+            if !var.storage_access.is_empty() {
+                let access = grammar::STORAGE_ACCESSES
+                    .iter()
+                    .filter(|&&(flag, _)| var.storage_access.contains(flag))
+                    .map(|&(_, name)| name)
+                    .collect::<Vec<_>>();
+                write!(text, " @access({})", access.join(", "))?;
+            }
+            self.line(&text)?;
+        }
+        Ok(())
+    }
+
+    fn expression(&self, expression: &Expression) -> Result<String, Error> {
+        let mut text = String::new();
+        match *expression {
+            Expression::Access { base, index } => {
+                write!(text, "access {}, {}", expr(base), expr(index))?
+            }
+            Expression::AccessIndex { base, index } => {
+                write!(text, "access_index {}, {}", expr(base), index)?
+            }
+            Expression::Constant(handle) => write!(text, "constant {}", constant(handle))?,
+            Expression::Compose {
+                ty: ty_,
+                ref components,
+            } => write!(text, "compose {} ({})", ty(ty_), expr_list(components))?,
+            Expression::FunctionArgument(index) => write!(text, "argument {}", index)?,
+            Expression::GlobalVariable(handle) => {
+                write!(text, "global {}{}", grammar::GLOBAL_PREFIX, handle.index())?
+            }
+            Expression::LocalVariable(handle) => {
+                write!(text, "local {}{}", grammar::LOCAL_PREFIX, handle.index())?
+            }
+            Expression::Load { pointer } => write!(text, "load {}", expr(pointer))?,
+            Expression::ImageSample {
+                image,
+                sampler,
+                coordinate,
+                array_index,
+                offset,
+                level,
+                depth_ref,
+            } => {
+                write!(
+                    text,
+                    "image_sample {}, {}, {}",
+                    expr(image),
+                    expr(sampler),
+                    expr(coordinate)
+                )?;
+                if let Some(array_index) = array_index {
+                    write!(text, " @array_index({})", expr(array_index))?;
+                }
+                if let Some(offset) = offset {
+                    write!(text, " @offset({})", constant(offset))?;
+                }
+                match level {
+                    crate::SampleLevel::Auto => {}
+                    crate::SampleLevel::Zero => write!(text, " @level(zero)")?,
+                    crate::SampleLevel::Exact(h) => write!(text, " @level(exact, {})", expr(h))?,
+                    crate::SampleLevel::Bias(h) => write!(text, " @level(bias, {})", expr(h))?,
+                    crate::SampleLevel::Gradient { x, y } => {
+                        write!(text, " @level(gradient, {}, {})", expr(x), expr(y))?
+                    }
+                }
+                if let Some(depth_ref) = depth_ref {
+                    write!(text, " @depth_ref({})", expr(depth_ref))?;
+                }
+            }
+            Expression::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index,
+            } => {
+                write!(text, "image_load {}, {}", expr(image), expr(coordinate))?;
+                if let Some(array_index) = array_index {
+                    write!(text, " @array_index({})", expr(array_index))?;
+                }
+                if let Some(index) = index {
+                    write!(text, " @index({})", expr(index))?;
+                }
+            }
+            Expression::ImageQuery { image, query } => {
+                let query = match query {
+                    crate::ImageQuery::Size { level: Some(level) } => {
+                        format!("size {} @level({})", expr(image), expr(level))
+                    }
+                    crate::ImageQuery::Size { level: None } => format!("size {}", expr(image)),
+                    crate::ImageQuery::NumLevels => format!("num_levels {}", expr(image)),
+                    crate::ImageQuery::NumLayers => format!("num_layers {}", expr(image)),
+                    crate::ImageQuery::NumSamples => format!("num_samples {}", expr(image)),
+                };
+                write!(text, "image_query {}", query)?
+            }
+            Expression::Unary { op, expr: operand } => write!(
+                text,
+                "unary {} {}",
+                keyword(grammar::UNARY_OPERATORS, op)?,
+                expr(operand)
+            )?,
+            Expression::Binary { op, left, right } => write!(
+                text,
+                "binary {} {}, {}",
+                keyword(grammar::BINARY_OPERATORS, op)?,
+                expr(left),
+                expr(right)
+            )?,
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => write!(
+                text,
+                "select {}, {}, {}",
+                expr(condition),
+                expr(accept),
+                expr(reject)
+            )?,
+            Expression::Derivative {
+                axis,
+                expr: operand,
+            } => write!(
+                text,
+                "derivative {} {}",
+                keyword(grammar::DERIVATIVE_AXES, axis)?,
+                expr(operand)
+            )?,
+            Expression::Relational { fun, argument } => write!(
+                text,
+                "relational {} {}",
+                keyword(grammar::RELATIONAL_FUNCTIONS, fun)?,
+                expr(argument)
+            )?,
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+            } => {
+                let args = [Some(arg), arg1, arg2]
+                    .iter()
+                    .filter_map(|&a| a)
+                    .collect::<Vec<_>>();
+                write!(
+                    text,
+                    "math {} {}",
+                    keyword(grammar::MATH_FUNCTIONS, fun)?,
+                    expr_list(&args)
+                )?
+            }
+            Expression::As {
+                expr: operand,
+                kind,
+                convert,
+            } => write!(
+                text,
+                "{} {} {}",
+                if convert { "convert" } else { "bitcast" },
+                keyword(grammar::SCALAR_KINDS, kind)?,
+                expr(operand)
+            )?,
+            Expression::Call(function) => write!(
+                text,
+                "call {}{}",
+                grammar::FUNCTION_PREFIX,
+                function.index()
+            )?,
+            Expression::ArrayLength(array) => write!(text, "array_length {}", expr(array))?,
+            Expression::AtomicResult {
+                kind,
+                width,
+                comparison,
+            } => {
+                write!(text, "atomic_result {}", grammar::scalar_name(kind, width))?;
+                if comparison {
+                    text += " @comparison";
+                }
+            }
+            Expression::SubgroupResult { kind, width, size } => {
+                write!(text, "subgroup_result {}", value_type(size, kind, width))?
+            }
+            Expression::ReportIntersectionResult => text += "report_intersection_result",
+        }
+        Ok(text)
+    }
+
+    fn write_block(&mut self, block: &[Statement]) -> Result<(), Error> {
+        self.indent += 1;
+        for statement in block {
+            self.write_statement(statement)?;
+        }
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn write_statement(&mut self, statement: &Statement) -> Result<(), Error> {
+        match *statement {
+            Statement::Emit(ref range) => {
+                let range = range.index_range();
+                self.line(&format!(
+                    "emit {p}{}..{p}{}",
+                    range.start,
+                    range.end,
+                    p = grammar::EXPRESSION_PREFIX
+                ))?;
+            }
+            Statement::Block(ref block) => {
+                self.line("block {")?;
+                self.write_block(block)?;
+                self.line("}")?;
+            }
+            Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                self.line(&format!("if {} {{", expr(condition)))?;
+                self.write_block(accept)?;
+                if !reject.is_empty() {
+                    self.line("} else {")?;
+                    self.write_block(reject)?;
+                }
+                self.line("}")?;
+            }
+            Statement::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                self.line(&format!("switch {} {{", expr(selector)))?;
+                self.indent += 1;
+                for case in cases {
+                    let fall_through = if case.fall_through {
+                        " @fallthrough"
+                    } else {
+                        ""
+                    };
+                    self.line(&format!("case {}{} {{", case.value, fall_through))?;
+                    self.write_block(&case.body)?;
+                    self.line("}")?;
+                }
+                self.line("default {")?;
+                self.write_block(default)?;
+                self.line("}")?;
+                self.indent -= 1;
+                self.line("}")?;
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                self.line("loop {")?;
+                self.write_block(body)?;
+                if !continuing.is_empty() {
+                    self.line("} continuing {")?;
+                    self.write_block(continuing)?;
+                }
+                self.line("}")?;
+            }
+            Statement::Break => self.line("break")?,
+            Statement::Continue => self.line("continue")?,
+            Statement::Return { value: Some(value) } => {
+                self.line(&format!("return {}", expr(value)))?
+            }
+            Statement::Return { value: None } => self.line("return")?,
+            Statement::Kill => self.line("kill")?,
+            Statement::Store { pointer, value } => {
+                self.line(&format!("store {}, {}", expr(pointer), expr(value)))?
+            }
+            Statement::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                let mut text = format!(
+                    "image_store {}, {}, {}",
+                    expr(image),
+                    expr(coordinate),
+                    expr(value)
+                );
+                if let Some(array_index) = array_index {
+                    write!(text, " @array_index({})", expr(array_index))?;
+                }
+                self.line(&text)?;
+            }
+            Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                let mut text = format!(
+                    "call {}{} ({})",
+                    grammar::FUNCTION_PREFIX,
+                    function.index(),
+                    expr_list(arguments)
+                );
+                if let Some(result) = result {
+                    write!(text, " -> {}", expr(result))?;
+                }
+                self.line(&text)?;
+            }
+            Statement::Atomic {
+                pointer,
+                fun,
+                value,
+                result,
+            } => {
+                let text = match fun {
+                    crate::AtomicFunction::Exchange { compare } => {
+                        let mut text =
+                            format!("atomic exchange {}, {}", expr(pointer), expr(value));
+                        if let Some(compare) = compare {
+                            write!(text, " @compare({})", expr(compare))?;
+                        }
+                        text
+                    }
+                    _ => format!(
+                        "atomic {} {}, {}",
+                        keyword(grammar::ATOMIC_FUNCTIONS, fun)?,
+                        expr(pointer),
+                        expr(value)
+                    ),
+                };
+                self.line(&format!("{} -> {}", text, expr(result)))?;
+            }
+            Statement::Barrier {
+                execution,
+                memory_scope,
+                memory,
+            } => {
+                let flags = grammar::BARRIERS
+                    .iter()
+                    .filter(|&&(flag, _)| memory.contains(flag))
+                    .map(|&(_, name)| name)
+                    .collect::<Vec<_>>();
+                let mut text = format!(
+                    "barrier {} ({})",
+                    keyword(grammar::SCOPES, memory_scope)?,
+                    flags.join(", ")
+                );
+                if let Some(execution) = execution {
+                    write!(
+                        text,
+                        " @execution({})",
+                        keyword(grammar::SCOPES, execution)?
+                    )?;
+                }
+                self.line(&text)?;
+            }
+            Statement::Subgroup { fun, result } => {
+                let text = match fun {
+                    crate::SubgroupFunction::Elect => "elect".to_string(),
+                    crate::SubgroupFunction::Ballot { predicate: None } => "ballot".to_string(),
+                    crate::SubgroupFunction::Ballot {
+                        predicate: Some(predicate),
+                    } => format!("ballot @predicate({})", expr(predicate)),
+                    crate::SubgroupFunction::Gather { mode, argument } => {
+                        use crate::GatherMode as Gm;
+                        let (mode, index) = match mode {
+                            Gm::BroadcastFirst => ("broadcast_first", None),
+                            Gm::Broadcast(index) => ("broadcast", Some(index)),
+                            Gm::Shuffle(index) => ("shuffle", Some(index)),
+                            Gm::ShuffleDown(index) => ("shuffle_down", Some(index)),
+                            Gm::ShuffleUp(index) => ("shuffle_up", Some(index)),
+                            Gm::ShuffleXor(index) => ("shuffle_xor", Some(index)),
+                        };
+                        match index {
+                            Some(index) => format!("{} {}, {}", mode, expr(argument), expr(index)),
+                            None => format!("{} {}", mode, expr(argument)),
+                        }
+                    }
+                    crate::SubgroupFunction::Collective {
+                        op,
+                        collective,
+                        argument,
+                    } => format!(
+                        "{} {} {}",
+                        keyword(grammar::COLLECTIVE_OPERATIONS, collective)?,
+                        keyword(grammar::SUBGROUP_OPERATIONS, op)?,
+                        expr(argument)
+                    ),
+                };
+                self.line(&format!("subgroup {} -> {}", text, expr(result)))?;
+            }
+            Statement::EmitVertex => self.line("emit_vertex")?,
+            Statement::EndPrimitive => self.line("end_primitive")?,
+            Statement::TraceRay {
+                acceleration_structure,
+                flags,
+                cull_mask,
+                sbt_offset,
+                sbt_stride,
+                miss_index,
+                origin,
+                t_min,
+                direction,
+                t_max,
+                payload,
+            } => self.line(&format!(
+                "trace_ray {}",
+                expr_list(&[
+                    acceleration_structure,
+                    flags,
+                    cull_mask,
+                    sbt_offset,
+                    sbt_stride,
+                    miss_index,
+                    origin,
+                    t_min,
+                    direction,
+                    t_max,
+                    payload,
+                ])
+            ))?,
+            Statement::ReportIntersection {
+                hit_t,
+                hit_kind,
+                result,
+            } => self.line(&format!(
+                "report_intersection {}, {} -> {}",
+                expr(hit_t),
+                expr(hit_kind),
+                expr(result)
+            ))?,
+            Statement::IgnoreIntersection => self.line("ignore_intersection")?,
+            Statement::TerminateRay => self.line("terminate_ray")?,
+        }
+        Ok(())
+    }
+
+    /// Writes the signature and the body of a function, after the `header`.
+    fn write_function(&mut self, header: &str, function: &crate::Function) -> Result<(), Error> {
+        let mut text = header.to_string();
+        if let Some(ref name) = function.name {
+            write!(text, " {:?}", name)?;
+        }
+        let arguments = function
+            .arguments
+            .iter()
+            .map(|argument| {
+                Ok(format!(
+                    "{}: {}{}{}",
+                    name(&argument.name),
+                    ty(argument.ty),
+                    Self::binding(&argument.binding)?,
+                    Self::interpolation(argument.interpolation)?
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        write!(text, "({})", arguments.join(", "))?;
+        if let Some(ref result) = function.result {
+            write!(
+                text,
+                " -> {}{}{}",
+                ty(result.ty),
+                Self::binding(&result.binding)?,
+                Self::interpolation(result.interpolation)?
+            )?;
+        }
+        self.line(&(text + " {"))?;
+        self.indent += 1;
+        for (handle, local) in function.local_variables.iter() {
+            let mut text = format!("local {}{}", grammar::LOCAL_PREFIX, handle.index());
+            if let Some(ref name) = local.name {
+                write!(text, " {:?}", name)?;
+            }
+            write!(text, " {}", ty(local.ty))?;
+            if let Some(init) = local.init {
+                write!(text, " = {}", constant(init))?;
+            }
+            self.line(&text)?;
+        }
+        for (handle, expression) in function.expressions.iter() {
+            let text = self.expression(expression)?;
+            self.line(&format!("{} = {}", expr(handle), text))?;
+        }
+        self.indent -= 1;
+        self.write_block(&function.body)?;
+        self.line("}")
+    }
+
+    fn write_entry_point(&mut self, entry_point: &crate::EntryPoint) -> Result<(), Error> {
+        let mut text = format!(
+            "entry {:?} {}",
+            entry_point.name,
+            keyword(grammar::SHADER_STAGES, entry_point.stage)?
+        );
+        let [x, y, z] = entry_point.workgroup_size;
+        write!(text, " @workgroup_size({}, {}, {})", x, y, z)?;
+        if let Some(early_depth_test) = entry_point.early_depth_test {
+            match early_depth_test.conservative {
+                Some(depth) => write!(
+                    text,
+                    " @early_depth_test({})",
+                    keyword(grammar::CONSERVATIVE_DEPTHS, depth)?
+                )?,
+                None => text += " @early_depth_test",
+            }
+        }
+        if let Some(geometry) = entry_point.geometry {
+            write!(
+                text,
+                " @geometry({}, {}, {})",
+                keyword(grammar::GEOMETRY_INPUTS, geometry.input)?,
+                keyword(grammar::GEOMETRY_OUTPUTS, geometry.output)?,
+                geometry.max_vertices
+            )?;
+        }
+        match entry_point.tessellation {
+            Some(crate::TessellationInfo::Control { output_vertices }) => {
+                write!(text, " @tessellation(control, {})", output_vertices)?
+            }
+            Some(crate::TessellationInfo::Evaluation {
+                primitive,
+                spacing,
+                vertex_order,
+            }) => write!(
+                text,
+                " @tessellation(evaluation, {}, {}, {})",
+                keyword(grammar::TESSELLATION_PRIMITIVES, primitive)?,
+                keyword(grammar::TESSELLATION_SPACINGS, spacing)?,
+                keyword(grammar::VERTEX_ORDERS, vertex_order)?
+            )?,
+            None => {}
+        }
+        self.line(&text)?;
+        self.write_function("fn", &entry_point.function)
+    }
+
+    fn write(&mut self, module: &crate::Module) -> Result<(), Error> {
+        self.write_types(module)?;
+        self.write_constants(module)?;
+        self.write_globals(module)?;
+        for (handle, function) in module.functions.iter() {
+            self.line("")?;
+            let header = format!("fn {}{}", grammar::FUNCTION_PREFIX, handle.index());
+            self.write_function(&header, function)?;
+        }
+        for entry_point in module.entry_points.iter() {
+            self.line("")?;
+            self.write_entry_point(entry_point)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints a module in the IR text format.
+pub fn write_string(module: &crate::Module) -> Result<String, Error> {
+    let mut writer = Writer {
+        out: String::new(),
+        indent: 0,
+    };
+    writer.write(module)?;
+    Ok(writer.out)
+}
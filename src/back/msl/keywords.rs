@@ -19,7 +19,9 @@ pub const RESERVED: &[&str] = &[
     "char",
     "int",
     "long",
+    "ulong",
     "float",
+    "half",
     "double",
     "char8_t",
     "wchar_t",
@@ -76,6 +76,10 @@ pub enum Error {
     UnsupportedCall(String),
     #[error("feature '{0}' is not implemented yet")]
     FeatureNotImplemented(String),
+    #[error("scalar {0:?} with width {1} is not supported by Metal")]
+    UnsupportedScalar(crate::ScalarKind, crate::Bytes),
+    #[error("array size {0:?} can't be a function constant")]
+    SpecializedArraySize(Handle<crate::Constant>),
     #[error("module is not valid")]
     Validation,
 }
@@ -112,15 +116,21 @@ impl Default for Options {
 }
 
 impl Options {
+    /// Function constants are supported starting with MSL 1.2.
+    /// Older versions get the default values of specializable constants.
+    fn has_function_constants(&self) -> bool {
+        self.lang_version >= (1, 2)
+    }
+
     fn resolve_binding(
         &self,
         stage: crate::ShaderStage,
-        var: &crate::GlobalVariable,
+        binding: Option<&crate::Binding>,
         mode: LocationMode,
     ) -> Result<ResolvedBinding, Error> {
-        match var.binding {
-            Some(crate::Binding::BuiltIn(built_in)) => Ok(ResolvedBinding::BuiltIn(built_in)),
-            Some(crate::Binding::Location(index)) => match mode {
+        match binding {
+            Some(&crate::Binding::BuiltIn(built_in)) => Ok(ResolvedBinding::BuiltIn(built_in)),
+            Some(&crate::Binding::Location(index)) => match mode {
                 LocationMode::VertexInput => Ok(ResolvedBinding::Attribute(index)),
                 LocationMode::FragmentOutput => Ok(ResolvedBinding::Color(index)),
                 LocationMode::Intermediate => Ok(ResolvedBinding::User {
@@ -139,7 +149,7 @@ impl Options {
                     Err(Error::Validation)
                 }
             },
-            Some(crate::Binding::Resource { group, binding }) => {
+            Some(&crate::Binding::Resource { group, binding }) => {
                 let source = BindSource {
                     stage,
                     group,
@@ -155,7 +165,7 @@ impl Options {
                 }
             }
             None => {
-                log::error!("Missing binding for {:?}", var.name);
+                log::error!("Missing binding");
                 Err(Error::Validation)
             }
         }
@@ -189,6 +199,34 @@ impl ResolvedBinding {
                     Bi::LocalInvocationIndex => "thread_index_in_threadgroup",
                     Bi::WorkGroupId => "threadgroup_position_in_grid",
                     Bi::WorkGroupSize => "dispatch_threads_per_threadgroup",
+                    // subgroup
+                    Bi::SubgroupSize => "threads_per_simdgroup",
+                    Bi::SubgroupInvocationId => "thread_index_in_simdgroup",
+                    // tessellation
+                    Bi::TessCoord => "position_in_patch",
+                    Bi::InvocationId
+                    | Bi::PatchVertices
+                    | Bi::TessLevelInner
+                    | Bi::TessLevelOuter
+                    // ray tracing
+                    | Bi::LaunchId
+                    | Bi::LaunchSize
+                    | Bi::WorldRayOrigin
+                    | Bi::WorldRayDirection
+                    | Bi::ObjectRayOrigin
+                    | Bi::ObjectRayDirection
+                    | Bi::RayTmin
+                    | Bi::RayTmax
+                    | Bi::InstanceCustomIndex
+                    | Bi::ObjectToWorld
+                    | Bi::WorldToObject
+                    | Bi::HitKind
+                    | Bi::IncomingRayFlags => {
+                        return Err(Error::FeatureNotImplemented(format!(
+                            "built-in {:?}",
+                            built_in
+                        )))
+                    }
                 };
                 Ok(write!(out, "{}", name)?)
             }
@@ -84,6 +84,16 @@ fn scalar_kind_string(kind: crate::ScalarKind) -> &'static str {
     }
 }
 
+fn scalar_string(kind: crate::ScalarKind, width: crate::Bytes) -> Result<&'static str, Error> {
+    Ok(match (kind, width) {
+        (crate::ScalarKind::Float, 2) => "half",
+        (crate::ScalarKind::Sint, 8) => "long",
+        (crate::ScalarKind::Uint, 8) => "ulong",
+        (_, 8) => return Err(Error::UnsupportedScalar(kind, width)),
+        _ => scalar_kind_string(kind),
+    })
+}
+
 fn vector_size_string(size: crate::VectorSize) -> &'static str {
     match size {
         crate::VectorSize::Bi => "2",
@@ -92,6 +102,24 @@ fn vector_size_string(size: crate::VectorSize) -> &'static str {
     }
 }
 
+const ATOMIC_ORDER: &str = "metal::memory_order_relaxed";
+
+fn atomic_fun_name(fun: &crate::AtomicFunction) -> &'static str {
+    match *fun {
+        crate::AtomicFunction::Add => "atomic_fetch_add_explicit",
+        crate::AtomicFunction::Subtract => "atomic_fetch_sub_explicit",
+        crate::AtomicFunction::And => "atomic_fetch_and_explicit",
+        crate::AtomicFunction::ExclusiveOr => "atomic_fetch_xor_explicit",
+        crate::AtomicFunction::InclusiveOr => "atomic_fetch_or_explicit",
+        crate::AtomicFunction::Min => "atomic_fetch_min_explicit",
+        crate::AtomicFunction::Max => "atomic_fetch_max_explicit",
+        crate::AtomicFunction::Exchange { compare: None } => "atomic_exchange_explicit",
+        crate::AtomicFunction::Exchange { compare: Some(_) } => {
+            "atomic_compare_exchange_weak_explicit"
+        }
+    }
+}
+
 const OUTPUT_STRUCT_NAME: &str = "output";
 const LOCATION_INPUT_STRUCT_NAME: &str = "input";
 const COMPONENTS: &[char] = &['x', 'y', 'z', 'w'];
@@ -130,6 +158,7 @@ impl crate::StorageClass {
                 "storage "
             }),
             Self::Private | Self::Function | Self::WorkGroup | Self::PushConstant => Some(""),
+            Self::RayPayload | Self::IncomingRayPayload | Self::HitAttribute => Some("ray_data"),
         }
     }
 }
@@ -146,10 +175,19 @@ struct ExpressionContext<'a> {
     analysis: &'a Analysis,
 }
 
+/// Field of the output structure that receives an entry point result.
+struct ResultField {
+    /// Name of the field in the output structure.
+    name: String,
+    /// Name of the result member it's taken from, if the result is a structure.
+    member: Option<String>,
+}
+
 struct StatementContext<'a> {
     expression: ExpressionContext<'a>,
     fun_info: &'a FunctionInfo,
     return_value: Option<&'a str>,
+    result_fields: &'a [ResultField],
 }
 
 impl<W: Write> Writer<W> {
@@ -201,6 +239,20 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    fn is_atomic_pointer(
+        &self,
+        pointer: Handle<crate::Expression>,
+        context: &ExpressionContext,
+    ) -> bool {
+        match *self.typifier.get(pointer, &context.module.types) {
+            crate::TypeInner::Pointer { base, .. } => match context.module.types[base].inner {
+                crate::TypeInner::Atomic { .. } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     fn put_expression(
         &mut self,
         expr_handle: Handle<crate::Expression>,
@@ -261,23 +313,27 @@ impl<W: Write> Writer<W> {
                         write!(self.out, "{}", scalar_kind_string(kind))?;
                         self.put_call_parameters(components.iter().cloned(), context)?;
                     }
-                    crate::TypeInner::Vector { size, kind, .. } => {
+                    crate::TypeInner::Vector { size, kind, width } => {
                         write!(
                             self.out,
                             "{}::{}{}",
                             NAMESPACE,
-                            scalar_kind_string(kind),
+                            scalar_string(kind, width)?,
                             vector_size_string(size)
                         )?;
                         self.put_call_parameters(components.iter().cloned(), context)?;
                     }
-                    crate::TypeInner::Matrix { columns, rows, .. } => {
+                    crate::TypeInner::Matrix {
+                        columns,
+                        rows,
+                        width,
+                    } => {
                         let kind = crate::ScalarKind::Float;
                         write!(
                             self.out,
                             "{}::{}{}x{}",
                             NAMESPACE,
-                            scalar_kind_string(kind),
+                            scalar_string(kind, width)?,
                             vector_size_string(columns),
                             vector_size_string(rows)
                         )?;
@@ -287,11 +343,15 @@ impl<W: Write> Writer<W> {
                 }
             }
             crate::Expression::FunctionArgument(index) => {
-                let fun_handle = match context.origin {
-                    FunctionOrigin::Handle(handle) => handle,
-                    FunctionOrigin::EntryPoint(_) => unreachable!(),
+                let name_key = match context.origin {
+                    FunctionOrigin::Handle(fun_handle) => {
+                        NameKey::FunctionArgument(fun_handle, index)
+                    }
+                    FunctionOrigin::EntryPoint(ep_index) => {
+                        NameKey::EntryPointArgument(ep_index, index)
+                    }
                 };
-                let name = &self.names[&NameKey::FunctionArgument(fun_handle, index)];
+                let name = &self.names[&name_key];
                 write!(self.out, "{}", name)?;
             }
             crate::Expression::GlobalVariable(handle) => {
@@ -327,8 +387,14 @@ impl<W: Write> Writer<W> {
                 write!(self.out, "{}", name)?;
             }
             crate::Expression::Load { pointer } => {
-                //write!(self.out, "*")?;
-                self.put_expression(pointer, context)?;
+                if self.is_atomic_pointer(pointer, context) {
+                    write!(self.out, "{}::atomic_load_explicit(&", NAMESPACE)?;
+                    self.put_expression(pointer, context)?;
+                    write!(self.out, ", {})", ATOMIC_ORDER)?;
+                } else {
+                    //write!(self.out, "*")?;
+                    self.put_expression(pointer, context)?;
+                }
             }
             crate::Expression::ImageSample {
                 image,
@@ -614,19 +680,24 @@ impl<W: Write> Writer<W> {
                 kind,
                 convert,
             } => {
-                let scalar = scalar_kind_string(kind);
-                let size = match *self.typifier.get(expr, &context.module.types) {
-                    crate::TypeInner::Scalar { .. } => "",
-                    crate::TypeInner::Vector { size, .. } => vector_size_string(size),
+                let (size, width) = match *self.typifier.get(expr, &context.module.types) {
+                    crate::TypeInner::Scalar { width, .. } => ("", width),
+                    crate::TypeInner::Vector { size, width, .. } => {
+                        (vector_size_string(size), width)
+                    }
                     _ => return Err(Error::Validation),
                 };
+                let scalar = scalar_string(kind, width)?;
                 let op = if convert { "static_cast" } else { "as_type" };
                 write!(self.out, "{}<{}{}>(", op, scalar, size)?;
                 self.put_expression(expr, context)?;
                 write!(self.out, ")")?;
             }
             // has to be a named expression
-            crate::Expression::Call(_) => unreachable!(),
+            crate::Expression::Call(_)
+            | crate::Expression::AtomicResult { .. }
+            | crate::Expression::SubgroupResult { .. }
+            | crate::Expression::ReportIntersectionResult => unreachable!(),
             crate::Expression::ArrayLength(expr) => {
                 match *self.typifier.get(expr, &context.module.types) {
                     crate::TypeInner::Array {
@@ -654,15 +725,15 @@ impl<W: Write> Writer<W> {
                 let ty_name = &self.names[&NameKey::Type(ty_handle)];
                 write!(self.out, "{}", ty_name)?;
             }
-            Err(&crate::TypeInner::Scalar { kind, .. }) => {
-                write!(self.out, "{}", scalar_kind_string(kind))?;
+            Err(&crate::TypeInner::Scalar { kind, width }) => {
+                write!(self.out, "{}", scalar_string(kind, width)?)?;
             }
-            Err(&crate::TypeInner::Vector { size, kind, .. }) => {
+            Err(&crate::TypeInner::Vector { size, kind, width }) => {
                 write!(
                     self.out,
                     "{}::{}{}",
                     NAMESPACE,
-                    scalar_kind_string(kind),
+                    scalar_string(kind, width)?,
                     vector_size_string(size)
                 )?;
             }
@@ -770,11 +841,38 @@ impl<W: Write> Writer<W> {
                 }
                 crate::Statement::Return {
                     value: Some(expr_handle),
-                } => {
+                } if context.result_fields.is_empty() => {
                     write!(self.out, "{}return ", level)?;
                     self.put_expression(expr_handle, &context.expression)?;
                     writeln!(self.out, ";")?;
                 }
+                crate::Statement::Return {
+                    value: Some(expr_handle),
+                } => {
+                    // entry point results are written into the output struct
+                    let temp_name = if context.result_fields[0].member.is_some() {
+                        let name = self.namer.call("_result");
+                        write!(self.out, "{}const auto {} = ", level, name)?;
+                        self.put_expression(expr_handle, &context.expression)?;
+                        writeln!(self.out, ";")?;
+                        Some(name)
+                    } else {
+                        None
+                    };
+                    for field in context.result_fields.iter() {
+                        write!(
+                            self.out,
+                            "{}{}.{} = ",
+                            level, OUTPUT_STRUCT_NAME, field.name
+                        )?;
+                        match (temp_name.as_ref(), field.member.as_ref()) {
+                            (Some(temp), Some(member)) => write!(self.out, "{}.{}", temp, member)?,
+                            _ => self.put_expression(expr_handle, &context.expression)?,
+                        }
+                        writeln!(self.out, ";")?;
+                    }
+                    writeln!(self.out, "{}return {};", level, OUTPUT_STRUCT_NAME)?;
+                }
                 crate::Statement::Return { value: None } => {
                     writeln!(
                         self.out,
@@ -786,12 +884,29 @@ impl<W: Write> Writer<W> {
                 crate::Statement::Kill => {
                     writeln!(self.out, "{}discard_fragment();", level)?;
                 }
+                crate::Statement::EmitVertex | crate::Statement::EndPrimitive => {
+                    return Err(Error::FeatureNotImplemented("geometry shaders".to_string()));
+                }
+                crate::Statement::TraceRay { .. }
+                | crate::Statement::ReportIntersection { .. }
+                | crate::Statement::IgnoreIntersection
+                | crate::Statement::TerminateRay => {
+                    return Err(Error::FeatureNotImplemented("ray tracing".to_string()));
+                }
                 crate::Statement::Store { pointer, value } => {
                     write!(self.out, "{}", level)?;
-                    self.put_expression(pointer, &context.expression)?;
-                    write!(self.out, " = ")?;
-                    self.put_expression(value, &context.expression)?;
-                    writeln!(self.out, ";")?;
+                    if self.is_atomic_pointer(pointer, &context.expression) {
+                        write!(self.out, "{}::atomic_store_explicit(&", NAMESPACE)?;
+                        self.put_expression(pointer, &context.expression)?;
+                        write!(self.out, ", ")?;
+                        self.put_expression(value, &context.expression)?;
+                        writeln!(self.out, ", {});", ATOMIC_ORDER)?;
+                    } else {
+                        self.put_expression(pointer, &context.expression)?;
+                        write!(self.out, " = ")?;
+                        self.put_expression(value, &context.expression)?;
+                        writeln!(self.out, ";")?;
+                    }
                 }
                 crate::Statement::ImageStore {
                     image,
@@ -847,6 +962,179 @@ impl<W: Write> Writer<W> {
                     // done
                     writeln!(self.out, ");")?;
                 }
+                crate::Statement::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    let fun_name = atomic_fun_name(fun);
+                    match *fun {
+                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                            // Metal returns the original value through the comparand pointer
+                            let kind = match context.expression.function.expressions[result] {
+                                crate::Expression::AtomicResult { kind, .. } => kind,
+                                _ => return Err(Error::Validation),
+                            };
+                            let comparand = self.namer.call("comparand");
+                            let exchanged = self.namer.call("exchanged");
+                            write!(
+                                self.out,
+                                "{}{} {} = ",
+                                level,
+                                scalar_kind_string(kind),
+                                comparand
+                            )?;
+                            self.put_expression(cmp, &context.expression)?;
+                            writeln!(self.out, ";")?;
+                            write!(
+                                self.out,
+                                "{}bool {} = {}::{}(&",
+                                level, exchanged, NAMESPACE, fun_name
+                            )?;
+                            self.put_expression(pointer, &context.expression)?;
+                            write!(self.out, ", &{}, ", comparand)?;
+                            self.put_expression(value, &context.expression)?;
+                            writeln!(self.out, ", {}, {});", ATOMIC_ORDER, ATOMIC_ORDER)?;
+                            write!(self.out, "{}", level)?;
+                            self.start_baking_expression(result)?;
+                            writeln!(
+                                self.out,
+                                "{}::{}2({}, {}({}));",
+                                NAMESPACE,
+                                scalar_kind_string(kind),
+                                comparand,
+                                scalar_kind_string(kind),
+                                exchanged
+                            )?;
+                        }
+                        _ => {
+                            write!(self.out, "{}", level)?;
+                            self.start_baking_expression(result)?;
+                            write!(self.out, "{}::{}(&", NAMESPACE, fun_name)?;
+                            self.put_expression(pointer, &context.expression)?;
+                            write!(self.out, ", ")?;
+                            self.put_expression(value, &context.expression)?;
+                            writeln!(self.out, ", {});", ATOMIC_ORDER)?;
+                        }
+                    }
+                    self.named_expressions.insert(result.index());
+                }
+                crate::Statement::Barrier {
+                    execution,
+                    memory_scope,
+                    memory,
+                } => {
+                    // Metal doesn't have standalone memory barriers,
+                    // so we emit the execution barrier of the matching scope.
+                    let fun_name = match execution.unwrap_or(memory_scope) {
+                        crate::Scope::SubGroup => "simdgroup_barrier",
+                        crate::Scope::WorkGroup | crate::Scope::Device => "threadgroup_barrier",
+                    };
+                    write!(self.out, "{}{}::{}(", level, NAMESPACE, fun_name)?;
+                    let flags = [
+                        (crate::Barrier::STORAGE, "mem_device"),
+                        (crate::Barrier::WORK_GROUP, "mem_threadgroup"),
+                        (crate::Barrier::IMAGE, "mem_texture"),
+                    ];
+                    let mut separator = "";
+                    for &(flag, name) in flags.iter() {
+                        if memory.contains(flag) {
+                            write!(self.out, "{}{}::mem_flags::{}", separator, NAMESPACE, name)?;
+                            separator = " | ";
+                        }
+                    }
+                    if memory.is_empty() {
+                        write!(self.out, "{}::mem_flags::mem_none", NAMESPACE)?;
+                    }
+                    writeln!(self.out, ");")?;
+                }
+                crate::Statement::Subgroup { ref fun, result } => {
+                    write!(self.out, "{}", level)?;
+                    self.start_baking_expression(result)?;
+                    match *fun {
+                        crate::SubgroupFunction::Elect => {
+                            write!(self.out, "{}::simd_is_first()", NAMESPACE)?;
+                        }
+                        crate::SubgroupFunction::Ballot { predicate } => {
+                            // the vote mask is 64 bits wide at most
+                            write!(
+                                self.out,
+                                "{}::uint4(as_type<{}::uint2>(({}::simd_vote::vote_t){}::simd_ballot(",
+                                NAMESPACE, NAMESPACE, NAMESPACE, NAMESPACE
+                            )?;
+                            match predicate {
+                                Some(predicate) => {
+                                    self.put_expression(predicate, &context.expression)?
+                                }
+                                None => write!(self.out, "true")?,
+                            }
+                            write!(self.out, ")), 0, 0)")?;
+                        }
+                        crate::SubgroupFunction::Gather { mode, argument } => {
+                            let (fun_name, index) = match mode {
+                                crate::GatherMode::BroadcastFirst => ("simd_broadcast_first", None),
+                                crate::GatherMode::Broadcast(index) => {
+                                    ("simd_broadcast", Some(index))
+                                }
+                                crate::GatherMode::Shuffle(index) => ("simd_shuffle", Some(index)),
+                                crate::GatherMode::ShuffleDown(delta) => {
+                                    ("simd_shuffle_down", Some(delta))
+                                }
+                                crate::GatherMode::ShuffleUp(delta) => {
+                                    ("simd_shuffle_up", Some(delta))
+                                }
+                                crate::GatherMode::ShuffleXor(mask) => {
+                                    ("simd_shuffle_xor", Some(mask))
+                                }
+                            };
+                            write!(self.out, "{}::{}(", NAMESPACE, fun_name)?;
+                            self.put_expression(argument, &context.expression)?;
+                            if let Some(index) = index {
+                                write!(self.out, ", ")?;
+                                self.put_expression(index, &context.expression)?;
+                            }
+                            write!(self.out, ")")?;
+                        }
+                        crate::SubgroupFunction::Collective {
+                            op,
+                            collective,
+                            argument,
+                        } => {
+                            use crate::{CollectiveOperation as Co, SubgroupOperation as So};
+                            let fun_name = match (op, collective) {
+                                (So::All, Co::Reduce) => "simd_all",
+                                (So::Any, Co::Reduce) => "simd_any",
+                                (So::Add, Co::Reduce) => "simd_sum",
+                                (So::Add, Co::InclusiveScan) => "simd_prefix_inclusive_sum",
+                                (So::Add, Co::ExclusiveScan) => "simd_prefix_exclusive_sum",
+                                (So::Multiply, Co::Reduce) => "simd_product",
+                                (So::Multiply, Co::InclusiveScan) => {
+                                    "simd_prefix_inclusive_product"
+                                }
+                                (So::Multiply, Co::ExclusiveScan) => {
+                                    "simd_prefix_exclusive_product"
+                                }
+                                (So::Min, Co::Reduce) => "simd_min",
+                                (So::Max, Co::Reduce) => "simd_max",
+                                (So::And, Co::Reduce) => "simd_and",
+                                (So::InclusiveOr, Co::Reduce) => "simd_or",
+                                (So::ExclusiveOr, Co::Reduce) => "simd_xor",
+                                _ => {
+                                    return Err(Error::FeatureNotImplemented(format!(
+                                        "subgroup {:?} of {:?}",
+                                        collective, op
+                                    )))
+                                }
+                            };
+                            write!(self.out, "{}::{}(", NAMESPACE, fun_name)?;
+                            self.put_expression(argument, &context.expression)?;
+                            write!(self.out, ")")?;
+                        }
+                    }
+                    writeln!(self.out, ";")?;
+                    self.named_expressions.insert(result.index());
+                }
             }
         }
         Ok(())
@@ -865,35 +1153,48 @@ impl<W: Write> Writer<W> {
         writeln!(self.out, "#include <simd/simd.h>")?;
         writeln!(self.out)?;
 
-        self.write_type_defs(module)?;
-        self.write_constants(module)?;
+        self.write_type_defs(module, options)?;
+        self.write_constants(module, options)?;
         self.write_functions(module, analysis, options)
     }
 
-    fn write_type_defs(&mut self, module: &crate::Module) -> Result<(), Error> {
+    fn write_type_defs(&mut self, module: &crate::Module, options: &Options) -> Result<(), Error> {
         for (handle, ty) in module.types.iter() {
             let name = &self.names[&NameKey::Type(handle)];
             let global_use = GlobalUse::all(); //TODO
             match ty.inner {
-                crate::TypeInner::Scalar { kind, .. } => {
-                    write!(self.out, "typedef {} {}", scalar_kind_string(kind), name)?;
+                crate::TypeInner::Scalar { kind, width } => {
+                    write!(self.out, "typedef {} {}", scalar_string(kind, width)?, name)?;
                 }
-                crate::TypeInner::Vector { size, kind, .. } => {
+                crate::TypeInner::Atomic { kind, .. } => {
                     write!(
                         self.out,
-                        "typedef {}::{}{} {}",
+                        "typedef {}::atomic_{} {}",
                         NAMESPACE,
                         scalar_kind_string(kind),
+                        name
+                    )?;
+                }
+                crate::TypeInner::Vector { size, kind, width } => {
+                    write!(
+                        self.out,
+                        "typedef {}::{}{} {}",
+                        NAMESPACE,
+                        scalar_string(kind, width)?,
                         vector_size_string(size),
                         name
                     )?;
                 }
-                crate::TypeInner::Matrix { columns, rows, .. } => {
+                crate::TypeInner::Matrix {
+                    columns,
+                    rows,
+                    width,
+                } => {
                     write!(
                         self.out,
                         "typedef {}::{}{}x{} {}",
                         NAMESPACE,
-                        scalar_kind_string(crate::ScalarKind::Float),
+                        scalar_string(crate::ScalarKind::Float, width)?,
                         vector_size_string(columns),
                         vector_size_string(rows),
                         name
@@ -910,7 +1211,7 @@ impl<W: Write> Writer<W> {
                 crate::TypeInner::ValuePointer {
                     size: None,
                     kind,
-                    width: _,
+                    width,
                     class,
                 } => {
                     let class_name = match class.get_name(global_use) {
@@ -921,14 +1222,14 @@ impl<W: Write> Writer<W> {
                         self.out,
                         "typedef {} {} *{}",
                         class_name,
-                        scalar_kind_string(kind),
+                        scalar_string(kind, width)?,
                         name
                     )?;
                 }
                 crate::TypeInner::ValuePointer {
                     size: Some(size),
                     kind,
-                    width: _,
+                    width,
                     class,
                 } => {
                     let class_name = match class.get_name(global_use) {
@@ -940,7 +1241,7 @@ impl<W: Write> Writer<W> {
                         "typedef {} {}::{}{} {}",
                         class_name,
                         NAMESPACE,
-                        scalar_kind_string(kind),
+                        scalar_string(kind, width)?,
                         vector_size_string(size),
                         name
                     )?;
@@ -953,6 +1254,12 @@ impl<W: Write> Writer<W> {
                     let base_name = &self.names[&NameKey::Type(base)];
                     let size_str = match size {
                         crate::ArraySize::Constant(const_handle) => {
+                            if options.has_function_constants()
+                                && module.constants[const_handle]
+                                    .is_specializable(&module.constants)
+                            {
+                                return Err(Error::SpecializedArraySize(const_handle));
+                            }
                             &self.names[&NameKey::Constant(const_handle)]
                         }
                         crate::ArraySize::Dynamic => "1",
@@ -1029,6 +1336,11 @@ impl<W: Write> Writer<W> {
                 crate::TypeInner::Sampler { comparison: _ } => {
                     write!(self.out, "typedef {}::sampler {}", NAMESPACE, name)?;
                 }
+                crate::TypeInner::AccelerationStructure => {
+                    return Err(Error::FeatureNotImplemented(
+                        "acceleration structures".to_string(),
+                    ));
+                }
             }
             writeln!(self.out, ";")?;
             writeln!(self.out)?;
@@ -1036,34 +1348,79 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
-    fn write_constants(&mut self, module: &crate::Module) -> Result<(), Error> {
+    fn write_constants(&mut self, module: &crate::Module, options: &Options) -> Result<(), Error> {
         for (handle, constant) in module.constants.iter() {
-            write!(self.out, "constexpr constant ")?;
             let name = &self.names[&NameKey::Constant(handle)];
             match constant.inner {
-                crate::ConstantInner::Scalar {
-                    width: _,
-                    ref value,
-                } => match *value {
-                    crate::ScalarValue::Sint(value) => {
-                        write!(self.out, "int {} = {}", name, value)?;
-                    }
-                    crate::ScalarValue::Uint(value) => {
-                        write!(self.out, "unsigned {} = {}u", name, value)?;
-                    }
-                    crate::ScalarValue::Float(value) => {
-                        write!(self.out, "float {} = {}", name, value)?;
-                        if value.fract() == 0.0 {
-                            write!(self.out, ".0")?;
+                crate::ConstantInner::Scalar { width, ref value } => {
+                    let ty = match *value {
+                        crate::ScalarValue::Sint(_) if width == 8 => "long",
+                        crate::ScalarValue::Sint(_) => "int",
+                        crate::ScalarValue::Uint(_) if width == 8 => "ulong",
+                        crate::ScalarValue::Uint(_) => "unsigned",
+                        crate::ScalarValue::Float(_) => {
+                            scalar_string(crate::ScalarKind::Float, width)?
+                        }
+                        crate::ScalarValue::Bool(_) => "bool",
+                    };
+                    match constant.specialization {
+                        // the default value is used unless the pipeline provides one
+                        Some(id) if options.has_function_constants() => {
+                            writeln!(
+                                self.out,
+                                "constant {} {}_tmp [[function_constant({})]];",
+                                ty, name, id
+                            )?;
+                            write!(
+                                self.out,
+                                "constant {} {} = is_function_constant_defined({}_tmp) ? {}_tmp : ",
+                                ty, name, name, name
+                            )?;
                         }
+                        _ => write!(self.out, "constexpr constant {} {} = ", ty, name)?,
                     }
-                    crate::ScalarValue::Bool(value) => {
-                        write!(self.out, "bool {} = {}", name, value)?;
+                    match *value {
+                        crate::ScalarValue::Sint(value) if width == 8 => {
+                            write!(self.out, "{}L", value)?;
+                        }
+                        crate::ScalarValue::Sint(value) => {
+                            write!(self.out, "{}", value)?;
+                        }
+                        crate::ScalarValue::Uint(value) if width == 8 => {
+                            write!(self.out, "{}uL", value)?;
+                        }
+                        crate::ScalarValue::Uint(value) => {
+                            write!(self.out, "{}u", value)?;
+                        }
+                        crate::ScalarValue::Float(value) => {
+                            write!(self.out, "{}", value)?;
+                            if value.fract() == 0.0 {
+                                write!(self.out, ".0")?;
+                            }
+                            if width == 2 {
+                                write!(self.out, "h")?;
+                            }
+                        }
+                        crate::ScalarValue::Bool(value) => {
+                            write!(self.out, "{}", value)?;
+                        }
                     }
-                },
+                }
                 crate::ConstantInner::Composite { ty, ref components } => {
+                    // function constants aren't known at compile time
+                    let qualifier = if options.has_function_constants()
+                        && constant.is_specializable(&module.constants)
+                    {
+                        "constant"
+                    } else {
+                        "constexpr constant"
+                    };
                     let ty_name = &self.names[&NameKey::Type(ty)];
-                    write!(self.out, "{} {} = {}(", ty_name, name, ty_name)?;
+                    write!(
+                        self.out,
+                        "{} {} {} = {}(",
+                        qualifier, ty_name, name, ty_name
+                    )?;
                     for (i, &sub_handle) in components.iter().enumerate() {
                         let separator = if i != 0 { ", " } else { "" };
                         let sub_name = &self.names[&NameKey::Constant(sub_handle)];
@@ -1107,8 +1464,8 @@ impl<W: Write> Writer<W> {
             }
 
             let fun_name = &self.names[&NameKey::Function(fun_handle)];
-            let result_type_name = match fun.return_type {
-                Some(ret_ty) => &self.names[&NameKey::Type(ret_ty)],
+            let result_type_name = match fun.result {
+                Some(ref result) => &self.names[&NameKey::Type(result.ty)],
                 None => "void",
             };
             writeln!(self.out, "{} {}(", result_type_name, fun_name)?;
@@ -1158,6 +1515,7 @@ impl<W: Write> Writer<W> {
                 },
                 fun_info,
                 return_value: None,
+                result_fields: &[],
             };
             self.named_expressions.clear();
             self.put_block(Level(1), &fun.body, &context)?;
@@ -1200,6 +1558,72 @@ impl<W: Write> Writer<W> {
                 }
             }
 
+            // Entry point arguments are split into the fields of the location input
+            // struct and built-in parameters, and then assembled back in the body.
+            let mut varying_fields = Vec::new();
+            let mut built_in_params = Vec::new();
+            let mut argument_sources = Vec::with_capacity(fun.arguments.len());
+            for (index, arg) in fun.arguments.iter().enumerate() {
+                let arg_name =
+                    &self.names[&NameKey::EntryPointArgument(ep_index as _, index as u32)];
+                let mut flattened = Vec::new();
+                match arg.binding {
+                    Some(ref binding) => flattened.push((arg_name.clone(), arg.ty, binding)),
+                    None => {
+                        if let crate::TypeInner::Struct { ref members, .. } =
+                            module.types[arg.ty].inner
+                        {
+                            for member in members.iter() {
+                                let name = self.namer.call_or(&member.name, "member");
+                                let binding = member.binding.as_ref().ok_or(Error::Validation)?;
+                                flattened.push((name, member.ty, binding));
+                            }
+                        }
+                    }
+                }
+                let mut sources = Vec::with_capacity(flattened.len());
+                for (name, ty, binding) in flattened {
+                    if let crate::Binding::BuiltIn(_) = *binding {
+                        sources.push(name.clone());
+                        built_in_params.push((name, ty, binding));
+                    } else {
+                        sources.push(format!("{}.{}", LOCATION_INPUT_STRUCT_NAME, name));
+                        varying_fields.push((name, ty, binding));
+                    }
+                }
+                argument_sources.push(sources);
+            }
+
+            // The result is written into the fields of the output struct.
+            let mut result_fields = Vec::new();
+            let mut result_bindings = Vec::new();
+            if let Some(ref result) = fun.result {
+                match result.binding {
+                    Some(ref binding) => {
+                        let name = self.namer.call("member");
+                        result_bindings.push((name.clone(), result.ty, binding));
+                        result_fields.push(ResultField { name, member: None });
+                    }
+                    None => {
+                        if let crate::TypeInner::Struct { ref members, .. } =
+                            module.types[result.ty].inner
+                        {
+                            for (index, member) in members.iter().enumerate() {
+                                let name = self.namer.call_or(&member.name, "member");
+                                let binding = member.binding.as_ref().ok_or(Error::Validation)?;
+                                let member_name =
+                                    &self.names[&NameKey::StructMember(result.ty, index as u32)];
+                                result_bindings.push((name.clone(), member.ty, binding));
+                                result_fields.push(ResultField {
+                                    name,
+                                    member: Some(member_name.clone()),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+
             let fun_name = &self.names[&NameKey::EntryPoint(ep_index as _)];
             info.entry_point_names.push(fun_name.clone());
             let output_name = format!("{}Output", fun_name);
@@ -1219,6 +1643,20 @@ impl<W: Write> Writer<W> {
                 crate::ShaderStage::Compute { .. } => {
                     ("kernel", LocationMode::Uniform, LocationMode::Uniform)
                 }
+                crate::ShaderStage::Geometry
+                | crate::ShaderStage::TessellationControl
+                | crate::ShaderStage::TessellationEvaluation
+                | crate::ShaderStage::RayGeneration
+                | crate::ShaderStage::Intersection
+                | crate::ShaderStage::AnyHit
+                | crate::ShaderStage::ClosestHit
+                | crate::ShaderStage::Miss
+                | crate::ShaderStage::Callable => {
+                    return Err(Error::FeatureNotImplemented(format!(
+                        "{:?} shaders",
+                        ep.stage
+                    )));
+                }
             };
 
             let return_value = match ep.stage {
@@ -1243,7 +1681,15 @@ impl<W: Write> Writer<W> {
                         };
                         write!(self.out, "{}", INDENT)?;
                         tyvar.try_fmt(&mut self.out)?;
-                        let resolved = options.resolve_binding(ep.stage, var, in_mode)?;
+                        let resolved =
+                            options.resolve_binding(ep.stage, var.binding.as_ref(), in_mode)?;
+                        resolved.try_fmt_decorated(&mut self.out, ";")?;
+                        writeln!(self.out)?;
+                    }
+                    for &(ref name, ty, binding) in varying_fields.iter() {
+                        let ty_name = &self.names[&NameKey::Type(ty)];
+                        write!(self.out, "{}{} {}", INDENT, ty_name, name)?;
+                        let resolved = options.resolve_binding(ep.stage, Some(binding), in_mode)?;
                         resolved.try_fmt_decorated(&mut self.out, ";")?;
                         writeln!(self.out)?;
                     }
@@ -1266,7 +1712,16 @@ impl<W: Write> Writer<W> {
                         };
                         write!(self.out, "{}", INDENT)?;
                         tyvar.try_fmt(&mut self.out)?;
-                        let resolved = options.resolve_binding(ep.stage, var, out_mode)?;
+                        let resolved =
+                            options.resolve_binding(ep.stage, var.binding.as_ref(), out_mode)?;
+                        resolved.try_fmt_decorated(&mut self.out, ";")?;
+                        writeln!(self.out)?;
+                    }
+                    for &(ref name, ty, binding) in result_bindings.iter() {
+                        let ty_name = &self.names[&NameKey::Type(ty)];
+                        write!(self.out, "{}{} {}", INDENT, ty_name, name)?;
+                        let resolved =
+                            options.resolve_binding(ep.stage, Some(binding), out_mode)?;
                         resolved.try_fmt_decorated(&mut self.out, ";")?;
                         writeln!(self.out)?;
                     }
@@ -1274,7 +1729,8 @@ impl<W: Write> Writer<W> {
                     writeln!(self.out)?;
 
                     writeln!(self.out, "{} {} {}(", em_str, output_name, fun_name)?;
-                    let separator = separate(last_used_global.is_none());
+                    let separator =
+                        separate(last_used_global.is_none() && built_in_params.is_empty());
                     writeln!(
                         self.out,
                         "{}{} {} [[stage_in]]{}",
@@ -1283,12 +1739,31 @@ impl<W: Write> Writer<W> {
 
                     Some(OUTPUT_STRUCT_NAME)
                 }
-                crate::ShaderStage::Compute => {
+                crate::ShaderStage::Compute
+                | crate::ShaderStage::Geometry
+                | crate::ShaderStage::TessellationControl
+                | crate::ShaderStage::TessellationEvaluation
+                | crate::ShaderStage::RayGeneration
+                | crate::ShaderStage::Intersection
+                | crate::ShaderStage::AnyHit
+                | crate::ShaderStage::ClosestHit
+                | crate::ShaderStage::Miss
+                | crate::ShaderStage::Callable => {
                     writeln!(self.out, "{} void {}(", em_str, fun_name)?;
                     None
                 }
             };
 
+            for (index, &(ref name, ty, binding)) in built_in_params.iter().enumerate() {
+                let ty_name = &self.names[&NameKey::Type(ty)];
+                let separator =
+                    separate(last_used_global.is_none() && index + 1 == built_in_params.len());
+                write!(self.out, "{}{} {}", INDENT, ty_name, name)?;
+                let resolved = options.resolve_binding(ep.stage, Some(binding), in_mode)?;
+                resolved.try_fmt_decorated(&mut self.out, separator)?;
+                writeln!(self.out)?;
+            }
+
             for (handle, var) in module.global_variables.iter() {
                 let usage = fun_info[handle];
                 if usage.is_empty() || var.class == crate::StorageClass::Output {
@@ -1323,7 +1798,8 @@ impl<W: Write> Writer<W> {
                 write!(self.out, "{}", INDENT)?;
                 tyvar.try_fmt(&mut self.out)?;
                 if var.binding.is_some() {
-                    let resolved = options.resolve_binding(ep.stage, var, loc_mode)?;
+                    let resolved =
+                        options.resolve_binding(ep.stage, var.binding.as_ref(), loc_mode)?;
                     resolved.try_fmt_decorated(&mut self.out, separator)?;
                 }
                 if let Some(value) = var.init {
@@ -1342,7 +1818,16 @@ impl<W: Write> Writer<W> {
                         INDENT, output_name, OUTPUT_STRUCT_NAME
                     )?;
                 }
-                crate::ShaderStage::Compute => {}
+                crate::ShaderStage::Compute
+                | crate::ShaderStage::Geometry
+                | crate::ShaderStage::TessellationControl
+                | crate::ShaderStage::TessellationEvaluation
+                | crate::ShaderStage::RayGeneration
+                | crate::ShaderStage::Intersection
+                | crate::ShaderStage::AnyHit
+                | crate::ShaderStage::ClosestHit
+                | crate::ShaderStage::Miss
+                | crate::ShaderStage::Callable => {}
             }
             for (local_handle, local) in fun.local_variables.iter() {
                 let name = &self.names[&NameKey::EntryPointLocal(ep_index as _, local_handle)];
@@ -1354,6 +1839,29 @@ impl<W: Write> Writer<W> {
                 }
                 writeln!(self.out, ";")?;
             }
+            for (index, (arg, sources)) in fun.arguments.iter().zip(argument_sources).enumerate() {
+                let name = &self.names[&NameKey::EntryPointArgument(ep_index as _, index as u32)];
+                let ty_name = &self.names[&NameKey::Type(arg.ty)];
+                if arg.binding.is_some() {
+                    // built-in parameters are already named after the argument
+                    if sources[0] != *name {
+                        writeln!(
+                            self.out,
+                            "{}const {} {} = {};",
+                            INDENT, ty_name, name, sources[0]
+                        )?;
+                    }
+                } else {
+                    writeln!(
+                        self.out,
+                        "{}const {} {} = {{ {} }};",
+                        INDENT,
+                        ty_name,
+                        name,
+                        sources.join(", ")
+                    )?;
+                }
+            }
 
             let context = StatementContext {
                 expression: ExpressionContext {
@@ -1364,6 +1872,7 @@ impl<W: Write> Writer<W> {
                 },
                 fun_info,
                 return_value,
+                result_fields: &result_fields,
             };
             self.named_expressions.clear();
             self.put_block(Level(1), &fun.body, &context)?;
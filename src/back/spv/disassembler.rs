@@ -0,0 +1,281 @@
+/*! Disassembler of SPIR-V binaries into the assembly text.
+
+The output follows the syntax of the SPIRV-Tools disassembler, with the result ids
+aligned, and it can be assembled back with `front::spv::assemble`.
+!*/
+
+use crate::spv_grammar::{self, Context, NumericType, OperandKind, Quantifier};
+use num_traits::FromPrimitive;
+use spirv::{Op, Word};
+use std::fmt::Write;
+use thiserror::Error;
+
+/// Column at which the opcodes start.
+const OPCODE_COLUMN: usize = 15;
+
+#[derive(Clone, Debug, Error)]
+pub enum DisassemblyError {
+    #[error("the header is missing or invalid")]
+    InvalidHeader,
+    #[error("instruction at word {0} has an invalid word count")]
+    InvalidWordCount(usize),
+    #[error("instruction at word {0} has an unknown opcode {1}")]
+    UnknownInstruction(usize, u16),
+    #[error("instruction {1:?} at word {0} has invalid operands")]
+    InvalidOperands(usize, Op),
+}
+
+struct Disassembler {
+    out: String,
+    context: Context,
+}
+
+/// Cursor over the operand words of an instruction.
+struct Operands<'a> {
+    words: &'a [Word],
+    position: usize,
+}
+
+impl Operands<'_> {
+    fn is_empty(&self) -> bool {
+        self.position == self.words.len()
+    }
+
+    fn next(&mut self) -> Option<Word> {
+        let word = self.words.get(self.position).cloned();
+        self.position += 1;
+        word
+    }
+}
+
+fn write_string_literal(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+impl Disassembler {
+    fn write_number(&mut self, ty: NumericType, operands: &mut Operands) -> Result<(), ()> {
+        let low = operands.next().ok_or(())?;
+        let bits = if ty.width() > 32 {
+            u64::from(low) | u64::from(operands.next().ok_or(())?) << 32
+        } else {
+            u64::from(low)
+        };
+        let _ = write!(self.out, " {}", spv_grammar::format_number(ty, bits));
+        Ok(())
+    }
+
+    fn write_operand(
+        &mut self,
+        kind: OperandKind,
+        instruction_type: Option<Word>,
+        first_operand: Option<Word>,
+        operands: &mut Operands,
+    ) -> Result<(), ()> {
+        match kind {
+            OperandKind::IdRef => {
+                let id = operands.next().ok_or(())?;
+                let _ = write!(self.out, " %{}", id);
+            }
+            OperandKind::LiteralInteger => {
+                let value = operands.next().ok_or(())?;
+                let _ = write!(self.out, " {}", value);
+            }
+            OperandKind::LiteralString => {
+                let (string, count) =
+                    spv_grammar::decode_string(&operands.words[operands.position..]).ok_or(())?;
+                operands.position += count;
+                self.out.push(' ');
+                write_string_literal(&mut self.out, &string);
+            }
+            OperandKind::LiteralContextDependentNumber => {
+                let ty = self.context.literal_type(instruction_type).ok_or(())?;
+                self.write_number(ty, operands)?;
+            }
+            OperandKind::LiteralExtInstInteger => {
+                let value = operands.next().ok_or(())?;
+                let set = self.context.ext_inst_set(first_operand);
+                match set.and_then(|set| spv_grammar::ext_inst_name(set, value)) {
+                    Some(name) => {
+                        let _ = write!(self.out, " {}", name);
+                    }
+                    None => {
+                        let _ = write!(self.out, " {}", value);
+                    }
+                }
+            }
+            OperandKind::LiteralSpecConstantOpInteger => {
+                let value = operands.next().ok_or(())?;
+                match Op::from_u32(value) {
+                    Some(op) => {
+                        let _ = write!(self.out, " {:?}", op);
+                    }
+                    None => return Err(()),
+                }
+            }
+            OperandKind::PairLiteralIntegerIdRef => {
+                // the selector defines the width of the literals
+                let ty = self.context.selector_type(first_operand).ok_or(())?;
+                self.write_number(ty, operands)?;
+                self.write_operand(OperandKind::IdRef, None, None, operands)?;
+            }
+            OperandKind::PairIdRefLiteralInteger => {
+                self.write_operand(OperandKind::IdRef, None, None, operands)?;
+                self.write_operand(OperandKind::LiteralInteger, None, None, operands)?;
+            }
+            OperandKind::PairIdRefIdRef => {
+                self.write_operand(OperandKind::IdRef, None, None, operands)?;
+                self.write_operand(OperandKind::IdRef, None, None, operands)?;
+            }
+            OperandKind::Value(value_enum) => {
+                let value = operands.next().ok_or(())?;
+                match value_enum.name(value) {
+                    Some(name) => {
+                        let _ = write!(self.out, " {}", name);
+                    }
+                    None => {
+                        let _ = write!(self.out, " {}", value);
+                    }
+                }
+                for &parameter in value_enum.parameters(value) {
+                    self.write_operand(parameter, None, None, operands)?;
+                }
+            }
+            OperandKind::Bits(bit_enum) => {
+                let mask = operands.next().ok_or(())?;
+                let enumerants = bit_enum.enumerants();
+                let known = enumerants.iter().fold(0, |known, &(_, bit, _)| known | bit);
+                if mask == 0 {
+                    self.out.push_str(" None");
+                } else if mask & !known != 0 {
+                    let _ = write!(self.out, " {:#x}", mask);
+                } else {
+                    let names = enumerants
+                        .iter()
+                        .filter(|&&(_, bit, _)| mask & bit != 0)
+                        .map(|&(name, _, _)| name)
+                        .collect::<Vec<_>>();
+                    let _ = write!(self.out, " {}", names.join("|"));
+                }
+                for &(_, bit, parameters) in enumerants {
+                    if mask & bit != 0 {
+                        for &parameter in parameters {
+                            self.write_operand(parameter, None, None, operands)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_instruction(&mut self, offset: usize, words: &[Word]) -> Result<(), DisassemblyError> {
+        let opcode = (words[0] & 0xffff) as u16;
+        let op =
+            Op::from_u16(opcode).ok_or(DisassemblyError::UnknownInstruction(offset, opcode))?;
+        let grammar = spv_grammar::instruction(op)
+            .ok_or(DisassemblyError::UnknownInstruction(offset, opcode))?;
+        let invalid = DisassemblyError::InvalidOperands(offset, op);
+
+        let mut rest = &words[1..];
+        let mut type_id = None;
+        if grammar.has_type {
+            type_id = Some(*rest.first().ok_or_else(|| invalid.clone())?);
+            rest = &rest[1..];
+        }
+        let mut result_id = None;
+        if grammar.has_result {
+            result_id = Some(*rest.first().ok_or_else(|| invalid.clone())?);
+            rest = &rest[1..];
+        }
+
+        match result_id {
+            Some(id) => {
+                let _ = write!(
+                    self.out,
+                    "{:>width$}",
+                    format!("%{} = ", id),
+                    width = OPCODE_COLUMN
+                );
+            }
+            None => {
+                let _ = write!(self.out, "{:width$}", "", width = OPCODE_COLUMN);
+            }
+        }
+        self.out.push_str(&spv_grammar::op_name(op));
+        if let Some(id) = type_id {
+            let _ = write!(self.out, " %{}", id);
+        }
+
+        let mut operands = Operands {
+            words: rest,
+            position: 0,
+        };
+        let first_operand = rest.first().cloned();
+        for &(kind, quantifier) in grammar.operands {
+            match quantifier {
+                Quantifier::One => {
+                    self.write_operand(kind, type_id, first_operand, &mut operands)
+                        .map_err(|()| invalid.clone())?;
+                }
+                Quantifier::Optional => {
+                    if !operands.is_empty() {
+                        self.write_operand(kind, type_id, first_operand, &mut operands)
+                            .map_err(|()| invalid.clone())?;
+                    }
+                }
+                Quantifier::Variadic => {
+                    while !operands.is_empty() {
+                        self.write_operand(kind, type_id, first_operand, &mut operands)
+                            .map_err(|()| invalid.clone())?;
+                    }
+                }
+            }
+        }
+        if !operands.is_empty() {
+            return Err(invalid);
+        }
+        self.out.push('\n');
+
+        self.context.track(op, type_id, result_id, rest);
+        Ok(())
+    }
+}
+
+/// Disassembles a SPIR-V binary, such as the output of `write_vec`.
+pub fn disassemble(words: &[Word]) -> Result<String, DisassemblyError> {
+    if words.len() < 5 || words[0] != spirv::MAGIC_NUMBER {
+        return Err(DisassemblyError::InvalidHeader);
+    }
+    let mut disassembler = Disassembler {
+        out: String::new(),
+        context: Context::default(),
+    };
+    let _ = writeln!(disassembler.out, "; SPIR-V");
+    let _ = writeln!(
+        disassembler.out,
+        "; Version: {}.{}",
+        (words[1] >> 16) & 0xff,
+        (words[1] >> 8) & 0xff
+    );
+    let _ = writeln!(disassembler.out, "; Generator: {:#010x}", words[2]);
+    let _ = writeln!(disassembler.out, "; Bound: {}", words[3]);
+    let _ = writeln!(disassembler.out, "; Schema: {}", words[4]);
+
+    let mut offset = 5;
+    while offset < words.len() {
+        let word_count = (words[offset] >> 16) as usize;
+        if word_count == 0 || offset + word_count > words.len() {
+            return Err(DisassemblyError::InvalidWordCount(offset));
+        }
+        disassembler.write_instruction(offset, &words[offset..offset + word_count])?;
+        offset += word_count;
+    }
+    Ok(disassembler.out)
+}
@@ -81,6 +81,12 @@ impl super::Instruction {
     //  Extension Instructions
     //
 
+    pub(super) fn extension(name: &str) -> Self {
+        let mut instruction = Self::new(Op::Extension);
+        instruction.add_operands(helpers::string_to_words(name));
+        instruction
+    }
+
     pub(super) fn ext_inst_import(id: Word, name: &str) -> Self {
         let mut instruction = Self::new(Op::ExtInstImport);
         instruction.set_result(id);
@@ -283,6 +289,12 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn type_acceleration_structure(id: Word) -> Self {
+        let mut instruction = Self::new(Op::TypeAccelerationStructureKHR);
+        instruction.set_result(id);
+        instruction
+    }
+
     pub(super) fn type_sampled_image(id: Word, image_type_id: Word) -> Self {
         let mut instruction = Self::new(Op::TypeSampledImage);
         instruction.set_result(id);
@@ -765,6 +777,10 @@ impl super::Instruction {
         Self::new(Op::Kill)
     }
 
+    pub(super) fn unreachable() -> Self {
+        Self::new(Op::Unreachable)
+    }
+
     pub(super) fn return_void() -> Self {
         Self::new(Op::Return)
     }
@@ -775,11 +791,215 @@ impl super::Instruction {
         instruction
     }
 
+    //
+    //  Barrier Instructions
+    //
+
+    pub(super) fn control_barrier(
+        execution_scope_id: Word,
+        memory_scope_id: Word,
+        semantics_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::ControlBarrier);
+        instruction.add_operand(execution_scope_id);
+        instruction.add_operand(memory_scope_id);
+        instruction.add_operand(semantics_id);
+        instruction
+    }
+
+    pub(super) fn memory_barrier(memory_scope_id: Word, semantics_id: Word) -> Self {
+        let mut instruction = Self::new(Op::MemoryBarrier);
+        instruction.add_operand(memory_scope_id);
+        instruction.add_operand(semantics_id);
+        instruction
+    }
+
     //
     //  Atomic Instructions
     //
 
+    pub(super) fn atomic_load(
+        result_type_id: Word,
+        id: Word,
+        pointer_id: Word,
+        scope_id: Word,
+        semantics_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::AtomicLoad);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(pointer_id);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(semantics_id);
+        instruction
+    }
+
+    pub(super) fn atomic_store(
+        pointer_id: Word,
+        scope_id: Word,
+        semantics_id: Word,
+        value_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::AtomicStore);
+        instruction.add_operand(pointer_id);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(semantics_id);
+        instruction.add_operand(value_id);
+        instruction
+    }
+
+    pub(super) fn atomic_binary(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        pointer_id: Word,
+        scope_id: Word,
+        semantics_id: Word,
+        value_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(pointer_id);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(semantics_id);
+        instruction.add_operand(value_id);
+        instruction
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn atomic_compare_exchange(
+        result_type_id: Word,
+        id: Word,
+        pointer_id: Word,
+        scope_id: Word,
+        equal_semantics_id: Word,
+        unequal_semantics_id: Word,
+        value_id: Word,
+        comparator_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::AtomicCompareExchange);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(pointer_id);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(equal_semantics_id);
+        instruction.add_operand(unequal_semantics_id);
+        instruction.add_operand(value_id);
+        instruction.add_operand(comparator_id);
+        instruction
+    }
+
+    //
+    //  Non-Uniform Instructions
+    //
+
+    pub(super) fn group_non_uniform_elect(
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::GroupNonUniformElect);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction
+    }
+
+    pub(super) fn group_non_uniform_unary(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        value_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(value_id);
+        instruction
+    }
+
+    pub(super) fn group_non_uniform_gather(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        value_id: Word,
+        index_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(value_id);
+        instruction.add_operand(index_id);
+        instruction
+    }
+
+    pub(super) fn group_non_uniform_arithmetic(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        group_op: spirv::GroupOperation,
+        value_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(group_op as u32);
+        instruction.add_operand(value_id);
+        instruction
+    }
+
     //
     //  Primitive Instructions
     //
+
+    pub(super) fn emit_vertex() -> Self {
+        Self::new(Op::EmitVertex)
+    }
+
+    pub(super) fn end_primitive() -> Self {
+        Self::new(Op::EndPrimitive)
+    }
+
+    //
+    //  Ray Tracing Instructions
+    //
+
+    /// The `operands` go in the order of `OpTraceRayKHR`,
+    /// from the acceleration structure to the payload.
+    pub(super) fn trace_ray(operands: &[Word]) -> Self {
+        let mut instruction = Self::new(Op::TraceRayKHR);
+        for operand in operands {
+            instruction.add_operand(*operand);
+        }
+        instruction
+    }
+
+    pub(super) fn report_intersection(
+        result_type_id: Word,
+        id: Word,
+        hit_id: Word,
+        hit_kind_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::ReportIntersectionKHR);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(hit_id);
+        instruction.add_operand(hit_kind_id);
+        instruction
+    }
+
+    pub(super) fn ignore_intersection() -> Self {
+        Self::new(Op::IgnoreIntersectionKHR)
+    }
+
+    pub(super) fn terminate_ray() -> Self {
+        Self::new(Op::TerminateRayKHR)
+    }
 }
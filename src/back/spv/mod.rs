@@ -1,8 +1,10 @@
+mod disassembler;
 mod helpers;
 mod instructions;
 mod layout;
 mod writer;
 
+pub use disassembler::{disassemble, DisassemblyError};
 pub use spirv::Capability;
 pub use writer::{Error, Writer};
 
@@ -46,12 +46,29 @@ struct LocalVariable {
     instruction: Instruction,
 }
 
+struct ResultMember {
+    id: Word,
+    type_id: Word,
+}
+
+/// Interface of an entry point, which is passed through
+/// `Input` and `Output` variables instead of parameters.
+struct EntryPointContext {
+    /// Values of the arguments, loaded from the `Input` variables in the prelude.
+    argument_ids: Vec<Word>,
+    /// `Output` variables for the result, or for each of its members.
+    results: Vec<ResultMember>,
+    /// Whether the result is bound by itself, rather than through its members.
+    result_is_bound: bool,
+}
+
 #[derive(Default)]
 struct Function {
     signature: Option<Instruction>,
     parameters: Vec<Instruction>,
     variables: crate::FastHashMap<Handle<crate::LocalVariable>, LocalVariable>,
     blocks: Vec<Block>,
+    entry_point_context: Option<EntryPointContext>,
 }
 
 impl Function {
@@ -105,12 +122,14 @@ enum LocalType {
 impl LocalType {
     fn from_inner(inner: &crate::TypeInner) -> Option<Self> {
         Some(match *inner {
-            crate::TypeInner::Scalar { kind, width } => LocalType::Value {
-                vector_size: None,
-                kind,
-                width,
-                pointer_class: None,
-            },
+            crate::TypeInner::Scalar { kind, width } | crate::TypeInner::Atomic { kind, width } => {
+                LocalType::Value {
+                    vector_size: None,
+                    kind,
+                    width,
+                    pointer_class: None,
+                }
+            }
             crate::TypeInner::Vector { size, kind, width } => LocalType::Value {
                 vector_size: Some(size),
                 kind,
@@ -164,6 +183,14 @@ fn map_dim(dim: crate::ImageDimension) -> spirv::Dim {
     }
 }
 
+fn map_scope(scope: crate::Scope) -> spirv::Scope {
+    match scope {
+        crate::Scope::SubGroup => spirv::Scope::Subgroup,
+        crate::Scope::WorkGroup => spirv::Scope::Workgroup,
+        crate::Scope::Device => spirv::Scope::Device,
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
 struct LookupFunctionType {
     parameter_type_ids: Vec<Word>,
@@ -186,6 +213,28 @@ fn get_dimension(type_inner: &crate::TypeInner) -> Dimension {
     }
 }
 
+/// Check if the type holds 16-bit floats, which need extra capabilities
+/// when they are placed in externally visible storage.
+fn contains_half(arena: &Arena<crate::Type>, ty: Handle<crate::Type>) -> bool {
+    match arena[ty].inner {
+        crate::TypeInner::Scalar {
+            kind: crate::ScalarKind::Float,
+            width: 2,
+        }
+        | crate::TypeInner::Vector {
+            kind: crate::ScalarKind::Float,
+            width: 2,
+            ..
+        }
+        | crate::TypeInner::Matrix { width: 2, .. } => true,
+        crate::TypeInner::Array { base, .. } => contains_half(arena, base),
+        crate::TypeInner::Struct { ref members, .. } => {
+            members.iter().any(|member| contains_half(arena, member.ty))
+        }
+        _ => false,
+    }
+}
+
 #[derive(Clone, Copy, Default)]
 struct LoopContext {
     continuing_id: Option<Word>,
@@ -370,11 +419,241 @@ impl Writer {
         id
     }
 
+    /// Create the scope and memory semantics constants for an atomic operation
+    /// on a pointer in the given storage class.
+    fn create_atomic_scope_and_semantics(
+        &mut self,
+        arena: &Arena<crate::Type>,
+        class: spirv::StorageClass,
+    ) -> Result<(Word, Word), Error> {
+        let (scope, semantics) = match class {
+            spirv::StorageClass::Workgroup => (
+                spirv::Scope::Workgroup,
+                spirv::MemorySemantics::WORKGROUP_MEMORY,
+            ),
+            _ => (spirv::Scope::Device, spirv::MemorySemantics::UNIFORM_MEMORY),
+        };
+        let uint_type_id = self.get_type_id(
+            arena,
+            LookupType::Local(LocalType::Value {
+                vector_size: None,
+                kind: crate::ScalarKind::Uint,
+                width: 4,
+                pointer_class: None,
+            }),
+        )?;
+        let scope_id = self.create_constant(uint_type_id, &[scope as u32]);
+        let semantics_id = self.create_constant(uint_type_id, &[semantics.bits()]);
+        Ok((scope_id, semantics_id))
+    }
+
+    fn write_subgroup_operation(
+        &mut self,
+        ir_module: &crate::Module,
+        ir_function: &crate::Function,
+        fun: &crate::SubgroupFunction,
+        result: Handle<crate::Expression>,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        use crate::{CollectiveOperation as Co, ScalarKind as Sk, SubgroupOperation as So};
+        use spirv::{Capability as Cap, Op};
+
+        let (kind, width, vector_size) = match ir_function.expressions[result] {
+            crate::Expression::SubgroupResult { kind, width, size } => (kind, width, size),
+            ref other => {
+                log::error!("Subgroup result {:?}", other);
+                return Err(Error::FeatureNotImplemented("subgroup result"));
+            }
+        };
+        let result_type_id = self.get_type_id(
+            &ir_module.types,
+            LookupType::Local(LocalType::Value {
+                vector_size,
+                kind,
+                width,
+                pointer_class: None,
+            }),
+        )?;
+        let uint_type_id = self.get_type_id(
+            &ir_module.types,
+            LookupType::Local(LocalType::Value {
+                vector_size: None,
+                kind: Sk::Uint,
+                width: 4,
+                pointer_class: None,
+            }),
+        )?;
+        let exec_scope_id = self.create_constant(uint_type_id, &[spirv::Scope::Subgroup as u32]);
+
+        let id = self.generate_id();
+        let instruction = match *fun {
+            crate::SubgroupFunction::Elect => {
+                self.check(&[Cap::GroupNonUniform])?;
+                Instruction::group_non_uniform_elect(result_type_id, id, exec_scope_id)
+            }
+            crate::SubgroupFunction::Ballot { predicate } => {
+                self.check(&[Cap::GroupNonUniformBallot])?;
+                let predicate_id = match predicate {
+                    Some(predicate) => self.cached[predicate],
+                    None => {
+                        let bool_type_id = self.get_type_id(
+                            &ir_module.types,
+                            LookupType::Local(LocalType::Value {
+                                vector_size: None,
+                                kind: Sk::Bool,
+                                width: crate::BOOL_WIDTH,
+                                pointer_class: None,
+                            }),
+                        )?;
+                        let true_id = self.generate_id();
+                        Instruction::constant_true(bool_type_id, true_id)
+                            .to_words(&mut self.logical_layout.declarations);
+                        true_id
+                    }
+                };
+                Instruction::group_non_uniform_unary(
+                    Op::GroupNonUniformBallot,
+                    result_type_id,
+                    id,
+                    exec_scope_id,
+                    predicate_id,
+                )
+            }
+            crate::SubgroupFunction::Gather { mode, argument } => {
+                let value_id = self.cached[argument];
+                let (op, capability, index) = match mode {
+                    crate::GatherMode::BroadcastFirst => (
+                        Op::GroupNonUniformBroadcastFirst,
+                        Cap::GroupNonUniformBallot,
+                        None,
+                    ),
+                    crate::GatherMode::Broadcast(index) => (
+                        Op::GroupNonUniformBroadcast,
+                        Cap::GroupNonUniformBallot,
+                        Some(index),
+                    ),
+                    crate::GatherMode::Shuffle(index) => (
+                        Op::GroupNonUniformShuffle,
+                        Cap::GroupNonUniformShuffle,
+                        Some(index),
+                    ),
+                    crate::GatherMode::ShuffleXor(index) => (
+                        Op::GroupNonUniformShuffleXor,
+                        Cap::GroupNonUniformShuffle,
+                        Some(index),
+                    ),
+                    crate::GatherMode::ShuffleDown(index) => (
+                        Op::GroupNonUniformShuffleDown,
+                        Cap::GroupNonUniformShuffleRelative,
+                        Some(index),
+                    ),
+                    crate::GatherMode::ShuffleUp(index) => (
+                        Op::GroupNonUniformShuffleUp,
+                        Cap::GroupNonUniformShuffleRelative,
+                        Some(index),
+                    ),
+                };
+                self.check(&[capability])?;
+                match index {
+                    Some(index) => Instruction::group_non_uniform_gather(
+                        op,
+                        result_type_id,
+                        id,
+                        exec_scope_id,
+                        value_id,
+                        self.cached[index],
+                    ),
+                    None => Instruction::group_non_uniform_unary(
+                        op,
+                        result_type_id,
+                        id,
+                        exec_scope_id,
+                        value_id,
+                    ),
+                }
+            }
+            crate::SubgroupFunction::Collective {
+                op,
+                collective,
+                argument,
+            } => {
+                let value_id = self.cached[argument];
+                let spirv_op = match (op, kind) {
+                    (So::All, _) => Op::GroupNonUniformAll,
+                    (So::Any, _) => Op::GroupNonUniformAny,
+                    (So::Add, Sk::Float) => Op::GroupNonUniformFAdd,
+                    (So::Add, _) => Op::GroupNonUniformIAdd,
+                    (So::Multiply, Sk::Float) => Op::GroupNonUniformFMul,
+                    (So::Multiply, _) => Op::GroupNonUniformIMul,
+                    (So::Min, Sk::Float) => Op::GroupNonUniformFMin,
+                    (So::Min, Sk::Sint) => Op::GroupNonUniformSMin,
+                    (So::Min, _) => Op::GroupNonUniformUMin,
+                    (So::Max, Sk::Float) => Op::GroupNonUniformFMax,
+                    (So::Max, Sk::Sint) => Op::GroupNonUniformSMax,
+                    (So::Max, _) => Op::GroupNonUniformUMax,
+                    (So::And, Sk::Bool) => Op::GroupNonUniformLogicalAnd,
+                    (So::And, _) => Op::GroupNonUniformBitwiseAnd,
+                    (So::InclusiveOr, Sk::Bool) => Op::GroupNonUniformLogicalOr,
+                    (So::InclusiveOr, _) => Op::GroupNonUniformBitwiseOr,
+                    (So::ExclusiveOr, Sk::Bool) => Op::GroupNonUniformLogicalXor,
+                    (So::ExclusiveOr, _) => Op::GroupNonUniformBitwiseXor,
+                };
+                match op {
+                    // votes don't have a group operation, they are always reductions
+                    So::All | So::Any => {
+                        self.check(&[Cap::GroupNonUniformVote])?;
+                        Instruction::group_non_uniform_unary(
+                            spirv_op,
+                            result_type_id,
+                            id,
+                            exec_scope_id,
+                            value_id,
+                        )
+                    }
+                    _ => {
+                        self.check(&[Cap::GroupNonUniformArithmetic])?;
+                        let group_op = match collective {
+                            Co::Reduce => spirv::GroupOperation::Reduce,
+                            Co::InclusiveScan => spirv::GroupOperation::InclusiveScan,
+                            Co::ExclusiveScan => spirv::GroupOperation::ExclusiveScan,
+                        };
+                        Instruction::group_non_uniform_arithmetic(
+                            spirv_op,
+                            result_type_id,
+                            id,
+                            exec_scope_id,
+                            group_op,
+                            value_id,
+                        )
+                    }
+                }
+            }
+        };
+        block.body.push(instruction);
+        self.cached[result] = id;
+        Ok(())
+    }
+
+    fn is_atomic_pointer(
+        &self,
+        arena: &Arena<crate::Type>,
+        pointer: Handle<crate::Expression>,
+    ) -> bool {
+        match *self.typifier.get(pointer, arena) {
+            crate::TypeInner::Pointer { base, .. } => match arena[base].inner {
+                crate::TypeInner::Atomic { .. } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     fn write_function(
         &mut self,
         ir_function: &crate::Function,
         info: &FunctionInfo,
         ir_module: &crate::Module,
+        mut varying_ids: Option<&mut Vec<Word>>,
     ) -> Result<Word, Error> {
         let mut function = Function::default();
 
@@ -415,20 +694,133 @@ impl Writer {
                 .insert(handle, LocalVariable { id, instruction });
         }
 
-        let return_type_id = match ir_function.return_type {
-            Some(handle) => self.get_type_id(&ir_module.types, LookupType::Handle(handle))?,
-            None => self.void_type,
+        // the label is assigned once the signature is written
+        let mut prelude = Block::new(0);
+        let mut ep_context = EntryPointContext {
+            argument_ids: Vec::new(),
+            results: Vec::new(),
+            result_is_bound: false,
         };
-        let mut parameter_type_ids = Vec::with_capacity(ir_function.arguments.len());
 
+        let mut parameter_type_ids = Vec::with_capacity(ir_function.arguments.len());
         for argument in ir_function.arguments.iter() {
-            let id = self.generate_id();
-            let parameter_type_id =
+            let argument_type_id =
                 self.get_type_id(&ir_module.types, LookupType::Handle(argument.ty))?;
-            parameter_type_ids.push(parameter_type_id);
-            function
-                .parameters
-                .push(Instruction::function_parameter(parameter_type_id, id));
+            if let Some(ref mut list) = varying_ids {
+                let id = if let Some(ref binding) = argument.binding {
+                    let name = argument.name.as_ref().map(AsRef::as_ref);
+                    let varying_id = self.write_varying(
+                        ir_module,
+                        crate::StorageClass::Input,
+                        name,
+                        argument.ty,
+                        binding,
+                        argument.interpolation,
+                    )?;
+                    list.push(varying_id);
+                    let id = self.generate_id();
+                    prelude
+                        .body
+                        .push(Instruction::load(argument_type_id, id, varying_id, None));
+                    id
+                } else if let crate::TypeInner::Struct { ref members, .. } =
+                    ir_module.types[argument.ty].inner
+                {
+                    let mut constituent_ids = Vec::with_capacity(members.len());
+                    for member in members {
+                        let type_id =
+                            self.get_type_id(&ir_module.types, LookupType::Handle(member.ty))?;
+                        let name = member.name.as_ref().map(AsRef::as_ref);
+                        let binding = member.binding.as_ref().unwrap();
+                        let varying_id = self.write_varying(
+                            ir_module,
+                            crate::StorageClass::Input,
+                            name,
+                            member.ty,
+                            binding,
+                            member.interpolation,
+                        )?;
+                        list.push(varying_id);
+                        let id = self.generate_id();
+                        prelude
+                            .body
+                            .push(Instruction::load(type_id, id, varying_id, None));
+                        constituent_ids.push(id);
+                    }
+                    let id = self.generate_id();
+                    prelude.body.push(Instruction::composite_construct(
+                        argument_type_id,
+                        id,
+                        &constituent_ids,
+                    ));
+                    id
+                } else {
+                    unreachable!("Missing argument binding on an entry point");
+                };
+                ep_context.argument_ids.push(id);
+            } else {
+                let id = self.generate_id();
+                parameter_type_ids.push(argument_type_id);
+                function
+                    .parameters
+                    .push(Instruction::function_parameter(argument_type_id, id));
+            }
+        }
+
+        let return_type_id = match ir_function.result {
+            Some(ref result) => {
+                if let Some(ref mut list) = varying_ids {
+                    if let Some(ref binding) = result.binding {
+                        let type_id =
+                            self.get_type_id(&ir_module.types, LookupType::Handle(result.ty))?;
+                        let varying_id = self.write_varying(
+                            ir_module,
+                            crate::StorageClass::Output,
+                            None,
+                            result.ty,
+                            binding,
+                            result.interpolation,
+                        )?;
+                        list.push(varying_id);
+                        ep_context.results.push(ResultMember {
+                            id: varying_id,
+                            type_id,
+                        });
+                        ep_context.result_is_bound = true;
+                    } else if let crate::TypeInner::Struct { ref members, .. } =
+                        ir_module.types[result.ty].inner
+                    {
+                        for member in members {
+                            let type_id =
+                                self.get_type_id(&ir_module.types, LookupType::Handle(member.ty))?;
+                            let name = member.name.as_ref().map(AsRef::as_ref);
+                            let binding = member.binding.as_ref().unwrap();
+                            let varying_id = self.write_varying(
+                                ir_module,
+                                crate::StorageClass::Output,
+                                name,
+                                member.ty,
+                                binding,
+                                member.interpolation,
+                            )?;
+                            list.push(varying_id);
+                            ep_context.results.push(ResultMember {
+                                id: varying_id,
+                                type_id,
+                            });
+                        }
+                    } else {
+                        unreachable!("Missing result binding on an entry point");
+                    }
+                    self.void_type
+                } else {
+                    self.get_type_id(&ir_module.types, LookupType::Handle(result.ty))?
+                }
+            }
+            None => self.void_type,
+        };
+        if varying_ids.is_some() {
+            function.entry_point_context = Some(ep_context);
         }
 
         let lookup_function_type = LookupFunctionType {
@@ -437,7 +829,8 @@ impl Writer {
         };
 
         let function_id = self.generate_id();
-        if self.flags.contains(WriterFlags::DEBUG) {
+        // entry points are named after themselves by `write_entry_point`
+        if self.flags.contains(WriterFlags::DEBUG) && varying_ids.is_none() {
             if let Some(ref name) = ir_function.name {
                 self.debugs.push(Instruction::name(function_id, name));
             }
@@ -451,9 +844,7 @@ impl Writer {
             function_type,
         ));
 
-        let prelude_id = self.generate_id();
-        let mut prelude = Block::new(prelude_id);
-
+        prelude.label_id = self.generate_id();
         // fill up the `GlobalVariable::handle_id`
         for gv in self.global_variables.iter_mut() {
             gv.handle_id = 0;
@@ -510,9 +901,14 @@ impl Writer {
         info: &FunctionInfo,
         ir_module: &crate::Module,
     ) -> Result<Instruction, Error> {
-        let function_id = self.write_function(&entry_point.function, info, ir_module)?;
-
         let mut interface_ids = Vec::new();
+        let function_id = self.write_function(
+            &entry_point.function,
+            info,
+            ir_module,
+            Some(&mut interface_ids),
+        )?;
+
         for (handle, var) in ir_module.global_variables.iter() {
             if info[handle].is_empty() {
                 continue;
@@ -543,6 +939,104 @@ impl Writer {
                 .to_words(&mut self.logical_layout.execution_modes);
                 spirv::ExecutionModel::GLCompute
             }
+            crate::ShaderStage::Geometry => {
+                let geometry = entry_point.geometry.ok_or(Error::FeatureNotImplemented(
+                    "geometry stage without primitives",
+                ))?;
+                let input_mode = match geometry.input {
+                    crate::GeometryInput::Points => spirv::ExecutionMode::InputPoints,
+                    crate::GeometryInput::Lines => spirv::ExecutionMode::InputLines,
+                    crate::GeometryInput::LinesAdjacency => {
+                        spirv::ExecutionMode::InputLinesAdjacency
+                    }
+                    crate::GeometryInput::Triangles => spirv::ExecutionMode::Triangles,
+                    crate::GeometryInput::TrianglesAdjacency => {
+                        spirv::ExecutionMode::InputTrianglesAdjacency
+                    }
+                };
+                let output_mode = match geometry.output {
+                    crate::GeometryOutput::Points => spirv::ExecutionMode::OutputPoints,
+                    crate::GeometryOutput::LineStrip => spirv::ExecutionMode::OutputLineStrip,
+                    crate::GeometryOutput::TriangleStrip => {
+                        spirv::ExecutionMode::OutputTriangleStrip
+                    }
+                };
+                for &(execution_mode, args) in [
+                    (input_mode, &[][..]),
+                    (spirv::ExecutionMode::Invocations, &[1][..]),
+                    (output_mode, &[][..]),
+                    (
+                        spirv::ExecutionMode::OutputVertices,
+                        &[geometry.max_vertices][..],
+                    ),
+                ]
+                .iter()
+                {
+                    self.check(execution_mode.required_capabilities())?;
+                    Instruction::execution_mode(function_id, execution_mode, args)
+                        .to_words(&mut self.logical_layout.execution_modes);
+                }
+                spirv::ExecutionModel::Geometry
+            }
+            crate::ShaderStage::TessellationControl => {
+                let output_vertices = match entry_point.tessellation {
+                    Some(crate::TessellationInfo::Control { output_vertices }) => output_vertices,
+                    _ => {
+                        return Err(Error::FeatureNotImplemented(
+                            "tessellation control stage without output vertices",
+                        ))
+                    }
+                };
+                let execution_mode = spirv::ExecutionMode::OutputVertices;
+                self.check(execution_mode.required_capabilities())?;
+                Instruction::execution_mode(function_id, execution_mode, &[output_vertices])
+                    .to_words(&mut self.logical_layout.execution_modes);
+                spirv::ExecutionModel::TessellationControl
+            }
+            crate::ShaderStage::TessellationEvaluation => {
+                let (primitive, spacing, vertex_order) = match entry_point.tessellation {
+                    Some(crate::TessellationInfo::Evaluation {
+                        primitive,
+                        spacing,
+                        vertex_order,
+                    }) => (primitive, spacing, vertex_order),
+                    _ => {
+                        return Err(Error::FeatureNotImplemented(
+                            "tessellation evaluation stage without primitives",
+                        ))
+                    }
+                };
+                let primitive_mode = match primitive {
+                    crate::TessellationPrimitive::Triangles => spirv::ExecutionMode::Triangles,
+                    crate::TessellationPrimitive::Quads => spirv::ExecutionMode::Quads,
+                    crate::TessellationPrimitive::Isolines => spirv::ExecutionMode::Isolines,
+                };
+                let spacing_mode = match spacing {
+                    crate::TessellationSpacing::Equal => spirv::ExecutionMode::SpacingEqual,
+                    crate::TessellationSpacing::FractionalEven => {
+                        spirv::ExecutionMode::SpacingFractionalEven
+                    }
+                    crate::TessellationSpacing::FractionalOdd => {
+                        spirv::ExecutionMode::SpacingFractionalOdd
+                    }
+                };
+                let order_mode = match vertex_order {
+                    crate::VertexOrder::Clockwise => spirv::ExecutionMode::VertexOrderCw,
+                    crate::VertexOrder::CounterClockwise => spirv::ExecutionMode::VertexOrderCcw,
+                };
+                for &execution_mode in [primitive_mode, spacing_mode, order_mode].iter() {
+                    self.check(execution_mode.required_capabilities())?;
+                    Instruction::execution_mode(function_id, execution_mode, &[])
+                        .to_words(&mut self.logical_layout.execution_modes);
+                }
+                spirv::ExecutionModel::TessellationEvaluation
+            }
+            crate::ShaderStage::RayGeneration => spirv::ExecutionModel::RayGenerationKHR,
+            crate::ShaderStage::Intersection => spirv::ExecutionModel::IntersectionKHR,
+            crate::ShaderStage::AnyHit => spirv::ExecutionModel::AnyHitKHR,
+            crate::ShaderStage::ClosestHit => spirv::ExecutionModel::ClosestHitKHR,
+            crate::ShaderStage::Miss => spirv::ExecutionModel::MissKHR,
+            crate::ShaderStage::Callable => spirv::ExecutionModel::CallableKHR,
         };
         self.check(exec_model.required_capabilities())?;
 
@@ -559,9 +1053,22 @@ impl Writer {
         ))
     }
 
-    fn write_scalar(&self, id: Word, kind: crate::ScalarKind, width: crate::Bytes) -> Instruction {
+    fn write_scalar(
+        &mut self,
+        id: Word,
+        kind: crate::ScalarKind,
+        width: crate::Bytes,
+    ) -> Result<Instruction, Error> {
+        match (kind, width) {
+            (crate::ScalarKind::Float, 2) => self.check(&[spirv::Capability::Float16])?,
+            (crate::ScalarKind::Float, 8) => self.check(&[spirv::Capability::Float64])?,
+            (crate::ScalarKind::Sint, 8) | (crate::ScalarKind::Uint, 8) => {
+                self.check(&[spirv::Capability::Int64])?
+            }
+            _ => {}
+        }
         let bits = (width * BITS_PER_BYTE) as u32;
-        match kind {
+        Ok(match kind {
             crate::ScalarKind::Sint => {
                 Instruction::type_int(id, bits, super::instructions::Signedness::Signed)
             }
@@ -570,7 +1077,7 @@ impl Writer {
             }
             crate::ScalarKind::Float => Instruction::type_float(id, bits),
             crate::ScalarKind::Bool => Instruction::type_bool(id),
-        }
+        })
     }
 
     fn parse_to_spirv_storage_class(&self, class: crate::StorageClass) -> spirv::StorageClass {
@@ -588,6 +1095,9 @@ impl Writer {
             }
             crate::StorageClass::WorkGroup => spirv::StorageClass::Workgroup,
             crate::StorageClass::PushConstant => spirv::StorageClass::PushConstant,
+            crate::StorageClass::RayPayload => spirv::StorageClass::RayPayloadKHR,
+            crate::StorageClass::IncomingRayPayload => spirv::StorageClass::IncomingRayPayloadKHR,
+            crate::StorageClass::HitAttribute => spirv::StorageClass::HitAttributeKHR,
         }
     }
 
@@ -603,7 +1113,7 @@ impl Writer {
                 kind,
                 width,
                 pointer_class: None,
-            } => self.write_scalar(id, kind, width),
+            } => self.write_scalar(id, kind, width)?,
             LocalType::Value {
                 vector_size: Some(size),
                 kind,
@@ -684,7 +1194,8 @@ impl Writer {
         }
 
         let instruction = match ty.inner {
-            crate::TypeInner::Scalar { kind, width } => {
+            // atomics are regular scalars in SPIR-V
+            crate::TypeInner::Scalar { kind, width } | crate::TypeInner::Atomic { kind, width } => {
                 self.lookup_type.insert(
                     LookupType::Local(LocalType::Value {
                         vector_size: None,
@@ -694,7 +1205,7 @@ impl Writer {
                     }),
                     id,
                 );
-                self.write_scalar(id, kind, width)
+                self.write_scalar(id, kind, width)?
             }
             crate::TypeInner::Vector { size, kind, width } => {
                 let scalar_id = self.get_type_id(
@@ -763,6 +1274,10 @@ impl Writer {
                 Instruction::type_image(id, type_id, dim, arrayed, class)
             }
             crate::TypeInner::Sampler { comparison: _ } => Instruction::type_sampler(id),
+            crate::TypeInner::AccelerationStructure => {
+                self.check(&[spirv::Capability::RayTracingProvisionalKHR])?;
+                Instruction::type_acceleration_structure(id)
+            }
             crate::TypeInner::Array { base, size, stride } => {
                 if let Some(array_stride) = stride {
                     self.annotations.push(Instruction::decorate(
@@ -914,6 +1429,7 @@ impl Writer {
                         pointer_class: None,
                     }),
                 )?;
+                // multi-word literals are stored with the low-order word first
                 let (solo, pair);
                 match *value {
                     crate::ScalarValue::Sint(val) => {
@@ -923,7 +1439,7 @@ impl Writer {
                                 &solo[..]
                             }
                             8 => {
-                                pair = [(val >> 32) as u32, val as u32];
+                                pair = [val as u32, (val >> 32) as u32];
                                 &pair
                             }
                             _ => unreachable!(),
@@ -937,7 +1453,7 @@ impl Writer {
                                 &solo[..]
                             }
                             8 => {
-                                pair = [(val >> 32) as u32, val as u32];
+                                pair = [val as u32, (val >> 32) as u32];
                                 &pair
                             }
                             _ => unreachable!(),
@@ -946,13 +1462,17 @@ impl Writer {
                     }
                     crate::ScalarValue::Float(val) => {
                         let words = match width {
+                            2 => {
+                                solo = [u32::from(crate::proc::f16_to_bits(val))];
+                                &solo[..]
+                            }
                             4 => {
                                 solo = [(val as f32).to_bits()];
                                 &solo[..]
                             }
                             8 => {
                                 let bits = f64::to_bits(val);
-                                pair = [(bits >> 32) as u32, bits as u32];
+                                pair = [bits as u32, (bits >> 32) as u32];
                                 &pair
                             }
                             _ => unreachable!(),
@@ -989,6 +1509,22 @@ impl Writer {
 
         let class = self.parse_to_spirv_storage_class(global_variable.class);
         self.check(class.required_capabilities())?;
+        if contains_half(&ir_module.types, global_variable.ty) {
+            let capability = match global_variable.class {
+                crate::StorageClass::Storage => Some(spirv::Capability::StorageBuffer16BitAccess),
+                crate::StorageClass::Uniform => {
+                    Some(spirv::Capability::UniformAndStorageBuffer16BitAccess)
+                }
+                crate::StorageClass::PushConstant => Some(spirv::Capability::StoragePushConstant16),
+                crate::StorageClass::Input | crate::StorageClass::Output => {
+                    Some(spirv::Capability::StorageInputOutput16)
+                }
+                _ => None,
+            };
+            if let Some(capability) = capability {
+                self.check(&[capability])?;
+            }
+        }
 
         let init_word = global_variable
             .init
@@ -1013,7 +1549,24 @@ impl Writer {
                 .push(Instruction::decorate(id, decoration, &[]));
         }
 
-        if let Some(interpolation) = global_variable.interpolation {
+        self.decorate_interface(
+            id,
+            global_variable.binding.as_ref(),
+            global_variable.interpolation,
+        )?;
+
+        // TODO Initializer is optional and not (yet) included in the IR
+        Ok((instruction, id, class))
+    }
+
+    /// Decorate an interface variable with its binding and interpolation qualifier.
+    fn decorate_interface(
+        &mut self,
+        id: Word,
+        binding: Option<&crate::Binding>,
+        interpolation: Option<crate::Interpolation>,
+    ) -> Result<(), Error> {
+        if let Some(interpolation) = interpolation {
             let decoration = match interpolation {
                 crate::Interpolation::Linear => Some(spirv::Decoration::NoPerspective),
                 crate::Interpolation::Flat => Some(spirv::Decoration::Flat),
@@ -1028,15 +1581,15 @@ impl Writer {
             }
         }
 
-        match global_variable.binding {
-            Some(crate::Binding::Location(location)) => {
+        match binding {
+            Some(&crate::Binding::Location(location)) => {
                 self.annotations.push(Instruction::decorate(
                     id,
                     spirv::Decoration::Location,
                     &[location],
                 ));
             }
-            Some(crate::Binding::Resource { group, binding }) => {
+            Some(&crate::Binding::Resource { group, binding }) => {
                 self.annotations.push(Instruction::decorate(
                     id,
                     spirv::Decoration::DescriptorSet,
@@ -1048,7 +1601,7 @@ impl Writer {
                     &[binding],
                 ));
             }
-            Some(crate::Binding::BuiltIn(built_in)) => {
+            Some(&crate::Binding::BuiltIn(built_in)) => {
                 use crate::BuiltIn as Bi;
                 let built_in = match built_in {
                     Bi::BaseInstance => spirv::BuiltIn::BaseInstance,
@@ -1071,6 +1624,74 @@ impl Writer {
                     Bi::LocalInvocationIndex => spirv::BuiltIn::LocalInvocationIndex,
                     Bi::WorkGroupId => spirv::BuiltIn::WorkgroupId,
                     Bi::WorkGroupSize => spirv::BuiltIn::WorkgroupSize,
+                    // subgroup
+                    Bi::SubgroupSize => {
+                        self.check(&[spirv::Capability::GroupNonUniform])?;
+                        spirv::BuiltIn::SubgroupSize
+                    }
+                    Bi::SubgroupInvocationId => {
+                        self.check(&[spirv::Capability::GroupNonUniform])?;
+                        spirv::BuiltIn::SubgroupLocalInvocationId
+                    }
+                    // geometry and tessellation
+                    Bi::InvocationId => {
+                        self.check(spirv::BuiltIn::InvocationId.required_capabilities())?;
+                        spirv::BuiltIn::InvocationId
+                    }
+                    Bi::PatchVertices => {
+                        self.check(&[spirv::Capability::Tessellation])?;
+                        spirv::BuiltIn::PatchVertices
+                    }
+                    Bi::TessCoord => {
+                        self.check(&[spirv::Capability::Tessellation])?;
+                        spirv::BuiltIn::TessCoord
+                    }
+                    Bi::TessLevelInner | Bi::TessLevelOuter => {
+                        self.check(&[spirv::Capability::Tessellation])?;
+                        // tessellation levels are per-patch
+                        self.annotations.push(Instruction::decorate(
+                            id,
+                            spirv::Decoration::Patch,
+                            &[],
+                        ));
+                        if built_in == Bi::TessLevelInner {
+                            spirv::BuiltIn::TessLevelInner
+                        } else {
+                            spirv::BuiltIn::TessLevelOuter
+                        }
+                    }
+                    // ray tracing
+                    Bi::LaunchId
+                    | Bi::LaunchSize
+                    | Bi::WorldRayOrigin
+                    | Bi::WorldRayDirection
+                    | Bi::ObjectRayOrigin
+                    | Bi::ObjectRayDirection
+                    | Bi::RayTmin
+                    | Bi::RayTmax
+                    | Bi::InstanceCustomIndex
+                    | Bi::ObjectToWorld
+                    | Bi::WorldToObject
+                    | Bi::HitKind
+                    | Bi::IncomingRayFlags => {
+                        let built_in = match built_in {
+                            Bi::LaunchId => spirv::BuiltIn::LaunchIdKHR,
+                            Bi::LaunchSize => spirv::BuiltIn::LaunchSizeKHR,
+                            Bi::WorldRayOrigin => spirv::BuiltIn::WorldRayOriginKHR,
+                            Bi::WorldRayDirection => spirv::BuiltIn::WorldRayDirectionKHR,
+                            Bi::ObjectRayOrigin => spirv::BuiltIn::ObjectRayOriginKHR,
+                            Bi::ObjectRayDirection => spirv::BuiltIn::ObjectRayDirectionKHR,
+                            Bi::RayTmin => spirv::BuiltIn::RayTminKHR,
+                            Bi::RayTmax => spirv::BuiltIn::RayTmaxKHR,
+                            Bi::InstanceCustomIndex => spirv::BuiltIn::InstanceCustomIndexKHR,
+                            Bi::ObjectToWorld => spirv::BuiltIn::ObjectToWorldKHR,
+                            Bi::WorldToObject => spirv::BuiltIn::WorldToObjectKHR,
+                            Bi::HitKind => spirv::BuiltIn::HitKindKHR,
+                            _ => spirv::BuiltIn::IncomingRayFlagsKHR,
+                        };
+                        self.check(built_in.required_capabilities())?;
+                        built_in
+                    }
                 };
 
                 self.annotations.push(Instruction::decorate(
@@ -1081,9 +1702,40 @@ impl Writer {
             }
             None => {}
         }
+        Ok(())
+    }
 
-        // TODO Initializer is optional and not (yet) included in the IR
-        Ok((instruction, id, class))
+    /// Declare an `Input` or `Output` variable for an entry point argument or result.
+    fn write_varying(
+        &mut self,
+        ir_module: &crate::Module,
+        class: crate::StorageClass,
+        debug_name: Option<&str>,
+        ty: Handle<crate::Type>,
+        binding: &crate::Binding,
+        interpolation: Option<crate::Interpolation>,
+    ) -> Result<Word, Error> {
+        let id = self.generate_id();
+        if contains_half(&ir_module.types, ty) {
+            self.check(&[spirv::Capability::StorageInputOutput16])?;
+        }
+        let pointer_type_id = self.get_pointer_id(&ir_module.types, ty, class)?;
+        Instruction::variable(
+            pointer_type_id,
+            id,
+            self.parse_to_spirv_storage_class(class),
+            None,
+        )
+        .to_words(&mut self.logical_layout.declarations);
+
+        if self.flags.contains(WriterFlags::DEBUG) {
+            if let Some(name) = debug_name {
+                self.debugs.push(Instruction::name(id, name));
+            }
+        }
+
+        self.decorate_interface(id, Some(binding), interpolation)?;
+        Ok(id)
     }
 
     fn get_function_type(&mut self, lookup_function_type: LookupFunctionType) -> Word {
@@ -1584,7 +2236,7 @@ impl Writer {
             }
             crate::Expression::LocalVariable(variable) => function.variables[&variable].id,
             crate::Expression::Load { pointer } => {
-                let (pointer_id, _) = self.write_expression_pointer(
+                let (pointer_id, class) = self.write_expression_pointer(
                     ir_module,
                     ir_function,
                     pointer,
@@ -1593,14 +2245,20 @@ impl Writer {
                 )?;
 
                 let id = self.generate_id();
-                block
-                    .body
-                    .push(Instruction::load(result_type_id, id, pointer_id, None));
+                let instruction = if self.is_atomic_pointer(&ir_module.types, pointer) {
+                    let (scope_id, semantics_id) =
+                        self.create_atomic_scope_and_semantics(&ir_module.types, class)?;
+                    Instruction::atomic_load(result_type_id, id, pointer_id, scope_id, semantics_id)
+                } else {
+                    Instruction::load(result_type_id, id, pointer_id, None)
+                };
+                block.body.push(instruction);
                 id
             }
-            crate::Expression::FunctionArgument(index) => {
-                function.parameters[index as usize].result_id.unwrap()
-            }
+            crate::Expression::FunctionArgument(index) => match function.entry_point_context {
+                Some(ref context) => context.argument_ids[index as usize],
+                None => function.parameters[index as usize].result_id.unwrap(),
+            },
             crate::Expression::Call(_function) => self.lookup_function_call[&expr_handle],
             crate::Expression::As {
                 expr,
@@ -2120,8 +2778,37 @@ impl Writer {
                         Some(Instruction::branch(loop_context.continuing_id.unwrap()));
                 }
                 crate::Statement::Return { value: Some(value) } => {
-                    let id = self.cached[value];
-                    block.termination = Some(Instruction::return_value(id));
+                    let value_id = self.cached[value];
+                    let instruction = match function.entry_point_context {
+                        // If this is an entry point, and we need to return anything,
+                        // let's instead store the output variables and return `void`.
+                        Some(ref context) => {
+                            if context.result_is_bound {
+                                let result = &context.results[0];
+                                block
+                                    .body
+                                    .push(Instruction::store(result.id, value_id, None));
+                            } else {
+                                for (index, result) in context.results.iter().enumerate() {
+                                    let member_value_id = self.generate_id();
+                                    block.body.push(Instruction::composite_extract(
+                                        result.type_id,
+                                        member_value_id,
+                                        value_id,
+                                        &[index as u32],
+                                    ));
+                                    block.body.push(Instruction::store(
+                                        result.id,
+                                        member_value_id,
+                                        None,
+                                    ));
+                                }
+                            }
+                            Instruction::return_void()
+                        }
+                        None => Instruction::return_value(value_id),
+                    };
+                    block.termination = Some(instruction);
                 }
                 crate::Statement::Return { value: None } => {
                     block.termination = Some(Instruction::return_void());
@@ -2129,8 +2816,70 @@ impl Writer {
                 crate::Statement::Kill => {
                     block.termination = Some(Instruction::kill());
                 }
+                // The provisional opcodes don't terminate the block by themselves.
+                crate::Statement::IgnoreIntersection => {
+                    block.body.push(Instruction::ignore_intersection());
+                    block.termination = Some(Instruction::unreachable());
+                }
+                crate::Statement::TerminateRay => {
+                    block.body.push(Instruction::terminate_ray());
+                    block.termination = Some(Instruction::unreachable());
+                }
+                crate::Statement::TraceRay {
+                    acceleration_structure,
+                    flags,
+                    cull_mask,
+                    sbt_offset,
+                    sbt_stride,
+                    miss_index,
+                    origin,
+                    t_min,
+                    direction,
+                    t_max,
+                    payload,
+                } => {
+                    let acceleration_structure_id =
+                        self.get_expression_global(ir_function, acceleration_structure);
+                    let mut operands = vec![acceleration_structure_id];
+                    for &expr in &[
+                        flags, cull_mask, sbt_offset, sbt_stride, miss_index, origin, t_min,
+                        direction, t_max, payload,
+                    ] {
+                        operands.push(self.cached[expr]);
+                    }
+                    block.body.push(Instruction::trace_ray(&operands));
+                }
+                crate::Statement::ReportIntersection {
+                    hit_t,
+                    hit_kind,
+                    result,
+                } => {
+                    let id = self.generate_id();
+                    let bool_type_id = self.get_type_id(
+                        &ir_module.types,
+                        LookupType::Local(LocalType::Value {
+                            vector_size: None,
+                            kind: crate::ScalarKind::Bool,
+                            width: crate::BOOL_WIDTH,
+                            pointer_class: None,
+                        }),
+                    )?;
+                    self.cached[result] = id;
+                    block.body.push(Instruction::report_intersection(
+                        bool_type_id,
+                        id,
+                        self.cached[hit_t],
+                        self.cached[hit_kind],
+                    ));
+                }
+                crate::Statement::EmitVertex => {
+                    block.body.push(Instruction::emit_vertex());
+                }
+                crate::Statement::EndPrimitive => {
+                    block.body.push(Instruction::end_primitive());
+                }
                 crate::Statement::Store { pointer, value } => {
-                    let (pointer_id, _) = self.write_expression_pointer(
+                    let (pointer_id, class) = self.write_expression_pointer(
                         ir_module,
                         ir_function,
                         pointer,
@@ -2139,9 +2888,14 @@ impl Writer {
                     )?;
                     let value_id = self.cached[value];
 
-                    block
-                        .body
-                        .push(Instruction::store(pointer_id, value_id, None));
+                    let instruction = if self.is_atomic_pointer(&ir_module.types, pointer) {
+                        let (scope_id, semantics_id) =
+                            self.create_atomic_scope_and_semantics(&ir_module.types, class)?;
+                        Instruction::atomic_store(pointer_id, scope_id, semantics_id, value_id)
+                    } else {
+                        Instruction::store(pointer_id, value_id, None)
+                    };
+                    block.body.push(instruction);
                 }
                 crate::Statement::ImageStore {
                     image,
@@ -2180,8 +2934,11 @@ impl Writer {
                         Some(expr) => {
                             self.cached[expr] = id;
                             self.lookup_function_call.insert(expr, id);
-                            let ty_handle =
-                                ir_module.functions[local_function].return_type.unwrap();
+                            let ty_handle = ir_module.functions[local_function]
+                                .result
+                                .as_ref()
+                                .unwrap()
+                                .ty;
                             self.get_type_id(&ir_module.types, LookupType::Handle(ty_handle))?
                         }
                         None => self.void_type,
@@ -2194,6 +2951,183 @@ impl Writer {
                         argument_ids.as_slice(),
                     ));
                 }
+                crate::Statement::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    let (kind, width) = match ir_function.expressions[result] {
+                        crate::Expression::AtomicResult { kind, width, .. } => (kind, width),
+                        ref other => {
+                            log::error!("Atomic result {:?}", other);
+                            return Err(Error::FeatureNotImplemented("atomic result"));
+                        }
+                    };
+                    let scalar_type_id = self.get_type_id(
+                        &ir_module.types,
+                        LookupType::Local(LocalType::Value {
+                            vector_size: None,
+                            kind,
+                            width,
+                            pointer_class: None,
+                        }),
+                    )?;
+                    let (pointer_id, class) = self.write_expression_pointer(
+                        ir_module,
+                        ir_function,
+                        pointer,
+                        &mut block,
+                        function,
+                    )?;
+                    let (scope_id, semantics_id) =
+                        self.create_atomic_scope_and_semantics(&ir_module.types, class)?;
+                    let value_id = self.cached[value];
+
+                    let id = self.generate_id();
+                    let instruction = match *fun {
+                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                            Instruction::atomic_compare_exchange(
+                                scalar_type_id,
+                                id,
+                                pointer_id,
+                                scope_id,
+                                semantics_id,
+                                semantics_id,
+                                value_id,
+                                self.cached[cmp],
+                            )
+                        }
+                        _ => {
+                            let op = match *fun {
+                                crate::AtomicFunction::Add => spirv::Op::AtomicIAdd,
+                                crate::AtomicFunction::Subtract => spirv::Op::AtomicISub,
+                                crate::AtomicFunction::And => spirv::Op::AtomicAnd,
+                                crate::AtomicFunction::ExclusiveOr => spirv::Op::AtomicXor,
+                                crate::AtomicFunction::InclusiveOr => spirv::Op::AtomicOr,
+                                crate::AtomicFunction::Min if kind == crate::ScalarKind::Sint => {
+                                    spirv::Op::AtomicSMin
+                                }
+                                crate::AtomicFunction::Min => spirv::Op::AtomicUMin,
+                                crate::AtomicFunction::Max if kind == crate::ScalarKind::Sint => {
+                                    spirv::Op::AtomicSMax
+                                }
+                                crate::AtomicFunction::Max => spirv::Op::AtomicUMax,
+                                crate::AtomicFunction::Exchange { .. } => spirv::Op::AtomicExchange,
+                            };
+                            Instruction::atomic_binary(
+                                op,
+                                scalar_type_id,
+                                id,
+                                pointer_id,
+                                scope_id,
+                                semantics_id,
+                                value_id,
+                            )
+                        }
+                    };
+                    block.body.push(instruction);
+
+                    self.cached[result] = match *fun {
+                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                            // compose the original value with the "exchanged" flag
+                            let bool_type_id = self.get_type_id(
+                                &ir_module.types,
+                                LookupType::Local(LocalType::Value {
+                                    vector_size: None,
+                                    kind: crate::ScalarKind::Bool,
+                                    width: crate::BOOL_WIDTH,
+                                    pointer_class: None,
+                                }),
+                            )?;
+                            let equal_id = self.generate_id();
+                            block.body.push(Instruction::binary(
+                                spirv::Op::IEqual,
+                                bool_type_id,
+                                equal_id,
+                                id,
+                                self.cached[cmp],
+                            ));
+                            let one_id = self.create_constant(scalar_type_id, &[1]);
+                            let zero_id = self.create_constant(scalar_type_id, &[0]);
+                            let flag_id = self.generate_id();
+                            block.body.push(Instruction::select(
+                                scalar_type_id,
+                                flag_id,
+                                equal_id,
+                                one_id,
+                                zero_id,
+                            ));
+                            let vector_type_id = self.get_type_id(
+                                &ir_module.types,
+                                LookupType::Local(LocalType::Value {
+                                    vector_size: Some(crate::VectorSize::Bi),
+                                    kind,
+                                    width,
+                                    pointer_class: None,
+                                }),
+                            )?;
+                            let composite_id = self.generate_id();
+                            block.body.push(Instruction::composite_construct(
+                                vector_type_id,
+                                composite_id,
+                                &[id, flag_id],
+                            ));
+                            composite_id
+                        }
+                        _ => id,
+                    };
+                }
+                crate::Statement::Subgroup { ref fun, result } => {
+                    self.write_subgroup_operation(ir_module, ir_function, fun, result, &mut block)?;
+                }
+                crate::Statement::Barrier {
+                    execution,
+                    memory_scope,
+                    memory,
+                } => {
+                    let uint_type_id = self.get_type_id(
+                        &ir_module.types,
+                        LookupType::Local(LocalType::Value {
+                            vector_size: None,
+                            kind: crate::ScalarKind::Uint,
+                            width: 4,
+                            pointer_class: None,
+                        }),
+                    )?;
+                    let mut semantics = spirv::MemorySemantics::empty();
+                    semantics.set(
+                        spirv::MemorySemantics::UNIFORM_MEMORY,
+                        memory.contains(crate::Barrier::STORAGE),
+                    );
+                    semantics.set(
+                        spirv::MemorySemantics::WORKGROUP_MEMORY,
+                        memory.contains(crate::Barrier::WORK_GROUP),
+                    );
+                    semantics.set(
+                        spirv::MemorySemantics::IMAGE_MEMORY,
+                        memory.contains(crate::Barrier::IMAGE),
+                    );
+                    // the ordering is only allowed with a non-empty set of storage classes
+                    if !semantics.is_empty() {
+                        semantics |= spirv::MemorySemantics::ACQUIRE_RELEASE;
+                    }
+                    let memory_scope_id =
+                        self.create_constant(uint_type_id, &[map_scope(memory_scope) as u32]);
+                    let semantics_id = self.create_constant(uint_type_id, &[semantics.bits()]);
+                    block.body.push(match execution {
+                        Some(scope) => {
+                            let execution_scope_id =
+                                self.create_constant(uint_type_id, &[map_scope(scope) as u32]);
+                            Instruction::control_barrier(
+                                execution_scope_id,
+                                memory_scope_id,
+                                semantics_id,
+                            )
+                        }
+                        None => Instruction::memory_barrier(memory_scope_id, semantics_id),
+                    });
+                }
             }
         }
 
@@ -2253,7 +3187,7 @@ impl Writer {
 
         for (handle, ir_function) in ir_module.functions.iter() {
             let info = &analysis[handle];
-            let id = self.write_function(ir_function, info, ir_module)?;
+            let id = self.write_function(ir_function, info, ir_module, None)?;
             self.lookup_function.insert(handle, id);
         }
 
@@ -2266,6 +3200,25 @@ impl Writer {
         for capability in self.capabilities.iter() {
             Instruction::capability(*capability).to_words(&mut self.logical_layout.capabilities);
         }
+        if self
+            .capabilities
+            .contains(&spirv::Capability::RayTracingProvisionalKHR)
+        {
+            Instruction::extension("SPV_KHR_ray_tracing")
+                .to_words(&mut self.logical_layout.extensions);
+        }
+        if self.capabilities.iter().any(|cap| {
+            matches!(
+                *cap,
+                spirv::Capability::StorageBuffer16BitAccess
+                    | spirv::Capability::UniformAndStorageBuffer16BitAccess
+                    | spirv::Capability::StoragePushConstant16
+                    | spirv::Capability::StorageInputOutput16
+            )
+        }) {
+            Instruction::extension("SPV_KHR_16bit_storage")
+                .to_words(&mut self.logical_layout.extensions);
+        }
 
         let addressing_model = spirv::AddressingModel::Logical;
         let memory_model = spirv::MemoryModel::GLSL450;
@@ -2325,3 +3278,48 @@ fn test_write_physical_layout() {
     writer.write_physical_layout();
     assert_eq!(writer.physical_layout.bound, 3);
 }
+
+#[cfg(feature = "spv-in")]
+#[test]
+fn test_write_wide_constants() {
+    let values = [
+        crate::ScalarValue::Sint(-2),
+        crate::ScalarValue::Uint(0x1234_5678_9abc_def0),
+        crate::ScalarValue::Float(-1.0e100),
+    ];
+    let mut module = crate::Module::default();
+    for value in values.iter() {
+        module.constants.append(crate::Constant {
+            name: None,
+            specialization: None,
+            inner: crate::ConstantInner::Scalar {
+                width: 8,
+                value: value.clone(),
+            },
+        });
+    }
+    let analysis = crate::proc::Validator::new().validate(&module).unwrap();
+
+    let mut options = Options::default();
+    options.capabilities.insert(spirv::Capability::Int64);
+    options.capabilities.insert(spirv::Capability::Float64);
+    let words = super::write_vec(&module, &analysis, &options).unwrap();
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .collect::<Vec<_>>();
+    let parsed = crate::front::spv::parse_u8_slice(&bytes, &Default::default()).unwrap();
+
+    let parsed_values = parsed
+        .constants
+        .iter()
+        .filter_map(|(_, constant)| match constant.inner {
+            crate::ConstantInner::Scalar {
+                width: 8,
+                ref value,
+            } => Some(value.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(parsed_values, values);
+}
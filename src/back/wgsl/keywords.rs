@@ -0,0 +1,215 @@
+// https://gpuweb.github.io/gpuweb/wgsl/#keyword-summary
+pub const RESERVED: &[&str] = &[
+    // type-defining keywords
+    "array",
+    "atomic",
+    "bool",
+    "f16",
+    "f32",
+    "i32",
+    "mat2x2",
+    "mat2x3",
+    "mat2x4",
+    "mat3x2",
+    "mat3x3",
+    "mat3x4",
+    "mat4x2",
+    "mat4x3",
+    "mat4x4",
+    "ptr",
+    "sampler",
+    "sampler_comparison",
+    "struct",
+    "texture_1d",
+    "texture_1d_array",
+    "texture_2d",
+    "texture_2d_array",
+    "texture_3d",
+    "texture_cube",
+    "texture_cube_array",
+    "texture_multisampled_2d",
+    "texture_multisampled_2d_array",
+    "texture_storage_1d",
+    "texture_storage_1d_array",
+    "texture_storage_2d",
+    "texture_storage_2d_array",
+    "texture_storage_3d",
+    "texture_depth_2d",
+    "texture_depth_2d_array",
+    "texture_depth_cube",
+    "texture_depth_cube_array",
+    "u32",
+    "vec2",
+    "vec3",
+    "vec4",
+    // other keywords
+    "bitcast",
+    "break",
+    "case",
+    "const",
+    "continue",
+    "continuing",
+    "default",
+    "discard",
+    "else",
+    "elseif",
+    "enable",
+    "fallthrough",
+    "false",
+    "fn",
+    "for",
+    "function",
+    "if",
+    "let",
+    "loop",
+    "private",
+    "return",
+    "storage",
+    "switch",
+    "true",
+    "type",
+    "uniform",
+    "var",
+    "workgroup",
+    "push_constant",
+    // reserved words
+    "asm",
+    "bf16",
+    "do",
+    "enum",
+    "f64",
+    "handle",
+    "i8",
+    "i16",
+    "i64",
+    "mat",
+    "premerge",
+    "regardless",
+    "typedef",
+    "u8",
+    "u16",
+    "u64",
+    "unless",
+    "using",
+    "vec",
+    "void",
+    "while",
+    // built-in functions, which take precedence over user functions
+    "abs",
+    "acos",
+    "all",
+    "any",
+    "arrayLength",
+    "asin",
+    "atan",
+    "atan2",
+    "atomicAdd",
+    "atomicAnd",
+    "atomicCompareExchangeWeak",
+    "atomicExchange",
+    "atomicLoad",
+    "atomicMax",
+    "atomicMin",
+    "atomicOr",
+    "atomicStore",
+    "atomicSub",
+    "atomicXor",
+    "ceil",
+    "clamp",
+    "cos",
+    "cosh",
+    "countOneBits",
+    "cross",
+    "determinant",
+    "distance",
+    "dot",
+    "dpdx",
+    "dpdy",
+    "dwidth",
+    "exp",
+    "exp2",
+    "faceForward",
+    "floor",
+    "fma",
+    "fract",
+    "frexp",
+    "inverseSqrt",
+    "isFinite",
+    "isInf",
+    "isNan",
+    "isNormal",
+    "ldexp",
+    "length",
+    "log",
+    "log2",
+    "max",
+    "min",
+    "mix",
+    "modf",
+    "normalize",
+    "outerProduct",
+    "pow",
+    "reflect",
+    "reverseBits",
+    "round",
+    "select",
+    "sign",
+    "sin",
+    "sinh",
+    "smoothStep",
+    "sqrt",
+    "step",
+    "storageBarrier",
+    "subgroupBarrier",
+    "subgroupElect",
+    "subgroupBallot",
+    "subgroupBroadcastFirst",
+    "subgroupBroadcast",
+    "subgroupShuffle",
+    "subgroupShuffleDown",
+    "subgroupShuffleUp",
+    "subgroupShuffleXor",
+    "subgroupAll",
+    "subgroupAny",
+    "subgroupAdd",
+    "subgroupMul",
+    "subgroupMin",
+    "subgroupMax",
+    "subgroupAnd",
+    "subgroupOr",
+    "subgroupXor",
+    "subgroupInclusiveAll",
+    "subgroupInclusiveAny",
+    "subgroupInclusiveAdd",
+    "subgroupInclusiveMul",
+    "subgroupInclusiveMin",
+    "subgroupInclusiveMax",
+    "subgroupInclusiveAnd",
+    "subgroupInclusiveOr",
+    "subgroupInclusiveXor",
+    "subgroupExclusiveAll",
+    "subgroupExclusiveAny",
+    "subgroupExclusiveAdd",
+    "subgroupExclusiveMul",
+    "subgroupExclusiveMin",
+    "subgroupExclusiveMax",
+    "subgroupExclusiveAnd",
+    "subgroupExclusiveOr",
+    "subgroupExclusiveXor",
+    "tan",
+    "tanh",
+    "textureDimensions",
+    "textureLoad",
+    "textureNumLayers",
+    "textureNumLevels",
+    "textureNumSamples",
+    "textureSample",
+    "textureSampleBias",
+    "textureSampleCompare",
+    "textureSampleGrad",
+    "textureSampleLevel",
+    "textureStore",
+    "transpose",
+    "trunc",
+    "workgroupBarrier",
+];
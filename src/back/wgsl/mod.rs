@@ -0,0 +1,65 @@
+/*! WebGPU Shading Language (WGSL) backend
+
+The output targets the same dialect of WGSL that `front::wgsl` accepts,
+so any module produced by the other front-ends can be migrated to WGSL,
+and the result can be parsed back into the IR.
+
+## Expressions
+
+Expressions that are referenced more than once are baked into `const`
+declarations, in the same way as the other text backends do it.
+Results of function calls, atomic and subgroup operations always get
+a `const` declaration of their own.
+!*/
+
+use crate::proc::{analyzer::Analysis, TypifyError};
+use std::{io::Error as IoError, string::FromUtf8Error};
+
+mod keywords;
+mod writer;
+
+pub use writer::Writer;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    IO(#[from] IoError),
+    #[error(transparent)]
+    Utf8(#[from] FromUtf8Error),
+    #[error(transparent)]
+    Type(#[from] TypifyError),
+    #[error("scalar {0:?} with width {1} is not supported by WGSL")]
+    UnsupportedScalar(crate::ScalarKind, crate::Bytes),
+    #[error("math function {0:?} is not supported by WGSL")]
+    UnsupportedMathFunction(crate::MathFunction),
+    #[error("feature '{0}' is not supported by WGSL")]
+    UnsupportedFeature(String),
+    #[error("module is not valid")]
+    Validation,
+}
+
+/// Options of the WGSL backend.
+///
+/// WGSL doesn't have any versions or binding models to choose from yet,
+/// so this is empty for now.
+#[derive(Clone, Debug, Default)]
+pub struct Options {}
+
+/// Information about a translated module that is required
+/// for the use of the result.
+pub struct TranslationInfo {
+    /// Mapping of the entry point names. Each item in the array
+    /// corresponds to an entry point in `module.entry_points.iter()`.
+    pub entry_point_names: Vec<String>,
+}
+
+pub fn write_string(
+    module: &crate::Module,
+    analysis: &Analysis,
+    options: &Options,
+) -> Result<(String, TranslationInfo), Error> {
+    let mut w = Writer::new(Vec::new());
+    let info = w.write(module, analysis, options)?;
+    let string = String::from_utf8(w.finish())?;
+    Ok((string, info))
+}
@@ -0,0 +1,1452 @@
+use super::{keywords::RESERVED, Error, Options, TranslationInfo};
+use crate::{
+    arena::Handle,
+    proc::{
+        analyzer::{Analysis, FunctionInfo},
+        EntryPointIndex, NameKey, Namer, ResolveContext, Typifier,
+    },
+    FastHashMap,
+};
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    io::Write,
+};
+
+const INDENT: &str = "    ";
+const BAKE_PREFIX: &str = "_e";
+const COMPONENTS: &[char] = &['x', 'y', 'z', 'w'];
+
+#[derive(Clone)]
+struct Level(usize);
+impl Level {
+    fn next(&self) -> Self {
+        Level(self.0 + 1)
+    }
+}
+impl Display for Level {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), FmtError> {
+        (0..self.0).try_for_each(|_| formatter.write_str(INDENT))
+    }
+}
+
+fn scalar_string(kind: crate::ScalarKind, width: crate::Bytes) -> Result<&'static str, Error> {
+    Ok(match (kind, width) {
+        (crate::ScalarKind::Float, 2) => "f16",
+        (crate::ScalarKind::Float, 4) => "f32",
+        (crate::ScalarKind::Sint, 4) => "i32",
+        (crate::ScalarKind::Uint, 4) => "u32",
+        (crate::ScalarKind::Bool, crate::BOOL_WIDTH) => "bool",
+        _ => return Err(Error::UnsupportedScalar(kind, width)),
+    })
+}
+
+fn storage_class_string(class: crate::StorageClass) -> Result<Option<&'static str>, Error> {
+    Ok(Some(match class {
+        crate::StorageClass::Function => "function",
+        crate::StorageClass::Input => "in",
+        crate::StorageClass::Output => "out",
+        crate::StorageClass::Private => "private",
+        crate::StorageClass::WorkGroup => "workgroup",
+        crate::StorageClass::Uniform => "uniform",
+        crate::StorageClass::Storage => "storage",
+        crate::StorageClass::PushConstant => "push_constant",
+        crate::StorageClass::Handle => return Ok(None),
+        crate::StorageClass::RayPayload
+        | crate::StorageClass::IncomingRayPayload
+        | crate::StorageClass::HitAttribute => {
+            return Err(Error::UnsupportedFeature(format!(
+                "storage class {:?}",
+                class
+            )))
+        }
+    }))
+}
+
+fn built_in_string(built_in: crate::BuiltIn) -> Result<&'static str, Error> {
+    use crate::BuiltIn as Bi;
+    Ok(match built_in {
+        // vertex
+        Bi::InstanceIndex => "instance_index",
+        Bi::Position => "position",
+        Bi::VertexIndex => "vertex_index",
+        // fragment
+        Bi::FragCoord => "frag_coord",
+        Bi::FragDepth => "frag_depth",
+        Bi::FrontFacing => "front_facing",
+        Bi::SampleIndex => "sample_index",
+        Bi::SampleMaskIn => "sample_mask_in",
+        Bi::SampleMaskOut => "sample_mask_out",
+        // compute
+        Bi::GlobalInvocationId => "global_invocation_id",
+        Bi::LocalInvocationId => "local_invocation_id",
+        Bi::LocalInvocationIndex => "local_invocation_index",
+        Bi::WorkGroupId => "workgroup_id",
+        Bi::WorkGroupSize => "workgroup_size",
+        // subgroup
+        Bi::SubgroupSize => "subgroup_size",
+        Bi::SubgroupInvocationId => "subgroup_invocation_id",
+        _ => {
+            return Err(Error::UnsupportedFeature(format!(
+                "built-in {:?}",
+                built_in
+            )))
+        }
+    })
+}
+
+fn interpolation_string(interpolation: crate::Interpolation) -> Result<&'static str, Error> {
+    Ok(match interpolation {
+        crate::Interpolation::Perspective => "perspective",
+        crate::Interpolation::Linear => "linear",
+        crate::Interpolation::Flat => "flat",
+        crate::Interpolation::Centroid => "centroid",
+        crate::Interpolation::Sample => "sample",
+        crate::Interpolation::Patch => {
+            return Err(Error::UnsupportedFeature("patch interpolation".to_string()))
+        }
+    })
+}
+
+fn storage_format_string(format: crate::StorageFormat) -> &'static str {
+    use crate::StorageFormat as Sf;
+    match format {
+        Sf::R8Unorm => "r8unorm",
+        Sf::R8Snorm => "r8snorm",
+        Sf::R8Uint => "r8uint",
+        Sf::R8Sint => "r8sint",
+        Sf::R16Uint => "r16uint",
+        Sf::R16Sint => "r16sint",
+        Sf::R16Float => "r16float",
+        Sf::Rg8Unorm => "rg8unorm",
+        Sf::Rg8Snorm => "rg8snorm",
+        Sf::Rg8Uint => "rg8uint",
+        Sf::Rg8Sint => "rg8sint",
+        Sf::R32Uint => "r32uint",
+        Sf::R32Sint => "r32sint",
+        Sf::R32Float => "r32float",
+        Sf::Rg16Uint => "rg16uint",
+        Sf::Rg16Sint => "rg16sint",
+        Sf::Rg16Float => "rg16float",
+        Sf::Rgba8Unorm => "rgba8unorm",
+        Sf::Rgba8Snorm => "rgba8snorm",
+        Sf::Rgba8Uint => "rgba8uint",
+        Sf::Rgba8Sint => "rgba8sint",
+        Sf::Rgb10a2Unorm => "rgb10a2unorm",
+        Sf::Rg11b10Float => "rg11b10float",
+        Sf::Rg32Uint => "rg32uint",
+        Sf::Rg32Sint => "rg32sint",
+        Sf::Rg32Float => "rg32float",
+        Sf::Rgba16Uint => "rgba16uint",
+        Sf::Rgba16Sint => "rgba16sint",
+        Sf::Rgba16Float => "rgba16float",
+        Sf::Rgba32Uint => "rgba32uint",
+        Sf::Rgba32Sint => "rgba32sint",
+        Sf::Rgba32Float => "rgba32float",
+    }
+}
+
+fn math_function_string(fun: crate::MathFunction) -> Result<&'static str, Error> {
+    use crate::MathFunction as Mf;
+    Ok(match fun {
+        // comparison
+        Mf::Abs => "abs",
+        Mf::Min => "min",
+        Mf::Max => "max",
+        Mf::Clamp => "clamp",
+        // trigonometry
+        Mf::Cos => "cos",
+        Mf::Cosh => "cosh",
+        Mf::Sin => "sin",
+        Mf::Sinh => "sinh",
+        Mf::Tan => "tan",
+        Mf::Tanh => "tanh",
+        Mf::Acos => "acos",
+        Mf::Asin => "asin",
+        Mf::Atan => "atan",
+        Mf::Atan2 => "atan2",
+        // decomposition
+        Mf::Ceil => "ceil",
+        Mf::Floor => "floor",
+        Mf::Round => "round",
+        Mf::Fract => "fract",
+        Mf::Trunc => "trunc",
+        Mf::Modf => "modf",
+        Mf::Frexp => "frexp",
+        Mf::Ldexp => "ldexp",
+        // exponent
+        Mf::Exp => "exp",
+        Mf::Exp2 => "exp2",
+        Mf::Log => "log",
+        Mf::Log2 => "log2",
+        Mf::Pow => "pow",
+        // geometry
+        Mf::Dot => "dot",
+        Mf::Outer => "outerProduct",
+        Mf::Cross => "cross",
+        Mf::Distance => "distance",
+        Mf::Length => "length",
+        Mf::Normalize => "normalize",
+        Mf::FaceForward => "faceForward",
+        Mf::Reflect => "reflect",
+        // computational
+        Mf::Sign => "sign",
+        Mf::Fma => "fma",
+        Mf::Mix => "mix",
+        Mf::Step => "step",
+        Mf::SmoothStep => "smoothStep",
+        Mf::Sqrt => "sqrt",
+        Mf::InverseSqrt => "inverseSqrt",
+        Mf::Transpose => "transpose",
+        Mf::Determinant => "determinant",
+        // bits
+        Mf::CountOneBits => "countOneBits",
+        Mf::ReverseBits => "reverseBits",
+        Mf::Inverse => return Err(Error::UnsupportedMathFunction(fun)),
+    })
+}
+
+fn atomic_fun_name(fun: &crate::AtomicFunction) -> &'static str {
+    match *fun {
+        crate::AtomicFunction::Add => "atomicAdd",
+        crate::AtomicFunction::Subtract => "atomicSub",
+        crate::AtomicFunction::And => "atomicAnd",
+        crate::AtomicFunction::ExclusiveOr => "atomicXor",
+        crate::AtomicFunction::InclusiveOr => "atomicOr",
+        crate::AtomicFunction::Min => "atomicMin",
+        crate::AtomicFunction::Max => "atomicMax",
+        crate::AtomicFunction::Exchange { compare: None } => "atomicExchange",
+        crate::AtomicFunction::Exchange { compare: Some(_) } => "atomicCompareExchangeWeak",
+    }
+}
+
+fn subgroup_operation_string(
+    op: crate::SubgroupOperation,
+    collective: crate::CollectiveOperation,
+) -> String {
+    let prefix = match collective {
+        crate::CollectiveOperation::Reduce => "",
+        crate::CollectiveOperation::InclusiveScan => "Inclusive",
+        crate::CollectiveOperation::ExclusiveScan => "Exclusive",
+    };
+    let name = match op {
+        crate::SubgroupOperation::All => "All",
+        crate::SubgroupOperation::Any => "Any",
+        crate::SubgroupOperation::Add => "Add",
+        crate::SubgroupOperation::Multiply => "Mul",
+        crate::SubgroupOperation::Min => "Min",
+        crate::SubgroupOperation::Max => "Max",
+        crate::SubgroupOperation::And => "And",
+        crate::SubgroupOperation::InclusiveOr => "Or",
+        crate::SubgroupOperation::ExclusiveOr => "Xor",
+    };
+    format!("subgroup{}{}", prefix, name)
+}
+
+/// Returns true if the constant gets a declaration of its own,
+/// as opposed to being written as a literal at the place of use.
+fn is_declared(constant: &crate::Constant) -> bool {
+    match constant.inner {
+        crate::ConstantInner::Scalar { .. } => {
+            constant.name.is_some() || constant.specialization.is_some()
+        }
+        crate::ConstantInner::Composite { .. } => true,
+    }
+}
+
+enum FunctionOrigin {
+    Handle(Handle<crate::Function>),
+    EntryPoint(EntryPointIndex),
+}
+
+struct FunctionContext<'a> {
+    function: &'a crate::Function,
+    origin: FunctionOrigin,
+    info: &'a FunctionInfo,
+    module: &'a crate::Module,
+}
+
+impl FunctionContext<'_> {
+    fn argument_key(&self, index: u32) -> NameKey {
+        match self.origin {
+            FunctionOrigin::Handle(handle) => NameKey::FunctionArgument(handle, index),
+            FunctionOrigin::EntryPoint(ep_index) => NameKey::EntryPointArgument(ep_index, index),
+        }
+    }
+
+    fn local_key(&self, local: Handle<crate::LocalVariable>) -> NameKey {
+        match self.origin {
+            FunctionOrigin::Handle(handle) => NameKey::FunctionLocal(handle, local),
+            FunctionOrigin::EntryPoint(ep_index) => NameKey::EntryPointLocal(ep_index, local),
+        }
+    }
+}
+
+pub struct Writer<W> {
+    out: W,
+    names: FastHashMap<NameKey, String>,
+    /// Baked expressions of the current function, numbered in the order of their
+    /// declarations, so that the names don't depend on the expression handles.
+    named_expressions: FastHashMap<Handle<crate::Expression>, usize>,
+    typifier: Typifier,
+    namer: Namer,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new `Writer` instance.
+    pub fn new(out: W) -> Self {
+        Writer {
+            out,
+            names: FastHashMap::default(),
+            named_expressions: FastHashMap::default(),
+            typifier: Typifier::new(),
+            namer: Namer::default(),
+        }
+    }
+
+    /// Finishes writing and returns the output.
+    pub fn finish(self) -> W {
+        self.out
+    }
+
+    pub fn write(
+        &mut self,
+        module: &crate::Module,
+        analysis: &Analysis,
+        _options: &Options,
+    ) -> Result<TranslationInfo, Error> {
+        self.names.clear();
+        self.namer.reset(module, RESERVED, &mut self.names);
+
+        let uses_f16 = module.types.iter().any(|(_, ty)| match ty.inner {
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+            }
+            | crate::TypeInner::Vector {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+                ..
+            }
+            | crate::TypeInner::Matrix { width: 2, .. } => true,
+            _ => false,
+        });
+        if uses_f16 {
+            writeln!(self.out, "enable f16;")?;
+            writeln!(self.out)?;
+        }
+
+        // Scalar constants go first, since array sizes may refer to them.
+        self.write_constants(module, false)?;
+        self.write_structs(module)?;
+        self.write_constants(module, true)?;
+        self.write_global_variables(module)?;
+
+        for (handle, function) in module.functions.iter() {
+            let context = FunctionContext {
+                function,
+                origin: FunctionOrigin::Handle(handle),
+                info: &analysis[handle],
+                module,
+            };
+            let name = self.names[&NameKey::Function(handle)].clone();
+            self.write_function(&name, &context)?;
+            writeln!(self.out)?;
+        }
+
+        let mut info = TranslationInfo {
+            entry_point_names: Vec::with_capacity(module.entry_points.len()),
+        };
+        for (index, ep) in module.entry_points.iter().enumerate() {
+            let stage = match ep.stage {
+                crate::ShaderStage::Vertex => "vertex",
+                crate::ShaderStage::Fragment => "fragment",
+                crate::ShaderStage::Compute => "compute",
+                _ => {
+                    return Err(Error::UnsupportedFeature(format!(
+                        "shader stage {:?}",
+                        ep.stage
+                    )))
+                }
+            };
+            write!(self.out, "[[stage({})", stage)?;
+            if ep.stage == crate::ShaderStage::Compute {
+                let [x, y, z] = ep.workgroup_size;
+                write!(self.out, ", workgroup_size({}, {}, {})", x, y, z)?;
+            }
+            if let Some(early_depth_test) = ep.early_depth_test {
+                write!(self.out, ", early_depth_test")?;
+                if let Some(conservative) = early_depth_test.conservative {
+                    let name = match conservative {
+                        crate::ConservativeDepth::GreaterEqual => "greater_equal",
+                        crate::ConservativeDepth::LessEqual => "less_equal",
+                        crate::ConservativeDepth::Unchanged => "unchanged",
+                    };
+                    write!(self.out, "({})", name)?;
+                }
+            }
+            writeln!(self.out, "]]")?;
+
+            let context = FunctionContext {
+                function: &ep.function,
+                origin: FunctionOrigin::EntryPoint(index as _),
+                info: analysis.get_entry_point(index),
+                module,
+            };
+            let name = self.names[&NameKey::EntryPoint(index as _)].clone();
+            self.write_function(&name, &context)?;
+            writeln!(self.out)?;
+            info.entry_point_names.push(name);
+        }
+
+        Ok(info)
+    }
+
+    fn write_scalar_value(
+        &mut self,
+        width: crate::Bytes,
+        value: &crate::ScalarValue,
+    ) -> Result<(), Error> {
+        match *value {
+            crate::ScalarValue::Sint(value) if width == 4 => write!(self.out, "{}", value)?,
+            crate::ScalarValue::Uint(value) if width == 4 => write!(self.out, "{}u", value)?,
+            crate::ScalarValue::Float(value) if width == 2 || width == 4 => {
+                if !value.is_finite() {
+                    return Err(Error::UnsupportedFeature(format!("float value {}", value)));
+                }
+                // Literals without a decimal point are parsed as integers.
+                let mut string = format!("{:?}", value);
+                if !string.contains('.') {
+                    let position = string.find('e').unwrap_or(string.len());
+                    string.insert_str(position, ".0");
+                }
+                let suffix = if width == 2 { "h" } else { "" };
+                write!(self.out, "{}{}", string, suffix)?;
+            }
+            crate::ScalarValue::Bool(value) => write!(self.out, "{}", value)?,
+            crate::ScalarValue::Sint(_) => {
+                return Err(Error::UnsupportedScalar(crate::ScalarKind::Sint, width))
+            }
+            crate::ScalarValue::Uint(_) => {
+                return Err(Error::UnsupportedScalar(crate::ScalarKind::Uint, width))
+            }
+            crate::ScalarValue::Float(_) => {
+                return Err(Error::UnsupportedScalar(crate::ScalarKind::Float, width))
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a reference to the constant: either its name, or a literal.
+    fn write_constant(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Constant>,
+    ) -> Result<(), Error> {
+        let constant = &module.constants[handle];
+        if is_declared(constant) {
+            write!(self.out, "{}", self.names[&NameKey::Constant(handle)])?;
+            return Ok(());
+        }
+        match constant.inner {
+            crate::ConstantInner::Scalar { width, ref value } => {
+                self.write_scalar_value(width, value)
+            }
+            crate::ConstantInner::Composite { .. } => Err(Error::Validation),
+        }
+    }
+
+    fn write_constants(&mut self, module: &crate::Module, composite: bool) -> Result<(), Error> {
+        let mut any = false;
+        for (handle, constant) in module.constants.iter() {
+            if !is_declared(constant) {
+                continue;
+            }
+            let name = &self.names[&NameKey::Constant(handle)];
+            match constant.inner {
+                crate::ConstantInner::Scalar { width, ref value } if !composite => {
+                    if let Some(id) = constant.specialization {
+                        write!(self.out, "[[constant_id({})]] ", id)?;
+                    }
+                    let kind = value.scalar_kind();
+                    write!(
+                        self.out,
+                        "const {}: {} = ",
+                        name,
+                        scalar_string(kind, width)?
+                    )?;
+                    self.write_scalar_value(width, value)?;
+                    writeln!(self.out, ";")?;
+                }
+                crate::ConstantInner::Composite { ty, ref components } if composite => {
+                    write!(self.out, "const {}: ", name)?;
+                    self.write_type(module, ty)?;
+                    write!(self.out, " = ")?;
+                    self.write_type(module, ty)?;
+                    write!(self.out, "(")?;
+                    for (index, &component) in components.iter().enumerate() {
+                        if index != 0 {
+                            write!(self.out, ", ")?;
+                        }
+                        self.write_constant(module, component)?;
+                    }
+                    writeln!(self.out, ");")?;
+                }
+                _ => continue,
+            }
+            any = true;
+        }
+        if any {
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn write_structs(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, ty) in module.types.iter() {
+            let (block, members) = match ty.inner {
+                crate::TypeInner::Struct { block, ref members } => (block, members),
+                _ => continue,
+            };
+            if block {
+                writeln!(self.out, "[[block]]")?;
+            }
+            writeln!(self.out, "struct {} {{", self.names[&NameKey::Type(handle)])?;
+            for (index, member) in members.iter().enumerate() {
+                write!(self.out, "{}", INDENT)?;
+                let mut decorations = Vec::new();
+                if let Some(span) = member.span {
+                    decorations.push(format!("span({})", span));
+                }
+                self.collect_decorations(
+                    member.binding.as_ref(),
+                    member.interpolation,
+                    &mut decorations,
+                )?;
+                self.write_decorations(&decorations)?;
+                let name = &self.names[&NameKey::StructMember(handle, index as u32)];
+                write!(self.out, "{}: ", name)?;
+                self.write_type(module, member.ty)?;
+                writeln!(self.out, ";")?;
+            }
+            writeln!(self.out, "}};")?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn write_global_variables(&mut self, module: &crate::Module) -> Result<(), Error> {
+        for (handle, var) in module.global_variables.iter() {
+            let mut decorations = Vec::new();
+            self.collect_decorations(var.binding.as_ref(), var.interpolation, &mut decorations)?;
+            self.write_decorations(&decorations)?;
+            write!(self.out, "var")?;
+            if let Some(class) = storage_class_string(var.class)? {
+                write!(self.out, "<{}>", class)?;
+            }
+            write!(
+                self.out,
+                " {}: ",
+                self.names[&NameKey::GlobalVariable(handle)]
+            )?;
+            if !var.storage_access.is_empty() {
+                let access = if var.storage_access == crate::StorageAccess::LOAD {
+                    "read"
+                } else if var.storage_access == crate::StorageAccess::STORE {
+                    "write"
+                } else {
+                    "read_write"
+                };
+                write!(self.out, "[[access({})]] ", access)?;
+            }
+            self.write_type(module, var.ty)?;
+            if let Some(init) = var.init {
+                write!(self.out, " = ")?;
+                self.write_constant(module, init)?;
+            }
+            writeln!(self.out, ";")?;
+        }
+        if !module.global_variables.is_empty() {
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn collect_decorations(
+        &self,
+        binding: Option<&crate::Binding>,
+        interpolation: Option<crate::Interpolation>,
+        decorations: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        match binding {
+            Some(&crate::Binding::BuiltIn(built_in)) => {
+                decorations.push(format!("builtin({})", built_in_string(built_in)?));
+            }
+            Some(&crate::Binding::Location(location)) => {
+                decorations.push(format!("location({})", location));
+            }
+            Some(&crate::Binding::Resource { group, binding }) => {
+                decorations.push(format!("group({})", group));
+                decorations.push(format!("binding({})", binding));
+            }
+            None => {}
+        }
+        if let Some(interpolation) = interpolation {
+            decorations.push(format!(
+                "interpolate({})",
+                interpolation_string(interpolation)?
+            ));
+        }
+        Ok(())
+    }
+
+    fn write_decorations(&mut self, decorations: &[String]) -> Result<(), Error> {
+        if !decorations.is_empty() {
+            write!(self.out, "[[{}]] ", decorations.join(", "))?;
+        }
+        Ok(())
+    }
+
+    fn write_type(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Type>,
+    ) -> Result<(), Error> {
+        match module.types[handle].inner {
+            crate::TypeInner::Struct { .. } => {
+                write!(self.out, "{}", self.names[&NameKey::Type(handle)])?;
+                Ok(())
+            }
+            ref other => self.write_type_inner(module, other),
+        }
+    }
+
+    fn write_type_inner(
+        &mut self,
+        module: &crate::Module,
+        inner: &crate::TypeInner,
+    ) -> Result<(), Error> {
+        match *inner {
+            crate::TypeInner::Scalar { kind, width } => {
+                write!(self.out, "{}", scalar_string(kind, width)?)?;
+            }
+            crate::TypeInner::Vector { size, kind, width } => {
+                write!(
+                    self.out,
+                    "vec{}<{}>",
+                    size as u8,
+                    scalar_string(kind, width)?
+                )?;
+            }
+            crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => {
+                let scalar = scalar_string(crate::ScalarKind::Float, width)?;
+                write!(self.out, "mat{}x{}<{}>", columns as u8, rows as u8, scalar)?;
+            }
+            crate::TypeInner::Atomic { kind, width } => {
+                write!(self.out, "atomic<{}>", scalar_string(kind, width)?)?;
+            }
+            crate::TypeInner::Pointer { base, class } => {
+                let class = storage_class_string(class)?.unwrap_or("handle");
+                write!(self.out, "ptr<{}, ", class)?;
+                self.write_type(module, base)?;
+                write!(self.out, ">")?;
+            }
+            crate::TypeInner::ValuePointer {
+                size,
+                kind,
+                width,
+                class,
+            } => {
+                let class = storage_class_string(class)?.unwrap_or("handle");
+                write!(self.out, "ptr<{}, ", class)?;
+                match size {
+                    Some(size) => self.write_type_inner(
+                        module,
+                        &crate::TypeInner::Vector { size, kind, width },
+                    )?,
+                    None => {
+                        self.write_type_inner(module, &crate::TypeInner::Scalar { kind, width })?
+                    }
+                }
+                write!(self.out, ">")?;
+            }
+            crate::TypeInner::Array { base, size, stride } => {
+                if let Some(stride) = stride {
+                    write!(self.out, "[[stride({})]] ", stride)?;
+                }
+                write!(self.out, "array<")?;
+                self.write_type(module, base)?;
+                if let crate::ArraySize::Constant(handle) = size {
+                    write!(self.out, ", ")?;
+                    self.write_constant(module, handle)?;
+                }
+                write!(self.out, ">")?;
+            }
+            crate::TypeInner::Struct { .. } => {
+                // structures are only referred to by name
+                return Err(Error::Validation);
+            }
+            crate::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                let dim_str = match dim {
+                    crate::ImageDimension::D1 => "1d",
+                    crate::ImageDimension::D2 => "2d",
+                    crate::ImageDimension::D3 => "3d",
+                    crate::ImageDimension::Cube => "cube",
+                };
+                let arrayed_str = if arrayed { "_array" } else { "" };
+                match class {
+                    crate::ImageClass::Sampled { kind, multi } => {
+                        let multi_str = if multi { "multisampled_" } else { "" };
+                        write!(
+                            self.out,
+                            "texture_{}{}{}<{}>",
+                            multi_str,
+                            dim_str,
+                            arrayed_str,
+                            scalar_string(kind, 4)?
+                        )?;
+                    }
+                    crate::ImageClass::Depth => {
+                        write!(self.out, "texture_depth_{}{}", dim_str, arrayed_str)?;
+                    }
+                    crate::ImageClass::Storage(format) => {
+                        write!(
+                            self.out,
+                            "texture_storage_{}{}<{}>",
+                            dim_str,
+                            arrayed_str,
+                            storage_format_string(format)
+                        )?;
+                    }
+                }
+            }
+            crate::TypeInner::Sampler { comparison } => {
+                let name = if comparison {
+                    "sampler_comparison"
+                } else {
+                    "sampler"
+                };
+                write!(self.out, "{}", name)?;
+            }
+            crate::TypeInner::AccelerationStructure => {
+                return Err(Error::UnsupportedFeature(
+                    "acceleration structures".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the type of an expression, as resolved by the typifier.
+    fn write_expression_type(
+        &mut self,
+        module: &crate::Module,
+        handle: Handle<crate::Expression>,
+    ) -> Result<(), Error> {
+        // The typifier is moved out while the resolved type is written.
+        let typifier = std::mem::replace(&mut self.typifier, Typifier::new());
+        let result = match typifier.get_handle(handle) {
+            Ok(ty) => self.write_type(module, ty),
+            Err(inner) => self.write_type_inner(module, inner),
+        };
+        self.typifier = typifier;
+        result
+    }
+
+    fn write_function(&mut self, name: &str, context: &FunctionContext) -> Result<(), Error> {
+        let module = context.module;
+        let function = context.function;
+        self.typifier.resolve_all(
+            &function.expressions,
+            &module.types,
+            &ResolveContext {
+                constants: &module.constants,
+                global_vars: &module.global_variables,
+                local_vars: &function.local_variables,
+                functions: &module.functions,
+                arguments: &function.arguments,
+            },
+        )?;
+
+        write!(self.out, "fn {}(", name)?;
+        for (index, arg) in function.arguments.iter().enumerate() {
+            if index != 0 {
+                write!(self.out, ", ")?;
+            }
+            let mut decorations = Vec::new();
+            self.collect_decorations(arg.binding.as_ref(), arg.interpolation, &mut decorations)?;
+            self.write_decorations(&decorations)?;
+            let arg_name = &self.names[&context.argument_key(index as u32)];
+            write!(self.out, "{}: ", arg_name)?;
+            self.write_type(module, arg.ty)?;
+        }
+        write!(self.out, ")")?;
+        if let Some(ref result) = function.result {
+            write!(self.out, " -> ")?;
+            let mut decorations = Vec::new();
+            self.collect_decorations(
+                result.binding.as_ref(),
+                result.interpolation,
+                &mut decorations,
+            )?;
+            self.write_decorations(&decorations)?;
+            self.write_type(module, result.ty)?;
+        }
+        writeln!(self.out, " {{")?;
+
+        for (handle, local) in function.local_variables.iter() {
+            let local_name = &self.names[&context.local_key(handle)];
+            write!(self.out, "{}var {}: ", INDENT, local_name)?;
+            self.write_type(module, local.ty)?;
+            if let Some(init) = local.init {
+                write!(self.out, " = ")?;
+                self.write_constant(module, init)?;
+            }
+            writeln!(self.out, ";")?;
+        }
+        if !function.local_variables.is_empty() {
+            writeln!(self.out)?;
+        }
+
+        self.named_expressions.clear();
+        self.write_block(Level(1), &function.body, context)?;
+        writeln!(self.out, "}}")?;
+        Ok(())
+    }
+
+    /// Starts a `const` declaration for the result of the expression.
+    /// The caller is responsible for marking the expression as named,
+    /// with `name_expression`.
+    fn start_baking_expression(
+        &mut self,
+        level: &Level,
+        handle: Handle<crate::Expression>,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        write!(
+            self.out,
+            "{}const {}{}: ",
+            level,
+            BAKE_PREFIX,
+            self.named_expressions.len()
+        )?;
+        self.write_expression_type(context.module, handle)?;
+        write!(self.out, " = ")?;
+        Ok(())
+    }
+
+    fn name_expression(&mut self, handle: Handle<crate::Expression>) {
+        let index = self.named_expressions.len();
+        self.named_expressions.insert(handle, index);
+    }
+
+    fn can_bake(&self, handle: Handle<crate::Expression>, context: &FunctionContext) -> bool {
+        if context.function.expressions[handle].needs_pre_emit() {
+            return false;
+        }
+        match *self.typifier.get(handle, &context.module.types) {
+            crate::TypeInner::Pointer { .. }
+            | crate::TypeInner::ValuePointer { .. }
+            | crate::TypeInner::Image { .. }
+            | crate::TypeInner::Sampler { .. } => false,
+            _ => true,
+        }
+    }
+
+    fn is_atomic_pointer(
+        &self,
+        pointer: Handle<crate::Expression>,
+        context: &FunctionContext,
+    ) -> bool {
+        match *self.typifier.get(pointer, &context.module.types) {
+            crate::TypeInner::Pointer { base, .. } => match context.module.types[base].inner {
+                crate::TypeInner::Atomic { .. } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn write_block(
+        &mut self,
+        level: Level,
+        block: &[crate::Statement],
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        for statement in block {
+            self.write_statement(&level, statement, context)?;
+        }
+        Ok(())
+    }
+
+    fn write_statement(
+        &mut self,
+        level: &Level,
+        statement: &crate::Statement,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        log::trace!("statement[{}] {:?}", level.0, statement);
+        match *statement {
+            crate::Statement::Emit(ref range) => {
+                for handle in range.clone() {
+                    let min_ref_count = context.function.expressions[handle].bake_ref_count();
+                    if min_ref_count <= context.info[handle].ref_count
+                        && self.can_bake(handle, context)
+                    {
+                        self.start_baking_expression(level, handle, context)?;
+                        self.put_expression(handle, context)?;
+                        writeln!(self.out, ";")?;
+                        self.name_expression(handle);
+                    }
+                }
+            }
+            crate::Statement::Block(ref block) => {
+                writeln!(self.out, "{}{{", level)?;
+                self.write_block(level.next(), block, context)?;
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                write!(self.out, "{}if (", level)?;
+                self.put_expression(condition, context)?;
+                writeln!(self.out, ") {{")?;
+                self.write_block(level.next(), accept, context)?;
+                if !reject.is_empty() {
+                    writeln!(self.out, "{}}} else {{", level)?;
+                    self.write_block(level.next(), reject, context)?;
+                }
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                write!(self.out, "{}switch(", level)?;
+                self.put_expression(selector, context)?;
+                writeln!(self.out, ") {{")?;
+                let lcase = level.next();
+                for case in cases.iter() {
+                    writeln!(self.out, "{}case {}: {{", lcase, case.value)?;
+                    self.write_block(lcase.next(), &case.body, context)?;
+                    if case.fall_through {
+                        writeln!(self.out, "{}fallthrough;", lcase.next())?;
+                    }
+                    writeln!(self.out, "{}}}", lcase)?;
+                }
+                writeln!(self.out, "{}default: {{", lcase)?;
+                self.write_block(lcase.next(), default, context)?;
+                writeln!(self.out, "{}}}", lcase)?;
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                writeln!(self.out, "{}loop {{", level)?;
+                self.write_block(level.next(), body, context)?;
+                if !continuing.is_empty() {
+                    writeln!(self.out, "{}continuing {{", level.next())?;
+                    self.write_block(level.next().next(), continuing, context)?;
+                    writeln!(self.out, "{}}}", level.next())?;
+                }
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::Statement::Break => {
+                writeln!(self.out, "{}break;", level)?;
+            }
+            crate::Statement::Continue => {
+                writeln!(self.out, "{}continue;", level)?;
+            }
+            crate::Statement::Return { value: Some(value) } => {
+                write!(self.out, "{}return ", level)?;
+                self.put_expression(value, context)?;
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::Return { value: None } => {
+                writeln!(self.out, "{}return;", level)?;
+            }
+            crate::Statement::Kill => {
+                writeln!(self.out, "{}discard;", level)?;
+            }
+            crate::Statement::Store { pointer, value } => {
+                write!(self.out, "{}", level)?;
+                if self.is_atomic_pointer(pointer, context) {
+                    write!(self.out, "atomicStore(&")?;
+                    self.put_expression(pointer, context)?;
+                    write!(self.out, ", ")?;
+                    self.put_expression(value, context)?;
+                    write!(self.out, ")")?;
+                } else {
+                    self.put_expression(pointer, context)?;
+                    write!(self.out, " = ")?;
+                    self.put_expression(value, context)?;
+                }
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                write!(self.out, "{}textureStore(", level)?;
+                self.put_expression(image, context)?;
+                write!(self.out, ", ")?;
+                self.put_expression(coordinate, context)?;
+                if let Some(index) = array_index {
+                    write!(self.out, ", ")?;
+                    self.put_expression(index, context)?;
+                }
+                write!(self.out, ", ")?;
+                self.put_expression(value, context)?;
+                writeln!(self.out, ");")?;
+            }
+            crate::Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                match result {
+                    Some(result) => {
+                        self.start_baking_expression(level, result, context)?;
+                        self.name_expression(result);
+                    }
+                    None => write!(self.out, "{}", level)?,
+                }
+                let name = &self.names[&NameKey::Function(function)];
+                write!(self.out, "{}", name)?;
+                self.put_call_parameters(arguments.iter().cloned(), context)?;
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                self.start_baking_expression(level, result, context)?;
+                self.name_expression(result);
+                write!(self.out, "{}(&", atomic_fun_name(fun))?;
+                self.put_expression(pointer, context)?;
+                if let crate::AtomicFunction::Exchange {
+                    compare: Some(compare),
+                } = *fun
+                {
+                    write!(self.out, ", ")?;
+                    self.put_expression(compare, context)?;
+                }
+                write!(self.out, ", ")?;
+                self.put_expression(value, context)?;
+                writeln!(self.out, ");")?;
+            }
+            crate::Statement::Barrier {
+                execution,
+                memory_scope: _,
+                memory,
+            } => {
+                // WGSL only has control barriers, so memory barriers
+                // are promoted to the closest one of them.
+                if execution == Some(crate::Scope::SubGroup) {
+                    writeln!(self.out, "{}subgroupBarrier();", level)?;
+                } else {
+                    if memory.intersects(crate::Barrier::STORAGE | crate::Barrier::IMAGE) {
+                        writeln!(self.out, "{}storageBarrier();", level)?;
+                    }
+                    if memory.contains(crate::Barrier::WORK_GROUP) || memory.is_empty() {
+                        writeln!(self.out, "{}workgroupBarrier();", level)?;
+                    }
+                }
+            }
+            crate::Statement::Subgroup { ref fun, result } => {
+                self.start_baking_expression(level, result, context)?;
+                self.name_expression(result);
+                match *fun {
+                    crate::SubgroupFunction::Elect => {
+                        write!(self.out, "subgroupElect()")?;
+                    }
+                    crate::SubgroupFunction::Ballot { predicate } => {
+                        write!(self.out, "subgroupBallot(")?;
+                        if let Some(predicate) = predicate {
+                            self.put_expression(predicate, context)?;
+                        }
+                        write!(self.out, ")")?;
+                    }
+                    crate::SubgroupFunction::Gather { mode, argument } => {
+                        let (name, index) = match mode {
+                            crate::GatherMode::BroadcastFirst => ("subgroupBroadcastFirst", None),
+                            crate::GatherMode::Broadcast(index) => {
+                                ("subgroupBroadcast", Some(index))
+                            }
+                            crate::GatherMode::Shuffle(index) => ("subgroupShuffle", Some(index)),
+                            crate::GatherMode::ShuffleDown(index) => {
+                                ("subgroupShuffleDown", Some(index))
+                            }
+                            crate::GatherMode::ShuffleUp(index) => {
+                                ("subgroupShuffleUp", Some(index))
+                            }
+                            crate::GatherMode::ShuffleXor(index) => {
+                                ("subgroupShuffleXor", Some(index))
+                            }
+                        };
+                        write!(self.out, "{}(", name)?;
+                        self.put_expression(argument, context)?;
+                        if let Some(index) = index {
+                            write!(self.out, ", ")?;
+                            self.put_expression(index, context)?;
+                        }
+                        write!(self.out, ")")?;
+                    }
+                    crate::SubgroupFunction::Collective {
+                        op,
+                        collective,
+                        argument,
+                    } => {
+                        write!(self.out, "{}(", subgroup_operation_string(op, collective))?;
+                        self.put_expression(argument, context)?;
+                        write!(self.out, ")")?;
+                    }
+                }
+                writeln!(self.out, ";")?;
+            }
+            crate::Statement::EmitVertex
+            | crate::Statement::EndPrimitive
+            | crate::Statement::TraceRay { .. }
+            | crate::Statement::ReportIntersection { .. }
+            | crate::Statement::IgnoreIntersection
+            | crate::Statement::TerminateRay => {
+                return Err(Error::UnsupportedFeature(format!(
+                    "statement {:?}",
+                    statement
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn put_call_parameters(
+        &mut self,
+        parameters: impl Iterator<Item = Handle<crate::Expression>>,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        write!(self.out, "(")?;
+        for (i, handle) in parameters.enumerate() {
+            if i != 0 {
+                write!(self.out, ", ")?;
+            }
+            self.put_expression(handle, context)?;
+        }
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
+    fn put_expression(
+        &mut self,
+        expr_handle: Handle<crate::Expression>,
+        context: &FunctionContext,
+    ) -> Result<(), Error> {
+        if let Some(&index) = self.named_expressions.get(&expr_handle) {
+            write!(self.out, "{}{}", BAKE_PREFIX, index)?;
+            return Ok(());
+        }
+
+        let module = context.module;
+        let expression = &context.function.expressions[expr_handle];
+        log::trace!("expression {:?} = {:?}", expr_handle, expression);
+        match *expression {
+            crate::Expression::Access { base, index } => {
+                self.put_expression(base, context)?;
+                write!(self.out, "[")?;
+                self.put_expression(index, context)?;
+                write!(self.out, "]")?;
+            }
+            crate::Expression::AccessIndex { base, index } => {
+                self.put_expression(base, context)?;
+                let mut resolved = self.typifier.get(base, &module.types);
+                let base_ty_handle = match *resolved {
+                    crate::TypeInner::Pointer { base, class: _ } => {
+                        resolved = &module.types[base].inner;
+                        Ok(base)
+                    }
+                    _ => self.typifier.get_handle(base),
+                };
+                match *resolved {
+                    crate::TypeInner::Struct { .. } => {
+                        let base_ty = base_ty_handle.map_err(|_| Error::Validation)?;
+                        let name = &self.names[&NameKey::StructMember(base_ty, index)];
+                        write!(self.out, ".{}", name)?;
+                    }
+                    // matrix columns are selected like vector components
+                    crate::TypeInner::ValuePointer { .. }
+                    | crate::TypeInner::Vector { .. }
+                    | crate::TypeInner::Matrix { .. } => {
+                        write!(self.out, ".{}", COMPONENTS[index as usize])?;
+                    }
+                    crate::TypeInner::Array { .. } => {
+                        write!(self.out, "[{}]", index)?;
+                    }
+                    _ => return Err(Error::Validation),
+                }
+            }
+            crate::Expression::Constant(handle) => {
+                self.write_constant(module, handle)?;
+            }
+            crate::Expression::Compose { ty, ref components } => {
+                self.write_type(module, ty)?;
+                self.put_call_parameters(components.iter().cloned(), context)?;
+            }
+            crate::Expression::FunctionArgument(index) => {
+                let name = &self.names[&context.argument_key(index)];
+                write!(self.out, "{}", name)?;
+            }
+            crate::Expression::GlobalVariable(handle) => {
+                let name = &self.names[&NameKey::GlobalVariable(handle)];
+                write!(self.out, "{}", name)?;
+            }
+            crate::Expression::LocalVariable(handle) => {
+                let name = &self.names[&context.local_key(handle)];
+                write!(self.out, "{}", name)?;
+            }
+            crate::Expression::Load { pointer } => {
+                if self.is_atomic_pointer(pointer, context) {
+                    write!(self.out, "atomicLoad(&")?;
+                    self.put_expression(pointer, context)?;
+                    write!(self.out, ")")?;
+                } else {
+                    // loads are implicit
+                    self.put_expression(pointer, context)?;
+                }
+            }
+            crate::Expression::ImageSample {
+                image,
+                sampler,
+                coordinate,
+                array_index,
+                offset,
+                level,
+                depth_ref,
+            } => {
+                let fun_name = match (depth_ref, level) {
+                    (Some(_), crate::SampleLevel::Auto) | (Some(_), crate::SampleLevel::Zero) => {
+                        "textureSampleCompare"
+                    }
+                    (Some(_), _) => {
+                        return Err(Error::UnsupportedFeature(format!(
+                            "depth comparison with sample level {:?}",
+                            level
+                        )))
+                    }
+                    (None, crate::SampleLevel::Auto) => "textureSample",
+                    (None, crate::SampleLevel::Zero) | (None, crate::SampleLevel::Exact(_)) => {
+                        "textureSampleLevel"
+                    }
+                    (None, crate::SampleLevel::Bias(_)) => "textureSampleBias",
+                    (None, crate::SampleLevel::Gradient { .. }) => "textureSampleGrad",
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.put_expression(image, context)?;
+                write!(self.out, ", ")?;
+                self.put_expression(sampler, context)?;
+                write!(self.out, ", ")?;
+                self.put_expression(coordinate, context)?;
+                if let Some(index) = array_index {
+                    write!(self.out, ", ")?;
+                    self.put_expression(index, context)?;
+                }
+                if let Some(depth_ref) = depth_ref {
+                    write!(self.out, ", ")?;
+                    self.put_expression(depth_ref, context)?;
+                }
+                match level {
+                    crate::SampleLevel::Auto => {}
+                    crate::SampleLevel::Zero => {
+                        if depth_ref.is_none() {
+                            write!(self.out, ", 0.0")?;
+                        }
+                    }
+                    crate::SampleLevel::Exact(expr) | crate::SampleLevel::Bias(expr) => {
+                        write!(self.out, ", ")?;
+                        self.put_expression(expr, context)?;
+                    }
+                    crate::SampleLevel::Gradient { x, y } => {
+                        write!(self.out, ", ")?;
+                        self.put_expression(x, context)?;
+                        write!(self.out, ", ")?;
+                        self.put_expression(y, context)?;
+                    }
+                }
+                if let Some(offset) = offset {
+                    write!(self.out, ", ")?;
+                    self.write_constant(module, offset)?;
+                }
+                write!(self.out, ")")?;
+            }
+            crate::Expression::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index,
+            } => {
+                write!(self.out, "textureLoad(")?;
+                self.put_expression(image, context)?;
+                write!(self.out, ", ")?;
+                self.put_expression(coordinate, context)?;
+                if let Some(array_index) = array_index {
+                    write!(self.out, ", ")?;
+                    self.put_expression(array_index, context)?;
+                }
+                if let Some(index) = index {
+                    write!(self.out, ", ")?;
+                    self.put_expression(index, context)?;
+                }
+                write!(self.out, ")")?;
+            }
+            crate::Expression::ImageQuery { image, query } => {
+                let (fun_name, level) = match query {
+                    crate::ImageQuery::Size { level } => ("textureDimensions", level),
+                    crate::ImageQuery::NumLevels => ("textureNumLevels", None),
+                    crate::ImageQuery::NumLayers => ("textureNumLayers", None),
+                    crate::ImageQuery::NumSamples => ("textureNumSamples", None),
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.put_expression(image, context)?;
+                if let Some(level) = level {
+                    write!(self.out, ", ")?;
+                    self.put_expression(level, context)?;
+                }
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Unary { op, expr } => {
+                let op_str = match op {
+                    crate::UnaryOperator::Negate => "-",
+                    crate::UnaryOperator::Not => "!",
+                };
+                write!(self.out, "{}", op_str)?;
+                self.put_expression(expr, context)?;
+            }
+            crate::Expression::Binary { op, left, right } => {
+                let op_str = match op {
+                    crate::BinaryOperator::Add => "+",
+                    crate::BinaryOperator::Subtract => "-",
+                    crate::BinaryOperator::Multiply => "*",
+                    crate::BinaryOperator::Divide => "/",
+                    crate::BinaryOperator::Modulo => "%",
+                    crate::BinaryOperator::Equal => "==",
+                    crate::BinaryOperator::NotEqual => "!=",
+                    crate::BinaryOperator::Less => "<",
+                    crate::BinaryOperator::LessEqual => "<=",
+                    crate::BinaryOperator::Greater => ">",
+                    crate::BinaryOperator::GreaterEqual => ">=",
+                    crate::BinaryOperator::And => "&",
+                    crate::BinaryOperator::ExclusiveOr => "^",
+                    crate::BinaryOperator::InclusiveOr => "|",
+                    crate::BinaryOperator::LogicalAnd => "&&",
+                    crate::BinaryOperator::LogicalOr => "||",
+                    crate::BinaryOperator::ShiftLeft => "<<",
+                    crate::BinaryOperator::ShiftRight => ">>",
+                };
+                write!(self.out, "(")?;
+                self.put_expression(left, context)?;
+                write!(self.out, " {} ", op_str)?;
+                self.put_expression(right, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                write!(self.out, "select(")?;
+                self.put_expression(accept, context)?;
+                write!(self.out, ", ")?;
+                self.put_expression(reject, context)?;
+                write!(self.out, ", ")?;
+                self.put_expression(condition, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Derivative { axis, expr } => {
+                let fun_name = match axis {
+                    crate::DerivativeAxis::X => "dpdx",
+                    crate::DerivativeAxis::Y => "dpdy",
+                    crate::DerivativeAxis::Width => "dwidth",
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.put_expression(expr, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Relational { fun, argument } => {
+                let fun_name = match fun {
+                    crate::RelationalFunction::All => "all",
+                    crate::RelationalFunction::Any => "any",
+                    crate::RelationalFunction::IsNan => "isNan",
+                    crate::RelationalFunction::IsInf => "isInf",
+                    crate::RelationalFunction::IsFinite => "isFinite",
+                    crate::RelationalFunction::IsNormal => "isNormal",
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.put_expression(argument, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+            } => {
+                write!(self.out, "{}", math_function_string(fun)?)?;
+                let arguments = std::iter::once(arg).chain(arg1).chain(arg2);
+                self.put_call_parameters(arguments, context)?;
+            }
+            crate::Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let target = match *self.typifier.get(expr, &module.types) {
+                    crate::TypeInner::Scalar { kind: _, width } => {
+                        crate::TypeInner::Scalar { kind, width }
+                    }
+                    crate::TypeInner::Vector {
+                        size,
+                        kind: _,
+                        width,
+                    } => crate::TypeInner::Vector { size, kind, width },
+                    _ => return Err(Error::Validation),
+                };
+                if convert {
+                    self.write_type_inner(module, &target)?;
+                } else {
+                    write!(self.out, "bitcast<")?;
+                    self.write_type_inner(module, &target)?;
+                    write!(self.out, ">")?;
+                }
+                write!(self.out, "(")?;
+                self.put_expression(expr, context)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::ArrayLength(expr) => {
+                write!(self.out, "arrayLength(")?;
+                self.put_expression(expr, context)?;
+                write!(self.out, ")")?;
+            }
+            // results of statements are always baked
+            crate::Expression::Call(_)
+            | crate::Expression::AtomicResult { .. }
+            | crate::Expression::SubgroupResult { .. } => return Err(Error::Validation),
+            crate::Expression::ReportIntersectionResult => {
+                return Err(Error::UnsupportedFeature("ray tracing".to_string()))
+            }
+        }
+        Ok(())
+    }
+}
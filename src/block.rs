@@ -0,0 +1,181 @@
+use crate::{Span, Statement};
+use std::ops::{Deref, DerefMut};
+
+/// A code block is a vector of statements, with a source span for each of them.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+#[cfg_attr(
+    any(feature = "serialize", feature = "deserialize"),
+    serde(transparent)
+)]
+pub struct Block {
+    body: Vec<Statement>,
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(skip))]
+    span_info: Vec<Span>,
+}
+
+impl Block {
+    /// Create a new empty block.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new empty block with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Block {
+            body: Vec::with_capacity(capacity),
+            span_info: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Create a block out of statements with unknown spans.
+    pub fn from_vec(body: Vec<Statement>) -> Self {
+        let span_info = vec![Span::UNDEFINED; body.len()];
+        Block { body, span_info }
+    }
+
+    /// Add a statement with an unknown span to the end of the block.
+    pub fn push(&mut self, statement: Statement) {
+        self.push_with_span(statement, Span::UNDEFINED);
+    }
+
+    /// Give an unknown span to the statements missing one, which is the case
+    /// of all of them after deserialization.
+    fn pad_spans(&mut self) {
+        if self.span_info.len() < self.body.len() {
+            self.span_info.resize(self.body.len(), Span::UNDEFINED);
+        }
+    }
+
+    /// Add a statement to the end of the block.
+    pub fn push_with_span(&mut self, statement: Statement, span: Span) {
+        self.pad_spans();
+        self.body.push(statement);
+        self.span_info.push(span);
+    }
+
+    /// Add statements with unknown spans to the end of the block.
+    pub fn extend<I: IntoIterator<Item = Statement>>(&mut self, statements: I) {
+        for statement in statements {
+            self.push(statement);
+        }
+    }
+
+    /// Add the statements of `other` to the end of this block, keeping their spans.
+    pub fn extend_block(&mut self, mut other: Self) {
+        self.pad_spans();
+        other.pad_spans();
+        self.body.extend(other.body);
+        self.span_info.extend(other.span_info);
+    }
+
+    /// Move all the statements of `other` to the end of this block.
+    pub fn append(&mut self, other: &mut Self) {
+        self.pad_spans();
+        other.pad_spans();
+        self.body.append(&mut other.body);
+        self.span_info.append(&mut other.span_info);
+    }
+
+    /// Insert a statement with an unknown span at the given position.
+    pub fn insert(&mut self, index: usize, statement: Statement) {
+        self.pad_spans();
+        self.body.insert(index, statement);
+        self.span_info.insert(index, Span::UNDEFINED);
+    }
+
+    /// Remove the last statement of the block.
+    pub fn pop(&mut self) -> Option<Statement> {
+        self.pad_spans();
+        self.span_info.pop();
+        self.body.pop()
+    }
+
+    /// Keep only the statements for which `predicate` returns `true`,
+    /// together with their spans.
+    pub(crate) fn retain_mut<P: FnMut(&mut Statement) -> bool>(&mut self, mut predicate: P) {
+        self.pad_spans();
+        let body = std::mem::take(&mut self.body);
+        let span_info = std::mem::take(&mut self.span_info);
+        for (mut statement, span) in body.into_iter().zip(span_info) {
+            if predicate(&mut statement) {
+                self.push_with_span(statement, span);
+            }
+        }
+    }
+
+    /// Returns the span of the statement at `index`.
+    pub fn get_span(&self, index: usize) -> Span {
+        self.span_info.get(index).cloned().unwrap_or_default()
+    }
+
+    /// Assign a span to all the statements starting from `index`,
+    /// which don't have one yet.
+    pub fn fill_spans_from(&mut self, index: usize, span: Span) {
+        self.pad_spans();
+        for info in self.span_info[index..].iter_mut() {
+            if !info.is_defined() {
+                *info = span;
+            }
+        }
+    }
+
+    /// Returns an iterator over the statements, together with their spans.
+    pub fn span_iter(&self) -> impl Iterator<Item = (&Statement, Span)> {
+        let span_info = &self.span_info;
+        self.body.iter().enumerate().map(move |(index, statement)| {
+            (statement, span_info.get(index).cloned().unwrap_or_default())
+        })
+    }
+}
+
+impl Deref for Block {
+    type Target = [Statement];
+    fn deref(&self) -> &[Statement] {
+        &self.body
+    }
+}
+
+impl DerefMut for Block {
+    fn deref_mut(&mut self) -> &mut [Statement] {
+        &mut self.body
+    }
+}
+
+impl From<Vec<Statement>> for Block {
+    fn from(body: Vec<Statement>) -> Self {
+        Self::from_vec(body)
+    }
+}
+
+impl IntoIterator for Block {
+    type Item = Statement;
+    type IntoIter = std::vec::IntoIter<Statement>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.body.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Block {
+    type Item = &'a Statement;
+    type IntoIter = std::slice::Iter<'a, Statement>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.body.iter()
+    }
+}
+
+#[cfg(all(test, feature = "deserialize"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_deserialized_spans() {
+        let mut block: Block = ron::de::from_str("[Kill, Return(value: None)]").unwrap();
+        let span = Span::new(4, 8);
+        block.fill_spans_from(1, span);
+        block.insert(0, Statement::Kill);
+        let spans = block.span_iter().map(|(_, span)| span).collect::<Vec<_>>();
+        assert_eq!(spans, [Span::UNDEFINED, Span::UNDEFINED, span]);
+    }
+}
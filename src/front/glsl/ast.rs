@@ -1,9 +1,10 @@
-use super::{constants::ConstantSolver, error::ErrorKind};
+use super::error::ErrorKind;
 use crate::{
-    proc::{ResolveContext, Typifier},
+    proc::{ConstantEvaluator, ResolveContext, Typifier},
     Arena, BinaryOperator, Binding, Constant, Expression, FastHashMap, Function, FunctionArgument,
-    GlobalVariable, Handle, Interpolation, LocalVariable, Module, RelationalFunction, ShaderStage,
-    Statement, StorageClass, Type, UnaryOperator,
+    GeometryInput, GeometryOutput, GlobalVariable, Handle, Interpolation, LocalVariable, Module,
+    RelationalFunction, ShaderStage, Statement, StorageClass, TessellationPrimitive,
+    TessellationSpacing, Type, UnaryOperator, VertexOrder,
 };
 
 #[derive(Debug)]
@@ -15,6 +16,10 @@ pub struct Program<'a> {
     pub lookup_type: FastHashMap<String, Handle<Type>>,
     pub lookup_global_variables: FastHashMap<String, Handle<GlobalVariable>>,
     pub lookup_constants: FastHashMap<String, Handle<Constant>>,
+    /// Ray payloads by their location, as used by `traceRayEXT`
+    pub ray_payloads: FastHashMap<u32, Handle<GlobalVariable>>,
+    pub input_layout: PrimitiveLayout,
+    pub output_layout: PrimitiveLayout,
     pub context: Context,
     pub module: Module,
 }
@@ -29,6 +34,9 @@ impl<'a> Program<'a> {
             lookup_type: FastHashMap::default(),
             lookup_global_variables: FastHashMap::default(),
             lookup_constants: FastHashMap::default(),
+            ray_payloads: FastHashMap::default(),
+            input_layout: PrimitiveLayout::default(),
+            output_layout: PrimitiveLayout::default(),
             context: Context {
                 expressions: Arena::<Expression>::new(),
                 local_variables: Arena::<LocalVariable>::new(),
@@ -36,6 +44,8 @@ impl<'a> Program<'a> {
                 scopes: vec![FastHashMap::default()],
                 lookup_global_var_exps: FastHashMap::default(),
                 lookup_constant_exps: FastHashMap::default(),
+                per_vertex_blocks: FastHashMap::default(),
+                per_vertex_indices: FastHashMap::default(),
                 typifier: Typifier::new(),
             },
             module: Module::default(),
@@ -47,19 +57,44 @@ impl<'a> Program<'a> {
         op: BinaryOperator,
         left: &ExpressionRule,
         right: &ExpressionRule,
-    ) -> ExpressionRule {
-        ExpressionRule::from_expression(self.context.expressions.append(Expression::Binary {
-            op,
-            left: left.expression,
-            right: right.expression,
-        }))
+    ) -> Result<ExpressionRule, ErrorKind> {
+        let left = self.rvalue(left.clone())?;
+        let right = self.rvalue(right.clone())?;
+        let mut statements = left.statements;
+        statements.extend(right.statements);
+        let expression = self.context.add_expression(
+            Expression::Binary {
+                op,
+                left: left.expression,
+                right: right.expression,
+            },
+            &mut statements,
+        );
+        Ok(ExpressionRule {
+            expression,
+            statements,
+            sampler: None,
+        })
     }
 
-    pub fn unary_expr(&mut self, op: UnaryOperator, tgt: &ExpressionRule) -> ExpressionRule {
-        ExpressionRule::from_expression(self.context.expressions.append(Expression::Unary {
-            op,
-            expr: tgt.expression,
-        }))
+    pub fn unary_expr(
+        &mut self,
+        op: UnaryOperator,
+        tgt: &ExpressionRule,
+    ) -> Result<ExpressionRule, ErrorKind> {
+        let mut tgt = self.rvalue(tgt.clone())?;
+        let expression = self.context.add_expression(
+            Expression::Unary {
+                op,
+                expr: tgt.expression,
+            },
+            &mut tgt.statements,
+        );
+        Ok(ExpressionRule {
+            expression,
+            statements: tgt.statements,
+            sampler: None,
+        })
     }
 
     /// Helper function to insert equality expressions, this handles the special
@@ -71,6 +106,8 @@ impl<'a> Program<'a> {
         left: &ExpressionRule,
         right: &ExpressionRule,
     ) -> Result<ExpressionRule, ErrorKind> {
+        let left = self.rvalue(left.clone())?;
+        let right = self.rvalue(right.clone())?;
         let left_is_vector = match self.resolve_type(left.expression)? {
             crate::TypeInner::Vector { .. } => true,
             _ => false,
@@ -86,23 +123,35 @@ impl<'a> Program<'a> {
             false => (BinaryOperator::NotEqual, RelationalFunction::Any),
         };
 
-        let expr =
-            ExpressionRule::from_expression(self.context.expressions.append(Expression::Binary {
-                op,
-                left: left.expression,
-                right: right.expression,
-            }));
+        let mut expr = self.binary_expr(op, &left, &right)?;
 
-        Ok(if left_is_vector && right_is_vector {
-            ExpressionRule::from_expression(self.context.expressions.append(
+        if left_is_vector && right_is_vector {
+            expr.expression = self.context.add_expression(
                 Expression::Relational {
                     fun,
                     argument: expr.expression,
                 },
-            ))
-        } else {
-            expr
-        })
+                &mut expr.statements,
+            );
+        }
+        Ok(expr)
+    }
+
+    /// Load the value behind `rule` if it evaluates to a pointer,
+    /// so that it can be used where a value is expected.
+    pub fn rvalue(&mut self, mut rule: ExpressionRule) -> Result<ExpressionRule, ErrorKind> {
+        match *self.resolve_type(rule.expression)? {
+            crate::TypeInner::Pointer { .. } | crate::TypeInner::ValuePointer { .. } => {
+                rule.expression = self.context.add_expression(
+                    Expression::Load {
+                        pointer: rule.expression,
+                    },
+                    &mut rule.statements,
+                );
+            }
+            _ => {}
+        }
+        Ok(rule)
     }
 
     pub fn resolve_type(
@@ -134,15 +183,15 @@ impl<'a> Program<'a> {
         &mut self,
         root: Handle<Expression>,
     ) -> Result<Handle<Constant>, ErrorKind> {
-        let mut solver = ConstantSolver {
-            types: &self.module.types,
+        let mut evaluator = ConstantEvaluator {
+            types: &mut self.module.types,
             expressions: &self.context.expressions,
             constants: &mut self.module.constants,
         };
 
-        solver
-            .solve(root)
-            .map_err(|_| ErrorKind::SemanticError("Can't solve constant".into()))
+        evaluator.evaluate(root).map_err(|error| {
+            ErrorKind::SemanticError(format!("Can't solve constant: {}", error).into())
+        })
     }
 }
 
@@ -160,6 +209,11 @@ pub struct Context {
     pub scopes: Vec<FastHashMap<String, Handle<Expression>>>,
     pub lookup_global_var_exps: FastHashMap<String, Handle<Expression>>,
     pub lookup_constant_exps: FastHashMap<String, Handle<Expression>>,
+    /// Placeholder expressions standing for the `gl_in` and `gl_out` blocks.
+    pub per_vertex_blocks: FastHashMap<Handle<Expression>, StorageClass>,
+    /// Index expressions applied to one of the `per_vertex_blocks`,
+    /// waiting for the member to be selected.
+    pub per_vertex_indices: FastHashMap<Handle<Expression>, StorageClass>,
     pub typifier: Typifier,
 }
 
@@ -202,9 +256,25 @@ impl Context {
     pub fn remove_current_scope(&mut self) {
         self.scopes.pop();
     }
+
+    /// Append an expression, pushing a `Statement::Emit` for it to `statements`
+    /// unless the expression is implicitly in scope.
+    pub fn add_expression(
+        &mut self,
+        expression: Expression,
+        statements: &mut Vec<Statement>,
+    ) -> Handle<Expression> {
+        let needs_emit = !expression.needs_pre_emit();
+        let start_len = self.expressions.len();
+        let handle = self.expressions.append(expression);
+        if needs_emit {
+            statements.push(Statement::Emit(self.expressions.range_from(start_len)));
+        }
+        handle
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ExpressionRule {
     pub expression: Handle<Expression>,
     pub statements: Vec<Statement>,
@@ -221,11 +291,22 @@ impl ExpressionRule {
     }
 }
 
+/// Prefix `statement` with the `statements` evaluating its operands.
+pub fn with_statements(mut statements: Vec<Statement>, statement: Statement) -> Statement {
+    if statements.is_empty() {
+        statement
+    } else {
+        statements.push(statement);
+        Statement::Block(statements.into())
+    }
+}
+
 #[derive(Debug)]
 pub enum TypeQualifier {
     StorageQualifier(StorageQualifier),
     Binding(Binding),
     Interpolation(Interpolation),
+    Primitive(PrimitiveLayout),
 }
 
 #[derive(Debug)]
@@ -257,4 +338,116 @@ pub enum StorageQualifier {
 pub enum StructLayout {
     Binding(Binding),
     PushConstant,
+    Primitive(PrimitiveLayout),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PrimitiveKind {
+    Points,
+    Lines,
+    LinesAdjacency,
+    Triangles,
+    TrianglesAdjacency,
+    LineStrip,
+    TriangleStrip,
+    Quads,
+    Isolines,
+}
+
+impl PrimitiveKind {
+    pub fn geometry_input(self) -> Option<GeometryInput> {
+        Some(match self {
+            PrimitiveKind::Points => GeometryInput::Points,
+            PrimitiveKind::Lines => GeometryInput::Lines,
+            PrimitiveKind::LinesAdjacency => GeometryInput::LinesAdjacency,
+            PrimitiveKind::Triangles => GeometryInput::Triangles,
+            PrimitiveKind::TrianglesAdjacency => GeometryInput::TrianglesAdjacency,
+            _ => return None,
+        })
+    }
+
+    pub fn geometry_output(self) -> Option<GeometryOutput> {
+        Some(match self {
+            PrimitiveKind::Points => GeometryOutput::Points,
+            PrimitiveKind::LineStrip => GeometryOutput::LineStrip,
+            PrimitiveKind::TriangleStrip => GeometryOutput::TriangleStrip,
+            _ => return None,
+        })
+    }
+
+    pub fn tessellation_primitive(self) -> Option<TessellationPrimitive> {
+        Some(match self {
+            PrimitiveKind::Triangles => TessellationPrimitive::Triangles,
+            PrimitiveKind::Quads => TessellationPrimitive::Quads,
+            PrimitiveKind::Isolines => TessellationPrimitive::Isolines,
+            _ => return None,
+        })
+    }
+}
+
+/// Geometry and tessellation shader layout,
+/// as in `layout(triangle_strip, max_vertices = 3) out;`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrimitiveLayout {
+    pub kind: Option<PrimitiveKind>,
+    pub max_vertices: Option<u32>,
+    pub output_vertices: Option<u32>,
+    pub spacing: Option<TessellationSpacing>,
+    pub vertex_order: Option<VertexOrder>,
+}
+
+impl PrimitiveLayout {
+    /// Returns `None` if any of the layout ids is not a primitive one.
+    pub fn from_ids(ids: &[(String, u32)]) -> Option<Self> {
+        let mut layout = PrimitiveLayout::default();
+        for &(ref id, value) in ids {
+            let kind = match id.as_str() {
+                "points" => PrimitiveKind::Points,
+                "lines" => PrimitiveKind::Lines,
+                "lines_adjacency" => PrimitiveKind::LinesAdjacency,
+                "triangles" => PrimitiveKind::Triangles,
+                "triangles_adjacency" => PrimitiveKind::TrianglesAdjacency,
+                "line_strip" => PrimitiveKind::LineStrip,
+                "triangle_strip" => PrimitiveKind::TriangleStrip,
+                "quads" => PrimitiveKind::Quads,
+                "isolines" => PrimitiveKind::Isolines,
+                "max_vertices" => {
+                    layout.max_vertices = Some(value);
+                    continue;
+                }
+                "vertices" => {
+                    layout.output_vertices = Some(value);
+                    continue;
+                }
+                "equal_spacing" | "fractional_even_spacing" | "fractional_odd_spacing" => {
+                    layout.spacing = Some(match id.as_str() {
+                        "equal_spacing" => TessellationSpacing::Equal,
+                        "fractional_even_spacing" => TessellationSpacing::FractionalEven,
+                        _ => TessellationSpacing::FractionalOdd,
+                    });
+                    continue;
+                }
+                "cw" | "ccw" => {
+                    layout.vertex_order = Some(if id == "cw" {
+                        VertexOrder::Clockwise
+                    } else {
+                        VertexOrder::CounterClockwise
+                    });
+                    continue;
+                }
+                _ => return None,
+            };
+            layout.kind = Some(kind);
+        }
+        Some(layout)
+    }
+
+    /// Merges the qualifiers of another declaration into this one.
+    pub fn merge(&mut self, other: &Self) {
+        self.kind = other.kind.or(self.kind);
+        self.max_vertices = other.max_vertices.or(self.max_vertices);
+        self.output_vertices = other.output_vertices.or(self.output_vertices);
+        self.spacing = other.spacing.or(self.spacing);
+        self.vertex_order = other.vertex_order.or(self.vertex_order);
+    }
 }
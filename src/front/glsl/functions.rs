@@ -1,7 +1,9 @@
 use crate::{
     proc::{ensure_block_returns, Typifier},
-    BinaryOperator, Block, EntryPoint, Expression, Function, MathFunction, RelationalFunction,
-    SampleLevel, TypeInner,
+    Barrier, BinaryOperator, Block, CollectiveOperation, Constant, ConstantInner, EntryPoint,
+    Expression, Function, GatherMode, GeometryInfo, MathFunction, RelationalFunction, SampleLevel,
+    ScalarKind, ScalarValue, Scope, ShaderStage, StorageClass, SubgroupFunction, SubgroupOperation,
+    TessellationInfo, TessellationSpacing, TypeInner, VectorSize, VertexOrder,
 };
 
 use super::{ast::*, error::ErrorKind};
@@ -10,29 +12,31 @@ impl Program<'_> {
     pub fn function_call(&mut self, fc: FunctionCall) -> Result<ExpressionRule, ErrorKind> {
         match fc.kind {
             FunctionCallKind::TypeConstructor(ty) => {
-                let h = if fc.args.len() == 1 {
+                let args = fc
+                    .args
+                    .into_iter()
+                    .map(|a| self.rvalue(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let expression = if args.len() == 1 {
                     let kind = self.module.types[ty].inner.scalar_kind().ok_or_else(|| {
                         ErrorKind::SemanticError("Can only cast to scalar or vector".into())
                     })?;
-                    self.context.expressions.append(Expression::As {
+                    Expression::As {
                         kind,
-                        expr: fc.args[0].expression,
+                        expr: args[0].expression,
                         convert: true,
-                    })
+                    }
                 } else {
-                    self.context.expressions.append(Expression::Compose {
+                    Expression::Compose {
                         ty,
-                        components: fc.args.iter().map(|a| a.expression).collect(),
-                    })
+                        components: args.iter().map(|a| a.expression).collect(),
+                    }
                 };
+                let mut statements: Vec<_> = args.into_iter().flat_map(|a| a.statements).collect();
+                let h = self.context.add_expression(expression, &mut statements);
                 Ok(ExpressionRule {
                     expression: h,
-                    statements: fc
-                        .args
-                        .into_iter()
-                        .map(|a| a.statements)
-                        .flatten()
-                        .collect(),
+                    statements,
                     sampler: None,
                 })
             }
@@ -58,26 +62,16 @@ impl Program<'_> {
                             return Err(ErrorKind::WrongNumberArgs(name, 2, fc.args.len()));
                         }
                         if let Some(sampler) = fc.args[0].sampler {
-                            Ok(ExpressionRule {
-                                expression: self.context.expressions.append(
-                                    Expression::ImageSample {
-                                        image: fc.args[0].expression,
-                                        sampler,
-                                        coordinate: fc.args[1].expression,
-                                        array_index: None, //TODO
-                                        offset: None,      //TODO
-                                        level: SampleLevel::Auto,
-                                        depth_ref: None,
-                                    },
-                                ),
-                                sampler: None,
-                                statements: fc
-                                    .args
-                                    .into_iter()
-                                    .map(|a| a.statements)
-                                    .flatten()
-                                    .collect(),
-                            })
+                            let expression = Expression::ImageSample {
+                                image: fc.args[0].expression,
+                                sampler,
+                                coordinate: fc.args[1].expression,
+                                array_index: None, //TODO
+                                offset: None,      //TODO
+                                level: SampleLevel::Auto,
+                                depth_ref: None,
+                            };
+                            Ok(self.call_result(expression, fc.args))
                         } else {
                             Err(ErrorKind::SemanticError("Bad call to texture".into()))
                         }
@@ -88,109 +82,280 @@ impl Program<'_> {
                         if fc.args.len() != 1 {
                             return Err(ErrorKind::WrongNumberArgs(name, 1, fc.args.len()));
                         }
-                        Ok(ExpressionRule {
-                            expression: self.context.expressions.append(Expression::Math {
-                                fun: match name.as_str() {
-                                    "ceil" => MathFunction::Ceil,
-                                    "round" => MathFunction::Round,
-                                    "floor" => MathFunction::Floor,
-                                    "fract" => MathFunction::Fract,
-                                    "trunc" => MathFunction::Trunc,
-                                    "sin" => MathFunction::Sin,
-                                    "abs" => MathFunction::Abs,
-                                    "sqrt" => MathFunction::Sqrt,
-                                    "inversesqrt" => MathFunction::InverseSqrt,
-                                    "exp" => MathFunction::Exp,
-                                    "exp2" => MathFunction::Exp2,
-                                    "sign" => MathFunction::Sign,
-                                    "transpose" => MathFunction::Transpose,
-                                    "inverse" => MathFunction::Inverse,
-                                    "normalize" => MathFunction::Normalize,
-                                    _ => unreachable!(),
-                                },
-                                arg: fc.args[0].expression,
-                                arg1: None,
-                                arg2: None,
-                            }),
-                            sampler: None,
-                            statements: fc.args.into_iter().flat_map(|a| a.statements).collect(),
-                        })
+                        let expression = Expression::Math {
+                            fun: match name.as_str() {
+                                "ceil" => MathFunction::Ceil,
+                                "round" => MathFunction::Round,
+                                "floor" => MathFunction::Floor,
+                                "fract" => MathFunction::Fract,
+                                "trunc" => MathFunction::Trunc,
+                                "sin" => MathFunction::Sin,
+                                "abs" => MathFunction::Abs,
+                                "sqrt" => MathFunction::Sqrt,
+                                "inversesqrt" => MathFunction::InverseSqrt,
+                                "exp" => MathFunction::Exp,
+                                "exp2" => MathFunction::Exp2,
+                                "sign" => MathFunction::Sign,
+                                "transpose" => MathFunction::Transpose,
+                                "inverse" => MathFunction::Inverse,
+                                "normalize" => MathFunction::Normalize,
+                                _ => unreachable!(),
+                            },
+                            arg: fc.args[0].expression,
+                            arg1: None,
+                            arg2: None,
+                        };
+                        Ok(self.call_result(expression, fc.args))
                     }
                     "pow" | "dot" | "max" => {
                         if fc.args.len() != 2 {
                             return Err(ErrorKind::WrongNumberArgs(name, 2, fc.args.len()));
                         }
-                        Ok(ExpressionRule {
-                            expression: self.context.expressions.append(Expression::Math {
-                                fun: match name.as_str() {
-                                    "pow" => MathFunction::Pow,
-                                    "dot" => MathFunction::Dot,
-                                    "max" => MathFunction::Max,
-                                    _ => unreachable!(),
-                                },
-                                arg: fc.args[0].expression,
-                                arg1: Some(fc.args[1].expression),
-                                arg2: None,
-                            }),
-                            sampler: None,
-                            statements: fc.args.into_iter().flat_map(|a| a.statements).collect(),
-                        })
+                        let expression = Expression::Math {
+                            fun: match name.as_str() {
+                                "pow" => MathFunction::Pow,
+                                "dot" => MathFunction::Dot,
+                                "max" => MathFunction::Max,
+                                _ => unreachable!(),
+                            },
+                            arg: fc.args[0].expression,
+                            arg1: Some(fc.args[1].expression),
+                            arg2: None,
+                        };
+                        Ok(self.call_result(expression, fc.args))
                     }
                     "mix" | "clamp" => {
                         if fc.args.len() != 3 {
                             return Err(ErrorKind::WrongNumberArgs(name, 3, fc.args.len()));
                         }
-                        Ok(ExpressionRule {
-                            expression: self.context.expressions.append(Expression::Math {
-                                fun: match name.as_str() {
-                                    "mix" => MathFunction::Mix,
-                                    "clamp" => MathFunction::Clamp,
-                                    _ => unreachable!(),
-                                },
-                                arg: fc.args[0].expression,
-                                arg1: Some(fc.args[1].expression),
-                                arg2: Some(fc.args[2].expression),
-                            }),
-                            sampler: None,
-                            statements: fc.args.into_iter().flat_map(|a| a.statements).collect(),
-                        })
+                        let expression = Expression::Math {
+                            fun: match name.as_str() {
+                                "mix" => MathFunction::Mix,
+                                "clamp" => MathFunction::Clamp,
+                                _ => unreachable!(),
+                            },
+                            arg: fc.args[0].expression,
+                            arg1: Some(fc.args[1].expression),
+                            arg2: Some(fc.args[2].expression),
+                        };
+                        Ok(self.call_result(expression, fc.args))
                     }
                     "lessThan" | "greaterThan" | "lessThanEqual" | "greaterThanEqual" | "equal"
                     | "notEqual" => {
                         if fc.args.len() != 2 {
                             return Err(ErrorKind::WrongNumberArgs(name, 2, fc.args.len()));
                         }
-                        Ok(ExpressionRule {
-                            expression: self.context.expressions.append(Expression::Binary {
-                                op: match name.as_str() {
-                                    "lessThan" => BinaryOperator::Less,
-                                    "greaterThan" => BinaryOperator::Greater,
-                                    "lessThanEqual" => BinaryOperator::LessEqual,
-                                    "greaterThanEqual" => BinaryOperator::GreaterEqual,
-                                    "equal" => BinaryOperator::Equal,
-                                    "notEqual" => BinaryOperator::NotEqual,
-                                    _ => unreachable!(),
-                                },
-                                left: fc.args[0].expression,
-                                right: fc.args[1].expression,
-                            }),
-                            sampler: None,
-                            statements: fc.args.into_iter().flat_map(|a| a.statements).collect(),
-                        })
+                        let expression = Expression::Binary {
+                            op: match name.as_str() {
+                                "lessThan" => BinaryOperator::Less,
+                                "greaterThan" => BinaryOperator::Greater,
+                                "lessThanEqual" => BinaryOperator::LessEqual,
+                                "greaterThanEqual" => BinaryOperator::GreaterEqual,
+                                "equal" => BinaryOperator::Equal,
+                                "notEqual" => BinaryOperator::NotEqual,
+                                _ => unreachable!(),
+                            },
+                            left: fc.args[0].expression,
+                            right: fc.args[1].expression,
+                        };
+                        Ok(self.call_result(expression, fc.args))
                     }
                     "isinf" => self.parse_relational_fun(name, fc.args, RelationalFunction::IsInf),
                     "isnan" => self.parse_relational_fun(name, fc.args, RelationalFunction::IsNan),
                     "all" => self.parse_relational_fun(name, fc.args, RelationalFunction::All),
                     "any" => self.parse_relational_fun(name, fc.args, RelationalFunction::Any),
+                    "barrier"
+                    | "memoryBarrier"
+                    | "memoryBarrierBuffer"
+                    | "memoryBarrierShared"
+                    | "memoryBarrierImage"
+                    | "groupMemoryBarrier"
+                    | "subgroupBarrier"
+                    | "subgroupMemoryBarrier"
+                    | "subgroupMemoryBarrierBuffer"
+                    | "subgroupMemoryBarrierShared"
+                    | "subgroupMemoryBarrierImage" => {
+                        if !fc.args.is_empty() {
+                            return Err(ErrorKind::WrongNumberArgs(name, 0, fc.args.len()));
+                        }
+                        let (execution, memory_scope, memory) = match name.as_str() {
+                            // in compute shaders `barrier()` also orders shared memory accesses
+                            "barrier" => (
+                                Some(Scope::WorkGroup),
+                                Scope::WorkGroup,
+                                Barrier::WORK_GROUP,
+                            ),
+                            "memoryBarrier" => (None, Scope::Device, Barrier::all()),
+                            "memoryBarrierBuffer" => (None, Scope::Device, Barrier::STORAGE),
+                            "memoryBarrierShared" => (None, Scope::WorkGroup, Barrier::WORK_GROUP),
+                            "memoryBarrierImage" => (None, Scope::Device, Barrier::IMAGE),
+                            "groupMemoryBarrier" => (None, Scope::WorkGroup, Barrier::all()),
+                            "subgroupBarrier" => {
+                                (Some(Scope::SubGroup), Scope::SubGroup, Barrier::all())
+                            }
+                            "subgroupMemoryBarrier" => (None, Scope::SubGroup, Barrier::all()),
+                            "subgroupMemoryBarrierBuffer" => {
+                                (None, Scope::SubGroup, Barrier::STORAGE)
+                            }
+                            "subgroupMemoryBarrierShared" => {
+                                (None, Scope::SubGroup, Barrier::WORK_GROUP)
+                            }
+                            "subgroupMemoryBarrierImage" => (None, Scope::SubGroup, Barrier::IMAGE),
+                            _ => unreachable!(),
+                        };
+                        // barriers don't produce a value, but the rule needs an expression
+                        let constant = self.module.constants.fetch_or_append(Constant {
+                            name: None,
+                            specialization: None,
+                            inner: ConstantInner::Scalar {
+                                width: 4,
+                                value: ScalarValue::Sint(0),
+                            },
+                        });
+                        Ok(ExpressionRule {
+                            expression: self
+                                .context
+                                .expressions
+                                .append(Expression::Constant(constant)),
+                            sampler: None,
+                            statements: vec![crate::Statement::Barrier {
+                                execution,
+                                memory_scope,
+                                memory,
+                            }],
+                        })
+                    }
+                    "EmitVertex" | "EndPrimitive" => {
+                        if !fc.args.is_empty() {
+                            return Err(ErrorKind::WrongNumberArgs(name, 0, fc.args.len()));
+                        }
+                        let statement = if name == "EmitVertex" {
+                            crate::Statement::EmitVertex
+                        } else {
+                            crate::Statement::EndPrimitive
+                        };
+                        // like barriers, these don't produce a value
+                        let constant = self.module.constants.fetch_or_append(Constant {
+                            name: None,
+                            specialization: None,
+                            inner: ConstantInner::Scalar {
+                                width: 4,
+                                value: ScalarValue::Sint(0),
+                            },
+                        });
+                        Ok(ExpressionRule {
+                            expression: self
+                                .context
+                                .expressions
+                                .append(Expression::Constant(constant)),
+                            sampler: None,
+                            statements: vec![statement],
+                        })
+                    }
+                    "traceRayEXT" => {
+                        if fc.args.len() != 11 {
+                            return Err(ErrorKind::WrongNumberArgs(name, 11, fc.args.len()));
+                        }
+                        // the payload is passed by the location of its global
+                        let location = self.solve_constant(fc.args[10].expression)?;
+                        let location = match self.module.constants[location].inner {
+                            ConstantInner::Scalar {
+                                value: ScalarValue::Sint(location),
+                                ..
+                            } => location as u32,
+                            ConstantInner::Scalar {
+                                value: ScalarValue::Uint(location),
+                                ..
+                            } => location as u32,
+                            _ => {
+                                return Err(ErrorKind::SemanticError(
+                                    "Ray payload location must be an integer".into(),
+                                ))
+                            }
+                        };
+                        let payload = *self.ray_payloads.get(&location).ok_or_else(|| {
+                            ErrorKind::SemanticError(
+                                format!("Unknown ray payload location: {}", location).into(),
+                            )
+                        })?;
+                        let payload = self
+                            .context
+                            .expressions
+                            .append(Expression::GlobalVariable(payload));
+                        let args: Vec<_> = fc.args.iter().map(|a| a.expression).collect();
+                        let mut statements: Vec<_> =
+                            fc.args.into_iter().flat_map(|a| a.statements).collect();
+                        statements.push(crate::Statement::TraceRay {
+                            acceleration_structure: args[0],
+                            flags: args[1],
+                            cull_mask: args[2],
+                            sbt_offset: args[3],
+                            sbt_stride: args[4],
+                            miss_index: args[5],
+                            origin: args[6],
+                            t_min: args[7],
+                            direction: args[8],
+                            t_max: args[9],
+                            payload,
+                        });
+                        // like barriers, this doesn't produce a value
+                        let constant = self.module.constants.fetch_or_append(Constant {
+                            name: None,
+                            specialization: None,
+                            inner: ConstantInner::Scalar {
+                                width: 4,
+                                value: ScalarValue::Sint(0),
+                            },
+                        });
+                        Ok(ExpressionRule {
+                            expression: self
+                                .context
+                                .expressions
+                                .append(Expression::Constant(constant)),
+                            sampler: None,
+                            statements,
+                        })
+                    }
+                    "reportIntersectionEXT" => {
+                        if fc.args.len() != 2 {
+                            return Err(ErrorKind::WrongNumberArgs(name, 2, fc.args.len()));
+                        }
+                        let hit_t = fc.args[0].expression;
+                        let hit_kind = fc.args[1].expression;
+                        let mut statements: Vec<_> =
+                            fc.args.into_iter().flat_map(|a| a.statements).collect();
+                        let result = self
+                            .context
+                            .expressions
+                            .append(Expression::ReportIntersectionResult);
+                        statements.push(crate::Statement::ReportIntersection {
+                            hit_t,
+                            hit_kind,
+                            result,
+                        });
+                        Ok(ExpressionRule {
+                            expression: result,
+                            sampler: None,
+                            statements,
+                        })
+                    }
+                    func_name if func_name.starts_with("subgroup") => {
+                        self.parse_subgroup_fun(name, fc.args)
+                    }
                     func_name => {
                         let function = *self.lookup_function.get(func_name).ok_or_else(|| {
                             ErrorKind::SemanticError(
                                 format!("Unknown function: {}", func_name).into(),
                             )
                         })?;
-                        let arguments: Vec<_> = fc.args.iter().map(|a| a.expression).collect();
+                        let args = fc
+                            .args
+                            .into_iter()
+                            .map(|a| self.rvalue(a))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let arguments: Vec<_> = args.iter().map(|a| a.expression).collect();
                         let mut statements: Vec<_> =
-                            fc.args.into_iter().flat_map(|a| a.statements).collect();
+                            args.into_iter().flat_map(|a| a.statements).collect();
                         let expression =
                             self.context.expressions.append(Expression::Call(function));
                         statements.push(crate::Statement::Call {
@@ -218,13 +383,127 @@ impl Program<'_> {
         if args.len() != 1 {
             return Err(ErrorKind::WrongNumberArgs(name, 1, args.len()));
         }
-        Ok(ExpressionRule {
-            expression: self.context.expressions.append(Expression::Relational {
-                fun,
+        let expression = Expression::Relational {
+            fun,
+            argument: args[0].expression,
+        };
+        Ok(self.call_result(expression, args))
+    }
+
+    /// Emit the `expression` computed by a built-in function call from its `args`.
+    fn call_result(&mut self, expression: Expression, args: Vec<ExpressionRule>) -> ExpressionRule {
+        let mut statements: Vec<_> = args.into_iter().flat_map(|a| a.statements).collect();
+        let expression = self.context.add_expression(expression, &mut statements);
+        ExpressionRule {
+            expression,
+            sampler: None,
+            statements,
+        }
+    }
+
+    pub fn parse_subgroup_fun(
+        &mut self,
+        name: String,
+        args: Vec<ExpressionRule>,
+    ) -> Result<ExpressionRule, ErrorKind> {
+        let arg_count = match name.as_str() {
+            "subgroupElect" => 0,
+            "subgroupBroadcast"
+            | "subgroupShuffle"
+            | "subgroupShuffleXor"
+            | "subgroupShuffleUp"
+            | "subgroupShuffleDown" => 2,
+            _ => 1,
+        };
+        if args.len() != arg_count {
+            return Err(ErrorKind::WrongNumberArgs(name, arg_count, args.len()));
+        }
+        let fun = match name.as_str() {
+            "subgroupElect" => SubgroupFunction::Elect,
+            "subgroupBallot" => SubgroupFunction::Ballot {
+                predicate: Some(args[0].expression),
+            },
+            "subgroupBroadcastFirst" => SubgroupFunction::Gather {
+                mode: GatherMode::BroadcastFirst,
                 argument: args[0].expression,
-            }),
+            },
+            "subgroupBroadcast"
+            | "subgroupShuffle"
+            | "subgroupShuffleXor"
+            | "subgroupShuffleUp"
+            | "subgroupShuffleDown" => {
+                let index = args[1].expression;
+                SubgroupFunction::Gather {
+                    mode: match name.as_str() {
+                        "subgroupBroadcast" => GatherMode::Broadcast(index),
+                        "subgroupShuffle" => GatherMode::Shuffle(index),
+                        "subgroupShuffleXor" => GatherMode::ShuffleXor(index),
+                        "subgroupShuffleUp" => GatherMode::ShuffleUp(index),
+                        _ => GatherMode::ShuffleDown(index),
+                    },
+                    argument: args[0].expression,
+                }
+            }
+            _ => {
+                // the remaining functions are named `subgroup[Inclusive|Exclusive]<Op>`
+                let rest = &name["subgroup".len()..];
+                let (collective, rest) = if let Some(rest) = rest.strip_prefix("Inclusive") {
+                    (CollectiveOperation::InclusiveScan, rest)
+                } else if let Some(rest) = rest.strip_prefix("Exclusive") {
+                    (CollectiveOperation::ExclusiveScan, rest)
+                } else {
+                    (CollectiveOperation::Reduce, rest)
+                };
+                let op = match rest {
+                    "All" => SubgroupOperation::All,
+                    "Any" => SubgroupOperation::Any,
+                    "Add" => SubgroupOperation::Add,
+                    "Mul" => SubgroupOperation::Multiply,
+                    "Min" => SubgroupOperation::Min,
+                    "Max" => SubgroupOperation::Max,
+                    "And" => SubgroupOperation::And,
+                    "Or" => SubgroupOperation::InclusiveOr,
+                    "Xor" => SubgroupOperation::ExclusiveOr,
+                    _ => {
+                        return Err(ErrorKind::SemanticError(
+                            format!("Unknown function: {}", name).into(),
+                        ))
+                    }
+                };
+                SubgroupFunction::Collective {
+                    op,
+                    collective,
+                    argument: args[0].expression,
+                }
+            }
+        };
+
+        let (kind, width, size) = match fun {
+            SubgroupFunction::Elect => (ScalarKind::Bool, crate::BOOL_WIDTH, None),
+            SubgroupFunction::Ballot { .. } => (ScalarKind::Uint, 4, Some(VectorSize::Quad)),
+            SubgroupFunction::Gather { argument, .. }
+            | SubgroupFunction::Collective { argument, .. } => {
+                match *self.resolve_type(argument)? {
+                    TypeInner::Scalar { kind, width } => (kind, width, None),
+                    TypeInner::Vector { size, kind, width } => (kind, width, Some(size)),
+                    _ => {
+                        return Err(ErrorKind::SemanticError(
+                            format!("Invalid operand type for {}", name).into(),
+                        ))
+                    }
+                }
+            }
+        };
+        let result =
+            self.context
+                .expressions
+                .append(Expression::SubgroupResult { kind, width, size });
+        let mut statements: Vec<_> = args.into_iter().flat_map(|a| a.statements).collect();
+        statements.push(crate::Statement::Subgroup { fun, result });
+        Ok(ExpressionRule {
+            expression: result,
             sampler: None,
-            statements: args.into_iter().flat_map(|a| a.statements).collect(),
+            statements,
         })
     }
 
@@ -282,12 +561,45 @@ impl Program<'_> {
         std::mem::swap(&mut f.arguments, &mut self.context.arguments);
         self.context.clear_scopes();
         self.context.lookup_global_var_exps.clear();
+        self.context.per_vertex_blocks.clear();
+        self.context.per_vertex_indices.clear();
         self.context.typifier = Typifier::new();
         ensure_block_returns(&mut block);
         f.body = block;
         f
     }
 
+    /// Record a qualifier-only `layout(...) in;` or `layout(...) out;` declaration,
+    /// which describes the primitives of a geometry or tessellation shader.
+    pub fn apply_primitive_layout(
+        &mut self,
+        qualifiers: &[TypeQualifier],
+    ) -> Result<(), ErrorKind> {
+        let storage_class = qualifiers.iter().find_map(|tq| {
+            if let TypeQualifier::StorageQualifier(StorageQualifier::StorageClass(sc)) = *tq {
+                Some(sc)
+            } else {
+                None
+            }
+        });
+        for tq in qualifiers {
+            let layout = match *tq {
+                TypeQualifier::Primitive(ref layout) => layout,
+                _ => continue,
+            };
+            match storage_class {
+                Some(StorageClass::Input) => self.input_layout.merge(layout),
+                Some(StorageClass::Output) => self.output_layout.merge(layout),
+                _ => {
+                    return Err(ErrorKind::SemanticError(
+                        "Primitive layouts require an `in` or `out` qualifier".into(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn declare_function(&mut self, f: Function) -> Result<(), ErrorKind> {
         let name = f
             .name
@@ -295,11 +607,61 @@ impl Program<'_> {
             .ok_or_else(|| ErrorKind::SemanticError("Unnamed function".into()))?;
         let stage = self.entry_points.get(&name);
         if let Some(&stage) = stage {
+            let geometry = if stage == ShaderStage::Geometry {
+                Some(GeometryInfo {
+                    input: self
+                        .input_layout
+                        .kind
+                        .and_then(PrimitiveKind::geometry_input)
+                        .ok_or_else(|| {
+                            ErrorKind::SemanticError("Missing geometry input layout".into())
+                        })?,
+                    output: self
+                        .output_layout
+                        .kind
+                        .and_then(PrimitiveKind::geometry_output)
+                        .ok_or_else(|| {
+                            ErrorKind::SemanticError("Missing geometry output layout".into())
+                        })?,
+                    max_vertices: self.output_layout.max_vertices.ok_or_else(|| {
+                        ErrorKind::SemanticError("Missing max_vertices layout".into())
+                    })?,
+                })
+            } else {
+                None
+            };
+            let tessellation = match stage {
+                ShaderStage::TessellationControl => Some(TessellationInfo::Control {
+                    output_vertices: self.output_layout.output_vertices.ok_or_else(|| {
+                        ErrorKind::SemanticError("Missing vertices layout".into())
+                    })?,
+                }),
+                ShaderStage::TessellationEvaluation => Some(TessellationInfo::Evaluation {
+                    primitive: self
+                        .input_layout
+                        .kind
+                        .and_then(PrimitiveKind::tessellation_primitive)
+                        .ok_or_else(|| {
+                            ErrorKind::SemanticError("Missing tessellation primitive layout".into())
+                        })?,
+                    spacing: self
+                        .input_layout
+                        .spacing
+                        .unwrap_or(TessellationSpacing::Equal),
+                    vertex_order: self
+                        .input_layout
+                        .vertex_order
+                        .unwrap_or(VertexOrder::CounterClockwise),
+                }),
+                _ => None,
+            };
             self.module.entry_points.push(EntryPoint {
                 name,
                 stage,
                 early_depth_test: None,
                 workgroup_size: [0; 3], //TODO
+                geometry,
+                tessellation,
                 function: f,
             });
         } else {
@@ -68,6 +68,16 @@ impl<'a> Iterator for Lexer<'a> {
                     "smooth" => Token::Interpolation((meta, crate::Interpolation::Perspective)),
                     "centroid" => Token::Interpolation((meta, crate::Interpolation::Centroid)),
                     "sample" => Token::Interpolation((meta, crate::Interpolation::Sample)),
+                    "patch" => Token::Interpolation((meta, crate::Interpolation::Patch)),
+                    "rayPayloadEXT" => {
+                        Token::RayTracingStorage((meta, crate::StorageClass::RayPayload))
+                    }
+                    "rayPayloadInEXT" => {
+                        Token::RayTracingStorage((meta, crate::StorageClass::IncomingRayPayload))
+                    }
+                    "hitAttributeEXT" => {
+                        Token::RayTracingStorage((meta, crate::StorageClass::HitAttribute))
+                    }
                     // values
                     "true" => Token::BoolConstant((meta, true)),
                     "false" => Token::BoolConstant((meta, false)),
@@ -76,6 +86,8 @@ impl<'a> Iterator for Lexer<'a> {
                     "break" => Token::Break(meta),
                     "return" => Token::Return(meta),
                     "discard" => Token::Discard(meta),
+                    "ignoreIntersectionEXT" => Token::IgnoreIntersection(meta),
+                    "terminateRayEXT" => Token::TerminateRay(meta),
                     // selection statements
                     "if" => Token::If(meta),
                     "else" => Token::Else(meta),
@@ -96,8 +108,11 @@ impl<'a> Iterator for Lexer<'a> {
                     },
                 }
             }
-            //TODO: unsigned etc
-            TokenValue::Integer(integer) => Token::IntConstant((meta, integer.value as i64)),
+            //TODO: 64-bit integers
+            TokenValue::Integer(integer) if integer.signed => {
+                Token::IntConstant((meta, integer.value as i64))
+            }
+            TokenValue::Integer(integer) => Token::UintConstant((meta, integer.value)),
             TokenValue::Punct(punct) => match punct {
                 // Compound assignments
                 Punct::AddAssign => Token::AddAssign(meta),
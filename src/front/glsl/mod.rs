@@ -1,4 +1,4 @@
-use crate::{FastHashMap, Module, ShaderStage};
+use crate::{FastHashMap, Module, ShaderStage, Span};
 
 mod lex;
 
@@ -7,7 +7,6 @@ use ast::Program;
 
 mod error;
 pub use error::ParseError;
-mod constants;
 mod functions;
 mod parser;
 #[cfg(test)]
@@ -26,8 +25,46 @@ pub fn parse_str(source: &str, options: &Options) -> Result<Module, ParseError>
 
     let lex = lex::Lexer::new(source, &options.defines);
     let mut parser = parser::Parser::new(&mut program);
+    // The grammar rules don't see the token locations, so everything produced
+    // while feeding a token is attributed to the tokens read since the last
+    // statement boundary.
+    let mut span = Span::UNDEFINED;
     for token in lex {
+        let is_boundary = matches!(
+            token,
+            parser::Token::Semicolon(_)
+                | parser::Token::LeftBrace(_)
+                | parser::Token::RightBrace(_)
+        );
+        span = span.union(&token.extra().chars.clone().into());
+
+        let program = parser.extra_mut();
+        let expression_count = program.context.expressions.len();
+        let local_count = program.context.local_variables.len();
+        let global_count = program.module.global_variables.len();
+
         parser.parse(token)?;
+
+        let program = parser.extra_mut();
+        if program.context.expressions.len() >= expression_count {
+            program
+                .context
+                .expressions
+                .fill_spans_from(expression_count, span);
+        }
+        if program.context.local_variables.len() >= local_count {
+            program
+                .context
+                .local_variables
+                .fill_spans_from(local_count, span);
+        }
+        program
+            .module
+            .global_variables
+            .fill_spans_from(global_count, span);
+        if is_boundary {
+            span = Span::UNDEFINED;
+        }
     }
     parser.end_of_input()?;
 
@@ -8,7 +8,7 @@ pomelo! {
             BOOL_WIDTH,
             Arena, BinaryOperator, Binding, Block, Constant,
             ConstantInner, Expression,
-            Function, GlobalVariable, Handle, Interpolation,
+            Function, FunctionResult, GlobalVariable, Handle, Interpolation,
             LocalVariable, ScalarValue, ScalarKind,
             Statement, StorageAccess, StorageClass, StructMember,
             SwitchCase, Type, TypeInner, UnaryOperator, FunctionArgument,
@@ -72,6 +72,7 @@ pomelo! {
     // expressions
     %type unary_expression ExpressionRule;
     %type postfix_expression ExpressionRule;
+    %type integer_expression ExpressionRule;
     %type primary_expression ExpressionRule;
     %type variable_identifier ExpressionRule;
 
@@ -119,6 +120,7 @@ pomelo! {
     %type storage_qualifier StorageQualifier;
     %type interpolation_qualifier Interpolation;
     %type Interpolation Interpolation;
+    %type RayTracingStorage StorageClass;
 
     // types
     %type fully_specified_type (Vec<TypeQualifier>, Option<Handle<Type>>);
@@ -159,9 +161,7 @@ pomelo! {
     variable_identifier ::= Identifier(v) {
         let var = extra.lookup_variable(&v.1)?;
         match var {
-            Some(expression) => {
-                ExpressionRule::from_expression(expression)
-            },
+            Some(rule) => rule,
             None => {
                 return Err(ErrorKind::UnknownVariable(v.0, v.1));
             }
@@ -239,14 +239,13 @@ pomelo! {
     }
 
     postfix_expression ::= primary_expression;
-    postfix_expression ::= postfix_expression LeftBracket integer_expression RightBracket {
-        //TODO
-        return Err(ErrorKind::NotImplemented("[]"))
+    postfix_expression ::= postfix_expression(e) LeftBracket integer_expression(i) RightBracket {
+        extra.index_expression(e, i)
     }
     postfix_expression ::= function_call;
-    postfix_expression ::= postfix_expression(e) Dot Identifier(i) /* FieldSelection in spec */ {
+    postfix_expression ::= postfix_expression(mut e) Dot Identifier(i) /* FieldSelection in spec */ {
         //TODO: how will this work as l-value?
-        let expression = extra.field_selection(e.expression, &*i.1, i.0)?;
+        let expression = extra.field_selection(e.expression, &*i.1, i.0, &mut e.statements)?;
         ExpressionRule { expression, statements: e.statements, sampler: None }
     }
     postfix_expression ::= postfix_expression(pe) IncOp {
@@ -325,18 +324,20 @@ pomelo! {
         tgt
     }
     unary_expression ::= Dash unary_expression(tgt) {
-        extra.unary_expr(UnaryOperator::Negate, &tgt)
+        extra.unary_expr(UnaryOperator::Negate, &tgt)?
     }
     unary_expression ::= Bang unary_expression(tgt) {
+        let tgt = extra.rvalue(tgt)?;
         if let TypeInner::Scalar { kind: ScalarKind::Bool, .. } = extra.resolve_type(tgt.expression)? {
-            extra.unary_expr(UnaryOperator::Not, &tgt)
+            extra.unary_expr(UnaryOperator::Not, &tgt)?
         } else {
             return Err(ErrorKind::SemanticError("Cannot apply '!' to non bool type".into()))
         }
     }
     unary_expression ::= Tilde unary_expression(tgt) {
+        let tgt = extra.rvalue(tgt)?;
         if extra.resolve_type(tgt.expression)?.scalar_kind() != Some(ScalarKind::Bool) {
-            extra.unary_expr(UnaryOperator::Not, &tgt)
+            extra.unary_expr(UnaryOperator::Not, &tgt)?
         } else {
             return Err(ErrorKind::SemanticError("Cannot apply '~' to type".into()))
         }
@@ -344,40 +345,40 @@ pomelo! {
 
     multiplicative_expression ::= unary_expression;
     multiplicative_expression ::= multiplicative_expression(left) Star unary_expression(right) {
-        extra.binary_expr(BinaryOperator::Multiply, &left, &right)
+        extra.binary_expr(BinaryOperator::Multiply, &left, &right)?
     }
     multiplicative_expression ::= multiplicative_expression(left) Slash unary_expression(right) {
-        extra.binary_expr(BinaryOperator::Divide, &left, &right)
+        extra.binary_expr(BinaryOperator::Divide, &left, &right)?
     }
     multiplicative_expression ::= multiplicative_expression(left) Percent unary_expression(right) {
-        extra.binary_expr(BinaryOperator::Modulo, &left, &right)
+        extra.binary_expr(BinaryOperator::Modulo, &left, &right)?
     }
     additive_expression ::= multiplicative_expression;
     additive_expression ::= additive_expression(left) Plus multiplicative_expression(right) {
-        extra.binary_expr(BinaryOperator::Add, &left, &right)
+        extra.binary_expr(BinaryOperator::Add, &left, &right)?
     }
     additive_expression ::= additive_expression(left) Dash multiplicative_expression(right) {
-        extra.binary_expr(BinaryOperator::Subtract, &left, &right)
+        extra.binary_expr(BinaryOperator::Subtract, &left, &right)?
     }
     shift_expression ::= additive_expression;
     shift_expression ::= shift_expression(left) LeftOp additive_expression(right) {
-        extra.binary_expr(BinaryOperator::ShiftLeft, &left, &right)
+        extra.binary_expr(BinaryOperator::ShiftLeft, &left, &right)?
     }
     shift_expression ::= shift_expression(left) RightOp additive_expression(right) {
-        extra.binary_expr(BinaryOperator::ShiftRight, &left, &right)
+        extra.binary_expr(BinaryOperator::ShiftRight, &left, &right)?
     }
     relational_expression ::= shift_expression;
     relational_expression ::= relational_expression(left) LeftAngle shift_expression(right) {
-        extra.binary_expr(BinaryOperator::Less, &left, &right)
+        extra.binary_expr(BinaryOperator::Less, &left, &right)?
     }
     relational_expression ::= relational_expression(left) RightAngle shift_expression(right) {
-        extra.binary_expr(BinaryOperator::Greater, &left, &right)
+        extra.binary_expr(BinaryOperator::Greater, &left, &right)?
     }
     relational_expression ::= relational_expression(left) LeOp shift_expression(right) {
-        extra.binary_expr(BinaryOperator::LessEqual, &left, &right)
+        extra.binary_expr(BinaryOperator::LessEqual, &left, &right)?
     }
     relational_expression ::= relational_expression(left) GeOp shift_expression(right) {
-        extra.binary_expr(BinaryOperator::GreaterEqual, &left, &right)
+        extra.binary_expr(BinaryOperator::GreaterEqual, &left, &right)?
     }
     equality_expression ::= relational_expression;
     equality_expression ::= equality_expression(left) EqOp relational_expression(right) {
@@ -388,32 +389,37 @@ pomelo! {
     }
     and_expression ::= equality_expression;
     and_expression ::= and_expression(left) Ampersand equality_expression(right) {
-        extra.binary_expr(BinaryOperator::And, &left, &right)
+        extra.binary_expr(BinaryOperator::And, &left, &right)?
     }
     exclusive_or_expression ::= and_expression;
     exclusive_or_expression ::= exclusive_or_expression(left) Caret and_expression(right) {
-        extra.binary_expr(BinaryOperator::ExclusiveOr, &left, &right)
+        extra.binary_expr(BinaryOperator::ExclusiveOr, &left, &right)?
     }
     inclusive_or_expression ::= exclusive_or_expression;
     inclusive_or_expression ::= inclusive_or_expression(left) VerticalBar exclusive_or_expression(right) {
-        extra.binary_expr(BinaryOperator::InclusiveOr, &left, &right)
+        extra.binary_expr(BinaryOperator::InclusiveOr, &left, &right)?
     }
     logical_and_expression ::= inclusive_or_expression;
     logical_and_expression ::= logical_and_expression(left) AndOp inclusive_or_expression(right) {
-        extra.binary_expr(BinaryOperator::LogicalAnd, &left, &right)
+        extra.binary_expr(BinaryOperator::LogicalAnd, &left, &right)?
     }
     logical_xor_expression ::= logical_and_expression;
     logical_xor_expression ::= logical_xor_expression(left) XorOp logical_and_expression(right) {
-        let exp1 = extra.binary_expr(BinaryOperator::LogicalOr, &left, &right);
+        let exp1 = extra.binary_expr(BinaryOperator::LogicalOr, &left, &right)?;
         let exp2 = {
-            let tmp = extra.binary_expr(BinaryOperator::LogicalAnd, &left, &right).expression;
-            ExpressionRule::from_expression(extra.context.expressions.append(Expression::Unary { op: UnaryOperator::Not, expr: tmp }))
+            // the operands are already emitted by `exp1`
+            let tmp = extra.binary_expr(
+                BinaryOperator::LogicalAnd,
+                &ExpressionRule::from_expression(left.expression),
+                &ExpressionRule::from_expression(right.expression),
+            )?;
+            extra.unary_expr(UnaryOperator::Not, &tmp)?
         };
-        extra.binary_expr(BinaryOperator::LogicalAnd, &exp1, &exp2)
+        extra.binary_expr(BinaryOperator::LogicalAnd, &exp1, &exp2)?
     }
     logical_or_expression ::= logical_xor_expression;
     logical_or_expression ::= logical_or_expression(left) OrOp logical_xor_expression(right) {
-        extra.binary_expr(BinaryOperator::LogicalOr, &left, &right)
+        extra.binary_expr(BinaryOperator::LogicalOr, &left, &right)?
     }
 
     conditional_expression ::= logical_or_expression;
@@ -424,6 +430,7 @@ pomelo! {
 
     assignment_expression ::= conditional_expression;
     assignment_expression ::= unary_expression(mut pointer) assignment_operator(op) assignment_expression(value) {
+        let value = extra.rvalue(value)?;
         pointer.statements.extend(value.statements);
         match op {
             BinaryOperator::Equal => {
@@ -434,12 +441,17 @@ pomelo! {
                 pointer
             },
             _ => {
-                let h = extra.context.expressions.append(
+                let left = extra.context.add_expression(
+                    Expression::Load { pointer: pointer.expression },
+                    &mut pointer.statements,
+                );
+                let h = extra.context.add_expression(
                     Expression::Binary{
                         op,
-                        left: pointer.expression,
+                        left,
                         right: value.expression,
-                    }
+                    },
+                    &mut pointer.statements,
                 );
                 pointer.statements.push(Statement::Store{
                     pointer: pointer.expression,
@@ -547,6 +559,11 @@ pomelo! {
     // declaration ::= type_qualifier(t) Identifier(i1) LeftBrace
     //     struct_declaration_list RightBrace Identifier(i2) array_specifier Semicolon;
 
+    declaration ::= type_qualifier(t) Semicolon {
+        extra.apply_primitive_layout(&t)?;
+        None
+    }
+
     init_declarator_list ::= single_declaration;
     init_declarator_list ::= init_declarator_list(mut idl) Comma Identifier(i) {
         idl.ids_initializers.push((Some(i.1), None));
@@ -612,6 +629,8 @@ pomelo! {
             StructLayout::Binding(Binding::Resource{ group, binding })
         } else if l.iter().any(|q| q.0.as_str() == "push_constant") {
             StructLayout::PushConstant
+        } else if let Some(layout) = PrimitiveLayout::from_ids(&l) {
+            StructLayout::Primitive(layout)
         } else {
             return Err(ErrorKind::NotImplemented("unsupported layout qualifier(s)"));
         }
@@ -649,6 +668,7 @@ pomelo! {
         match l {
             StructLayout::Binding(b) => TypeQualifier::Binding(b),
             StructLayout::PushConstant => TypeQualifier::StorageQualifier(StorageQualifier::StorageClass(StorageClass::PushConstant)),
+            StructLayout::Primitive(p) => TypeQualifier::Primitive(p),
         }
     }
     // single_type_qualifier ::= precision_qualifier;
@@ -674,6 +694,9 @@ pomelo! {
     storage_qualifier ::= Uniform {
         StorageQualifier::StorageClass(StorageClass::Uniform)
     }
+    storage_qualifier ::= RayTracingStorage((_, class)) {
+        StorageQualifier::StorageClass(class)
+    }
     //TODO: other storage qualifiers
 
     type_specifier ::= type_specifier_nonarray(t) {
@@ -724,6 +747,8 @@ pomelo! {
                 name: Some(name.clone()),
                 span: None,
                 ty,
+                binding: None,
+                interpolation: None,
             }).collect()
         } else {
             return Err(ErrorKind::SemanticError("Struct member can't be void".into()))
@@ -765,6 +790,7 @@ pomelo! {
                         return Err(ErrorKind::VariableAlreadyDeclared(id))
                     }
                 }
+                let initializer = initializer.map(|i| extra.rvalue(i)).transpose()?;
                 let mut init_exp: Option<Handle<Expression>> = None;
                 let localVar = extra.context.local_variables.append(
                     LocalVariable {
@@ -796,7 +822,7 @@ pomelo! {
         };
         match statements.len() {
             1 => statements.remove(0),
-            _ => Statement::Block(statements),
+            _ => Statement::Block(statements.into()),
         }
     }
 
@@ -814,19 +840,19 @@ pomelo! {
 
 
     selection_statement ::= If LeftParen expression(e) RightParen statement(s1) Else statement(s2) {
-        Statement::If {
+        with_statements(e.statements, Statement::If {
             condition: e.expression,
-            accept: vec![s1],
-            reject: vec![s2],
-        }
+            accept: vec![s1].into(),
+            reject: vec![s2].into(),
+        })
     }
 
     selection_statement ::= If LeftParen expression(e) RightParen statement(s) [Else] {
-        Statement::If {
+        with_statements(e.statements, Statement::If {
             condition: e.expression,
-            accept: vec![s],
-            reject: vec![],
-        }
+            accept: vec![s].into(),
+            reject: Block::new(),
+        })
     }
 
     selection_statement ::= Switch LeftParen expression(e) RightParen LeftBrace switch_statement_list(ls) RightBrace {
@@ -843,11 +869,11 @@ pomelo! {
                 default.extend_from_slice(&body);
             }
         }
-        Statement::Switch {
+        with_statements(e.statements, Statement::Switch {
             selector: e.expression,
             cases,
-            default,
-        }
+            default: default.into(),
+        })
     }
 
     switch_statement_list ::= {
@@ -873,44 +899,47 @@ pomelo! {
     }
 
     iteration_statement ::= While LeftParen expression(e) RightParen compound_statement_no_new_scope(sl) {
-        let mut body = Vec::with_capacity(sl.len() + 1);
+        let mut body = Vec::with_capacity(sl.len() + e.statements.len() + 1);
+        body.extend(e.statements);
         body.push(
             Statement::If {
                 condition: e.expression,
-                accept: vec![Statement::Break],
-                reject: vec![],
+                accept: vec![Statement::Break].into(),
+                reject: Block::new(),
             }
         );
         body.extend_from_slice(&sl);
         Statement::Loop {
-            body,
-            continuing: vec![],
+            body: body.into(),
+            continuing: Block::new(),
         }
     }
 
     iteration_statement ::= Do compound_statement(sl) While LeftParen expression(e) RightParen  {
         let mut body = sl;
+        body.extend(e.statements);
         body.push(
             Statement::If {
                 condition: e.expression,
-                accept: vec![Statement::Break],
-                reject: vec![],
+                accept: vec![Statement::Break].into(),
+                reject: Block::new(),
             }
         );
         Statement::Loop {
             body,
-            continuing: vec![],
+            continuing: Block::new(),
         }
     }
 
     iteration_statement ::= For LeftParen for_init_statement(s_init) for_rest_statement((cond_e, loop_e)) RightParen compound_statement_no_new_scope(sl) {
         let mut body = Vec::with_capacity(sl.len() + 2);
         if let Some(cond_e) = cond_e {
+            body.extend(cond_e.statements);
             body.push(
                 Statement::If {
                     condition: cond_e.expression,
-                    accept: vec![Statement::Break],
-                    reject: vec![],
+                    accept: vec![Statement::Break].into(),
+                    reject: Block::new(),
                 }
             );
         }
@@ -921,10 +950,10 @@ pomelo! {
         Statement::Block(vec![
             s_init,
             Statement::Loop {
-                body,
-                continuing: vec![],
+                body: body.into(),
+                continuing: Block::new(),
             }
-        ])
+        ].into())
     }
 
     for_init_statement ::= expression_statement;
@@ -944,7 +973,7 @@ pomelo! {
     }
 
     compound_statement ::= LeftBrace RightBrace {
-        vec![]
+        Block::new()
     }
     compound_statement ::= left_brace_scope statement_list(sl) RightBrace {
         extra.context.remove_current_scope();
@@ -958,7 +987,7 @@ pomelo! {
 
 
     compound_statement_no_new_scope ::= LeftBrace RightBrace {
-        vec![]
+        Block::new()
     }
     compound_statement_no_new_scope ::= LeftBrace statement_list(sl) RightBrace {
         sl
@@ -967,19 +996,19 @@ pomelo! {
     statement_list ::= statement(s) {
         //TODO: catch this earlier and don't populate the statements
         match s {
-            Statement::Block(ref block) if block.is_empty() => vec![],
-            _ => vec![s],
+            Statement::Block(ref block) if block.is_empty() => Block::new(),
+            _ => vec![s].into(),
         }
     }
     statement_list ::= statement_list(mut ss) statement(s) { ss.push(s); ss }
 
     expression_statement ::= Semicolon  {
-        Statement::Block(Vec::new())
+        Statement::Block(Block::new())
     }
     expression_statement ::= expression(mut e) Semicolon {
         match e.statements.len() {
             1 => e.statements.remove(0),
-            _ => Statement::Block(e.statements),
+            _ => Statement::Block(e.statements.into()),
         }
     }
 
@@ -1005,10 +1034,10 @@ pomelo! {
         Function {
             name: Some(n.1),
             arguments: vec![],
-            return_type: t.1,
+            result: t.1.map(|ty| FunctionResult { ty, binding: None, interpolation: None }),
             local_variables: Arena::<LocalVariable>::new(),
             expressions: Arena::<Expression>::new(),
-            body: vec![],
+            body: Block::new(),
         }
     }
     function_header_with_parameters ::= function_header(h) parameter_declaration(p) {
@@ -1019,12 +1048,12 @@ pomelo! {
         (h, args)
     }
     parameter_declarator ::= parameter_type_specifier(ty) Identifier(n) {
-        FunctionArgument { name: Some(n.1), ty }
+        FunctionArgument { name: Some(n.1), ty, binding: None, interpolation: None }
     }
     // parameter_declarator ::= type_specifier(ty) Identifier(ident) array_specifier;
     parameter_declaration ::= parameter_declarator;
     parameter_declaration ::= parameter_type_specifier(ty) {
-        FunctionArgument { name: None, ty }
+        FunctionArgument { name: None, ty, binding: None, interpolation: None }
     }
 
     parameter_type_specifier ::= type_specifier(t) {
@@ -1044,11 +1073,12 @@ pomelo! {
     jump_statement ::= Return Semicolon {
         Statement::Return { value: None }
     }
-    jump_statement ::= Return expression(mut e) Semicolon {
+    jump_statement ::= Return expression(e) Semicolon {
+        let mut e = extra.rvalue(e)?;
         let ret = Statement::Return{ value: Some(e.expression) };
         if !e.statements.is_empty() {
             e.statements.push(ret);
-            Statement::Block(e.statements)
+            Statement::Block(e.statements.into())
         } else {
             ret
         }
@@ -1056,6 +1086,12 @@ pomelo! {
     jump_statement ::= Discard Semicolon  {
         Statement::Kill
     } // Fragment shader only
+    jump_statement ::= IgnoreIntersection Semicolon {
+        Statement::IgnoreIntersection
+    } // Any hit shader only
+    jump_statement ::= TerminateRay Semicolon {
+        Statement::TerminateRay
+    } // Any hit shader only
 
     // Grammar Note: No 'goto'. Gotos are not supported.
 
@@ -1084,13 +1120,23 @@ pomelo! {
                         if let TypeQualifier::Interpolation(i) = tq { Some(*i) } else { None }
                     });
 
+                    // ray payloads are referenced by location in `traceRayEXT`,
+                    // but aren't bound to anything in the IR
+                    let (binding, payload_location) = match (storage_class, binding) {
+                        (StorageClass::RayPayload, Some(Binding::Location(location)))
+                        | (StorageClass::IncomingRayPayload, Some(Binding::Location(location))) => {
+                            (None, Some(location))
+                        }
+                        (_, binding) => (binding, None),
+                    };
+
                     for (id, initializer) in d.ids_initializers {
                         let init = initializer.map(|init| extra.solve_constant(init.expression)).transpose()?;
 
-                        // use StorageClass::Handle for texture and sampler uniforms
+                        // use StorageClass::Handle for texture, sampler and acceleration structure uniforms
                         let class = if storage_class == StorageClass::Uniform {
                             match extra.module.types[d.ty].inner {
-                                TypeInner::Image{..} | TypeInner::Sampler{..} => StorageClass::Handle,
+                                TypeInner::Image{..} | TypeInner::Sampler{..} | TypeInner::AccelerationStructure => StorageClass::Handle,
                                 _ => storage_class,
                             }
                         } else {
@@ -1111,6 +1157,9 @@ pomelo! {
                         if let Some(id) = id {
                             extra.lookup_global_variables.insert(id, h);
                         }
+                        if let (StorageClass::RayPayload, Some(location)) = (class, payload_location) {
+                            extra.ray_payloads.insert(location, h);
+                        }
                     }
                 }
                 StorageQualifier::Const => {
@@ -245,6 +245,88 @@ fn functions() {
     .unwrap();
 }
 
+#[test]
+fn barriers() {
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::Compute);
+
+    let program = parse_program(
+        r#"
+        #  version 450
+        void main() {
+            memoryBarrierShared();
+            barrier();
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+
+    let function = &program.module.entry_points[0].function;
+    let barriers: Vec<_> = function
+        .body
+        .iter()
+        .filter_map(|statement| match *statement {
+            crate::Statement::Barrier {
+                execution, memory, ..
+            } => Some((execution, memory)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        barriers,
+        [
+            (None, crate::Barrier::WORK_GROUP),
+            (Some(crate::Scope::WorkGroup), crate::Barrier::WORK_GROUP),
+        ]
+    );
+}
+
+#[test]
+fn subgroup_operations() {
+    use crate::{CollectiveOperation, SubgroupFunction, SubgroupOperation};
+
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::Compute);
+
+    let program = parse_program(
+        r#"
+        #  version 450
+        void main() {
+            subgroupElect();
+            subgroupAdd(1.0);
+            subgroupExclusiveMul(2);
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+
+    let function = &program.module.entry_points[0].function;
+    let functions: Vec<_> = function
+        .body
+        .iter()
+        .filter_map(|statement| match *statement {
+            crate::Statement::Subgroup { ref fun, .. } => Some(match *fun {
+                SubgroupFunction::Collective { op, collective, .. } => Some((op, collective)),
+                _ => None,
+            }),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        functions,
+        [
+            None,
+            Some((SubgroupOperation::Add, CollectiveOperation::Reduce)),
+            Some((
+                SubgroupOperation::Multiply,
+                CollectiveOperation::ExclusiveScan
+            )),
+        ]
+    );
+}
+
 #[test]
 fn constants() {
     use crate::{Constant, ConstantInner, ScalarValue};
@@ -303,3 +385,209 @@ fn constants() {
 
     assert!(constants.next().is_none());
 }
+
+#[test]
+fn geometry_layouts() {
+    use crate::{GeometryInfo, GeometryInput, GeometryOutput, Statement};
+
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::Geometry);
+
+    let program = parse_program(
+        r#"
+        #  version 450
+        layout(triangles) in;
+        layout(line_strip, max_vertices = 4) out;
+        void main() {
+            gl_Position = vec4(1.0);
+            EmitVertex();
+            EndPrimitive();
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+
+    let entry_point = &program.module.entry_points[0];
+    assert_eq!(
+        entry_point.geometry,
+        Some(GeometryInfo {
+            input: GeometryInput::Triangles,
+            output: GeometryOutput::LineStrip,
+            max_vertices: 4,
+        })
+    );
+    assert!(entry_point
+        .function
+        .body
+        .iter()
+        .any(|s| matches!(*s, Statement::EmitVertex)));
+    assert!(entry_point
+        .function
+        .body
+        .iter()
+        .any(|s| matches!(*s, Statement::EndPrimitive)));
+
+    assert_eq!(
+        format!(
+            "{:?}",
+            parse_program(
+                r#"
+                #  version 450
+                layout(points) in;
+                void main() {}
+                "#,
+                &entry_points,
+            )
+            .err()
+            .unwrap()
+        ),
+        "SemanticError(\"Missing geometry output layout\")"
+    );
+}
+
+#[test]
+fn tessellation_layouts() {
+    use crate::{
+        Binding, BuiltIn, StorageClass, TessellationInfo, TessellationPrimitive,
+        TessellationSpacing, VertexOrder,
+    };
+
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::TessellationControl);
+
+    let program = parse_program(
+        r#"
+        #  version 450
+        layout(vertices = 3) out;
+        void main() {
+            gl_TessLevelOuter[0] = 1.0;
+            gl_out[gl_InvocationID].gl_Position = gl_in[gl_InvocationID].gl_Position;
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+    assert_eq!(
+        program.module.entry_points[0].tessellation,
+        Some(TessellationInfo::Control { output_vertices: 3 })
+    );
+    let position_classes = program
+        .module
+        .global_variables
+        .iter()
+        .filter(|&(_, var)| var.binding == Some(Binding::BuiltIn(BuiltIn::Position)))
+        .map(|(_, var)| var.class)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        position_classes,
+        [StorageClass::Output, StorageClass::Input]
+    );
+
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::TessellationEvaluation);
+
+    let program = parse_program(
+        r#"
+        #  version 450
+        layout(quads, fractional_odd_spacing, cw) in;
+        void main() {
+            gl_Position = vec4(gl_TessCoord, 1.0);
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+    assert_eq!(
+        program.module.entry_points[0].tessellation,
+        Some(TessellationInfo::Evaluation {
+            primitive: TessellationPrimitive::Quads,
+            spacing: TessellationSpacing::FractionalOdd,
+            vertex_order: VertexOrder::Clockwise,
+        })
+    );
+}
+
+#[test]
+fn ray_tracing() {
+    use crate::{Expression, Statement, StorageClass, TypeInner};
+
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::RayGeneration);
+
+    let program = parse_program(
+        r#"
+        #  version 460
+        layout(set = 0, binding = 0) uniform accelerationStructureEXT scene;
+        layout(location = 1) rayPayloadEXT vec4 payload;
+        void main() {
+            traceRayEXT(scene, 0u, 255u, 0u, 0u, 0u, vec3(0.0), 0.0, vec3(1.0), 100.0, 1);
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+
+    let module = &program.module;
+    let (payload, _) = module
+        .global_variables
+        .iter()
+        .find(|&(_, var)| var.class == StorageClass::RayPayload)
+        .unwrap();
+    assert_eq!(module.global_variables[payload].binding, None);
+    assert!(module
+        .global_variables
+        .iter()
+        .any(|(_, var)| var.class == StorageClass::Handle
+            && module.types[var.ty].inner == TypeInner::AccelerationStructure));
+    let function = &module.entry_points[0].function;
+    let is_trace_ray = |s: &Statement| match *s {
+        Statement::TraceRay { payload: expr, .. } => {
+            function.expressions[expr] == Expression::GlobalVariable(payload)
+        }
+        _ => false,
+    };
+    // the statement follows the ones emitting its operands
+    assert!(function.body.iter().any(|s| match *s {
+        Statement::Block(ref block) => block.iter().any(is_trace_ray),
+        ref other => is_trace_ray(other),
+    }));
+
+    assert_eq!(
+        format!(
+            "{:?}",
+            parse_program(
+                r#"
+                #  version 460
+                layout(set = 0, binding = 0) uniform accelerationStructureEXT scene;
+                void main() {
+                    traceRayEXT(scene, 0u, 255u, 0u, 0u, 0u, vec3(0.0), 0.0, vec3(1.0), 100.0, 0);
+                }
+                "#,
+                &entry_points,
+            )
+            .err()
+            .unwrap()
+        ),
+        "SemanticError(\"Unknown ray payload location: 0\")"
+    );
+
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("main".to_string(), ShaderStage::AnyHit);
+
+    let program = parse_program(
+        r#"
+        #  version 460
+        void main() {
+            ignoreIntersectionEXT;
+        }
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+    assert!(program.module.entry_points[0]
+        .function
+        .body
+        .iter()
+        .any(|s| matches!(*s, Statement::IgnoreIntersection)));
+}
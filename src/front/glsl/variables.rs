@@ -1,22 +1,249 @@
 use crate::{
-    Binding, BuiltIn, Expression, GlobalVariable, Handle, ScalarKind, StorageAccess, StorageClass,
-    Type, TypeInner, VectorSize,
+    ArraySize, Binding, BuiltIn, Constant, ConstantInner, Expression, GlobalVariable, Handle,
+    ScalarKind, ScalarValue, ShaderStage, Statement, StorageAccess, StorageClass, Type, TypeInner,
+    VectorSize,
 };
 
 use super::ast::*;
 use super::error::ErrorKind;
 use super::token::TokenMetadata;
 
+/// Size of the `gl_in` array of the tessellation stages, `gl_MaxPatchVertices`.
+const MAX_PATCH_VERTICES: u32 = 32;
+
 impl Program<'_> {
-    pub fn lookup_variable(&mut self, name: &str) -> Result<Option<Handle<Expression>>, ErrorKind> {
+    fn has_stage(&self, stage: ShaderStage) -> bool {
+        self.entry_points.values().any(|&s| s == stage)
+    }
+
+    fn add_built_in(
+        &mut self,
+        name: &str,
+        class: StorageClass,
+        built_in: BuiltIn,
+        inner: TypeInner,
+    ) -> Handle<GlobalVariable> {
+        if let Some(&h) = self.lookup_global_variables.get(name) {
+            return h;
+        }
+        let h = self.module.global_variables.append(GlobalVariable {
+            name: Some(name.into()),
+            class,
+            binding: Some(Binding::BuiltIn(built_in)),
+            ty: self
+                .module
+                .types
+                .fetch_or_append(Type { name: None, inner }),
+            init: None,
+            interpolation: None,
+            storage_access: StorageAccess::empty(),
+        });
+        self.lookup_global_variables.insert(name.into(), h);
+        h
+    }
+
+    fn array_type(&mut self, base: TypeInner, size: u32) -> TypeInner {
+        let base = self.module.types.fetch_or_append(Type {
+            name: None,
+            inner: base,
+        });
+        let size = self.module.constants.fetch_or_append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Uint(size as u64),
+            },
+        });
+        TypeInner::Array {
+            base,
+            size: ArraySize::Constant(size),
+            stride: None,
+        }
+    }
+
+    /// Number of elements in the `gl_in` or `gl_out` array.
+    fn per_vertex_count(&self, class: StorageClass) -> Result<u32, ErrorKind> {
+        match class {
+            StorageClass::Input if self.has_stage(ShaderStage::Geometry) => self
+                .input_layout
+                .kind
+                .and_then(PrimitiveKind::geometry_input)
+                .map(|input| input.vertex_count())
+                .ok_or_else(|| ErrorKind::SemanticError("Missing geometry input layout".into())),
+            StorageClass::Input => Ok(MAX_PATCH_VERTICES),
+            _ => self
+                .output_layout
+                .output_vertices
+                .ok_or_else(|| ErrorKind::SemanticError("Missing vertices layout".into())),
+        }
+    }
+
+    pub fn index_expression(
+        &mut self,
+        base: ExpressionRule,
+        index: ExpressionRule,
+    ) -> ExpressionRule {
+        let mut statements = base.statements;
+        statements.extend(index.statements);
+        // `gl_in[i]` and `gl_out[i]` are resolved once the member is selected
+        let expression = match self.context.per_vertex_blocks.get(&base.expression) {
+            Some(&class) => {
+                self.context
+                    .per_vertex_indices
+                    .insert(index.expression, class);
+                index.expression
+            }
+            None => self.context.add_expression(
+                Expression::Access {
+                    base: base.expression,
+                    index: index.expression,
+                },
+                &mut statements,
+            ),
+        };
+        ExpressionRule {
+            expression,
+            statements,
+            sampler: None,
+        }
+    }
+
+    /// Select a member of the `gl_in` or `gl_out` block at the given vertex `index`.
+    fn per_vertex_member(
+        &mut self,
+        index: Handle<Expression>,
+        class: StorageClass,
+        name: &str,
+        meta: TokenMetadata,
+        statements: &mut Vec<Statement>,
+    ) -> Result<Handle<Expression>, ErrorKind> {
+        let (built_in, inner) = match name {
+            "gl_Position" => (
+                BuiltIn::Position,
+                TypeInner::Vector {
+                    size: VectorSize::Quad,
+                    kind: ScalarKind::Float,
+                    width: 4,
+                },
+            ),
+            "gl_PointSize" => (
+                BuiltIn::PointSize,
+                TypeInner::Scalar {
+                    kind: ScalarKind::Float,
+                    width: 4,
+                },
+            ),
+            _ => return Err(ErrorKind::UnknownField(meta, name.into())),
+        };
+        let block = if class == StorageClass::Input {
+            "gl_in"
+        } else {
+            "gl_out"
+        };
+        let full_name = format!("{}.{}", block, name);
+        let base = match self.context.lookup_global_var_exps.get(&full_name) {
+            Some(&expr) => expr,
+            None => {
+                let count = self.per_vertex_count(class)?;
+                let inner = self.array_type(inner, count);
+                let h = self.add_built_in(&full_name, class, built_in, inner);
+                let expr = self
+                    .context
+                    .expressions
+                    .append(Expression::GlobalVariable(h));
+                self.context.lookup_global_var_exps.insert(full_name, expr);
+                expr
+            }
+        };
+        Ok(self
+            .context
+            .add_expression(Expression::Access { base, index }, statements))
+    }
+
+    /// Built-in inputs which are read by value, loaded anew at every use.
+    fn lookup_built_in_value(&mut self, name: &str) -> Option<ExpressionRule> {
+        let uint = TypeInner::Scalar {
+            kind: ScalarKind::Uint,
+            width: 4,
+        };
+        let float = TypeInner::Scalar {
+            kind: ScalarKind::Float,
+            width: 4,
+        };
+        let vec3 = |kind| TypeInner::Vector {
+            size: VectorSize::Tri,
+            kind,
+            width: 4,
+        };
+        let matrix = TypeInner::Matrix {
+            columns: VectorSize::Quad,
+            rows: VectorSize::Tri,
+            width: 4,
+        };
+        // the integer indices are `int` in GLSL, but `uint` in the IR
+        let (built_in, inner, signed) = match name {
+            "gl_VertexIndex" => (BuiltIn::VertexIndex, uint, true),
+            "gl_InstanceIndex" => (BuiltIn::InstanceIndex, uint, true),
+            "gl_InvocationID" => (BuiltIn::InvocationId, uint, true),
+            "gl_PatchVerticesIn" => (BuiltIn::PatchVertices, uint, true),
+            "gl_InstanceCustomIndexEXT" => (BuiltIn::InstanceCustomIndex, uint, true),
+            "gl_LaunchIDEXT" => (BuiltIn::LaunchId, vec3(ScalarKind::Uint), false),
+            "gl_LaunchSizeEXT" => (BuiltIn::LaunchSize, vec3(ScalarKind::Uint), false),
+            "gl_WorldRayOriginEXT" => (BuiltIn::WorldRayOrigin, vec3(ScalarKind::Float), false),
+            "gl_WorldRayDirectionEXT" => {
+                (BuiltIn::WorldRayDirection, vec3(ScalarKind::Float), false)
+            }
+            "gl_ObjectRayOriginEXT" => (BuiltIn::ObjectRayOrigin, vec3(ScalarKind::Float), false),
+            "gl_ObjectRayDirectionEXT" => {
+                (BuiltIn::ObjectRayDirection, vec3(ScalarKind::Float), false)
+            }
+            "gl_RayTminEXT" => (BuiltIn::RayTmin, float, false),
+            "gl_RayTmaxEXT" => (BuiltIn::RayTmax, float, false),
+            "gl_ObjectToWorldEXT" => (BuiltIn::ObjectToWorld, matrix, false),
+            "gl_WorldToObjectEXT" => (BuiltIn::WorldToObject, matrix, false),
+            "gl_HitKindEXT" => (BuiltIn::HitKind, uint, false),
+            "gl_IncomingRayFlagsEXT" => (BuiltIn::IncomingRayFlags, uint, false),
+            _ => return None,
+        };
+        let h = self.add_built_in(name, StorageClass::Input, built_in, inner);
+        let mut statements = Vec::new();
+        let pointer = self
+            .context
+            .expressions
+            .append(Expression::GlobalVariable(h));
+        let mut expression = self
+            .context
+            .add_expression(Expression::Load { pointer }, &mut statements);
+        if signed {
+            expression = self.context.add_expression(
+                Expression::As {
+                    expr: expression,
+                    kind: ScalarKind::Sint,
+                    convert: true,
+                },
+                &mut statements,
+            );
+        }
+        Some(ExpressionRule {
+            expression,
+            statements,
+            sampler: None,
+        })
+    }
+
+    pub fn lookup_variable(&mut self, name: &str) -> Result<Option<ExpressionRule>, ErrorKind> {
         if let Some(local_var) = self.context.lookup_local_var(name) {
-            return Ok(Some(local_var));
+            return Ok(Some(ExpressionRule::from_expression(local_var)));
+        }
+        if let Some(rule) = self.lookup_built_in_value(name) {
+            return Ok(Some(rule));
         }
         if let Some(global_var) = self.context.lookup_global_var_exps.get(name) {
-            return Ok(Some(*global_var));
+            return Ok(Some(ExpressionRule::from_expression(*global_var)));
         }
         if let Some(constant) = self.context.lookup_constant_exps.get(name) {
-            return Ok(Some(*constant));
+            return Ok(Some(ExpressionRule::from_expression(*constant)));
         }
         match name {
             "gl_Position" => {
@@ -43,71 +270,66 @@ impl Program<'_> {
                     .append(Expression::GlobalVariable(h));
                 self.context.lookup_global_var_exps.insert(name.into(), exp);
 
-                Ok(Some(exp))
+                Ok(Some(ExpressionRule::from_expression(exp)))
             }
-            "gl_VertexIndex" => {
-                let h = self.module.global_variables.append(GlobalVariable {
-                    name: Some(name.into()),
-                    class: StorageClass::Input,
-                    binding: Some(Binding::BuiltIn(BuiltIn::VertexIndex)),
-                    ty: self.module.types.fetch_or_append(Type {
-                        name: None,
-                        inner: TypeInner::Scalar {
-                            kind: ScalarKind::Uint,
+            "gl_TessCoord" | "gl_TessLevelOuter" | "gl_TessLevelInner" => {
+                let float = TypeInner::Scalar {
+                    kind: ScalarKind::Float,
+                    width: 4,
+                };
+                let (built_in, inner) = match name {
+                    "gl_TessCoord" => (
+                        BuiltIn::TessCoord,
+                        TypeInner::Vector {
+                            size: VectorSize::Tri,
+                            kind: ScalarKind::Float,
                             width: 4,
                         },
-                    }),
-                    init: None,
-                    interpolation: None,
-                    storage_access: StorageAccess::empty(),
-                });
-                self.lookup_global_variables.insert(name.into(), h);
-                let mut expr = self
+                    ),
+                    "gl_TessLevelOuter" => (BuiltIn::TessLevelOuter, self.array_type(float, 4)),
+                    _ => (BuiltIn::TessLevelInner, self.array_type(float, 2)),
+                };
+                // tessellation levels are written by the control stage
+                let class = if built_in == BuiltIn::TessCoord
+                    || self.has_stage(ShaderStage::TessellationEvaluation)
+                {
+                    StorageClass::Input
+                } else {
+                    StorageClass::Output
+                };
+                let h = self.add_built_in(name, class, built_in, inner);
+                let expr = self
                     .context
                     .expressions
                     .append(Expression::GlobalVariable(h));
-                expr = self.context.expressions.append(Expression::As {
-                    expr,
-                    kind: ScalarKind::Sint,
-                    convert: true,
-                });
                 self.context
                     .lookup_global_var_exps
                     .insert(name.into(), expr);
 
-                Ok(Some(expr))
+                Ok(Some(ExpressionRule::from_expression(expr)))
             }
-            "gl_InstanceIndex" => {
-                let h = self.module.global_variables.append(GlobalVariable {
-                    name: Some(name.into()),
-                    class: StorageClass::Input,
-                    binding: Some(Binding::BuiltIn(BuiltIn::InstanceIndex)),
-                    ty: self.module.types.fetch_or_append(Type {
-                        name: None,
-                        inner: TypeInner::Scalar {
-                            kind: ScalarKind::Uint,
-                            width: 4,
-                        },
-                    }),
-                    init: None,
-                    interpolation: None,
-                    storage_access: StorageAccess::empty(),
+            "gl_in" | "gl_out" => {
+                let class = if name == "gl_in" {
+                    StorageClass::Input
+                } else {
+                    StorageClass::Output
+                };
+                // the blocks themselves are never used as values, only their members
+                let placeholder = self.module.constants.fetch_or_append(Constant {
+                    name: None,
+                    specialization: None,
+                    inner: ConstantInner::Scalar {
+                        width: 4,
+                        value: ScalarValue::Sint(0),
+                    },
                 });
-                self.lookup_global_variables.insert(name.into(), h);
-                let mut expr = self
+                let expr = self
                     .context
                     .expressions
-                    .append(Expression::GlobalVariable(h));
-                expr = self.context.expressions.append(Expression::As {
-                    expr,
-                    kind: ScalarKind::Sint,
-                    convert: true,
-                });
-                self.context
-                    .lookup_global_var_exps
-                    .insert(name.into(), expr);
+                    .append(Expression::Constant(placeholder));
+                self.context.per_vertex_blocks.insert(expr, class);
 
-                Ok(Some(expr))
+                Ok(Some(ExpressionRule::from_expression(expr)))
             }
             _ => Ok(None),
         }
@@ -118,7 +340,11 @@ impl Program<'_> {
         expression: Handle<Expression>,
         name: &str,
         meta: TokenMetadata,
+        statements: &mut Vec<Statement>,
     ) -> Result<Handle<Expression>, ErrorKind> {
+        if let Some(class) = self.context.per_vertex_indices.remove(&expression) {
+            return self.per_vertex_member(expression, class, name, meta, statements);
+        }
         match *self.resolve_type(expression)? {
             TypeInner::Struct {
                 block: _,
@@ -128,10 +354,13 @@ impl Program<'_> {
                     .iter()
                     .position(|m| m.name == Some(name.into()))
                     .ok_or_else(|| ErrorKind::UnknownField(meta, name.into()))?;
-                Ok(self.context.expressions.append(Expression::AccessIndex {
-                    base: expression,
-                    index: index as u32,
-                }))
+                Ok(self.context.add_expression(
+                    Expression::AccessIndex {
+                        base: expression,
+                        index: index as u32,
+                    },
+                    statements,
+                ))
             }
             // swizzles (xyzw, rgba, stpq)
             TypeInner::Vector { size, kind, width } => {
@@ -157,43 +386,37 @@ impl Program<'_> {
                     .or_else(|| check_swizzle_components("stpq"));
 
                 if let Some(v) = indices {
-                    let components: Vec<Handle<Expression>> = v
-                        .iter()
-                        .map(|idx| {
-                            self.context.expressions.append(Expression::AccessIndex {
+                    let mut components = Vec::with_capacity(v.len());
+                    for idx in v.iter() {
+                        components.push(self.context.add_expression(
+                            Expression::AccessIndex {
                                 base: expression,
                                 index: *idx as u32,
-                            })
-                        })
-                        .collect();
+                            },
+                            statements,
+                        ));
+                    }
                     if components.len() == 1 {
                         // only single element swizzle, like pos.y, just return that component
                         Ok(components[0])
                     } else {
-                        Ok(self.context.expressions.append(Expression::Compose {
-                            ty: self.module.types.fetch_or_append(Type {
-                                name: None,
-                                inner: TypeInner::Vector {
-                                    kind,
-                                    width,
-                                    size: match components.len() {
-                                        2 => VectorSize::Bi,
-                                        3 => VectorSize::Tri,
-                                        4 => VectorSize::Quad,
-                                        _ => {
-                                            return Err(ErrorKind::SemanticError(
-                                                format!(
-                                                    "Bad swizzle size for \"{:?}\": {:?}",
-                                                    name, v
-                                                )
-                                                .into(),
-                                            ));
-                                        }
-                                    },
-                                },
-                            }),
-                            components,
-                        }))
+                        let size = match components.len() {
+                            2 => VectorSize::Bi,
+                            3 => VectorSize::Tri,
+                            4 => VectorSize::Quad,
+                            _ => {
+                                return Err(ErrorKind::SemanticError(
+                                    format!("Bad swizzle size for \"{:?}\": {:?}", name, v).into(),
+                                ));
+                            }
+                        };
+                        let ty = self.module.types.fetch_or_append(Type {
+                            name: None,
+                            inner: TypeInner::Vector { kind, width, size },
+                        });
+                        Ok(self
+                            .context
+                            .add_expression(Expression::Compose { ty, components }, statements))
                     }
                 } else {
                     Err(ErrorKind::SemanticError(
@@ -0,0 +1,382 @@
+/*! Keywords of the IR text format.
+
+This is shared by the parser in `front::ir` and the printer in `back::ir`,
+so that every enumerant is spelled the same way in both directions.
+!*/
+// the parser and the printer use different parts of it
+#![cfg_attr(not(all(feature = "ir-in", feature = "ir-out")), allow(dead_code))]
+
+use crate::{
+    BinaryOperator as Bo, BuiltIn as Bi, CollectiveOperation as Co, ConservativeDepth as Cd,
+    DerivativeAxis as Da, GeometryInput as Gi, GeometryOutput as Go, ImageDimension as Id,
+    Interpolation as In, MathFunction as Mf, RelationalFunction as Rf, ScalarKind as Sk, Scope,
+    ShaderStage as Ss, StorageClass as Sc, StorageFormat as Sf, SubgroupOperation as So,
+    TessellationPrimitive as Tp, TessellationSpacing as Ts, UnaryOperator as Uo, VertexOrder as Vo,
+};
+
+/// Table of the keywords spelling the values of an enumeration.
+pub(crate) type Keywords<T> = &'static [(T, &'static str)];
+
+/// Returns the keyword of a value.
+pub(crate) fn keyword<T: PartialEq>(table: Keywords<T>, value: T) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|&(v, _)| *v == value)
+        .map(|&(_, name)| name)
+}
+
+/// Returns the value spelled by a keyword.
+pub(crate) fn lookup<T: Copy>(table: Keywords<T>, name: &str) -> Option<T> {
+    table.iter().find(|&&(_, n)| n == name).map(|&(v, _)| v)
+}
+
+pub(crate) const SHADER_STAGES: Keywords<Ss> = &[
+    (Ss::Vertex, "vertex"),
+    (Ss::Fragment, "fragment"),
+    (Ss::Compute, "compute"),
+    (Ss::Geometry, "geometry"),
+    (Ss::TessellationControl, "tessellation_control"),
+    (Ss::TessellationEvaluation, "tessellation_evaluation"),
+    (Ss::RayGeneration, "ray_generation"),
+    (Ss::Intersection, "intersection"),
+    (Ss::AnyHit, "any_hit"),
+    (Ss::ClosestHit, "closest_hit"),
+    (Ss::Miss, "miss"),
+    (Ss::Callable, "callable"),
+];
+
+pub(crate) const CONSERVATIVE_DEPTHS: Keywords<Cd> = &[
+    (Cd::GreaterEqual, "greater_equal"),
+    (Cd::LessEqual, "less_equal"),
+    (Cd::Unchanged, "unchanged"),
+];
+
+pub(crate) const GEOMETRY_INPUTS: Keywords<Gi> = &[
+    (Gi::Points, "points"),
+    (Gi::Lines, "lines"),
+    (Gi::LinesAdjacency, "lines_adjacency"),
+    (Gi::Triangles, "triangles"),
+    (Gi::TrianglesAdjacency, "triangles_adjacency"),
+];
+
+pub(crate) const GEOMETRY_OUTPUTS: Keywords<Go> = &[
+    (Go::Points, "points"),
+    (Go::LineStrip, "line_strip"),
+    (Go::TriangleStrip, "triangle_strip"),
+];
+
+pub(crate) const TESSELLATION_PRIMITIVES: Keywords<Tp> = &[
+    (Tp::Triangles, "triangles"),
+    (Tp::Quads, "quads"),
+    (Tp::Isolines, "isolines"),
+];
+
+pub(crate) const TESSELLATION_SPACINGS: Keywords<Ts> = &[
+    (Ts::Equal, "equal"),
+    (Ts::FractionalEven, "fractional_even"),
+    (Ts::FractionalOdd, "fractional_odd"),
+];
+
+pub(crate) const VERTEX_ORDERS: Keywords<Vo> =
+    &[(Vo::Clockwise, "cw"), (Vo::CounterClockwise, "ccw")];
+
+pub(crate) const STORAGE_CLASSES: Keywords<Sc> = &[
+    (Sc::Function, "function"),
+    (Sc::Input, "input"),
+    (Sc::Output, "output"),
+    (Sc::Private, "private"),
+    (Sc::WorkGroup, "workgroup"),
+    (Sc::Uniform, "uniform"),
+    (Sc::Storage, "storage"),
+    (Sc::Handle, "handle"),
+    (Sc::PushConstant, "push_constant"),
+    (Sc::RayPayload, "ray_payload"),
+    (Sc::IncomingRayPayload, "incoming_ray_payload"),
+    (Sc::HitAttribute, "hit_attribute"),
+];
+
+pub(crate) const BUILT_INS: Keywords<Bi> = &[
+    (Bi::BaseInstance, "base_instance"),
+    (Bi::BaseVertex, "base_vertex"),
+    (Bi::ClipDistance, "clip_distance"),
+    (Bi::InstanceIndex, "instance_index"),
+    (Bi::PointSize, "point_size"),
+    (Bi::Position, "position"),
+    (Bi::VertexIndex, "vertex_index"),
+    (Bi::FragCoord, "frag_coord"),
+    (Bi::FragDepth, "frag_depth"),
+    (Bi::FrontFacing, "front_facing"),
+    (Bi::SampleIndex, "sample_index"),
+    (Bi::SampleMaskIn, "sample_mask_in"),
+    (Bi::SampleMaskOut, "sample_mask_out"),
+    (Bi::GlobalInvocationId, "global_invocation_id"),
+    (Bi::LocalInvocationId, "local_invocation_id"),
+    (Bi::LocalInvocationIndex, "local_invocation_index"),
+    (Bi::WorkGroupId, "workgroup_id"),
+    (Bi::WorkGroupSize, "workgroup_size"),
+    (Bi::SubgroupSize, "subgroup_size"),
+    (Bi::SubgroupInvocationId, "subgroup_invocation_id"),
+    (Bi::InvocationId, "invocation_id"),
+    (Bi::PatchVertices, "patch_vertices"),
+    (Bi::TessCoord, "tess_coord"),
+    (Bi::TessLevelInner, "tess_level_inner"),
+    (Bi::TessLevelOuter, "tess_level_outer"),
+    (Bi::LaunchId, "launch_id"),
+    (Bi::LaunchSize, "launch_size"),
+    (Bi::WorldRayOrigin, "world_ray_origin"),
+    (Bi::WorldRayDirection, "world_ray_direction"),
+    (Bi::ObjectRayOrigin, "object_ray_origin"),
+    (Bi::ObjectRayDirection, "object_ray_direction"),
+    (Bi::RayTmin, "ray_tmin"),
+    (Bi::RayTmax, "ray_tmax"),
+    (Bi::InstanceCustomIndex, "instance_custom_index"),
+    (Bi::ObjectToWorld, "object_to_world"),
+    (Bi::WorldToObject, "world_to_object"),
+    (Bi::HitKind, "hit_kind"),
+    (Bi::IncomingRayFlags, "incoming_ray_flags"),
+];
+
+pub(crate) const SCALAR_KINDS: Keywords<Sk> = &[
+    (Sk::Sint, "sint"),
+    (Sk::Uint, "uint"),
+    (Sk::Float, "float"),
+    (Sk::Bool, "bool"),
+];
+
+pub(crate) const INTERPOLATIONS: Keywords<In> = &[
+    (In::Perspective, "perspective"),
+    (In::Linear, "linear"),
+    (In::Flat, "flat"),
+    (In::Patch, "patch"),
+    (In::Centroid, "centroid"),
+    (In::Sample, "sample"),
+];
+
+pub(crate) const IMAGE_DIMENSIONS: Keywords<Id> = &[
+    (Id::D1, "d1"),
+    (Id::D2, "d2"),
+    (Id::D3, "d3"),
+    (Id::Cube, "cube"),
+];
+
+pub(crate) const STORAGE_FORMATS: Keywords<Sf> = &[
+    (Sf::R8Unorm, "r8unorm"),
+    (Sf::R8Snorm, "r8snorm"),
+    (Sf::R8Uint, "r8uint"),
+    (Sf::R8Sint, "r8sint"),
+    (Sf::R16Uint, "r16uint"),
+    (Sf::R16Sint, "r16sint"),
+    (Sf::R16Float, "r16float"),
+    (Sf::Rg8Unorm, "rg8unorm"),
+    (Sf::Rg8Snorm, "rg8snorm"),
+    (Sf::Rg8Uint, "rg8uint"),
+    (Sf::Rg8Sint, "rg8sint"),
+    (Sf::R32Uint, "r32uint"),
+    (Sf::R32Sint, "r32sint"),
+    (Sf::R32Float, "r32float"),
+    (Sf::Rg16Uint, "rg16uint"),
+    (Sf::Rg16Sint, "rg16sint"),
+    (Sf::Rg16Float, "rg16float"),
+    (Sf::Rgba8Unorm, "rgba8unorm"),
+    (Sf::Rgba8Snorm, "rgba8snorm"),
+    (Sf::Rgba8Uint, "rgba8uint"),
+    (Sf::Rgba8Sint, "rgba8sint"),
+    (Sf::Rgb10a2Unorm, "rgb10a2unorm"),
+    (Sf::Rg11b10Float, "rg11b10float"),
+    (Sf::Rg32Uint, "rg32uint"),
+    (Sf::Rg32Sint, "rg32sint"),
+    (Sf::Rg32Float, "rg32float"),
+    (Sf::Rgba16Uint, "rgba16uint"),
+    (Sf::Rgba16Sint, "rgba16sint"),
+    (Sf::Rgba16Float, "rgba16float"),
+    (Sf::Rgba32Uint, "rgba32uint"),
+    (Sf::Rgba32Sint, "rgba32sint"),
+    (Sf::Rgba32Float, "rgba32float"),
+];
+
+pub(crate) const UNARY_OPERATORS: Keywords<Uo> = &[(Uo::Negate, "negate"), (Uo::Not, "not")];
+
+pub(crate) const BINARY_OPERATORS: Keywords<Bo> = &[
+    (Bo::Add, "add"),
+    (Bo::Subtract, "subtract"),
+    (Bo::Multiply, "multiply"),
+    (Bo::Divide, "divide"),
+    (Bo::Modulo, "modulo"),
+    (Bo::Equal, "equal"),
+    (Bo::NotEqual, "not_equal"),
+    (Bo::Less, "less"),
+    (Bo::LessEqual, "less_equal"),
+    (Bo::Greater, "greater"),
+    (Bo::GreaterEqual, "greater_equal"),
+    (Bo::And, "and"),
+    (Bo::ExclusiveOr, "exclusive_or"),
+    (Bo::InclusiveOr, "inclusive_or"),
+    (Bo::LogicalAnd, "logical_and"),
+    (Bo::LogicalOr, "logical_or"),
+    (Bo::ShiftLeft, "shift_left"),
+    (Bo::ShiftRight, "shift_right"),
+];
+
+pub(crate) const DERIVATIVE_AXES: Keywords<Da> =
+    &[(Da::X, "x"), (Da::Y, "y"), (Da::Width, "width")];
+
+pub(crate) const RELATIONAL_FUNCTIONS: Keywords<Rf> = &[
+    (Rf::All, "all"),
+    (Rf::Any, "any"),
+    (Rf::IsNan, "is_nan"),
+    (Rf::IsInf, "is_inf"),
+    (Rf::IsFinite, "is_finite"),
+    (Rf::IsNormal, "is_normal"),
+];
+
+pub(crate) const MATH_FUNCTIONS: Keywords<Mf> = &[
+    (Mf::Abs, "abs"),
+    (Mf::Min, "min"),
+    (Mf::Max, "max"),
+    (Mf::Clamp, "clamp"),
+    (Mf::Cos, "cos"),
+    (Mf::Cosh, "cosh"),
+    (Mf::Sin, "sin"),
+    (Mf::Sinh, "sinh"),
+    (Mf::Tan, "tan"),
+    (Mf::Tanh, "tanh"),
+    (Mf::Acos, "acos"),
+    (Mf::Asin, "asin"),
+    (Mf::Atan, "atan"),
+    (Mf::Atan2, "atan2"),
+    (Mf::Ceil, "ceil"),
+    (Mf::Floor, "floor"),
+    (Mf::Round, "round"),
+    (Mf::Fract, "fract"),
+    (Mf::Trunc, "trunc"),
+    (Mf::Modf, "modf"),
+    (Mf::Frexp, "frexp"),
+    (Mf::Ldexp, "ldexp"),
+    (Mf::Exp, "exp"),
+    (Mf::Exp2, "exp2"),
+    (Mf::Log, "log"),
+    (Mf::Log2, "log2"),
+    (Mf::Pow, "pow"),
+    (Mf::Dot, "dot"),
+    (Mf::Outer, "outer"),
+    (Mf::Cross, "cross"),
+    (Mf::Distance, "distance"),
+    (Mf::Length, "length"),
+    (Mf::Normalize, "normalize"),
+    (Mf::FaceForward, "face_forward"),
+    (Mf::Reflect, "reflect"),
+    (Mf::Sign, "sign"),
+    (Mf::Fma, "fma"),
+    (Mf::Mix, "mix"),
+    (Mf::Step, "step"),
+    (Mf::SmoothStep, "smooth_step"),
+    (Mf::Sqrt, "sqrt"),
+    (Mf::InverseSqrt, "inverse_sqrt"),
+    (Mf::Inverse, "inverse"),
+    (Mf::Transpose, "transpose"),
+    (Mf::Determinant, "determinant"),
+    (Mf::CountOneBits, "count_one_bits"),
+    (Mf::ReverseBits, "reverse_bits"),
+];
+
+/// Atomic functions without operands of their own.
+/// `AtomicFunction::Exchange` is spelled "exchange".
+pub(crate) const ATOMIC_FUNCTIONS: Keywords<crate::AtomicFunction> = &[
+    (crate::AtomicFunction::Add, "add"),
+    (crate::AtomicFunction::Subtract, "subtract"),
+    (crate::AtomicFunction::And, "and"),
+    (crate::AtomicFunction::ExclusiveOr, "exclusive_or"),
+    (crate::AtomicFunction::InclusiveOr, "inclusive_or"),
+    (crate::AtomicFunction::Min, "min"),
+    (crate::AtomicFunction::Max, "max"),
+];
+
+pub(crate) const SCOPES: Keywords<Scope> = &[
+    (Scope::SubGroup, "subgroup"),
+    (Scope::WorkGroup, "workgroup"),
+    (Scope::Device, "device"),
+];
+
+pub(crate) const BARRIERS: Keywords<crate::Barrier> = &[
+    (crate::Barrier::STORAGE, "storage"),
+    (crate::Barrier::WORK_GROUP, "workgroup"),
+    (crate::Barrier::IMAGE, "image"),
+];
+
+pub(crate) const STORAGE_ACCESSES: Keywords<crate::StorageAccess> = &[
+    (crate::StorageAccess::LOAD, "load"),
+    (crate::StorageAccess::STORE, "store"),
+];
+
+pub(crate) const SUBGROUP_OPERATIONS: Keywords<So> = &[
+    (So::All, "all"),
+    (So::Any, "any"),
+    (So::Add, "add"),
+    (So::Multiply, "multiply"),
+    (So::Min, "min"),
+    (So::Max, "max"),
+    (So::And, "and"),
+    (So::InclusiveOr, "inclusive_or"),
+    (So::ExclusiveOr, "exclusive_or"),
+];
+
+pub(crate) const COLLECTIVE_OPERATIONS: Keywords<Co> = &[
+    (Co::Reduce, "reduce"),
+    (Co::InclusiveScan, "inclusive_scan"),
+    (Co::ExclusiveScan, "exclusive_scan"),
+];
+
+/// Prefixes of the references to the arena items.
+pub(crate) const TYPE_PREFIX: char = 'T';
+pub(crate) const CONSTANT_PREFIX: char = 'C';
+pub(crate) const GLOBAL_PREFIX: char = 'G';
+pub(crate) const FUNCTION_PREFIX: char = 'F';
+pub(crate) const LOCAL_PREFIX: char = 'L';
+pub(crate) const EXPRESSION_PREFIX: char = '%';
+
+/// Returns the name of a scalar type, such as "f32" or "bool".
+pub(crate) fn scalar_name(kind: Sk, width: crate::Bytes) -> String {
+    let prefix = match kind {
+        Sk::Bool if width == crate::BOOL_WIDTH => return "bool".to_string(),
+        Sk::Sint => 'i',
+        Sk::Uint => 'u',
+        Sk::Float => 'f',
+        Sk::Bool => 'b',
+    };
+    format!("{}{}", prefix, u32::from(width) * 8)
+}
+
+/// Parses the name of a scalar type.
+pub(crate) fn parse_scalar(name: &str) -> Option<(Sk, crate::Bytes)> {
+    if name == "bool" {
+        return Some((Sk::Bool, crate::BOOL_WIDTH));
+    }
+    let kind = match name.chars().next()? {
+        'i' => Sk::Sint,
+        'u' => Sk::Uint,
+        'f' => Sk::Float,
+        'b' => Sk::Bool,
+        _ => return None,
+    };
+    let bits: u32 = name[1..].parse().ok()?;
+    if bits == 0 || !bits.is_multiple_of(8) || bits > 8 * 255 || name[1..].starts_with('0') {
+        return None;
+    }
+    Some((kind, (bits / 8) as crate::Bytes))
+}
+
+#[test]
+fn scalar_names() {
+    for &(kind, width) in &[
+        (Sk::Sint, 4),
+        (Sk::Uint, 8),
+        (Sk::Float, 2),
+        (Sk::Bool, 1),
+        (Sk::Bool, 4),
+    ] {
+        assert_eq!(parse_scalar(&scalar_name(kind, width)), Some((kind, width)));
+    }
+    assert_eq!(parse_scalar("f"), None);
+    assert_eq!(parse_scalar("i12"), None);
+    assert_eq!(parse_scalar("u032"), None);
+}
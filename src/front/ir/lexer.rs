@@ -0,0 +1,143 @@
+use super::{ParseError, ParseErrorKind};
+use crate::Span;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Token<'a> {
+    /// Keyword, scalar type or a reference to a module item, like `T0`.
+    Word(&'a str),
+    /// Number, possibly negative, like `-1`, `0.5` or `-inf`.
+    Number(&'a str),
+    String(String),
+    /// Reference to an expression, like `%3`, with the index.
+    Expression(&'a str),
+    Punct(&'static str),
+    End,
+}
+
+const PUNCTUATION: &[&str] = &["..", "->", "{", "}", "(", ")", "<", ">", ",", "=", ":", "@"];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns the length of the number at the start of `input`.
+fn number_length(input: &str) -> usize {
+    let mut after_exponent = false;
+    let mut end = input.len();
+    for (index, c) in input.char_indices().skip(1) {
+        let exponent_sign = after_exponent && (c == '-' || c == '+');
+        after_exponent = c == 'e' || c == 'E';
+        if !(is_word_char(c) || c == '.' || exponent_sign) || input[index..].starts_with("..") {
+            end = index;
+            break;
+        }
+    }
+    end
+}
+
+/// Parses a string literal with the escapes of Rust's `{:?}` formatting,
+/// returning the string and the length of the literal.
+fn string_literal(input: &str, start: usize) -> Result<(String, usize), ParseError> {
+    let unterminated = || ParseError {
+        span: Span::from(start..start + input.len()),
+        kind: ParseErrorKind::UnterminatedString,
+    };
+    let mut string = String::new();
+    let mut chars = input.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((string, index + 1)),
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 't')) => '\t',
+                    Some((_, '0')) => '\0',
+                    Some((_, 'u')) => {
+                        let rest = &input[index + 2..];
+                        let code = match (rest.starts_with('{'), rest.find('}')) {
+                            (true, Some(close)) => u32::from_str_radix(&rest[1..close], 16)
+                                .ok()
+                                .and_then(std::char::from_u32),
+                            _ => None,
+                        };
+                        match code {
+                            Some(c) => {
+                                for _ in 0..rest.find('}').unwrap_or(0) + 1 {
+                                    chars.next();
+                                }
+                                c
+                            }
+                            None => {
+                                return Err(ParseError {
+                                    span: Span::from(start + index..start + index + 2),
+                                    kind: ParseErrorKind::InvalidEscape,
+                                })
+                            }
+                        }
+                    }
+                    Some((_, c @ '\\')) | Some((_, c @ '"')) | Some((_, c @ '\'')) => c,
+                    Some((other, _)) => {
+                        return Err(ParseError {
+                            span: Span::from(start + index..start + other + 1),
+                            kind: ParseErrorKind::InvalidEscape,
+                        })
+                    }
+                    None => return Err(unterminated()),
+                };
+                string.push(escaped);
+            }
+            _ => string.push(c),
+        }
+    }
+    Err(unterminated())
+}
+
+/// Splits the source into tokens, ending with `Token::End`.
+pub(super) fn tokenize(source: &str) -> Result<Vec<(Token<'_>, Span)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    loop {
+        let rest = &source[offset..];
+        let trimmed = rest.trim_start();
+        offset += rest.len() - trimmed.len();
+        let rest = trimmed;
+        if rest.starts_with("//") {
+            offset += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => {
+                tokens.push((Token::End, Span::from(offset..offset)));
+                return Ok(tokens);
+            }
+        };
+        let next = rest[c.len_utf8()..].chars().next();
+        let (token, length) = if c == '"' {
+            let (string, length) = string_literal(rest, offset)?;
+            (Token::String(string), length)
+        } else if c.is_ascii_digit() || (c == '-' && matches!(next, Some(c) if is_word_char(c))) {
+            let length = number_length(rest);
+            (Token::Number(&rest[..length]), length)
+        } else if c == '%' && matches!(next, Some(c) if c.is_ascii_digit()) {
+            let length = 1 + rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or_else(|| rest.len() - 1);
+            (Token::Expression(&rest[1..length]), length)
+        } else if is_word_char(c) {
+            let length = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+            (Token::Word(&rest[..length]), length)
+        } else if let Some(&punct) = PUNCTUATION.iter().find(|&&p| rest.starts_with(p)) {
+            (Token::Punct(punct), punct.len())
+        } else {
+            return Err(ParseError {
+                span: Span::from(offset..offset + c.len_utf8()),
+                kind: ParseErrorKind::UnexpectedCharacter(c),
+            });
+        };
+        tokens.push((token, Span::from(offset..offset + length)));
+        offset += length;
+    }
+}
@@ -0,0 +1,20 @@
+/*! Parser of the IR text format.
+
+This reads back the text printed by `back::ir`, see the documentation
+there for an overview of the syntax. Items are labelled with their index
+in the arena, which has to match the order of declaration, so that the
+parsed module has exactly the same handles as the printed one.
+
+The keywords live in `grammar`, which `back::ir` prints with.
+!*/
+
+pub(crate) mod grammar;
+#[cfg(feature = "ir-in")]
+mod lexer;
+#[cfg(feature = "ir-in")]
+mod parser;
+#[cfg(all(test, feature = "ir-in"))]
+mod tests;
+
+#[cfg(feature = "ir-in")]
+pub use self::parser::{parse_str, ParseError, ParseErrorKind};
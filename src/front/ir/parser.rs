@@ -0,0 +1,1533 @@
+use super::{
+    grammar::{self, lookup, Keywords},
+    lexer::{self, Token},
+};
+use crate::{
+    arena::{Arena, Handle},
+    Block, Expression, ScalarKind, Span, Statement, TypeInner,
+};
+use std::{num::NonZeroU32, str::FromStr};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ParseErrorKind {
+    #[error("unexpected character `{0}`")]
+    UnexpectedCharacter(char),
+    #[error("unterminated string literal")]
+    UnterminatedString,
+    #[error("invalid escape sequence")]
+    InvalidEscape,
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("expected {expected}, found `{found}`")]
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("unknown {what} `{name}`")]
+    UnknownKeyword { what: &'static str, name: String },
+    #[error("unknown attribute `{0}`")]
+    UnknownAttribute(String),
+    #[error("expected label `{expected}`, found `{found}`")]
+    InvalidLabel { expected: String, found: String },
+    #[error("`{0}` doesn't refer to a declared item")]
+    InvalidReference(String),
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{kind}")]
+pub struct ParseError {
+    /// Location of the error in the source.
+    pub span: Span,
+    pub kind: ParseErrorKind,
+}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// Reference to a module item, which is checked after the whole module is parsed.
+#[derive(Clone, Copy)]
+enum ModuleReference {
+    Type(usize),
+    Constant(usize),
+    Global(usize),
+    Function(usize),
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, Span)>,
+    position: usize,
+    module_references: Vec<(ModuleReference, Span)>,
+    /// Local variable references of the current function.
+    local_references: Vec<(usize, Span)>,
+    /// Expression references of the current function.
+    /// Ends of `emit` ranges are included as well.
+    expression_references: Vec<(usize, Span)>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token<'a> {
+        &self.tokens[self.position].0
+    }
+
+    fn next(&mut self) -> (Token<'a>, Span) {
+        let token = self.tokens[self.position].clone();
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn start(&self) -> Span {
+        self.tokens[self.position].1
+    }
+
+    /// Returns the span from `start` up to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        match self.position {
+            0 => start,
+            position => start.union(&self.tokens[position - 1].1),
+        }
+    }
+
+    fn unexpected<T>(&self, expected: &'static str) -> Result<T> {
+        let (ref token, span) = self.tokens[self.position];
+        let found = match *token {
+            Token::Word(text) | Token::Number(text) | Token::Punct(text) => text.to_string(),
+            Token::String(ref string) => format!("{:?}", string),
+            Token::Expression(index) => format!("{}{}", grammar::EXPRESSION_PREFIX, index),
+            Token::End => "end of file".to_string(),
+        };
+        Err(ParseError {
+            span,
+            kind: ParseErrorKind::Unexpected { expected, found },
+        })
+    }
+
+    fn skip(&mut self, punct: &'static str) -> bool {
+        if *self.peek() == Token::Punct(punct) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<()> {
+        if self.skip(punct) {
+            Ok(())
+        } else {
+            self.unexpected(punct)
+        }
+    }
+
+    fn skip_word(&mut self, word: &str) -> bool {
+        if *self.peek() == Token::Word(word) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn word(&mut self, expected: &'static str) -> Result<(&'a str, Span)> {
+        match *self.peek() {
+            Token::Word(word) => Ok((word, self.next().1)),
+            _ => self.unexpected(expected),
+        }
+    }
+
+    fn keyword<T: Copy>(&mut self, table: Keywords<T>, what: &'static str) -> Result<T> {
+        let (name, span) = self.word(what)?;
+        lookup(table, name).ok_or_else(|| ParseError {
+            span,
+            kind: ParseErrorKind::UnknownKeyword {
+                what,
+                name: name.to_string(),
+            },
+        })
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T> {
+        match *self.peek() {
+            Token::Number(text) => {
+                let span = self.next().1;
+                text.parse().map_err(|_| ParseError {
+                    span,
+                    kind: ParseErrorKind::InvalidNumber(text.to_string()),
+                })
+            }
+            _ => self.unexpected("number"),
+        }
+    }
+
+    fn non_zero(&mut self) -> Result<NonZeroU32> {
+        let start = self.start();
+        let value = self.number()?;
+        NonZeroU32::new(value).ok_or_else(|| ParseError {
+            span: start,
+            kind: ParseErrorKind::InvalidNumber(value.to_string()),
+        })
+    }
+
+    /// Parses an optional name of an item.
+    fn name(&mut self) -> Option<String> {
+        match *self.peek() {
+            Token::String(ref string) => {
+                let string = string.clone();
+                self.position += 1;
+                Some(string)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses the name of a member or an argument, which is `_` if absent.
+    fn member_name(&mut self) -> Result<Option<String>> {
+        if self.skip_word("_") {
+            Ok(None)
+        } else {
+            match self.name() {
+                Some(name) => Ok(Some(name)),
+                None => self.unexpected("name"),
+            }
+        }
+    }
+
+    /// Parses the index of a reference like `T3`.
+    fn reference(&mut self, prefix: char, expected: &'static str) -> Result<(usize, Span)> {
+        let (word, span) = self.word(expected)?;
+        let index = if word.starts_with(prefix) && word.len() > 1 {
+            word[1..].parse().ok()
+        } else {
+            None
+        };
+        match index {
+            Some(index) => Ok((index, span)),
+            None => {
+                self.position -= 1;
+                self.unexpected(expected)
+            }
+        }
+    }
+
+    /// Parses the label of a declaration, which must match the `index` in its arena.
+    fn label(&mut self, prefix: char, index: usize) -> Result<()> {
+        let start = self.start();
+        let found = match *self.peek() {
+            Token::Word(word) => word.to_string(),
+            Token::Expression(text) => format!("{}{}", grammar::EXPRESSION_PREFIX, text),
+            _ => return self.unexpected("label"),
+        };
+        self.position += 1;
+        let expected = format!("{}{}", prefix, index);
+        if found == expected {
+            Ok(())
+        } else {
+            Err(ParseError {
+                span: start,
+                kind: ParseErrorKind::InvalidLabel { expected, found },
+            })
+        }
+    }
+
+    fn module_reference<T>(
+        &mut self,
+        prefix: char,
+        expected: &'static str,
+        reference: fn(usize) -> ModuleReference,
+    ) -> Result<Handle<T>> {
+        let (index, span) = self.reference(prefix, expected)?;
+        self.module_references.push((reference(index), span));
+        Ok(Handle::from_usize(index))
+    }
+
+    fn type_handle(&mut self) -> Result<Handle<crate::Type>> {
+        self.module_reference(grammar::TYPE_PREFIX, "type", ModuleReference::Type)
+    }
+
+    fn constant_handle(&mut self) -> Result<Handle<crate::Constant>> {
+        self.module_reference(
+            grammar::CONSTANT_PREFIX,
+            "constant",
+            ModuleReference::Constant,
+        )
+    }
+
+    fn expression_index(&mut self) -> Result<(usize, Span)> {
+        match *self.peek() {
+            Token::Expression(text) => {
+                let span = self.next().1;
+                text.parse()
+                    .map(|index| (index, span))
+                    .map_err(|_| ParseError {
+                        span,
+                        kind: ParseErrorKind::InvalidNumber(text.to_string()),
+                    })
+            }
+            _ => self.unexpected("expression"),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Handle<Expression>> {
+        let (index, span) = self.expression_index()?;
+        self.expression_references.push((index, span));
+        Ok(Handle::from_usize(index))
+    }
+
+    /// Parses a list of expressions, separated by commas.
+    fn expr_list(&mut self) -> Result<Vec<Handle<Expression>>> {
+        let mut list = vec![self.expr()?];
+        while self.skip(",") {
+            list.push(self.expr()?);
+        }
+        Ok(list)
+    }
+
+    /// Parses a parenthesized list, separated by commas.
+    fn parenthesized<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.expect("(")?;
+        let mut list = Vec::new();
+        if !self.skip(")") {
+            loop {
+                list.push(item(self)?);
+                if !self.skip(",") {
+                    self.expect(")")?;
+                    break;
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    /// Parses the name of the next attribute, if there is one.
+    fn attribute(&mut self) -> Result<Option<(&'a str, Span)>> {
+        if self.skip("@") {
+            self.word("attribute").map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Parses the arguments of the attributes of entry point inputs and outputs.
+    /// Returns `false` if the attribute is not one of them.
+    fn binding_attribute(
+        &mut self,
+        name: &str,
+        binding: &mut Option<crate::Binding>,
+        interpolation: &mut Option<crate::Interpolation>,
+    ) -> Result<bool> {
+        self.expect("(")?;
+        match name {
+            "builtin" => {
+                let built_in = self.keyword(grammar::BUILT_INS, "built-in")?;
+                *binding = Some(crate::Binding::BuiltIn(built_in));
+            }
+            "location" => *binding = Some(crate::Binding::Location(self.number()?)),
+            "resource" => {
+                let group = self.number()?;
+                self.expect(",")?;
+                let index = self.number()?;
+                *binding = Some(crate::Binding::Resource {
+                    group,
+                    binding: index,
+                });
+            }
+            "interpolation" => {
+                *interpolation = Some(self.keyword(grammar::INTERPOLATIONS, "interpolation")?)
+            }
+            _ => {
+                self.position -= 1;
+                return Ok(false);
+            }
+        }
+        self.expect(")")?;
+        Ok(true)
+    }
+
+    fn unknown_attribute<T>(name: &str, span: Span) -> Result<T> {
+        Err(ParseError {
+            span,
+            kind: ParseErrorKind::UnknownAttribute(name.to_string()),
+        })
+    }
+
+    /// Parses the attributes of an entry point input or output.
+    fn bindings(&mut self) -> Result<(Option<crate::Binding>, Option<crate::Interpolation>)> {
+        let (mut binding, mut interpolation) = (None, None);
+        while let Some((name, span)) = self.attribute()? {
+            if !self.binding_attribute(name, &mut binding, &mut interpolation)? {
+                return Self::unknown_attribute(name, span);
+            }
+        }
+        Ok((binding, interpolation))
+    }
+
+    fn scalar(&mut self) -> Result<(ScalarKind, crate::Bytes)> {
+        let (name, span) = self.word("scalar type")?;
+        grammar::parse_scalar(name).ok_or_else(|| ParseError {
+            span,
+            kind: ParseErrorKind::UnknownKeyword {
+                what: "scalar type",
+                name: name.to_string(),
+            },
+        })
+    }
+
+    fn vector_size(digit: &str) -> Option<crate::VectorSize> {
+        match digit {
+            "2" => Some(crate::VectorSize::Bi),
+            "3" => Some(crate::VectorSize::Tri),
+            "4" => Some(crate::VectorSize::Quad),
+            _ => None,
+        }
+    }
+
+    /// Parses a scalar or a vector type, like `f32` or `vec3<f32>`.
+    fn value_type(&mut self) -> Result<(Option<crate::VectorSize>, ScalarKind, crate::Bytes)> {
+        if let Token::Word(word) = *self.peek() {
+            if word.starts_with("vec") {
+                if let Some(size) = Self::vector_size(&word[3..]) {
+                    self.position += 1;
+                    self.expect("<")?;
+                    let (kind, width) = self.scalar()?;
+                    self.expect(">")?;
+                    return Ok((Some(size), kind, width));
+                }
+            }
+        }
+        let (kind, width) = self.scalar()?;
+        Ok((None, kind, width))
+    }
+
+    fn type_inner(&mut self) -> Result<TypeInner> {
+        let (word, _) = self.word("type")?;
+        let inner = match word {
+            "atomic" => {
+                self.expect("<")?;
+                let (kind, width) = self.scalar()?;
+                self.expect(">")?;
+                TypeInner::Atomic { kind, width }
+            }
+            "ptr" => {
+                self.expect("<")?;
+                let class = self.keyword(grammar::STORAGE_CLASSES, "storage class")?;
+                self.expect(",")?;
+                let inner = match *self.peek() {
+                    Token::Word(word) if word.starts_with(grammar::TYPE_PREFIX) => {
+                        TypeInner::Pointer {
+                            base: self.type_handle()?,
+                            class,
+                        }
+                    }
+                    _ => {
+                        let (size, kind, width) = self.value_type()?;
+                        TypeInner::ValuePointer {
+                            size,
+                            kind,
+                            width,
+                            class,
+                        }
+                    }
+                };
+                self.expect(">")?;
+                inner
+            }
+            "array" => {
+                self.expect("<")?;
+                let base = self.type_handle()?;
+                let size = if self.skip(",") {
+                    crate::ArraySize::Constant(self.constant_handle()?)
+                } else {
+                    crate::ArraySize::Dynamic
+                };
+                self.expect(">")?;
+                let mut stride = None;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "stride" => {
+                            self.expect("(")?;
+                            stride = Some(self.non_zero()?);
+                            self.expect(")")?;
+                        }
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                TypeInner::Array { base, size, stride }
+            }
+            "struct" => {
+                let mut block = false;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "block" => block = true,
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                self.expect("{")?;
+                let mut members = Vec::new();
+                while !self.skip("}") {
+                    let name = self.member_name()?;
+                    self.expect(":")?;
+                    let ty = self.type_handle()?;
+                    let (mut binding, mut interpolation, mut span) = (None, None, None);
+                    while let Some((attribute, attribute_span)) = self.attribute()? {
+                        if attribute == "span" {
+                            self.expect("(")?;
+                            span = Some(self.non_zero()?);
+                            self.expect(")")?;
+                        } else if !self.binding_attribute(
+                            attribute,
+                            &mut binding,
+                            &mut interpolation,
+                        )? {
+                            return Self::unknown_attribute(attribute, attribute_span);
+                        }
+                    }
+                    members.push(crate::StructMember {
+                        name,
+                        span,
+                        ty,
+                        binding,
+                        interpolation,
+                    });
+                    if !self.skip(",") {
+                        self.expect("}")?;
+                        break;
+                    }
+                }
+                TypeInner::Struct { block, members }
+            }
+            "image" => {
+                self.expect("<")?;
+                let dim = self.keyword(grammar::IMAGE_DIMENSIONS, "image dimension")?;
+                self.expect(",")?;
+                let arrayed = self.skip_word("arrayed");
+                if arrayed {
+                    self.expect(",")?;
+                }
+                let (class_name, class_span) = self.word("image class")?;
+                let class = match class_name {
+                    "sampled" => {
+                        self.expect("<")?;
+                        let kind = self.keyword(grammar::SCALAR_KINDS, "scalar kind")?;
+                        let multi = self.skip(",");
+                        if multi && !self.skip_word("multi") {
+                            return self.unexpected("multi");
+                        }
+                        self.expect(">")?;
+                        crate::ImageClass::Sampled { kind, multi }
+                    }
+                    "depth" => crate::ImageClass::Depth,
+                    "storage" => {
+                        self.expect("<")?;
+                        let format = self.keyword(grammar::STORAGE_FORMATS, "storage format")?;
+                        self.expect(">")?;
+                        crate::ImageClass::Storage(format)
+                    }
+                    _ => {
+                        return Err(ParseError {
+                            span: class_span,
+                            kind: ParseErrorKind::UnknownKeyword {
+                                what: "image class",
+                                name: class_name.to_string(),
+                            },
+                        })
+                    }
+                };
+                self.expect(">")?;
+                TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class,
+                }
+            }
+            "sampler" => {
+                let mut comparison = false;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "comparison" => comparison = true,
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                TypeInner::Sampler { comparison }
+            }
+            "acceleration_structure" => TypeInner::AccelerationStructure,
+            _ if word.starts_with("mat") && word.len() == 6 && &word[4..5] == "x" => {
+                let columns = Self::vector_size(&word[3..4]);
+                let rows = Self::vector_size(&word[5..]);
+                let (columns, rows) = match (columns, rows) {
+                    (Some(columns), Some(rows)) => (columns, rows),
+                    _ => {
+                        self.position -= 1;
+                        return self.unexpected("type");
+                    }
+                };
+                self.expect("<")?;
+                let (kind, width) = self.scalar()?;
+                if kind != ScalarKind::Float {
+                    self.position -= 1;
+                    return self.unexpected("floating-point type");
+                }
+                self.expect(">")?;
+                TypeInner::Matrix {
+                    columns,
+                    rows,
+                    width,
+                }
+            }
+            _ => {
+                self.position -= 1;
+                match self.value_type()? {
+                    (Some(size), kind, width) => TypeInner::Vector { size, kind, width },
+                    (None, kind, width) => TypeInner::Scalar { kind, width },
+                }
+            }
+        };
+        Ok(inner)
+    }
+
+    fn parse_type(&mut self, module: &mut crate::Module) -> Result<()> {
+        let start = self.start();
+        self.label(grammar::TYPE_PREFIX, module.types.len())?;
+        let name = self.name();
+        self.expect("=")?;
+        let inner = self.type_inner()?;
+        let span = self.span_from(start);
+        module
+            .types
+            .append_with_span(crate::Type { name, inner }, span);
+        Ok(())
+    }
+
+    fn scalar_value(&mut self, kind: ScalarKind) -> Result<crate::ScalarValue> {
+        use crate::ScalarValue as Sv;
+        Ok(match kind {
+            ScalarKind::Sint => Sv::Sint(self.number()?),
+            ScalarKind::Uint => Sv::Uint(self.number()?),
+            ScalarKind::Float => match *self.peek() {
+                // `inf` and `NaN` are lexed as words
+                Token::Word(word) => {
+                    let span = self.next().1;
+                    Sv::Float(word.parse().map_err(|_| ParseError {
+                        span,
+                        kind: ParseErrorKind::InvalidNumber(word.to_string()),
+                    })?)
+                }
+                _ => Sv::Float(self.number()?),
+            },
+            ScalarKind::Bool => {
+                if self.skip_word("true") {
+                    Sv::Bool(true)
+                } else if self.skip_word("false") {
+                    Sv::Bool(false)
+                } else {
+                    return self.unexpected("boolean");
+                }
+            }
+        })
+    }
+
+    fn parse_constant(&mut self, module: &mut crate::Module) -> Result<()> {
+        let start = self.start();
+        self.label(grammar::CONSTANT_PREFIX, module.constants.len())?;
+        let name = self.name();
+        self.expect("=")?;
+        let inner = if self.skip_word("composite") {
+            let ty = self.type_handle()?;
+            let components = self.parenthesized(Self::constant_handle)?;
+            crate::ConstantInner::Composite { ty, components }
+        } else {
+            let (kind, width) = self.scalar()?;
+            let value = self.scalar_value(kind)?;
+            crate::ConstantInner::Scalar { width, value }
+        };
+        let mut specialization = None;
+        while let Some((name, span)) = self.attribute()? {
+            match name {
+                "specialization" => {
+                    self.expect("(")?;
+                    specialization = Some(self.number()?);
+                    self.expect(")")?;
+                }
+                _ => return Self::unknown_attribute(name, span),
+            }
+        }
+        let span = self.span_from(start);
+        module.constants.append_with_span(
+            crate::Constant {
+                name,
+                specialization,
+                inner,
+            },
+            span,
+        );
+        Ok(())
+    }
+
+    fn parse_global(&mut self, module: &mut crate::Module) -> Result<()> {
+        let start = self.start();
+        self.label(grammar::GLOBAL_PREFIX, module.global_variables.len())?;
+        let name = self.name();
+        let class = self.keyword(grammar::STORAGE_CLASSES, "storage class")?;
+        let ty = self.type_handle()?;
+        let init = if self.skip("=") {
+            Some(self.constant_handle()?)
+        } else {
+            None
+        };
+        let (mut binding, mut interpolation) = (None, None);
+        let mut storage_access = crate::StorageAccess::empty();
+        while let Some((attribute, span)) = self.attribute()? {
+            if attribute == "access" {
+                let flags = self.parenthesized(|parser| {
+                    parser.keyword(grammar::STORAGE_ACCESSES, "storage access")
+                })?;
+                for flag in flags {
+                    storage_access |= flag;
+                }
+            } else if !self.binding_attribute(attribute, &mut binding, &mut interpolation)? {
+                return Self::unknown_attribute(attribute, span);
+            }
+        }
+        let span = self.span_from(start);
+        module.global_variables.append_with_span(
+            crate::GlobalVariable {
+                name,
+                class,
+                binding,
+                ty,
+                init,
+                interpolation,
+                storage_access,
+            },
+            span,
+        );
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Expression> {
+        let (word, span) = self.word("expression")?;
+        let expression = match word {
+            "access" => {
+                let base = self.expr()?;
+                self.expect(",")?;
+                let index = self.expr()?;
+                Expression::Access { base, index }
+            }
+            "access_index" => {
+                let base = self.expr()?;
+                self.expect(",")?;
+                let index = self.number()?;
+                Expression::AccessIndex { base, index }
+            }
+            "constant" => Expression::Constant(self.constant_handle()?),
+            "compose" => {
+                let ty = self.type_handle()?;
+                let components = self.parenthesized(Self::expr)?;
+                Expression::Compose { ty, components }
+            }
+            "argument" => Expression::FunctionArgument(self.number()?),
+            "global" => Expression::GlobalVariable(self.module_reference(
+                grammar::GLOBAL_PREFIX,
+                "global variable",
+                ModuleReference::Global,
+            )?),
+            "local" => {
+                let (index, span) = self.reference(grammar::LOCAL_PREFIX, "local variable")?;
+                self.local_references.push((index, span));
+                Expression::LocalVariable(Handle::from_usize(index))
+            }
+            "load" => Expression::Load {
+                pointer: self.expr()?,
+            },
+            "image_sample" => {
+                let image = self.expr()?;
+                self.expect(",")?;
+                let sampler = self.expr()?;
+                self.expect(",")?;
+                let coordinate = self.expr()?;
+                let (mut array_index, mut offset, mut depth_ref) = (None, None, None);
+                let mut level = crate::SampleLevel::Auto;
+                while let Some((name, span)) = self.attribute()? {
+                    self.expect("(")?;
+                    match name {
+                        "array_index" => array_index = Some(self.expr()?),
+                        "offset" => offset = Some(self.constant_handle()?),
+                        "depth_ref" => depth_ref = Some(self.expr()?),
+                        "level" => {
+                            let (kind, kind_span) = self.word("sample level")?;
+                            level = match kind {
+                                "zero" => crate::SampleLevel::Zero,
+                                "exact" => {
+                                    self.expect(",")?;
+                                    crate::SampleLevel::Exact(self.expr()?)
+                                }
+                                "bias" => {
+                                    self.expect(",")?;
+                                    crate::SampleLevel::Bias(self.expr()?)
+                                }
+                                "gradient" => {
+                                    self.expect(",")?;
+                                    let x = self.expr()?;
+                                    self.expect(",")?;
+                                    let y = self.expr()?;
+                                    crate::SampleLevel::Gradient { x, y }
+                                }
+                                _ => {
+                                    return Err(ParseError {
+                                        span: kind_span,
+                                        kind: ParseErrorKind::UnknownKeyword {
+                                            what: "sample level",
+                                            name: kind.to_string(),
+                                        },
+                                    })
+                                }
+                            };
+                        }
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                    self.expect(")")?;
+                }
+                Expression::ImageSample {
+                    image,
+                    sampler,
+                    coordinate,
+                    array_index,
+                    offset,
+                    level,
+                    depth_ref,
+                }
+            }
+            "image_load" => {
+                let image = self.expr()?;
+                self.expect(",")?;
+                let coordinate = self.expr()?;
+                let (mut array_index, mut index) = (None, None);
+                while let Some((name, span)) = self.attribute()? {
+                    self.expect("(")?;
+                    match name {
+                        "array_index" => array_index = Some(self.expr()?),
+                        "index" => index = Some(self.expr()?),
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                    self.expect(")")?;
+                }
+                Expression::ImageLoad {
+                    image,
+                    coordinate,
+                    array_index,
+                    index,
+                }
+            }
+            "image_query" => {
+                let (kind, kind_span) = self.word("image query")?;
+                let image = self.expr()?;
+                let query = match kind {
+                    "size" => {
+                        let mut level = None;
+                        while let Some((name, span)) = self.attribute()? {
+                            match name {
+                                "level" => {
+                                    self.expect("(")?;
+                                    level = Some(self.expr()?);
+                                    self.expect(")")?;
+                                }
+                                _ => return Self::unknown_attribute(name, span),
+                            }
+                        }
+                        crate::ImageQuery::Size { level }
+                    }
+                    "num_levels" => crate::ImageQuery::NumLevels,
+                    "num_layers" => crate::ImageQuery::NumLayers,
+                    "num_samples" => crate::ImageQuery::NumSamples,
+                    _ => {
+                        return Err(ParseError {
+                            span: kind_span,
+                            kind: ParseErrorKind::UnknownKeyword {
+                                what: "image query",
+                                name: kind.to_string(),
+                            },
+                        })
+                    }
+                };
+                Expression::ImageQuery { image, query }
+            }
+            "unary" => {
+                let op = self.keyword(grammar::UNARY_OPERATORS, "unary operator")?;
+                Expression::Unary {
+                    op,
+                    expr: self.expr()?,
+                }
+            }
+            "binary" => {
+                let op = self.keyword(grammar::BINARY_OPERATORS, "binary operator")?;
+                let left = self.expr()?;
+                self.expect(",")?;
+                let right = self.expr()?;
+                Expression::Binary { op, left, right }
+            }
+            "select" => {
+                let condition = self.expr()?;
+                self.expect(",")?;
+                let accept = self.expr()?;
+                self.expect(",")?;
+                let reject = self.expr()?;
+                Expression::Select {
+                    condition,
+                    accept,
+                    reject,
+                }
+            }
+            "derivative" => {
+                let axis = self.keyword(grammar::DERIVATIVE_AXES, "derivative axis")?;
+                Expression::Derivative {
+                    axis,
+                    expr: self.expr()?,
+                }
+            }
+            "relational" => {
+                let fun = self.keyword(grammar::RELATIONAL_FUNCTIONS, "relational function")?;
+                Expression::Relational {
+                    fun,
+                    argument: self.expr()?,
+                }
+            }
+            "math" => {
+                let fun = self.keyword(grammar::MATH_FUNCTIONS, "math function")?;
+                let start = self.start();
+                let args = self.expr_list()?;
+                if args.len() > 3 {
+                    return Err(ParseError {
+                        span: self.span_from(start),
+                        kind: ParseErrorKind::Unexpected {
+                            expected: "at most 3 arguments",
+                            found: format!("{} arguments", args.len()),
+                        },
+                    });
+                }
+                Expression::Math {
+                    fun,
+                    arg: args[0],
+                    arg1: args.get(1).cloned(),
+                    arg2: args.get(2).cloned(),
+                }
+            }
+            "convert" | "bitcast" => {
+                let kind = self.keyword(grammar::SCALAR_KINDS, "scalar kind")?;
+                Expression::As {
+                    expr: self.expr()?,
+                    kind,
+                    convert: word == "convert",
+                }
+            }
+            "call" => Expression::Call(self.module_reference(
+                grammar::FUNCTION_PREFIX,
+                "function",
+                ModuleReference::Function,
+            )?),
+            "array_length" => Expression::ArrayLength(self.expr()?),
+            "atomic_result" => {
+                let (kind, width) = self.scalar()?;
+                let mut comparison = false;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "comparison" => comparison = true,
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                Expression::AtomicResult {
+                    kind,
+                    width,
+                    comparison,
+                }
+            }
+            "subgroup_result" => {
+                let (size, kind, width) = self.value_type()?;
+                Expression::SubgroupResult { kind, width, size }
+            }
+            "report_intersection_result" => Expression::ReportIntersectionResult,
+            _ => {
+                return Err(ParseError {
+                    span,
+                    kind: ParseErrorKind::UnknownKeyword {
+                        what: "expression",
+                        name: word.to_string(),
+                    },
+                })
+            }
+        };
+        Ok(expression)
+    }
+
+    /// Parses an optional `-> %N` result of a statement.
+    fn result(&mut self) -> Result<Option<Handle<Expression>>> {
+        if self.skip("->") {
+            self.expr().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn required_result(&mut self) -> Result<Handle<Expression>> {
+        self.expect("->")?;
+        self.expr()
+    }
+
+    fn block(&mut self) -> Result<Block> {
+        self.expect("{")?;
+        self.statements()
+    }
+
+    /// Parses statements up to the closing brace of a block.
+    fn statements(&mut self) -> Result<Block> {
+        let mut block = Block::new();
+        while !self.skip("}") {
+            let start = self.start();
+            let statement = self.statement()?;
+            block.push_with_span(statement, self.span_from(start));
+        }
+        Ok(block)
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let (word, span) = self.word("statement")?;
+        let statement = match word {
+            "emit" => {
+                let (start, _) = self.expression_index()?;
+                self.expect("..")?;
+                let (end, end_span) = self.expression_index()?;
+                if end < start {
+                    return Err(ParseError {
+                        span: self.span_from(span),
+                        kind: ParseErrorKind::InvalidReference(format!(
+                            "{p}{}..{p}{}",
+                            start,
+                            end,
+                            p = grammar::EXPRESSION_PREFIX
+                        )),
+                    });
+                }
+                // the end is exclusive, so it may be equal to the number of expressions
+                if end > 0 {
+                    self.expression_references.push((end - 1, end_span));
+                }
+                Statement::Emit(crate::arena::Range::from_index_range(
+                    start as u32..end as u32,
+                ))
+            }
+            "block" => Statement::Block(self.block()?),
+            "if" => {
+                let condition = self.expr()?;
+                let accept = self.block()?;
+                let reject = if self.skip_word("else") {
+                    self.block()?
+                } else {
+                    Block::new()
+                };
+                Statement::If {
+                    condition,
+                    accept,
+                    reject,
+                }
+            }
+            "switch" => {
+                let selector = self.expr()?;
+                self.expect("{")?;
+                let mut cases = Vec::new();
+                let mut default = Block::new();
+                while !self.skip("}") {
+                    if self.skip_word("default") {
+                        default = self.block()?;
+                        continue;
+                    }
+                    if !self.skip_word("case") {
+                        return self.unexpected("case");
+                    }
+                    let value = self.number()?;
+                    let mut fall_through = false;
+                    while let Some((name, span)) = self.attribute()? {
+                        match name {
+                            "fallthrough" => fall_through = true,
+                            _ => return Self::unknown_attribute(name, span),
+                        }
+                    }
+                    cases.push(crate::SwitchCase {
+                        value,
+                        body: self.block()?,
+                        fall_through,
+                    });
+                }
+                Statement::Switch {
+                    selector,
+                    cases,
+                    default,
+                }
+            }
+            "loop" => {
+                let body = self.block()?;
+                let continuing = if self.skip_word("continuing") {
+                    self.block()?
+                } else {
+                    Block::new()
+                };
+                Statement::Loop { body, continuing }
+            }
+            "break" => Statement::Break,
+            "continue" => Statement::Continue,
+            "return" => {
+                let value = match *self.peek() {
+                    Token::Expression(_) => Some(self.expr()?),
+                    _ => None,
+                };
+                Statement::Return { value }
+            }
+            "kill" => Statement::Kill,
+            "store" => {
+                let pointer = self.expr()?;
+                self.expect(",")?;
+                let value = self.expr()?;
+                Statement::Store { pointer, value }
+            }
+            "image_store" => {
+                let image = self.expr()?;
+                self.expect(",")?;
+                let coordinate = self.expr()?;
+                self.expect(",")?;
+                let value = self.expr()?;
+                let mut array_index = None;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "array_index" => {
+                            self.expect("(")?;
+                            array_index = Some(self.expr()?);
+                            self.expect(")")?;
+                        }
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                Statement::ImageStore {
+                    image,
+                    coordinate,
+                    array_index,
+                    value,
+                }
+            }
+            "call" => {
+                let function = self.module_reference(
+                    grammar::FUNCTION_PREFIX,
+                    "function",
+                    ModuleReference::Function,
+                )?;
+                let arguments = self.parenthesized(Self::expr)?;
+                Statement::Call {
+                    function,
+                    arguments,
+                    result: self.result()?,
+                }
+            }
+            "atomic" => {
+                let exchange = self.skip_word("exchange");
+                let fun = if exchange {
+                    None
+                } else {
+                    Some(self.keyword(grammar::ATOMIC_FUNCTIONS, "atomic function")?)
+                };
+                let pointer = self.expr()?;
+                self.expect(",")?;
+                let value = self.expr()?;
+                let mut compare = None;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "compare" if exchange => {
+                            self.expect("(")?;
+                            compare = Some(self.expr()?);
+                            self.expect(")")?;
+                        }
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                Statement::Atomic {
+                    pointer,
+                    fun: fun.unwrap_or(crate::AtomicFunction::Exchange { compare }),
+                    value,
+                    result: self.required_result()?,
+                }
+            }
+            "barrier" => {
+                let memory_scope = self.keyword(grammar::SCOPES, "scope")?;
+                let flags =
+                    self.parenthesized(|parser| parser.keyword(grammar::BARRIERS, "barrier"))?;
+                let memory = flags
+                    .into_iter()
+                    .fold(crate::Barrier::empty(), |all, flag| all | flag);
+                let mut execution = None;
+                while let Some((name, span)) = self.attribute()? {
+                    match name {
+                        "execution" => {
+                            self.expect("(")?;
+                            execution = Some(self.keyword(grammar::SCOPES, "scope")?);
+                            self.expect(")")?;
+                        }
+                        _ => return Self::unknown_attribute(name, span),
+                    }
+                }
+                Statement::Barrier {
+                    execution,
+                    memory_scope,
+                    memory,
+                }
+            }
+            "subgroup" => {
+                use crate::{GatherMode as Gm, SubgroupFunction as Sf};
+                let (name, name_span) = self.word("subgroup function")?;
+                let fun = match name {
+                    "elect" => Sf::Elect,
+                    "ballot" => {
+                        let mut predicate = None;
+                        while let Some((name, span)) = self.attribute()? {
+                            match name {
+                                "predicate" => {
+                                    self.expect("(")?;
+                                    predicate = Some(self.expr()?);
+                                    self.expect(")")?;
+                                }
+                                _ => return Self::unknown_attribute(name, span),
+                            }
+                        }
+                        Sf::Ballot { predicate }
+                    }
+                    "broadcast_first" => Sf::Gather {
+                        mode: Gm::BroadcastFirst,
+                        argument: self.expr()?,
+                    },
+                    "broadcast" | "shuffle" | "shuffle_down" | "shuffle_up" | "shuffle_xor" => {
+                        let argument = self.expr()?;
+                        self.expect(",")?;
+                        let index = self.expr()?;
+                        let mode = match name {
+                            "broadcast" => Gm::Broadcast(index),
+                            "shuffle" => Gm::Shuffle(index),
+                            "shuffle_down" => Gm::ShuffleDown(index),
+                            "shuffle_up" => Gm::ShuffleUp(index),
+                            _ => Gm::ShuffleXor(index),
+                        };
+                        Sf::Gather { mode, argument }
+                    }
+                    _ => match lookup(grammar::COLLECTIVE_OPERATIONS, name) {
+                        Some(collective) => {
+                            let op =
+                                self.keyword(grammar::SUBGROUP_OPERATIONS, "subgroup operation")?;
+                            Sf::Collective {
+                                op,
+                                collective,
+                                argument: self.expr()?,
+                            }
+                        }
+                        None => {
+                            return Err(ParseError {
+                                span: name_span,
+                                kind: ParseErrorKind::UnknownKeyword {
+                                    what: "subgroup function",
+                                    name: name.to_string(),
+                                },
+                            })
+                        }
+                    },
+                };
+                Statement::Subgroup {
+                    fun,
+                    result: self.required_result()?,
+                }
+            }
+            "emit_vertex" => Statement::EmitVertex,
+            "end_primitive" => Statement::EndPrimitive,
+            "trace_ray" => {
+                let start = self.start();
+                let operands = self.expr_list()?;
+                if operands.len() != 11 {
+                    return Err(ParseError {
+                        span: self.span_from(start),
+                        kind: ParseErrorKind::Unexpected {
+                            expected: "11 operands",
+                            found: format!("{} operands", operands.len()),
+                        },
+                    });
+                }
+                Statement::TraceRay {
+                    acceleration_structure: operands[0],
+                    flags: operands[1],
+                    cull_mask: operands[2],
+                    sbt_offset: operands[3],
+                    sbt_stride: operands[4],
+                    miss_index: operands[5],
+                    origin: operands[6],
+                    t_min: operands[7],
+                    direction: operands[8],
+                    t_max: operands[9],
+                    payload: operands[10],
+                }
+            }
+            "report_intersection" => {
+                let hit_t = self.expr()?;
+                self.expect(",")?;
+                let hit_kind = self.expr()?;
+                Statement::ReportIntersection {
+                    hit_t,
+                    hit_kind,
+                    result: self.required_result()?,
+                }
+            }
+            "ignore_intersection" => Statement::IgnoreIntersection,
+            "terminate_ray" => Statement::TerminateRay,
+            _ => {
+                return Err(ParseError {
+                    span,
+                    kind: ParseErrorKind::UnknownKeyword {
+                        what: "statement",
+                        name: word.to_string(),
+                    },
+                })
+            }
+        };
+        Ok(statement)
+    }
+
+    /// Parses a function after the `fn` keyword and the label, if any.
+    fn function(&mut self) -> Result<crate::Function> {
+        let mut function = crate::Function {
+            name: self.name(),
+            ..Default::default()
+        };
+        function.arguments = self.parenthesized(|parser| {
+            let name = parser.member_name()?;
+            parser.expect(":")?;
+            let ty = parser.type_handle()?;
+            let (binding, interpolation) = parser.bindings()?;
+            Ok(crate::FunctionArgument {
+                name,
+                ty,
+                binding,
+                interpolation,
+            })
+        })?;
+        if self.skip("->") {
+            let ty = self.type_handle()?;
+            let (binding, interpolation) = self.bindings()?;
+            function.result = Some(crate::FunctionResult {
+                ty,
+                binding,
+                interpolation,
+            });
+        }
+
+        self.expect("{")?;
+        while *self.peek() == Token::Word("local") {
+            let start = self.next().1;
+            self.label(grammar::LOCAL_PREFIX, function.local_variables.len())?;
+            let name = self.name();
+            let ty = self.type_handle()?;
+            let init = if self.skip("=") {
+                Some(self.constant_handle()?)
+            } else {
+                None
+            };
+            let span = self.span_from(start);
+            function
+                .local_variables
+                .append_with_span(crate::LocalVariable { name, ty, init }, span);
+        }
+        while let Token::Expression(_) = *self.peek() {
+            let start = self.start();
+            self.label(grammar::EXPRESSION_PREFIX, function.expressions.len())?;
+            self.expect("=")?;
+            let expression = self.expression()?;
+            let span = self.span_from(start);
+            function.expressions.append_with_span(expression, span);
+        }
+        function.body = self.statements()?;
+
+        check_references(
+            self.local_references.drain(..),
+            grammar::LOCAL_PREFIX,
+            &function.local_variables,
+        )?;
+        check_references(
+            self.expression_references.drain(..),
+            grammar::EXPRESSION_PREFIX,
+            &function.expressions,
+        )?;
+        Ok(function)
+    }
+
+    fn entry_point(&mut self) -> Result<crate::EntryPoint> {
+        let name = match self.name() {
+            Some(name) => name,
+            None => return self.unexpected("entry point name"),
+        };
+        let stage = self.keyword(grammar::SHADER_STAGES, "shader stage")?;
+        let mut entry_point = crate::EntryPoint {
+            name,
+            stage,
+            early_depth_test: None,
+            workgroup_size: [0; 3],
+            geometry: None,
+            tessellation: None,
+            function: crate::Function::default(),
+        };
+        while let Some((name, span)) = self.attribute()? {
+            match name {
+                "workgroup_size" => {
+                    self.expect("(")?;
+                    for (i, size) in entry_point.workgroup_size.iter_mut().enumerate() {
+                        if i != 0 {
+                            self.expect(",")?;
+                        }
+                        *size = self.number()?;
+                    }
+                    self.expect(")")?;
+                }
+                "early_depth_test" => {
+                    let conservative = if self.skip("(") {
+                        let depth =
+                            self.keyword(grammar::CONSERVATIVE_DEPTHS, "conservative depth")?;
+                        self.expect(")")?;
+                        Some(depth)
+                    } else {
+                        None
+                    };
+                    entry_point.early_depth_test = Some(crate::EarlyDepthTest { conservative });
+                }
+                "geometry" => {
+                    self.expect("(")?;
+                    let input = self.keyword(grammar::GEOMETRY_INPUTS, "geometry input")?;
+                    self.expect(",")?;
+                    let output = self.keyword(grammar::GEOMETRY_OUTPUTS, "geometry output")?;
+                    self.expect(",")?;
+                    let max_vertices = self.number()?;
+                    self.expect(")")?;
+                    entry_point.geometry = Some(crate::GeometryInfo {
+                        input,
+                        output,
+                        max_vertices,
+                    });
+                }
+                "tessellation" => {
+                    self.expect("(")?;
+                    let info = if self.skip_word("control") {
+                        self.expect(",")?;
+                        crate::TessellationInfo::Control {
+                            output_vertices: self.number()?,
+                        }
+                    } else if self.skip_word("evaluation") {
+                        self.expect(",")?;
+                        let primitive = self
+                            .keyword(grammar::TESSELLATION_PRIMITIVES, "tessellation primitive")?;
+                        self.expect(",")?;
+                        let spacing =
+                            self.keyword(grammar::TESSELLATION_SPACINGS, "tessellation spacing")?;
+                        self.expect(",")?;
+                        let vertex_order = self.keyword(grammar::VERTEX_ORDERS, "vertex order")?;
+                        crate::TessellationInfo::Evaluation {
+                            primitive,
+                            spacing,
+                            vertex_order,
+                        }
+                    } else {
+                        return self.unexpected("tessellation stage");
+                    };
+                    self.expect(")")?;
+                    entry_point.tessellation = Some(info);
+                }
+                _ => return Self::unknown_attribute(name, span),
+            }
+        }
+        if !self.skip_word("fn") {
+            return self.unexpected("fn");
+        }
+        entry_point.function = self.function()?;
+        Ok(entry_point)
+    }
+
+    fn parse(&mut self) -> Result<crate::Module> {
+        let mut module = crate::Module::default();
+        loop {
+            let (word, span) = match *self.peek() {
+                Token::End => break,
+                _ => self.word("declaration")?,
+            };
+            match word {
+                "type" => self.parse_type(&mut module)?,
+                "const" => self.parse_constant(&mut module)?,
+                "global" => self.parse_global(&mut module)?,
+                "fn" => {
+                    let start = self.start();
+                    self.label(grammar::FUNCTION_PREFIX, module.functions.len())?;
+                    let function = self.function()?;
+                    let span = self.span_from(start);
+                    module.functions.append_with_span(function, span);
+                }
+                "entry" => {
+                    let entry_point = self.entry_point()?;
+                    module.entry_points.push(entry_point);
+                }
+                _ => {
+                    return Err(ParseError {
+                        span,
+                        kind: ParseErrorKind::UnknownKeyword {
+                            what: "declaration",
+                            name: word.to_string(),
+                        },
+                    })
+                }
+            }
+        }
+
+        for &(reference, span) in self.module_references.iter() {
+            let (prefix, index, len) = match reference {
+                ModuleReference::Type(index) => (grammar::TYPE_PREFIX, index, module.types.len()),
+                ModuleReference::Constant(index) => {
+                    (grammar::CONSTANT_PREFIX, index, module.constants.len())
+                }
+                ModuleReference::Global(index) => {
+                    (grammar::GLOBAL_PREFIX, index, module.global_variables.len())
+                }
+                ModuleReference::Function(index) => {
+                    (grammar::FUNCTION_PREFIX, index, module.functions.len())
+                }
+            };
+            if index >= len {
+                return Err(ParseError {
+                    span,
+                    kind: ParseErrorKind::InvalidReference(format!("{}{}", prefix, index)),
+                });
+            }
+        }
+        Ok(module)
+    }
+}
+
+fn check_references<T>(
+    references: impl Iterator<Item = (usize, Span)>,
+    prefix: char,
+    arena: &Arena<T>,
+) -> Result<()> {
+    let mut references = references;
+    match references.find(|&(index, _)| index >= arena.len()) {
+        Some((index, span)) => Err(ParseError {
+            span,
+            kind: ParseErrorKind::InvalidReference(format!("{}{}", prefix, index)),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Parses a module from the IR text format.
+pub fn parse_str(source: &str) -> Result<crate::Module> {
+    let mut parser = Parser {
+        tokens: lexer::tokenize(source)?,
+        position: 0,
+        module_references: Vec::new(),
+        local_references: Vec::new(),
+        expression_references: Vec::new(),
+    };
+    parser.parse()
+}
@@ -0,0 +1,107 @@
+use super::{parse_str, ParseErrorKind};
+
+fn error_kind(source: &str) -> ParseErrorKind {
+    parse_str(source).unwrap_err().kind
+}
+
+#[test]
+fn parse_declarations() {
+    let module = parse_str(
+        "// comment
+        type T0 \"a \\\"quoted\\\" \\u{e9}\" = f32
+        type T1 = mat4x3<f32>
+        type T2 = ptr<private, vec2<i32>>
+        const C0 = f32 -inf
+        const C1 = f32 1e-7
+        const C2 = i32 -5 @specialization(3)
+        const C3 = composite T1 (C0, C1)
+        global G0 private T0 = C0",
+    )
+    .unwrap();
+    assert_eq!(
+        module.types.iter().next().unwrap().1.name.as_deref(),
+        Some("a \"quoted\" \u{e9}")
+    );
+    assert_eq!(module.constants.len(), 4);
+    assert_eq!(module.global_variables.len(), 1);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(
+        error_kind("type T1 = f32"),
+        ParseErrorKind::InvalidLabel {
+            expected: "T0".to_string(),
+            found: "T1".to_string(),
+        }
+    );
+    assert_eq!(
+        error_kind("type T0 = array<T1>"),
+        ParseErrorKind::InvalidReference("T1".to_string())
+    );
+    assert_eq!(
+        error_kind("type T0 = f33"),
+        ParseErrorKind::UnknownKeyword {
+            what: "scalar type",
+            name: "f33".to_string(),
+        }
+    );
+    assert_eq!(
+        error_kind("type T0 = sampler @foo"),
+        ParseErrorKind::UnknownAttribute("foo".to_string())
+    );
+    assert_eq!(
+        error_kind("type T0 \"name = f32"),
+        ParseErrorKind::UnterminatedString
+    );
+    assert_eq!(
+        error_kind("type T0 = u32 const C0 = u32 -1"),
+        ParseErrorKind::InvalidNumber("-1".to_string())
+    );
+    assert_eq!(
+        error_kind("type T0 = $"),
+        ParseErrorKind::UnexpectedCharacter('$')
+    );
+    assert_eq!(
+        error_kind("type T0 = u32 fn F0() {"),
+        ParseErrorKind::Unexpected {
+            expected: "statement",
+            found: "end of file".to_string(),
+        }
+    );
+}
+
+#[test]
+fn parse_function_references() {
+    let source = "type T0 = u32
+        fn F0(_: T0) -> T0 {
+            %0 = argument 0
+            %1 = unary negate %0
+            emit %1..%2
+            return %1
+        }";
+    parse_str(source).unwrap();
+    assert_eq!(
+        error_kind(&source.replace("emit %1..%2", "emit %1..%3")),
+        ParseErrorKind::InvalidReference("%2".to_string())
+    );
+    assert_eq!(
+        error_kind(&source.replace("return %1", "store %1, %5")),
+        ParseErrorKind::InvalidReference("%5".to_string())
+    );
+    assert_eq!(
+        error_kind(&source.replace("%1 = unary", "%2 = unary")),
+        ParseErrorKind::InvalidLabel {
+            expected: "%1".to_string(),
+            found: "%2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn parse_error_span() {
+    let source = "type T0 = u32\nconst C0 = u32 1\nglobal G0 privat T0";
+    let error = parse_str(source).unwrap_err();
+    assert_eq!(error.span.location(source), (3, 11));
+    assert_eq!(error.to_string(), "unknown storage class `privat`");
+}
@@ -2,6 +2,8 @@
 
 #[cfg(feature = "glsl-in")]
 pub mod glsl;
+#[cfg(any(feature = "ir-in", feature = "ir-out"))]
+pub mod ir;
 #[cfg(feature = "spv-in")]
 pub mod spv;
 #[cfg(feature = "wgsl-in")]
@@ -0,0 +1,574 @@
+/*! Assembler of the SPIR-V assembly text into a binary.
+
+The syntax is the one of the SPIRV-Tools assembler: one instruction per line,
+with the result id on the left of `=`, and the result type as the first operand.
+Ids are either numeric, like `%12`, which are kept as is, or named, like `%main`,
+which are given ids past the largest numeric one, in the order of appearance.
+
+Enumerants are written by name or as numbers, and masks are joined with `|`.
+The header is taken from the `; Version:`, `; Generator:` and `; Schema:`
+comments at the top of the source, if present.
+!*/
+
+use crate::{
+    spv_grammar::{self, BitEnum, Context, OperandKind, Quantifier, ValueEnum},
+    FastHashMap,
+};
+use spirv::{Op, Word};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum AssemblyErrorKind {
+    #[error("unterminated string literal")]
+    UnterminatedString,
+    #[error("unknown instruction `{0}`")]
+    UnknownInstruction(String),
+    #[error("instruction `{0}` doesn't produce a result")]
+    UnexpectedResult(String),
+    #[error("instruction `{0}` requires a result id")]
+    MissingResult(String),
+    #[error("expected an id, found `{0}`")]
+    ExpectedId(String),
+    #[error("expected a literal, found `{0}`")]
+    ExpectedLiteral(String),
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("the type of literal `{0}` is unknown")]
+    UnknownType(String),
+    #[error("unknown enumerant `{0}`")]
+    UnknownEnumerant(String),
+    #[error("unknown extended instruction `{0}`")]
+    UnknownExtInst(String),
+    #[error("missing operand")]
+    MissingOperand,
+    #[error("unexpected operand `{0}`")]
+    UnexpectedOperand(String),
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("line {line}: {kind}")]
+pub struct AssemblyError {
+    /// Line of the error, starting from 1.
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Equals,
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match *self {
+            Token::Word(ref word) => word.clone(),
+            Token::String(ref string) => format!("{:?}", string),
+            Token::Equals => "=".to_string(),
+        }
+    }
+}
+
+/// Splits a line into tokens, returning them together with the comment.
+fn tokenize(line: &str) -> Result<(Vec<Token>, Option<&str>), AssemblyErrorKind> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            ';' => return Ok((tokens, Some(&line[start + 1..]))),
+            '=' => {
+                chars.next();
+                tokens.push(Token::Equals);
+            }
+            '"' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => string.push(c),
+                            None => return Err(AssemblyErrorKind::UnterminatedString),
+                        },
+                        Some((_, c)) => string.push(c),
+                        None => return Err(AssemblyErrorKind::UnterminatedString),
+                    }
+                }
+                tokens.push(Token::String(string));
+            }
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut end = line.len();
+                while let Some(&(index, c)) = chars.peek() {
+                    if c.is_whitespace() || c == ';' || c == '=' || c == '"' {
+                        end = index;
+                        break;
+                    }
+                    chars.next();
+                }
+                tokens.push(Token::Word(line[start..end].to_string()));
+            }
+        }
+    }
+    Ok((tokens, None))
+}
+
+/// Returns the number of a numeric id, like `%12`.
+fn numeric_id(name: &str) -> Option<Word> {
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    name.parse().ok().filter(|&id| id != 0)
+}
+
+struct Header {
+    version: Word,
+    generator: Word,
+    schema: Word,
+}
+
+impl Header {
+    /// Applies a header comment, like `Version: 1.3`.
+    fn parse_comment(&mut self, comment: &str) -> Result<(), AssemblyErrorKind> {
+        let colon = match comment.find(':') {
+            Some(colon) => colon,
+            None => return Ok(()),
+        };
+        let value = comment[colon + 1..].trim();
+        let invalid = || AssemblyErrorKind::InvalidNumber(value.to_string());
+        let parse_word = |text: &str| {
+            spv_grammar::parse_integer(text)
+                .filter(|&v| v >= 0 && v <= i128::from(Word::MAX))
+                .map(|v| v as Word)
+        };
+        match comment[..colon].trim() {
+            "Version" => {
+                let mut parts = value.splitn(2, '.');
+                let major = parts.next().and_then(parse_word).ok_or_else(invalid)?;
+                let minor = parts.next().and_then(parse_word).ok_or_else(invalid)?;
+                if major > 0xff || minor > 0xff {
+                    return Err(invalid());
+                }
+                self.version = major << 16 | minor << 8;
+            }
+            "Generator" => self.generator = parse_word(value).ok_or_else(invalid)?,
+            "Schema" => self.schema = parse_word(value).ok_or_else(invalid)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+struct Assembler {
+    ops: FastHashMap<String, Op>,
+    value_enumerants: FastHashMap<ValueEnum, FastHashMap<String, Word>>,
+    ext_insts: FastHashMap<String, FastHashMap<String, Word>>,
+    ids: FastHashMap<String, Word>,
+    next_id: Word,
+    bound: Word,
+    context: Context,
+}
+
+/// Operand tokens of an instruction, with the words they are assembled into.
+struct Operands<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    words: Vec<Word>,
+}
+
+impl<'a> Operands<'a> {
+    fn is_empty(&self) -> bool {
+        self.position == self.tokens.len()
+    }
+
+    fn next(&mut self) -> Result<&'a Token, AssemblyErrorKind> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or(AssemblyErrorKind::MissingOperand)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn next_word(&mut self) -> Result<String, AssemblyErrorKind> {
+        match *self.next()? {
+            Token::Word(ref word) => Ok(word.clone()),
+            ref other => Err(AssemblyErrorKind::UnexpectedOperand(other.text())),
+        }
+    }
+}
+
+impl Assembler {
+    fn id(&mut self, token: &Token) -> Result<Word, AssemblyErrorKind> {
+        let name = match *token {
+            Token::Word(ref word) if word.len() > 1 && word.starts_with('%') => &word[1..],
+            _ => return Err(AssemblyErrorKind::ExpectedId(token.text())),
+        };
+        let id = match numeric_id(name) {
+            Some(id) => id,
+            None => {
+                if name.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(AssemblyErrorKind::ExpectedId(token.text()));
+                }
+                let next_id = &mut self.next_id;
+                *self.ids.entry(name.to_string()).or_insert_with(|| {
+                    *next_id += 1;
+                    *next_id - 1
+                })
+            }
+        };
+        self.bound = self.bound.max(id + 1);
+        Ok(id)
+    }
+
+    fn enumerant(&mut self, value_enum: ValueEnum, text: &str) -> Result<Word, AssemblyErrorKind> {
+        let enumerants = self
+            .value_enumerants
+            .entry(value_enum)
+            .or_insert_with(|| value_enum.enumerants().into_iter().collect());
+        match enumerants.get(text) {
+            Some(&value) => Ok(value),
+            None => parse_literal(text)
+                .map_err(|_| AssemblyErrorKind::UnknownEnumerant(text.to_string())),
+        }
+    }
+
+    fn ext_inst(&mut self, set: Option<Word>, text: &str) -> Result<Word, AssemblyErrorKind> {
+        if let Ok(value) = parse_literal(text) {
+            return Ok(value);
+        }
+        let set = self
+            .context
+            .ext_inst_set(set)
+            .ok_or_else(|| AssemblyErrorKind::UnknownExtInst(text.to_string()))?
+            .to_string();
+        let names = self
+            .ext_insts
+            .entry(set.clone())
+            .or_insert_with(|| spv_grammar::ext_inst_names(&set).into_iter().collect());
+        names
+            .get(text)
+            .cloned()
+            .ok_or_else(|| AssemblyErrorKind::UnknownExtInst(text.to_string()))
+    }
+
+    fn number(
+        &mut self,
+        ty: Option<spv_grammar::NumericType>,
+        operands: &mut Operands,
+    ) -> Result<(), AssemblyErrorKind> {
+        let text = match *operands.next()? {
+            Token::Word(ref word) => word.clone(),
+            ref other => return Err(AssemblyErrorKind::ExpectedLiteral(other.text())),
+        };
+        let ty = ty.ok_or_else(|| AssemblyErrorKind::UnknownType(text.clone()))?;
+        let bits = spv_grammar::parse_number(ty, &text)
+            .ok_or_else(|| AssemblyErrorKind::InvalidNumber(text.clone()))?;
+        operands.words.push(bits as Word);
+        if ty.width() > 32 {
+            operands.words.push((bits >> 32) as Word);
+        }
+        Ok(())
+    }
+
+    fn operand(
+        &mut self,
+        kind: OperandKind,
+        instruction_type: Option<Word>,
+        operands: &mut Operands,
+    ) -> Result<(), AssemblyErrorKind> {
+        match kind {
+            OperandKind::IdRef => {
+                let token = operands.next()?.clone();
+                let id = self.id(&token)?;
+                operands.words.push(id);
+            }
+            OperandKind::LiteralInteger => {
+                let text = match *operands.next()? {
+                    Token::Word(ref word) => word.clone(),
+                    ref other => return Err(AssemblyErrorKind::ExpectedLiteral(other.text())),
+                };
+                operands.words.push(parse_literal(&text)?);
+            }
+            OperandKind::LiteralString => match *operands.next()? {
+                Token::String(ref string) => {
+                    let encoded = spv_grammar::encode_string(string);
+                    operands.words.extend(encoded);
+                }
+                ref other => return Err(AssemblyErrorKind::ExpectedLiteral(other.text())),
+            },
+            OperandKind::LiteralContextDependentNumber => {
+                let ty = self.context.literal_type(instruction_type);
+                self.number(ty, operands)?;
+            }
+            OperandKind::LiteralExtInstInteger => {
+                let text = operands.next_word()?;
+                let set = operands.words.first().cloned();
+                let value = self.ext_inst(set, &text)?;
+                operands.words.push(value);
+            }
+            OperandKind::LiteralSpecConstantOpInteger => {
+                let text = operands.next_word()?;
+                let value = match self.ops.get(&format!("Op{}", text)) {
+                    Some(&op) => op as Word,
+                    None => parse_literal(&text)
+                        .map_err(|_| AssemblyErrorKind::UnknownInstruction(text.clone()))?,
+                };
+                operands.words.push(value);
+            }
+            OperandKind::PairLiteralIntegerIdRef => {
+                // the selector defines the width of the literals
+                let selector = operands.words.first().cloned();
+                let ty = self.context.selector_type(selector);
+                self.number(ty, operands)?;
+                self.operand(OperandKind::IdRef, None, operands)?;
+            }
+            OperandKind::PairIdRefLiteralInteger => {
+                self.operand(OperandKind::IdRef, None, operands)?;
+                self.operand(OperandKind::LiteralInteger, None, operands)?;
+            }
+            OperandKind::PairIdRefIdRef => {
+                self.operand(OperandKind::IdRef, None, operands)?;
+                self.operand(OperandKind::IdRef, None, operands)?;
+            }
+            OperandKind::Value(value_enum) => {
+                let text = operands.next_word()?;
+                let value = self.enumerant(value_enum, &text)?;
+                operands.words.push(value);
+                for &parameter in value_enum.parameters(value) {
+                    self.operand(parameter, None, operands)?;
+                }
+            }
+            OperandKind::Bits(bit_enum) => {
+                let text = operands.next_word()?;
+                let mask = parse_mask(bit_enum, &text)?;
+                operands.words.push(mask);
+                for &(_, bit, parameters) in bit_enum.enumerants() {
+                    if mask & bit != 0 {
+                        for &parameter in parameters {
+                            self.operand(parameter, None, operands)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        tokens: &[Token],
+        output: &mut Vec<Word>,
+    ) -> Result<(), AssemblyErrorKind> {
+        let (result, tokens) = match tokens.get(1) {
+            Some(&Token::Equals) => (Some(&tokens[0]), &tokens[2..]),
+            _ => (None, tokens),
+        };
+        let name = match tokens.first() {
+            Some(Token::Word(name)) => name.clone(),
+            Some(other) => return Err(AssemblyErrorKind::UnknownInstruction(other.text())),
+            None => return Err(AssemblyErrorKind::UnknownInstruction(String::new())),
+        };
+        let op = *self
+            .ops
+            .get(&name)
+            .ok_or_else(|| AssemblyErrorKind::UnknownInstruction(name.clone()))?;
+        let grammar = spv_grammar::instruction(op).unwrap();
+
+        let mut operands = Operands {
+            tokens: &tokens[1..],
+            position: 0,
+            words: Vec::new(),
+        };
+        let type_id = if grammar.has_type {
+            let token = operands.next()?.clone();
+            Some(self.id(&token)?)
+        } else {
+            None
+        };
+        let result_id = match (result, grammar.has_result) {
+            (Some(token), true) => Some(self.id(token)?),
+            (None, false) => None,
+            (Some(_), false) => return Err(AssemblyErrorKind::UnexpectedResult(name)),
+            (None, true) => return Err(AssemblyErrorKind::MissingResult(name)),
+        };
+
+        for &(kind, quantifier) in grammar.operands {
+            match quantifier {
+                Quantifier::One => self.operand(kind, type_id, &mut operands)?,
+                Quantifier::Optional => {
+                    if !operands.is_empty() {
+                        self.operand(kind, type_id, &mut operands)?;
+                    }
+                }
+                Quantifier::Variadic => {
+                    while !operands.is_empty() {
+                        self.operand(kind, type_id, &mut operands)?;
+                    }
+                }
+            }
+        }
+        if let Some(token) = operands.tokens.get(operands.position) {
+            return Err(AssemblyErrorKind::UnexpectedOperand(token.text()));
+        }
+
+        let word_count = 1 + type_id.iter().len() + result_id.iter().len() + operands.words.len();
+        output.push((word_count as Word) << 16 | op as Word);
+        output.extend(type_id);
+        output.extend(result_id);
+        output.extend_from_slice(&operands.words);
+
+        self.context.track(op, type_id, result_id, &operands.words);
+        Ok(())
+    }
+}
+
+/// Parses a literal integer operand, which is a single word.
+fn parse_literal(text: &str) -> Result<Word, AssemblyErrorKind> {
+    match spv_grammar::parse_integer(text) {
+        Some(value) if value >= i128::from(i32::MIN) && value < 0 => Ok(value as Word),
+        Some(value) if value >= 0 && value <= i128::from(Word::MAX) => Ok(value as Word),
+        _ => Err(AssemblyErrorKind::InvalidNumber(text.to_string())),
+    }
+}
+
+fn parse_mask(bit_enum: BitEnum, text: &str) -> Result<Word, AssemblyErrorKind> {
+    let mut mask = 0;
+    for name in text.split('|') {
+        if name == "None" {
+            continue;
+        }
+        mask |= match bit_enum.enumerants().iter().find(|&&(n, _, _)| n == name) {
+            Some(&(_, bit, _)) => bit,
+            None => parse_literal(name)
+                .map_err(|_| AssemblyErrorKind::UnknownEnumerant(name.to_string()))?,
+        };
+    }
+    Ok(mask)
+}
+
+/// Assembles the SPIR-V assembly text into the words of a binary.
+pub fn assemble(source: &str) -> Result<Vec<Word>, AssemblyError> {
+    let mut lines = Vec::new();
+    let mut header = Header {
+        version: 0x1_0000,
+        generator: 0,
+        schema: 0,
+    };
+    let mut max_id = 0;
+    for (index, line) in source.lines().enumerate() {
+        let error = |kind| AssemblyError {
+            line: index + 1,
+            kind,
+        };
+        let (tokens, comment) = tokenize(line).map_err(error)?;
+        if tokens.is_empty() {
+            // header comments are only taken before the first instruction
+            if let (Some(comment), true) = (comment, lines.is_empty()) {
+                header.parse_comment(comment).map_err(error)?;
+            }
+            continue;
+        }
+        for token in tokens.iter() {
+            if let Token::Word(ref word) = *token {
+                if word.starts_with('%') {
+                    max_id = max_id.max(numeric_id(&word[1..]).unwrap_or(0));
+                }
+            }
+        }
+        lines.push((index + 1, tokens));
+    }
+
+    let mut assembler = Assembler {
+        ops: spv_grammar::ops().into_iter().collect(),
+        value_enumerants: FastHashMap::default(),
+        ext_insts: FastHashMap::default(),
+        ids: FastHashMap::default(),
+        next_id: max_id + 1,
+        bound: 1,
+        context: Context::default(),
+    };
+    let mut instructions = Vec::new();
+    for (line, tokens) in lines {
+        assembler
+            .instruction(&tokens, &mut instructions)
+            .map_err(|kind| AssemblyError { line, kind })?;
+    }
+
+    let mut words = vec![
+        spirv::MAGIC_NUMBER,
+        header.version,
+        header.generator,
+        assembler.bound,
+        header.schema,
+    ];
+    words.extend(instructions);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{assemble, AssemblyError, AssemblyErrorKind};
+
+    #[test]
+    fn named_ids() {
+        let words = assemble(
+            "; Version: 1.3
+               OpCapability Shader
+               OpMemoryModel Logical GLSL450
+      %float = OpTypeFloat 32
+          %5 = OpConstant %float -1.5",
+        )
+        .unwrap();
+        assert_eq!(
+            words,
+            vec![
+                spirv::MAGIC_NUMBER,
+                0x1_0300,
+                0,
+                7,
+                0,
+                0x0002_0011,
+                1,
+                0x0003_000e,
+                0,
+                1,
+                0x0003_0016,
+                6,
+                32,
+                0x0004_002b,
+                6,
+                5,
+                (-1.5f32).to_bits(),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            assemble("OpCapability Shader\n%1 = OpCapability Shader"),
+            Err(AssemblyError {
+                line: 2,
+                kind: AssemblyErrorKind::UnexpectedResult("OpCapability".to_string()),
+            })
+        );
+        assert_eq!(
+            assemble("OpTypeFloat 32").unwrap_err().kind,
+            AssemblyErrorKind::MissingResult("OpTypeFloat".to_string())
+        );
+        assert_eq!(
+            assemble("%1 = OpTypeInt 32 0\n%2 = OpConstant %1 -1")
+                .unwrap_err()
+                .kind,
+            AssemblyErrorKind::InvalidNumber("-1".to_string())
+        );
+        assert_eq!(
+            assemble("OpName %1 \"main").unwrap_err().kind,
+            AssemblyErrorKind::UnterminatedString
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use crate::arena::{Arena, Handle};
+
+use super::Error;
+
+/// Upgrade the type of the value behind `ty` at the given access `path`
+/// to be atomic, returning the new type handle.
+///
+/// SPIR-V doesn't have atomic types, so we have to derive them from the usage.
+/// New types are appended to the arena, to keep them ordered after their bases.
+fn upgrade_type(
+    ty: Handle<crate::Type>,
+    path: &[Option<u32>],
+    arena: &mut Arena<crate::Type>,
+) -> Result<Handle<crate::Type>, Error> {
+    let (index, rest) = match path.split_first() {
+        Some((&index, rest)) => (index, rest),
+        None => {
+            return match arena[ty].inner {
+                crate::TypeInner::Scalar { kind, width } => {
+                    Ok(arena.fetch_or_append(crate::Type {
+                        name: None,
+                        inner: crate::TypeInner::Atomic { kind, width },
+                    }))
+                }
+                crate::TypeInner::Atomic { .. } => Ok(ty),
+                _ => Err(Error::UnsupportedType(ty)),
+            }
+        }
+    };
+
+    let inner = match arena[ty].inner {
+        crate::TypeInner::Array { base, size, stride } => {
+            let new_base = upgrade_type(base, rest, arena)?;
+            if new_base == base {
+                return Ok(ty);
+            }
+            crate::TypeInner::Array {
+                base: new_base,
+                size,
+                stride,
+            }
+        }
+        crate::TypeInner::Struct { block, ref members } => {
+            let index = index.ok_or(Error::UnsupportedType(ty))? as usize;
+            let mut members = members.clone();
+            let member = members.get_mut(index).ok_or(Error::UnsupportedType(ty))?;
+            let new_ty = upgrade_type(member.ty, rest, arena)?;
+            if new_ty == member.ty {
+                return Ok(ty);
+            }
+            member.ty = new_ty;
+            crate::TypeInner::Struct { block, members }
+        }
+        _ => return Err(Error::UnsupportedType(ty)),
+    };
+
+    let name = arena[ty].name.clone();
+    Ok(arena.append(crate::Type { name, inner }))
+}
+
+/// Make the value behind the `pointer` expression atomic.
+pub(super) fn patch_atomic_pointer(
+    pointer: Handle<crate::Expression>,
+    expressions: &Arena<crate::Expression>,
+    global_vars: &mut Arena<crate::GlobalVariable>,
+    types: &mut Arena<crate::Type>,
+) -> Result<(), Error> {
+    let mut path = Vec::new();
+    let mut current = pointer;
+    let var_handle = loop {
+        match expressions[current] {
+            crate::Expression::Access { base, index: _ } => {
+                path.push(None);
+                current = base;
+            }
+            crate::Expression::AccessIndex { base, index } => {
+                path.push(Some(index));
+                current = base;
+            }
+            crate::Expression::GlobalVariable(handle) => break handle,
+            ref other => return Err(Error::InvalidGlobalVar(other.clone())),
+        }
+    };
+    path.reverse();
+
+    let var = global_vars.get_mut(var_handle);
+    log::debug!("Upgrading {:?} to atomic at {:?}", var, path);
+    var.ty = upgrade_type(var.ty, &path, types)?;
+    Ok(())
+}
@@ -141,10 +141,69 @@ pub fn map_builtin(word: spirv::Word, is_output: bool) -> Result<crate::BuiltIn,
         Some(Bi::LocalInvocationIndex) => crate::BuiltIn::LocalInvocationIndex,
         Some(Bi::WorkgroupId) => crate::BuiltIn::WorkGroupId,
         Some(Bi::WorkgroupSize) => crate::BuiltIn::WorkGroupSize,
+        // subgroup
+        Some(Bi::SubgroupSize) => crate::BuiltIn::SubgroupSize,
+        Some(Bi::SubgroupLocalInvocationId) => crate::BuiltIn::SubgroupInvocationId,
+        // geometry and tessellation
+        Some(Bi::InvocationId) => crate::BuiltIn::InvocationId,
+        Some(Bi::PatchVertices) => crate::BuiltIn::PatchVertices,
+        Some(Bi::TessCoord) => crate::BuiltIn::TessCoord,
+        Some(Bi::TessLevelInner) => crate::BuiltIn::TessLevelInner,
+        Some(Bi::TessLevelOuter) => crate::BuiltIn::TessLevelOuter,
+        // ray tracing
+        Some(Bi::LaunchIdKHR) => crate::BuiltIn::LaunchId,
+        Some(Bi::LaunchSizeKHR) => crate::BuiltIn::LaunchSize,
+        Some(Bi::WorldRayOriginKHR) => crate::BuiltIn::WorldRayOrigin,
+        Some(Bi::WorldRayDirectionKHR) => crate::BuiltIn::WorldRayDirection,
+        Some(Bi::ObjectRayOriginKHR) => crate::BuiltIn::ObjectRayOrigin,
+        Some(Bi::ObjectRayDirectionKHR) => crate::BuiltIn::ObjectRayDirection,
+        Some(Bi::RayTminKHR) => crate::BuiltIn::RayTmin,
+        Some(Bi::RayTmaxKHR) => crate::BuiltIn::RayTmax,
+        Some(Bi::InstanceCustomIndexKHR) => crate::BuiltIn::InstanceCustomIndex,
+        Some(Bi::ObjectToWorldKHR) => crate::BuiltIn::ObjectToWorld,
+        Some(Bi::WorldToObjectKHR) => crate::BuiltIn::WorldToObject,
+        Some(Bi::HitKindKHR) => crate::BuiltIn::HitKind,
+        Some(Bi::IncomingRayFlagsKHR) => crate::BuiltIn::IncomingRayFlags,
         _ => return Err(Error::UnsupportedBuiltIn(word)),
     })
 }
 
+pub fn map_scope(word: spirv::Word) -> Result<crate::Scope, Error> {
+    use spirv::Scope as Sc;
+    Ok(match Sc::from_u32(word) {
+        Some(Sc::CrossDevice) | Some(Sc::Device) => crate::Scope::Device,
+        Some(Sc::Workgroup) => crate::Scope::WorkGroup,
+        Some(Sc::Subgroup) => crate::Scope::SubGroup,
+        _ => return Err(Error::UnsupportedScope(word)),
+    })
+}
+
+pub fn map_group_operation(word: spirv::Word) -> Result<crate::CollectiveOperation, Error> {
+    use spirv::GroupOperation as Go;
+    Ok(match Go::from_u32(word) {
+        Some(Go::Reduce) => crate::CollectiveOperation::Reduce,
+        Some(Go::InclusiveScan) => crate::CollectiveOperation::InclusiveScan,
+        Some(Go::ExclusiveScan) => crate::CollectiveOperation::ExclusiveScan,
+        _ => return Err(Error::UnsupportedGroupOperation(word)),
+    })
+}
+
+pub fn map_barrier_semantics(word: spirv::Word) -> crate::Barrier {
+    use spirv::MemorySemantics as Ms;
+    let semantics = Ms::from_bits_truncate(word);
+    let mut barrier = crate::Barrier::empty();
+    barrier.set(
+        crate::Barrier::STORAGE,
+        semantics.contains(Ms::UNIFORM_MEMORY),
+    );
+    barrier.set(
+        crate::Barrier::WORK_GROUP,
+        semantics.contains(Ms::WORKGROUP_MEMORY),
+    );
+    barrier.set(crate::Barrier::IMAGE, semantics.contains(Ms::IMAGE_MEMORY));
+    barrier
+}
+
 pub fn map_storage_class(word: spirv::Word) -> Result<crate::StorageClass, Error> {
     use spirv::StorageClass as Sc;
     Ok(match Sc::from_u32(word) {
@@ -158,6 +217,9 @@ pub fn map_storage_class(word: spirv::Word) -> Result<crate::StorageClass, Error
         Some(Sc::Uniform) => crate::StorageClass::Uniform,
         Some(Sc::Workgroup) => crate::StorageClass::WorkGroup,
         Some(Sc::PushConstant) => crate::StorageClass::PushConstant,
+        Some(Sc::RayPayloadKHR) => crate::StorageClass::RayPayload,
+        Some(Sc::IncomingRayPayloadKHR) => crate::StorageClass::IncomingRayPayload,
+        Some(Sc::HitAttributeKHR) => crate::StorageClass::HitAttribute,
         _ => return Err(Error::UnsupportedStorageClass(word)),
     })
 }
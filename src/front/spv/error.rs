@@ -4,6 +4,7 @@ use crate::arena::Handle;
 //TODO: use `thiserror`
 #[derive(Debug)]
 pub enum Error {
+    Assembly(super::AssemblyError),
     InvalidHeader,
     InvalidWordCount,
     UnknownInstruction(u16),
@@ -21,6 +22,8 @@ pub enum Error {
     UnsupportedImageDim(spirv::Word),
     UnsupportedImageFormat(spirv::Word),
     UnsupportedBuiltIn(spirv::Word),
+    UnsupportedScope(spirv::Word),
+    UnsupportedGroupOperation(spirv::Word),
     UnsupportedControlFlow(spirv::Word),
     UnsupportedBinaryOperator(spirv::Word),
     UnknownBinaryOperator(spirv::Op),
@@ -226,7 +226,7 @@ impl FlowGraph {
         stop_node_index: Option<BlockNodeIndex>,
     ) -> Result<crate::Block, Error> {
         if stop_node_index == Some(node_index) {
-            return Ok(vec![]);
+            return Ok(crate::Block::new());
         }
 
         let node = &self.flow[node_index];
@@ -266,11 +266,11 @@ impl FlowGraph {
                         result.push(crate::Statement::If {
                             condition,
                             accept: self.naga_traverse(true_node_index, stop_node_index)?,
-                            reject: vec![],
+                            reject: crate::Block::new(),
                         });
                     }
 
-                    result.extend(self.naga_traverse(merge_node_index, stop_node_index)?);
+                    result.extend_block(self.naga_traverse(merge_node_index, stop_node_index)?);
 
                     Ok(result)
                 }
@@ -313,7 +313,7 @@ impl FlowGraph {
                             .naga_traverse(self.block_to_node[&default], Some(merge_node_index))?,
                     });
 
-                    result.extend(self.naga_traverse(merge_node_index, stop_node_index)?);
+                    result.extend_block(self.naga_traverse(merge_node_index, stop_node_index)?);
 
                     Ok(result)
                 }
@@ -344,14 +344,15 @@ impl FlowGraph {
                         reject: self
                             .naga_traverse(self.block_to_node[&false_id], Some(merge_node_index))?,
                     }),
-                    Terminator::Branch { target_id } => body.extend(
+                    Terminator::Branch { target_id } => body.extend_block(
                         self.naga_traverse(self.block_to_node[&target_id], Some(merge_node_index))?,
                     ),
                     _ => return Err(Error::InvalidTerminator),
                 };
 
-                let mut result = vec![crate::Statement::Loop { body, continuing }];
-                result.extend(self.naga_traverse(merge_node_index, stop_node_index)?);
+                let mut result = crate::Block::new();
+                result.push(crate::Statement::Loop { body, continuing });
+                result.extend_block(self.naga_traverse(merge_node_index, stop_node_index)?);
 
                 Ok(result)
             }
@@ -374,14 +375,14 @@ impl FlowGraph {
                         if true_edge == ControlFlowEdgeType::LoopBreak {
                             result.push(crate::Statement::If {
                                 condition,
-                                accept: vec![crate::Statement::Break],
+                                accept: vec![crate::Statement::Break].into(),
                                 reject: self.naga_traverse(false_node_id, stop_node_index)?,
                             });
                         } else if false_edge == ControlFlowEdgeType::LoopBreak {
                             result.push(crate::Statement::If {
                                 condition,
                                 accept: self.naga_traverse(true_node_id, stop_node_index)?,
-                                reject: vec![crate::Statement::Break],
+                                reject: vec![crate::Statement::Break].into(),
                             });
                         } else {
                             return Err(Error::InvalidEdgeClassification);
@@ -413,7 +414,7 @@ impl FlowGraph {
             Some(ControlFlowNodeType::Merge) | None => match node.terminator {
                 Terminator::Branch { target_id } => {
                     let mut result = node.block.clone();
-                    result.extend(
+                    result.extend_block(
                         self.naga_traverse(self.block_to_node[&target_id], stop_node_index)?,
                     );
                     Ok(result)
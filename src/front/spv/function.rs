@@ -1,6 +1,9 @@
-use crate::arena::{Arena, Handle};
+use crate::{
+    arena::{Arena, Handle},
+    FastHashMap, Span,
+};
 
-use super::{flow::*, Error, Instruction, LookupExpression, LookupHelper as _};
+use super::{flow::*, Error, Instruction, LookupExpression, LookupHelper as _, Varying};
 
 pub type BlockId = u32;
 
@@ -50,6 +53,7 @@ pub enum Terminator {
 
 impl<I: Iterator<Item = u32>> super::Parser<I> {
     pub fn parse_function(&mut self, module: &mut crate::Module) -> Result<(), Error> {
+        let span = self.span;
         let result_type_id = self.next()?;
         let fun_id = self.next()?;
         let _fun_control = self.next()?;
@@ -63,14 +67,18 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
             crate::Function {
                 name: self.future_decor.remove(&fun_id).and_then(|dec| dec.name),
                 arguments: Vec::with_capacity(ft.parameter_type_ids.len()),
-                return_type: if self.lookup_void_type == Some(result_type_id) {
+                result: if self.lookup_void_type == Some(result_type_id) {
                     None
                 } else {
-                    Some(self.lookup_type.lookup(result_type_id)?.handle)
+                    Some(crate::FunctionResult {
+                        ty: self.lookup_type.lookup(result_type_id)?.handle,
+                        binding: None,
+                        interpolation: None,
+                    })
                 },
                 local_variables: Arena::new(),
                 expressions: self.make_expression_storage(),
-                body: Vec::new(),
+                body: crate::Block::new(),
             }
         };
 
@@ -99,8 +107,12 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
                         return Err(Error::WrongFunctionArgumentType(type_id));
                     }
                     let ty = self.lookup_type.lookup(type_id)?.handle;
-                    fun.arguments
-                        .push(crate::FunctionArgument { name: None, ty });
+                    fun.arguments.push(crate::FunctionArgument {
+                        name: None,
+                        ty,
+                        binding: None,
+                        interpolation: None,
+                    });
                 }
                 Instruction { op, .. } => return Err(Error::InvalidParameter(op)),
             }
@@ -157,18 +169,30 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
 
         fun.body = flow_graph.to_naga()?;
 
+        for pointer in self.atomic_pointers.drain(..) {
+            super::atomic::patch_atomic_pointer(
+                pointer,
+                &fun.expressions,
+                &mut module.global_variables,
+                &mut module.types,
+            )?;
+        }
+
         match self.lookup_entry_point.remove(&fun_id) {
             Some(ep) => {
+                self.entry_point_interfaces.push((ep.variable_ids, span));
                 module.entry_points.push(crate::EntryPoint {
                     name: ep.name,
                     stage: ep.stage,
                     early_depth_test: ep.early_depth_test,
                     workgroup_size: ep.workgroup_size,
+                    geometry: ep.geometry,
+                    tessellation: ep.tessellation,
                     function: fun,
                 });
             }
             None => {
-                let handle = module.functions.append(fun);
+                let handle = module.functions.append_with_span(fun, span);
                 self.lookup_function.insert(fun_id, handle);
             }
         };
@@ -177,4 +201,172 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
         self.lookup_sampled_image.clear();
         Ok(())
     }
+
+    /// Turn the entry point at `ep_index` into a wrapper, which takes the inputs as
+    /// arguments and stores them into the private variables that the original code
+    /// reads, calls the original code as a regular function, and returns the outputs.
+    ///
+    /// The `varyings` map keeps the original class, binding, and interpolation
+    /// of the interface variables that are shared between the entry points.
+    pub(super) fn wrap_entry_point(
+        &self,
+        ep_index: usize,
+        variable_ids: &[spirv::Word],
+        span: Span,
+        varyings: &mut FastHashMap<Handle<crate::GlobalVariable>, Varying>,
+        module: &mut crate::Module,
+    ) -> Result<(), Error> {
+        match module.entry_points[ep_index].stage {
+            crate::ShaderStage::Vertex
+            | crate::ShaderStage::Fragment
+            | crate::ShaderStage::Compute => {}
+            _ => return Ok(()),
+        }
+
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for &id in variable_ids {
+            let handle = self.lookup_variable.lookup(id)?.handle;
+            let var = module.global_variables.get_mut(handle);
+            let varying = varyings.entry(handle).or_insert_with(|| Varying {
+                class: var.class,
+                binding: var.binding.take(),
+                interpolation: var.interpolation.take(),
+            });
+            match varying.class {
+                crate::StorageClass::Input => inputs.push(handle),
+                crate::StorageClass::Output => outputs.push(handle),
+                _ => continue,
+            }
+            var.class = crate::StorageClass::Private;
+        }
+
+        let original = std::mem::take(&mut module.entry_points[ep_index].function);
+        let mut function = crate::Function {
+            name: original.name.clone(),
+            ..Default::default()
+        };
+        let original_handle = module.functions.append_with_span(original, span);
+
+        for handle in inputs {
+            let var = &module.global_variables[handle];
+            let varying = &varyings[&handle];
+            let index = function.arguments.len() as u32;
+            function.arguments.push(crate::FunctionArgument {
+                name: var.name.clone(),
+                ty: var.ty,
+                binding: varying.binding.clone(),
+                interpolation: varying.interpolation,
+            });
+            let value = function
+                .expressions
+                .append(crate::Expression::FunctionArgument(index));
+            let pointer = function
+                .expressions
+                .append(crate::Expression::GlobalVariable(handle));
+            function
+                .body
+                .push(crate::Statement::Store { pointer, value });
+        }
+
+        function.body.push(crate::Statement::Call {
+            function: original_handle,
+            arguments: Vec::new(),
+            result: None,
+        });
+
+        let pointers = outputs
+            .iter()
+            .map(|&handle| {
+                function
+                    .expressions
+                    .append(crate::Expression::GlobalVariable(handle))
+            })
+            .collect::<Vec<_>>();
+        let mut emitter = super::super::Emitter::default();
+        emitter.start(&function.expressions);
+        let mut members = Vec::new();
+        let mut components = Vec::new();
+        for (&handle, &pointer) in outputs.iter().zip(pointers.iter()) {
+            let var = &module.global_variables[handle];
+            let varying = &varyings[&handle];
+            match (varying.binding.as_ref(), &module.types[var.ty].inner) {
+                (Some(binding), _) => {
+                    members.push(crate::StructMember {
+                        name: var.name.clone(),
+                        span: None,
+                        ty: var.ty,
+                        binding: Some(binding.clone()),
+                        interpolation: varying.interpolation,
+                    });
+                    components.push(
+                        function
+                            .expressions
+                            .append(crate::Expression::Load { pointer }),
+                    );
+                }
+                // Blocks of built-ins, such as `gl_PerVertex`, are flattened.
+                // Members without a binding are not passed to the next stage.
+                (
+                    None,
+                    crate::TypeInner::Struct {
+                        members: var_members,
+                        ..
+                    },
+                ) => {
+                    for (index, member) in var_members.iter().enumerate() {
+                        if member.binding.is_none() {
+                            continue;
+                        }
+                        members.push(member.clone());
+                        let member_pointer =
+                            function.expressions.append(crate::Expression::AccessIndex {
+                                base: pointer,
+                                index: index as u32,
+                            });
+                        components.push(function.expressions.append(crate::Expression::Load {
+                            pointer: member_pointer,
+                        }));
+                    }
+                }
+                (None, _) => {
+                    log::warn!("Output {:?} doesn't have a binding", var.name);
+                }
+            }
+        }
+
+        let value = if members.len() > 1 {
+            let ty = module.types.append(crate::Type {
+                name: None,
+                inner: crate::TypeInner::Struct {
+                    block: false,
+                    members,
+                },
+            });
+            function.result = Some(crate::FunctionResult {
+                ty,
+                binding: None,
+                interpolation: None,
+            });
+            Some(
+                function
+                    .expressions
+                    .append(crate::Expression::Compose { ty, components }),
+            )
+        } else if let Some(member) = members.pop() {
+            function.result = Some(crate::FunctionResult {
+                ty: member.ty,
+                binding: member.binding,
+                interpolation: member.interpolation,
+            });
+            components.pop()
+        } else {
+            None
+        };
+        function.body.extend(emitter.finish(&function.expressions));
+        function.body.push(crate::Statement::Return { value });
+
+        module.entry_points[ep_index].function = function;
+        Ok(())
+    }
 }
@@ -0,0 +1,945 @@
+/*! Grammar of the SPIR-V instructions.
+
+This is shared by the assembler in `front::spv` and the disassembler
+in `back::spv`. It covers the core instructions that can appear in shaders,
+and a few ray tracing extension instructions that our backend produces.
+!*/
+// the assembler and the disassembler use different parts of it
+#![cfg_attr(not(all(feature = "spv-in", feature = "spv-out")), allow(dead_code))]
+
+use crate::FastHashMap;
+use num_traits::FromPrimitive;
+use spirv::{Op, Word};
+
+/// The largest value of an enumerant that we look up by name.
+const MAX_ENUMERANT: u32 = 0x4000;
+
+/// Kind of a logical operand of an instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum OperandKind {
+    IdRef,
+    LiteralInteger,
+    LiteralString,
+    /// Literal number, which type is given by the result type of the instruction.
+    LiteralContextDependentNumber,
+    /// Instruction of an extended instruction set.
+    LiteralExtInstInteger,
+    /// Opcode of an `OpSpecConstantOp`.
+    LiteralSpecConstantOpInteger,
+    /// Pair of a selector value and a label of `OpSwitch`,
+    /// which width is given by the type of the selector.
+    PairLiteralIntegerIdRef,
+    PairIdRefLiteralInteger,
+    PairIdRefIdRef,
+    Value(ValueEnum),
+    Bits(BitEnum),
+}
+
+/// Operand kinds with a single value from a list of enumerants.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum ValueEnum {
+    SourceLanguage,
+    ExecutionModel,
+    AddressingModel,
+    MemoryModel,
+    ExecutionMode,
+    StorageClass,
+    Dim,
+    SamplerAddressingMode,
+    SamplerFilterMode,
+    ImageFormat,
+    FPRoundingMode,
+    LinkageType,
+    AccessQualifier,
+    FunctionParameterAttribute,
+    Decoration,
+    BuiltIn,
+    GroupOperation,
+    Capability,
+}
+
+/// Operand kinds with a combination of enumerant bits.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum BitEnum {
+    ImageOperands,
+    FPFastMathMode,
+    SelectionControl,
+    LoopControl,
+    FunctionControl,
+    MemoryAccess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Quantifier {
+    One,
+    Optional,
+    Variadic,
+}
+
+pub(crate) type Operand = (OperandKind, Quantifier);
+
+pub(crate) struct InstructionGrammar {
+    pub has_type: bool,
+    pub has_result: bool,
+    pub operands: &'static [Operand],
+}
+
+macro_rules! value_enum_name {
+    ($value_enum:expr, $value:expr, $($name:ident),*) => {
+        match $value_enum {
+            $(
+                ValueEnum::$name => {
+                    spirv::$name::from_u32($value).map(|e| format!("{:?}", e))
+                }
+            )*
+        }
+    };
+}
+
+impl ValueEnum {
+    /// Returns the name of the enumerant with the given value.
+    pub(crate) fn name(self, value: u32) -> Option<String> {
+        let name = value_enum_name!(
+            self,
+            value,
+            SourceLanguage,
+            ExecutionModel,
+            AddressingModel,
+            MemoryModel,
+            ExecutionMode,
+            StorageClass,
+            Dim,
+            SamplerAddressingMode,
+            SamplerFilterMode,
+            ImageFormat,
+            FPRoundingMode,
+            LinkageType,
+            AccessQualifier,
+            FunctionParameterAttribute,
+            Decoration,
+            BuiltIn,
+            GroupOperation,
+            Capability
+        )?;
+        // `Dim1D` is spelled as `1D` in the assembly
+        Some(match self {
+            ValueEnum::Dim => name["Dim".len()..].to_string(),
+            _ => name,
+        })
+    }
+
+    /// Returns all the enumerants, with their names.
+    pub(crate) fn enumerants(self) -> Vec<(String, u32)> {
+        (0..MAX_ENUMERANT)
+            .filter_map(|value| self.name(value).map(|name| (name, value)))
+            .collect()
+    }
+
+    /// Returns the kinds of the extra operands following the enumerant.
+    pub(crate) fn parameters(self, value: u32) -> &'static [OperandKind] {
+        use OperandKind as K;
+        const LIT: &[OperandKind] = &[K::LiteralInteger];
+        const LIT3: &[OperandKind] = &[K::LiteralInteger, K::LiteralInteger, K::LiteralInteger];
+        const ID: &[OperandKind] = &[K::IdRef];
+        const ID3: &[OperandKind] = &[K::IdRef, K::IdRef, K::IdRef];
+        const STR: &[OperandKind] = &[K::LiteralString];
+        match self {
+            ValueEnum::ExecutionMode => match spirv::ExecutionMode::from_u32(value) {
+                Some(spirv::ExecutionMode::Invocations)
+                | Some(spirv::ExecutionMode::OutputVertices)
+                | Some(spirv::ExecutionMode::VecTypeHint)
+                | Some(spirv::ExecutionMode::SubgroupSize)
+                | Some(spirv::ExecutionMode::SubgroupsPerWorkgroup)
+                | Some(spirv::ExecutionMode::OutputPrimitivesNV) => LIT,
+                Some(spirv::ExecutionMode::LocalSize)
+                | Some(spirv::ExecutionMode::LocalSizeHint) => LIT3,
+                Some(spirv::ExecutionMode::SubgroupsPerWorkgroupId) => ID,
+                Some(spirv::ExecutionMode::LocalSizeId)
+                | Some(spirv::ExecutionMode::LocalSizeHintId) => ID3,
+                _ => &[],
+            },
+            ValueEnum::Decoration => match spirv::Decoration::from_u32(value) {
+                Some(spirv::Decoration::SpecId)
+                | Some(spirv::Decoration::ArrayStride)
+                | Some(spirv::Decoration::MatrixStride)
+                | Some(spirv::Decoration::Stream)
+                | Some(spirv::Decoration::Location)
+                | Some(spirv::Decoration::Component)
+                | Some(spirv::Decoration::Index)
+                | Some(spirv::Decoration::Binding)
+                | Some(spirv::Decoration::DescriptorSet)
+                | Some(spirv::Decoration::Offset)
+                | Some(spirv::Decoration::XfbBuffer)
+                | Some(spirv::Decoration::XfbStride)
+                | Some(spirv::Decoration::InputAttachmentIndex)
+                | Some(spirv::Decoration::Alignment)
+                | Some(spirv::Decoration::MaxByteOffset)
+                | Some(spirv::Decoration::SecondaryViewportRelativeNV) => LIT,
+                Some(spirv::Decoration::BuiltIn) => &[K::Value(ValueEnum::BuiltIn)],
+                Some(spirv::Decoration::UniformId)
+                | Some(spirv::Decoration::AlignmentId)
+                | Some(spirv::Decoration::MaxByteOffsetId)
+                | Some(spirv::Decoration::CounterBuffer) => ID,
+                Some(spirv::Decoration::FuncParamAttr) => {
+                    &[K::Value(ValueEnum::FunctionParameterAttribute)]
+                }
+                Some(spirv::Decoration::FPRoundingMode) => &[K::Value(ValueEnum::FPRoundingMode)],
+                Some(spirv::Decoration::FPFastMathMode) => &[K::Bits(BitEnum::FPFastMathMode)],
+                Some(spirv::Decoration::LinkageAttributes) => {
+                    &[K::LiteralString, K::Value(ValueEnum::LinkageType)]
+                }
+                Some(spirv::Decoration::UserSemantic) | Some(spirv::Decoration::UserTypeGOOGLE) => {
+                    STR
+                }
+                _ => &[],
+            },
+            _ => &[],
+        }
+    }
+}
+
+impl BitEnum {
+    /// Returns the names, the bits, and the extra operands of the enumerants.
+    pub(crate) fn enumerants(self) -> &'static [(&'static str, u32, &'static [OperandKind])] {
+        use OperandKind as K;
+        const LIT: &[OperandKind] = &[K::LiteralInteger];
+        const ID: &[OperandKind] = &[K::IdRef];
+        const ID2: &[OperandKind] = &[K::IdRef, K::IdRef];
+        match self {
+            BitEnum::ImageOperands => &[
+                ("Bias", 0x1, ID),
+                ("Lod", 0x2, ID),
+                ("Grad", 0x4, ID2),
+                ("ConstOffset", 0x8, ID),
+                ("Offset", 0x10, ID),
+                ("ConstOffsets", 0x20, ID),
+                ("Sample", 0x40, ID),
+                ("MinLod", 0x80, ID),
+                ("MakeTexelAvailable", 0x100, ID),
+                ("MakeTexelVisible", 0x200, ID),
+                ("NonPrivateTexel", 0x400, &[]),
+                ("VolatileTexel", 0x800, &[]),
+                ("SignExtend", 0x1000, &[]),
+                ("ZeroExtend", 0x2000, &[]),
+            ],
+            BitEnum::FPFastMathMode => &[
+                ("NotNaN", 0x1, &[]),
+                ("NotInf", 0x2, &[]),
+                ("NSZ", 0x4, &[]),
+                ("AllowRecip", 0x8, &[]),
+                ("Fast", 0x10, &[]),
+            ],
+            BitEnum::SelectionControl => &[("Flatten", 0x1, &[]), ("DontFlatten", 0x2, &[])],
+            BitEnum::LoopControl => &[
+                ("Unroll", 0x1, &[]),
+                ("DontUnroll", 0x2, &[]),
+                ("DependencyInfinite", 0x4, &[]),
+                ("DependencyLength", 0x8, LIT),
+                ("MinIterations", 0x10, LIT),
+                ("MaxIterations", 0x20, LIT),
+                ("IterationMultiple", 0x40, LIT),
+                ("PeelCount", 0x80, LIT),
+                ("PartialCount", 0x100, LIT),
+            ],
+            BitEnum::FunctionControl => &[
+                ("Inline", 0x1, &[]),
+                ("DontInline", 0x2, &[]),
+                ("Pure", 0x4, &[]),
+                ("Const", 0x8, &[]),
+            ],
+            BitEnum::MemoryAccess => &[
+                ("Volatile", 0x1, &[]),
+                ("Aligned", 0x2, LIT),
+                ("Nontemporal", 0x4, &[]),
+                ("MakePointerAvailable", 0x8, ID),
+                ("MakePointerVisible", 0x10, ID),
+                ("NonPrivatePointer", 0x20, &[]),
+            ],
+        }
+    }
+}
+
+/// Returns the name of the opcode, as it's written in the assembly.
+pub(crate) fn op_name(op: Op) -> String {
+    format!("Op{:?}", op)
+}
+
+/// Returns all the opcodes known to the grammar, with their names.
+pub(crate) fn ops() -> Vec<(String, Op)> {
+    (0..MAX_ENUMERANT)
+        .filter_map(Op::from_u32)
+        .filter(|&op| instruction(op).is_some())
+        .map(|op| (op_name(op), op))
+        .collect()
+}
+
+/// Name of the extended instruction set that has named instructions.
+pub(crate) const GLSL_STD_450: &str = "GLSL.std.450";
+
+/// Returns the name of an instruction in an extended instruction set.
+pub(crate) fn ext_inst_name(set: &str, value: u32) -> Option<String> {
+    if set != GLSL_STD_450 {
+        return None;
+    }
+    spirv::GLOp::from_u32(value).map(|op| format!("{:?}", op))
+}
+
+/// Returns all the named instructions of an extended instruction set.
+pub(crate) fn ext_inst_names(set: &str) -> Vec<(String, u32)> {
+    (0..MAX_ENUMERANT)
+        .filter_map(|value| ext_inst_name(set, value).map(|name| (name, value)))
+        .collect()
+}
+
+/// Decodes a nul-terminated string literal,
+/// returning it together with the number of words it takes.
+pub(crate) fn decode_string(words: &[Word]) -> Option<(String, usize)> {
+    let mut bytes = Vec::new();
+    for (index, &word) in words.iter().enumerate() {
+        for &byte in word.to_le_bytes().iter() {
+            if byte == 0 {
+                let string = String::from_utf8(bytes).ok()?;
+                return Some((string, index + 1));
+            }
+            bytes.push(byte);
+        }
+    }
+    None
+}
+
+/// Encodes a string literal, including the nul terminator.
+pub(crate) fn encode_string(string: &str) -> Vec<Word> {
+    let mut bytes = string.as_bytes().to_vec();
+    // the terminator, and the padding to the word size
+    bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+    bytes
+        .chunks(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Scalar type of a context-dependent literal number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum NumericType {
+    Int { width: Word, signed: bool },
+    Float { width: Word },
+}
+
+impl NumericType {
+    pub(crate) fn width(self) -> Word {
+        match self {
+            NumericType::Int { width, .. } | NumericType::Float { width } => width,
+        }
+    }
+}
+
+/// Parameters of a binary floating-point format.
+struct FloatFormat {
+    mantissa_bits: u32,
+    exponent_bias: i32,
+}
+
+impl FloatFormat {
+    fn new(width: Word) -> Option<Self> {
+        let (mantissa_bits, exponent_bias) = match width {
+            16 => (10, 15),
+            32 => (23, 127),
+            64 => (52, 1023),
+            _ => return None,
+        };
+        Some(FloatFormat {
+            mantissa_bits,
+            exponent_bias,
+        })
+    }
+}
+
+/// Formats the bits of a literal number of the given type.
+///
+/// Infinities and NaNs are written as hexadecimal floats,
+/// in the same way as SPIRV-Tools does it.
+pub(crate) fn format_number(ty: NumericType, bits: u64) -> String {
+    match ty {
+        NumericType::Int { width, signed } => {
+            if signed {
+                let shift = 64 - width.min(64);
+                (((bits << shift) as i64) >> shift).to_string()
+            } else {
+                bits.to_string()
+            }
+        }
+        NumericType::Float { width } => {
+            let value = match width {
+                16 => crate::proc::f16_from_bits(bits as u16),
+                32 => f64::from(f32::from_bits(bits as u32)),
+                _ => f64::from_bits(bits),
+            };
+            if value.is_finite() {
+                return match width {
+                    // all the single-precision floats print back exactly
+                    32 => format!("{:?}", value as f32),
+                    _ => format!("{:?}", value),
+                };
+            }
+            let format = match FloatFormat::new(width) {
+                Some(format) => format,
+                None => return bits.to_string(),
+            };
+            let sign = if bits >> (width - 1) & 1 != 0 {
+                "-"
+            } else {
+                ""
+            };
+            let mantissa = bits & ((1 << format.mantissa_bits) - 1);
+            let padding = (4 - format.mantissa_bits % 4) % 4;
+            let digits = ((format.mantissa_bits + padding) / 4) as usize;
+            let fraction = format!("{:0width$x}", mantissa << padding, width = digits);
+            let fraction = fraction.trim_end_matches('0');
+            let exponent = format.exponent_bias + 1;
+            if fraction.is_empty() {
+                format!("{}0x1p+{}", sign, exponent)
+            } else {
+                format!("{}0x1.{}p+{}", sign, fraction, exponent)
+            }
+        }
+    }
+}
+
+fn split_sign(text: &str) -> (bool, &str) {
+    if text.starts_with('-') {
+        (true, &text[1..])
+    } else {
+        (false, text)
+    }
+}
+
+fn is_hex(text: &str) -> bool {
+    text.starts_with("0x") || text.starts_with("0X")
+}
+
+/// Parses an integer literal, in decimal or hexadecimal.
+pub(crate) fn parse_integer(text: &str) -> Option<i128> {
+    let (negative, text) = split_sign(text);
+    let value = if is_hex(text) {
+        i128::from_str_radix(&text[2..], 16).ok()?
+    } else {
+        text.parse::<i128>().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Parses a hexadecimal float, like `-0x1.8p+3`, into its sign, mantissa and exponent.
+fn parse_hex_float(text: &str) -> Option<(bool, f64, i32, u64)> {
+    let (negative, text) = split_sign(text);
+    if !is_hex(text) {
+        return None;
+    }
+    let text = &text[2..];
+    let p = text.find(&['p', 'P'][..])?;
+    let exponent = text[p + 1..].parse::<i32>().ok()?;
+    let (integer, fraction) = match text[..p].find('.') {
+        Some(dot) => (&text[..dot], &text[dot + 1..p]),
+        None => (&text[..p], ""),
+    };
+    let integer = u64::from_str_radix(integer, 16).ok()?;
+    let mut value = integer as f64;
+    let mut fraction_bits = 0u64;
+    for (index, c) in fraction.chars().enumerate() {
+        let digit = c.to_digit(16)?;
+        value += f64::from(digit) * 16f64.powi(-(index as i32 + 1));
+        fraction_bits = fraction_bits.checked_mul(16)? | u64::from(digit);
+    }
+    // the fraction bits aligned to the top of 64 bits, for NaN payloads
+    let aligned = if fraction.is_empty() {
+        0
+    } else {
+        fraction_bits << (64 - 4 * fraction.len() as u32)
+    };
+    Some((negative, value, exponent, aligned))
+}
+
+/// Parses a literal number of the given type into its bits.
+pub(crate) fn parse_number(ty: NumericType, text: &str) -> Option<u64> {
+    match ty {
+        NumericType::Int { width, signed } => {
+            let value = parse_integer(text)?;
+            let (min, max) = match (width, signed) {
+                (64, true) => (i128::from(i64::MIN), i128::from(i64::MAX)),
+                (64, false) => (0, i128::from(u64::MAX)),
+                (_, true) => (-(1 << (width - 1)), (1 << (width - 1)) - 1),
+                (_, false) => (0, (1 << width) - 1),
+            };
+            if value < min || value > max {
+                return None;
+            }
+            // literals narrower than a word are sign-extended to it
+            let bits = value as u64;
+            Some(if width < 32 {
+                u64::from(bits as u32)
+            } else if width == 32 {
+                bits & 0xffff_ffff
+            } else {
+                bits
+            })
+        }
+        NumericType::Float { width } => {
+            let format = FloatFormat::new(width)?;
+            let value = if is_hex(split_sign(text).1) {
+                let (negative, mantissa, exponent, fraction) = parse_hex_float(text)?;
+                if exponent == format.exponent_bias + 1 {
+                    // infinity or NaN, with the given payload
+                    let sign = u64::from(negative) << (width - 1);
+                    let exponent_bits =
+                        ((1u64 << (width - 1 - format.mantissa_bits)) - 1) << format.mantissa_bits;
+                    let payload = fraction >> (64 - format.mantissa_bits);
+                    return Some(sign | exponent_bits | payload);
+                }
+                let value = mantissa * 2f64.powi(exponent);
+                if negative {
+                    -value
+                } else {
+                    value
+                }
+            } else {
+                text.parse::<f64>().ok()?
+            };
+            Some(match width {
+                16 => u64::from(crate::proc::f16_to_bits(value)),
+                32 => u64::from((value as f32).to_bits()),
+                _ => value.to_bits(),
+            })
+        }
+    }
+}
+
+const fn none(operands: &'static [Operand]) -> InstructionGrammar {
+    InstructionGrammar {
+        has_type: false,
+        has_result: false,
+        operands,
+    }
+}
+
+const fn result(operands: &'static [Operand]) -> InstructionGrammar {
+    InstructionGrammar {
+        has_type: false,
+        has_result: true,
+        operands,
+    }
+}
+
+const fn typed(operands: &'static [Operand]) -> InstructionGrammar {
+    InstructionGrammar {
+        has_type: true,
+        has_result: true,
+        operands,
+    }
+}
+
+/// Returns the grammar of the instruction with the given opcode.
+pub(crate) fn instruction(op: Op) -> Option<InstructionGrammar> {
+    use OperandKind as K;
+    use Quantifier as Q;
+
+    const ID: Operand = (K::IdRef, Q::One);
+    const OPT_ID: Operand = (K::IdRef, Q::Optional);
+    const IDS: Operand = (K::IdRef, Q::Variadic);
+    const LIT: Operand = (K::LiteralInteger, Q::One);
+    const LITS: Operand = (K::LiteralInteger, Q::Variadic);
+    const STR: Operand = (K::LiteralString, Q::One);
+    const OPT_STR: Operand = (K::LiteralString, Q::Optional);
+    const NUMBER: Operand = (K::LiteralContextDependentNumber, Q::One);
+    const fn value(value_enum: ValueEnum) -> Operand {
+        (K::Value(value_enum), Q::One)
+    }
+    const fn bits(bit_enum: BitEnum) -> Operand {
+        (K::Bits(bit_enum), Q::One)
+    }
+    const fn opt_bits(bit_enum: BitEnum) -> Operand {
+        (K::Bits(bit_enum), Q::Optional)
+    }
+    const IMAGE_OPERANDS: Operand = bits(BitEnum::ImageOperands);
+    const OPT_IMAGE_OPERANDS: Operand = opt_bits(BitEnum::ImageOperands);
+    const OPT_MEMORY_ACCESS: Operand = opt_bits(BitEnum::MemoryAccess);
+    const GROUP_OPERATION: Operand = value(ValueEnum::GroupOperation);
+    const ACCESS_QUALIFIER: Operand = value(ValueEnum::AccessQualifier);
+    const ADDRESSING_MODEL: Operand = value(ValueEnum::AddressingModel);
+    const CAPABILITY: Operand = value(ValueEnum::Capability);
+    const DECORATION: Operand = value(ValueEnum::Decoration);
+    const DIM: Operand = value(ValueEnum::Dim);
+    const EXECUTION_MODE: Operand = value(ValueEnum::ExecutionMode);
+    const EXECUTION_MODEL: Operand = value(ValueEnum::ExecutionModel);
+    const FUNCTION_CONTROL: Operand = bits(BitEnum::FunctionControl);
+    const IMAGE_FORMAT: Operand = value(ValueEnum::ImageFormat);
+    const LOOP_CONTROL: Operand = bits(BitEnum::LoopControl);
+    const MEMORY_MODEL: Operand = value(ValueEnum::MemoryModel);
+    const SAMPLER_ADDRESSING_MODE: Operand = value(ValueEnum::SamplerAddressingMode);
+    const SAMPLER_FILTER_MODE: Operand = value(ValueEnum::SamplerFilterMode);
+    const SELECTION_CONTROL: Operand = bits(BitEnum::SelectionControl);
+    const SOURCE_LANGUAGE: Operand = value(ValueEnum::SourceLanguage);
+    const STORAGE_CLASS: Operand = value(ValueEnum::StorageClass);
+
+    Some(match op {
+        // miscellaneous
+        Op::Nop => none(&[]),
+        Op::Undef => typed(&[]),
+        // debug
+        Op::SourceContinued => none(&[STR]),
+        Op::Source => none(&[SOURCE_LANGUAGE, LIT, OPT_ID, OPT_STR]),
+        Op::SourceExtension => none(&[STR]),
+        Op::Name => none(&[ID, STR]),
+        Op::MemberName => none(&[ID, LIT, STR]),
+        Op::String => result(&[STR]),
+        Op::Line => none(&[ID, LIT, LIT]),
+        Op::NoLine => none(&[]),
+        Op::ModuleProcessed => none(&[STR]),
+        // annotation
+        Op::Decorate | Op::DecorateId | Op::DecorateString => none(&[ID, DECORATION]),
+        Op::MemberDecorate | Op::MemberDecorateString => none(&[ID, LIT, DECORATION]),
+        Op::DecorationGroup => result(&[]),
+        Op::GroupDecorate => none(&[ID, IDS]),
+        Op::GroupMemberDecorate => none(&[ID, (K::PairIdRefLiteralInteger, Q::Variadic)]),
+        // extension
+        Op::Extension => none(&[STR]),
+        Op::ExtInstImport => result(&[STR]),
+        Op::ExtInst => typed(&[ID, (K::LiteralExtInstInteger, Q::One), IDS]),
+        // mode setting
+        Op::MemoryModel => none(&[ADDRESSING_MODEL, MEMORY_MODEL]),
+        Op::EntryPoint => none(&[EXECUTION_MODEL, ID, STR, IDS]),
+        Op::ExecutionMode | Op::ExecutionModeId => none(&[ID, EXECUTION_MODE]),
+        Op::Capability => none(&[CAPABILITY]),
+        // type declaration
+        Op::TypeVoid
+        | Op::TypeBool
+        | Op::TypeSampler
+        | Op::TypeEvent
+        | Op::TypeDeviceEvent
+        | Op::TypeReserveId
+        | Op::TypeQueue
+        | Op::TypeAccelerationStructureNV => result(&[]),
+        Op::TypeInt => result(&[LIT, LIT]),
+        Op::TypeFloat => result(&[LIT]),
+        Op::TypeVector | Op::TypeMatrix => result(&[ID, LIT]),
+        Op::TypeImage => result(&[
+            ID,
+            DIM,
+            LIT,
+            LIT,
+            LIT,
+            LIT,
+            IMAGE_FORMAT,
+            (K::Value(ValueEnum::AccessQualifier), Q::Optional),
+        ]),
+        Op::TypeSampledImage | Op::TypeRuntimeArray => result(&[ID]),
+        Op::TypeArray => result(&[ID, ID]),
+        Op::TypeStruct => result(&[IDS]),
+        Op::TypeOpaque => result(&[STR]),
+        Op::TypePointer => result(&[STORAGE_CLASS, ID]),
+        Op::TypeFunction => result(&[ID, IDS]),
+        Op::TypePipe => result(&[ACCESS_QUALIFIER]),
+        Op::TypeForwardPointer => none(&[ID, STORAGE_CLASS]),
+        // constant creation
+        Op::ConstantTrue
+        | Op::ConstantFalse
+        | Op::ConstantNull
+        | Op::SpecConstantTrue
+        | Op::SpecConstantFalse => typed(&[]),
+        Op::Constant | Op::SpecConstant => typed(&[NUMBER]),
+        Op::ConstantComposite | Op::SpecConstantComposite => typed(&[IDS]),
+        Op::ConstantSampler => typed(&[SAMPLER_ADDRESSING_MODE, LIT, SAMPLER_FILTER_MODE]),
+        Op::SpecConstantOp => typed(&[(K::LiteralSpecConstantOpInteger, Q::One), IDS]),
+        // memory
+        Op::Variable => typed(&[STORAGE_CLASS, OPT_ID]),
+        Op::ImageTexelPointer => typed(&[ID, ID, ID]),
+        Op::Load => typed(&[ID, OPT_MEMORY_ACCESS]),
+        Op::Store => none(&[ID, ID, OPT_MEMORY_ACCESS]),
+        Op::CopyMemory => none(&[ID, ID, OPT_MEMORY_ACCESS, OPT_MEMORY_ACCESS]),
+        Op::CopyMemorySized => none(&[ID, ID, ID, OPT_MEMORY_ACCESS, OPT_MEMORY_ACCESS]),
+        Op::AccessChain | Op::InBoundsAccessChain => typed(&[ID, IDS]),
+        Op::PtrAccessChain | Op::InBoundsPtrAccessChain => typed(&[ID, ID, IDS]),
+        Op::ArrayLength => typed(&[ID, LIT]),
+        Op::GenericPtrMemSemantics => typed(&[ID]),
+        Op::PtrEqual | Op::PtrNotEqual | Op::PtrDiff => typed(&[ID, ID]),
+        // function
+        Op::Function => typed(&[FUNCTION_CONTROL, ID]),
+        Op::FunctionParameter => typed(&[]),
+        Op::FunctionEnd => none(&[]),
+        Op::FunctionCall => typed(&[ID, IDS]),
+        // image
+        Op::SampledImage => typed(&[ID, ID]),
+        Op::ImageSampleImplicitLod | Op::ImageSampleProjImplicitLod => {
+            typed(&[ID, ID, OPT_IMAGE_OPERANDS])
+        }
+        Op::ImageSampleExplicitLod | Op::ImageSampleProjExplicitLod => {
+            typed(&[ID, ID, IMAGE_OPERANDS])
+        }
+        Op::ImageSampleDrefImplicitLod | Op::ImageSampleProjDrefImplicitLod => {
+            typed(&[ID, ID, ID, OPT_IMAGE_OPERANDS])
+        }
+        Op::ImageSampleDrefExplicitLod | Op::ImageSampleProjDrefExplicitLod => {
+            typed(&[ID, ID, ID, IMAGE_OPERANDS])
+        }
+        Op::ImageFetch | Op::ImageRead => typed(&[ID, ID, OPT_IMAGE_OPERANDS]),
+        Op::ImageGather | Op::ImageDrefGather => typed(&[ID, ID, ID, OPT_IMAGE_OPERANDS]),
+        Op::ImageWrite => none(&[ID, ID, ID, OPT_IMAGE_OPERANDS]),
+        Op::Image
+        | Op::ImageQueryFormat
+        | Op::ImageQueryOrder
+        | Op::ImageQuerySize
+        | Op::ImageQueryLevels
+        | Op::ImageQuerySamples => typed(&[ID]),
+        Op::ImageQuerySizeLod | Op::ImageQueryLod => typed(&[ID, ID]),
+        // conversion
+        Op::ConvertFToU
+        | Op::ConvertFToS
+        | Op::ConvertSToF
+        | Op::ConvertUToF
+        | Op::UConvert
+        | Op::SConvert
+        | Op::FConvert
+        | Op::QuantizeToF16
+        | Op::ConvertPtrToU
+        | Op::SatConvertSToU
+        | Op::SatConvertUToS
+        | Op::ConvertUToPtr
+        | Op::PtrCastToGeneric
+        | Op::GenericCastToPtr
+        | Op::Bitcast => typed(&[ID]),
+        Op::GenericCastToPtrExplicit => typed(&[ID, STORAGE_CLASS]),
+        // composite
+        Op::VectorExtractDynamic => typed(&[ID, ID]),
+        Op::VectorInsertDynamic => typed(&[ID, ID, ID]),
+        Op::VectorShuffle => typed(&[ID, ID, LITS]),
+        Op::CompositeConstruct => typed(&[IDS]),
+        Op::CompositeExtract => typed(&[ID, LITS]),
+        Op::CompositeInsert => typed(&[ID, ID, LITS]),
+        Op::CopyObject | Op::Transpose | Op::CopyLogical => typed(&[ID]),
+        // arithmetic
+        Op::SNegate | Op::FNegate => typed(&[ID]),
+        Op::IAdd
+        | Op::FAdd
+        | Op::ISub
+        | Op::FSub
+        | Op::IMul
+        | Op::FMul
+        | Op::UDiv
+        | Op::SDiv
+        | Op::FDiv
+        | Op::UMod
+        | Op::SRem
+        | Op::SMod
+        | Op::FRem
+        | Op::FMod
+        | Op::VectorTimesScalar
+        | Op::MatrixTimesScalar
+        | Op::VectorTimesMatrix
+        | Op::MatrixTimesVector
+        | Op::MatrixTimesMatrix
+        | Op::OuterProduct
+        | Op::Dot
+        | Op::IAddCarry
+        | Op::ISubBorrow
+        | Op::UMulExtended
+        | Op::SMulExtended => typed(&[ID, ID]),
+        // bit
+        Op::ShiftRightLogical
+        | Op::ShiftRightArithmetic
+        | Op::ShiftLeftLogical
+        | Op::BitwiseOr
+        | Op::BitwiseXor
+        | Op::BitwiseAnd => typed(&[ID, ID]),
+        Op::Not | Op::BitReverse | Op::BitCount => typed(&[ID]),
+        Op::BitFieldInsert => typed(&[ID, ID, ID, ID]),
+        Op::BitFieldSExtract | Op::BitFieldUExtract => typed(&[ID, ID, ID]),
+        // relational and logical
+        Op::Any
+        | Op::All
+        | Op::IsNan
+        | Op::IsInf
+        | Op::IsFinite
+        | Op::IsNormal
+        | Op::SignBitSet
+        | Op::LogicalNot => typed(&[ID]),
+        Op::LessOrGreater
+        | Op::Ordered
+        | Op::Unordered
+        | Op::LogicalEqual
+        | Op::LogicalNotEqual
+        | Op::LogicalOr
+        | Op::LogicalAnd
+        | Op::IEqual
+        | Op::INotEqual
+        | Op::UGreaterThan
+        | Op::SGreaterThan
+        | Op::UGreaterThanEqual
+        | Op::SGreaterThanEqual
+        | Op::ULessThan
+        | Op::SLessThan
+        | Op::ULessThanEqual
+        | Op::SLessThanEqual
+        | Op::FOrdEqual
+        | Op::FUnordEqual
+        | Op::FOrdNotEqual
+        | Op::FUnordNotEqual
+        | Op::FOrdLessThan
+        | Op::FUnordLessThan
+        | Op::FOrdGreaterThan
+        | Op::FUnordGreaterThan
+        | Op::FOrdLessThanEqual
+        | Op::FUnordLessThanEqual
+        | Op::FOrdGreaterThanEqual
+        | Op::FUnordGreaterThanEqual => typed(&[ID, ID]),
+        Op::Select => typed(&[ID, ID, ID]),
+        // derivative
+        Op::DPdx
+        | Op::DPdy
+        | Op::Fwidth
+        | Op::DPdxFine
+        | Op::DPdyFine
+        | Op::FwidthFine
+        | Op::DPdxCoarse
+        | Op::DPdyCoarse
+        | Op::FwidthCoarse => typed(&[ID]),
+        // primitive
+        Op::EmitVertex | Op::EndPrimitive => none(&[]),
+        Op::EmitStreamVertex | Op::EndStreamPrimitive => none(&[ID]),
+        // barrier
+        Op::ControlBarrier => none(&[ID, ID, ID]),
+        Op::MemoryBarrier => none(&[ID, ID]),
+        // atomic
+        Op::AtomicLoad | Op::AtomicIIncrement | Op::AtomicIDecrement => typed(&[ID, ID, ID]),
+        Op::AtomicStore => none(&[ID, ID, ID, ID]),
+        Op::AtomicExchange
+        | Op::AtomicIAdd
+        | Op::AtomicISub
+        | Op::AtomicSMin
+        | Op::AtomicUMin
+        | Op::AtomicSMax
+        | Op::AtomicUMax
+        | Op::AtomicAnd
+        | Op::AtomicOr
+        | Op::AtomicXor => typed(&[ID, ID, ID, ID]),
+        Op::AtomicCompareExchange | Op::AtomicCompareExchangeWeak => {
+            typed(&[ID, ID, ID, ID, ID, ID])
+        }
+        Op::AtomicFlagTestAndSet => typed(&[ID, ID, ID]),
+        Op::AtomicFlagClear => none(&[ID, ID, ID]),
+        // control flow
+        Op::Phi => typed(&[(K::PairIdRefIdRef, Q::Variadic)]),
+        Op::LoopMerge => none(&[ID, ID, LOOP_CONTROL]),
+        Op::SelectionMerge => none(&[ID, SELECTION_CONTROL]),
+        Op::Label => result(&[]),
+        Op::Branch => none(&[ID]),
+        Op::BranchConditional => none(&[ID, ID, ID, LITS]),
+        Op::Switch => none(&[ID, ID, (K::PairLiteralIntegerIdRef, Q::Variadic)]),
+        Op::Kill | Op::Return | Op::Unreachable => none(&[]),
+        Op::ReturnValue => none(&[ID]),
+        Op::LifetimeStart | Op::LifetimeStop => none(&[ID, LIT]),
+        // non-uniform
+        Op::GroupNonUniformElect => typed(&[ID]),
+        Op::GroupNonUniformAll
+        | Op::GroupNonUniformAny
+        | Op::GroupNonUniformAllEqual
+        | Op::GroupNonUniformBroadcastFirst
+        | Op::GroupNonUniformBallot
+        | Op::GroupNonUniformInverseBallot
+        | Op::GroupNonUniformBallotFindLSB
+        | Op::GroupNonUniformBallotFindMSB => typed(&[ID, ID]),
+        Op::GroupNonUniformBroadcast
+        | Op::GroupNonUniformBallotBitExtract
+        | Op::GroupNonUniformShuffle
+        | Op::GroupNonUniformShuffleXor
+        | Op::GroupNonUniformShuffleUp
+        | Op::GroupNonUniformShuffleDown
+        | Op::GroupNonUniformQuadBroadcast
+        | Op::GroupNonUniformQuadSwap => typed(&[ID, ID, ID]),
+        Op::GroupNonUniformBallotBitCount => typed(&[ID, GROUP_OPERATION, ID]),
+        Op::GroupNonUniformIAdd
+        | Op::GroupNonUniformFAdd
+        | Op::GroupNonUniformIMul
+        | Op::GroupNonUniformFMul
+        | Op::GroupNonUniformSMin
+        | Op::GroupNonUniformUMin
+        | Op::GroupNonUniformFMin
+        | Op::GroupNonUniformSMax
+        | Op::GroupNonUniformUMax
+        | Op::GroupNonUniformFMax
+        | Op::GroupNonUniformBitwiseAnd
+        | Op::GroupNonUniformBitwiseOr
+        | Op::GroupNonUniformBitwiseXor
+        | Op::GroupNonUniformLogicalAnd
+        | Op::GroupNonUniformLogicalOr
+        | Op::GroupNonUniformLogicalXor => typed(&[ID, GROUP_OPERATION, ID, OPT_ID]),
+        // ray tracing
+        Op::ReportIntersectionNV => typed(&[ID, ID]),
+        Op::IgnoreIntersectionNV | Op::TerminateRayNV => none(&[]),
+        Op::TraceNV => none(&[ID, ID, ID, ID, ID, ID, ID, ID, ID, ID, ID]),
+        Op::ExecuteCallableNV => none(&[ID, ID]),
+        _ => return None,
+    })
+}
+
+/// What the instructions need to know about the ones before them,
+/// in order to interpret the literal operands.
+#[derive(Default)]
+pub(crate) struct Context {
+    pub numeric_types: FastHashMap<Word, NumericType>,
+    pub result_types: FastHashMap<Word, Word>,
+    pub ext_inst_sets: FastHashMap<Word, String>,
+}
+
+impl Context {
+    /// Returns the type of the literals in the instruction with the given result type.
+    pub(crate) fn literal_type(&self, type_id: Option<Word>) -> Option<NumericType> {
+        type_id.and_then(|id| self.numeric_types.get(&id).cloned())
+    }
+
+    /// Returns the type of the `OpSwitch` literals, for the given selector.
+    pub(crate) fn selector_type(&self, selector: Option<Word>) -> Option<NumericType> {
+        self.literal_type(selector.and_then(|id| self.result_types.get(&id).cloned()))
+    }
+
+    /// Returns the name of the extended instruction set with the given id.
+    pub(crate) fn ext_inst_set(&self, set: Option<Word>) -> Option<&str> {
+        set.and_then(|id| self.ext_inst_sets.get(&id))
+            .map(|name| name.as_str())
+    }
+
+    /// Records an instruction, given the words of its operands.
+    pub(crate) fn track(
+        &mut self,
+        op: Op,
+        type_id: Option<Word>,
+        result_id: Option<Word>,
+        operands: &[Word],
+    ) {
+        let result_id = match result_id {
+            Some(id) => id,
+            None => return,
+        };
+        if let Some(type_id) = type_id {
+            self.result_types.insert(result_id, type_id);
+        }
+        match op {
+            Op::TypeInt => {
+                if let [width, signedness] = *operands {
+                    let signed = signedness != 0;
+                    self.numeric_types
+                        .insert(result_id, NumericType::Int { width, signed });
+                }
+            }
+            Op::TypeFloat => {
+                if let [width] = *operands {
+                    self.numeric_types
+                        .insert(result_id, NumericType::Float { width });
+                }
+            }
+            Op::ExtInstImport => {
+                if let Some((name, _)) = decode_string(operands) {
+                    self.ext_inst_sets.insert(result_id, name);
+                }
+            }
+            _ => {}
+        }
+    }
+}
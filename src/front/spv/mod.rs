@@ -11,12 +11,15 @@ TODO: would be nice to find ways that avoid looking up as much
 !*/
 #![allow(dead_code)]
 
+mod assembler;
+mod atomic;
 mod convert;
 mod error;
 mod flow;
 mod function;
 mod image;
 
+pub use assembler::{assemble, AssemblyError, AssemblyErrorKind};
 use convert::*;
 use error::Error;
 use flow::*;
@@ -24,7 +27,7 @@ use function::*;
 
 use crate::{
     arena::{Arena, Handle},
-    FastHashMap, FastHashSet,
+    FastHashMap, FastHashSet, Span,
 };
 
 use num_traits::cast::FromPrimitive;
@@ -41,8 +44,24 @@ pub const SUPPORTED_CAPABILITIES: &[spirv::Capability] = &[
     spirv::Capability::StorageImageExtendedFormats,
     spirv::Capability::Sampled1D,
     spirv::Capability::SampledCubeArray,
+    spirv::Capability::Geometry,
+    spirv::Capability::Tessellation,
+    spirv::Capability::GroupNonUniform,
+    spirv::Capability::GroupNonUniformVote,
+    spirv::Capability::GroupNonUniformArithmetic,
+    spirv::Capability::GroupNonUniformBallot,
+    spirv::Capability::GroupNonUniformShuffle,
+    spirv::Capability::GroupNonUniformShuffleRelative,
+    spirv::Capability::RayTracingProvisionalKHR,
+    spirv::Capability::Float16,
+    spirv::Capability::Float64,
+    spirv::Capability::Int64,
+    spirv::Capability::StorageBuffer16BitAccess,
+    spirv::Capability::UniformAndStorageBuffer16BitAccess,
+    spirv::Capability::StoragePushConstant16,
+    spirv::Capability::StorageInputOutput16,
 ];
-pub const SUPPORTED_EXTENSIONS: &[&str] = &[];
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["SPV_KHR_ray_tracing", "SPV_KHR_16bit_storage"];
 pub const SUPPORTED_EXT_SETS: &[&str] = &["GLSL.std.450"];
 
 #[derive(Copy, Clone)]
@@ -250,6 +269,8 @@ struct EntryPoint {
     name: String,
     early_depth_test: Option<crate::EarlyDepthTest>,
     workgroup_size: [u32; 3],
+    geometry: Option<crate::GeometryInfo>,
+    tessellation: Option<crate::TessellationInfo>,
     function_id: spirv::Word,
     variable_ids: Vec<spirv::Word>,
 }
@@ -266,6 +287,15 @@ struct LookupConstant {
     type_id: spirv::Word,
 }
 
+/// Interface of an entry point variable, which is made private
+/// once the entry point is wrapped.
+#[derive(Debug)]
+struct Varying {
+    class: crate::StorageClass,
+    binding: Option<crate::Binding>,
+    interpolation: Option<crate::Interpolation>,
+}
+
 #[derive(Debug)]
 struct LookupVariable {
     handle: Handle<crate::GlobalVariable>,
@@ -309,11 +339,19 @@ pub struct Parser<I> {
     lookup_function_type: FastHashMap<spirv::Word, LookupFunctionType>,
     lookup_function: FastHashMap<spirv::Word, Handle<crate::Function>>,
     lookup_entry_point: FastHashMap<spirv::Word, EntryPoint>,
+    /// Interface variables and the span of each of the parsed entry points.
+    entry_point_interfaces: Vec<(Vec<spirv::Word>, Span)>,
+    // Pointers used by atomic operations in the current function.
+    atomic_pointers: Vec<Handle<crate::Expression>>,
     //Note: the key here is fully artificial, has nothing to do with the module
     deferred_function_calls: FastHashMap<Handle<crate::Function>, spirv::Word>,
     dummy_functions: Arena<crate::Function>,
     options: Options,
     index_constants: Vec<Handle<crate::Constant>>,
+    /// Source text given by `OpSource`, along with the id of its file name.
+    source: Option<(Option<spirv::Word>, String)>,
+    /// Span of the source line given by the last `OpLine`.
+    span: Span,
 }
 
 impl<I: Iterator<Item = u32>> Parser<I> {
@@ -337,10 +375,14 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             lookup_function_type: FastHashMap::default(),
             lookup_function: FastHashMap::default(),
             lookup_entry_point: FastHashMap::default(),
+            entry_point_interfaces: Vec::new(),
+            atomic_pointers: Vec::new(),
             deferred_function_calls: FastHashMap::default(),
             dummy_functions: Arena::new(),
             options: options.clone(),
             index_constants: Vec::new(),
+            source: None,
+            span: Span::UNDEFINED,
         }
     }
 
@@ -571,6 +613,40 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         }))
     }
 
+    fn lookup_uint_constant(
+        &self,
+        id: spirv::Word,
+        const_arena: &Arena<crate::Constant>,
+    ) -> Result<u32, Error> {
+        let handle = self.lookup_constant.lookup(id)?.handle;
+        match const_arena[handle].inner {
+            crate::ConstantInner::Scalar {
+                width: 4,
+                value: crate::ScalarValue::Uint(v),
+            } => Ok(v as u32),
+            crate::ConstantInner::Scalar {
+                width: 4,
+                value: crate::ScalarValue::Sint(v),
+            } => Ok(v as u32),
+            _ => Err(Error::InvalidOperand),
+        }
+    }
+
+    fn append_subgroup_result(
+        &self,
+        result_type_id: spirv::Word,
+        type_arena: &Arena<crate::Type>,
+        expressions: &mut Arena<crate::Expression>,
+    ) -> Result<Handle<crate::Expression>, Error> {
+        let result_type = self.lookup_type.lookup(result_type_id)?.handle;
+        let (kind, width, size) = match type_arena[result_type].inner {
+            crate::TypeInner::Scalar { kind, width } => (kind, width, None),
+            crate::TypeInner::Vector { size, kind, width } => (kind, width, Some(size)),
+            _ => return Err(Error::UnsupportedType(result_type)),
+        };
+        Ok(expressions.append(crate::Expression::SubgroupResult { kind, width, size }))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn next_block(
         &mut self,
@@ -581,22 +657,28 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         const_arena: &Arena<crate::Constant>,
         global_arena: &Arena<crate::GlobalVariable>,
     ) -> Result<ControlFlowNode, Error> {
-        let mut block = Vec::new();
+        let mut block = crate::Block::new();
         let mut phis = Vec::new();
         let mut emitter = super::Emitter::default();
         emitter.start(expressions);
         let mut merge = None;
+        // everything parsed since the last `OpLine` gets its span
+        let (mut statement_start, mut expression_start, mut local_start) =
+            (0, expressions.len(), local_arena.len());
         let terminator = loop {
             use spirv::Op;
             let inst = self.next_inst()?;
             log::debug!("\t\t{:?} [{}]", inst.op, inst.wc);
 
             match inst.op {
-                Op::Line => {
-                    inst.expect(4)?;
-                    let _file_id = self.next()?;
-                    let _row_id = self.next()?;
-                    let _col_id = self.next()?;
+                Op::Line | Op::NoLine => {
+                    block.fill_spans_from(statement_start, self.span);
+                    expressions.fill_spans_from(expression_start, self.span);
+                    local_arena.fill_spans_from(local_start, self.span);
+                    statement_start = block.len();
+                    expression_start = expressions.len();
+                    local_start = local_arena.len();
+                    self.parse_line(inst)?;
                 }
                 Op::Undef => {
                     inst.expect(3)?;
@@ -988,9 +1070,9 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                     let base_lexp = self.lookup_expression.lookup(pointer_id)?;
                     let type_lookup = self.lookup_type.lookup(base_lexp.type_id)?;
                     let handle = match type_arena[type_lookup.handle].inner {
-                        crate::TypeInner::Image { .. } | crate::TypeInner::Sampler { .. } => {
-                            base_lexp.handle
-                        }
+                        crate::TypeInner::Image { .. }
+                        | crate::TypeInner::Sampler { .. }
+                        | crate::TypeInner::AccelerationStructure => base_lexp.handle,
                         _ => expressions.append(crate::Expression::Load {
                             pointer: base_lexp.handle,
                         }),
@@ -1022,6 +1104,274 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                     });
                     emitter.start(expressions);
                 }
+                Op::AtomicLoad => {
+                    inst.expect(6)?;
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+
+                    let pointer = self.lookup_expression.lookup(pointer_id)?.handle;
+                    self.atomic_pointers.push(pointer);
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: expressions.append(crate::Expression::Load { pointer }),
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::AtomicStore => {
+                    inst.expect(5)?;
+                    block.extend(emitter.finish(expressions));
+
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+                    let value_id = self.next()?;
+
+                    let pointer = self.lookup_expression.lookup(pointer_id)?.handle;
+                    self.atomic_pointers.push(pointer);
+                    let value = self.lookup_expression.lookup(value_id)?.handle;
+                    block.push(crate::Statement::Store { pointer, value });
+                    emitter.start(expressions);
+                }
+                Op::AtomicExchange
+                | Op::AtomicCompareExchange
+                | Op::AtomicIAdd
+                | Op::AtomicISub
+                | Op::AtomicSMin
+                | Op::AtomicUMin
+                | Op::AtomicSMax
+                | Op::AtomicUMax
+                | Op::AtomicAnd
+                | Op::AtomicOr
+                | Op::AtomicXor => {
+                    let comparison = inst.op == Op::AtomicCompareExchange;
+                    inst.expect(if comparison { 9 } else { 7 })?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+                    if comparison {
+                        let _unequal_semantics_id = self.next()?;
+                    }
+                    let value_id = self.next()?;
+
+                    let pointer = self.lookup_expression.lookup(pointer_id)?.handle;
+                    self.atomic_pointers.push(pointer);
+                    let value = self.lookup_expression.lookup(value_id)?.handle;
+                    let fun = match inst.op {
+                        Op::AtomicExchange => crate::AtomicFunction::Exchange { compare: None },
+                        Op::AtomicCompareExchange => {
+                            let compare_id = self.next()?;
+                            let compare = self.lookup_expression.lookup(compare_id)?.handle;
+                            crate::AtomicFunction::Exchange {
+                                compare: Some(compare),
+                            }
+                        }
+                        Op::AtomicIAdd => crate::AtomicFunction::Add,
+                        Op::AtomicISub => crate::AtomicFunction::Subtract,
+                        Op::AtomicSMin | Op::AtomicUMin => crate::AtomicFunction::Min,
+                        Op::AtomicSMax | Op::AtomicUMax => crate::AtomicFunction::Max,
+                        Op::AtomicAnd => crate::AtomicFunction::And,
+                        Op::AtomicOr => crate::AtomicFunction::InclusiveOr,
+                        _ => crate::AtomicFunction::ExclusiveOr,
+                    };
+
+                    let result_type = self.lookup_type.lookup(result_type_id)?.handle;
+                    let (kind, width) = match type_arena[result_type].inner {
+                        crate::TypeInner::Scalar { kind, width } => (kind, width),
+                        _ => return Err(Error::UnsupportedType(result_type)),
+                    };
+                    let result = expressions.append(crate::Expression::AtomicResult {
+                        kind,
+                        width,
+                        comparison,
+                    });
+                    block.push(crate::Statement::Atomic {
+                        pointer,
+                        fun,
+                        value,
+                        result,
+                    });
+                    emitter.start(expressions);
+
+                    // SPIR-V only returns the original value
+                    let handle = if comparison {
+                        expressions.append(crate::Expression::AccessIndex {
+                            base: result,
+                            index: 0,
+                        })
+                    } else {
+                        result
+                    };
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle,
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::GroupNonUniformElect
+                | Op::GroupNonUniformAll
+                | Op::GroupNonUniformAny
+                | Op::GroupNonUniformBallot
+                | Op::GroupNonUniformBroadcastFirst
+                | Op::GroupNonUniformBroadcast
+                | Op::GroupNonUniformShuffle
+                | Op::GroupNonUniformShuffleXor
+                | Op::GroupNonUniformShuffleUp
+                | Op::GroupNonUniformShuffleDown => {
+                    inst.expect_at_least(4)?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let scope_id = self.next()?;
+                    let scope = self.lookup_uint_constant(scope_id, const_arena)?;
+                    if map_scope(scope)? != crate::Scope::SubGroup {
+                        return Err(Error::UnsupportedScope(scope));
+                    }
+
+                    let mut operands = [None; 2];
+                    for operand in operands.iter_mut().take(inst.wc as usize - 4) {
+                        let id = self.next()?;
+                        *operand = Some(self.lookup_expression.lookup(id)?.handle);
+                    }
+                    let fun = match (inst.op, operands) {
+                        (Op::GroupNonUniformElect, [None, None]) => crate::SubgroupFunction::Elect,
+                        (Op::GroupNonUniformAll, [Some(argument), None])
+                        | (Op::GroupNonUniformAny, [Some(argument), None]) => {
+                            crate::SubgroupFunction::Collective {
+                                op: if inst.op == Op::GroupNonUniformAll {
+                                    crate::SubgroupOperation::All
+                                } else {
+                                    crate::SubgroupOperation::Any
+                                },
+                                collective: crate::CollectiveOperation::Reduce,
+                                argument,
+                            }
+                        }
+                        (Op::GroupNonUniformBallot, [Some(predicate), None]) => {
+                            crate::SubgroupFunction::Ballot {
+                                predicate: Some(predicate),
+                            }
+                        }
+                        (Op::GroupNonUniformBroadcastFirst, [Some(argument), None]) => {
+                            crate::SubgroupFunction::Gather {
+                                mode: crate::GatherMode::BroadcastFirst,
+                                argument,
+                            }
+                        }
+                        (_, [Some(argument), Some(index)]) => {
+                            let mode = match inst.op {
+                                Op::GroupNonUniformBroadcast => crate::GatherMode::Broadcast(index),
+                                Op::GroupNonUniformShuffle => crate::GatherMode::Shuffle(index),
+                                Op::GroupNonUniformShuffleXor => {
+                                    crate::GatherMode::ShuffleXor(index)
+                                }
+                                Op::GroupNonUniformShuffleUp => crate::GatherMode::ShuffleUp(index),
+                                Op::GroupNonUniformShuffleDown => {
+                                    crate::GatherMode::ShuffleDown(index)
+                                }
+                                _ => return Err(Error::InvalidOperandCount(inst.op, inst.wc)),
+                            };
+                            crate::SubgroupFunction::Gather { mode, argument }
+                        }
+                        _ => return Err(Error::InvalidOperandCount(inst.op, inst.wc)),
+                    };
+
+                    let result =
+                        self.append_subgroup_result(result_type_id, type_arena, expressions)?;
+                    block.push(crate::Statement::Subgroup { fun, result });
+                    emitter.start(expressions);
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: result,
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::GroupNonUniformIAdd
+                | Op::GroupNonUniformFAdd
+                | Op::GroupNonUniformIMul
+                | Op::GroupNonUniformFMul
+                | Op::GroupNonUniformSMin
+                | Op::GroupNonUniformUMin
+                | Op::GroupNonUniformFMin
+                | Op::GroupNonUniformSMax
+                | Op::GroupNonUniformUMax
+                | Op::GroupNonUniformFMax
+                | Op::GroupNonUniformBitwiseAnd
+                | Op::GroupNonUniformBitwiseOr
+                | Op::GroupNonUniformBitwiseXor
+                | Op::GroupNonUniformLogicalAnd
+                | Op::GroupNonUniformLogicalOr
+                | Op::GroupNonUniformLogicalXor => {
+                    // clustered operations are not supported
+                    inst.expect(6)?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let scope_id = self.next()?;
+                    let scope = self.lookup_uint_constant(scope_id, const_arena)?;
+                    if map_scope(scope)? != crate::Scope::SubGroup {
+                        return Err(Error::UnsupportedScope(scope));
+                    }
+                    let collective = map_group_operation(self.next()?)?;
+                    let value_id = self.next()?;
+                    let argument = self.lookup_expression.lookup(value_id)?.handle;
+
+                    let op = match inst.op {
+                        Op::GroupNonUniformIAdd | Op::GroupNonUniformFAdd => {
+                            crate::SubgroupOperation::Add
+                        }
+                        Op::GroupNonUniformIMul | Op::GroupNonUniformFMul => {
+                            crate::SubgroupOperation::Multiply
+                        }
+                        Op::GroupNonUniformSMin
+                        | Op::GroupNonUniformUMin
+                        | Op::GroupNonUniformFMin => crate::SubgroupOperation::Min,
+                        Op::GroupNonUniformSMax
+                        | Op::GroupNonUniformUMax
+                        | Op::GroupNonUniformFMax => crate::SubgroupOperation::Max,
+                        Op::GroupNonUniformBitwiseAnd | Op::GroupNonUniformLogicalAnd => {
+                            crate::SubgroupOperation::And
+                        }
+                        Op::GroupNonUniformBitwiseOr | Op::GroupNonUniformLogicalOr => {
+                            crate::SubgroupOperation::InclusiveOr
+                        }
+                        _ => crate::SubgroupOperation::ExclusiveOr,
+                    };
+
+                    let result =
+                        self.append_subgroup_result(result_type_id, type_arena, expressions)?;
+                    block.push(crate::Statement::Subgroup {
+                        fun: crate::SubgroupFunction::Collective {
+                            op,
+                            collective,
+                            argument,
+                        },
+                        result,
+                    });
+                    emitter.start(expressions);
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: result,
+                            type_id: result_type_id,
+                        },
+                    );
+                }
                 // Arithmetic Instructions +, -, *, /, %
                 Op::SNegate | Op::FNegate => {
                     inst.expect(4)?;
@@ -1455,6 +1805,97 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                         },
                     );
                 }
+                Op::ControlBarrier | Op::MemoryBarrier => {
+                    let is_control = inst.op == Op::ControlBarrier;
+                    inst.expect(if is_control { 4 } else { 3 })?;
+                    block.extend(emitter.finish(expressions));
+
+                    let execution = if is_control {
+                        let execution_id = self.next()?;
+                        let scope = self.lookup_uint_constant(execution_id, const_arena)?;
+                        Some(map_scope(scope)?)
+                    } else {
+                        None
+                    };
+                    let memory_scope_id = self.next()?;
+                    let semantics_id = self.next()?;
+                    let memory_scope =
+                        map_scope(self.lookup_uint_constant(memory_scope_id, const_arena)?)?;
+                    let semantics = self.lookup_uint_constant(semantics_id, const_arena)?;
+                    block.push(crate::Statement::Barrier {
+                        execution,
+                        memory_scope,
+                        memory: map_barrier_semantics(semantics),
+                    });
+                    emitter.start(expressions);
+                }
+                Op::EmitVertex | Op::EndPrimitive => {
+                    inst.expect(1)?;
+                    block.extend(emitter.finish(expressions));
+                    block.push(if inst.op == Op::EmitVertex {
+                        crate::Statement::EmitVertex
+                    } else {
+                        crate::Statement::EndPrimitive
+                    });
+                    emitter.start(expressions);
+                }
+                Op::TraceRayKHR => {
+                    inst.expect(12)?;
+                    block.extend(emitter.finish(expressions));
+                    let mut operands = Vec::with_capacity(11);
+                    for _ in 0..11 {
+                        let id = self.next()?;
+                        operands.push(self.lookup_expression.lookup(id)?.handle);
+                    }
+                    block.push(crate::Statement::TraceRay {
+                        acceleration_structure: operands[0],
+                        flags: operands[1],
+                        cull_mask: operands[2],
+                        sbt_offset: operands[3],
+                        sbt_stride: operands[4],
+                        miss_index: operands[5],
+                        origin: operands[6],
+                        t_min: operands[7],
+                        direction: operands[8],
+                        t_max: operands[9],
+                        payload: operands[10],
+                    });
+                    emitter.start(expressions);
+                }
+                Op::ReportIntersectionKHR => {
+                    inst.expect(5)?;
+                    block.extend(emitter.finish(expressions));
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let hit_t_id = self.next()?;
+                    let hit_kind_id = self.next()?;
+                    let hit_t = self.lookup_expression.lookup(hit_t_id)?.handle;
+                    let hit_kind = self.lookup_expression.lookup(hit_kind_id)?.handle;
+                    let result = expressions.append(crate::Expression::ReportIntersectionResult);
+                    block.push(crate::Statement::ReportIntersection {
+                        hit_t,
+                        hit_kind,
+                        result,
+                    });
+                    emitter.start(expressions);
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: result,
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::IgnoreIntersectionKHR | Op::TerminateRayKHR => {
+                    inst.expect(1)?;
+                    block.extend(emitter.finish(expressions));
+                    block.push(if inst.op == Op::IgnoreIntersectionKHR {
+                        crate::Statement::IgnoreIntersection
+                    } else {
+                        crate::Statement::TerminateRay
+                    });
+                    emitter.start(expressions);
+                }
                 Op::Kill => {
                     inst.expect(1)?;
                     break Terminator::Kill;
@@ -1565,6 +2006,9 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         };
 
         block.extend(emitter.finish(expressions));
+        block.fill_spans_from(statement_start, self.span);
+        expressions.fill_spans_from(expression_start, self.span);
+        local_arena.fill_spans_from(local_start, self.span);
         Ok(ControlFlowNode {
             id: block_id,
             ty: None,
@@ -1657,7 +2101,16 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 | S::Return { .. }
                 | S::Kill
                 | S::Store { .. }
-                | S::ImageStore { .. } => {}
+                | S::ImageStore { .. }
+                | S::Atomic { .. }
+                | S::Barrier { .. }
+                | S::Subgroup { .. }
+                | S::EmitVertex
+                | S::EndPrimitive
+                | S::TraceRay { .. }
+                | S::ReportIntersection { .. }
+                | S::IgnoreIntersection
+                | S::TerminateRay => {}
                 S::Call {
                     ref mut function, ..
                 } => {
@@ -1725,7 +2178,9 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 Op::ExecutionMode => self.parse_execution_mode(inst),
                 Op::String => self.parse_string(inst),
                 Op::Source => self.parse_source(inst),
+                Op::SourceContinued => self.parse_source_continued(inst),
                 Op::SourceExtension => self.parse_source_extension(inst),
+                Op::Line | Op::NoLine => self.parse_line(inst),
                 Op::Name => self.parse_name(inst),
                 Op::MemberName => self.parse_member_name(inst),
                 Op::Decorate => self.parse_decorate(inst),
@@ -1744,6 +2199,9 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 Op::TypeImage => self.parse_type_image(inst, &mut module),
                 Op::TypeSampledImage => self.parse_type_sampled_image(inst),
                 Op::TypeSampler => self.parse_type_sampler(inst, &mut module),
+                Op::TypeAccelerationStructureKHR => {
+                    self.parse_type_acceleration_structure(inst, &mut module)
+                }
                 Op::Constant | Op::SpecConstant => self.parse_constant(inst, &mut module),
                 Op::ConstantComposite => self.parse_composite_constant(inst, &mut module),
                 Op::ConstantNull | Op::Undef => self.parse_null_constant(inst, &mut module),
@@ -1769,6 +2227,16 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         }
         self.lookup_function.clear();
 
+        // Vertex, fragment, and compute entry points take their inputs as arguments
+        // and return their outputs, so they are turned into wrappers of the original code.
+        let mut varyings = FastHashMap::default();
+        for (index, (variable_ids, span)) in std::mem::take(&mut self.entry_point_interfaces)
+            .into_iter()
+            .enumerate()
+        {
+            self.wrap_entry_point(index, &variable_ids, span, &mut varyings, &mut module)?;
+        }
+
         // Check all the images and samplers to have consistent comparison property.
         for (handle, flags) in self.handle_sampling.drain() {
             if !image::patch_comparison_type(
@@ -1848,16 +2316,50 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             .ok_or(Error::UnsupportedExecutionModel(exec_model))?;
         let function_id = self.next()?;
         let (name, left) = self.next_string(inst.wc - 3)?;
+        let stage = match exec_model {
+            spirv::ExecutionModel::Vertex => crate::ShaderStage::Vertex,
+            spirv::ExecutionModel::Fragment => crate::ShaderStage::Fragment,
+            spirv::ExecutionModel::GLCompute => crate::ShaderStage::Compute,
+            spirv::ExecutionModel::Geometry => crate::ShaderStage::Geometry,
+            spirv::ExecutionModel::TessellationControl => crate::ShaderStage::TessellationControl,
+            spirv::ExecutionModel::TessellationEvaluation => {
+                crate::ShaderStage::TessellationEvaluation
+            }
+            spirv::ExecutionModel::RayGenerationKHR => crate::ShaderStage::RayGeneration,
+            spirv::ExecutionModel::IntersectionKHR => crate::ShaderStage::Intersection,
+            spirv::ExecutionModel::AnyHitKHR => crate::ShaderStage::AnyHit,
+            spirv::ExecutionModel::ClosestHitKHR => crate::ShaderStage::ClosestHit,
+            spirv::ExecutionModel::MissKHR => crate::ShaderStage::Miss,
+            spirv::ExecutionModel::CallableKHR => crate::ShaderStage::Callable,
+            _ => return Err(Error::UnsupportedExecutionModel(exec_model as u32)),
+        };
         let ep = EntryPoint {
-            stage: match exec_model {
-                spirv::ExecutionModel::Vertex => crate::ShaderStage::Vertex,
-                spirv::ExecutionModel::Fragment => crate::ShaderStage::Fragment,
-                spirv::ExecutionModel::GLCompute => crate::ShaderStage::Compute,
-                _ => return Err(Error::UnsupportedExecutionModel(exec_model as u32)),
-            },
+            stage,
             name,
             early_depth_test: None,
             workgroup_size: [0; 3],
+            // the actual primitives are specified by the execution modes
+            geometry: match stage {
+                crate::ShaderStage::Geometry => Some(crate::GeometryInfo {
+                    input: crate::GeometryInput::Triangles,
+                    output: crate::GeometryOutput::TriangleStrip,
+                    max_vertices: 0,
+                }),
+                _ => None,
+            },
+            tessellation: match stage {
+                crate::ShaderStage::TessellationControl => {
+                    Some(crate::TessellationInfo::Control { output_vertices: 0 })
+                }
+                crate::ShaderStage::TessellationEvaluation => {
+                    Some(crate::TessellationInfo::Evaluation {
+                        primitive: crate::TessellationPrimitive::Triangles,
+                        spacing: crate::TessellationSpacing::Equal,
+                        vertex_order: crate::VertexOrder::CounterClockwise,
+                    })
+                }
+                _ => None,
+            },
             function_id,
             variable_ids: self.data.by_ref().take(left as usize).collect(),
         };
@@ -1912,6 +2414,98 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             ExecutionMode::LocalSize => {
                 ep.workgroup_size = [args[0], args[1], args[2]];
             }
+            ExecutionMode::Invocations if args.first() == Some(&1) => {
+                // Single invocation is the default.
+            }
+            ExecutionMode::Triangles | ExecutionMode::Quads | ExecutionMode::Isolines
+                if ep.geometry.is_none() =>
+            {
+                match ep.tessellation {
+                    Some(crate::TessellationInfo::Evaluation {
+                        ref mut primitive, ..
+                    }) => {
+                        *primitive = match mode {
+                            ExecutionMode::Triangles => crate::TessellationPrimitive::Triangles,
+                            ExecutionMode::Quads => crate::TessellationPrimitive::Quads,
+                            _ => crate::TessellationPrimitive::Isolines,
+                        };
+                    }
+                    _ => return Err(Error::UnsupportedExecutionMode(mode_id)),
+                }
+            }
+            ExecutionMode::SpacingEqual
+            | ExecutionMode::SpacingFractionalEven
+            | ExecutionMode::SpacingFractionalOdd => match ep.tessellation {
+                Some(crate::TessellationInfo::Evaluation {
+                    ref mut spacing, ..
+                }) => {
+                    *spacing = match mode {
+                        ExecutionMode::SpacingEqual => crate::TessellationSpacing::Equal,
+                        ExecutionMode::SpacingFractionalEven => {
+                            crate::TessellationSpacing::FractionalEven
+                        }
+                        _ => crate::TessellationSpacing::FractionalOdd,
+                    };
+                }
+                _ => return Err(Error::UnsupportedExecutionMode(mode_id)),
+            },
+            ExecutionMode::VertexOrderCw | ExecutionMode::VertexOrderCcw => match ep.tessellation {
+                Some(crate::TessellationInfo::Evaluation {
+                    ref mut vertex_order,
+                    ..
+                }) => {
+                    *vertex_order = match mode {
+                        ExecutionMode::VertexOrderCw => crate::VertexOrder::Clockwise,
+                        _ => crate::VertexOrder::CounterClockwise,
+                    };
+                }
+                _ => return Err(Error::UnsupportedExecutionMode(mode_id)),
+            },
+            ExecutionMode::OutputVertices if ep.geometry.is_none() => match ep.tessellation {
+                Some(crate::TessellationInfo::Control {
+                    ref mut output_vertices,
+                }) => {
+                    *output_vertices = *args.first().ok_or(Error::InvalidOperand)?;
+                }
+                _ => return Err(Error::UnsupportedExecutionMode(mode_id)),
+            },
+            ExecutionMode::InputPoints
+            | ExecutionMode::InputLines
+            | ExecutionMode::InputLinesAdjacency
+            | ExecutionMode::Triangles
+            | ExecutionMode::InputTrianglesAdjacency => {
+                let geometry = ep
+                    .geometry
+                    .as_mut()
+                    .ok_or(Error::UnsupportedExecutionMode(mode_id))?;
+                geometry.input = match mode {
+                    ExecutionMode::InputPoints => crate::GeometryInput::Points,
+                    ExecutionMode::InputLines => crate::GeometryInput::Lines,
+                    ExecutionMode::InputLinesAdjacency => crate::GeometryInput::LinesAdjacency,
+                    ExecutionMode::Triangles => crate::GeometryInput::Triangles,
+                    _ => crate::GeometryInput::TrianglesAdjacency,
+                };
+            }
+            ExecutionMode::OutputPoints
+            | ExecutionMode::OutputLineStrip
+            | ExecutionMode::OutputTriangleStrip => {
+                let geometry = ep
+                    .geometry
+                    .as_mut()
+                    .ok_or(Error::UnsupportedExecutionMode(mode_id))?;
+                geometry.output = match mode {
+                    ExecutionMode::OutputPoints => crate::GeometryOutput::Points,
+                    ExecutionMode::OutputLineStrip => crate::GeometryOutput::LineStrip,
+                    _ => crate::GeometryOutput::TriangleStrip,
+                };
+            }
+            ExecutionMode::OutputVertices => {
+                let geometry = ep
+                    .geometry
+                    .as_mut()
+                    .ok_or(Error::UnsupportedExecutionMode(mode_id))?;
+                geometry.max_vertices = *args.first().ok_or(Error::InvalidOperand)?;
+            }
             _ => {
                 return Err(Error::UnsupportedExecutionMode(mode_id));
             }
@@ -1930,12 +2524,60 @@ impl<I: Iterator<Item = u32>> Parser<I> {
 
     fn parse_source(&mut self, inst: Instruction) -> Result<(), Error> {
         self.switch(ModuleState::Source, inst.op)?;
-        for _ in 1..inst.wc {
-            let _ = self.next()?;
+        inst.expect_at_least(3)?;
+        let _language = self.next()?;
+        let _version = self.next()?;
+        if inst.wc > 3 {
+            let file_id = self.next()?;
+            if inst.wc > 4 {
+                let (text, _) = self.next_string(inst.wc - 4)?;
+                self.source = Some((Some(file_id), text));
+            }
         }
         Ok(())
     }
 
+    fn parse_source_continued(&mut self, inst: Instruction) -> Result<(), Error> {
+        self.switch(ModuleState::Source, inst.op)?;
+        inst.expect_at_least(2)?;
+        let (text, _) = self.next_string(inst.wc - 1)?;
+        match self.source {
+            Some((_, ref mut source)) => source.push_str(&text),
+            None => self.source = Some((None, text)),
+        }
+        Ok(())
+    }
+
+    fn parse_line(&mut self, inst: Instruction) -> Result<(), Error> {
+        if inst.op == spirv::Op::NoLine {
+            inst.expect(1)?;
+            self.span = Span::UNDEFINED;
+            return Ok(());
+        }
+        inst.expect(4)?;
+        let file_id = self.next()?;
+        let line = self.next()?;
+        let column = self.next()?;
+        self.span = match self.source {
+            Some((id, ref text)) if id.unwrap_or(file_id) == file_id => {
+                // span from the given column to the end of the line
+                let mut offset = 0;
+                let mut span = Span::UNDEFINED;
+                for (index, line_text) in text.split('\n').enumerate() {
+                    if index + 1 == line as usize {
+                        let start = (column as usize).saturating_sub(1).min(line_text.len());
+                        span = (offset + start..offset + line_text.len()).into();
+                        break;
+                    }
+                    offset += line_text.len() + 1;
+                }
+                span
+            }
+            _ => Span::UNDEFINED,
+        };
+        Ok(())
+    }
+
     fn parse_source_extension(&mut self, inst: Instruction) -> Result<(), Error> {
         self.switch(ModuleState::Source, inst.op)?;
         inst.expect_at_least(2)?;
@@ -2321,10 +2963,13 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 .unwrap_or_default();
             // this is a bit of a hack
             host_shared |= decor.offset.is_some();
+            let binding = decor.get_binding(false);
             members.push(crate::StructMember {
                 name: decor.name,
                 span: None, //TODO
                 ty,
+                binding,
+                interpolation: decor.interpolation,
             });
         }
 
@@ -2436,6 +3081,29 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         Ok(())
     }
 
+    fn parse_type_acceleration_structure(
+        &mut self,
+        inst: Instruction,
+        module: &mut crate::Module,
+    ) -> Result<(), Error> {
+        self.switch(ModuleState::Type, inst.op)?;
+        inst.expect(2)?;
+        let id = self.next()?;
+        let decor = self.future_decor.remove(&id).unwrap_or_default();
+        let handle = module.types.append(crate::Type {
+            name: decor.name,
+            inner: crate::TypeInner::AccelerationStructure,
+        });
+        self.lookup_type.insert(
+            id,
+            LookupType {
+                handle,
+                base_id: None,
+            },
+        );
+        Ok(())
+    }
+
     fn parse_type_sampler(
         &mut self,
         inst: Instruction,
@@ -2478,7 +3146,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             } => {
                 let low = self.next()?;
                 let high = if width > 4 {
-                    inst.expect(4)?;
+                    inst.expect(5)?;
                     self.next()?
                 } else {
                     0
@@ -2494,19 +3162,19 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             } => {
                 use std::cmp::Ordering;
                 let low = self.next()?;
-                let high = match width.cmp(&4) {
+                let value = match width.cmp(&4) {
                     Ordering::Less => return Err(Error::InvalidTypeWidth(u32::from(width))),
                     Ordering::Greater => {
-                        inst.expect(4)?;
-                        self.next()?
+                        inst.expect(5)?;
+                        let high = self.next()?;
+                        ((u64::from(high) << 32) | u64::from(low)) as i64
                     }
-                    Ordering::Equal => 0,
+                    // sign-extend the single word
+                    Ordering::Equal => i64::from(low as i32),
                 };
                 crate::ConstantInner::Scalar {
                     width,
-                    value: crate::ScalarValue::Sint(
-                        ((u64::from(high) << 32) | u64::from(low)) as i64,
-                    ),
+                    value: crate::ScalarValue::Sint(value),
                 }
             }
             crate::TypeInner::Scalar {
@@ -2515,9 +3183,10 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             } => {
                 let low = self.next()?;
                 let extended = match width {
+                    2 => crate::proc::f16_from_bits(low as u16),
                     4 => f64::from(f32::from_bits(low)),
                     8 => {
-                        inst.expect(4)?;
+                        inst.expect(5)?;
                         let high = self.next()?;
                         f64::from_bits((u64::from(high) << 32) | u64::from(low))
                     }
@@ -2724,7 +3393,10 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 | crate::BuiltIn::InstanceIndex
                 | crate::BuiltIn::SampleIndex
                 | crate::BuiltIn::VertexIndex
-                | crate::BuiltIn::LocalInvocationIndex => Some(crate::TypeInner::Scalar {
+                | crate::BuiltIn::LocalInvocationIndex
+                | crate::BuiltIn::InvocationId
+                | crate::BuiltIn::PatchVertices
+                | crate::BuiltIn::InstanceCustomIndex => Some(crate::TypeInner::Scalar {
                     kind: crate::ScalarKind::Uint,
                     width: 4,
                 }),
@@ -2749,16 +3421,23 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             }
         }
 
+        // Built-ins can be decorated with `Patch`, but the IR
+        // knows which of them are per-patch already.
+        let interpolation = match binding {
+            Some(crate::Binding::BuiltIn(_)) => None,
+            _ => dec.interpolation,
+        };
+
         let var = crate::GlobalVariable {
             name: dec.name,
             class,
             binding,
             ty: effective_ty,
             init,
-            interpolation: dec.interpolation,
+            interpolation,
             storage_access,
         };
-        let handle = module.global_variables.append(var);
+        let handle = module.global_variables.append_with_span(var, self.span);
         self.lookup_variable
             .insert(id, LookupVariable { handle, type_id });
 
@@ -2782,6 +3461,12 @@ pub fn parse_u8_slice(data: &[u8], options: &Options) -> Result<crate::Module, E
     Parser::new(words, options).parse()
 }
 
+/// Parses a module from the SPIR-V assembly text.
+pub fn parse_str(source: &str, options: &Options) -> Result<crate::Module, Error> {
+    let words = assemble(source).map_err(Error::Assembly)?;
+    Parser::new(words.into_iter(), options).parse()
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -2797,4 +3482,23 @@ mod test {
         ];
         let _ = super::parse_u8_slice(&bin, &Default::default()).unwrap();
     }
+
+    #[test]
+    fn parse_assembly() {
+        let source = "
+               OpCapability Shader
+               OpMemoryModel Logical GLSL450
+               OpEntryPoint GLCompute %main \"main\"
+               OpExecutionMode %main LocalSize 64 1 1
+       %void = OpTypeVoid
+         %fn = OpTypeFunction %void
+       %main = OpFunction %void None %fn
+      %entry = OpLabel
+               OpReturn
+               OpFunctionEnd
+        ";
+        let module = super::parse_str(source, &Default::default()).unwrap();
+        assert_eq!(module.entry_points.len(), 1);
+        assert_eq!(module.entry_points[0].workgroup_size, [64, 1, 1]);
+    }
 }
@@ -7,6 +7,7 @@ pub fn map_storage_class(word: &str) -> Result<crate::StorageClass, Error<'_>> {
         "private" => Ok(crate::StorageClass::Private),
         "uniform" => Ok(crate::StorageClass::Uniform),
         "storage" => Ok(crate::StorageClass::Storage),
+        "workgroup" => Ok(crate::StorageClass::WorkGroup),
         "push_constant" => Ok(crate::StorageClass::PushConstant),
         _ => Err(Error::UnknownStorageClass(word)),
     }
@@ -31,6 +32,9 @@ pub fn map_built_in(word: &str) -> Result<crate::BuiltIn, Error<'_>> {
         "local_invocation_index" => crate::BuiltIn::LocalInvocationIndex,
         "workgroup_id" => crate::BuiltIn::WorkGroupId,
         "workgroup_size" => crate::BuiltIn::WorkGroupSize,
+        // subgroup
+        "subgroup_size" => crate::BuiltIn::SubgroupSize,
+        "subgroup_invocation_id" => crate::BuiltIn::SubgroupInvocationId,
         _ => return Err(Error::UnknownBuiltin(word)),
     })
 }
@@ -96,6 +100,7 @@ pub fn map_storage_format(word: &str) -> Result<crate::StorageFormat, Error<'_>>
 
 pub fn get_scalar_type(word: &str) -> Option<(crate::ScalarKind, crate::Bytes)> {
     match word {
+        "f16" => Some((crate::ScalarKind::Float, 2)),
         "f32" => Some((crate::ScalarKind::Float, 4)),
         "i32" => Some((crate::ScalarKind::Sint, 4)),
         "u32" => Some((crate::ScalarKind::Uint, 4)),
@@ -125,6 +130,72 @@ pub fn map_relational_fun(word: &str) -> Option<crate::RelationalFunction> {
     }
 }
 
+pub fn map_atomic_fun(word: &str) -> Option<crate::AtomicFunction> {
+    match word {
+        "atomicAdd" => Some(crate::AtomicFunction::Add),
+        "atomicSub" => Some(crate::AtomicFunction::Subtract),
+        "atomicAnd" => Some(crate::AtomicFunction::And),
+        "atomicOr" => Some(crate::AtomicFunction::InclusiveOr),
+        "atomicXor" => Some(crate::AtomicFunction::ExclusiveOr),
+        "atomicMin" => Some(crate::AtomicFunction::Min),
+        "atomicMax" => Some(crate::AtomicFunction::Max),
+        "atomicExchange" => Some(crate::AtomicFunction::Exchange { compare: None }),
+        _ => None,
+    }
+}
+
+pub fn map_barrier(word: &str) -> Option<crate::Statement> {
+    let (execution, memory_scope, memory) = match word {
+        "workgroupBarrier" => (
+            crate::Scope::WorkGroup,
+            crate::Scope::WorkGroup,
+            crate::Barrier::WORK_GROUP,
+        ),
+        "storageBarrier" => (
+            crate::Scope::WorkGroup,
+            crate::Scope::Device,
+            crate::Barrier::STORAGE,
+        ),
+        "subgroupBarrier" => (
+            crate::Scope::SubGroup,
+            crate::Scope::SubGroup,
+            crate::Barrier::STORAGE | crate::Barrier::WORK_GROUP,
+        ),
+        _ => return None,
+    };
+    Some(crate::Statement::Barrier {
+        execution: Some(execution),
+        memory_scope,
+        memory,
+    })
+}
+
+pub fn map_subgroup_operation(
+    word: &str,
+) -> Option<(crate::SubgroupOperation, crate::CollectiveOperation)> {
+    let word = word.strip_prefix("subgroup")?;
+    let (collective, word) = if let Some(rest) = word.strip_prefix("Inclusive") {
+        (crate::CollectiveOperation::InclusiveScan, rest)
+    } else if let Some(rest) = word.strip_prefix("Exclusive") {
+        (crate::CollectiveOperation::ExclusiveScan, rest)
+    } else {
+        (crate::CollectiveOperation::Reduce, word)
+    };
+    let op = match word {
+        "All" => crate::SubgroupOperation::All,
+        "Any" => crate::SubgroupOperation::Any,
+        "Add" => crate::SubgroupOperation::Add,
+        "Mul" => crate::SubgroupOperation::Multiply,
+        "Min" => crate::SubgroupOperation::Min,
+        "Max" => crate::SubgroupOperation::Max,
+        "And" => crate::SubgroupOperation::And,
+        "Or" => crate::SubgroupOperation::InclusiveOr,
+        "Xor" => crate::SubgroupOperation::ExclusiveOr,
+        _ => return None,
+    };
+    Some((op, collective))
+}
+
 pub fn map_standard_fun(word: &str) -> Option<crate::MathFunction> {
     use crate::MathFunction as Mf;
     Some(match word {
@@ -46,6 +46,15 @@ fn consume_number(input: &str) -> (Token, &str) {
             let (width, rest) = rest[1..].split_at(width_end);
             (Token::Number { value, ty, width }, rest)
         }
+        // `h` is a shorthand for half-precision floats
+        'h' => (
+            Token::Number {
+                value,
+                ty: 'f',
+                width: "16",
+            },
+            rest_iter.as_str(),
+        ),
         // default to `i32` or `f32`
         _ => (
             Token::Number {
@@ -179,6 +188,16 @@ impl<'a> Lexer<'a> {
         self.source.len() - self.input.len()
     }
 
+    /// Returns the byte offset of the next token, skipping any trivia.
+    pub(super) fn start_byte_offset(&mut self) -> usize {
+        self.peek().1.start
+    }
+
+    /// Returns the span from `start` to the end of the last consumed token.
+    pub(super) fn span_from(&self, start: usize) -> crate::Span {
+        (start..self.current_byte_offset()).into()
+    }
+
     #[must_use]
     pub(super) fn next(&mut self) -> TokenSpan<'a> {
         let mut start_byte_offset = self.current_byte_offset();
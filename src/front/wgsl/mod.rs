@@ -9,7 +9,10 @@ mod tests;
 
 use crate::{
     arena::{Arena, Handle},
-    proc::{ensure_block_returns, ResolveContext, ResolveError, Typifier},
+    proc::{
+        ensure_block_returns, ConstantEvaluator, ConstantEvaluatorError, ResolveContext,
+        ResolveError, Typifier,
+    },
     FastHashMap,
 };
 
@@ -24,7 +27,6 @@ use codespan_reporting::{
 };
 use std::{
     io::{self, Write},
-    iter,
     num::NonZeroU32,
     ops::Range,
 };
@@ -134,6 +136,8 @@ pub enum Error<'a> {
     BadTypeCast(&'a str),
     #[error(transparent)]
     InvalidResolve(ResolveError),
+    #[error("invalid constant expression: {0}")]
+    InvalidConstant(ConstantEvaluatorError),
     #[error("invalid statement {0:?}, expected {1}")]
     InvalidStatement(crate::Statement, &'a str),
     #[error("resource type {0:?} is invalid")]
@@ -164,12 +168,22 @@ pub enum Error<'a> {
     UnknownStorageFormat(&'a str),
     #[error("unknown conservative depth: `{0}`")]
     UnknownConservativeDepth(&'a str),
+    #[error("unknown extension: `{0}`")]
+    UnknownExtension(&'a str),
+    #[error("extension `{0}` has to be enabled first")]
+    DisabledExtension(&'a str),
     #[error("array stride must not be 0")]
     ZeroStride,
     #[error("not a composite type: {0:?}")]
     NotCompositeType(Handle<crate::Type>),
     #[error("call to local `{0}(..)` can't be resolved")]
     UnknownLocalFunction(&'a str),
+    #[error("atomic operand `{0}` is not a pointer to an atomic")]
+    InvalidAtomicPointer(&'a str),
+    #[error("atomic operand {0:?} has invalid type")]
+    InvalidAtomicOperand(Handle<crate::Expression>),
+    #[error("subgroup operand {0:?} has invalid type")]
+    InvalidSubgroupOperand(Handle<crate::Expression>),
     #[error("builtin {0:?} is not implemented")]
     UnimplementedBuiltin(crate::BuiltIn),
     #[error("other error")]
@@ -314,6 +328,7 @@ impl<'a> ExpressionContext<'a, '_, '_> {
             ExpressionContext<'a, '_, '_>,
         ) -> Result<Handle<crate::Expression>, Error<'a>>,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        let start = lexer.start_byte_offset();
         let mut left = parser(lexer, self.reborrow())?;
         while let Some(op) = classifier(lexer.peek().0) {
             let _ = lexer.next();
@@ -322,7 +337,9 @@ impl<'a> ExpressionContext<'a, '_, '_> {
                 left,
                 right: parser(lexer, self.reborrow())?,
             };
-            left = self.expressions.append(expression);
+            left = self
+                .expressions
+                .append_with_span(expression, lexer.span_from(start));
         }
         Ok(left)
     }
@@ -502,6 +519,7 @@ impl<'a> std::error::Error for ParseError<'a> {
 pub struct Parser {
     scopes: Vec<Scope>,
     lookup_type: FastHashMap<String, Handle<crate::Type>>,
+    f16_enabled: bool,
 }
 
 impl Parser {
@@ -509,6 +527,7 @@ impl Parser {
         Parser {
             scopes: Vec::new(),
             lookup_type: FastHashMap::default(),
+            f16_enabled: false,
         }
     }
 
@@ -577,6 +596,162 @@ impl Parser {
         Ok(Some((fun_handle, arguments)))
     }
 
+    fn parse_atomic_pointer<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        lexer.expect(Token::Operation('&'))?;
+        let name = lexer.next_ident()?;
+        let expr = ctx.lookup_ident.lookup(name)?;
+        let pointer = self.parse_postfix(lexer, ctx.reborrow(), expr, false)?;
+        match *ctx.resolve_type(pointer)? {
+            crate::TypeInner::Pointer { base, .. } => match ctx.types[base].inner {
+                crate::TypeInner::Atomic { .. } => Ok(pointer),
+                _ => Err(Error::InvalidAtomicPointer(name)),
+            },
+            _ => Err(Error::InvalidAtomicPointer(name)),
+        }
+    }
+
+    fn parse_atomic_helper<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        name: &'a str,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Option<Handle<crate::Expression>>, Error<'a>> {
+        let fun = match conv::map_atomic_fun(name) {
+            Some(fun) => Some(fun),
+            None if name == "atomicCompareExchangeWeak" => None,
+            None => return Ok(None),
+        };
+        lexer.expect(Token::Paren('('))?;
+        let pointer = self.parse_atomic_pointer(lexer, ctx.reborrow())?;
+        lexer.expect(Token::Separator(','))?;
+        let fun = match fun {
+            Some(fun) => fun,
+            None => {
+                let compare = self.parse_general_expression(lexer, ctx.reborrow())?;
+                lexer.expect(Token::Separator(','))?;
+                crate::AtomicFunction::Exchange {
+                    compare: Some(compare),
+                }
+            }
+        };
+        let value = self.parse_general_expression(lexer, ctx.reborrow())?;
+        lexer.expect(Token::Paren(')'))?;
+
+        let (kind, width) = match *ctx.resolve_type(value)? {
+            crate::TypeInner::Scalar { kind, width } => (kind, width),
+            _ => return Err(Error::InvalidAtomicOperand(value)),
+        };
+        ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+        let result = ctx.expressions.append(crate::Expression::AtomicResult {
+            kind,
+            width,
+            comparison: matches!(fun, crate::AtomicFunction::Exchange { compare: Some(_) }),
+        });
+        ctx.block.push(crate::Statement::Atomic {
+            pointer,
+            fun,
+            value,
+            result,
+        });
+        // restart the emitter
+        ctx.emitter.start(ctx.expressions);
+        Ok(Some(result))
+    }
+
+    fn parse_subgroup_helper<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        name: &'a str,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Option<Handle<crate::Expression>>, Error<'a>> {
+        let fun = match name {
+            "subgroupElect" => {
+                lexer.expect(Token::Paren('('))?;
+                lexer.expect(Token::Paren(')'))?;
+                crate::SubgroupFunction::Elect
+            }
+            "subgroupBallot" => {
+                lexer.expect(Token::Paren('('))?;
+                let predicate = if lexer.skip(Token::Paren(')')) {
+                    None
+                } else {
+                    let predicate = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    lexer.expect(Token::Paren(')'))?;
+                    Some(predicate)
+                };
+                crate::SubgroupFunction::Ballot { predicate }
+            }
+            "subgroupBroadcastFirst" => {
+                lexer.expect(Token::Paren('('))?;
+                let argument = self.parse_general_expression(lexer, ctx.reborrow())?;
+                lexer.expect(Token::Paren(')'))?;
+                crate::SubgroupFunction::Gather {
+                    mode: crate::GatherMode::BroadcastFirst,
+                    argument,
+                }
+            }
+            "subgroupBroadcast"
+            | "subgroupShuffle"
+            | "subgroupShuffleDown"
+            | "subgroupShuffleUp"
+            | "subgroupShuffleXor" => {
+                lexer.expect(Token::Paren('('))?;
+                let argument = self.parse_general_expression(lexer, ctx.reborrow())?;
+                lexer.expect(Token::Separator(','))?;
+                let index = self.parse_general_expression(lexer, ctx.reborrow())?;
+                lexer.expect(Token::Paren(')'))?;
+                let mode = match name {
+                    "subgroupBroadcast" => crate::GatherMode::Broadcast(index),
+                    "subgroupShuffle" => crate::GatherMode::Shuffle(index),
+                    "subgroupShuffleDown" => crate::GatherMode::ShuffleDown(index),
+                    "subgroupShuffleUp" => crate::GatherMode::ShuffleUp(index),
+                    _ => crate::GatherMode::ShuffleXor(index),
+                };
+                crate::SubgroupFunction::Gather { mode, argument }
+            }
+            _ => match conv::map_subgroup_operation(name) {
+                Some((op, collective)) => {
+                    lexer.expect(Token::Paren('('))?;
+                    let argument = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    lexer.expect(Token::Paren(')'))?;
+                    crate::SubgroupFunction::Collective {
+                        op,
+                        collective,
+                        argument,
+                    }
+                }
+                None => return Ok(None),
+            },
+        };
+
+        let (kind, width, size) = match fun {
+            crate::SubgroupFunction::Elect => (crate::ScalarKind::Bool, crate::BOOL_WIDTH, None),
+            crate::SubgroupFunction::Ballot { .. } => {
+                (crate::ScalarKind::Uint, 4, Some(crate::VectorSize::Quad))
+            }
+            crate::SubgroupFunction::Gather { argument, .. }
+            | crate::SubgroupFunction::Collective { argument, .. } => {
+                match *ctx.resolve_type(argument)? {
+                    crate::TypeInner::Scalar { kind, width } => (kind, width, None),
+                    crate::TypeInner::Vector { size, kind, width } => (kind, width, Some(size)),
+                    _ => return Err(Error::InvalidSubgroupOperand(argument)),
+                }
+            }
+        };
+        ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+        let result =
+            ctx.expressions
+                .append(crate::Expression::SubgroupResult { kind, width, size });
+        ctx.block.push(crate::Statement::Subgroup { fun, result });
+        // restart the emitter
+        ctx.emitter.start(ctx.expressions);
+        Ok(Some(result))
+    }
+
     fn parse_function_call_inner<'a>(
         &mut self,
         lexer: &mut Lexer<'a>,
@@ -616,6 +791,15 @@ impl Parser {
                 arg1,
                 arg2,
             }
+        } else if let Some(handle) = self.parse_atomic_helper(lexer, name, ctx.reborrow())? {
+            return Ok(Some(handle));
+        } else if let Some(handle) = self.parse_subgroup_helper(lexer, name, ctx.reborrow())? {
+            return Ok(Some(handle));
+        } else if name == "atomicLoad" {
+            lexer.expect(Token::Paren('('))?;
+            let pointer = self.parse_atomic_pointer(lexer, ctx.reborrow())?;
+            lexer.expect(Token::Paren(')'))?;
+            crate::Expression::Load { pointer }
         } else if name == "select" {
             lexer.expect(Token::Paren('('))?;
             let accept = self.parse_general_expression(lexer, ctx.reborrow())?;
@@ -899,7 +1083,6 @@ impl Parser {
         &mut self,
         first_token_span: TokenSpan<'a>,
         lexer: &mut Lexer<'a>,
-        register_name: Option<&'a str>,
         type_arena: &mut Arena<crate::Type>,
         const_arena: &mut Arena<crate::Constant>,
     ) -> Result<Handle<crate::Constant>, Error<'a>> {
@@ -914,7 +1097,19 @@ impl Parser {
                     ref width,
                 },
                 _,
-            ) => Self::get_constant_inner(*value, *ty, *width, first_token_span)?,
+            ) => {
+                let inner = Self::get_constant_inner(*value, *ty, *width, first_token_span)?;
+                if let crate::ConstantInner::Scalar {
+                    width: 2,
+                    value: crate::ScalarValue::Float(_),
+                } = inner
+                {
+                    if !self.f16_enabled {
+                        return Err(Error::DisabledExtension("f16"));
+                    }
+                }
+                inner
+            }
             (Token::Word(name), _) => {
                 // look for an existing constant first
                 for (handle, var) in const_arena.iter() {
@@ -951,19 +1146,11 @@ impl Parser {
             other => return Err(Error::Unexpected(other, "constant")),
         };
 
-        let handle = if let Some(name) = register_name {
-            const_arena.append(crate::Constant {
-                name: Some(name.to_string()),
-                specialization: None,
-                inner,
-            })
-        } else {
-            const_arena.fetch_or_append(crate::Constant {
-                name: None,
-                specialization: None,
-                inner,
-            })
-        };
+        let handle = const_arena.fetch_or_append(crate::Constant {
+            name: None,
+            specialization: None,
+            inner,
+        });
 
         self.scopes.pop();
         Ok(handle)
@@ -975,7 +1162,64 @@ impl Parser {
         type_arena: &mut Arena<crate::Type>,
         const_arena: &mut Arena<crate::Constant>,
     ) -> Result<Handle<crate::Constant>, Error<'a>> {
-        self.parse_const_expression_impl(lexer.next(), lexer, None, type_arena, const_arena)
+        self.parse_const_expression_impl(lexer.next(), lexer, type_arena, const_arena)
+    }
+
+    /// Parses the initializer of a module-scope constant,
+    /// which can be any expression that folds into a constant.
+    fn parse_const_initializer<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        name: &'a str,
+        module: &mut crate::Module,
+        lookup_global_expression: &FastHashMap<&'a str, crate::Expression>,
+    ) -> Result<Handle<crate::Constant>, Error<'a>> {
+        let mut lookup_ident = FastHashMap::default();
+        let mut expressions = Arena::new();
+        for (&name, expression) in lookup_global_expression.iter() {
+            let expr_handle = expressions.append(expression.clone());
+            lookup_ident.insert(name, expr_handle);
+        }
+        let mut typifier = Typifier::new();
+        let mut block = crate::Block::new();
+        let mut emitter = super::Emitter::default();
+        emitter.start(&expressions);
+        let constant_count = module.constants.len();
+        let root = self.parse_general_expression(
+            lexer,
+            ExpressionContext {
+                lookup_ident: &lookup_ident,
+                typifier: &mut typifier,
+                expressions: &mut expressions,
+                types: &mut module.types,
+                constants: &mut module.constants,
+                global_vars: &module.global_variables,
+                local_vars: &Arena::new(),
+                arguments: &[],
+                functions: &module.functions,
+                block: &mut block,
+                emitter: &mut emitter,
+            },
+        )?;
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut module.types,
+            expressions: &expressions,
+            constants: &mut module.constants,
+        };
+        let handle = evaluator.evaluate(root).map_err(Error::InvalidConstant)?;
+        // name the constant if it was just made, or make a copy otherwise
+        if handle.index() >= constant_count && module.constants[handle].name.is_none() {
+            module.constants.get_mut(handle).name = Some(name.to_string());
+            Ok(handle)
+        } else {
+            let inner = module.constants[handle].inner.clone();
+            Ok(module.constants.append(crate::Constant {
+                name: Some(name.to_string()),
+                specialization: None,
+                inner,
+            }))
+        }
     }
 
     fn parse_primary_expression<'a>(
@@ -984,9 +1228,11 @@ impl Parser {
         mut ctx: ExpressionContext<'a, '_, '_>,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
         self.scopes.push(Scope::PrimaryExpr);
+        let start = lexer.start_byte_offset();
+        let expression_count = ctx.expressions.len();
         let handle = match lexer.next() {
             (Token::Paren('('), _) => {
-                let expr = self.parse_general_expression(lexer, ctx)?;
+                let expr = self.parse_general_expression(lexer, ctx.reborrow())?;
                 lexer.expect(Token::Paren(')'))?;
                 expr
             }
@@ -994,7 +1240,7 @@ impl Parser {
             | token @ (Token::Word("false"), _)
             | token @ (Token::Number { .. }, _) => {
                 let const_handle =
-                    self.parse_const_expression_impl(token, lexer, None, ctx.types, ctx.constants)?;
+                    self.parse_const_expression_impl(token, lexer, ctx.types, ctx.constants)?;
                 // pause the emitter while generating this expression, since it's pre-emitted
                 ctx.block.extend(ctx.emitter.finish(ctx.expressions));
                 let expr = ctx
@@ -1056,6 +1302,8 @@ impl Parser {
             }
             other => return Err(Error::Unexpected(other, "primary expression")),
         };
+        ctx.expressions
+            .fill_spans_from(expression_count, lexer.span_from(start));
         self.scopes.pop();
         Ok(handle)
     }
@@ -1204,6 +1452,8 @@ impl Parser {
         mut ctx: ExpressionContext<'a, '_, '_>,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
         self.scopes.push(Scope::SingularExpr);
+        let start = lexer.start_byte_offset();
+        let expression_count = ctx.expressions.len();
         //TODO: refactor this to avoid backing up
         let backup = lexer.clone();
         let handle = match lexer.next().0 {
@@ -1236,7 +1486,9 @@ impl Parser {
             }
         };
 
-        let post_handle = self.parse_postfix(lexer, ctx, handle, true)?;
+        let post_handle = self.parse_postfix(lexer, ctx.reborrow(), handle, true)?;
+        ctx.expressions
+            .fill_spans_from(expression_count, lexer.span_from(start));
         self.scopes.pop();
         Ok(post_handle)
     }
@@ -1396,6 +1648,50 @@ impl Parser {
         Ok((name, ty, access))
     }
 
+    /// Parse the optional `[[location(..)]]`, `[[builtin(..)]]` and `[[interpolate(..)]]`
+    /// decorations of an entry point input or output.
+    fn parse_varying_decorations<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+    ) -> Result<(Option<crate::Binding>, Option<crate::Interpolation>), Error<'a>> {
+        let mut binding = None;
+        let mut interpolation = None;
+        if lexer.skip(Token::DoubleParen('[')) {
+            self.scopes.push(Scope::Decoration);
+            loop {
+                match lexer.next_ident()? {
+                    "location" => {
+                        lexer.expect(Token::Paren('('))?;
+                        let loc = lexer.next_uint_literal()?;
+                        lexer.expect(Token::Paren(')'))?;
+                        binding = Some(crate::Binding::Location(loc));
+                    }
+                    "builtin" => {
+                        lexer.expect(Token::Paren('('))?;
+                        let builtin = conv::map_built_in(lexer.next_ident()?)?;
+                        lexer.expect(Token::Paren(')'))?;
+                        binding = Some(crate::Binding::BuiltIn(builtin));
+                    }
+                    "interpolate" => {
+                        lexer.expect(Token::Paren('('))?;
+                        interpolation = Some(conv::map_interpolation(lexer.next_ident()?)?);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    word => return Err(Error::UnknownDecoration(word)),
+                }
+                match lexer.next() {
+                    (Token::DoubleParen(']'), _) => {
+                        break;
+                    }
+                    (Token::Separator(','), _) => {}
+                    other => return Err(Error::Unexpected(other, "decoration separator")),
+                }
+            }
+            self.scopes.pop();
+        }
+        Ok((binding, interpolation))
+    }
+
     fn parse_variable_decl<'a>(
         &mut self,
         lexer: &mut Lexer<'a>,
@@ -1440,6 +1736,8 @@ impl Parser {
         lexer.expect(Token::Paren('{'))?;
         loop {
             let mut span = 0;
+            let mut binding = None;
+            let mut interpolation = None;
             if lexer.skip(Token::DoubleParen('[')) {
                 self.scopes.push(Scope::Decoration);
                 let mut ready = true;
@@ -1464,6 +1762,26 @@ impl Parser {
                             lexer.expect(Token::Paren(')'))?;
                             ready = false;
                         }
+                        (Token::Word("location"), _) if ready => {
+                            lexer.expect(Token::Paren('('))?;
+                            let loc = lexer.next_uint_literal()?;
+                            lexer.expect(Token::Paren(')'))?;
+                            binding = Some(crate::Binding::Location(loc));
+                            ready = false;
+                        }
+                        (Token::Word("builtin"), _) if ready => {
+                            lexer.expect(Token::Paren('('))?;
+                            let builtin = conv::map_built_in(lexer.next_ident()?)?;
+                            lexer.expect(Token::Paren(')'))?;
+                            binding = Some(crate::Binding::BuiltIn(builtin));
+                            ready = false;
+                        }
+                        (Token::Word("interpolate"), _) if ready => {
+                            lexer.expect(Token::Paren('('))?;
+                            interpolation = Some(conv::map_interpolation(lexer.next_ident()?)?);
+                            lexer.expect(Token::Paren(')'))?;
+                            ready = false;
+                        }
                         other => return Err(Error::Unexpected(other, "decoration separator")),
                     }
                 }
@@ -1481,6 +1799,8 @@ impl Parser {
                 name: Some(name.to_owned()),
                 span: NonZeroU32::new(span),
                 ty,
+                binding,
+                interpolation,
             });
         }
     }
@@ -1493,7 +1813,11 @@ impl Parser {
         type_arena: &mut Arena<crate::Type>,
         const_arena: &mut Arena<crate::Constant>,
     ) -> Result<crate::TypeInner, Error<'a>> {
-        Ok(match word {
+        let inner = match word {
+            "f16" => crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+            },
             "f32" => crate::TypeInner::Scalar {
                 kind: crate::ScalarKind::Float,
                 width: 4,
@@ -1606,6 +1930,10 @@ impl Parser {
                     width,
                 }
             }
+            "atomic" => {
+                let (kind, width) = lexer.next_scalar_generic()?;
+                crate::TypeInner::Atomic { kind, width }
+            }
             "ptr" => {
                 lexer.expect_generic_paren('<')?;
                 let class = conv::map_storage_class(lexer.next_ident()?)?;
@@ -1767,7 +2095,24 @@ impl Parser {
                 }
             }
             _ => return Err(Error::UnknownType(word)),
-        })
+        };
+        match inner {
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+            }
+            | crate::TypeInner::Vector {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+                ..
+            }
+            | crate::TypeInner::Matrix { width: 2, .. }
+                if !self.f16_enabled =>
+            {
+                Err(Error::DisabledExtension("f16"))
+            }
+            _ => Ok(inner),
+        }
     }
 
     /// Parse type declaration of a given name and decoration.
@@ -1863,7 +2208,30 @@ impl Parser {
                     value,
                 }
             }
+            None if ident == "atomicStore" => {
+                lexer.expect(Token::Paren('('))?;
+                let pointer = self.parse_atomic_pointer(lexer, context.reborrow())?;
+                lexer.expect(Token::Separator(','))?;
+                let value = self.parse_general_expression(lexer, context.reborrow())?;
+                lexer.expect(Token::Paren(')'))?;
+                crate::Statement::Store { pointer, value }
+            }
+            None if conv::map_barrier(ident).is_some() => {
+                lexer.expect(Token::Paren('('))?;
+                lexer.expect(Token::Paren(')'))?;
+                conv::map_barrier(ident).unwrap()
+            }
             None => {
+                // atomic operations produce their own statements
+                if self
+                    .parse_atomic_helper(lexer, ident, context.reborrow())?
+                    .is_some()
+                {
+                    context
+                        .block
+                        .extend(context.emitter.finish(context.expressions));
+                    return Ok(());
+                }
                 let (function, arguments) = self
                     .parse_local_function_call(lexer, ident, context.reborrow())?
                     .ok_or(Error::UnknownLocalFunction(ident))?;
@@ -1888,11 +2256,15 @@ impl Parser {
         block: &'out mut crate::Block,
         is_uniform_control_flow: bool,
     ) -> Result<(), Error<'a>> {
+        let start = lexer.start_byte_offset();
+        let statement_count = block.len();
+        let expression_count = context.expressions.len();
+        let variable_count = context.variables.len();
         let word = match lexer.next() {
             (Token::Separator(';'), _) => return Ok(()),
             (Token::Paren('{'), _) => {
                 self.scopes.push(Scope::Block);
-                let mut statements = Vec::new();
+                let mut statements = crate::Block::new();
                 while !lexer.skip(Token::Paren('}')) {
                     self.parse_statement(
                         lexer,
@@ -1902,7 +2274,7 @@ impl Parser {
                     )?;
                 }
                 self.scopes.pop();
-                block.push(crate::Statement::Block(statements));
+                block.push_with_span(crate::Statement::Block(statements), lexer.span_from(start));
                 return Ok(());
             }
             (Token::Word(word), _) => word,
@@ -1997,7 +2369,11 @@ impl Parser {
 
                 let accept = self.parse_block(lexer, context.reborrow(), false)?;
                 let mut elsif_stack = Vec::new();
-                while lexer.skip(Token::Word("elseif")) {
+                loop {
+                    let other_start = lexer.start_byte_offset();
+                    if !lexer.skip(Token::Word("elseif")) {
+                        break;
+                    }
                     let mut sub_emitter = super::Emitter::default();
                     sub_emitter.start(context.expressions);
                     lexer.expect(Token::Paren('('))?;
@@ -2008,24 +2384,30 @@ impl Parser {
                     lexer.expect(Token::Paren(')'))?;
                     let other_emit = sub_emitter.finish(context.expressions);
                     let other_block = self.parse_block(lexer, context.reborrow(), false)?;
-                    elsif_stack.push((other_condition, other_emit, other_block));
+                    let other_span = lexer.span_from(other_start);
+                    elsif_stack.push((other_condition, other_emit, other_block, other_span));
                 }
                 let mut reject = if lexer.skip(Token::Word("else")) {
                     self.parse_block(lexer, context.reborrow(), false)?
                 } else {
-                    Vec::new()
+                    crate::Block::new()
                 };
                 // reverse-fold the else-if blocks
                 //Note: we may consider uplifting this to the IR
-                for (other_cond, other_emit, other_block) in elsif_stack.drain(..).rev() {
-                    reject = other_emit
-                        .into_iter()
-                        .chain(iter::once(crate::Statement::If {
+                for (other_cond, other_emit, other_block, other_span) in elsif_stack.drain(..).rev()
+                {
+                    let mut sub_block = crate::Block::new();
+                    sub_block.extend(other_emit);
+                    sub_block.fill_spans_from(0, other_span);
+                    sub_block.push_with_span(
+                        crate::Statement::If {
                             condition: other_cond,
                             accept: other_block,
                             reject,
-                        }))
-                        .collect();
+                        },
+                        other_span,
+                    );
+                    reject = sub_block;
                 }
 
                 block.push(crate::Statement::If {
@@ -2043,7 +2425,7 @@ impl Parser {
                 block.extend(emitter.finish(context.expressions));
                 lexer.expect(Token::Paren('{'))?;
                 let mut cases = Vec::new();
-                let mut default = Vec::new();
+                let mut default = crate::Block::new();
 
                 loop {
                     // cases + default
@@ -2056,7 +2438,7 @@ impl Parser {
                                     (Token::Separator(','), _) => {
                                         cases.push(crate::SwitchCase {
                                             value,
-                                            body: Vec::new(),
+                                            body: crate::Block::new(),
                                             fall_through: true,
                                         });
                                     }
@@ -2067,7 +2449,7 @@ impl Parser {
                                 }
                             };
 
-                            let mut body = Vec::new();
+                            let mut body = crate::Block::new();
                             lexer.expect(Token::Paren('{'))?;
                             let fall_through = loop {
                                 // default statements
@@ -2104,8 +2486,8 @@ impl Parser {
                 });
             }
             "loop" => {
-                let mut body = Vec::new();
-                let mut continuing = Vec::new();
+                let mut body = crate::Block::new();
+                let mut continuing = crate::Block::new();
                 lexer.expect(Token::Paren('{'))?;
 
                 loop {
@@ -2145,7 +2527,7 @@ impl Parser {
                     }
                 };
 
-                let mut body = Vec::new();
+                let mut body = crate::Block::new();
                 if !lexer.skip(Token::Separator(';')) {
                     emitter.start(context.expressions);
                     let condition = self.parse_general_expression(
@@ -2154,14 +2536,17 @@ impl Parser {
                     )?;
                     lexer.expect(Token::Separator(';'))?;
                     body.extend(emitter.finish(context.expressions));
+                    let mut reject = crate::Block::new();
+                    reject.push(crate::Statement::Break);
                     body.push(crate::Statement::If {
                         condition,
-                        accept: Vec::new(),
-                        reject: vec![crate::Statement::Break],
+                        accept: crate::Block::new(),
+                        reject,
                     });
+                    body.fill_spans_from(0, lexer.span_from(start));
                 };
 
-                let mut continuing = Vec::new();
+                let mut continuing = crate::Block::new();
                 if let Token::Word(ident) = lexer.peek().0 {
                     // manually parse the next statement here instead of calling parse_statement
                     // because the statement is not terminated with a semicolon
@@ -2227,6 +2612,10 @@ impl Parser {
                 lexer.expect(Token::Separator(';'))?;
             }
         }
+        let span = lexer.span_from(start);
+        block.fill_spans_from(statement_count, span);
+        context.expressions.fill_spans_from(expression_count, span);
+        context.variables.fill_spans_from(variable_count, span);
         self.scopes.pop();
         Ok(())
     }
@@ -2236,10 +2625,10 @@ impl Parser {
         lexer: &mut Lexer<'a>,
         mut context: StatementContext<'a, '_, '_>,
         is_uniform_control_flow: bool,
-    ) -> Result<Vec<crate::Statement>, Error<'a>> {
+    ) -> Result<crate::Block, Error<'a>> {
         self.scopes.push(Scope::Block);
         lexer.expect(Token::Paren('{'))?;
-        let mut block = Vec::new();
+        let mut block = crate::Block::new();
         while !lexer.skip(Token::Paren('}')) {
             self.parse_statement(
                 lexer,
@@ -2275,6 +2664,7 @@ impl Parser {
             if !arguments.is_empty() {
                 lexer.expect(Token::Separator(','))?;
             }
+            let (binding, interpolation) = self.parse_varying_decorations(lexer)?;
             let (param_name, param_type, _access) =
                 self.parse_variable_ident_decl(lexer, &mut module.types, &mut module.constants)?;
             let param_index = arguments.len() as u32;
@@ -2284,13 +2674,20 @@ impl Parser {
             arguments.push(crate::FunctionArgument {
                 name: Some(param_name.to_string()),
                 ty: param_type,
+                binding,
+                interpolation,
             });
         }
         // read return type
-        let return_type = if lexer.skip(Token::Arrow) && !lexer.skip(Token::Word("void")) {
-            let (handle, _access) =
+        let result = if lexer.skip(Token::Arrow) && !lexer.skip(Token::Word("void")) {
+            let (binding, interpolation) = self.parse_varying_decorations(lexer)?;
+            let (ty, _access) =
                 self.parse_type_decl(lexer, None, &mut module.types, &mut module.constants)?;
-            Some(handle)
+            Some(crate::FunctionResult {
+                ty,
+                binding,
+                interpolation,
+            })
         } else {
             None
         };
@@ -2298,10 +2695,10 @@ impl Parser {
         let mut fun = crate::Function {
             name: Some(fun_name.to_string()),
             arguments,
-            return_type,
+            result,
             local_variables: Arena::new(),
             expressions,
-            body: Vec::new(),
+            body: crate::Block::new(),
         };
 
         // read body
@@ -2343,6 +2740,8 @@ impl Parser {
         let mut is_block = false;
         let mut workgroup_size = [0u32; 3];
         let mut early_depth_test = None;
+        let mut constant_id = None;
+        let start = lexer.start_byte_offset();
 
         if lexer.skip(Token::DoubleParen('[')) {
             let (mut bind_index, mut bind_group) = (None, None);
@@ -2369,6 +2768,11 @@ impl Parser {
                     "block" => {
                         is_block = true;
                     }
+                    "constant_id" => {
+                        lexer.expect(Token::Paren('('))?;
+                        constant_id = Some(lexer.next_uint_literal()?);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
                     "group" => {
                         lexer.expect(Token::Paren('('))?;
                         bind_group = Some(lexer.next_uint_literal()?);
@@ -2437,6 +2841,13 @@ impl Parser {
         // read items
         match lexer.next() {
             (Token::Separator(';'), _) => {}
+            (Token::Word("enable"), _) => {
+                match lexer.next_ident()? {
+                    "f16" => self.f16_enabled = true,
+                    other => return Err(Error::UnknownExtension(other)),
+                }
+                lexer.expect(Token::Separator(';'))?;
+            }
             (Token::Word("struct"), _) => {
                 let name = lexer.next_ident()?;
                 let members =
@@ -2450,6 +2861,7 @@ impl Parser {
                 });
                 self.lookup_type.insert(name.to_owned(), ty);
                 lexer.expect(Token::Separator(';'))?;
+                module.types.set_span(ty, lexer.span_from(start));
             }
             (Token::Word("type"), _) => {
                 let name = lexer.next_ident()?;
@@ -2470,16 +2882,18 @@ impl Parser {
                     &mut module.constants,
                 )?;
                 lexer.expect(Token::Operation('='))?;
-                let first_token_span = lexer.next();
-                let const_handle = self.parse_const_expression_impl(
-                    first_token_span,
-                    lexer,
-                    Some(name),
-                    &mut module.types,
-                    &mut module.constants,
-                )?;
+                let const_handle =
+                    self.parse_const_initializer(lexer, name, module, lookup_global_expression)?;
                 //TODO: check `ty` against `const_handle`.
                 lexer.expect(Token::Separator(';'))?;
+                if constant_id.is_some() {
+                    module.constants.get_mut(const_handle).specialization = constant_id;
+                }
+                if !module.constants.get_span(const_handle).is_defined() {
+                    module
+                        .constants
+                        .set_span(const_handle, lexer.span_from(start));
+                }
                 lookup_global_expression.insert(name, crate::Expression::Constant(const_handle));
             }
             (Token::Word("var"), _) => {
@@ -2489,7 +2903,9 @@ impl Parser {
                     Some(c) => c,
                     None => match binding {
                         Some(crate::Binding::BuiltIn(builtin)) => match builtin {
-                            crate::BuiltIn::GlobalInvocationId => crate::StorageClass::Input,
+                            crate::BuiltIn::GlobalInvocationId
+                            | crate::BuiltIn::SubgroupSize
+                            | crate::BuiltIn::SubgroupInvocationId => crate::StorageClass::Input,
                             crate::BuiltIn::Position => crate::StorageClass::Output,
                             _ => return Err(Error::UnimplementedBuiltin(builtin)),
                         },
@@ -2516,15 +2932,18 @@ impl Parser {
                         },
                     },
                 };
-                let var_handle = module.global_variables.append(crate::GlobalVariable {
-                    name: Some(pvar.name.to_owned()),
-                    class,
-                    binding: binding.take(),
-                    ty: pvar.ty,
-                    init: pvar.init,
-                    interpolation,
-                    storage_access: pvar.access,
-                });
+                let var_handle = module.global_variables.append_with_span(
+                    crate::GlobalVariable {
+                        name: Some(pvar.name.to_owned()),
+                        class,
+                        binding: binding.take(),
+                        ty: pvar.ty,
+                        init: pvar.init,
+                        interpolation,
+                        storage_access: pvar.access,
+                    },
+                    lexer.span_from(start),
+                );
                 lookup_global_expression
                     .insert(pvar.name, crate::Expression::GlobalVariable(var_handle));
             }
@@ -2537,10 +2956,14 @@ impl Parser {
                         stage,
                         early_depth_test,
                         workgroup_size,
+                        geometry: None,
+                        tessellation: None,
                         function,
                     }),
                     None => {
-                        module.functions.append(function);
+                        module
+                            .functions
+                            .append_with_span(function, lexer.span_from(start));
                     }
                 }
             }
@@ -2558,6 +2981,7 @@ impl Parser {
     pub fn parse<'a>(&mut self, source: &'a str) -> Result<crate::Module, ParseError<'a>> {
         self.scopes.clear();
         self.lookup_type.clear();
+        self.f16_enabled = false;
 
         let mut module = crate::Module::default();
         let mut lexer = Lexer::new(source);
@@ -48,6 +48,32 @@ fn parse_type_cast() {
     .unwrap();
 }
 
+#[test]
+fn parse_const_expression() {
+    let module = parse_str(
+        "
+        const n : u32 = 2u * 3u + 1u;
+        const v : vec2<f32> = vec2<f32>(f32(n), -1.0) * 2.0;
+        var<private> a: array<f32, n>;
+    ",
+    )
+    .unwrap();
+    let n = module
+        .constants
+        .iter()
+        .find(|(_, c)| c.name.as_deref() == Some("n"));
+    assert_eq!(
+        n.unwrap().1.inner,
+        crate::ConstantInner::Scalar {
+            width: 4,
+            value: crate::ScalarValue::Uint(7),
+        }
+    );
+    assert!(parse_str("const a : i32 = 2147483647 + 1;").is_err());
+    assert!(parse_str("const a : i32 = 1 / 0;").is_err());
+    assert!(parse_str("var<private> x: f32; const a : f32 = x;").is_err());
+}
+
 #[test]
 fn parse_struct() {
     parse_str(
@@ -240,3 +266,73 @@ fn parse_expressions() {
         const z: bool = !(0.0 == 1.0);
     }").unwrap();
 }
+
+#[test]
+fn parse_half_float() {
+    parse_str(
+        "
+        enable f16;
+        const a: f16 = 1.5h;
+        fn foo() {
+            var v: vec2<f16> = vec2<f16>(a, 2.0h);
+            var m: mat2x2<f16>;
+        }
+    ",
+    )
+    .unwrap();
+    assert!(parse_str("const a: f16 = 1.0h;").is_err());
+    assert!(parse_str("enable f64;").is_err());
+}
+
+#[test]
+fn parse_spans() {
+    let source = "
+        var<private> v: f32;
+        fn foo() -> i32 {
+            var x: i32 = 1;
+            if (x) {
+                x = x + 2;
+            }
+            return x;
+        }
+    ";
+    let module = parse_str(source).unwrap();
+    let text = |span: crate::Span| &source[span.to_range().unwrap()];
+
+    let (var_handle, _) = module.global_variables.iter().next().unwrap();
+    assert_eq!(
+        text(module.global_variables.get_span(var_handle)),
+        "var<private> v: f32;"
+    );
+
+    let (fun_handle, fun) = module.functions.iter().next().unwrap();
+    assert!(text(module.functions.get_span(fun_handle)).starts_with("fn foo()"));
+    let spans = fun
+        .body
+        .span_iter()
+        .filter(|&(statement, _)| !matches!(*statement, crate::Statement::Emit(_)))
+        .map(|(_, span)| text(span))
+        .collect::<Vec<_>>();
+    assert!(spans[0].starts_with("if (x) {"));
+    assert_eq!(spans[1], "return x;");
+
+    let (local_handle, _) = fun.local_variables.iter().next().unwrap();
+    assert_eq!(
+        text(fun.local_variables.get_span(local_handle)),
+        "var x: i32 = 1;"
+    );
+
+    let binary = fun
+        .expressions
+        .iter()
+        .find(|&(_, expr)| matches!(*expr, crate::Expression::Binary { .. }))
+        .unwrap()
+        .0;
+    assert_eq!(text(fun.expressions.get_span(binary)), "x + 2");
+
+    let location = crate::proc::Validator::new()
+        .validate(&module)
+        .err()
+        .map(|error| error.span().location(source));
+    assert_eq!(location, Some((5, 13)));
+}
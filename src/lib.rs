@@ -15,7 +15,7 @@ emitted in order to take effect. This happens in one of the following ways:
   2. Local and global variables are implicitly emitted. However, in order to use parts
       of them in right-hand-side expressions, the `Expression::Load` must be explicitly emitted,
       with an exception of `StorageClass::Handle` global variables.
-  3. Result of `Statement::Call` is automatically emitted.
+  3. Result of `Statement::Call`, `Statement::Atomic` and `Statement::Subgroup` is automatically emitted.
   4. `Statement::Emit` range is explicitly emitted.
 
 !*/
@@ -37,10 +37,19 @@ emitted in order to take effect. This happens in one of the following ways:
 
 mod arena;
 pub mod back;
+mod block;
 pub mod front;
 pub mod proc;
+mod span;
+// The grammar lives with the assembler, but the disassembler needs it too,
+// and `front::spv` is only built with the parser.
+#[cfg(any(feature = "spv-in", feature = "spv-out"))]
+#[path = "front/spv/grammar.rs"]
+mod spv_grammar;
 
 pub use crate::arena::{Arena, Handle, Range};
+pub use crate::block::Block;
+pub use crate::span::Span;
 
 use std::{
     collections::{HashMap, HashSet},
@@ -112,6 +121,133 @@ pub enum ShaderStage {
     Vertex,
     Fragment,
     Compute,
+    Geometry,
+    TessellationControl,
+    TessellationEvaluation,
+    RayGeneration,
+    Intersection,
+    AnyHit,
+    ClosestHit,
+    Miss,
+    Callable,
+}
+
+/// Kind of the primitives a geometry stage is invoked on.
+///
+/// SPIR-V: ExecutionMode InputPoints/InputLines/InputLinesAdjacency/Triangles/InputTrianglesAdjacency
+/// GLSL: layout(points/lines/lines_adjacency/triangles/triangles_adjacency) in;
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[allow(missing_docs)] // The names are self evident
+pub enum GeometryInput {
+    Points,
+    Lines,
+    LinesAdjacency,
+    Triangles,
+    TrianglesAdjacency,
+}
+
+/// Kind of the primitives a geometry stage outputs.
+///
+/// SPIR-V: ExecutionMode OutputPoints/OutputLineStrip/OutputTriangleStrip
+/// GLSL: layout(points/line_strip/triangle_strip) out;
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[allow(missing_docs)] // The names are self evident
+pub enum GeometryOutput {
+    Points,
+    LineStrip,
+    TriangleStrip,
+}
+
+/// Primitive configuration of a geometry stage.
+///
+/// The per-vertex inputs of a geometry stage are arrays,
+/// with an element for each vertex of the input primitive.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct GeometryInfo {
+    /// Kind of the input primitives.
+    pub input: GeometryInput,
+    /// Kind of the output primitives.
+    pub output: GeometryOutput,
+    /// Maximum number of vertices emitted by a single invocation.
+    ///
+    /// SPIR-V: ExecutionMode OutputVertices
+    /// GLSL: layout(max_vertices = N) out;
+    pub max_vertices: u32,
+}
+
+/// Kind of the abstract patch that is subdivided by the tessellator.
+///
+/// SPIR-V: ExecutionMode Triangles/Quads/Isolines
+/// GLSL: layout(triangles/quads/isolines) in;
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[allow(missing_docs)] // The names are self evident
+pub enum TessellationPrimitive {
+    Triangles,
+    Quads,
+    Isolines,
+}
+
+/// Spacing of the vertices generated by the tessellator.
+///
+/// SPIR-V: ExecutionMode SpacingEqual/SpacingFractionalEven/SpacingFractionalOdd
+/// GLSL: layout(equal_spacing/fractional_even_spacing/fractional_odd_spacing) in;
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[allow(missing_docs)] // The names are self evident
+pub enum TessellationSpacing {
+    Equal,
+    FractionalEven,
+    FractionalOdd,
+}
+
+/// Winding of the triangles generated by the tessellator.
+///
+/// SPIR-V: ExecutionMode VertexOrderCw/VertexOrderCcw
+/// GLSL: layout(cw/ccw) in;
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[allow(missing_docs)] // The names are self evident
+pub enum VertexOrder {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Configuration of a tessellation stage.
+///
+/// The per-vertex inputs of both tessellation stages, as well as the per-vertex
+/// outputs of the control stage, are arrays with an element for each vertex of the patch.
+/// Patch-constant data is passed in varyings with [`Interpolation::Patch`].
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum TessellationInfo {
+    /// Tessellation control stage.
+    Control {
+        /// Number of vertices in the output patch.
+        ///
+        /// SPIR-V: ExecutionMode OutputVertices
+        /// GLSL: layout(vertices = N) out;
+        output_vertices: u32,
+    },
+    /// Tessellation evaluation stage.
+    Evaluation {
+        /// Kind of the tessellated patch.
+        primitive: TessellationPrimitive,
+        /// Spacing of the generated vertices.
+        spacing: TessellationSpacing,
+        /// Winding of the generated triangles.
+        vertex_order: VertexOrder,
+    },
 }
 
 /// Class of storage for variables.
@@ -138,6 +274,12 @@ pub enum StorageClass {
     Handle,
     /// Push constants.
     PushConstant,
+    /// Payload of the rays traced by this invocation, mutable.
+    RayPayload,
+    /// Payload of the ray that invoked this hit or miss stage, mutable.
+    IncomingRayPayload,
+    /// Attributes of the intersection reported by an intersection stage.
+    HitAttribute,
 }
 
 /// Built-in inputs and outputs.
@@ -166,6 +308,29 @@ pub enum BuiltIn {
     LocalInvocationIndex,
     WorkGroupId,
     WorkGroupSize,
+    // subgroup
+    SubgroupSize,
+    SubgroupInvocationId,
+    // geometry and tessellation
+    InvocationId,
+    PatchVertices,
+    TessCoord,
+    TessLevelInner,
+    TessLevelOuter,
+    // ray tracing
+    LaunchId,
+    LaunchSize,
+    WorldRayOrigin,
+    WorldRayDirection,
+    ObjectRayOrigin,
+    ObjectRayDirection,
+    RayTmin,
+    RayTmax,
+    InstanceCustomIndex,
+    ObjectToWorld,
+    WorldToObject,
+    HitKind,
+    IncomingRayFlags,
 }
 
 /// Number of bytes.
@@ -246,6 +411,10 @@ pub struct StructMember {
     pub name: Option<String>,
     pub span: Option<NonZeroU32>,
     pub ty: Handle<Type>,
+    /// For entry point inputs and outputs, how this member is bound.
+    pub binding: Option<Binding>,
+    /// For entry point inputs and outputs, the interpolation qualifier, if any.
+    pub interpolation: Option<Interpolation>,
 }
 
 /// The number of dimensions an image has.
@@ -345,7 +514,7 @@ pub enum ImageClass {
 }
 
 /// A data type declared in the module.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Type {
@@ -356,7 +525,7 @@ pub struct Type {
 }
 
 /// Enum with additional information, depending on the kind of type.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub enum TypeInner {
@@ -374,6 +543,11 @@ pub enum TypeInner {
         rows: VectorSize,
         width: Bytes,
     },
+    /// Atomic scalar.
+    ///
+    /// Can only be accessed through the `Statement::Atomic`, as well as
+    /// `Expression::Load` and `Statement::Store` on its pointer.
+    Atomic { kind: ScalarKind, width: Bytes },
     /// Pointer to another type.
     Pointer {
         base: Handle<Type>,
@@ -405,10 +579,12 @@ pub enum TypeInner {
     },
     /// Can be used to sample values from images.
     Sampler { comparison: bool },
+    /// Opaque structure of geometry, that rays can be traced against.
+    AccelerationStructure,
 }
 
 /// Constant value.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Constant {
@@ -471,8 +647,6 @@ pub struct GlobalVariable {
     pub ty: Handle<Type>,
     /// Initial value for this variable.
     pub init: Option<Handle<Constant>>,
-    //TODO: require fragment input interpolation once the entry point I/O
-    // is refactored.
     /// The interpolation qualifier, if any.
     /// If the this `GlobalVariable` is a vertex output
     /// or fragment input, `None` corresponds to the
@@ -647,6 +821,130 @@ pub enum ImageQuery {
     NumSamples,
 }
 
+/// Operation on an atomic value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum AtomicFunction {
+    Add,
+    Subtract,
+    And,
+    ExclusiveOr,
+    InclusiveOr,
+    Min,
+    Max,
+    /// Replace the value, optionally only if it's equal to `compare`.
+    Exchange {
+        compare: Option<Handle<Expression>>,
+    },
+}
+
+/// Set of invocations participating in a synchronization operation.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum Scope {
+    /// Invocations executed together as a subgroup (a.k.a. warp or SIMD group).
+    SubGroup,
+    /// Invocations of the same compute workgroup.
+    WorkGroup,
+    /// All invocations on the device.
+    Device,
+}
+
+bitflags::bitflags! {
+    /// Memory accesses ordered by a barrier.
+    #[cfg_attr(feature = "serialize", derive(Serialize))]
+    #[cfg_attr(feature = "deserialize", derive(Deserialize))]
+    #[derive(Default)]
+    pub struct Barrier: u32 {
+        /// Accesses to the `StorageClass::Storage` and `StorageClass::Uniform` buffers.
+        const STORAGE = 0x1;
+        /// Accesses to the `StorageClass::WorkGroup` variables.
+        const WORK_GROUP = 0x2;
+        /// Accesses to the storage images.
+        const IMAGE = 0x4;
+    }
+}
+
+/// Operation combining the values of the invocations in a subgroup.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum SubgroupOperation {
+    /// Logical "and" of the boolean values, only as a reduction.
+    All,
+    /// Logical "or" of the boolean values, only as a reduction.
+    Any,
+    Add,
+    Multiply,
+    Min,
+    Max,
+    And,
+    InclusiveOr,
+    ExclusiveOr,
+}
+
+/// The way a `SubgroupOperation` is applied across the invocations.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum CollectiveOperation {
+    /// All the active invocations get the combination of all the values.
+    Reduce,
+    /// Each invocation gets the combination of the values of the invocations
+    /// with lower or equal subgroup invocation ID.
+    InclusiveScan,
+    /// Each invocation gets the combination of the values of the invocations
+    /// with lower subgroup invocation ID.
+    ExclusiveScan,
+}
+
+/// The invocation to read the value from in `SubgroupFunction::Gather`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum GatherMode {
+    /// The active invocation with the lowest subgroup invocation ID.
+    BroadcastFirst,
+    /// The invocation with the given subgroup invocation ID,
+    /// which has to be uniform across the subgroup.
+    Broadcast(Handle<Expression>),
+    /// The invocation with the given subgroup invocation ID.
+    Shuffle(Handle<Expression>),
+    /// The invocation with the current ID plus the given delta.
+    ShuffleDown(Handle<Expression>),
+    /// The invocation with the current ID minus the given delta.
+    ShuffleUp(Handle<Expression>),
+    /// The invocation with the current ID xor-ed with the given mask.
+    ShuffleXor(Handle<Expression>),
+}
+
+/// Operation performed collectively by the invocations of a subgroup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum SubgroupFunction {
+    /// Returns `true` for exactly one active invocation.
+    Elect,
+    /// Returns a `vec4<u32>` bit mask of the active invocations
+    /// for which the `predicate` is true (or all of them, if there is none).
+    Ballot {
+        predicate: Option<Handle<Expression>>,
+    },
+    /// Returns the `argument` value of another invocation.
+    Gather {
+        mode: GatherMode,
+        argument: Handle<Expression>,
+    },
+    /// Combines the `argument` values of the active invocations.
+    Collective {
+        op: SubgroupOperation,
+        collective: CollectiveOperation,
+        argument: Handle<Expression>,
+    },
+}
+
 /// An expression that can be evaluated to obtain a value.
 ///
 /// This is a Single Static Assignment (SSA) scheme similar to SPIR-V.
@@ -754,11 +1052,30 @@ pub enum Expression {
     Call(Handle<Function>),
     /// Get the length of an array.
     ArrayLength(Handle<Expression>),
+    /// Result of an atomic operation.
+    ///
+    /// This is the original value in memory, before the operation.
+    /// If `comparison` is true, the result is a 2-component vector,
+    /// where the second component is 1 if the exchange took place, and 0 otherwise.
+    AtomicResult {
+        kind: ScalarKind,
+        width: Bytes,
+        comparison: bool,
+    },
+    /// Result of a subgroup operation.
+    ///
+    /// This is a scalar if `size` is `None`, and a vector otherwise.
+    SubgroupResult {
+        kind: ScalarKind,
+        width: Bytes,
+        size: Option<VectorSize>,
+    },
+    /// Result of reporting an intersection.
+    ///
+    /// This is a boolean, which is true if the intersection was accepted.
+    ReportIntersectionResult,
 }
 
-/// A code block is just a vector of statements.
-pub type Block = Vec<Statement>;
-
 /// A case for a switch statement.
 // Clone is used only for error reporting and is not intended for end users
 #[derive(Clone, Debug)]
@@ -837,10 +1154,91 @@ pub enum Statement {
         arguments: Vec<Handle<Expression>>,
         result: Option<Handle<Expression>>,
     },
+    /// Atomically performs an operation on a value in memory.
+    ///
+    /// The `pointer` has to refer to a `TypeInner::Atomic` value.
+    /// The `result` expression has to be `Expression::AtomicResult`,
+    /// and this statement serves as a barrier for any operations on it.
+    Atomic {
+        pointer: Handle<Expression>,
+        fun: AtomicFunction,
+        value: Handle<Expression>,
+        result: Handle<Expression>,
+    },
+    /// Synchronizes invocations and/or memory accesses.
+    ///
+    /// If `execution` is set, this is a control barrier: all the invocations
+    /// within that scope wait for each other, so it has to be executed
+    /// in uniform control flow. Otherwise, this is only a memory barrier.
+    ///
+    /// The `memory` accesses done by the invocations within `memory_scope`
+    /// before the barrier are made visible to them after it.
+    Barrier {
+        execution: Option<Scope>,
+        memory_scope: Scope,
+        memory: Barrier,
+    },
+    /// Performs an operation across the invocations of a subgroup.
+    ///
+    /// Has to be executed in uniform control flow.
+    /// The `result` expression has to be `Expression::SubgroupResult`,
+    /// and this statement serves as a barrier for any operations on it.
+    Subgroup {
+        fun: SubgroupFunction,
+        result: Handle<Expression>,
+    },
+    /// Emits the current values of the output variables as a vertex
+    /// of the current output primitive.
+    ///
+    /// Only allowed in geometry stages.
+    EmitVertex,
+    /// Finishes the current output primitive, so that the following
+    /// vertices start a new one.
+    ///
+    /// Only allowed in geometry stages.
+    EndPrimitive,
+    /// Traces a ray through the acceleration structure, invoking the
+    /// intersection, hit and miss stages on the way.
+    ///
+    /// The `payload` has to point to a variable in `StorageClass::RayPayload`,
+    /// which is shared with the invoked stages.
+    /// Only allowed in ray generation, closest hit and miss stages.
+    TraceRay {
+        acceleration_structure: Handle<Expression>,
+        flags: Handle<Expression>,
+        cull_mask: Handle<Expression>,
+        sbt_offset: Handle<Expression>,
+        sbt_stride: Handle<Expression>,
+        miss_index: Handle<Expression>,
+        origin: Handle<Expression>,
+        t_min: Handle<Expression>,
+        direction: Handle<Expression>,
+        t_max: Handle<Expression>,
+        payload: Handle<Expression>,
+    },
+    /// Reports an intersection with the ray at `hit_t` distance,
+    /// with an application-defined `hit_kind`.
+    ///
+    /// The `result` expression has to be `Expression::ReportIntersectionResult`.
+    /// Only allowed in intersection stages.
+    ReportIntersection {
+        hit_t: Handle<Expression>,
+        hit_kind: Handle<Expression>,
+        result: Handle<Expression>,
+    },
+    /// Rejects the current intersection and aborts the invocation.
+    ///
+    /// Only allowed in any hit stages.
+    IgnoreIntersection,
+    /// Accepts the current intersection, stops the traversal of the ray
+    /// and aborts the invocation.
+    ///
+    /// Only allowed in any hit stages.
+    TerminateRay,
 }
 
 /// A function argument.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct FunctionArgument {
@@ -848,10 +1246,29 @@ pub struct FunctionArgument {
     pub name: Option<String>,
     /// Type of the argument.
     pub ty: Handle<Type>,
+    /// For entry points, an argument has to have a binding
+    /// unless it's a structure whose members all have bindings.
+    pub binding: Option<Binding>,
+    /// For entry point inputs, the interpolation qualifier, if any.
+    pub interpolation: Option<Interpolation>,
+}
+
+/// A function result.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct FunctionResult {
+    /// Type of the result.
+    pub ty: Handle<Type>,
+    /// For entry points, the result has to have a binding
+    /// unless it's a structure whose members all have bindings.
+    pub binding: Option<Binding>,
+    /// For entry point outputs, the interpolation qualifier, if any.
+    pub interpolation: Option<Interpolation>,
 }
 
 /// A function defined in the module.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Function {
@@ -859,8 +1276,8 @@ pub struct Function {
     pub name: Option<String>,
     /// Information about function argument.
     pub arguments: Vec<FunctionArgument>,
-    /// The return type of this function, if any.
-    pub return_type: Option<Handle<Type>>,
+    /// The result of this function, if any.
+    pub result: Option<FunctionResult>,
     /// Local variables defined and used in the function.
     pub local_variables: Arena<LocalVariable>,
     /// Expressions used inside this function.
@@ -870,7 +1287,7 @@ pub struct Function {
 }
 
 /// Exported function, to be run at a certain stage in the pipeline.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct EntryPoint {
@@ -882,6 +1299,10 @@ pub struct EntryPoint {
     pub early_depth_test: Option<EarlyDepthTest>,
     /// Workgroup size for compute stages
     pub workgroup_size: [u32; 3],
+    /// Primitive configuration for geometry stages.
+    pub geometry: Option<GeometryInfo>,
+    /// Configuration for tessellation stages.
+    pub tessellation: Option<TessellationInfo>,
     /// The entrance function.
     pub function: Function,
 }
@@ -897,7 +1318,7 @@ pub struct EntryPoint {
 /// Alternatively, you can load an existing shader using one of the [available front ends][front].
 ///
 /// When finished, you can export modules using one of the [available back ends][back].
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Module {
@@ -6,9 +6,25 @@ Figures out the following properties:
   - expression reference counts
 !*/
 
-use crate::arena::{Arena, Handle};
+use super::visitor::{walk_expression, Visitor};
+use crate::{
+    arena::{Arena, Handle},
+    Span,
+};
 use std::ops;
 
+/// Item of a function that takes part in the uniformity of its control flow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum UniformityItem {
+    Expression(Handle<crate::Expression>),
+    /// A call statement without a result.
+    Call(Handle<crate::Function>),
+    /// A control barrier statement.
+    Barrier,
+}
+
 /// Uniform control flow characteristics.
 #[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
@@ -26,25 +42,34 @@ pub struct Uniformity {
     /// and process all branches of the control flow.
     ///
     /// Any operations that depend on non-uniform results also produce non-uniform.
+    /// The handle always points to the source of non-uniformity within the
+    /// function: a non-uniform variable or argument, an atomic or subgroup
+    /// result, or a call to a function that produces non-uniform results.
     non_uniform_result: Option<Handle<crate::Expression>>,
-    /// A child expression that requires uniform control flow.
+    /// A child item that requires uniform control flow.
     ///
     /// Some operations can only be done within uniform control flow:
     /// derivatives and auto-level image sampling in fragment shaders,
     /// and group barriers in compute shaders.
-    require_uniform: Option<Handle<crate::Expression>>,
+    require_uniform: Option<UniformityItem>,
+    /// Span of the statement that requires uniform control flow,
+    /// if it's not an expression.
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(skip))]
+    require_uniform_span: Span,
 }
 
-//TODO: instead of doing cur | next, we could reverse this everywhere
-// and do `next | cur`, which would allow us to trace the cause of
-// uniformity requirement/disruption across the expression chain.
-
 impl ops::BitOr for Uniformity {
     type Output = Self;
     fn bitor(self, other: Self) -> Self {
+        let (require_uniform, require_uniform_span) = if self.require_uniform.is_some() {
+            (self.require_uniform, self.require_uniform_span)
+        } else {
+            (other.require_uniform, other.require_uniform_span)
+        };
         Uniformity {
             non_uniform_result: self.non_uniform_result.or(other.non_uniform_result),
-            require_uniform: self.require_uniform.or(other.require_uniform),
+            require_uniform,
+            require_uniform_span,
         }
     }
 }
@@ -60,19 +85,48 @@ impl Uniformity {
         Uniformity {
             non_uniform_result: Some(expr),
             require_uniform: None,
+            require_uniform_span: Span::UNDEFINED,
         }
     }
 
     fn require_uniform(expr: Handle<crate::Expression>) -> Self {
         Uniformity {
             non_uniform_result: None,
-            require_uniform: Some(expr),
+            require_uniform: Some(UniformityItem::Expression(expr)),
+            require_uniform_span: Span::UNDEFINED,
+        }
+    }
+
+    fn require_uniform_statement(item: UniformityItem, span: Span) -> Self {
+        Uniformity {
+            non_uniform_result: None,
+            require_uniform: Some(item),
+            require_uniform_span: span,
+        }
+    }
+
+    /// Uniformity of the result of a call expression `handle`,
+    /// given the uniformity of the called function.
+    fn call_result(handle: Handle<crate::Expression>, callee: &Self) -> Self {
+        Uniformity {
+            non_uniform_result: callee.non_uniform_result.map(|_| handle),
+            require_uniform: callee
+                .require_uniform
+                .map(|_| UniformityItem::Expression(handle)),
+            require_uniform_span: Span::UNDEFINED,
         }
     }
 
     fn disruptor(&self) -> Option<UniformityDisruptor> {
         self.non_uniform_result.map(UniformityDisruptor::Expression)
     }
+
+    /// Disruptor of the control flow conditioned on the `expr` value
+    /// with this uniformity.
+    fn disruptor_at(&self, expr: Handle<crate::Expression>) -> Option<UniformityDisruptor> {
+        self.non_uniform_result
+            .map(|_| UniformityDisruptor::Expression(expr))
+    }
 }
 
 bitflags::bitflags! {
@@ -101,6 +155,21 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    /// Indicates which ray tracing operations are performed.
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+    #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+    pub struct RayTracingOps: u8 {
+        /// Rays are traced with `Statement::TraceRay`.
+        const TRACE_RAY = 0x1;
+        /// Intersections are reported with `Statement::ReportIntersection`.
+        const REPORT_INTERSECTION = 0x2;
+        /// Intersections are ignored or accepted with `Statement::IgnoreIntersection`
+        /// and `Statement::TerminateRay`.
+        const RESOLVE_HIT = 0x4;
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
@@ -125,6 +194,11 @@ pub struct FunctionInfo {
     pub uniformity: Uniformity,
     /// Function may kill the invocation.
     pub may_kill: bool,
+    /// Function may emit vertices or end primitives,
+    /// which is only allowed in geometry stages.
+    pub may_emit_vertices: bool,
+    /// Ray tracing operations, each of which is only allowed in some of the ray tracing stages.
+    pub ray_tracing_ops: RayTracingOps,
     /// Set of image-sampler pais used with sampling.
     pub sampling_set: crate::FastHashSet<SamplingKey>,
     /// Vector of global variable usages.
@@ -194,13 +268,156 @@ impl UniformityDisruptor {
     }
 }
 
+/// Function or entry point of a module.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum FunctionOrigin {
+    Function(Handle<crate::Function>),
+    /// Index of the entry point in the module.
+    EntryPoint(usize),
+}
+
+/// Step of a path through which the uniformity of the control flow is decided.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct UniformityStep {
+    /// Function that the item belongs to.
+    pub origin: FunctionOrigin,
+    pub item: UniformityItem,
+    /// Span of the item, if it's known.
+    pub span: Span,
+}
+
 #[derive(Clone, Debug, thiserror::Error)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum AnalysisError {
     #[error("Expression {0:?} is not a global variable!")]
     ExpectedGlobalVariable(crate::Expression),
-    #[error("Required uniformity of control flow for {0:?} is not fulfilled because of {1:?}")]
-    NonUniformControlFlow(Handle<crate::Expression>, UniformityDisruptor),
+    #[error("Required uniformity of control flow for {item:?} is not fulfilled because of {disruptor:?}")]
+    NonUniformControlFlow {
+        item: UniformityItem,
+        disruptor: UniformityDisruptor,
+        /// Span of the statement executed in the non-uniform control flow.
+        span: Span,
+        /// Path from the source of non-uniformity to the value that the
+        /// control flow depends on. Empty if the control flow is disrupted
+        /// by a return or a discard.
+        cause: Vec<UniformityStep>,
+        /// Path from the statement executed in the non-uniform control flow
+        /// to the operation that requires uniformity, across the called functions.
+        requirement: Vec<UniformityStep>,
+    },
+}
+
+impl AnalysisError {
+    /// Returns the span of the statement executed in the non-uniform
+    /// control flow, if it's known.
+    pub fn span(&self) -> Span {
+        match *self {
+            AnalysisError::ExpectedGlobalVariable(_) => Span::UNDEFINED,
+            AnalysisError::NonUniformControlFlow { span, .. } => span,
+        }
+    }
+
+    /// Renders the error together with the path of the non-uniformity,
+    /// quoting the `source` text of the steps with known spans.
+    ///
+    /// The module has to be the one the error was produced for.
+    pub fn emit_to_string(&self, module: &crate::Module, source: &str) -> String {
+        let mut output = self.to_string();
+        if let AnalysisError::NonUniformControlFlow {
+            ref cause,
+            ref requirement,
+            ..
+        } = *self
+        {
+            for (index, step) in cause.iter().enumerate() {
+                let note = if index == 0 {
+                    "non-uniform value"
+                } else {
+                    "depends on it"
+                };
+                render_step(&mut output, step, note, module, source);
+            }
+            for (index, step) in requirement.iter().enumerate() {
+                let note = if index + 1 == requirement.len() {
+                    "requires uniform control flow"
+                } else {
+                    "called function requires uniform control flow"
+                };
+                render_step(&mut output, step, note, module, source);
+            }
+        }
+        output
+    }
+}
+
+fn render_step(
+    output: &mut String,
+    step: &UniformityStep,
+    note: &str,
+    module: &crate::Module,
+    source: &str,
+) {
+    use std::fmt::Write as _;
+
+    let (function, function_name) = match step.origin {
+        FunctionOrigin::Function(handle) => {
+            let function = &module.functions[handle];
+            (function, function.name.as_deref())
+        }
+        FunctionOrigin::EntryPoint(index) => {
+            let entry_point = &module.entry_points[index];
+            (&entry_point.function, Some(entry_point.name.as_str()))
+        }
+    };
+    let function_name = function_name.unwrap_or("?");
+    let _ = match step.span.to_range().and_then(|range| source.get(range)) {
+        Some(text) => {
+            let (line, column) = step.span.location(source);
+            let text = text.lines().next().unwrap_or_default();
+            write!(
+                output,
+                "\n  {}:{} in `{}`: {}: `{}`",
+                line, column, function_name, note, text
+            )
+        }
+        None => {
+            // name the arguments and globals, which don't have spans of their own
+            let name = match step.item {
+                UniformityItem::Expression(expr) => match function.expressions[expr] {
+                    crate::Expression::FunctionArgument(index) => {
+                        function.arguments[index as usize].name.as_ref()
+                    }
+                    crate::Expression::GlobalVariable(var) => {
+                        module.global_variables[var].name.as_ref()
+                    }
+                    _ => None,
+                },
+                UniformityItem::Call(_) | UniformityItem::Barrier => None,
+            };
+            match name {
+                Some(name) => write!(output, "\n  in `{}`: {}: `{}`", function_name, note, name),
+                None => write!(
+                    output,
+                    "\n  in `{}`: {}: {:?}",
+                    function_name, note, step.item
+                ),
+            }
+        }
+    };
+}
+
+/// Collects the handles of the operands of an expression.
+#[derive(Default)]
+struct Operands(Vec<Handle<crate::Expression>>);
+
+impl Visitor for Operands {
+    fn visit_expression_handle(&mut self, handle: &Handle<crate::Expression>) {
+        self.0.push(*handle);
+    }
 }
 
 impl FunctionInfo {
@@ -248,14 +465,13 @@ impl FunctionInfo {
     }
 
     /// Inherit information from a called function.
-    fn process_call(&mut self, info: &Self) -> Uniformity {
+    fn process_call(&mut self, info: &Self) {
         for key in info.sampling_set.iter() {
             self.sampling_set.insert(key.clone());
         }
         for (mine, other) in self.global_uses.iter_mut().zip(info.global_uses.iter()) {
             *mine |= *other;
         }
-        info.uniformity.clone()
     }
 
     /// Computes the expression info and stores it in `self.expressions`.
@@ -293,7 +509,9 @@ impl FunctionInfo {
                         crate::BuiltIn::FrontFacing
                         // per-work-group built-ins are uniform
                         | crate::BuiltIn::WorkGroupId
-                        | crate::BuiltIn::WorkGroupSize => true,
+                        | crate::BuiltIn::WorkGroupSize
+                        // per-dispatch built-ins are uniform
+                        | crate::BuiltIn::LaunchSize => true,
                         _ => false,
                     }
                 } else {
@@ -301,7 +519,13 @@ impl FunctionInfo {
                     match var.class {
                         // only flat inputs are uniform
                         Sc::Input => var.interpolation == Some(crate::Interpolation::Flat),
-                        Sc::Output | Sc::Function | Sc::Private | Sc::WorkGroup => false,
+                        Sc::Output
+                        | Sc::Function
+                        | Sc::Private
+                        | Sc::WorkGroup
+                        | Sc::RayPayload
+                        | Sc::IncomingRayPayload
+                        | Sc::HitAttribute => false,
                         // uniform data
                         Sc::Uniform | Sc::PushConstant => true,
                         // storage data is only uniform when read-only
@@ -412,8 +636,19 @@ impl FunctionInfo {
                 self.add_ref(arg) | arg1_flags | arg2_flags
             }
             E::As { expr, .. } => self.add_ref(expr),
-            E::Call(function) => self.process_call(&other_functions[function.index()]),
+            E::Call(function) => {
+                let info = &other_functions[function.index()];
+                self.process_call(info);
+                Uniformity::call_result(handle, &info.uniformity)
+            }
             E::ArrayLength(expr) => self.add_ref_impl(expr, GlobalUse::QUERY),
+            // the result of an atomic operation depends on other invocations
+            E::AtomicResult { .. } => Uniformity::non_uniform_result(handle),
+            // subgroups are smaller than the workgroup, and some of the
+            // results are different per invocation
+            E::SubgroupResult { .. } => Uniformity::non_uniform_result(handle),
+            // any hit stages may reject the intersection
+            E::ReportIntersectionResult => Uniformity::non_uniform_result(handle),
         };
 
         self.expressions[handle.index()] = ExpressionInfo {
@@ -428,22 +663,27 @@ impl FunctionInfo {
     /// (as a sequence of statements), and returns them.
     /// The parent control flow is uniform if `disruptor.is_none()`.
     ///
-    /// Returns a `NonUniformControlFlow` error if any of the expressions in the block
+    /// Returns a `NonUniformControlFlow` error if any of the items in the block
     /// require uniformity, but the current flow is non-uniform.
+    /// The paths of the error are left empty.
     #[allow(clippy::or_fun_call)]
     fn process_block(
         &mut self,
-        statements: &[crate::Statement],
+        block: &crate::Block,
         other_functions: &[FunctionInfo],
         mut disruptor: Option<UniformityDisruptor>,
     ) -> Result<(Uniformity, ExitFlags), AnalysisError> {
         use crate::Statement as S;
         let mut block_uniformity = Uniformity::default();
         let mut block_exit = ExitFlags::empty();
-        for statement in statements {
+        for (statement, span) in block.span_iter() {
             let (cur_uniformity, cur_exit) = match *statement {
                 S::Emit(_) | S::Break | S::Continue => (Uniformity::default(), ExitFlags::empty()),
                 S::Kill => (Uniformity::default(), ExitFlags::MAY_KILL),
+                S::IgnoreIntersection | S::TerminateRay => {
+                    self.ray_tracing_ops |= RayTracingOps::RESOLVE_HIT;
+                    (Uniformity::default(), ExitFlags::MAY_KILL)
+                }
                 S::Block(ref b) => self.process_block(b, other_functions, disruptor)?,
                 S::If {
                     condition,
@@ -451,7 +691,8 @@ impl FunctionInfo {
                     ref reject,
                 } => {
                     let condition_uniformity = self.add_ref(condition);
-                    let branch_disruptor = disruptor.or(condition_uniformity.disruptor());
+                    let branch_disruptor =
+                        disruptor.or(condition_uniformity.disruptor_at(condition));
                     let (accept_uniformity, accept_exit) =
                         self.process_block(accept, other_functions, branch_disruptor)?;
                     let (reject_uniformity, reject_exit) =
@@ -467,7 +708,7 @@ impl FunctionInfo {
                     ref default,
                 } => {
                     let selector_uniformity = self.add_ref(selector);
-                    let branch_disruptor = disruptor.or(selector_uniformity.disruptor());
+                    let branch_disruptor = disruptor.or(selector_uniformity.disruptor_at(selector));
                     let mut uniformity = selector_uniformity;
                     let mut exit = ExitFlags::empty();
                     let mut case_disruptor = disruptor;
@@ -534,13 +775,28 @@ impl FunctionInfo {
                 S::Call {
                     function,
                     ref arguments,
-                    result: _,
+                    result,
                 } => {
                     let info = &other_functions[function.index()];
-                    let mut uniformity = self.process_call(info);
+                    self.process_call(info);
+                    let mut uniformity = match result {
+                        // the result expression carries the uniformity of the call
+                        Some(expr) => self.expressions[expr.index()].uniformity.clone(),
+                        None => match info.uniformity.require_uniform {
+                            Some(_) => Uniformity::require_uniform_statement(
+                                UniformityItem::Call(function),
+                                span,
+                            ),
+                            None => Uniformity::default(),
+                        },
+                    };
                     for &argument in arguments {
                         uniformity |= self.add_ref(argument);
                     }
+                    if info.may_emit_vertices {
+                        self.may_emit_vertices = true;
+                    }
+                    self.ray_tracing_ops |= info.ray_tracing_ops;
                     let exit = if info.may_kill {
                         ExitFlags::MAY_KILL
                     } else {
@@ -548,10 +804,105 @@ impl FunctionInfo {
                     };
                     (uniformity, exit)
                 }
+                S::Atomic {
+                    pointer,
+                    fun,
+                    value,
+                    result: _,
+                } => {
+                    let compare_uniformity = match fun {
+                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => self.add_ref(cmp),
+                        _ => Uniformity::default(),
+                    };
+                    let uniformity = compare_uniformity
+                        | self.add_ref_impl(pointer, GlobalUse::READ | GlobalUse::WRITE)
+                        | self.add_ref(value);
+                    (uniformity, ExitFlags::empty())
+                }
+                S::Subgroup { ref fun, result } => {
+                    let mut uniformity = Uniformity::require_uniform(result);
+                    match *fun {
+                        crate::SubgroupFunction::Elect => {}
+                        crate::SubgroupFunction::Ballot { predicate } => {
+                            if let Some(predicate) = predicate {
+                                uniformity |= self.add_ref(predicate);
+                            }
+                        }
+                        crate::SubgroupFunction::Gather { mode, argument } => {
+                            match mode {
+                                crate::GatherMode::BroadcastFirst => {}
+                                crate::GatherMode::Broadcast(index)
+                                | crate::GatherMode::Shuffle(index)
+                                | crate::GatherMode::ShuffleDown(index)
+                                | crate::GatherMode::ShuffleUp(index)
+                                | crate::GatherMode::ShuffleXor(index) => {
+                                    uniformity |= self.add_ref(index);
+                                }
+                            }
+                            uniformity |= self.add_ref(argument);
+                        }
+                        crate::SubgroupFunction::Collective { argument, .. } => {
+                            uniformity |= self.add_ref(argument);
+                        }
+                    }
+                    (uniformity, ExitFlags::empty())
+                }
+                S::EmitVertex | S::EndPrimitive => {
+                    self.may_emit_vertices = true;
+                    (Uniformity::default(), ExitFlags::empty())
+                }
+                S::TraceRay {
+                    acceleration_structure,
+                    flags,
+                    cull_mask,
+                    sbt_offset,
+                    sbt_stride,
+                    miss_index,
+                    origin,
+                    t_min,
+                    direction,
+                    t_max,
+                    payload,
+                } => {
+                    self.ray_tracing_ops |= RayTracingOps::TRACE_RAY;
+                    let mut uniformity = self.add_ref(acceleration_structure);
+                    for &expr in &[
+                        flags, cull_mask, sbt_offset, sbt_stride, miss_index, origin, t_min,
+                        direction, t_max,
+                    ] {
+                        uniformity |= self.add_ref(expr);
+                    }
+                    uniformity |= self.add_ref_impl(payload, GlobalUse::READ | GlobalUse::WRITE);
+                    (uniformity, ExitFlags::empty())
+                }
+                S::ReportIntersection {
+                    hit_t,
+                    hit_kind,
+                    result: _,
+                } => {
+                    self.ray_tracing_ops |= RayTracingOps::REPORT_INTERSECTION;
+                    let uniformity = self.add_ref(hit_t) | self.add_ref(hit_kind);
+                    (uniformity, ExitFlags::empty())
+                }
+                S::Barrier { execution, .. } => {
+                    let uniformity = match execution {
+                        Some(_) => {
+                            Uniformity::require_uniform_statement(UniformityItem::Barrier, span)
+                        }
+                        None => Uniformity::default(),
+                    };
+                    (uniformity, ExitFlags::empty())
+                }
             };
 
-            if let (Some(expr), Some(cause)) = (cur_uniformity.require_uniform, disruptor) {
-                return Err(AnalysisError::NonUniformControlFlow(expr, cause));
+            if let (Some(disruptor), Some(item)) = (disruptor, cur_uniformity.require_uniform) {
+                return Err(AnalysisError::NonUniformControlFlow {
+                    item,
+                    disruptor,
+                    span,
+                    cause: Vec::new(),
+                    requirement: Vec::new(),
+                });
             }
             disruptor = disruptor.or(UniformityDisruptor::from_exit(cur_exit));
             block_uniformity |= cur_uniformity;
@@ -575,40 +926,168 @@ impl Analysis {
     fn process_function(
         &self,
         fun: &crate::Function,
-        global_var_arena: &Arena<crate::GlobalVariable>,
+        origin: FunctionOrigin,
+        module: &crate::Module,
     ) -> Result<FunctionInfo, AnalysisError> {
         let mut info = FunctionInfo {
             uniformity: Uniformity::default(),
             may_kill: false,
+            may_emit_vertices: false,
+            ray_tracing_ops: RayTracingOps::empty(),
             sampling_set: crate::FastHashSet::default(),
-            global_uses: vec![GlobalUse::empty(); global_var_arena.len()].into_boxed_slice(),
+            global_uses: vec![GlobalUse::empty(); module.global_variables.len()].into_boxed_slice(),
             expressions: vec![ExpressionInfo::default(); fun.expressions.len()].into_boxed_slice(),
         };
 
         for (handle, _) in fun.expressions.iter() {
-            info.process_expression(handle, &fun.expressions, global_var_arena, &self.functions)?;
+            info.process_expression(
+                handle,
+                &fun.expressions,
+                &module.global_variables,
+                &self.functions,
+            )?;
         }
 
-        let (uniformity, exit) = info.process_block(&fun.body, &self.functions, None)?;
+        let (uniformity, exit) = match info.process_block(&fun.body, &self.functions, None) {
+            Ok(result) => result,
+            Err(AnalysisError::NonUniformControlFlow {
+                item,
+                disruptor,
+                span,
+                ..
+            }) => {
+                let cause = match disruptor {
+                    UniformityDisruptor::Expression(expr) => {
+                        self.cause_path(&info, fun, origin, expr, module)
+                    }
+                    UniformityDisruptor::Return | UniformityDisruptor::Discard => Vec::new(),
+                };
+                let requirement = self.requirement_path(fun, origin, item, span, module);
+                return Err(AnalysisError::NonUniformControlFlow {
+                    item,
+                    disruptor,
+                    span,
+                    cause,
+                    requirement,
+                });
+            }
+            Err(other) => return Err(other),
+        };
         info.uniformity = uniformity;
         info.may_kill = exit.contains(ExitFlags::MAY_KILL);
 
         Ok(info)
     }
 
+    /// Traces the non-uniform value `expr` back to its source,
+    /// and returns the path from the source to `expr`.
+    ///
+    /// If the source is a call, the path continues into the called function.
+    fn cause_path(
+        &self,
+        info: &FunctionInfo,
+        fun: &crate::Function,
+        origin: FunctionOrigin,
+        expr: Handle<crate::Expression>,
+        module: &crate::Module,
+    ) -> Vec<UniformityStep> {
+        let mut path = Vec::new();
+        let mut current = expr;
+        loop {
+            path.push(UniformityStep {
+                origin,
+                item: UniformityItem::Expression(current),
+                span: fun.expressions.get_span(current),
+            });
+            let source = match info[current].uniformity.non_uniform_result {
+                Some(source) if source != current => source,
+                _ => break,
+            };
+            // follow the operand that got its non-uniformity from the same source
+            let mut operands = Operands::default();
+            walk_expression(&mut operands, &fun.expressions[current]);
+            match operands
+                .0
+                .into_iter()
+                .find(|&operand| info[operand].uniformity.non_uniform_result == Some(source))
+            {
+                Some(operand) => current = operand,
+                None => break,
+            }
+        }
+        path.reverse();
+
+        if let crate::Expression::Call(function) = fun.expressions[current] {
+            let callee = &self.functions[function.index()];
+            if let Some(result) = callee.uniformity.non_uniform_result {
+                let mut callee_path = self.cause_path(
+                    callee,
+                    &module.functions[function],
+                    FunctionOrigin::Function(function),
+                    result,
+                    module,
+                );
+                callee_path.extend(path);
+                path = callee_path;
+            }
+        }
+        path
+    }
+
+    /// Returns the path from the `item` that requires uniform control flow,
+    /// down to the operation inside the called functions that actually requires it.
+    ///
+    /// The `span` is the one of the statement, used if `item` is not an expression.
+    fn requirement_path<'a>(
+        &self,
+        mut fun: &'a crate::Function,
+        mut origin: FunctionOrigin,
+        mut item: UniformityItem,
+        mut span: Span,
+        module: &'a crate::Module,
+    ) -> Vec<UniformityStep> {
+        let mut path = Vec::new();
+        loop {
+            if let UniformityItem::Expression(expr) = item {
+                span = fun.expressions.get_span(expr);
+            }
+            path.push(UniformityStep { origin, item, span });
+            let function = match item {
+                UniformityItem::Expression(expr) => match fun.expressions[expr] {
+                    crate::Expression::Call(function) => function,
+                    _ => break,
+                },
+                UniformityItem::Call(function) => function,
+                UniformityItem::Barrier => break,
+            };
+            let callee = &self.functions[function.index()].uniformity;
+            match callee.require_uniform {
+                Some(callee_item) => {
+                    fun = &module.functions[function];
+                    origin = FunctionOrigin::Function(function);
+                    item = callee_item;
+                    span = callee.require_uniform_span;
+                }
+                None => break,
+            }
+        }
+        path
+    }
+
     /// Analyze a module and return the `Analysis`, if successful.
     pub fn new(module: &crate::Module) -> Result<Self, AnalysisError> {
         let mut this = Analysis {
             functions: Vec::with_capacity(module.functions.len()),
             entry_points: Vec::with_capacity(module.entry_points.len()),
         };
-        for (_, fun) in module.functions.iter() {
-            let info = this.process_function(fun, &module.global_variables)?;
+        for (handle, fun) in module.functions.iter() {
+            let info = this.process_function(fun, FunctionOrigin::Function(handle), module)?;
             this.functions.push(info);
         }
 
-        for ep in module.entry_points.iter() {
-            let info = this.process_function(&ep.function, &module.global_variables)?;
+        for (index, ep) in module.entry_points.iter().enumerate() {
+            let info =
+                this.process_function(&ep.function, FunctionOrigin::EntryPoint(index), module)?;
             this.entry_points.push(info);
         }
 
@@ -689,6 +1168,8 @@ fn uniform_control_flow() {
     let mut info = FunctionInfo {
         uniformity: Uniformity::default(),
         may_kill: false,
+        may_emit_vertices: false,
+        ray_tracing_ops: RayTracingOps::empty(),
         sampling_set: crate::FastHashSet::default(),
         global_uses: vec![GlobalUse::empty(); global_var_arena.len()].into_boxed_slice(),
         expressions: vec![ExpressionInfo::default(); expressions.len()].into_boxed_slice(),
@@ -706,14 +1187,14 @@ fn uniform_control_flow() {
 
     let stmt_if_uniform = S::If {
         condition: uniform_global_expr,
-        accept: Vec::new(),
-        reject: vec![S::Store {
+        accept: crate::Block::new(),
+        reject: crate::Block::from_vec(vec![S::Store {
             pointer: constant_expr,
             value: derivative_expr,
-        }],
+        }]),
     };
     assert_eq!(
-        info.process_block(&[stmt_if_uniform], &[], None),
+        info.process_block(&crate::Block::from_vec(vec![stmt_if_uniform]), &[], None),
         Ok((
             Uniformity::require_uniform(derivative_expr),
             ExitFlags::empty()
@@ -724,18 +1205,25 @@ fn uniform_control_flow() {
 
     let stmt_if_non_uniform = S::If {
         condition: non_uniform_global_expr,
-        accept: vec![S::Store {
+        accept: crate::Block::from_vec(vec![S::Store {
             pointer: constant_expr,
             value: derivative_expr,
-        }],
-        reject: Vec::new(),
+        }]),
+        reject: crate::Block::new(),
     };
     assert_eq!(
-        info.process_block(&[stmt_if_non_uniform], &[], None),
-        Err(AnalysisError::NonUniformControlFlow(
-            derivative_expr,
-            UniformityDisruptor::Expression(non_uniform_global_expr)
-        )),
+        info.process_block(
+            &crate::Block::from_vec(vec![stmt_if_non_uniform]),
+            &[],
+            None
+        ),
+        Err(AnalysisError::NonUniformControlFlow {
+            item: UniformityItem::Expression(derivative_expr),
+            disruptor: UniformityDisruptor::Expression(non_uniform_global_expr),
+            span: Span::UNDEFINED,
+            cause: Vec::new(),
+            requirement: Vec::new(),
+        }),
     );
     assert_eq!(info[derivative_expr].ref_count, 2);
     assert_eq!(info[non_uniform_global], GlobalUse::READ);
@@ -745,7 +1233,7 @@ fn uniform_control_flow() {
     };
     assert_eq!(
         info.process_block(
-            &[stmt_return_non_uniform],
+            &crate::Block::from_vec(vec![stmt_return_non_uniform]),
             &[],
             Some(UniformityDisruptor::Return)
         ),
@@ -761,11 +1249,97 @@ fn uniform_control_flow() {
         value: query_expr,
     };
     assert_eq!(
-        info.process_block(&[stmt_assign], &[], Some(UniformityDisruptor::Discard)),
+        info.process_block(
+            &crate::Block::from_vec(vec![stmt_assign]),
+            &[],
+            Some(UniformityDisruptor::Discard)
+        ),
         Ok((
             Uniformity::non_uniform_result(non_uniform_global_expr),
             ExitFlags::empty()
         )),
     );
     assert_eq!(info[non_uniform_global], GlobalUse::READ | GlobalUse::WRITE);
+
+    let stmt_barrier_non_uniform = S::If {
+        condition: non_uniform_global_expr,
+        accept: crate::Block::from_vec(vec![S::Barrier {
+            execution: Some(crate::Scope::WorkGroup),
+            memory_scope: crate::Scope::WorkGroup,
+            memory: crate::Barrier::WORK_GROUP,
+        }]),
+        reject: crate::Block::new(),
+    };
+    assert_eq!(
+        info.process_block(
+            &crate::Block::from_vec(vec![stmt_barrier_non_uniform]),
+            &[],
+            None
+        ),
+        Err(AnalysisError::NonUniformControlFlow {
+            item: UniformityItem::Barrier,
+            disruptor: UniformityDisruptor::Expression(non_uniform_global_expr),
+            span: Span::UNDEFINED,
+            cause: Vec::new(),
+            requirement: Vec::new(),
+        }),
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn non_uniform_path() {
+    let source = "
+fn slope(x: f32) -> f32 {
+    return dpdx(x);
+}
+
+[[stage(fragment)]]
+fn main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    var result: f32 = 0.0;
+    if (position.x > 0.5) {
+        result = slope(position.y);
+    }
+    return vec4<f32>(result, 0.0, 0.0, 1.0);
+}
+";
+    let module = crate::front::wgsl::parse_str(source).unwrap();
+    let error = Analysis::new(&module).err().unwrap();
+    assert!(matches!(error, AnalysisError::NonUniformControlFlow { .. }));
+    let (cause, requirement) = match error {
+        AnalysisError::NonUniformControlFlow {
+            ref cause,
+            ref requirement,
+            ..
+        } => (cause.as_slice(), requirement.as_slice()),
+        _ => (&[][..], &[][..]),
+    };
+    let main = FunctionOrigin::EntryPoint(0);
+    let slope = FunctionOrigin::Function(module.functions.iter().next().unwrap().0);
+    assert_eq!(
+        cause.iter().map(|step| step.origin).collect::<Vec<_>>(),
+        vec![main; 3],
+    );
+    assert_eq!(
+        requirement
+            .iter()
+            .map(|step| step.origin)
+            .collect::<Vec<_>>(),
+        vec![main, slope],
+    );
+    assert!(error.span().is_defined());
+    assert_eq!(
+        error
+            .emit_to_string(&module, source)
+            .lines()
+            .skip(1)
+            .collect::<Vec<_>>(),
+        vec![
+            "  in `main`: non-uniform value: `position`",
+            "  9:9 in `main`: depends on it: `position.x`",
+            "  9:9 in `main`: depends on it: `position.x > 0.5`",
+            "  10:18 in `main`: called function requires uniform control flow: `slope(position.y)`",
+            "  3:12 in `slope`: requires uniform control flow: `dpdx(x)`",
+        ],
+    );
 }
@@ -0,0 +1,520 @@
+/*! Construction of modules from code.
+
+The [`FunctionBuilder`] appends the expressions of a function and takes care of
+emitting them: whenever a statement is added, the expressions appended since the
+previous statement are covered by a `Statement::Emit` placed right before it.
+!*/
+
+use super::ensure_block_returns;
+use crate::{
+    arena::Handle, BinaryOperator, Block, Bytes, Constant, ConstantInner, EntryPoint, Expression,
+    Function, FunctionArgument, FunctionResult, GlobalVariable, LocalVariable, Module, ScalarKind,
+    ScalarValue, ShaderStage, Statement, SwitchCase, Type, TypeInner, UnaryOperator, VectorSize,
+};
+
+/// Builder of a [`Module`].
+///
+/// Types, constants and global variables are only added if an equal one
+/// isn't already in the module.
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    module: Module,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        ModuleBuilder::default()
+    }
+
+    /// Continue building an existing module.
+    pub fn from_module(module: Module) -> Self {
+        ModuleBuilder { module }
+    }
+
+    /// The module built so far.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    pub fn ty(&mut self, name: Option<&str>, inner: TypeInner) -> Handle<Type> {
+        self.module.types.fetch_or_append(Type {
+            name: name.map(str::to_string),
+            inner,
+        })
+    }
+
+    pub fn scalar_type(&mut self, kind: ScalarKind, width: Bytes) -> Handle<Type> {
+        self.ty(None, TypeInner::Scalar { kind, width })
+    }
+
+    pub fn vector_type(
+        &mut self,
+        size: VectorSize,
+        kind: ScalarKind,
+        width: Bytes,
+    ) -> Handle<Type> {
+        self.ty(None, TypeInner::Vector { size, kind, width })
+    }
+
+    pub fn constant(&mut self, name: Option<&str>, inner: ConstantInner) -> Handle<Constant> {
+        self.module.constants.fetch_or_append(Constant {
+            name: name.map(str::to_string),
+            specialization: None,
+            inner,
+        })
+    }
+
+    pub fn scalar_constant(&mut self, value: ScalarValue, width: Bytes) -> Handle<Constant> {
+        self.constant(None, ConstantInner::Scalar { width, value })
+    }
+
+    pub fn composite_constant(
+        &mut self,
+        ty: Handle<Type>,
+        components: Vec<Handle<Constant>>,
+    ) -> Handle<Constant> {
+        self.constant(None, ConstantInner::Composite { ty, components })
+    }
+
+    pub fn global(&mut self, variable: GlobalVariable) -> Handle<GlobalVariable> {
+        self.module.global_variables.fetch_or_append(variable)
+    }
+
+    /// Add a finished function, which can then be called by the following ones.
+    pub fn function(&mut self, builder: FunctionBuilder) -> Handle<Function> {
+        self.module.functions.append(builder.finish())
+    }
+
+    /// Add an entry point, returning it for the stage-specific settings.
+    pub fn entry_point(
+        &mut self,
+        name: &str,
+        stage: ShaderStage,
+        builder: FunctionBuilder,
+    ) -> &mut EntryPoint {
+        self.module.entry_points.push(EntryPoint {
+            name: name.to_string(),
+            stage,
+            early_depth_test: None,
+            workgroup_size: [0; 3],
+            geometry: None,
+            tessellation: None,
+            function: builder.finish(),
+        });
+        self.module.entry_points.last_mut().unwrap()
+    }
+
+    pub fn finish(self) -> Module {
+        self.module
+    }
+}
+
+/// Builder of a [`Function`].
+///
+/// The structured statements take closures building their blocks
+/// with the same builder.
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    function: Function,
+    /// Block the statements are added to.
+    block: Block,
+    /// Length of the expression arena when the pending expressions started.
+    emit_start: usize,
+}
+
+impl FunctionBuilder {
+    pub fn new(name: &str, result: Option<FunctionResult>) -> Self {
+        FunctionBuilder {
+            function: Function {
+                name: Some(name.to_string()),
+                result,
+                ..Function::default()
+            },
+            block: Block::new(),
+            emit_start: 0,
+        }
+    }
+
+    /// The function built so far, without the statements of the current block.
+    pub fn function(&self) -> &Function {
+        &self.function
+    }
+
+    /// Add an argument, returning the expression of its value.
+    pub fn argument(&mut self, argument: FunctionArgument) -> Handle<Expression> {
+        let index = self.function.arguments.len() as u32;
+        self.function.arguments.push(argument);
+        self.expression(Expression::FunctionArgument(index))
+    }
+
+    /// Add a local variable, returning the expression of its pointer.
+    pub fn local(
+        &mut self,
+        name: &str,
+        ty: Handle<Type>,
+        init: Option<Handle<Constant>>,
+    ) -> Handle<Expression> {
+        let variable = self.function.local_variables.append(LocalVariable {
+            name: Some(name.to_string()),
+            ty,
+            init,
+        });
+        self.expression(Expression::LocalVariable(variable))
+    }
+
+    /// Append an expression.
+    ///
+    /// The results of the calls, atomics and other operations are not emitted,
+    /// so they have to be used by the matching statement.
+    pub fn expression(&mut self, expression: Expression) -> Handle<Expression> {
+        let emitted = match expression {
+            Expression::Call(_)
+            | Expression::AtomicResult { .. }
+            | Expression::SubgroupResult { .. }
+            | Expression::ReportIntersectionResult => false,
+            ref other => !other.needs_pre_emit(),
+        };
+        if emitted {
+            return self.function.expressions.append(expression);
+        }
+        // keep these out of the emitted ranges
+        self.flush();
+        let handle = self.function.expressions.append(expression);
+        self.emit_start = self.function.expressions.len();
+        handle
+    }
+
+    pub fn constant(&mut self, constant: Handle<Constant>) -> Handle<Expression> {
+        self.expression(Expression::Constant(constant))
+    }
+
+    pub fn global(&mut self, variable: Handle<GlobalVariable>) -> Handle<Expression> {
+        self.expression(Expression::GlobalVariable(variable))
+    }
+
+    pub fn load(&mut self, pointer: Handle<Expression>) -> Handle<Expression> {
+        self.expression(Expression::Load { pointer })
+    }
+
+    pub fn access(
+        &mut self,
+        base: Handle<Expression>,
+        index: Handle<Expression>,
+    ) -> Handle<Expression> {
+        self.expression(Expression::Access { base, index })
+    }
+
+    pub fn access_index(&mut self, base: Handle<Expression>, index: u32) -> Handle<Expression> {
+        self.expression(Expression::AccessIndex { base, index })
+    }
+
+    pub fn compose(
+        &mut self,
+        ty: Handle<Type>,
+        components: Vec<Handle<Expression>>,
+    ) -> Handle<Expression> {
+        self.expression(Expression::Compose { ty, components })
+    }
+
+    pub fn unary(&mut self, op: UnaryOperator, expr: Handle<Expression>) -> Handle<Expression> {
+        self.expression(Expression::Unary { op, expr })
+    }
+
+    pub fn binary(
+        &mut self,
+        op: BinaryOperator,
+        left: Handle<Expression>,
+        right: Handle<Expression>,
+    ) -> Handle<Expression> {
+        self.expression(Expression::Binary { op, left, right })
+    }
+
+    pub fn select(
+        &mut self,
+        condition: Handle<Expression>,
+        accept: Handle<Expression>,
+        reject: Handle<Expression>,
+    ) -> Handle<Expression> {
+        self.expression(Expression::Select {
+            condition,
+            accept,
+            reject,
+        })
+    }
+
+    /// Emit the expressions appended since the last statement.
+    fn flush(&mut self) {
+        let expressions = &self.function.expressions;
+        if self.emit_start != expressions.len() {
+            let range = expressions.range_from(self.emit_start);
+            self.emit_start = expressions.len();
+            self.block.push(Statement::Emit(range));
+        }
+    }
+
+    /// Add a statement, after emitting the pending expressions.
+    pub fn statement(&mut self, statement: Statement) {
+        self.flush();
+        self.block.push(statement);
+    }
+
+    /// Build a block with the statements added by `fill`.
+    pub fn block(&mut self, fill: impl FnOnce(&mut Self)) -> Block {
+        self.flush();
+        let outer = std::mem::take(&mut self.block);
+        fill(self);
+        self.flush();
+        std::mem::replace(&mut self.block, outer)
+    }
+
+    pub fn store(&mut self, pointer: Handle<Expression>, value: Handle<Expression>) {
+        self.statement(Statement::Store { pointer, value });
+    }
+
+    /// Call a function, returning the expression of its result if it has one.
+    pub fn call(
+        &mut self,
+        function: Handle<Function>,
+        arguments: Vec<Handle<Expression>>,
+        has_result: bool,
+    ) -> Option<Handle<Expression>> {
+        self.flush();
+        let result = if has_result {
+            Some(self.expression(Expression::Call(function)))
+        } else {
+            None
+        };
+        self.statement(Statement::Call {
+            function,
+            arguments,
+            result,
+        });
+        result
+    }
+
+    pub fn return_(&mut self, value: Option<Handle<Expression>>) {
+        self.statement(Statement::Return { value });
+    }
+
+    pub fn break_(&mut self) {
+        self.statement(Statement::Break);
+    }
+
+    pub fn continue_(&mut self) {
+        self.statement(Statement::Continue);
+    }
+
+    pub fn kill(&mut self) {
+        self.statement(Statement::Kill);
+    }
+
+    pub fn if_(&mut self, condition: Handle<Expression>, accept: impl FnOnce(&mut Self)) {
+        self.if_else(condition, accept, |_| {});
+    }
+
+    pub fn if_else(
+        &mut self,
+        condition: Handle<Expression>,
+        accept: impl FnOnce(&mut Self),
+        reject: impl FnOnce(&mut Self),
+    ) {
+        let accept = self.block(accept);
+        let reject = self.block(reject);
+        self.block.push(Statement::If {
+            condition,
+            accept,
+            reject,
+        });
+    }
+
+    pub fn loop_(&mut self, body: impl FnOnce(&mut Self), continuing: impl FnOnce(&mut Self)) {
+        let body = self.block(body);
+        let continuing = self.block(continuing);
+        self.block.push(Statement::Loop { body, continuing });
+    }
+
+    /// Add a switch with the cases added by `fill`.
+    pub fn switch(&mut self, selector: Handle<Expression>, fill: impl FnOnce(&mut SwitchBuilder)) {
+        self.flush();
+        let mut switch = SwitchBuilder {
+            builder: self,
+            cases: Vec::new(),
+            default: Block::new(),
+        };
+        fill(&mut switch);
+        let SwitchBuilder { cases, default, .. } = switch;
+        self.block.push(Statement::Switch {
+            selector,
+            cases,
+            default,
+        });
+    }
+
+    /// Finish the function, making sure that its control flow ends with a return.
+    pub fn finish(mut self) -> Function {
+        self.flush();
+        ensure_block_returns(&mut self.block);
+        self.function.body = self.block;
+        self.function
+    }
+}
+
+/// Builder of the cases of a switch statement.
+#[derive(Debug)]
+pub struct SwitchBuilder<'a> {
+    builder: &'a mut FunctionBuilder,
+    cases: Vec<SwitchCase>,
+    default: Block,
+}
+
+impl SwitchBuilder<'_> {
+    pub fn case(
+        &mut self,
+        value: i32,
+        fall_through: bool,
+        body: impl FnOnce(&mut FunctionBuilder),
+    ) -> &mut Self {
+        let body = self.builder.block(body);
+        self.cases.push(SwitchCase {
+            value,
+            body,
+            fall_through,
+        });
+        self
+    }
+
+    pub fn default(&mut self, body: impl FnOnce(&mut FunctionBuilder)) -> &mut Self {
+        self.default = self.builder.block(body);
+        self
+    }
+}
+
+#[test]
+fn build_compute() {
+    use super::{Interpreter, Resource, ResourceBinding, Resources, Validator};
+    use crate::{ArraySize, Binding, StorageAccess, StorageClass, StructMember};
+    use std::num::NonZeroU32;
+
+    let mut builder = ModuleBuilder::new();
+    let int = builder.scalar_type(ScalarKind::Sint, 4);
+    let values = builder.ty(
+        None,
+        TypeInner::Array {
+            base: int,
+            size: ArraySize::Dynamic,
+            stride: NonZeroU32::new(4),
+        },
+    );
+    let data_ty = builder.ty(
+        Some("Data"),
+        TypeInner::Struct {
+            block: true,
+            members: vec![StructMember {
+                name: Some("values".to_string()),
+                span: None,
+                ty: values,
+                binding: None,
+                interpolation: None,
+            }],
+        },
+    );
+    let data = builder.global(GlobalVariable {
+        name: Some("data".to_string()),
+        class: StorageClass::Storage,
+        binding: Some(Binding::Resource {
+            group: 0,
+            binding: 0,
+        }),
+        ty: data_ty,
+        init: None,
+        interpolation: None,
+        storage_access: StorageAccess::LOAD | StorageAccess::STORE,
+    });
+    let zero = builder.scalar_constant(ScalarValue::Sint(0), 4);
+    let one = builder.scalar_constant(ScalarValue::Sint(1), 4);
+    let ten = builder.scalar_constant(ScalarValue::Sint(10), 4);
+    let twenty = builder.scalar_constant(ScalarValue::Sint(20), 4);
+    let six = builder.scalar_constant(ScalarValue::Uint(6), 4);
+    // types and constants are shared
+    assert_eq!(builder.scalar_type(ScalarKind::Sint, 4), int);
+    assert_eq!(builder.scalar_constant(ScalarValue::Sint(1), 4), one);
+
+    let mut fb = FunctionBuilder::new(
+        "classify",
+        Some(FunctionResult {
+            ty: int,
+            binding: None,
+            interpolation: None,
+        }),
+    );
+    let x = fb.argument(FunctionArgument {
+        name: Some("x".to_string()),
+        ty: int,
+        binding: None,
+        interpolation: None,
+    });
+    fb.switch(x, |switch| {
+        switch
+            .case(0, false, |fb| {
+                let ten = fb.constant(ten);
+                fb.return_(Some(ten));
+            })
+            .case(1, true, |_| {})
+            .case(2, false, |fb| {
+                let twenty = fb.constant(twenty);
+                let sum = fb.binary(BinaryOperator::Add, twenty, x);
+                fb.return_(Some(sum));
+            });
+    });
+    let square = fb.binary(BinaryOperator::Multiply, x, x);
+    fb.return_(Some(square));
+    let classify = builder.function(fb);
+
+    let mut fb = FunctionBuilder::new("main", None);
+    let index = fb.local("index", int, Some(zero));
+    fb.loop_(
+        |fb| {
+            let current = fb.load(index);
+            let limit = fb.constant(six);
+            let current_uint = fb.expression(Expression::As {
+                expr: current,
+                kind: ScalarKind::Uint,
+                convert: true,
+            });
+            let done = fb.binary(BinaryOperator::GreaterEqual, current_uint, limit);
+            fb.if_(done, |fb| fb.break_());
+            let result = fb.call(classify, vec![current], true).unwrap();
+            let data = fb.global(data);
+            let values = fb.access_index(data, 0);
+            let element = fb.access(values, current);
+            fb.store(element, result);
+        },
+        |fb| {
+            let current = fb.load(index);
+            let one = fb.constant(one);
+            let next = fb.binary(BinaryOperator::Add, current, one);
+            fb.store(index, next);
+        },
+    );
+    builder
+        .entry_point("main", ShaderStage::Compute, fb)
+        .workgroup_size = [1, 1, 1];
+    let module = builder.finish();
+
+    Validator::new().validate(&module).unwrap();
+    let binding = ResourceBinding {
+        group: 0,
+        binding: 0,
+    };
+    let mut resources = Resources::default();
+    resources.insert(binding, Resource::Buffer(vec![0; 24]));
+    Interpreter::new(&module)
+        .dispatch("main", [1, 1, 1], &mut resources)
+        .unwrap();
+    let expected = [10i32, 21, 22, 9, 16, 25];
+    let bytes = expected.iter().flat_map(|v| v.to_le_bytes().to_vec());
+    assert_eq!(
+        resources.remove(&binding),
+        Some(Resource::Buffer(bytes.collect()))
+    );
+}
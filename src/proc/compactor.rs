@@ -0,0 +1,519 @@
+/*! Removal of the unused items of a module.
+
+The module is traced from the selected entry points, in two steps:
+1. The functions, global variables, constants and types are marked as used,
+   starting from the entry points and following their handles. Within a function,
+   the statements mark the expressions and local variables they depend on.
+2. Each arena gets a map from its old handles to the new ones, which only counts
+   the used items, in their original order.
+
+The arenas are then rebuilt from the used items, and the handles of everything
+that is kept are adjusted with the maps.
+!*/
+
+use super::visitor::{
+    walk_constant, walk_constant_mut, walk_expression, walk_expression_mut, walk_global_variable,
+    walk_global_variable_mut, walk_statement_mut, walk_type, walk_type_mut, Visitor, VisitorMut,
+};
+use crate::{
+    arena::{Arena, Handle, Range},
+    Block, Constant, EntryPoint, Expression, Function, GlobalVariable, LocalVariable, Module,
+    ShaderStage, Statement, Type,
+};
+use std::marker::PhantomData;
+
+/// Items of an arena that are in use.
+struct Usage<T> {
+    used: Vec<bool>,
+    pending: Vec<Handle<T>>,
+}
+
+impl<T> Usage<T> {
+    fn new(length: usize) -> Self {
+        Usage {
+            used: vec![false; length],
+            pending: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, handle: Handle<T>) {
+        let used = &mut self.used[handle.index()];
+        if !*used {
+            *used = true;
+            self.pending.push(handle);
+        }
+    }
+
+    fn insert_option(&mut self, handle: Option<Handle<T>>) {
+        if let Some(handle) = handle {
+            self.insert(handle);
+        }
+    }
+
+    fn contains(&self, handle: Handle<T>) -> bool {
+        self.used[handle.index()]
+    }
+
+    /// Returns the new handles of the used items, once the others are removed.
+    fn into_map(self) -> HandleMap<T> {
+        let mut count = 0;
+        let mut new_index = Vec::with_capacity(self.used.len() + 1);
+        for &used in self.used.iter() {
+            new_index.push(count);
+            count += used as u32;
+        }
+        new_index.push(count);
+        HandleMap {
+            used: self.used,
+            new_index,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Mapping of the handles of an arena to the compacted one.
+struct HandleMap<T> {
+    used: Vec<bool>,
+    /// Number of the used items before each item, and in total.
+    new_index: Vec<u32>,
+    marker: PhantomData<T>,
+}
+
+impl<T> HandleMap<T> {
+    fn adjust(&self, handle: &mut Handle<T>) {
+        debug_assert!(self.used[handle.index()]);
+        *handle = Handle::from_usize(self.new_index[handle.index()] as usize);
+    }
+
+    fn adjust_option(&self, handle: &mut Option<Handle<T>>) {
+        if let Some(ref mut handle) = *handle {
+            self.adjust(handle);
+        }
+    }
+
+    /// Adjusts a range of handles, returning `None` if nothing is left of it.
+    fn adjust_range(&self, range: &Range<T>) -> Option<Range<T>> {
+        let old = range.index_range();
+        let new = self.new_index[old.start as usize]..self.new_index[old.end as usize];
+        if new.start < new.end {
+            Some(Range::from_index_range(new))
+        } else {
+            None
+        }
+    }
+
+    fn compact(&self, arena: &mut Arena<T>, mut adjust: impl FnMut(&mut T)) {
+        arena.retain_mut(|handle, value| {
+            let used = self.used[handle.index()];
+            if used {
+                adjust(value);
+            }
+            used
+        });
+    }
+
+    /// Returns a new arena with the adjusted copies of the used items.
+    fn extract(&self, arena: &Arena<T>, mut adjust: impl FnMut(&mut T)) -> Arena<T>
+    where
+        T: Clone,
+    {
+        let mut extracted = Arena::new();
+        for (handle, value) in arena.iter() {
+            if self.used[handle.index()] {
+                let mut value = value.clone();
+                adjust(&mut value);
+                extracted.append_with_span(value, arena.get_span(handle));
+            }
+        }
+        extracted
+    }
+}
+
+/// Items of the module in use.
+struct ModuleUsage {
+    types: Usage<Type>,
+    constants: Usage<Constant>,
+    globals: Usage<GlobalVariable>,
+    functions: Usage<Function>,
+}
+
+/// Items of a function in use.
+struct FunctionUsage {
+    expressions: Usage<Expression>,
+    locals: Usage<LocalVariable>,
+}
+
+impl FunctionUsage {
+    fn new(function: &Function) -> Self {
+        FunctionUsage {
+            expressions: Usage::new(function.expressions.len()),
+            locals: Usage::new(function.local_variables.len()),
+        }
+    }
+
+    fn trace(function: &Function, module: &mut ModuleUsage) -> Self {
+        let mut usage = Self::new(function);
+        let mut tracer = FunctionTracer {
+            function: &mut usage,
+            module,
+        };
+        tracer.visit_block(&function.body);
+        // the operands of the used expressions are used as well
+        while let Some(handle) = tracer.function.expressions.pending.pop() {
+            walk_expression(&mut tracer, &function.expressions[handle]);
+        }
+        for argument in function.arguments.iter() {
+            module.types.insert(argument.ty);
+        }
+        if let Some(ref result) = function.result {
+            module.types.insert(result.ty);
+        }
+        for (handle, local) in function.local_variables.iter() {
+            if usage.locals.contains(handle) {
+                module.types.insert(local.ty);
+                module.constants.insert_option(local.init);
+            }
+        }
+        usage
+    }
+}
+
+impl Visitor for ModuleUsage {
+    fn visit_type_handle(&mut self, handle: &Handle<Type>) {
+        self.types.insert(*handle);
+    }
+    fn visit_constant_handle(&mut self, handle: &Handle<Constant>) {
+        self.constants.insert(*handle);
+    }
+    fn visit_global_variable_handle(&mut self, handle: &Handle<GlobalVariable>) {
+        self.globals.insert(*handle);
+    }
+    fn visit_function_handle(&mut self, handle: &Handle<Function>) {
+        self.functions.insert(*handle);
+    }
+}
+
+/// Marks the items used by the statements of a function.
+struct FunctionTracer<'a> {
+    function: &'a mut FunctionUsage,
+    module: &'a mut ModuleUsage,
+}
+
+impl Visitor for FunctionTracer<'_> {
+    fn visit_type_handle(&mut self, handle: &Handle<Type>) {
+        self.module.visit_type_handle(handle);
+    }
+    fn visit_constant_handle(&mut self, handle: &Handle<Constant>) {
+        self.module.visit_constant_handle(handle);
+    }
+    fn visit_global_variable_handle(&mut self, handle: &Handle<GlobalVariable>) {
+        self.module.visit_global_variable_handle(handle);
+    }
+    fn visit_function_handle(&mut self, handle: &Handle<Function>) {
+        self.module.visit_function_handle(handle);
+    }
+    fn visit_local_variable_handle(&mut self, handle: &Handle<LocalVariable>) {
+        self.function.locals.insert(*handle);
+    }
+    fn visit_expression_handle(&mut self, handle: &Handle<Expression>) {
+        self.function.expressions.insert(*handle);
+    }
+    fn visit_expression_range(&mut self, _range: &Range<Expression>) {
+        // emitting an expression doesn't make it used
+    }
+}
+
+/// New handles of the module items.
+struct ModuleMap {
+    types: HandleMap<Type>,
+    constants: HandleMap<Constant>,
+    globals: HandleMap<GlobalVariable>,
+    functions: HandleMap<Function>,
+}
+
+impl ModuleMap {
+    fn adjust_type(&self, ty: &mut Type) {
+        walk_type_mut(&mut &*self, ty);
+    }
+
+    fn adjust_constant(&self, constant: &mut Constant) {
+        walk_constant_mut(&mut &*self, constant);
+    }
+
+    fn adjust_global(&self, global: &mut GlobalVariable) {
+        walk_global_variable_mut(&mut &*self, global);
+    }
+}
+
+impl VisitorMut for &ModuleMap {
+    fn visit_type_handle(&mut self, handle: &mut Handle<Type>) {
+        self.types.adjust(handle);
+    }
+    fn visit_constant_handle(&mut self, handle: &mut Handle<Constant>) {
+        self.constants.adjust(handle);
+    }
+    fn visit_global_variable_handle(&mut self, handle: &mut Handle<GlobalVariable>) {
+        self.globals.adjust(handle);
+    }
+    fn visit_function_handle(&mut self, handle: &mut Handle<Function>) {
+        self.functions.adjust(handle);
+    }
+}
+
+/// New handles of the function items.
+struct FunctionMap {
+    expressions: HandleMap<Expression>,
+    locals: HandleMap<LocalVariable>,
+}
+
+impl FunctionMap {
+    fn adjust(usage: FunctionUsage, function: &mut Function, module: &ModuleMap) {
+        let map = FunctionMap {
+            expressions: usage.expressions.into_map(),
+            locals: usage.locals.into_map(),
+        };
+        for argument in function.arguments.iter_mut() {
+            module.types.adjust(&mut argument.ty);
+        }
+        if let Some(ref mut result) = function.result {
+            module.types.adjust(&mut result.ty);
+        }
+        map.locals.compact(&mut function.local_variables, |local| {
+            module.types.adjust(&mut local.ty);
+            module.constants.adjust_option(&mut local.init);
+        });
+        let mut adjuster = FunctionAdjuster {
+            function: &map,
+            module,
+        };
+        map.expressions
+            .compact(&mut function.expressions, |expression| {
+                walk_expression_mut(&mut adjuster, expression)
+            });
+        adjuster.visit_block(&mut function.body);
+    }
+}
+
+/// Adjusts the handles of the statements of a function.
+struct FunctionAdjuster<'a> {
+    function: &'a FunctionMap,
+    module: &'a ModuleMap,
+}
+
+impl VisitorMut for FunctionAdjuster<'_> {
+    fn visit_block(&mut self, block: &mut Block) {
+        block.retain_mut(|statement| {
+            if let Statement::Emit(ref mut range) = *statement {
+                // the range is dropped if none of its expressions are left
+                match self.function.expressions.adjust_range(range) {
+                    Some(new_range) => *range = new_range,
+                    None => return false,
+                }
+            } else {
+                walk_statement_mut(self, statement);
+            }
+            true
+        });
+    }
+    fn visit_type_handle(&mut self, handle: &mut Handle<Type>) {
+        self.module.types.adjust(handle);
+    }
+    fn visit_constant_handle(&mut self, handle: &mut Handle<Constant>) {
+        self.module.constants.adjust(handle);
+    }
+    fn visit_global_variable_handle(&mut self, handle: &mut Handle<GlobalVariable>) {
+        self.module.globals.adjust(handle);
+    }
+    fn visit_function_handle(&mut self, handle: &mut Handle<Function>) {
+        self.module.functions.adjust(handle);
+    }
+    fn visit_local_variable_handle(&mut self, handle: &mut Handle<LocalVariable>) {
+        self.function.locals.adjust(handle);
+    }
+    fn visit_expression_handle(&mut self, handle: &mut Handle<Expression>) {
+        self.function.expressions.adjust(handle);
+    }
+}
+
+/// Usage of the module items by a set of entry points.
+struct ModuleTrace {
+    map: ModuleMap,
+    entry_points: Vec<FunctionUsage>,
+    /// Usage of each function, or `None` for the unused ones.
+    functions: Vec<Option<FunctionUsage>>,
+}
+
+fn trace_module<'a, I>(module: &Module, entry_points: I) -> ModuleTrace
+where
+    I: IntoIterator<Item = &'a EntryPoint>,
+{
+    let mut usage = ModuleUsage {
+        types: Usage::new(module.types.len()),
+        constants: Usage::new(module.constants.len()),
+        globals: Usage::new(module.global_variables.len()),
+        functions: Usage::new(module.functions.len()),
+    };
+
+    let entry_point_usages = entry_points
+        .into_iter()
+        .map(|entry_point| FunctionUsage::trace(&entry_point.function, &mut usage))
+        .collect::<Vec<_>>();
+    let mut function_usages = Vec::new();
+    function_usages.resize_with(module.functions.len(), || None);
+    while let Some(handle) = usage.functions.pending.pop() {
+        let function_usage = FunctionUsage::trace(&module.functions[handle], &mut usage);
+        function_usages[handle.index()] = Some(function_usage);
+    }
+
+    while let Some(handle) = usage.globals.pending.pop() {
+        walk_global_variable(&mut usage, &module.global_variables[handle]);
+    }
+    // types and constants refer to each other through array sizes
+    loop {
+        if let Some(handle) = usage.types.pending.pop() {
+            walk_type(&mut usage, &module.types[handle]);
+        } else if let Some(handle) = usage.constants.pending.pop() {
+            walk_constant(&mut usage, &module.constants[handle]);
+        } else {
+            break;
+        }
+    }
+
+    ModuleTrace {
+        map: ModuleMap {
+            types: usage.types.into_map(),
+            constants: usage.constants.into_map(),
+            globals: usage.globals.into_map(),
+            functions: usage.functions.into_map(),
+        },
+        entry_points: entry_point_usages,
+        functions: function_usages,
+    }
+}
+
+/// Remove everything from the module that the selected entry points don't use.
+///
+/// The entry points for which `keep_entry_point` returns `false` are removed first.
+/// Then the functions, global variables, constants and types that are not reachable
+/// from the remaining entry points are removed, as well as the local variables and
+/// expressions that no statement depends on. The remaining items keep their order,
+/// and all the handles in the module are adjusted to the new arenas.
+///
+/// Emitting an expression doesn't count as a use of it, so a module without
+/// entry points ends up empty.
+pub fn compact<F>(module: &mut Module, keep_entry_point: F)
+where
+    F: FnMut(&EntryPoint) -> bool,
+{
+    module.entry_points.retain(keep_entry_point);
+    let trace = trace_module(module, &module.entry_points);
+    let map = &trace.map;
+
+    map.types
+        .compact(&mut module.types, |ty| map.adjust_type(ty));
+    map.constants.compact(&mut module.constants, |constant| {
+        map.adjust_constant(constant)
+    });
+    map.globals.compact(&mut module.global_variables, |global| {
+        map.adjust_global(global)
+    });
+
+    let mut function_usages = trace.functions.into_iter();
+    map.functions.compact(&mut module.functions, |function| {
+        // only the used functions are adjusted, in order
+        let usage = function_usages.by_ref().flatten().next().unwrap();
+        FunctionMap::adjust(usage, function, map);
+    });
+    for (entry_point, usage) in module.entry_points.iter_mut().zip(trace.entry_points) {
+        FunctionMap::adjust(usage, &mut entry_point.function, map);
+    }
+}
+
+/// Extract an entry point into a standalone module.
+///
+/// The new module only contains the entry point and the items it uses, in the
+/// same way as [`compact`] would leave them. Returns `None` if the module has
+/// no entry point with this stage and name.
+pub fn extract_entry_point(module: &Module, stage: ShaderStage, name: &str) -> Option<Module> {
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|ep| ep.stage == stage && ep.name == name)?;
+    let trace = trace_module(module, Some(entry_point));
+    let map = &trace.map;
+
+    let mut function_usages = trace.functions.into_iter();
+    let functions = map.functions.extract(&module.functions, |function| {
+        let usage = function_usages.by_ref().flatten().next().unwrap();
+        FunctionMap::adjust(usage, function, map);
+    });
+    let mut entry_point = entry_point.clone();
+    let usage = trace.entry_points.into_iter().next().unwrap();
+    FunctionMap::adjust(usage, &mut entry_point.function, map);
+
+    Some(Module {
+        types: map.types.extract(&module.types, |ty| map.adjust_type(ty)),
+        constants: map
+            .constants
+            .extract(&module.constants, |constant| map.adjust_constant(constant)),
+        global_variables: map
+            .globals
+            .extract(&module.global_variables, |global| map.adjust_global(global)),
+        functions,
+        entry_points: vec![entry_point],
+    })
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+#[test]
+fn compact_unused() {
+    let mut module = crate::front::wgsl::parse_str(
+        "
+        [[block]]
+        struct Data {
+            values: [[stride(4)]] array<f32>;
+        };
+        [[group(0), binding(0)]]
+        var<storage> data: [[access(read_write)]] Data;
+        [[group(0), binding(1)]]
+        var<storage> unused: [[access(read)]] Data;
+        var<private> table: array<u32, 4>;
+
+        fn double(x: f32) -> f32 {
+            const unused_value: f32 = x + 3.0;
+            return x * 2.0;
+        }
+        fn unreachable() -> u32 {
+            return table[1];
+        }
+
+        [[stage(compute), workgroup_size(1)]]
+        fn main() {
+            var i: i32 = 7;
+            data.values[0] = double(data.values[0]);
+        }
+        [[stage(compute), workgroup_size(1)]]
+        fn other() {
+            data.values[1] = f32(unreachable());
+        }
+        ",
+    )
+    .unwrap();
+    let old_types = module.types.len();
+    let old_expressions = module.entry_points[0].function.expressions.len();
+
+    compact(&mut module, |entry_point| entry_point.name == "main");
+    super::Validator::new().validate(&module).unwrap();
+    assert_eq!(module.entry_points.len(), 1);
+    assert_eq!(module.functions.len(), 1);
+    assert_eq!(module.global_variables.len(), 1);
+    assert!(module.types.len() < old_types);
+    // the local variable and its initializer are gone
+    let function = &module.entry_points[0].function;
+    assert!(function.local_variables.is_empty());
+    assert!(function.expressions.len() < old_expressions);
+    // only `x * 2.0` is left in the called function
+    let (_, function) = module.functions.iter().next().unwrap();
+    assert_eq!(function.expressions.len(), 3);
+    assert_eq!(module.constants.len(), 2);
+}
@@ -0,0 +1,1152 @@
+/*! Constant evaluation.
+
+Folds the expressions whose operands are constants into new constants,
+for the front-ends to compute array sizes and initializers, and for
+the `fold_constants` pass to simplify the functions before writing.
+
+The arithmetic is shared with the interpreter, except that the integer
+operations report an overflow instead of wrapping around.
+!*/
+
+use super::{
+    interpreter::{self, Fault, Value},
+    visitor::{walk_block_mut, VisitorMut},
+};
+use crate::{
+    arena::{Arena, Handle},
+    BinaryOperator, Block, Bytes, Constant, ConstantInner, Expression, Function, MathFunction,
+    Module, ScalarValue, ShaderStage, Statement, Type, TypeInner, UnaryOperator, VectorSize,
+};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ConstantEvaluatorError {
+    #[error("Constants cannot access function arguments")]
+    FunctionArg,
+    #[error("Constants cannot access global variables")]
+    GlobalVariable,
+    #[error("Constants cannot access local variables")]
+    LocalVariable,
+    #[error("Cannot get the array length of a non array type")]
+    InvalidArrayLengthArg,
+    #[error("Constants cannot get the array length of a dynamically sized array")]
+    ArrayLengthDynamic,
+    #[error("Constants cannot call functions")]
+    Call,
+    #[error("Constants don't support relational functions")]
+    Relational,
+    #[error("Constants don't support derivative functions")]
+    Derivative,
+    #[error("Constants don't support load expressions")]
+    Load,
+    #[error("Constants don't support image expressions")]
+    ImageExpression,
+    #[error("Constants don't support atomic functions")]
+    Atomic,
+    #[error("Constants don't support subgroup functions")]
+    Subgroup,
+    #[error("Constants don't support ray tracing functions")]
+    RayTracing,
+    #[error("Constant {0:?} can be overridden by the pipeline")]
+    Specializable(Handle<Constant>),
+    #[error("Cannot access the type")]
+    InvalidAccessBase,
+    #[error("Cannot access at the index")]
+    InvalidAccessIndex,
+    #[error("Cannot access with index of type")]
+    InvalidAccessIndexTy,
+    #[error("Cannot cast type")]
+    InvalidCastArg,
+    #[error("Cannot apply the unary op to the argument")]
+    InvalidUnaryOpArg,
+    #[error("Cannot apply the binary op to the arguments")]
+    InvalidBinaryOpArgs,
+    #[error("Cannot select between the arguments")]
+    InvalidSelectArgs,
+    #[error("Cannot apply the math function {0:?} to the arguments")]
+    InvalidMathArgs(MathFunction),
+    #[error("Integer overflow")]
+    Overflow,
+    #[error("Division by zero")]
+    DivisionByZero,
+}
+
+impl ConstantEvaluatorError {
+    fn from_fault(fault: Fault, error: Self) -> Self {
+        match fault {
+            Fault::Overflow => Self::Overflow,
+            Fault::DivisionByZero => Self::DivisionByZero,
+            _ => error,
+        }
+    }
+}
+
+fn vector_size(count: usize) -> Option<VectorSize> {
+    match count {
+        2 => Some(VectorSize::Bi),
+        3 => Some(VectorSize::Tri),
+        4 => Some(VectorSize::Quad),
+        _ => None,
+    }
+}
+
+/// Returns the integer value of a scalar.
+fn integer(value: &ScalarValue) -> Option<i128> {
+    match *value {
+        ScalarValue::Sint(v) => Some(i128::from(v)),
+        ScalarValue::Uint(v) => Some(i128::from(v)),
+        _ => None,
+    }
+}
+
+/// Checks that the result of an integer operation fits its width.
+fn check_binary(
+    op: BinaryOperator,
+    width: Bytes,
+    left: &ScalarValue,
+    right: &ScalarValue,
+) -> Result<(), Fault> {
+    use BinaryOperator as Bo;
+
+    let signed = match *left {
+        ScalarValue::Sint(_) => true,
+        ScalarValue::Uint(_) => false,
+        _ => return Ok(()),
+    };
+    let (a, b) = match (integer(left), integer(right)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Ok(()),
+    };
+    let result = match op {
+        Bo::ShiftLeft | Bo::ShiftRight
+            if b < 0 || b >= i128::from(interpreter::bit_count(width)) =>
+        {
+            return Err(Fault::Overflow)
+        }
+        Bo::Add => a + b,
+        Bo::Subtract => a - b,
+        Bo::Multiply => a.checked_mul(b).ok_or(Fault::Overflow)?,
+        Bo::Divide if b != 0 => a / b,
+        _ => return Ok(()),
+    };
+    let (min, max) = interpreter::int_range(width, signed);
+    if result < min || result > max {
+        Err(Fault::Overflow)
+    } else {
+        Ok(())
+    }
+}
+
+/// Evaluator of the expressions over constant operands.
+///
+/// The results are added to `constants` without a name,
+/// reusing an equal constant if there is one.
+#[derive(Debug)]
+pub struct ConstantEvaluator<'a> {
+    pub types: &'a mut Arena<Type>,
+    pub expressions: &'a Arena<Expression>,
+    pub constants: &'a mut Arena<Constant>,
+}
+
+impl ConstantEvaluator<'_> {
+    pub fn evaluate(
+        &mut self,
+        expr: Handle<Expression>,
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        let expressions = self.expressions;
+        match expressions[expr] {
+            Expression::Constant(constant) => Ok(constant),
+            Expression::AccessIndex { base, index } => {
+                let base = self.evaluate(base)?;
+                self.access(base, index as usize)
+            }
+            Expression::Access { base, index } => {
+                let base = self.evaluate(base)?;
+                let index = self.evaluate(index)?;
+                let index = self.index(index)?;
+                self.access(base, index)
+            }
+            Expression::Compose { ty, ref components } => {
+                let components = components
+                    .iter()
+                    .map(|&component| self.evaluate(component))
+                    .collect::<Result<_, _>>()?;
+                Ok(self.compose(ty, components))
+            }
+            Expression::Unary { op, expr } => {
+                let constant = self.evaluate(expr)?;
+                self.unary_op(op, constant)
+            }
+            Expression::Binary { op, left, right } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.binary_op(op, left, right)
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = self.evaluate(condition)?;
+                let accept = self.evaluate(accept)?;
+                let reject = self.evaluate(reject)?;
+                self.select(condition, accept, reject)
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+            } => {
+                let mut args = vec![self.evaluate(arg)?];
+                for &arg in arg1.iter().chain(arg2.iter()) {
+                    args.push(self.evaluate(arg)?);
+                }
+                self.math(fun, &args)
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let error = ConstantEvaluatorError::InvalidCastArg;
+                let constant = self.evaluate(expr)?;
+                let value = self.value(constant, error.clone())?;
+                let value = interpreter::convert(&value, kind, convert)
+                    .map_err(|fault| ConstantEvaluatorError::from_fault(fault, error.clone()))?;
+                self.append_value(value, error)
+            }
+            Expression::ArrayLength(expr) => {
+                let array = self.evaluate(expr)?;
+
+                match self.constants[array].inner {
+                    ConstantInner::Scalar { .. } => {
+                        Err(ConstantEvaluatorError::InvalidArrayLengthArg)
+                    }
+                    ConstantInner::Composite { ty, .. } => match self.types[ty].inner {
+                        TypeInner::Array { size, .. } => match size {
+                            crate::ArraySize::Constant(constant) => Ok(constant),
+                            crate::ArraySize::Dynamic => {
+                                Err(ConstantEvaluatorError::ArrayLengthDynamic)
+                            }
+                        },
+                        _ => Err(ConstantEvaluatorError::InvalidArrayLengthArg),
+                    },
+                }
+            }
+
+            Expression::Load { .. } => Err(ConstantEvaluatorError::Load),
+            Expression::LocalVariable(_) => Err(ConstantEvaluatorError::LocalVariable),
+            Expression::Derivative { .. } => Err(ConstantEvaluatorError::Derivative),
+            Expression::Relational { .. } => Err(ConstantEvaluatorError::Relational),
+            Expression::Call { .. } => Err(ConstantEvaluatorError::Call),
+            Expression::FunctionArgument(_) => Err(ConstantEvaluatorError::FunctionArg),
+            Expression::GlobalVariable(_) => Err(ConstantEvaluatorError::GlobalVariable),
+            Expression::ImageSample { .. }
+            | Expression::ImageLoad { .. }
+            | Expression::ImageQuery { .. } => Err(ConstantEvaluatorError::ImageExpression),
+            Expression::AtomicResult { .. } => Err(ConstantEvaluatorError::Atomic),
+            Expression::SubgroupResult { .. } => Err(ConstantEvaluatorError::Subgroup),
+            Expression::ReportIntersectionResult => Err(ConstantEvaluatorError::RayTracing),
+        }
+    }
+
+    fn check_specializable(
+        &self,
+        constant: Handle<Constant>,
+    ) -> Result<(), ConstantEvaluatorError> {
+        if self.constants[constant].is_specializable(self.constants) {
+            Err(ConstantEvaluatorError::Specializable(constant))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the value of a scalar, vector or matrix constant.
+    fn value(
+        &self,
+        constant: Handle<Constant>,
+        error: ConstantEvaluatorError,
+    ) -> Result<Value, ConstantEvaluatorError> {
+        self.check_specializable(constant)?;
+        Ok(match self.constants[constant].inner {
+            ConstantInner::Scalar { width, ref value } => Value::Scalar {
+                width,
+                value: value.clone(),
+            },
+            ConstantInner::Composite { ty, ref components } => {
+                match self.types[ty].inner {
+                    TypeInner::Vector { .. } | TypeInner::Matrix { .. } => {}
+                    _ => return Err(error),
+                }
+                Value::Composite(
+                    components
+                        .iter()
+                        .map(|&component| self.value(component, error.clone()))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        })
+    }
+
+    /// Adds a constant for a scalar, vector or matrix value.
+    fn append_value(
+        &mut self,
+        value: Value,
+        error: ConstantEvaluatorError,
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        let inner = match value {
+            Value::Scalar { width, value } => ConstantInner::Scalar { width, value },
+            Value::Composite(components) => {
+                let inner = match components[..] {
+                    [Value::Scalar { width, ref value }, ..] => TypeInner::Vector {
+                        size: vector_size(components.len()).ok_or_else(|| error.clone())?,
+                        kind: value.scalar_kind(),
+                        width,
+                    },
+                    [Value::Composite(ref rows), ..] => match rows[..] {
+                        [Value::Scalar {
+                            width,
+                            value: ScalarValue::Float(_),
+                        }, ..] => TypeInner::Matrix {
+                            columns: vector_size(components.len()).ok_or_else(|| error.clone())?,
+                            rows: vector_size(rows.len()).ok_or_else(|| error.clone())?,
+                            width,
+                        },
+                        _ => return Err(error),
+                    },
+                    _ => return Err(error),
+                };
+                let ty = self.types.fetch_or_append(Type { name: None, inner });
+                let components = components
+                    .into_iter()
+                    .map(|component| self.append_value(component, error.clone()))
+                    .collect::<Result<_, _>>()?;
+                ConstantInner::Composite { ty, components }
+            }
+            Value::Pointer(_) | Value::Resource(_) => return Err(error),
+        };
+        Ok(self.constants.fetch_or_append(Constant {
+            name: None,
+            specialization: None,
+            inner,
+        }))
+    }
+
+    fn access(
+        &mut self,
+        base: Handle<Constant>,
+        index: usize,
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        self.check_specializable(base)?;
+        match self.constants[base].inner {
+            ConstantInner::Scalar { .. } => Err(ConstantEvaluatorError::InvalidAccessBase),
+            ConstantInner::Composite { ty, ref components } => {
+                match self.types[ty].inner {
+                    TypeInner::Vector { .. }
+                    | TypeInner::Matrix { .. }
+                    | TypeInner::Array { .. }
+                    | TypeInner::Struct { .. } => (),
+                    _ => return Err(ConstantEvaluatorError::InvalidAccessBase),
+                }
+
+                components
+                    .get(index)
+                    .copied()
+                    .ok_or(ConstantEvaluatorError::InvalidAccessIndex)
+            }
+        }
+    }
+
+    fn index(&self, constant: Handle<Constant>) -> Result<usize, ConstantEvaluatorError> {
+        self.check_specializable(constant)?;
+        match self.constants[constant].inner {
+            ConstantInner::Scalar {
+                value: ScalarValue::Uint(index),
+                ..
+            } => Ok(index as usize),
+            ConstantInner::Scalar {
+                value: ScalarValue::Sint(index),
+                ..
+            } if index >= 0 => Ok(index as usize),
+            _ => Err(ConstantEvaluatorError::InvalidAccessIndexTy),
+        }
+    }
+
+    fn compose(&mut self, ty: Handle<Type>, components: Vec<Handle<Constant>>) -> Handle<Constant> {
+        // vectors can be built out of smaller vectors
+        let components = if let TypeInner::Vector { .. } = self.types[ty].inner {
+            let mut flattened = Vec::with_capacity(components.len());
+            for component in components {
+                match self.constants[component].inner {
+                    ConstantInner::Composite { ref components, .. } => {
+                        flattened.extend_from_slice(components)
+                    }
+                    ConstantInner::Scalar { .. } => flattened.push(component),
+                }
+            }
+            flattened
+        } else {
+            components
+        };
+
+        self.constants.fetch_or_append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite { ty, components },
+        })
+    }
+
+    fn unary_op(
+        &mut self,
+        op: UnaryOperator,
+        constant: Handle<Constant>,
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        let error = ConstantEvaluatorError::InvalidUnaryOpArg;
+        let value = self.value(constant, error.clone())?;
+        let value = interpreter::map(&[&value], &|width, args| {
+            Ok(match (op, args[0]) {
+                (UnaryOperator::Negate, &ScalarValue::Sint(v)) => {
+                    let (min, _) = interpreter::int_range(width, true);
+                    if i128::from(v) == min {
+                        return Err(Fault::Overflow);
+                    }
+                    ScalarValue::Sint(-v)
+                }
+                (UnaryOperator::Negate, &ScalarValue::Float(v)) => ScalarValue::Float(-v),
+                (UnaryOperator::Not, &ScalarValue::Sint(v)) => ScalarValue::Sint(!v),
+                (UnaryOperator::Not, &ScalarValue::Uint(v)) => ScalarValue::Uint(!v),
+                (UnaryOperator::Not, &ScalarValue::Bool(v)) => ScalarValue::Bool(!v),
+                _ => return Err(Fault::Invalid),
+            })
+        })
+        .map_err(|fault| ConstantEvaluatorError::from_fault(fault, error.clone()))?;
+        self.append_value(value, error)
+    }
+
+    fn binary_op(
+        &mut self,
+        op: BinaryOperator,
+        left: Handle<Constant>,
+        right: Handle<Constant>,
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        let error = ConstantEvaluatorError::InvalidBinaryOpArgs;
+        let left = self.value(left, error.clone())?;
+        let right = self.value(right, error.clone())?;
+        let value = if op == BinaryOperator::Multiply && (left.is_matrix() || right.is_matrix()) {
+            interpreter::binary(op, &left, &right)
+        } else {
+            interpreter::map(&[&left, &right], &|width, args| {
+                check_binary(op, width, args[0], args[1])?;
+                interpreter::binary_scalar(op, width, args[0], args[1])
+            })
+        }
+        .map_err(|fault| ConstantEvaluatorError::from_fault(fault, error.clone()))?;
+        self.append_value(value, error)
+    }
+
+    fn select(
+        &mut self,
+        condition: Handle<Constant>,
+        accept: Handle<Constant>,
+        reject: Handle<Constant>,
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        let error = ConstantEvaluatorError::InvalidSelectArgs;
+        match self.value(condition, error.clone())? {
+            Value::Scalar {
+                value: ScalarValue::Bool(condition),
+                ..
+            } => Ok(if condition { accept } else { reject }),
+            condition => {
+                let accept = self.value(accept, error.clone())?;
+                let reject = self.value(reject, error.clone())?;
+                let value =
+                    interpreter::map(&[&condition, &accept, &reject], &|_, args| match *args[0] {
+                        ScalarValue::Bool(true) => Ok(args[1].clone()),
+                        ScalarValue::Bool(false) => Ok(args[2].clone()),
+                        _ => Err(Fault::Invalid),
+                    })
+                    .map_err(|_| error.clone())?;
+                self.append_value(value, error)
+            }
+        }
+    }
+
+    fn math(
+        &mut self,
+        fun: MathFunction,
+        args: &[Handle<Constant>],
+    ) -> Result<Handle<Constant>, ConstantEvaluatorError> {
+        let error = ConstantEvaluatorError::InvalidMathArgs(fun);
+        if args.len() != fun.argument_count() {
+            return Err(error);
+        }
+        let values = args
+            .iter()
+            .map(|&arg| self.value(arg, error.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let value = interpreter::math(fun, &values[0], values.get(1), values.get(2))
+            .map_err(|fault| ConstantEvaluatorError::from_fault(fault, error.clone()))?;
+        self.append_value(value, error)
+    }
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum FoldError {
+    #[error("Expression {expression:?} of function {handle:?} can't be evaluated")]
+    Function {
+        handle: Handle<Function>,
+        expression: Handle<Expression>,
+        #[source]
+        error: ConstantEvaluatorError,
+    },
+    #[error("Expression {expression:?} of entry point {stage:?} '{name}' can't be evaluated")]
+    EntryPoint {
+        stage: ShaderStage,
+        name: String,
+        expression: Handle<Expression>,
+        #[source]
+        error: ConstantEvaluatorError,
+    },
+}
+
+/// Removes the folded expressions from the `Emit` statements,
+/// since constants are not emitted.
+struct EmitSplitter<'a> {
+    folded: &'a [bool],
+}
+
+impl VisitorMut for EmitSplitter<'_> {
+    fn visit_block(&mut self, block: &mut Block) {
+        let old = std::mem::take(block);
+        let spans = (0..old.len())
+            .map(|index| old.get_span(index))
+            .collect::<Vec<_>>();
+        for (statement, span) in old.into_iter().zip(spans) {
+            let range = match statement {
+                Statement::Emit(ref range) => range.index_range(),
+                _ => {
+                    block.push_with_span(statement, span);
+                    continue;
+                }
+            };
+            let mut start = None;
+            for index in range.clone().chain(Some(range.end)) {
+                let emitted = index < range.end && !self.folded[index as usize];
+                match (start, emitted) {
+                    (None, true) => start = Some(index),
+                    (Some(first), false) => {
+                        let range = crate::arena::Range::from_index_range(first..index);
+                        block.push_with_span(Statement::Emit(range), span);
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        walk_block_mut(self, block);
+    }
+}
+
+fn fold_function(
+    fun: &mut Function,
+    types: &mut Arena<Type>,
+    constants: &mut Arena<Constant>,
+) -> Result<(), (Handle<Expression>, ConstantEvaluatorError)> {
+    let mut folded = vec![false; fun.expressions.len()];
+    for (index, is_folded) in folded.iter_mut().enumerate() {
+        let handle = Handle::from_usize(index);
+        match fun.expressions[handle] {
+            Expression::Access { .. }
+            | Expression::AccessIndex { .. }
+            | Expression::Compose { .. }
+            | Expression::Unary { .. }
+            | Expression::Binary { .. }
+            | Expression::Select { .. }
+            | Expression::Math { .. }
+            | Expression::As { .. } => {}
+            _ => continue,
+        }
+        let mut evaluator = ConstantEvaluator {
+            types,
+            expressions: &fun.expressions,
+            constants,
+        };
+        match evaluator.evaluate(handle) {
+            Ok(constant) => {
+                *fun.expressions.get_mut(handle) = Expression::Constant(constant);
+                *is_folded = true;
+            }
+            Err(error @ ConstantEvaluatorError::Overflow)
+            | Err(error @ ConstantEvaluatorError::DivisionByZero) => return Err((handle, error)),
+            // the other operands are only known at runtime
+            Err(_) => {}
+        }
+    }
+    if folded.contains(&true) {
+        EmitSplitter { folded: &folded }.visit_block(&mut fun.body);
+    }
+    Ok(())
+}
+
+/// Fold the expressions of the functions whose operands are constants.
+///
+/// Every access, composition, operator, select, math function and conversion
+/// over non-specializable constants is replaced with a constant, and removed
+/// from its `Emit` statement. Integer overflows and divisions by zero are errors.
+///
+/// The operands of the folded expressions are left in place, so it's
+/// best to run `compact` afterwards.
+pub fn fold_constants(module: &mut Module) -> Result<(), FoldError> {
+    for (handle, fun) in module.functions.iter_mut() {
+        fold_function(fun, &mut module.types, &mut module.constants).map_err(
+            |(expression, error)| FoldError::Function {
+                handle,
+                expression,
+                error,
+            },
+        )?;
+    }
+    for ep in module.entry_points.iter_mut() {
+        fold_function(&mut ep.function, &mut module.types, &mut module.constants).map_err(
+            |(expression, error)| FoldError::EntryPoint {
+                stage: ep.stage,
+                name: ep.name.clone(),
+                expression,
+                error,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use crate::{
+        Arena, BinaryOperator, Constant, ConstantInner, Expression, MathFunction, ScalarKind,
+        ScalarValue, Type, TypeInner, UnaryOperator, VectorSize,
+    };
+
+    use super::{ConstantEvaluator, ConstantEvaluatorError};
+
+    /// Evaluates a binary operation over two scalars of 32 bits.
+    fn binary(
+        op: BinaryOperator,
+        left: ScalarValue,
+        right: ScalarValue,
+    ) -> Result<ConstantInner, ConstantEvaluatorError> {
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+        let mut operand = |value| {
+            let constant = constants.append(Constant {
+                name: None,
+                specialization: None,
+                inner: ConstantInner::Scalar { width: 4, value },
+            });
+            expressions.append(Expression::Constant(constant))
+        };
+        let left = operand(left);
+        let right = operand(right);
+        let root = expressions.append(Expression::Binary { op, left, right });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut Arena::new(),
+            expressions: &expressions,
+            constants: &mut constants,
+        };
+        let res = evaluator.evaluate(root)?;
+        Ok(constants[res].inner.clone())
+    }
+
+    #[test]
+    fn unary_op() {
+        let mut types = Arena::new();
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let vec_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: VectorSize::Bi,
+                kind: ScalarKind::Sint,
+                width: 4,
+            },
+        });
+
+        let h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(4),
+            },
+        });
+
+        let h1 = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(8),
+            },
+        });
+
+        let vec_h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components: vec![h, h1],
+            },
+        });
+
+        let expr = expressions.append(Expression::Constant(h));
+        let expr1 = expressions.append(Expression::Constant(vec_h));
+
+        let root1 = expressions.append(Expression::Unary {
+            op: UnaryOperator::Negate,
+            expr,
+        });
+
+        let root2 = expressions.append(Expression::Unary {
+            op: UnaryOperator::Not,
+            expr,
+        });
+
+        let root3 = expressions.append(Expression::Unary {
+            op: UnaryOperator::Not,
+            expr: expr1,
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut types,
+            expressions: &expressions,
+            constants: &mut constants,
+        };
+
+        let res1 = evaluator.evaluate(root1).unwrap();
+        let res2 = evaluator.evaluate(root2).unwrap();
+        let res3 = evaluator.evaluate(root3).unwrap();
+
+        assert_eq!(
+            constants[res1].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(-4),
+            },
+        );
+
+        assert_eq!(
+            constants[res2].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(!4),
+            },
+        );
+
+        let res3_inner = &constants[res3].inner;
+
+        assert!(matches!(*res3_inner, ConstantInner::Composite { ty, .. } if ty == vec_ty));
+        if let ConstantInner::Composite { ref components, .. } = *res3_inner {
+            let mut components_iter = components.iter().copied();
+            assert_eq!(
+                constants[components_iter.next().unwrap()].inner,
+                ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Sint(!4),
+                },
+            );
+            assert_eq!(
+                constants[components_iter.next().unwrap()].inner,
+                ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Sint(!8),
+                },
+            );
+            assert!(components_iter.next().is_none());
+        }
+    }
+
+    #[test]
+    fn cast() {
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(4),
+            },
+        });
+
+        let expr = expressions.append(Expression::Constant(h));
+
+        let root = expressions.append(Expression::As {
+            expr,
+            kind: ScalarKind::Bool,
+            convert: true,
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut Arena::new(),
+            expressions: &expressions,
+            constants: &mut constants,
+        };
+
+        let res = evaluator.evaluate(root).unwrap();
+
+        assert_eq!(
+            constants[res].inner,
+            ConstantInner::Scalar {
+                width: crate::BOOL_WIDTH,
+                value: ScalarValue::Bool(true),
+            },
+        );
+    }
+
+    #[test]
+    fn access() {
+        let mut types = Arena::new();
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let matrix_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Matrix {
+                columns: VectorSize::Bi,
+                rows: VectorSize::Tri,
+                width: 4,
+            },
+        });
+
+        let vec_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: VectorSize::Tri,
+                kind: ScalarKind::Float,
+                width: 4,
+            },
+        });
+
+        let mut vec1_components = Vec::with_capacity(3);
+        let mut vec2_components = Vec::with_capacity(3);
+
+        for i in 0..3 {
+            let h = constants.append(Constant {
+                name: None,
+                specialization: None,
+                inner: ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(i as f64),
+                },
+            });
+
+            vec1_components.push(h)
+        }
+
+        for i in 3..6 {
+            let h = constants.append(Constant {
+                name: None,
+                specialization: None,
+                inner: ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(i as f64),
+                },
+            });
+
+            vec2_components.push(h)
+        }
+
+        let vec1 = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components: vec1_components,
+            },
+        });
+
+        let vec2 = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components: vec2_components,
+            },
+        });
+
+        let h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: matrix_ty,
+                components: vec![vec1, vec2],
+            },
+        });
+
+        let base = expressions.append(Expression::Constant(h));
+        let root1 = expressions.append(Expression::AccessIndex { base, index: 1 });
+        let root2 = expressions.append(Expression::AccessIndex {
+            base: root1,
+            index: 2,
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut types,
+            expressions: &expressions,
+            constants: &mut constants,
+        };
+
+        let res1 = evaluator.evaluate(root1).unwrap();
+        let res2 = evaluator.evaluate(root2).unwrap();
+
+        let res1_inner = &constants[res1].inner;
+
+        assert!(matches!(*res1_inner, ConstantInner::Composite { ty, .. } if ty == vec_ty));
+        if let ConstantInner::Composite { ref components, .. } = *res1_inner {
+            let mut components_iter = components.iter().copied();
+            assert_eq!(
+                constants[components_iter.next().unwrap()].inner,
+                ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(3.),
+                },
+            );
+            assert_eq!(
+                constants[components_iter.next().unwrap()].inner,
+                ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(4.),
+                },
+            );
+            assert_eq!(
+                constants[components_iter.next().unwrap()].inner,
+                ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(5.),
+                },
+            );
+            assert!(components_iter.next().is_none());
+        }
+
+        assert_eq!(
+            constants[res2].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Float(5.),
+            },
+        );
+    }
+
+    #[test]
+    fn binary_op() {
+        assert_eq!(
+            binary(
+                BinaryOperator::Add,
+                ScalarValue::Sint(i64::from(i32::MAX) - 1),
+                ScalarValue::Sint(1)
+            ),
+            Ok(ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(i64::from(i32::MAX)),
+            }),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::Less,
+                ScalarValue::Float(1.5),
+                ScalarValue::Float(2.0)
+            ),
+            Ok(ConstantInner::Scalar {
+                width: crate::BOOL_WIDTH,
+                value: ScalarValue::Bool(true),
+            }),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::ShiftLeft,
+                ScalarValue::Uint(1),
+                ScalarValue::Uint(31)
+            ),
+            Ok(ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Uint(1 << 31),
+            }),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::Add,
+                ScalarValue::Sint(i64::from(i32::MAX)),
+                ScalarValue::Sint(1)
+            ),
+            Err(ConstantEvaluatorError::Overflow),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::Subtract,
+                ScalarValue::Uint(0),
+                ScalarValue::Uint(1)
+            ),
+            Err(ConstantEvaluatorError::Overflow),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::ShiftLeft,
+                ScalarValue::Uint(1),
+                ScalarValue::Uint(32)
+            ),
+            Err(ConstantEvaluatorError::Overflow),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::Modulo,
+                ScalarValue::Sint(1),
+                ScalarValue::Sint(0)
+            ),
+            Err(ConstantEvaluatorError::DivisionByZero),
+        );
+        assert_eq!(
+            binary(
+                BinaryOperator::Add,
+                ScalarValue::Bool(true),
+                ScalarValue::Bool(false)
+            ),
+            Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
+        );
+    }
+
+    #[test]
+    fn math_and_select() {
+        let mut types = Arena::new();
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let vec_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: VectorSize::Bi,
+                kind: ScalarKind::Float,
+                width: 4,
+            },
+        });
+        let mut float = |value| {
+            constants.append(Constant {
+                name: None,
+                specialization: None,
+                inner: ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(value),
+                },
+            })
+        };
+        let components = vec![float(3.0), float(4.0)];
+        let vector = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components,
+            },
+        });
+        let condition = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: crate::BOOL_WIDTH,
+                value: ScalarValue::Bool(false),
+            },
+        });
+
+        let vector = expressions.append(Expression::Constant(vector));
+        let condition = expressions.append(Expression::Constant(condition));
+        let length = expressions.append(Expression::Math {
+            fun: MathFunction::Length,
+            arg: vector,
+            arg1: None,
+            arg2: None,
+        });
+        let swizzle = expressions.append(Expression::AccessIndex {
+            base: vector,
+            index: 0,
+        });
+        let select = expressions.append(Expression::Select {
+            condition,
+            accept: length,
+            reject: swizzle,
+        });
+        let invalid = expressions.append(Expression::Math {
+            fun: MathFunction::Cross,
+            arg: vector,
+            arg1: Some(vector),
+            arg2: None,
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut types,
+            expressions: &expressions,
+            constants: &mut constants,
+        };
+
+        let res1 = evaluator.evaluate(length).unwrap();
+        let res2 = evaluator.evaluate(select).unwrap();
+        assert_eq!(
+            evaluator.evaluate(invalid),
+            Err(ConstantEvaluatorError::InvalidMathArgs(MathFunction::Cross)),
+        );
+
+        assert_eq!(
+            constants[res1].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Float(5.),
+            },
+        );
+        assert_eq!(
+            constants[res2].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Float(3.),
+            },
+        );
+    }
+
+    #[cfg(feature = "wgsl-in")]
+    #[test]
+    fn fold_constants() {
+        let source = "
+            [[stage(compute), workgroup_size(1)]]
+            fn main() {
+                const x : f32 = max(2.0 * 3.0, 1.0);
+                const v : vec2<f32> = vec2<f32>(x, -x);
+                const i : i32 = 7 % 0;
+            }
+        ";
+        let mut module = crate::front::wgsl::parse_str(source).unwrap();
+        assert_eq!(
+            super::fold_constants(&mut module),
+            Err(super::FoldError::EntryPoint {
+                stage: crate::ShaderStage::Compute,
+                name: "main".to_string(),
+                expression: crate::Handle::from_usize(9),
+                error: ConstantEvaluatorError::DivisionByZero,
+            }),
+        );
+
+        let mut module = crate::front::wgsl::parse_str(&source.replace("% 0", "% 4")).unwrap();
+        super::fold_constants(&mut module).unwrap();
+        let fun = &module.entry_points[0].function;
+        assert!(fun
+            .expressions
+            .iter()
+            .all(|(_, expr)| matches!(*expr, Expression::Constant(_))));
+        assert!(fun
+            .body
+            .iter()
+            .all(|statement| !matches!(*statement, crate::Statement::Emit(_))));
+        crate::proc::Validator::new().validate(&module).unwrap();
+    }
+}
@@ -0,0 +1,739 @@
+/*! Function inlining.
+
+Calls are replaced by a copy of the called function, in three steps:
+1. The call sites to inline are collected in the order of the statements.
+2. The expression arena of the caller is rebuilt, with the expressions of each
+   called function inserted in place of the call result, so that every expression
+   still comes after its operands.
+3. The statements of the caller are rebuilt, with the lowered body of each called
+   function in place of the `Call` statement.
+
+The `Return` statements of an inlined function become stores to a result local
+variable. When the control flow can't simply fall out of the inlined body,
+a boolean local variable records that the function has returned, and the
+statements that follow are guarded by it.
+!*/
+
+use super::visitor::{
+    remap_range, walk_expression_mut, walk_statement, walk_statement_mut, Visitor, VisitorMut,
+};
+use crate::{
+    arena::{Arena, Handle, Range},
+    Block, Constant, ConstantInner, Expression, Function, LocalVariable, Module, ScalarKind,
+    ScalarValue, Span, Statement, Type, TypeInner,
+};
+use std::mem;
+
+/// Options of the inlining pass.
+#[derive(Clone, Debug, Default)]
+pub struct InlineOptions {
+    /// Only inline the functions that are smaller than this threshold,
+    /// counting both the expressions and the statements.
+    /// All calls are inlined if this is `None`.
+    pub size_threshold: Option<usize>,
+}
+
+impl InlineOptions {
+    fn allows(&self, function: &Function) -> bool {
+        match self.size_threshold {
+            Some(threshold) => {
+                function.expressions.len() + count_statements(&function.body) < threshold
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum InlineError {
+    #[error("Function {0:?} calls itself recursively")]
+    Recursion(Handle<Function>),
+}
+
+fn count_statements(block: &Block) -> usize {
+    struct Counter(usize);
+    impl Visitor for Counter {
+        fn visit_statement(&mut self, statement: &Statement) {
+            self.0 += 1;
+            walk_statement(self, statement);
+        }
+    }
+    let mut counter = Counter(0);
+    counter.visit_block(block);
+    counter.0
+}
+
+/// Returns `true` if the statement is a `Return` or contains one.
+fn may_return(statement: &Statement) -> bool {
+    let block_returns = |block: &[Statement]| block.iter().any(may_return);
+    match *statement {
+        Statement::Return { .. } => true,
+        Statement::Block(ref block) => block_returns(block),
+        Statement::If {
+            ref accept,
+            ref reject,
+            ..
+        } => block_returns(accept) || block_returns(reject),
+        Statement::Switch {
+            ref cases,
+            ref default,
+            ..
+        } => cases.iter().any(|case| block_returns(&case.body)) || block_returns(default),
+        // the continuing block can't return
+        Statement::Loop { ref body, .. } => block_returns(body),
+        _ => false,
+    }
+}
+
+/// Returns the statements of a block together with their spans.
+fn spanned_statements(block: &Block) -> Vec<(&Statement, Span)> {
+    block.span_iter().collect()
+}
+
+fn emit_single(handle: Handle<Expression>) -> Statement {
+    let index = handle.index() as u32;
+    Statement::Emit(Range::from_index_range(index..index + 1))
+}
+
+/// New handles of the expressions and local variables of a function.
+struct Remap {
+    expressions: Vec<Handle<Expression>>,
+    locals: Vec<Handle<LocalVariable>>,
+}
+
+impl Remap {
+    fn adjust(&self, handle: &mut Handle<Expression>) {
+        *handle = self.expressions[handle.index()];
+    }
+
+    /// Adjusts the handles of a statement, but not the ones of its nested blocks.
+    fn adjust_statement(&self, statement: &mut Statement) {
+        walk_statement_mut(&mut &*self, statement);
+    }
+
+    fn adjust_expression(&self, expression: &mut Expression) {
+        walk_expression_mut(&mut &*self, expression);
+    }
+}
+
+impl VisitorMut for &Remap {
+    fn visit_block(&mut self, _block: &mut Block) {
+        // the nested blocks are adjusted when they are rebuilt
+    }
+    fn visit_local_variable_handle(&mut self, handle: &mut Handle<LocalVariable>) {
+        *handle = self.locals[handle.index()];
+    }
+    fn visit_expression_handle(&mut self, handle: &mut Handle<Expression>) {
+        self.adjust(handle);
+    }
+    fn visit_expression_range(&mut self, range: &mut Range<Expression>) {
+        *range = remap_range(range, |handle| self.expressions[handle.index()]);
+    }
+}
+
+/// A call to inline.
+struct CallSite {
+    function: Handle<Function>,
+    arguments: Vec<Handle<Expression>>,
+    result: Option<Handle<Expression>>,
+    in_loop: bool,
+}
+
+/// Visitor collecting the call sites in the order of the statements.
+struct CallSiteCollector<'a> {
+    in_loop: bool,
+    functions: &'a Arena<Function>,
+    options: &'a InlineOptions,
+    sites: Vec<Option<CallSite>>,
+}
+
+impl Visitor for CallSiteCollector<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match *statement {
+            Statement::Loop { .. } => {
+                let in_loop = mem::replace(&mut self.in_loop, true);
+                walk_statement(self, statement);
+                self.in_loop = in_loop;
+            }
+            Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                // the inlined expressions take the place of the result,
+                // so the arguments have to come before it
+                let ordered = match result {
+                    Some(result) => arguments.iter().all(|&argument| argument < result),
+                    None => true,
+                };
+                let site = if ordered && self.options.allows(&self.functions[function]) {
+                    Some(CallSite {
+                        function,
+                        arguments: arguments.clone(),
+                        result,
+                        in_loop: self.in_loop,
+                    })
+                } else {
+                    None
+                };
+                self.sites.push(site);
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+}
+
+/// Collects the call sites of a block, with `None` for the calls that are kept.
+fn collect_call_sites(
+    block: &Block,
+    functions: &Arena<Function>,
+    options: &InlineOptions,
+) -> Vec<Option<CallSite>> {
+    let mut collector = CallSiteCollector {
+        in_loop: false,
+        functions,
+        options,
+        sites: Vec::new(),
+    };
+    collector.visit_block(block);
+    collector.sites
+}
+
+/// Local variable recording that the inlined function has returned.
+#[derive(Clone, Copy)]
+struct ReturnFlag {
+    pointer: Handle<Expression>,
+    true_value: Handle<Expression>,
+}
+
+/// Lowering of the body of an inlined function.
+struct Lowering<'a> {
+    remap: Remap,
+    result_pointer: Option<Handle<Expression>>,
+    flag: Option<ReturnFlag>,
+    expressions: &'a mut Arena<Expression>,
+}
+
+impl Lowering<'_> {
+    /// Returns a guard that only runs `rest` if the function hasn't returned yet.
+    fn guard(&mut self, flag: ReturnFlag, rest: Block, break_on_return: bool) -> Block {
+        let condition = self.expressions.append(Expression::Load {
+            pointer: flag.pointer,
+        });
+        let mut accept = Block::new();
+        if break_on_return {
+            accept.push(Statement::Break);
+        }
+        let mut block = Block::with_capacity(2);
+        block.push(emit_single(condition));
+        block.push(Statement::If {
+            condition,
+            accept,
+            reject: rest,
+        });
+        block
+    }
+
+    fn lower_block(&mut self, block: &Block, break_on_return: bool) -> Block {
+        self.lower_statements(&spanned_statements(block), break_on_return)
+    }
+
+    /// Lowers the statements of a block. Returning from inside a loop of the inlined
+    /// function breaks out of it, as indicated by `break_on_return`.
+    fn lower_statements(
+        &mut self,
+        statements: &[(&Statement, Span)],
+        break_on_return: bool,
+    ) -> Block {
+        let mut lowered = Block::with_capacity(statements.len());
+        for (index, &(statement, span)) in statements.iter().enumerate() {
+            if let Statement::Return { value } = *statement {
+                if let (Some(pointer), Some(mut value)) = (self.result_pointer, value) {
+                    self.remap.adjust(&mut value);
+                    lowered.push_with_span(Statement::Store { pointer, value }, span);
+                }
+                if let Some(flag) = self.flag {
+                    let store = Statement::Store {
+                        pointer: flag.pointer,
+                        value: flag.true_value,
+                    };
+                    lowered.push_with_span(store, span);
+                }
+                if break_on_return {
+                    lowered.push_with_span(Statement::Break, span);
+                }
+                return lowered;
+            }
+
+            let mut statement = statement.clone();
+            self.remap.adjust_statement(&mut statement);
+            match statement {
+                Statement::Block(ref mut block) => {
+                    *block = self.lower_block(block, break_on_return)
+                }
+                Statement::If {
+                    ref mut accept,
+                    ref mut reject,
+                    ..
+                } => {
+                    *accept = self.lower_block(accept, break_on_return);
+                    *reject = self.lower_block(reject, break_on_return);
+                }
+                Statement::Switch {
+                    ref mut cases,
+                    ref mut default,
+                    ..
+                } => {
+                    // `Break` would be ambiguous inside of a switch, so the cases that
+                    // are entered by falling through a returning case are guarded
+                    let mut guard_next = false;
+                    for case in cases.iter_mut() {
+                        let returns = case.body.iter().any(may_return);
+                        let body = self.lower_block(&case.body, false);
+                        case.body = match self.flag {
+                            Some(flag) if guard_next && !body.is_empty() => {
+                                self.guard(flag, body, false)
+                            }
+                            _ => body,
+                        };
+                        guard_next = case.fall_through && (guard_next || returns);
+                    }
+                    let body = self.lower_block(default, false);
+                    *default = match self.flag {
+                        Some(flag) if guard_next && !body.is_empty() => {
+                            self.guard(flag, body, false)
+                        }
+                        _ => body,
+                    };
+                }
+                Statement::Loop {
+                    ref mut body,
+                    ref mut continuing,
+                } => {
+                    *body = self.lower_block(body, true);
+                    *continuing = self.lower_block(continuing, false);
+                }
+                _ => {}
+            }
+            lowered.push_with_span(statement, span);
+
+            let rest = &statements[index + 1..];
+            if let Some(flag) = self.flag {
+                if may_return(statements[index].0) && (!rest.is_empty() || break_on_return) {
+                    let rest = self.lower_statements(rest, break_on_return);
+                    lowered.extend_block(self.guard(flag, rest, break_on_return));
+                    return lowered;
+                }
+            }
+        }
+        lowered
+    }
+}
+
+/// Module items that inlining adds to.
+struct ModuleContext<'a> {
+    types: &'a mut Arena<Type>,
+    constants: &'a mut Arena<Constant>,
+    functions: &'a Arena<Function>,
+}
+
+impl ModuleContext<'_> {
+    fn bool_constant(&mut self, value: bool) -> Handle<Constant> {
+        self.constants.fetch_or_append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: crate::BOOL_WIDTH,
+                value: ScalarValue::Bool(value),
+            },
+        })
+    }
+
+    /// Appends a copy of the called function to the caller, returning the
+    /// lowered body and the handle of the result.
+    fn instantiate(
+        &mut self,
+        site: &CallSite,
+        caller_remap: &Remap,
+        caller: &mut Function,
+        result_span: Span,
+    ) -> (Block, Option<Handle<Expression>>) {
+        let functions = self.functions;
+        let callee = &functions[site.function];
+        let mut remap = Remap {
+            expressions: Vec::with_capacity(callee.expressions.len()),
+            locals: Vec::with_capacity(callee.local_variables.len()),
+        };
+        for (_, local) in callee.local_variables.iter() {
+            remap
+                .locals
+                .push(caller.local_variables.append(local.clone()));
+        }
+        for (handle, expression) in callee.expressions.iter() {
+            let new_handle = match *expression {
+                Expression::FunctionArgument(index) => {
+                    let argument = site.arguments[index as usize];
+                    caller_remap.expressions[argument.index()]
+                }
+                ref other => {
+                    let mut expression = other.clone();
+                    remap.adjust_expression(&mut expression);
+                    let span = callee.expressions.get_span(handle);
+                    caller.expressions.append_with_span(expression, span)
+                }
+            };
+            remap.expressions.push(new_handle);
+        }
+
+        let mut body = Block::new();
+        if site.in_loop {
+            // the copies of the local variables are only initialized once
+            for (handle, local) in callee.local_variables.iter() {
+                if let Some(init) = local.init {
+                    let local = remap.locals[handle.index()];
+                    let pointer = caller.expressions.append(Expression::LocalVariable(local));
+                    let value = caller.expressions.append(Expression::Constant(init));
+                    body.push(Statement::Store { pointer, value });
+                }
+            }
+        }
+
+        let result_pointer = callee.result.as_ref().map(|result| {
+            let local = caller.local_variables.append(LocalVariable {
+                name: callee.name.as_ref().map(|name| format!("{}_result", name)),
+                ty: result.ty,
+                init: None,
+            });
+            caller.expressions.append(Expression::LocalVariable(local))
+        });
+
+        let needs_flag = callee.body.iter().any(|statement| match *statement {
+            Statement::Return { .. } => false,
+            ref other => may_return(other),
+        });
+        let flag = if needs_flag {
+            let ty = self.types.fetch_or_append(Type {
+                name: None,
+                inner: TypeInner::Scalar {
+                    kind: ScalarKind::Bool,
+                    width: crate::BOOL_WIDTH,
+                },
+            });
+            let local = caller.local_variables.append(LocalVariable {
+                name: Some("returned".to_string()),
+                ty,
+                init: None,
+            });
+            let pointer = caller.expressions.append(Expression::LocalVariable(local));
+            let false_value = self.bool_constant(false);
+            let false_value = caller.expressions.append(Expression::Constant(false_value));
+            let true_value = self.bool_constant(true);
+            let true_value = caller.expressions.append(Expression::Constant(true_value));
+            body.push(Statement::Store {
+                pointer,
+                value: false_value,
+            });
+            Some(ReturnFlag {
+                pointer,
+                true_value,
+            })
+        } else {
+            None
+        };
+
+        let mut lowering = Lowering {
+            remap,
+            result_pointer,
+            flag,
+            expressions: &mut caller.expressions,
+        };
+        body.extend_block(lowering.lower_block(&callee.body, false));
+
+        let result = match (site.result, result_pointer) {
+            (Some(_), Some(pointer)) => {
+                let load = caller
+                    .expressions
+                    .append_with_span(Expression::Load { pointer }, result_span);
+                body.push(emit_single(load));
+                Some(load)
+            }
+            _ => None,
+        };
+        (body, result)
+    }
+
+    fn inline_into(&mut self, caller: &mut Function, options: &InlineOptions) {
+        let sites = collect_call_sites(&caller.body, self.functions, options);
+        if sites.iter().all(Option::is_none) {
+            return;
+        }
+
+        // rebuild the expressions, instantiating the calls at their results
+        let mut site_by_result = vec![None; caller.expressions.len()];
+        for (index, site) in sites.iter().enumerate() {
+            if let Some(CallSite {
+                result: Some(result),
+                ..
+            }) = *site
+            {
+                site_by_result[result.index()] = Some(index);
+            }
+        }
+        let old_expressions = mem::take(&mut caller.expressions);
+        let mut remap = Remap {
+            expressions: Vec::with_capacity(old_expressions.len()),
+            locals: caller
+                .local_variables
+                .iter()
+                .map(|(handle, _)| handle)
+                .collect(),
+        };
+        let mut bodies = Vec::with_capacity(sites.len());
+        bodies.resize_with(sites.len(), || None);
+        for (handle, expression) in old_expressions.iter() {
+            let span = old_expressions.get_span(handle);
+            let new_handle = match site_by_result[handle.index()] {
+                Some(index) => {
+                    let site = sites[index].as_ref().unwrap();
+                    let (body, result) = self.instantiate(site, &remap, caller, span);
+                    bodies[index] = Some(body);
+                    result.unwrap()
+                }
+                None => {
+                    let mut expression = expression.clone();
+                    remap.adjust_expression(&mut expression);
+                    caller.expressions.append_with_span(expression, span)
+                }
+            };
+            remap.expressions.push(new_handle);
+        }
+        for (index, site) in sites.iter().enumerate() {
+            if let Some(ref site) = *site {
+                if bodies[index].is_none() {
+                    let (body, _) = self.instantiate(site, &remap, caller, Span::UNDEFINED);
+                    bodies[index] = Some(body);
+                }
+            }
+        }
+
+        let body = mem::take(&mut caller.body);
+        caller.body = rebuild_block(body, &remap, &mut bodies.into_iter());
+    }
+}
+
+/// Rebuilds a block of the caller, replacing the inlined calls by their bodies.
+fn rebuild_block(
+    block: Block,
+    remap: &Remap,
+    bodies: &mut impl Iterator<Item = Option<Block>>,
+) -> Block {
+    let spans = (0..block.len())
+        .map(|index| block.get_span(index))
+        .collect::<Vec<_>>();
+    let mut rebuilt = Block::with_capacity(block.len());
+    for (mut statement, span) in block.into_iter().zip(spans) {
+        match statement {
+            Statement::Block(ref mut block) => {
+                *block = rebuild_block(mem::take(block), remap, bodies)
+            }
+            Statement::If {
+                ref mut accept,
+                ref mut reject,
+                ..
+            } => {
+                *accept = rebuild_block(mem::take(accept), remap, bodies);
+                *reject = rebuild_block(mem::take(reject), remap, bodies);
+            }
+            Statement::Switch {
+                ref mut cases,
+                ref mut default,
+                ..
+            } => {
+                for case in cases.iter_mut() {
+                    case.body = rebuild_block(mem::take(&mut case.body), remap, bodies);
+                }
+                *default = rebuild_block(mem::take(default), remap, bodies);
+            }
+            Statement::Loop {
+                ref mut body,
+                ref mut continuing,
+            } => {
+                *body = rebuild_block(mem::take(body), remap, bodies);
+                *continuing = rebuild_block(mem::take(continuing), remap, bodies);
+            }
+            Statement::Call { .. } => {
+                // the result is emitted in the body, so it has to stay in this block
+                if let Some(body) = bodies.next().unwrap() {
+                    rebuilt.extend_block(body);
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        remap.adjust_statement(&mut statement);
+        rebuilt.push_with_span(statement, span);
+    }
+    rebuilt
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Pending,
+    InProgress,
+    Done,
+}
+
+fn inline_function(
+    module: &mut Module,
+    handle: Handle<Function>,
+    options: &InlineOptions,
+    states: &mut [State],
+) -> Result<(), InlineError> {
+    states[handle.index()] = State::InProgress;
+    let function = &module.functions[handle];
+    let sites = collect_call_sites(&function.body, &module.functions, &InlineOptions::default());
+    for site in sites.into_iter().flatten() {
+        match states[site.function.index()] {
+            State::Pending => inline_function(module, site.function, options, states)?,
+            State::InProgress => return Err(InlineError::Recursion(site.function)),
+            State::Done => {}
+        }
+    }
+
+    let mut function = mem::take(module.functions.get_mut(handle));
+    ModuleContext {
+        types: &mut module.types,
+        constants: &mut module.constants,
+        functions: &module.functions,
+    }
+    .inline_into(&mut function, options);
+    *module.functions.get_mut(handle) = function;
+    states[handle.index()] = State::Done;
+    Ok(())
+}
+
+/// Inline the function calls of the module.
+///
+/// Every function is processed after the functions it calls, so the inlined bodies
+/// are free of the calls that could be inlined themselves. The called functions
+/// are kept in the module: use [`compact`](super::compact) afterwards to remove
+/// the ones that are no longer used.
+pub fn inline_calls(module: &mut Module, options: &InlineOptions) -> Result<(), InlineError> {
+    let mut states = vec![State::Pending; module.functions.len()];
+    for index in 0..module.functions.len() {
+        if states[index] == State::Pending {
+            inline_function(module, Handle::from_usize(index), options, &mut states)?;
+        }
+    }
+
+    let mut entry_points = mem::take(&mut module.entry_points);
+    for entry_point in entry_points.iter_mut() {
+        ModuleContext {
+            types: &mut module.types,
+            constants: &mut module.constants,
+            functions: &module.functions,
+        }
+        .inline_into(&mut entry_point.function, options);
+    }
+    module.entry_points = entry_points;
+    Ok(())
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+#[test]
+fn inline_early_returns() {
+    use super::{Interpreter, Resource, ResourceBinding, Resources};
+
+    fn count_calls(block: &[Statement]) -> usize {
+        block
+            .iter()
+            .map(|statement| match *statement {
+                Statement::Call { .. } => 1,
+                Statement::Block(ref block) => count_calls(block),
+                Statement::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => count_calls(accept) + count_calls(reject),
+                Statement::Loop {
+                    ref body,
+                    ref continuing,
+                } => count_calls(body) + count_calls(continuing),
+                _ => 0,
+            })
+            .sum()
+    }
+    fn run(module: &Module) -> Resource {
+        let binding = ResourceBinding {
+            group: 0,
+            binding: 0,
+        };
+        let mut resources = Resources::default();
+        let values = [0i32, 1, 2, 3, 4, 5, -7, 9];
+        let bytes = values.iter().flat_map(|v| v.to_le_bytes().to_vec());
+        resources.insert(binding, Resource::Buffer(bytes.collect()));
+        Interpreter::new(module)
+            .dispatch("main", [1, 1, 1], &mut resources)
+            .unwrap();
+        resources.remove(&binding).unwrap()
+    }
+
+    let source = "
+        [[block]]
+        struct Data {
+            values: [[stride(4)]] array<i32>;
+        };
+        [[group(0), binding(0)]]
+        var<storage> data: [[access(read_write)]] Data;
+
+        fn classify(x: i32) -> i32 {
+            switch (x) {
+                case 0: { return 10; }
+                case 1: { fallthrough; }
+                case 2: {
+                    if (x == 1) { return 11; }
+                    fallthrough;
+                }
+                default: {}
+            }
+            var n: i32 = 0;
+            loop {
+                if (n * n >= x) { return n; }
+                n = n + 1;
+            }
+            return -1;
+        }
+        fn store(index: i32, value: i32) {
+            if (value < 12) { return; }
+            data.values[index] = value;
+        }
+
+        [[stage(compute), workgroup_size(1)]]
+        fn main() {
+            var i: i32 = 0;
+            loop {
+                if (i >= 8) { break; }
+                store(i, classify(data.values[i]) + classify(i) + 10);
+                i = i + 1;
+            }
+        }
+    ";
+    let original = crate::front::wgsl::parse_str(source).unwrap();
+    let expected = run(&original);
+
+    let mut module = crate::front::wgsl::parse_str(source).unwrap();
+    inline_calls(&mut module, &InlineOptions::default()).unwrap();
+    super::Validator::new().validate(&module).unwrap();
+    assert_eq!(count_calls(&module.entry_points[0].function.body), 0);
+    assert_eq!(run(&module), expected);
+
+    // only the store is small enough
+    let mut module = crate::front::wgsl::parse_str(source).unwrap();
+    let options = InlineOptions {
+        size_threshold: Some(20),
+    };
+    inline_calls(&mut module, &options).unwrap();
+    super::Validator::new().validate(&module).unwrap();
+    assert_eq!(count_calls(&module.entry_points[0].function.body), 2);
+    assert_eq!(run(&module), expected);
+}
@@ -0,0 +1,2436 @@
+/*! Interpreter of the IR.
+
+Executes compute entry points on the CPU, in order to check the semantics
+of the translated shaders without a GPU.
+
+The invocations of a workgroup are executed one after another, each of them
+running until it finishes or reaches a workgroup barrier, at which point the
+next one takes over. Subgroups contain a single invocation.
+
+Uniform and storage buffers are provided by the host as bytes, laid out
+in the same way as the SPIR-V backend declares them. Images are provided
+as arrays of texels, and only support loads, stores and queries.
+!*/
+
+use super::{Layouter, ResourceBinding};
+use crate::{
+    arena::Handle, BinaryOperator, Bytes, Expression, FastHashMap, GlobalVariable, MathFunction,
+    ScalarKind, ScalarValue, Statement, StorageClass, TypeInner, VectorSize,
+};
+use thiserror::Error;
+
+/// Image provided by the host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    /// Width, height and depth of the image.
+    pub size: [u32; 3],
+    /// Number of array layers.
+    pub layers: u32,
+    /// Texels ordered by layer, then Z, Y and X coordinates.
+    /// Only as many components as the format has are meaningful.
+    pub texels: Vec<[f64; 4]>,
+}
+
+impl Image {
+    /// Creates an image with all the texels set to zero.
+    pub fn new(size: [u32; 3], layers: u32) -> Self {
+        let count = size.iter().map(|&s| s as usize).product::<usize>() * layers as usize;
+        Image {
+            size,
+            layers,
+            texels: vec![[0.0; 4]; count],
+        }
+    }
+
+    fn texel_index(&self, coordinate: [i64; 3], layer: i64) -> Option<usize> {
+        let in_bounds = coordinate
+            .iter()
+            .zip(self.size.iter())
+            .all(|(&c, &size)| c >= 0 && c < i64::from(size));
+        if !in_bounds || layer < 0 || layer >= i64::from(self.layers) {
+            return None;
+        }
+        let [width, height, depth] = self.size;
+        let index = ((layer as u64 * u64::from(depth) + coordinate[2] as u64) * u64::from(height)
+            + coordinate[1] as u64)
+            * u64::from(width)
+            + coordinate[0] as u64;
+        Some(index as usize)
+    }
+}
+
+/// Resource provided by the host.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resource {
+    Buffer(Vec<u8>),
+    Image(Image),
+}
+
+pub type Resources = FastHashMap<ResourceBinding, Resource>;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum InterpreterError {
+    #[error("compute entry point '{0}' is not found")]
+    MissingEntryPoint(String),
+    #[error("resource {0:?} is not provided")]
+    MissingResource(ResourceBinding),
+    #[error("resource {0:?} doesn't match the type of its variable")]
+    InvalidResource(ResourceBinding),
+    #[error("access at offset {1} is out of the bounds of buffer {0:?}")]
+    BufferOverrun(ResourceBinding, usize),
+    #[error("index {1} is out of bounds in expression {0:?}")]
+    IndexOutOfBounds(Handle<Expression>, i64),
+    #[error("expression {0:?} has invalid operands")]
+    InvalidExpression(Handle<Expression>),
+    #[error("integer division by zero in expression {0:?}")]
+    DivisionByZero(Handle<Expression>),
+    #[error("{0} is not supported by the interpreter")]
+    Unsupported(String),
+    #[error("the limit of {0} executed statements is exceeded")]
+    StepLimitExceeded(u64),
+}
+
+/// Failure of an operation, which is turned into an error
+/// once the expression it happened in is known.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Fault {
+    Invalid,
+    DivisionByZero,
+    /// Integer result out of the range of its width, only reported
+    /// by the constant evaluator, since the interpreter wraps around.
+    Overflow,
+    IndexOutOfBounds(i64),
+    BufferOverrun(usize),
+}
+
+impl Fault {
+    fn into_error(self, expr: Handle<Expression>) -> InterpreterError {
+        match self {
+            Fault::Invalid | Fault::Overflow | Fault::BufferOverrun(_) => {
+                InterpreterError::InvalidExpression(expr)
+            }
+            Fault::DivisionByZero => InterpreterError::DivisionByZero(expr),
+            Fault::IndexOutOfBounds(index) => InterpreterError::IndexOutOfBounds(expr, index),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Root {
+    Local {
+        frame: usize,
+        variable: Handle<crate::LocalVariable>,
+    },
+    Global(Handle<GlobalVariable>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Pointer {
+    root: Root,
+    /// Indices of the components, from the root.
+    path: Vec<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Value {
+    Scalar {
+        width: Bytes,
+        value: ScalarValue,
+    },
+    /// Vector, matrix (as columns), array or structure.
+    Composite(Vec<Value>),
+    Pointer(Pointer),
+    /// Global variable in the `Handle` storage class.
+    Resource(Handle<GlobalVariable>),
+}
+
+impl Value {
+    fn bool(value: bool) -> Self {
+        Value::Scalar {
+            width: crate::BOOL_WIDTH,
+            value: ScalarValue::Bool(value),
+        }
+    }
+
+    fn uint(value: u32) -> Self {
+        Value::Scalar {
+            width: 4,
+            value: ScalarValue::Uint(u64::from(value)),
+        }
+    }
+
+    fn sint(value: i64) -> Self {
+        Value::Scalar {
+            width: 4,
+            value: ScalarValue::Sint(value),
+        }
+    }
+
+    fn float(width: Bytes, value: f64) -> Self {
+        Value::Scalar {
+            width,
+            value: ScalarValue::Float(round_float(width, value)),
+        }
+    }
+
+    fn zero(kind: ScalarKind, width: Bytes) -> Self {
+        Value::Scalar {
+            width,
+            value: match kind {
+                ScalarKind::Sint => ScalarValue::Sint(0),
+                ScalarKind::Uint => ScalarValue::Uint(0),
+                ScalarKind::Float => ScalarValue::Float(0.0),
+                ScalarKind::Bool => ScalarValue::Bool(false),
+            },
+        }
+    }
+
+    fn as_index(&self) -> Option<i64> {
+        match *self {
+            Value::Scalar {
+                value: ScalarValue::Sint(value),
+                ..
+            } => Some(value),
+            Value::Scalar {
+                value: ScalarValue::Uint(value),
+                ..
+            } => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Scalar {
+                value: ScalarValue::Bool(value),
+                ..
+            } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(super) fn is_matrix(&self) -> bool {
+        match *self {
+            Value::Composite(ref columns) => match columns.first() {
+                Some(&Value::Composite(_)) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns the float components of a scalar or a vector.
+    fn floats(&self) -> Result<(Bytes, Vec<f64>), Fault> {
+        let components = match *self {
+            Value::Composite(ref components) => &components[..],
+            ref scalar => std::slice::from_ref(scalar),
+        };
+        let mut width = 4;
+        let mut floats = Vec::with_capacity(components.len());
+        for component in components {
+            match *component {
+                Value::Scalar {
+                    width: w,
+                    value: ScalarValue::Float(value),
+                } => {
+                    width = w;
+                    floats.push(value);
+                }
+                _ => return Err(Fault::Invalid),
+            }
+        }
+        Ok((width, floats))
+    }
+
+    fn from_floats(width: Bytes, floats: &[f64], vector: bool) -> Self {
+        if vector {
+            Value::Composite(floats.iter().map(|&f| Value::float(width, f)).collect())
+        } else {
+            Value::float(width, floats[0])
+        }
+    }
+
+    /// Returns the columns of a matrix.
+    fn columns(&self) -> Result<(Bytes, Vec<Vec<f64>>), Fault> {
+        let mut width = 4;
+        let columns = match *self {
+            Value::Composite(ref columns) => columns
+                .iter()
+                .map(|column| {
+                    let (w, floats) = column.floats()?;
+                    width = w;
+                    Ok(floats)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(Fault::Invalid),
+        };
+        Ok((width, columns))
+    }
+
+    fn from_columns(width: Bytes, columns: &[Vec<f64>]) -> Self {
+        Value::Composite(
+            columns
+                .iter()
+                .map(|column| Value::from_floats(width, column, true))
+                .collect(),
+        )
+    }
+}
+
+fn round_float(width: Bytes, value: f64) -> f64 {
+    match width {
+        2 => super::f16_from_bits(super::f16_to_bits(value)),
+        4 => f64::from(value as f32),
+        _ => value,
+    }
+}
+
+pub(super) fn bit_count(width: Bytes) -> u32 {
+    u32::from(width) * 8
+}
+
+/// Returns the range of the integers of the given width.
+pub(super) fn int_range(width: Bytes, signed: bool) -> (i128, i128) {
+    let bits = bit_count(width);
+    if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    }
+}
+
+/// Wraps the value around the range of its width.
+fn wrap(width: Bytes, value: ScalarValue) -> ScalarValue {
+    let bits = bit_count(width);
+    match value {
+        ScalarValue::Sint(v) if bits < 64 => {
+            let shift = 64 - bits;
+            ScalarValue::Sint(v << shift >> shift)
+        }
+        ScalarValue::Uint(v) if bits < 64 => ScalarValue::Uint(v & ((1 << bits) - 1)),
+        ScalarValue::Float(v) => ScalarValue::Float(round_float(width, v)),
+        other => other,
+    }
+}
+
+fn to_bits(width: Bytes, value: &ScalarValue) -> u64 {
+    let bits = match *value {
+        ScalarValue::Sint(v) => v as u64,
+        ScalarValue::Uint(v) => v,
+        ScalarValue::Float(v) => match width {
+            2 => u64::from(super::f16_to_bits(v)),
+            4 => u64::from((v as f32).to_bits()),
+            _ => v.to_bits(),
+        },
+        ScalarValue::Bool(v) => v as u64,
+    };
+    match bit_count(width) {
+        64 => bits,
+        count => bits & ((1 << count) - 1),
+    }
+}
+
+fn from_bits(kind: ScalarKind, width: Bytes, bits: u64) -> ScalarValue {
+    match kind {
+        ScalarKind::Sint => wrap(width, ScalarValue::Sint(bits as i64)),
+        ScalarKind::Uint => ScalarValue::Uint(bits),
+        ScalarKind::Float => ScalarValue::Float(match width {
+            2 => super::f16_from_bits(bits as u16),
+            4 => f64::from(f32::from_bits(bits as u32)),
+            _ => f64::from_bits(bits),
+        }),
+        ScalarKind::Bool => ScalarValue::Bool(bits != 0),
+    }
+}
+
+/// Function of the scalar components of the operands, with their width.
+type ComponentFunction<'f> = dyn Fn(Bytes, &[&ScalarValue]) -> Result<ScalarValue, Fault> + 'f;
+
+/// Applies a function to the components of the arguments,
+/// splatting the scalar arguments to the size of the composite ones.
+pub(super) fn map(args: &[&Value], fun: &ComponentFunction) -> Result<Value, Fault> {
+    let size = args.iter().find_map(|arg| match **arg {
+        Value::Composite(ref components) => Some(components.len()),
+        _ => None,
+    });
+    match size {
+        Some(size) => {
+            let mut components = Vec::with_capacity(size);
+            for index in 0..size {
+                let component_args = args
+                    .iter()
+                    .map(|arg| match **arg {
+                        Value::Composite(ref components) => {
+                            components.get(index).ok_or(Fault::Invalid)
+                        }
+                        ref scalar => Ok(scalar),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                components.push(map(&component_args, fun)?);
+            }
+            Ok(Value::Composite(components))
+        }
+        None => {
+            let mut width = 4;
+            let mut scalars = Vec::with_capacity(args.len());
+            for arg in args {
+                match **arg {
+                    Value::Scalar {
+                        width: w,
+                        ref value,
+                    } => {
+                        if scalars.is_empty() {
+                            width = w;
+                        }
+                        scalars.push(value);
+                    }
+                    _ => return Err(Fault::Invalid),
+                }
+            }
+            let value = fun(width, &scalars)?;
+            let width = match (scalars.first(), &value) {
+                (_, &ScalarValue::Bool(_)) => crate::BOOL_WIDTH,
+                (Some(&&ScalarValue::Bool(_)), _) => 4,
+                _ => width,
+            };
+            Ok(Value::Scalar {
+                width,
+                value: wrap(width, value),
+            })
+        }
+    }
+}
+
+fn map_floats(value: &Value, fun: fn(f64) -> f64) -> Result<Value, Fault> {
+    map(&[value], &|_, args| match *args[0] {
+        ScalarValue::Float(x) => Ok(ScalarValue::Float(fun(x))),
+        _ => Err(Fault::Invalid),
+    })
+}
+
+fn map_floats2(a: &Value, b: &Value, fun: fn(f64, f64) -> f64) -> Result<Value, Fault> {
+    map(&[a, b], &|_, args| match (args[0], args[1]) {
+        (&ScalarValue::Float(x), &ScalarValue::Float(y)) => Ok(ScalarValue::Float(fun(x, y))),
+        _ => Err(Fault::Invalid),
+    })
+}
+
+fn map_floats3(
+    a: &Value,
+    b: &Value,
+    c: &Value,
+    fun: fn(f64, f64, f64) -> f64,
+) -> Result<Value, Fault> {
+    map(&[a, b, c], &|_, args| match (args[0], args[1], args[2]) {
+        (&ScalarValue::Float(x), &ScalarValue::Float(y), &ScalarValue::Float(z)) => {
+            Ok(ScalarValue::Float(fun(x, y, z)))
+        }
+        _ => Err(Fault::Invalid),
+    })
+}
+
+pub(super) fn binary_scalar(
+    op: BinaryOperator,
+    width: Bytes,
+    left: &ScalarValue,
+    right: &ScalarValue,
+) -> Result<ScalarValue, Fault> {
+    use BinaryOperator as Bo;
+    use ScalarValue as Sv;
+
+    if let Bo::ShiftLeft | Bo::ShiftRight = op {
+        let amount = match *right {
+            Sv::Sint(v) => v as u32,
+            Sv::Uint(v) => v as u32,
+            _ => return Err(Fault::Invalid),
+        } % bit_count(width);
+        // the values are kept sign-extended, so the right shift of signed integers is arithmetic
+        return Ok(match (left, op) {
+            (&Sv::Sint(v), Bo::ShiftLeft) => Sv::Sint(v.wrapping_shl(amount)),
+            (&Sv::Sint(v), _) => Sv::Sint(v >> amount),
+            (&Sv::Uint(v), Bo::ShiftLeft) => Sv::Uint(v.wrapping_shl(amount)),
+            (&Sv::Uint(v), _) => Sv::Uint(v >> amount),
+            _ => return Err(Fault::Invalid),
+        });
+    }
+
+    Ok(match (left, right) {
+        (&Sv::Sint(a), &Sv::Sint(b)) => match op {
+            Bo::Add => Sv::Sint(a.wrapping_add(b)),
+            Bo::Subtract => Sv::Sint(a.wrapping_sub(b)),
+            Bo::Multiply => Sv::Sint(a.wrapping_mul(b)),
+            Bo::Divide | Bo::Modulo if b == 0 => return Err(Fault::DivisionByZero),
+            Bo::Divide => Sv::Sint(a.wrapping_div(b)),
+            Bo::Modulo => Sv::Sint(a.wrapping_rem(b)),
+            Bo::Equal => Sv::Bool(a == b),
+            Bo::NotEqual => Sv::Bool(a != b),
+            Bo::Less => Sv::Bool(a < b),
+            Bo::LessEqual => Sv::Bool(a <= b),
+            Bo::Greater => Sv::Bool(a > b),
+            Bo::GreaterEqual => Sv::Bool(a >= b),
+            Bo::And => Sv::Sint(a & b),
+            Bo::ExclusiveOr => Sv::Sint(a ^ b),
+            Bo::InclusiveOr => Sv::Sint(a | b),
+            _ => return Err(Fault::Invalid),
+        },
+        (&Sv::Uint(a), &Sv::Uint(b)) => match op {
+            Bo::Add => Sv::Uint(a.wrapping_add(b)),
+            Bo::Subtract => Sv::Uint(a.wrapping_sub(b)),
+            Bo::Multiply => Sv::Uint(a.wrapping_mul(b)),
+            Bo::Divide | Bo::Modulo if b == 0 => return Err(Fault::DivisionByZero),
+            Bo::Divide => Sv::Uint(a / b),
+            Bo::Modulo => Sv::Uint(a % b),
+            Bo::Equal => Sv::Bool(a == b),
+            Bo::NotEqual => Sv::Bool(a != b),
+            Bo::Less => Sv::Bool(a < b),
+            Bo::LessEqual => Sv::Bool(a <= b),
+            Bo::Greater => Sv::Bool(a > b),
+            Bo::GreaterEqual => Sv::Bool(a >= b),
+            Bo::And => Sv::Uint(a & b),
+            Bo::ExclusiveOr => Sv::Uint(a ^ b),
+            Bo::InclusiveOr => Sv::Uint(a | b),
+            _ => return Err(Fault::Invalid),
+        },
+        (&Sv::Float(a), &Sv::Float(b)) => match op {
+            Bo::Add => Sv::Float(a + b),
+            Bo::Subtract => Sv::Float(a - b),
+            Bo::Multiply => Sv::Float(a * b),
+            Bo::Divide => Sv::Float(a / b),
+            Bo::Modulo => Sv::Float(a % b),
+            Bo::Equal => Sv::Bool(a == b),
+            Bo::NotEqual => Sv::Bool(a != b),
+            Bo::Less => Sv::Bool(a < b),
+            Bo::LessEqual => Sv::Bool(a <= b),
+            Bo::Greater => Sv::Bool(a > b),
+            Bo::GreaterEqual => Sv::Bool(a >= b),
+            _ => return Err(Fault::Invalid),
+        },
+        // integer literals may have a different signedness than the other operand
+        (&Sv::Sint(_), &Sv::Uint(b)) => {
+            let right = wrap(width, Sv::Sint(b as i64));
+            return binary_scalar(op, width, left, &right);
+        }
+        (&Sv::Uint(_), &Sv::Sint(b)) => {
+            let right = wrap(width, Sv::Uint(b as u64));
+            return binary_scalar(op, width, left, &right);
+        }
+        (&Sv::Bool(a), &Sv::Bool(b)) => match op {
+            Bo::Equal => Sv::Bool(a == b),
+            Bo::NotEqual | Bo::ExclusiveOr => Sv::Bool(a != b),
+            Bo::And | Bo::LogicalAnd => Sv::Bool(a && b),
+            Bo::InclusiveOr | Bo::LogicalOr => Sv::Bool(a || b),
+            _ => return Err(Fault::Invalid),
+        },
+        _ => return Err(Fault::Invalid),
+    })
+}
+
+pub(super) fn binary(op: BinaryOperator, left: &Value, right: &Value) -> Result<Value, Fault> {
+    if op == BinaryOperator::Multiply {
+        match (left.is_matrix(), right.is_matrix(), left, right) {
+            (true, true, _, Value::Composite(columns)) => {
+                return columns
+                    .iter()
+                    .map(|column| matrix_times_vector(left, column))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Composite);
+            }
+            (true, false, _, Value::Composite(_)) => return matrix_times_vector(left, right),
+            (false, true, Value::Composite(_), Value::Composite(columns)) => {
+                return columns
+                    .iter()
+                    .map(|column| dot(left, column))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Composite);
+            }
+            _ => {}
+        }
+    }
+    map(&[left, right], &|width, args| {
+        binary_scalar(op, width, args[0], args[1])
+    })
+}
+
+fn matrix_times_vector(matrix: &Value, vector: &Value) -> Result<Value, Fault> {
+    match (matrix, vector) {
+        (Value::Composite(columns), Value::Composite(components))
+            if columns.len() == components.len() =>
+        {
+            let mut sum = binary(BinaryOperator::Multiply, &columns[0], &components[0])?;
+            for (column, component) in columns.iter().zip(components.iter()).skip(1) {
+                let product = binary(BinaryOperator::Multiply, column, component)?;
+                sum = binary(BinaryOperator::Add, &sum, &product)?;
+            }
+            Ok(sum)
+        }
+        _ => Err(Fault::Invalid),
+    }
+}
+
+fn dot(left: &Value, right: &Value) -> Result<Value, Fault> {
+    match binary(BinaryOperator::Multiply, left, right)? {
+        Value::Composite(products) => {
+            let mut iter = products.into_iter();
+            let first = iter.next().ok_or(Fault::Invalid)?;
+            iter.try_fold(first, |sum, product| {
+                binary(BinaryOperator::Add, &sum, &product)
+            })
+        }
+        _ => Err(Fault::Invalid),
+    }
+}
+
+fn length(value: &Value) -> Result<f64, Fault> {
+    let (_, floats) = value.floats()?;
+    Ok(floats.iter().map(|f| f * f).sum::<f64>().sqrt())
+}
+
+/// Rounds half-way cases to the nearest even integer.
+fn round_even(x: f64) -> f64 {
+    if (x - x.trunc()).abs() == 0.5 {
+        2.0 * (x / 2.0).round()
+    } else {
+        x.round()
+    }
+}
+
+/// Returns the exponent of `frexp`, for which the mantissa is in [0.5, 1).
+fn frexp_exponent(x: f64) -> i32 {
+    if x == 0.0 || !x.is_finite() {
+        0
+    } else {
+        x.abs().log2().floor() as i32 + 1
+    }
+}
+
+/// Removes a column and a row from a matrix.
+fn minor(columns: &[Vec<f64>], column: usize, row: usize) -> Vec<Vec<f64>> {
+    columns
+        .iter()
+        .enumerate()
+        .filter(|&(c, _)| c != column)
+        .map(|(_, values)| {
+            values
+                .iter()
+                .enumerate()
+                .filter(|&(r, _)| r != row)
+                .map(|(_, &value)| value)
+                .collect()
+        })
+        .collect()
+}
+
+fn determinant(columns: &[Vec<f64>]) -> f64 {
+    if columns.len() == 1 {
+        return columns[0][0];
+    }
+    (0..columns.len())
+        .map(|row| {
+            let sign = if row % 2 == 0 { 1.0 } else { -1.0 };
+            sign * columns[0][row] * determinant(&minor(columns, 0, row))
+        })
+        .sum()
+}
+
+pub(super) fn math(
+    fun: MathFunction,
+    arg: &Value,
+    arg1: Option<&Value>,
+    arg2: Option<&Value>,
+) -> Result<Value, Fault> {
+    use MathFunction as Mf;
+    use ScalarValue as Sv;
+
+    let arg1 = || arg1.ok_or(Fault::Invalid);
+    let arg2 = || arg2.ok_or(Fault::Invalid);
+    Ok(match fun {
+        Mf::Abs => map(&[arg], &|_, args| {
+            Ok(match *args[0] {
+                Sv::Sint(v) => Sv::Sint(v.wrapping_abs()),
+                Sv::Float(v) => Sv::Float(v.abs()),
+                ref other => other.clone(),
+            })
+        })?,
+        Mf::Min | Mf::Max => map(&[arg, arg1()?], &|_, args| {
+            let less = match (args[0], args[1]) {
+                (&Sv::Sint(a), &Sv::Sint(b)) => a < b,
+                (&Sv::Uint(a), &Sv::Uint(b)) => a < b,
+                (&Sv::Float(a), &Sv::Float(b)) => a < b || b.is_nan(),
+                _ => return Err(Fault::Invalid),
+            };
+            let first = less == (fun == Mf::Min);
+            Ok(if first { args[0] } else { args[1] }.clone())
+        })?,
+        Mf::Clamp => {
+            let low = math(Mf::Max, arg, Some(arg1()?), None)?;
+            math(Mf::Min, &low, Some(arg2()?), None)?
+        }
+        Mf::Cos => map_floats(arg, f64::cos)?,
+        Mf::Cosh => map_floats(arg, f64::cosh)?,
+        Mf::Sin => map_floats(arg, f64::sin)?,
+        Mf::Sinh => map_floats(arg, f64::sinh)?,
+        Mf::Tan => map_floats(arg, f64::tan)?,
+        Mf::Tanh => map_floats(arg, f64::tanh)?,
+        Mf::Acos => map_floats(arg, f64::acos)?,
+        Mf::Asin => map_floats(arg, f64::asin)?,
+        Mf::Atan => map_floats(arg, f64::atan)?,
+        Mf::Atan2 => map_floats2(arg, arg1()?, f64::atan2)?,
+        Mf::Ceil => map_floats(arg, f64::ceil)?,
+        Mf::Floor => map_floats(arg, f64::floor)?,
+        Mf::Round => map_floats(arg, round_even)?,
+        Mf::Fract => map_floats(arg, |x| x - x.floor())?,
+        Mf::Trunc => map_floats(arg, f64::trunc)?,
+        // these store a part of the result through a pointer, see `Executor::math`
+        Mf::Modf | Mf::Frexp => return Err(Fault::Invalid),
+        Mf::Ldexp => map(&[arg, arg1()?], &|_, args| match (args[0], args[1]) {
+            (&Sv::Float(x), &Sv::Sint(e)) => Ok(Sv::Float(x * 2f64.powi(e as i32))),
+            (&Sv::Float(x), &Sv::Uint(e)) => Ok(Sv::Float(x * 2f64.powi(e as i32))),
+            _ => Err(Fault::Invalid),
+        })?,
+        Mf::Exp => map_floats(arg, f64::exp)?,
+        Mf::Exp2 => map_floats(arg, f64::exp2)?,
+        Mf::Log => map_floats(arg, f64::ln)?,
+        Mf::Log2 => map_floats(arg, f64::log2)?,
+        Mf::Pow => map_floats2(arg, arg1()?, f64::powf)?,
+        Mf::Dot => dot(arg, arg1()?)?,
+        Mf::Outer => match *arg1()? {
+            Value::Composite(ref components) => Value::Composite(
+                components
+                    .iter()
+                    .map(|component| binary(BinaryOperator::Multiply, arg, component))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(Fault::Invalid),
+        },
+        Mf::Cross => {
+            let (width, a) = arg.floats()?;
+            let (_, b) = arg1()?.floats()?;
+            if a.len() != 3 || b.len() != 3 {
+                return Err(Fault::Invalid);
+            }
+            let cross = [
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ];
+            Value::from_floats(width, &cross, true)
+        }
+        Mf::Distance => {
+            let difference = binary(BinaryOperator::Subtract, arg, arg1()?)?;
+            let (width, _) = arg.floats()?;
+            Value::float(width, length(&difference)?)
+        }
+        Mf::Length => {
+            let (width, _) = arg.floats()?;
+            Value::float(width, length(arg)?)
+        }
+        Mf::Normalize => {
+            let (width, floats) = arg.floats()?;
+            let length = length(arg)?;
+            let normalized = floats.iter().map(|f| f / length).collect::<Vec<_>>();
+            Value::from_floats(width, &normalized, true)
+        }
+        Mf::FaceForward => {
+            let reference = dot(arg2()?, arg1()?)?.floats()?.1[0];
+            if reference < 0.0 {
+                arg.clone()
+            } else {
+                map_floats(arg, |x| -x)?
+            }
+        }
+        Mf::Reflect => {
+            let normal = arg1()?;
+            let (width, _) = arg.floats()?;
+            let factor = 2.0 * dot(normal, arg)?.floats()?.1[0];
+            let scaled = binary(
+                BinaryOperator::Multiply,
+                normal,
+                &Value::float(width, factor),
+            )?;
+            binary(BinaryOperator::Subtract, arg, &scaled)?
+        }
+        Mf::Sign => map(&[arg], &|_, args| {
+            Ok(match *args[0] {
+                Sv::Sint(v) => Sv::Sint(v.signum()),
+                Sv::Float(v) if v > 0.0 => Sv::Float(1.0),
+                Sv::Float(v) if v < 0.0 => Sv::Float(-1.0),
+                Sv::Float(_) => Sv::Float(0.0),
+                _ => return Err(Fault::Invalid),
+            })
+        })?,
+        Mf::Fma => map_floats3(arg, arg1()?, arg2()?, f64::mul_add)?,
+        Mf::Mix => map_floats3(arg, arg1()?, arg2()?, |x, y, a| x * (1.0 - a) + y * a)?,
+        Mf::Step => map_floats2(arg, arg1()?, |edge, x| if x < edge { 0.0 } else { 1.0 })?,
+        Mf::SmoothStep => map_floats3(arg, arg1()?, arg2()?, |low, high, x| {
+            let t = ((x - low) / (high - low)).max(0.0);
+            let t = t.min(1.0);
+            t * t * (3.0 - 2.0 * t)
+        })?,
+        Mf::Sqrt => map_floats(arg, f64::sqrt)?,
+        Mf::InverseSqrt => map_floats(arg, |x| 1.0 / x.sqrt())?,
+        Mf::Inverse => {
+            let (width, columns) = arg.columns()?;
+            let det = determinant(&columns);
+            let size = columns.len();
+            let inverse = (0..size)
+                .map(|c| {
+                    (0..size)
+                        .map(|r| {
+                            let sign = if (r + c) % 2 == 0 { 1.0 } else { -1.0 };
+                            let cofactor = match size {
+                                1 => 1.0,
+                                _ => determinant(&minor(&columns, r, c)),
+                            };
+                            sign * cofactor / det
+                        })
+                        .collect()
+                })
+                .collect::<Vec<Vec<f64>>>();
+            Value::from_columns(width, &inverse)
+        }
+        Mf::Transpose => {
+            let (width, columns) = arg.columns()?;
+            let rows = columns.first().map_or(0, |column| column.len());
+            let transposed = (0..rows)
+                .map(|r| columns.iter().map(|column| column[r]).collect())
+                .collect::<Vec<Vec<f64>>>();
+            Value::from_columns(width, &transposed)
+        }
+        Mf::Determinant => {
+            let (width, columns) = arg.columns()?;
+            Value::float(width, determinant(&columns))
+        }
+        Mf::CountOneBits => map(&[arg], &|width, args| {
+            let count = u64::from(to_bits(width, args[0]).count_ones());
+            Ok(match *args[0] {
+                Sv::Sint(_) => Sv::Sint(count as i64),
+                Sv::Uint(_) => Sv::Uint(count),
+                _ => return Err(Fault::Invalid),
+            })
+        })?,
+        Mf::ReverseBits => map(&[arg], &|width, args| {
+            let reversed = to_bits(width, args[0]).reverse_bits() >> (64 - bit_count(width));
+            Ok(match *args[0] {
+                Sv::Sint(_) => wrap(width, Sv::Sint(reversed as i64)),
+                Sv::Uint(_) => Sv::Uint(reversed),
+                _ => return Err(Fault::Invalid),
+            })
+        })?,
+    })
+}
+
+pub(super) fn convert(value: &Value, kind: ScalarKind, convert: bool) -> Result<Value, Fault> {
+    use ScalarValue as Sv;
+
+    map(&[value], &|width, args| {
+        if !convert {
+            return Ok(from_bits(kind, width, to_bits(width, args[0])));
+        }
+        let float_to_int = |x: f64, signed: bool| {
+            let (min, max) = int_range(width, signed);
+            if x.is_nan() {
+                0
+            } else {
+                (x.trunc().max(min as f64).min(max as f64)) as i128
+            }
+        };
+        Ok(match (args[0], kind) {
+            (&Sv::Bool(b), ScalarKind::Bool) => Sv::Bool(b),
+            (&Sv::Bool(b), ScalarKind::Sint) => Sv::Sint(b as i64),
+            (&Sv::Bool(b), ScalarKind::Uint) => Sv::Uint(b as u64),
+            (&Sv::Bool(b), ScalarKind::Float) => Sv::Float(b as u8 as f64),
+            (&Sv::Sint(v), ScalarKind::Bool) => Sv::Bool(v != 0),
+            (&Sv::Uint(v), ScalarKind::Bool) => Sv::Bool(v != 0),
+            (&Sv::Float(v), ScalarKind::Bool) => Sv::Bool(v != 0.0),
+            (&Sv::Sint(v), ScalarKind::Sint) => Sv::Sint(v),
+            (&Sv::Sint(v), ScalarKind::Uint) => Sv::Uint(v as u64),
+            (&Sv::Sint(v), ScalarKind::Float) => Sv::Float(v as f64),
+            (&Sv::Uint(v), ScalarKind::Sint) => Sv::Sint(v as i64),
+            (&Sv::Uint(v), ScalarKind::Uint) => Sv::Uint(v),
+            (&Sv::Uint(v), ScalarKind::Float) => Sv::Float(v as f64),
+            (&Sv::Float(v), ScalarKind::Sint) => Sv::Sint(float_to_int(v, true) as i64),
+            (&Sv::Float(v), ScalarKind::Uint) => Sv::Uint(float_to_int(v, false) as u64),
+            (&Sv::Float(v), ScalarKind::Float) => Sv::Float(v),
+        })
+    })
+}
+
+/// Returns the identity value of a subgroup operation, in the shape of `value`.
+fn identity(op: crate::SubgroupOperation, value: &Value) -> Result<Value, Fault> {
+    use crate::SubgroupOperation as So;
+    use ScalarValue as Sv;
+
+    map(&[value], &|width, args| {
+        Ok(match (op, args[0]) {
+            (So::All, _) => Sv::Bool(true),
+            (So::Any, _) => Sv::Bool(false),
+            (So::Add, &Sv::Float(_)) => Sv::Float(0.0),
+            (So::Multiply, &Sv::Float(_)) => Sv::Float(1.0),
+            (So::Min, &Sv::Float(_)) => Sv::Float(f64::INFINITY),
+            (So::Max, &Sv::Float(_)) => Sv::Float(f64::NEG_INFINITY),
+            (So::Min, &Sv::Sint(_)) => Sv::Sint(int_range(width, true).1 as i64),
+            (So::Max, &Sv::Sint(_)) => Sv::Sint(int_range(width, true).0 as i64),
+            (So::Min, &Sv::Uint(_)) | (So::And, &Sv::Uint(_)) => Sv::Uint(u64::MAX),
+            (So::And, &Sv::Sint(_)) => Sv::Sint(-1),
+            (So::And, &Sv::Bool(_)) => Sv::Bool(true),
+            (So::Multiply, &Sv::Sint(_)) => Sv::Sint(1),
+            (So::Multiply, &Sv::Uint(_)) => Sv::Uint(1),
+            (_, &Sv::Sint(_)) => Sv::Sint(0),
+            (_, &Sv::Uint(_)) => Sv::Uint(0),
+            (_, &Sv::Bool(_)) => Sv::Bool(false),
+            (_, &Sv::Float(_)) => return Err(Fault::Invalid),
+        })
+    })
+}
+
+/// Location of a value in a buffer.
+#[derive(Clone, Copy, Debug)]
+enum Slot {
+    Type(Handle<crate::Type>),
+    Vector(VectorSize, ScalarKind, Bytes),
+    Scalar(ScalarKind, Bytes),
+}
+
+/// Byte stride between the columns of a matrix in a buffer.
+fn column_stride(rows: VectorSize, width: Bytes) -> usize {
+    match rows {
+        VectorSize::Bi => 2 * width as usize,
+        VectorSize::Tri | VectorSize::Quad => 4 * width as usize,
+    }
+}
+
+fn decode_scalar(
+    bytes: &[u8],
+    offset: usize,
+    kind: ScalarKind,
+    width: Bytes,
+) -> Result<Value, Fault> {
+    let source = bytes
+        .get(offset..offset + width as usize)
+        .ok_or(Fault::BufferOverrun(offset))?;
+    let mut raw = [0; 8];
+    raw[..source.len()].copy_from_slice(source);
+    Ok(Value::Scalar {
+        width,
+        value: from_bits(kind, width, u64::from_le_bytes(raw)),
+    })
+}
+
+fn encode_scalar(bytes: &mut [u8], offset: usize, value: &Value) -> Result<(), Fault> {
+    let (width, bits) = match *value {
+        Value::Scalar { width, ref value } => (width, to_bits(width, value)),
+        _ => return Err(Fault::Invalid),
+    };
+    let target = bytes
+        .get_mut(offset..offset + width as usize)
+        .ok_or(Fault::BufferOverrun(offset))?;
+    target.copy_from_slice(&bits.to_le_bytes()[..width as usize]);
+    Ok(())
+}
+
+/// Identifiers of an invocation, for its built-in inputs.
+#[derive(Clone, Copy, Debug)]
+struct InvocationIds {
+    global: [u32; 3],
+    local: [u32; 3],
+    workgroup: [u32; 3],
+    workgroup_size: [u32; 3],
+}
+
+enum CursorKind<'a> {
+    Block,
+    Loop {
+        body: &'a crate::Block,
+        continuing: &'a crate::Block,
+        in_continuing: bool,
+    },
+    Switch {
+        cases: &'a [crate::SwitchCase],
+        default: &'a crate::Block,
+        case: usize,
+    },
+}
+
+/// Position in a block being executed.
+struct Cursor<'a> {
+    statements: &'a [Statement],
+    index: usize,
+    kind: CursorKind<'a>,
+}
+
+impl<'a> Cursor<'a> {
+    fn block(statements: &'a [Statement]) -> Self {
+        Cursor {
+            statements,
+            index: 0,
+            kind: CursorKind::Block,
+        }
+    }
+}
+
+struct Frame<'a> {
+    function: &'a crate::Function,
+    arguments: Vec<Value>,
+    locals: Vec<Value>,
+    /// Values of the emitted expressions.
+    values: Vec<Option<Value>>,
+    cursors: Vec<Cursor<'a>>,
+    /// Expression of the caller that receives the result.
+    result: Option<Handle<Expression>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    Running,
+    Barrier,
+    Done,
+}
+
+struct Invocation<'a> {
+    frames: Vec<Frame<'a>>,
+    /// Values of the private, input and output global variables.
+    globals: Vec<Option<Value>>,
+    status: Status,
+}
+
+/// Executes the invocations of a workgroup.
+struct Executor<'a, 'r> {
+    module: &'a crate::Module,
+    layouter: &'r Layouter,
+    resources: &'r mut Resources,
+    /// Values of the workgroup global variables.
+    workgroup: &'r mut Vec<Option<Value>>,
+    steps: &'r mut u64,
+    step_limit: Option<u64>,
+}
+
+impl<'a, 'r> Executor<'a, 'r> {
+    fn array_length(&self, size: crate::ArraySize) -> Option<u32> {
+        match size {
+            crate::ArraySize::Constant(handle) => match self.module.constants[handle].inner {
+                crate::ConstantInner::Scalar {
+                    value: ScalarValue::Uint(value),
+                    ..
+                } => Some(value as u32),
+                crate::ConstantInner::Scalar {
+                    value: ScalarValue::Sint(value),
+                    ..
+                } => Some(value as u32),
+                _ => None,
+            },
+            crate::ArraySize::Dynamic => None,
+        }
+    }
+
+    fn array_stride(
+        &self,
+        base: Handle<crate::Type>,
+        stride: Option<std::num::NonZeroU32>,
+    ) -> usize {
+        match stride {
+            Some(stride) => stride.get() as usize,
+            None => self.layouter.resolve(base).size.max(1) as usize,
+        }
+    }
+
+    /// Returns the offset of a structure member, as the SPIR-V backend computes it.
+    fn member_offset(&self, members: &[crate::StructMember], index: usize) -> usize {
+        let mut offset = 0;
+        for (i, member) in members.iter().enumerate() {
+            let layout = self.layouter.resolve(member.ty);
+            offset += layout.pad(offset);
+            if i == index {
+                break;
+            }
+            offset += match member.span {
+                Some(span) => span.get(),
+                None => layout.size,
+            };
+        }
+        offset as usize
+    }
+
+    fn constant(&self, handle: Handle<crate::Constant>) -> Value {
+        match self.module.constants[handle].inner {
+            crate::ConstantInner::Scalar { width, ref value } => Value::Scalar {
+                width,
+                value: value.clone(),
+            },
+            crate::ConstantInner::Composite { ref components, .. } => Value::Composite(
+                components
+                    .iter()
+                    .map(|&component| self.constant(component))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn zero_value(&self, ty: Handle<crate::Type>) -> Result<Value, InterpreterError> {
+        Ok(match self.module.types[ty].inner {
+            TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
+                Value::zero(kind, width)
+            }
+            TypeInner::Vector { size, kind, width } => {
+                Value::Composite(vec![Value::zero(kind, width); size as usize])
+            }
+            TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => {
+                let column =
+                    Value::Composite(vec![Value::zero(ScalarKind::Float, width); rows as usize]);
+                Value::Composite(vec![column; columns as usize])
+            }
+            TypeInner::Array { base, size, .. } => match self.array_length(size) {
+                Some(length) => Value::Composite(vec![self.zero_value(base)?; length as usize]),
+                None => {
+                    return Err(InterpreterError::Unsupported(
+                        "runtime-sized array outside of a buffer".to_string(),
+                    ))
+                }
+            },
+            TypeInner::Struct { ref members, .. } => Value::Composite(
+                members
+                    .iter()
+                    .map(|member| self.zero_value(member.ty))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ref other => {
+                return Err(InterpreterError::Unsupported(format!(
+                    "variable of type {:?}",
+                    other
+                )))
+            }
+        })
+    }
+
+    /// Converts a value to the scalar kind of the given type.
+    fn cast_to(&self, value: Value, ty: Handle<crate::Type>) -> Value {
+        match self.module.types[ty].inner.scalar_kind() {
+            Some(kind) => convert(&value, kind, true).unwrap_or(value),
+            None => value,
+        }
+    }
+
+    fn built_in(
+        &self,
+        built_in: crate::BuiltIn,
+        ty: Handle<crate::Type>,
+        ids: &InvocationIds,
+    ) -> Result<Value, InterpreterError> {
+        use crate::BuiltIn as Bi;
+
+        let vector = |v: [u32; 3]| Value::Composite(v.iter().map(|&c| Value::uint(c)).collect());
+        let value = match built_in {
+            Bi::GlobalInvocationId => vector(ids.global),
+            Bi::LocalInvocationId => vector(ids.local),
+            Bi::WorkGroupId => vector(ids.workgroup),
+            Bi::WorkGroupSize => vector(ids.workgroup_size),
+            Bi::LocalInvocationIndex => {
+                let [x, y, z] = ids.local;
+                let [width, height, _] = ids.workgroup_size;
+                Value::uint((z * height + y) * width + x)
+            }
+            Bi::SubgroupSize => Value::uint(1),
+            Bi::SubgroupInvocationId => Value::uint(0),
+            other => {
+                return Err(InterpreterError::Unsupported(format!(
+                    "built-in {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(self.cast_to(value, ty))
+    }
+
+    fn entry_argument(
+        &self,
+        argument: &crate::FunctionArgument,
+        ids: &InvocationIds,
+    ) -> Result<Value, InterpreterError> {
+        match argument.binding {
+            Some(crate::Binding::BuiltIn(built_in)) => self.built_in(built_in, argument.ty, ids),
+            None => match self.module.types[argument.ty].inner {
+                TypeInner::Struct { ref members, .. } => members
+                    .iter()
+                    .map(|member| match member.binding {
+                        Some(crate::Binding::BuiltIn(built_in)) => {
+                            self.built_in(built_in, member.ty, ids)
+                        }
+                        _ => Err(InterpreterError::Unsupported(
+                            "compute input without a built-in binding".to_string(),
+                        )),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Value::Composite),
+                _ => Err(InterpreterError::Unsupported(
+                    "compute input without a binding".to_string(),
+                )),
+            },
+            Some(_) => Err(InterpreterError::Unsupported(
+                "compute input without a built-in binding".to_string(),
+            )),
+        }
+    }
+
+    fn frame(
+        &self,
+        function: &'a crate::Function,
+        arguments: Vec<Value>,
+        result: Option<Handle<Expression>>,
+    ) -> Result<Frame<'a>, InterpreterError> {
+        let locals = function
+            .local_variables
+            .iter()
+            .map(|(_, variable)| match variable.init {
+                Some(constant) => Ok(self.constant(constant)),
+                None => self.zero_value(variable.ty),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Frame {
+            function,
+            arguments,
+            locals,
+            values: vec![None; function.expressions.len()],
+            cursors: vec![Cursor::block(&function.body)],
+            result,
+        })
+    }
+
+    fn invocation(
+        &self,
+        entry_point: &'a crate::EntryPoint,
+        ids: &InvocationIds,
+    ) -> Result<Invocation<'a>, InterpreterError> {
+        let mut globals = Vec::with_capacity(self.module.global_variables.len());
+        for (_, variable) in self.module.global_variables.iter() {
+            globals.push(match variable.class {
+                StorageClass::Private | StorageClass::Output => Some(match variable.init {
+                    Some(constant) => self.constant(constant),
+                    None => self.zero_value(variable.ty)?,
+                }),
+                StorageClass::Input => match variable.binding {
+                    Some(crate::Binding::BuiltIn(built_in)) => {
+                        Some(self.built_in(built_in, variable.ty, ids)?)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            });
+        }
+        let arguments = entry_point
+            .function
+            .arguments
+            .iter()
+            .map(|argument| self.entry_argument(argument, ids))
+            .collect::<Result<_, _>>()?;
+        Ok(Invocation {
+            frames: vec![self.frame(&entry_point.function, arguments, None)?],
+            globals,
+            status: Status::Running,
+        })
+    }
+
+    fn resource_binding(
+        &self,
+        handle: Handle<GlobalVariable>,
+    ) -> Result<ResourceBinding, InterpreterError> {
+        match self.module.global_variables[handle].binding {
+            Some(crate::Binding::Resource { group, binding }) => {
+                Ok(ResourceBinding { group, binding })
+            }
+            _ => Err(InterpreterError::Unsupported(
+                "resource without a binding".to_string(),
+            )),
+        }
+    }
+
+    fn image(&self, handle: Handle<GlobalVariable>) -> Result<&Image, InterpreterError> {
+        let binding = self.resource_binding(handle)?;
+        match self.resources.get(&binding) {
+            Some(Resource::Image(image)) => Ok(image),
+            Some(_) => Err(InterpreterError::InvalidResource(binding)),
+            None => Err(InterpreterError::MissingResource(binding)),
+        }
+    }
+
+    /// Returns the offset and the type of the component of a buffer at the given path.
+    fn buffer_slot(&self, ty: Handle<crate::Type>, path: &[u32]) -> Result<(usize, Slot), Fault> {
+        let mut offset = 0;
+        let mut slot = Slot::Type(ty);
+        for &index in path {
+            let out_of_bounds = Fault::IndexOutOfBounds(i64::from(index));
+            let (step, next) = match slot {
+                Slot::Type(ty) => match self.module.types[ty].inner {
+                    TypeInner::Vector { size, kind, width } => {
+                        if index >= size as u32 {
+                            return Err(out_of_bounds);
+                        }
+                        (index as usize * width as usize, Slot::Scalar(kind, width))
+                    }
+                    TypeInner::Matrix {
+                        columns,
+                        rows,
+                        width,
+                    } => {
+                        if index >= columns as u32 {
+                            return Err(out_of_bounds);
+                        }
+                        (
+                            index as usize * column_stride(rows, width),
+                            Slot::Vector(rows, ScalarKind::Float, width),
+                        )
+                    }
+                    TypeInner::Array { base, size, stride } => {
+                        if let Some(length) = self.array_length(size) {
+                            if index >= length {
+                                return Err(out_of_bounds);
+                            }
+                        }
+                        (
+                            index as usize * self.array_stride(base, stride),
+                            Slot::Type(base),
+                        )
+                    }
+                    TypeInner::Struct { ref members, .. } => {
+                        let member = members.get(index as usize).ok_or(out_of_bounds)?;
+                        (
+                            self.member_offset(members, index as usize),
+                            Slot::Type(member.ty),
+                        )
+                    }
+                    _ => return Err(Fault::Invalid),
+                },
+                Slot::Vector(size, kind, width) => {
+                    if index >= size as u32 {
+                        return Err(out_of_bounds);
+                    }
+                    (index as usize * width as usize, Slot::Scalar(kind, width))
+                }
+                Slot::Scalar(..) => return Err(Fault::Invalid),
+            };
+            offset += step;
+            slot = next;
+        }
+        Ok((offset, slot))
+    }
+
+    fn decode(&self, bytes: &[u8], offset: usize, slot: Slot) -> Result<Value, Fault> {
+        let ty = match slot {
+            Slot::Scalar(kind, width) => return decode_scalar(bytes, offset, kind, width),
+            Slot::Vector(size, kind, width) => {
+                return (0..size as usize)
+                    .map(|i| decode_scalar(bytes, offset + i * width as usize, kind, width))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Composite)
+            }
+            Slot::Type(ty) => ty,
+        };
+        match self.module.types[ty].inner {
+            TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
+                decode_scalar(bytes, offset, kind, width)
+            }
+            TypeInner::Vector { size, kind, width } => {
+                self.decode(bytes, offset, Slot::Vector(size, kind, width))
+            }
+            TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => (0..columns as usize)
+                .map(|i| {
+                    let column_offset = offset + i * column_stride(rows, width);
+                    self.decode(
+                        bytes,
+                        column_offset,
+                        Slot::Vector(rows, ScalarKind::Float, width),
+                    )
+                })
+                .collect::<Result<_, _>>()
+                .map(Value::Composite),
+            TypeInner::Array { base, size, stride } => {
+                let stride = self.array_stride(base, stride);
+                let length = match self.array_length(size) {
+                    Some(length) => length as usize,
+                    None => bytes.len().saturating_sub(offset) / stride,
+                };
+                (0..length)
+                    .map(|i| self.decode(bytes, offset + i * stride, Slot::Type(base)))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Composite)
+            }
+            TypeInner::Struct { ref members, .. } => members
+                .iter()
+                .enumerate()
+                .map(|(i, member)| {
+                    let member_offset = offset + self.member_offset(members, i);
+                    self.decode(bytes, member_offset, Slot::Type(member.ty))
+                })
+                .collect::<Result<_, _>>()
+                .map(Value::Composite),
+            _ => Err(Fault::Invalid),
+        }
+    }
+
+    fn encode(
+        &self,
+        bytes: &mut [u8],
+        offset: usize,
+        slot: Slot,
+        value: &Value,
+    ) -> Result<(), Fault> {
+        let components = match *value {
+            Value::Scalar { .. } => return encode_scalar(bytes, offset, value),
+            Value::Composite(ref components) => components,
+            _ => return Err(Fault::Invalid),
+        };
+        match slot {
+            Slot::Scalar(..) => Err(Fault::Invalid),
+            Slot::Vector(_, _, width) => {
+                for (i, component) in components.iter().enumerate() {
+                    encode_scalar(bytes, offset + i * width as usize, component)?;
+                }
+                Ok(())
+            }
+            Slot::Type(ty) => {
+                for (i, component) in components.iter().enumerate() {
+                    let (component_offset, component_slot) = self.buffer_slot(ty, &[i as u32])?;
+                    self.encode(bytes, offset + component_offset, component_slot, component)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the storage of a value that isn't in a buffer.
+    fn storage<'i>(
+        &'i mut self,
+        invocation: &'i mut Invocation<'a>,
+        root: &Root,
+    ) -> Result<&'i mut Value, InterpreterError> {
+        let storage = match *root {
+            Root::Local { frame, variable } => {
+                return Ok(&mut invocation.frames[frame].locals[variable.index()])
+            }
+            Root::Global(handle) => match self.module.global_variables[handle].class {
+                StorageClass::WorkGroup => self.workgroup[handle.index()].as_mut(),
+                _ => invocation.globals[handle.index()].as_mut(),
+            },
+        };
+        storage.ok_or_else(|| InterpreterError::Unsupported("input without a built-in".to_string()))
+    }
+
+    fn buffer_variable(&self, root: &Root) -> Option<Handle<GlobalVariable>> {
+        match *root {
+            Root::Global(handle) => match self.module.global_variables[handle].class {
+                StorageClass::Uniform | StorageClass::Storage => Some(handle),
+                _ => None,
+            },
+            Root::Local { .. } => None,
+        }
+    }
+
+    fn buffer_mut(
+        &mut self,
+        handle: Handle<GlobalVariable>,
+    ) -> Result<(ResourceBinding, &mut Vec<u8>), InterpreterError> {
+        let binding = self.resource_binding(handle)?;
+        match self.resources.get_mut(&binding) {
+            Some(&mut Resource::Buffer(ref mut bytes)) => Ok((binding, bytes)),
+            Some(_) => Err(InterpreterError::InvalidResource(binding)),
+            None => Err(InterpreterError::MissingResource(binding)),
+        }
+    }
+
+    fn read(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        pointer: &Pointer,
+        expr: Handle<Expression>,
+    ) -> Result<Value, InterpreterError> {
+        if let Some(handle) = self.buffer_variable(&pointer.root) {
+            let ty = self.module.global_variables[handle].ty;
+            let (offset, slot) = self
+                .buffer_slot(ty, &pointer.path)
+                .map_err(|fault| fault.into_error(expr))?;
+            let (binding, bytes) = self.buffer_mut(handle)?;
+            let bytes = std::mem::take(bytes);
+            let result = self.decode(&bytes, offset, slot);
+            *self.buffer_mut(handle)?.1 = bytes;
+            return result.map_err(|fault| match fault {
+                Fault::BufferOverrun(offset) => InterpreterError::BufferOverrun(binding, offset),
+                other => other.into_error(expr),
+            });
+        }
+        let mut value = &*self.storage(invocation, &pointer.root)?;
+        for &index in pointer.path.iter() {
+            value = match *value {
+                Value::Composite(ref components) => components
+                    .get(index as usize)
+                    .ok_or(InterpreterError::IndexOutOfBounds(expr, i64::from(index)))?,
+                _ => return Err(InterpreterError::InvalidExpression(expr)),
+            };
+        }
+        Ok(value.clone())
+    }
+
+    fn write(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        pointer: &Pointer,
+        value: Value,
+        expr: Handle<Expression>,
+    ) -> Result<(), InterpreterError> {
+        if let Some(handle) = self.buffer_variable(&pointer.root) {
+            let ty = self.module.global_variables[handle].ty;
+            let (offset, slot) = self
+                .buffer_slot(ty, &pointer.path)
+                .map_err(|fault| fault.into_error(expr))?;
+            let (binding, bytes) = self.buffer_mut(handle)?;
+            let mut bytes = std::mem::take(bytes);
+            let result = self.encode(&mut bytes, offset, slot, &value);
+            *self.buffer_mut(handle)?.1 = bytes;
+            return result.map_err(|fault| match fault {
+                Fault::BufferOverrun(offset) => InterpreterError::BufferOverrun(binding, offset),
+                other => other.into_error(expr),
+            });
+        }
+        let mut target = self.storage(invocation, &pointer.root)?;
+        for &index in pointer.path.iter() {
+            target = match *target {
+                Value::Composite(ref mut components) => components
+                    .get_mut(index as usize)
+                    .ok_or(InterpreterError::IndexOutOfBounds(expr, i64::from(index)))?,
+                _ => return Err(InterpreterError::InvalidExpression(expr)),
+            };
+        }
+        *target = value;
+        Ok(())
+    }
+
+    fn pointer(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        expr: Handle<Expression>,
+    ) -> Result<Pointer, InterpreterError> {
+        match self.value(invocation, expr)? {
+            Value::Pointer(pointer) => Ok(pointer),
+            _ => Err(InterpreterError::InvalidExpression(expr)),
+        }
+    }
+
+    /// Returns the value of an expression, evaluating it if it isn't emitted.
+    fn value(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        expr: Handle<Expression>,
+    ) -> Result<Value, InterpreterError> {
+        let frame = invocation.frames.last().unwrap();
+        match frame.values[expr.index()] {
+            Some(ref value) => Ok(value.clone()),
+            None => self.evaluate(invocation, expr),
+        }
+    }
+
+    fn image_coordinate(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        coordinate: Handle<Expression>,
+        array_index: Option<Handle<Expression>>,
+    ) -> Result<([i64; 3], i64), InterpreterError> {
+        let invalid = InterpreterError::InvalidExpression(coordinate);
+        let mut coordinates = [0; 3];
+        match self.value(invocation, coordinate)? {
+            Value::Composite(ref components) if components.len() <= 3 => {
+                for (c, component) in coordinates.iter_mut().zip(components.iter()) {
+                    *c = component.as_index().ok_or_else(|| invalid.clone())?;
+                }
+            }
+            ref scalar => coordinates[0] = scalar.as_index().ok_or(invalid)?,
+        }
+        let layer = match array_index {
+            Some(index) => self
+                .value(invocation, index)?
+                .as_index()
+                .ok_or(InterpreterError::InvalidExpression(index))?,
+            None => 0,
+        };
+        Ok((coordinates, layer))
+    }
+
+    fn image_variable(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        image: Handle<Expression>,
+    ) -> Result<(Handle<GlobalVariable>, crate::ImageClass), InterpreterError> {
+        match self.value(invocation, image)? {
+            Value::Resource(handle) => {
+                let ty = self.module.global_variables[handle].ty;
+                match self.module.types[ty].inner {
+                    TypeInner::Image { class, .. } => Ok((handle, class)),
+                    _ => Err(InterpreterError::InvalidExpression(image)),
+                }
+            }
+            _ => Err(InterpreterError::InvalidExpression(image)),
+        }
+    }
+
+    /// Evaluates the math functions that store a part of the result through a pointer.
+    fn math_with_pointer(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        fun: MathFunction,
+        arg: &Value,
+        pointer: Handle<Expression>,
+        expr: Handle<Expression>,
+    ) -> Result<Value, InterpreterError> {
+        let target = self.pointer(invocation, pointer)?;
+        let fault = |fault: Fault| fault.into_error(expr);
+        let (result, stored) = if fun == MathFunction::Modf {
+            let whole = map_floats(arg, f64::trunc).map_err(fault)?;
+            let fraction = binary(BinaryOperator::Subtract, arg, &whole).map_err(fault)?;
+            (fraction, whole)
+        } else {
+            let mantissa = map_floats(arg, |x| x / 2f64.powi(frexp_exponent(x))).map_err(fault)?;
+            let exponents = map(&[arg], &|_, args| match *args[0] {
+                ScalarValue::Float(x) => Ok(ScalarValue::Sint(i64::from(frexp_exponent(x)))),
+                _ => Err(Fault::Invalid),
+            })
+            .map_err(fault)?;
+            (mantissa, exponents)
+        };
+        self.write(invocation, &target, stored, expr)?;
+        Ok(result)
+    }
+
+    fn evaluate(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        expr: Handle<Expression>,
+    ) -> Result<Value, InterpreterError> {
+        let frame_index = invocation.frames.len() - 1;
+        let function = invocation.frames[frame_index].function;
+        let invalid = InterpreterError::InvalidExpression(expr);
+        let fault = |fault: Fault| fault.into_error(expr);
+
+        Ok(match function.expressions[expr] {
+            Expression::Access { base, index } => {
+                let index = self
+                    .value(invocation, index)?
+                    .as_index()
+                    .ok_or_else(|| invalid.clone())?;
+                if index < 0 {
+                    return Err(InterpreterError::IndexOutOfBounds(expr, index));
+                }
+                self.access(invocation, base, index as u32, expr)?
+            }
+            Expression::AccessIndex { base, index } => {
+                self.access(invocation, base, index, expr)?
+            }
+            Expression::Constant(handle) => self.constant(handle),
+            Expression::Compose { ty, ref components } => {
+                let mut values = Vec::with_capacity(components.len());
+                for &component in components {
+                    values.push(self.value(invocation, component)?);
+                }
+                match self.module.types[ty].inner {
+                    // vectors can be composed of smaller vectors
+                    TypeInner::Vector { size, .. } => {
+                        let mut scalars = Vec::with_capacity(size as usize);
+                        for value in values {
+                            match value {
+                                Value::Composite(components) => scalars.extend(components),
+                                scalar => scalars.push(scalar),
+                            }
+                        }
+                        if scalars.len() != size as usize {
+                            return Err(invalid);
+                        }
+                        Value::Composite(scalars)
+                    }
+                    TypeInner::Matrix { columns, rows, .. }
+                        if values.len() == columns as usize * rows as usize =>
+                    {
+                        Value::Composite(
+                            values
+                                .chunks(rows as usize)
+                                .map(|column| Value::Composite(column.to_vec()))
+                                .collect(),
+                        )
+                    }
+                    _ => Value::Composite(values),
+                }
+            }
+            Expression::FunctionArgument(index) => invocation.frames[frame_index]
+                .arguments
+                .get(index as usize)
+                .cloned()
+                .ok_or(invalid)?,
+            Expression::GlobalVariable(handle) => {
+                match self.module.global_variables[handle].class {
+                    StorageClass::Handle => Value::Resource(handle),
+                    _ => Value::Pointer(Pointer {
+                        root: Root::Global(handle),
+                        path: Vec::new(),
+                    }),
+                }
+            }
+            Expression::LocalVariable(variable) => Value::Pointer(Pointer {
+                root: Root::Local {
+                    frame: frame_index,
+                    variable,
+                },
+                path: Vec::new(),
+            }),
+            Expression::Load { pointer } => {
+                let pointer_value = self.pointer(invocation, pointer)?;
+                self.read(invocation, &pointer_value, pointer)?
+            }
+            Expression::ImageSample { .. } => {
+                return Err(InterpreterError::Unsupported("image sampling".to_string()))
+            }
+            Expression::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index: _,
+            } => {
+                let (handle, class) = self.image_variable(invocation, image)?;
+                let (coordinate, layer) =
+                    self.image_coordinate(invocation, coordinate, array_index)?;
+                let image = self.image(handle)?;
+                // out of bounds loads return zero
+                let texel = image
+                    .texel_index(coordinate, layer)
+                    .and_then(|index| image.texels.get(index))
+                    .cloned()
+                    .unwrap_or([0.0; 4]);
+                let kind = match class {
+                    crate::ImageClass::Depth => return Ok(Value::float(4, texel[0])),
+                    crate::ImageClass::Sampled { kind, .. } => kind,
+                    crate::ImageClass::Storage(format) => format.into(),
+                };
+                Value::Composite(
+                    texel
+                        .iter()
+                        .map(|&component| match kind {
+                            ScalarKind::Sint => Value::sint(component as i64),
+                            ScalarKind::Uint => Value::Scalar {
+                                width: 4,
+                                value: ScalarValue::Uint(component as u64),
+                            },
+                            _ => Value::float(4, component),
+                        })
+                        .collect(),
+                )
+            }
+            Expression::ImageQuery { image, query } => {
+                let (handle, _) = self.image_variable(invocation, image)?;
+                let ty = self.module.global_variables[handle].ty;
+                let dim = match self.module.types[ty].inner {
+                    TypeInner::Image { dim, .. } => dim,
+                    _ => return Err(invalid),
+                };
+                let image = self.image(handle)?;
+                match query {
+                    crate::ImageQuery::Size { level: _ } => {
+                        let [width, height, depth] = image.size;
+                        let size = |values: &[u32]| {
+                            Value::Composite(
+                                values.iter().map(|&v| Value::sint(i64::from(v))).collect(),
+                            )
+                        };
+                        match dim {
+                            crate::ImageDimension::D1 => Value::sint(i64::from(width)),
+                            crate::ImageDimension::D2 => size(&[width, height]),
+                            crate::ImageDimension::D3 | crate::ImageDimension::Cube => {
+                                size(&[width, height, depth])
+                            }
+                        }
+                    }
+                    crate::ImageQuery::NumLevels | crate::ImageQuery::NumSamples => Value::sint(1),
+                    crate::ImageQuery::NumLayers => Value::sint(i64::from(image.layers)),
+                }
+            }
+            Expression::Unary { op, expr: operand } => {
+                let value = self.value(invocation, operand)?;
+                map(&[&value], &|_, args| {
+                    Ok(match (op, args[0]) {
+                        (crate::UnaryOperator::Negate, &ScalarValue::Sint(v)) => {
+                            ScalarValue::Sint(v.wrapping_neg())
+                        }
+                        (crate::UnaryOperator::Negate, &ScalarValue::Uint(v)) => {
+                            ScalarValue::Uint(v.wrapping_neg())
+                        }
+                        (crate::UnaryOperator::Negate, &ScalarValue::Float(v)) => {
+                            ScalarValue::Float(-v)
+                        }
+                        (crate::UnaryOperator::Not, &ScalarValue::Sint(v)) => ScalarValue::Sint(!v),
+                        (crate::UnaryOperator::Not, &ScalarValue::Uint(v)) => ScalarValue::Uint(!v),
+                        (crate::UnaryOperator::Not, &ScalarValue::Bool(v)) => ScalarValue::Bool(!v),
+                        _ => return Err(Fault::Invalid),
+                    })
+                })
+                .map_err(fault)?
+            }
+            Expression::Binary { op, left, right } => {
+                let left = self.value(invocation, left)?;
+                let right = self.value(invocation, right)?;
+                binary(op, &left, &right).map_err(fault)?
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = self.value(invocation, condition)?;
+                let accept = self.value(invocation, accept)?;
+                let reject = self.value(invocation, reject)?;
+                match condition.as_bool() {
+                    Some(true) => accept,
+                    Some(false) => reject,
+                    None => map(&[&condition, &accept, &reject], &|_, args| match *args[0] {
+                        ScalarValue::Bool(true) => Ok(args[1].clone()),
+                        ScalarValue::Bool(false) => Ok(args[2].clone()),
+                        _ => Err(Fault::Invalid),
+                    })
+                    .map_err(fault)?,
+                }
+            }
+            Expression::Derivative { .. } => {
+                return Err(InterpreterError::Unsupported("derivatives".to_string()))
+            }
+            Expression::Relational { fun, argument } => {
+                use crate::RelationalFunction as Rf;
+                let value = self.value(invocation, argument)?;
+                match fun {
+                    Rf::All | Rf::Any => {
+                        let components = match value {
+                            Value::Composite(components) => components,
+                            scalar => vec![scalar],
+                        };
+                        let mut bools = components.iter().map(Value::as_bool);
+                        let result = if fun == Rf::All {
+                            bools.try_fold(true, |all, b| b.map(|b| all && b))
+                        } else {
+                            bools.try_fold(false, |any, b| b.map(|b| any || b))
+                        };
+                        Value::bool(result.ok_or(invalid)?)
+                    }
+                    _ => map(&[&value], &|width, args| match *args[0] {
+                        ScalarValue::Float(x) => Ok(ScalarValue::Bool(match fun {
+                            Rf::IsNan => x.is_nan(),
+                            Rf::IsInf => x.is_infinite(),
+                            Rf::IsFinite => x.is_finite(),
+                            // the value is checked at its own precision
+                            _ => match width {
+                                2 => {
+                                    let exponent = super::f16_to_bits(x) & 0x7c00;
+                                    exponent != 0 && exponent != 0x7c00
+                                }
+                                4 => (x as f32).is_normal(),
+                                _ => x.is_normal(),
+                            },
+                        })),
+                        _ => Err(Fault::Invalid),
+                    })
+                    .map_err(fault)?,
+                }
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+            } => {
+                let arg = self.value(invocation, arg)?;
+                if let (MathFunction::Modf, Some(pointer)) | (MathFunction::Frexp, Some(pointer)) =
+                    (fun, arg1)
+                {
+                    return self.math_with_pointer(invocation, fun, &arg, pointer, expr);
+                }
+                let arg1 = match arg1 {
+                    Some(arg1) => Some(self.value(invocation, arg1)?),
+                    None => None,
+                };
+                let arg2 = match arg2 {
+                    Some(arg2) => Some(self.value(invocation, arg2)?),
+                    None => None,
+                };
+                math(fun, &arg, arg1.as_ref(), arg2.as_ref()).map_err(fault)?
+            }
+            Expression::As {
+                expr: operand,
+                kind,
+                convert: conversion,
+            } => {
+                let value = self.value(invocation, operand)?;
+                convert(&value, kind, conversion).map_err(fault)?
+            }
+            Expression::ArrayLength(array) => {
+                let pointer = self.pointer(invocation, array)?;
+                let handle = self
+                    .buffer_variable(&pointer.root)
+                    .ok_or_else(|| invalid.clone())?;
+                let ty = self.module.global_variables[handle].ty;
+                let (offset, slot) = self.buffer_slot(ty, &pointer.path).map_err(fault)?;
+                let stride = match slot {
+                    Slot::Type(ty) => match self.module.types[ty].inner {
+                        TypeInner::Array { base, stride, .. } => self.array_stride(base, stride),
+                        _ => return Err(invalid),
+                    },
+                    _ => return Err(invalid),
+                };
+                let (_, bytes) = self.buffer_mut(handle)?;
+                Value::uint((bytes.len().saturating_sub(offset) / stride) as u32)
+            }
+            // these are only produced by statements
+            Expression::Call(_)
+            | Expression::AtomicResult { .. }
+            | Expression::SubgroupResult { .. }
+            | Expression::ReportIntersectionResult => return Err(invalid),
+        })
+    }
+
+    fn access(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        base: Handle<Expression>,
+        index: u32,
+        expr: Handle<Expression>,
+    ) -> Result<Value, InterpreterError> {
+        match self.value(invocation, base)? {
+            Value::Pointer(mut pointer) => {
+                pointer.path.push(index);
+                Ok(Value::Pointer(pointer))
+            }
+            Value::Composite(mut components) => {
+                if index as usize >= components.len() {
+                    return Err(InterpreterError::IndexOutOfBounds(expr, i64::from(index)));
+                }
+                Ok(components.swap_remove(index as usize))
+            }
+            _ => Err(InterpreterError::InvalidExpression(expr)),
+        }
+    }
+
+    fn set_value(invocation: &mut Invocation<'a>, expr: Handle<Expression>, value: Value) {
+        invocation.frames.last_mut().unwrap().values[expr.index()] = Some(value);
+    }
+
+    /// Returns from the current function.
+    fn finish_call(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        value: Option<Value>,
+    ) -> Result<(), InterpreterError> {
+        let frame = invocation.frames.pop().unwrap();
+        if invocation.frames.is_empty() {
+            invocation.status = Status::Done;
+            return Ok(());
+        }
+        if let Some(result) = frame.result {
+            let value = value.ok_or(InterpreterError::InvalidExpression(result))?;
+            Self::set_value(invocation, result, value);
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, invocation: &mut Invocation<'a>) -> Result<(), InterpreterError> {
+        *self.steps += 1;
+        if let Some(limit) = self.step_limit {
+            if *self.steps > limit {
+                return Err(InterpreterError::StepLimitExceeded(limit));
+            }
+        }
+
+        let frame = invocation.frames.last_mut().unwrap();
+        let cursor = match frame.cursors.last_mut() {
+            Some(cursor) => cursor,
+            // the function ends without a return
+            None => return self.finish_call(invocation, None),
+        };
+        let statements = cursor.statements;
+        if cursor.index == statements.len() {
+            match cursor.kind {
+                CursorKind::Block => {
+                    frame.cursors.pop();
+                }
+                CursorKind::Loop {
+                    body,
+                    continuing,
+                    ref mut in_continuing,
+                } => {
+                    cursor.statements = if *in_continuing { body } else { continuing };
+                    cursor.index = 0;
+                    *in_continuing = !*in_continuing;
+                }
+                CursorKind::Switch {
+                    cases,
+                    default,
+                    ref mut case,
+                } => {
+                    if *case < cases.len() && cases[*case].fall_through {
+                        *case += 1;
+                        cursor.statements = match cases.get(*case) {
+                            Some(next) => &next.body,
+                            None => default,
+                        };
+                        cursor.index = 0;
+                    } else {
+                        frame.cursors.pop();
+                    }
+                }
+            }
+            return Ok(());
+        }
+        let statement = &statements[cursor.index];
+        cursor.index += 1;
+        self.execute(invocation, statement)
+    }
+
+    fn push_cursor(invocation: &mut Invocation<'a>, cursor: Cursor<'a>) {
+        invocation.frames.last_mut().unwrap().cursors.push(cursor);
+    }
+
+    fn execute(
+        &mut self,
+        invocation: &mut Invocation<'a>,
+        statement: &'a Statement,
+    ) -> Result<(), InterpreterError> {
+        match *statement {
+            Statement::Emit(ref range) => {
+                for expr in range.clone() {
+                    let value = self.evaluate(invocation, expr)?;
+                    Self::set_value(invocation, expr, value);
+                }
+            }
+            Statement::Block(ref block) => Self::push_cursor(invocation, Cursor::block(block)),
+            Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                let value = self
+                    .value(invocation, condition)?
+                    .as_bool()
+                    .ok_or(InterpreterError::InvalidExpression(condition))?;
+                let block = if value { accept } else { reject };
+                Self::push_cursor(invocation, Cursor::block(block));
+            }
+            Statement::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                let value = self
+                    .value(invocation, selector)?
+                    .as_index()
+                    .ok_or(InterpreterError::InvalidExpression(selector))?;
+                let case = cases
+                    .iter()
+                    .position(|case| i64::from(case.value) == value)
+                    .unwrap_or(cases.len());
+                let statements = match cases.get(case) {
+                    Some(case) => &case.body,
+                    None => default,
+                };
+                Self::push_cursor(
+                    invocation,
+                    Cursor {
+                        statements,
+                        index: 0,
+                        kind: CursorKind::Switch {
+                            cases,
+                            default,
+                            case,
+                        },
+                    },
+                );
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+            } => Self::push_cursor(
+                invocation,
+                Cursor {
+                    statements: body,
+                    index: 0,
+                    kind: CursorKind::Loop {
+                        body,
+                        continuing,
+                        in_continuing: false,
+                    },
+                },
+            ),
+            Statement::Break => {
+                let cursors = &mut invocation.frames.last_mut().unwrap().cursors;
+                while let Some(cursor) = cursors.pop() {
+                    if let CursorKind::Loop { .. } | CursorKind::Switch { .. } = cursor.kind {
+                        break;
+                    }
+                }
+            }
+            Statement::Continue => {
+                let cursors = &mut invocation.frames.last_mut().unwrap().cursors;
+                while let Some(cursor) = cursors.last_mut() {
+                    if let CursorKind::Loop {
+                        continuing,
+                        ref mut in_continuing,
+                        ..
+                    } = cursor.kind
+                    {
+                        cursor.statements = continuing;
+                        cursor.index = 0;
+                        *in_continuing = true;
+                        break;
+                    }
+                    cursors.pop();
+                }
+            }
+            Statement::Return { value } => {
+                let value = match value {
+                    Some(value) => Some(self.value(invocation, value)?),
+                    None => None,
+                };
+                self.finish_call(invocation, value)?;
+            }
+            Statement::Kill => {
+                invocation.frames.clear();
+                invocation.status = Status::Done;
+            }
+            Statement::Store { pointer, value } => {
+                let target = self.pointer(invocation, pointer)?;
+                let value = self.value(invocation, value)?;
+                self.write(invocation, &target, value, pointer)?;
+            }
+            Statement::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                let (handle, _) = self.image_variable(invocation, image)?;
+                let (coordinate, layer) =
+                    self.image_coordinate(invocation, coordinate, array_index)?;
+                let mut texel = [0.0; 4];
+                let components = match self.value(invocation, value)? {
+                    Value::Composite(components) => components,
+                    scalar => vec![scalar],
+                };
+                for (t, component) in texel.iter_mut().zip(components.iter()) {
+                    *t = match *component {
+                        Value::Scalar {
+                            value: ScalarValue::Float(v),
+                            ..
+                        } => v,
+                        ref other => other
+                            .as_index()
+                            .ok_or(InterpreterError::InvalidExpression(value))?
+                            as f64,
+                    };
+                }
+                let binding = self.resource_binding(handle)?;
+                if let Some(&mut Resource::Image(ref mut image)) = self.resources.get_mut(&binding)
+                {
+                    // out of bounds stores are discarded
+                    if let Some(index) = image.texel_index(coordinate, layer) {
+                        image.texels[index] = texel;
+                    }
+                } else {
+                    self.image(handle)?;
+                }
+            }
+            Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                let mut values = Vec::with_capacity(arguments.len());
+                for &argument in arguments {
+                    values.push(self.value(invocation, argument)?);
+                }
+                let frame = self.frame(&self.module.functions[function], values, result)?;
+                invocation.frames.push(frame);
+            }
+            Statement::Atomic {
+                pointer,
+                fun,
+                value,
+                result,
+            } => {
+                let target = self.pointer(invocation, pointer)?;
+                let old = self.read(invocation, &target, pointer)?;
+                let operand = self.value(invocation, value)?;
+                let fault = |fault: Fault| fault.into_error(result);
+                let (new, exchanged) = match fun {
+                    crate::AtomicFunction::Add => {
+                        (binary(BinaryOperator::Add, &old, &operand), None)
+                    }
+                    crate::AtomicFunction::Subtract => {
+                        (binary(BinaryOperator::Subtract, &old, &operand), None)
+                    }
+                    crate::AtomicFunction::And => {
+                        (binary(BinaryOperator::And, &old, &operand), None)
+                    }
+                    crate::AtomicFunction::ExclusiveOr => {
+                        (binary(BinaryOperator::ExclusiveOr, &old, &operand), None)
+                    }
+                    crate::AtomicFunction::InclusiveOr => {
+                        (binary(BinaryOperator::InclusiveOr, &old, &operand), None)
+                    }
+                    crate::AtomicFunction::Min => {
+                        (math(MathFunction::Min, &old, Some(&operand), None), None)
+                    }
+                    crate::AtomicFunction::Max => {
+                        (math(MathFunction::Max, &old, Some(&operand), None), None)
+                    }
+                    crate::AtomicFunction::Exchange { compare: None } => (Ok(operand), None),
+                    crate::AtomicFunction::Exchange {
+                        compare: Some(compare),
+                    } => {
+                        let compare = self.value(invocation, compare)?;
+                        let equal = match (&old, &compare) {
+                            (Value::Scalar { value: a, .. }, Value::Scalar { value: b, .. }) => {
+                                a == b
+                            }
+                            _ => false,
+                        };
+                        let new = if equal { operand } else { old.clone() };
+                        (Ok(new), Some(equal))
+                    }
+                };
+                let new = new.map_err(fault)?;
+                self.write(invocation, &target, new, pointer)?;
+                let result_value = match exchanged {
+                    Some(exchanged) => {
+                        let flag = match old {
+                            Value::Scalar {
+                                value: ScalarValue::Sint(_),
+                                ..
+                            } => Value::sint(exchanged as i64),
+                            _ => Value::uint(exchanged as u32),
+                        };
+                        Value::Composite(vec![old, flag])
+                    }
+                    None => old,
+                };
+                Self::set_value(invocation, result, result_value);
+            }
+            Statement::Barrier { execution, .. } => match execution {
+                Some(crate::Scope::WorkGroup) | Some(crate::Scope::Device) => {
+                    invocation.status = Status::Barrier;
+                }
+                // memory is always coherent, and subgroups have a single invocation
+                Some(crate::Scope::SubGroup) | None => {}
+            },
+            Statement::Subgroup { fun, result } => {
+                let fault = |fault: Fault| fault.into_error(result);
+                let value = match fun {
+                    crate::SubgroupFunction::Elect => Value::bool(true),
+                    crate::SubgroupFunction::Ballot { predicate } => {
+                        let bit = match predicate {
+                            Some(predicate) => self
+                                .value(invocation, predicate)?
+                                .as_bool()
+                                .ok_or(InterpreterError::InvalidExpression(predicate))?,
+                            None => true,
+                        };
+                        Value::Composite(vec![
+                            Value::uint(bit as u32),
+                            Value::uint(0),
+                            Value::uint(0),
+                            Value::uint(0),
+                        ])
+                    }
+                    crate::SubgroupFunction::Gather { argument, .. } => {
+                        self.value(invocation, argument)?
+                    }
+                    crate::SubgroupFunction::Collective {
+                        op,
+                        collective,
+                        argument,
+                    } => {
+                        let value = self.value(invocation, argument)?;
+                        match collective {
+                            crate::CollectiveOperation::Reduce
+                            | crate::CollectiveOperation::InclusiveScan => value,
+                            crate::CollectiveOperation::ExclusiveScan => {
+                                identity(op, &value).map_err(fault)?
+                            }
+                        }
+                    }
+                };
+                Self::set_value(invocation, result, value);
+            }
+            Statement::EmitVertex
+            | Statement::EndPrimitive
+            | Statement::TraceRay { .. }
+            | Statement::ReportIntersection { .. }
+            | Statement::IgnoreIntersection
+            | Statement::TerminateRay => {
+                return Err(InterpreterError::Unsupported(
+                    "statements of graphics and ray tracing stages".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the invocation until it finishes or reaches a barrier.
+    fn run(&mut self, invocation: &mut Invocation<'a>) -> Result<(), InterpreterError> {
+        invocation.status = Status::Running;
+        while invocation.status == Status::Running {
+            self.step(invocation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Executes the compute entry points of a module.
+pub struct Interpreter<'a> {
+    module: &'a crate::Module,
+    layouter: Layouter,
+    step_limit: Option<u64>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(module: &'a crate::Module) -> Self {
+        Interpreter {
+            module,
+            layouter: Layouter::new(&module.types, &module.constants),
+            step_limit: None,
+        }
+    }
+
+    /// Limits the number of statements executed by a dispatch,
+    /// in order to catch infinite loops.
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    /// Runs a compute entry point on a grid of workgroups.
+    ///
+    /// The buffers and images are looked up by the bindings of their global variables.
+    pub fn dispatch(
+        &self,
+        entry_point: &str,
+        group_count: [u32; 3],
+        resources: &mut Resources,
+    ) -> Result<(), InterpreterError> {
+        let entry_point = self
+            .module
+            .entry_points
+            .iter()
+            .find(|ep| ep.stage == crate::ShaderStage::Compute && ep.name == entry_point)
+            .ok_or_else(|| InterpreterError::MissingEntryPoint(entry_point.to_string()))?;
+        let mut workgroup_size = entry_point.workgroup_size;
+        for size in workgroup_size.iter_mut() {
+            *size = (*size).max(1);
+        }
+
+        let mut steps = 0;
+        for group_z in 0..group_count[2] {
+            for group_y in 0..group_count[1] {
+                for group_x in 0..group_count[0] {
+                    let workgroup = [group_x, group_y, group_z];
+                    let mut workgroup_values = Vec::new();
+                    let mut executor = Executor {
+                        module: self.module,
+                        layouter: &self.layouter,
+                        resources,
+                        workgroup: &mut workgroup_values,
+                        steps: &mut steps,
+                        step_limit: self.step_limit,
+                    };
+                    let mut values = Vec::with_capacity(self.module.global_variables.len());
+                    for (_, variable) in self.module.global_variables.iter() {
+                        values.push(match variable.class {
+                            StorageClass::WorkGroup => Some(executor.zero_value(variable.ty)?),
+                            _ => None,
+                        });
+                    }
+                    *executor.workgroup = values;
+
+                    let mut invocations = Vec::new();
+                    for z in 0..workgroup_size[2] {
+                        for y in 0..workgroup_size[1] {
+                            for x in 0..workgroup_size[0] {
+                                let local = [x, y, z];
+                                let mut global = [0; 3];
+                                for i in 0..3 {
+                                    global[i] = workgroup[i] * workgroup_size[i] + local[i];
+                                }
+                                let ids = InvocationIds {
+                                    global,
+                                    local,
+                                    workgroup,
+                                    workgroup_size,
+                                };
+                                invocations.push(executor.invocation(entry_point, &ids)?);
+                            }
+                        }
+                    }
+
+                    // run until all the invocations are at a barrier or done, then release them
+                    while invocations.iter().any(|inv| inv.status != Status::Done) {
+                        for invocation in invocations.iter_mut() {
+                            if invocation.status != Status::Done {
+                                executor.run(invocation)?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn math_functions() {
+    let vector = |values: &[f64]| Value::from_floats(4, values, true);
+    let matrix = Value::from_columns(4, &[vec![2.0, 1.0], vec![1.0, 1.0]]);
+
+    assert_eq!(round_even(2.5), 2.0);
+    assert_eq!(round_even(-3.5), -4.0);
+    assert_eq!(
+        math(MathFunction::Determinant, &matrix, None, None).unwrap(),
+        Value::float(4, 1.0)
+    );
+    assert_eq!(
+        math(MathFunction::Inverse, &matrix, None, None).unwrap(),
+        Value::from_columns(4, &[vec![1.0, -1.0], vec![-1.0, 2.0]])
+    );
+    assert_eq!(
+        binary(BinaryOperator::Multiply, &matrix, &vector(&[1.0, 2.0])).unwrap(),
+        vector(&[4.0, 3.0])
+    );
+    assert_eq!(
+        math(
+            MathFunction::Cross,
+            &vector(&[1.0, 0.0, 0.0]),
+            Some(&vector(&[0.0, 1.0, 0.0])),
+            None
+        )
+        .unwrap(),
+        vector(&[0.0, 0.0, 1.0])
+    );
+    let count = Value::Scalar {
+        width: 4,
+        value: ScalarValue::Uint(0xF0),
+    };
+    assert_eq!(
+        math(MathFunction::CountOneBits, &count, None, None).unwrap(),
+        Value::uint(4)
+    );
+    assert_eq!(
+        math(MathFunction::ReverseBits, &count, None, None).unwrap(),
+        Value::uint(0x0F00_0000)
+    );
+    assert_eq!(
+        convert(&Value::float(4, -1.5), ScalarKind::Uint, true).unwrap(),
+        Value::uint(0)
+    );
+}
@@ -27,6 +27,14 @@ pub struct Layouter {
 }
 
 impl Layouter {
+    fn vector_alignment(size: crate::VectorSize, width: crate::Bytes) -> Alignment {
+        let count = match size {
+            crate::VectorSize::Bi => 2,
+            crate::VectorSize::Tri | crate::VectorSize::Quad => 4,
+        };
+        Alignment::new(count * width as u32).unwrap()
+    }
+
     pub fn new(types: &Arena<crate::Type>, constants: &Arena<crate::Constant>) -> Self {
         let mut this = Layouter::default();
         this.initialize(types, constants);
@@ -41,27 +49,31 @@ impl Layouter {
 
         for (_, ty) in types.iter() {
             self.layouts.push(match ty.inner {
-                Ti::Scalar { kind: _, width } => TypeLayout {
+                Ti::Scalar { kind: _, width } | Ti::Atomic { kind: _, width } => TypeLayout {
                     size: width as u32,
                     alignment: Alignment::new(width as u32).unwrap(),
                 },
+                // vectors are aligned to their size rounded up to a power of two
                 Ti::Vector {
                     size,
                     kind: _,
                     width,
                 } => TypeLayout {
-                    size: (size as u8 * width) as u32,
-                    //TODO: reconsider if this needs to match the size
-                    alignment: Alignment::new(width as u32).unwrap(),
+                    size: size as u32 * width as u32,
+                    alignment: Self::vector_alignment(size, width),
                 },
+                // matrices are laid out as arrays of column vectors
                 Ti::Matrix {
                     columns,
                     rows,
                     width,
-                } => TypeLayout {
-                    size: (columns as u8 * rows as u8 * width) as u32,
-                    alignment: Alignment::new((columns as u8 * width) as u32).unwrap(),
-                },
+                } => {
+                    let alignment = Self::vector_alignment(rows, width);
+                    TypeLayout {
+                        size: columns as u32 * alignment.get(),
+                        alignment,
+                    }
+                }
                 Ti::Pointer { .. } | Ti::ValuePointer { .. } => TypeLayout {
                     size: 4,
                     alignment: Alignment::new(1).unwrap(),
@@ -116,7 +128,7 @@ impl Layouter {
                         alignment: biggest_alignment,
                     }
                 }
-                Ti::Image { .. } | Ti::Sampler { .. } => TypeLayout {
+                Ti::Image { .. } | Ti::Sampler { .. } | Ti::AccelerationStructure => TypeLayout {
                     size: 0,
                     alignment: Alignment::new(1).unwrap(),
                 },
@@ -0,0 +1,762 @@
+/*! Module linking.
+
+The modules are merged one after another into the linked module.
+Types and constants are deduplicated structurally, while global variables
+and functions are matched by name. The functions listed as declarations of a module
+are resolved by a function with the same name and signature in any module.
+!*/
+
+use super::{Visitor, VisitorMut};
+use crate::{
+    arena::{Arena, Handle},
+    ArraySize, Binding, BuiltIn, Constant, ConstantInner, Function, GlobalVariable, Module,
+    ShaderStage, Span, StorageClass, Type, TypeInner,
+};
+use std::mem;
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum LinkError {
+    #[error("Global variable `{0}` is defined differently by several modules")]
+    GlobalConflict(String),
+    #[error(
+        "Resource binding (group {group}, binding {binding}) is used by several global variables"
+    )]
+    BindingConflict { group: u32, binding: u32 },
+    #[error("Entry point `{entry_point}` uses several {class:?} {built_in:?} built-ins")]
+    BuiltInConflict {
+        entry_point: String,
+        class: StorageClass,
+        built_in: BuiltIn,
+    },
+    #[error("Entry point `{entry_point}` uses several {class:?} variables at location {location}")]
+    LocationConflict {
+        entry_point: String,
+        class: StorageClass,
+        location: u32,
+    },
+    #[error("Function `{0}` is defined by several modules")]
+    FunctionRedefinition(String),
+    #[error("Function `{0}` is declared with different signatures")]
+    SignatureMismatch(String),
+    #[error("Entry point `{name}` for the {stage:?} stage is defined by several modules")]
+    EntryPointConflict { stage: ShaderStage, name: String },
+    #[error("Function {0:?} calls itself recursively once linked")]
+    Recursion(Option<String>),
+}
+
+/// Appends a value to the arena, unless an identical one is already there.
+fn fetch_or_append<T: PartialEq>(arena: &mut Arena<T>, value: T, span: Span) -> Handle<T> {
+    match arena.fetch_if(|existing| *existing == value) {
+        Some(handle) => handle,
+        None => arena.append_with_span(value, span),
+    }
+}
+
+/// Adjusts the function handles of the calls.
+struct CallAdjuster<'a> {
+    functions: &'a [Handle<Function>],
+}
+
+impl VisitorMut for CallAdjuster<'_> {
+    fn visit_function_handle(&mut self, handle: &mut Handle<Function>) {
+        *handle = self.functions[handle.index()];
+    }
+}
+
+/// Adjusts the module handles of the visited items to the linked module.
+struct HandleAdjuster<'a> {
+    import: &'a Import,
+}
+
+impl VisitorMut for HandleAdjuster<'_> {
+    fn visit_type_handle(&mut self, handle: &mut Handle<Type>) {
+        self.import.adjust_type(handle);
+    }
+    fn visit_constant_handle(&mut self, handle: &mut Handle<Constant>) {
+        self.import.adjust_constant(handle);
+    }
+    fn visit_global_variable_handle(&mut self, handle: &mut Handle<GlobalVariable>) {
+        *handle = self.import.global_map[handle.index()];
+    }
+    fn visit_function_handle(&mut self, handle: &mut Handle<Function>) {
+        *handle = self.import.function_map[handle.index()];
+    }
+}
+
+/// Collects the functions called and the global variables used.
+#[derive(Default)]
+struct UsageCollector {
+    calls: Vec<Handle<Function>>,
+    globals: Vec<Handle<GlobalVariable>>,
+}
+
+impl Visitor for UsageCollector {
+    fn visit_function_handle(&mut self, handle: &Handle<Function>) {
+        self.calls.push(*handle);
+    }
+    fn visit_global_variable_handle(&mut self, handle: &Handle<GlobalVariable>) {
+        self.globals.push(*handle);
+    }
+}
+
+/// Items of a module being merged, with their handles in the linked module.
+struct Import {
+    types: Vec<Option<(Type, Span)>>,
+    type_map: Vec<Option<Handle<Type>>>,
+    constants: Vec<Option<(Constant, Span)>>,
+    constant_map: Vec<Option<Handle<Constant>>>,
+    global_map: Vec<Handle<GlobalVariable>>,
+    function_map: Vec<Handle<Function>>,
+}
+
+impl Import {
+    fn type_handle(&mut self, handle: Handle<Type>, module: &mut Module) -> Handle<Type> {
+        if let Some(new_handle) = self.type_map[handle.index()] {
+            return new_handle;
+        }
+        let (mut ty, span) = self.types[handle.index()].take().unwrap();
+        match ty.inner {
+            TypeInner::Pointer { ref mut base, .. } => *base = self.type_handle(*base, module),
+            TypeInner::Array {
+                ref mut base,
+                ref mut size,
+                ..
+            } => {
+                *base = self.type_handle(*base, module);
+                if let ArraySize::Constant(ref mut constant) = *size {
+                    *constant = self.constant_handle(*constant, module);
+                }
+            }
+            TypeInner::Struct {
+                ref mut members, ..
+            } => {
+                for member in members.iter_mut() {
+                    member.ty = self.type_handle(member.ty, module);
+                }
+            }
+            _ => {}
+        }
+        let new_handle = fetch_or_append(&mut module.types, ty, span);
+        self.type_map[handle.index()] = Some(new_handle);
+        new_handle
+    }
+
+    fn constant_handle(
+        &mut self,
+        handle: Handle<Constant>,
+        module: &mut Module,
+    ) -> Handle<Constant> {
+        if let Some(new_handle) = self.constant_map[handle.index()] {
+            return new_handle;
+        }
+        let (mut constant, span) = self.constants[handle.index()].take().unwrap();
+        if let ConstantInner::Composite {
+            ref mut ty,
+            ref mut components,
+        } = constant.inner
+        {
+            *ty = self.type_handle(*ty, module);
+            for component in components.iter_mut() {
+                *component = self.constant_handle(*component, module);
+            }
+        }
+        let new_handle = fetch_or_append(&mut module.constants, constant, span);
+        self.constant_map[handle.index()] = Some(new_handle);
+        new_handle
+    }
+
+    fn adjust_type(&self, handle: &mut Handle<Type>) {
+        *handle = self.type_map[handle.index()].unwrap();
+    }
+
+    fn adjust_constant(&self, handle: &mut Handle<Constant>) {
+        *handle = self.constant_map[handle.index()].unwrap();
+    }
+
+    /// Adjusts the types of the function signature.
+    fn adjust_signature(&self, function: &mut Function) {
+        for argument in function.arguments.iter_mut() {
+            self.adjust_type(&mut argument.ty);
+        }
+        if let Some(ref mut result) = function.result {
+            self.adjust_type(&mut result.ty);
+        }
+    }
+
+    /// Adjusts the module handles used in the function body.
+    fn adjust_body(&self, function: &mut Function) {
+        // the signature is adjusted on its own, when the function is matched
+        let mut adjuster = HandleAdjuster { import: self };
+        for (handle, local) in function.local_variables.iter_mut() {
+            adjuster.visit_local_variable(handle, local);
+        }
+        for (handle, expression) in function.expressions.iter_mut() {
+            adjuster.visit_expression(handle, expression);
+        }
+        adjuster.visit_block(&mut function.body);
+    }
+}
+
+/// Returns the function that stands for a linked function until its body is moved in,
+/// so that the following functions can be matched with it.
+fn placeholder(function: &Function) -> Function {
+    Function {
+        name: function.name.clone(),
+        arguments: function.arguments.clone(),
+        result: function.result.clone(),
+        ..Function::default()
+    }
+}
+
+fn same_signature(a: &Function, b: &Function) -> bool {
+    a.arguments.len() == b.arguments.len()
+        && a.arguments
+            .iter()
+            .zip(b.arguments.iter())
+            .all(|(a, b)| a.ty == b.ty)
+        && a.result.as_ref().map(|result| result.ty) == b.result.as_ref().map(|result| result.ty)
+}
+
+/// A module to link.
+#[derive(Debug, Default)]
+pub struct LinkInput {
+    /// The module to link.
+    pub module: Module,
+    /// Functions of the module that are only declared by it, to be defined
+    /// by another module. Only their name and signature are used.
+    pub declarations: Vec<Handle<Function>>,
+}
+
+impl From<Module> for LinkInput {
+    fn from(module: Module) -> Self {
+        LinkInput {
+            module,
+            declarations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Linker {
+    module: Module,
+    /// Whether each function of the linked module is still only declared.
+    declared: Vec<bool>,
+    /// Index of the input that each global variable of the linked module comes from.
+    global_inputs: Vec<usize>,
+    /// Index of the input being added.
+    input_index: usize,
+}
+
+impl Linker {
+    /// Matches a global variable with the ones of the previous inputs.
+    ///
+    /// The built-ins and locations can only collide for the entry points using them,
+    /// which is checked once all the modules are linked.
+    fn link_global(
+        &mut self,
+        global: GlobalVariable,
+        span: Span,
+    ) -> Result<Handle<GlobalVariable>, LinkError> {
+        let mut name_conflict = false;
+        for (handle, existing) in self.module.global_variables.iter() {
+            // the globals of a module are already consistent
+            if self.global_inputs[handle.index()] == self.input_index {
+                continue;
+            }
+            if let Some(ref name) = global.name {
+                if existing.name.as_ref() == Some(name) {
+                    if *existing == global {
+                        return Ok(handle);
+                    }
+                    name_conflict = true;
+                }
+            }
+            if let Some(Binding::Resource { group, binding }) = global.binding {
+                if existing.binding == global.binding {
+                    return Err(LinkError::BindingConflict { group, binding });
+                }
+            }
+        }
+        if name_conflict {
+            return Err(LinkError::GlobalConflict(global.name.unwrap()));
+        }
+        self.global_inputs.push(self.input_index);
+        Ok(self.module.global_variables.append_with_span(global, span))
+    }
+
+    /// Checks that no entry point uses the same built-in or location
+    /// from the global variables of different inputs.
+    fn check_interfaces(&self) -> Result<(), LinkError> {
+        let globals = &self.module.global_variables;
+        for entry_point in self.module.entry_points.iter() {
+            let mut collector = UsageCollector::default();
+            collector.visit_function(&entry_point.function);
+            let mut visited = vec![false; self.module.functions.len()];
+            while let Some(callee) = collector.calls.pop() {
+                if !mem::replace(&mut visited[callee.index()], true) {
+                    collector.visit_function(&self.module.functions[callee]);
+                }
+            }
+            let mut used = collector.globals;
+            used.sort();
+            used.dedup();
+            for (i, &first) in used.iter().enumerate() {
+                for &second in used[i + 1..].iter() {
+                    if self.global_inputs[first.index()] == self.global_inputs[second.index()] {
+                        continue;
+                    }
+                    let (first, second) = (&globals[first], &globals[second]);
+                    if first.class != second.class || first.binding != second.binding {
+                        continue;
+                    }
+                    match first.binding {
+                        Some(Binding::BuiltIn(built_in)) => {
+                            return Err(LinkError::BuiltInConflict {
+                                entry_point: entry_point.name.clone(),
+                                class: first.class,
+                                built_in,
+                            })
+                        }
+                        Some(Binding::Location(location)) => {
+                            return Err(LinkError::LocationConflict {
+                                entry_point: entry_point.name.clone(),
+                                class: first.class,
+                                location,
+                            })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the function of the linked module that the function resolves to,
+    /// returning whether the body of the function has to be moved there.
+    fn resolve_function(
+        &self,
+        function: &Function,
+        declared: bool,
+    ) -> Result<Option<(Handle<Function>, bool)>, LinkError> {
+        let name = match function.name {
+            Some(ref name) => name,
+            None => return Ok(None),
+        };
+        let found = self
+            .module
+            .functions
+            .iter()
+            .find(|&(_, existing)| existing.name.as_ref() == Some(name));
+        let (handle, existing) = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        if !same_signature(existing, function) {
+            return Err(LinkError::SignatureMismatch(name.clone()));
+        }
+        match (self.declared[handle.index()], declared) {
+            (_, true) => Ok(Some((handle, false))),
+            (true, false) => Ok(Some((handle, true))),
+            (false, false) => Err(LinkError::FunctionRedefinition(name.clone())),
+        }
+    }
+
+    fn add(&mut self, input: LinkInput) -> Result<(), LinkError> {
+        let source = input.module;
+        let mut declared = vec![false; source.functions.len()];
+        for handle in input.declarations {
+            declared[handle.index()] = true;
+        }
+        let mut import = Import {
+            type_map: vec![None; source.types.len()],
+            types: source.types.into_values_with_spans().map(Some).collect(),
+            constant_map: vec![None; source.constants.len()],
+            constants: source
+                .constants
+                .into_values_with_spans()
+                .map(Some)
+                .collect(),
+            global_map: Vec::with_capacity(source.global_variables.len()),
+            function_map: Vec::with_capacity(source.functions.len()),
+        };
+        for index in 0..import.types.len() {
+            import.type_handle(Handle::from_usize(index), &mut self.module);
+        }
+        for index in 0..import.constants.len() {
+            import.constant_handle(Handle::from_usize(index), &mut self.module);
+        }
+
+        for (mut global, span) in source.global_variables.into_values_with_spans() {
+            import.adjust_type(&mut global.ty);
+            if let Some(ref mut init) = global.init {
+                import.adjust_constant(init);
+            }
+            let handle = self.link_global(global, span)?;
+            import.global_map.push(handle);
+        }
+
+        // all the functions are mapped before any body is adjusted
+        let mut definitions = Vec::new();
+        let functions = source.functions.into_values_with_spans();
+        for ((mut function, span), declared) in functions.zip(declared) {
+            import.adjust_signature(&mut function);
+            let handle = match self.resolve_function(&function, declared)? {
+                Some((handle, true)) => {
+                    self.declared[handle.index()] = false;
+                    definitions.push((handle, function));
+                    handle
+                }
+                Some((handle, false)) => handle,
+                None => {
+                    let handle = self
+                        .module
+                        .functions
+                        .append_with_span(placeholder(&function), span);
+                    self.declared.push(declared);
+                    if !declared {
+                        definitions.push((handle, function));
+                    }
+                    handle
+                }
+            };
+            import.function_map.push(handle);
+        }
+        for (handle, mut function) in definitions {
+            import.adjust_body(&mut function);
+            *self.module.functions.get_mut(handle) = function;
+        }
+
+        for mut entry_point in source.entry_points {
+            if self
+                .module
+                .entry_points
+                .iter()
+                .any(|ep| ep.stage == entry_point.stage && ep.name == entry_point.name)
+            {
+                return Err(LinkError::EntryPointConflict {
+                    stage: entry_point.stage,
+                    name: entry_point.name,
+                });
+            }
+            import.adjust_signature(&mut entry_point.function);
+            import.adjust_body(&mut entry_point.function);
+            self.module.entry_points.push(entry_point);
+        }
+        self.input_index += 1;
+        Ok(())
+    }
+
+    /// Reorders the functions so that they come after the functions they call.
+    fn finish(mut self) -> Result<Module, LinkError> {
+        self.check_interfaces()?;
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Pending,
+            InProgress,
+            Done,
+        }
+        let count = self.module.functions.len();
+        let mut states = vec![State::Pending; count];
+        let mut order = Vec::with_capacity(count);
+        for index in 0..count {
+            // depth-first traversal of the call graph, with an explicit stack
+            let mut stack = vec![(Handle::<Function>::from_usize(index), Vec::new(), false)];
+            while let Some((handle, mut calls, visited)) = stack.pop() {
+                if !visited {
+                    match states[handle.index()] {
+                        State::Done => continue,
+                        State::InProgress => {
+                            let name = self.module.functions[handle].name.clone();
+                            return Err(LinkError::Recursion(name));
+                        }
+                        State::Pending => {}
+                    }
+                    states[handle.index()] = State::InProgress;
+                    let mut collector = UsageCollector::default();
+                    collector.visit_block(&self.module.functions[handle].body);
+                    calls = collector.calls;
+                    calls.reverse();
+                }
+                match calls.pop() {
+                    Some(callee) => {
+                        stack.push((handle, calls, true));
+                        if states[callee.index()] != State::Done {
+                            stack.push((callee, Vec::new(), false));
+                        }
+                    }
+                    None => {
+                        states[handle.index()] = State::Done;
+                        order.push(handle);
+                    }
+                }
+            }
+        }
+
+        let mut function_map = vec![Handle::from_usize(0); count];
+        for (new_index, &handle) in order.iter().enumerate() {
+            function_map[handle.index()] = Handle::from_usize(new_index);
+        }
+        // both the `Call` expressions and statements refer to the callee
+        let mut adjuster = CallAdjuster {
+            functions: &function_map,
+        };
+        let mut functions = mem::take(&mut self.module.functions)
+            .into_values_with_spans()
+            .map(Some)
+            .collect::<Vec<_>>();
+        for handle in order {
+            let (mut function, span) = functions[handle.index()].take().unwrap();
+            adjuster.visit_function(&mut function);
+            self.module.functions.append_with_span(function, span);
+        }
+        for entry_point in self.module.entry_points.iter_mut() {
+            adjuster.visit_function(&mut entry_point.function);
+        }
+        Ok(self.module)
+    }
+}
+
+/// Merge several modules into one.
+///
+/// Identical types and constants are merged. Global variables with the same name
+/// have to be identical, and the resource bindings can't be shared between global variables
+/// of different modules. An entry point can't use the same built-in or location from
+/// the global variables of different modules either. Functions are matched by name:
+/// the declarations of a module are resolved by the definition from any of the modules.
+/// The declarations without a definition are kept with an empty body.
+///
+/// The functions of the linked module come after the ones they call.
+pub fn link<I>(inputs: I) -> Result<Module, LinkError>
+where
+    I: IntoIterator,
+    I::Item: Into<LinkInput>,
+{
+    let mut linker = Linker::default();
+    for input in inputs {
+        linker.add(input.into())?;
+    }
+    linker.finish()
+}
+
+#[cfg(all(test, feature = "wgsl-in", feature = "ir-in"))]
+#[test]
+fn link_declarations() {
+    use super::{Interpreter, Resource, ResourceBinding, Resources};
+
+    let library = "
+        [[block]]
+        struct Values {
+            data: [[stride(4)]] array<f32>;
+        };
+        [[group(0), binding(0)]]
+        var<storage> values: [[access(read_write)]] Values;
+
+        fn square(x: f32) -> f32 {
+            return x * x;
+        }
+        fn scaled(x: f32) -> f32 {
+            return square(x) * values.data[0];
+        }
+    ";
+    let shader = "
+        type T0 = f32
+        type T1 = array<T0> @stride(4)
+        type T2 \"Values\" = struct @block {
+            \"data\": T1,
+        }
+        type T3 = vec3<u32>
+        global G0 \"values\" storage T2 @resource(0, 0) @access(load, store)
+        global G1 \"global_id\" input T3 @builtin(global_invocation_id)
+
+        fn F0 \"scaled\"(\"x\": T0) -> T0 {
+        }
+
+        entry \"main\" compute @workgroup_size(4, 1, 1)
+        fn \"main\"() {
+            %0 = global G0
+            %1 = global G1
+            %2 = access_index %0, 0
+            %3 = load %1
+            %4 = access_index %3, 0
+            %5 = access %2, %4
+            %6 = load %5
+            %7 = call F0
+            emit %2..%7
+            call F0 (%6) -> %7
+            store %5, %7
+            return
+        }
+    ";
+    let parse_library = || crate::front::wgsl::parse_str(library).unwrap();
+    let parse_shader = || crate::front::ir::parse_str(shader).unwrap();
+
+    // the shader only declares `scaled`
+    let shader_input = || LinkInput {
+        module: parse_shader(),
+        declarations: vec![Handle::from_usize(0)],
+    };
+
+    let module = link(vec![shader_input(), parse_library().into()]).unwrap();
+    super::Validator::new().validate(&module).unwrap();
+    assert_eq!(module.types.len(), 4);
+    assert_eq!(module.global_variables.len(), 2);
+    // the callees come first
+    let names = module
+        .functions
+        .iter()
+        .map(|(_, function)| function.name.as_deref().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["square", "scaled"]);
+
+    let binding = ResourceBinding {
+        group: 0,
+        binding: 0,
+    };
+    let mut resources = Resources::default();
+    let data = [3.0f32, 1.0, 2.0, -1.0];
+    let bytes = data.iter().flat_map(|v| v.to_bits().to_le_bytes().to_vec());
+    resources.insert(binding, Resource::Buffer(bytes.collect()));
+    Interpreter::new(&module)
+        .dispatch("main", [1, 1, 1], &mut resources)
+        .unwrap();
+    let bytes = match resources[&binding] {
+        Resource::Buffer(ref bytes) => bytes,
+        Resource::Image(_) => unreachable!(),
+    };
+    let values = bytes
+        .chunks(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect::<Vec<_>>();
+    // the invocations run in order, so the first one changes the scale of the others
+    assert_eq!(values, [27.0, 27.0, 108.0, 27.0]);
+
+    // an empty body doesn't make a declaration
+    assert!(matches!(
+        link(vec![parse_shader(), parse_library()]),
+        Err(LinkError::FunctionRedefinition(ref name)) if name == "scaled"
+    ));
+    assert!(matches!(
+        link(vec![parse_library(), parse_library()]),
+        Err(LinkError::FunctionRedefinition(ref name)) if name == "square"
+    ));
+    assert!(matches!(
+        link(vec![shader_input(), shader_input()]),
+        Err(LinkError::EntryPointConflict { ref name, .. }) if name == "main"
+    ));
+    let other_values = "
+        [[block]]
+        struct Values {
+            data: [[stride(4)]] array<u32>;
+        };
+        [[group(0), binding(1)]]
+        var<storage> values: [[access(read_write)]] Values;
+    ";
+    let other_values = crate::front::wgsl::parse_str(other_values).unwrap();
+    assert!(matches!(
+        link(vec![parse_library(), other_values]),
+        Err(LinkError::GlobalConflict(ref name)) if name == "values"
+    ));
+    let other_binding = "
+        [[block]]
+        struct Values {
+            data: [[stride(4)]] array<f32>;
+        };
+        [[group(0), binding(0)]]
+        var<storage> other: [[access(read)]] Values;
+    ";
+    let other_binding = crate::front::wgsl::parse_str(other_binding).unwrap();
+    assert!(matches!(
+        link(vec![parse_library(), other_binding]),
+        Err(LinkError::BindingConflict {
+            group: 0,
+            binding: 0,
+        })
+    ));
+    // the built-in only collides when the entry point uses it
+    let built_in_library = "
+        [[builtin(global_invocation_id)]] var<in> invocation_id: vec3<u32>;
+        fn scaled(x: f32) -> f32 {
+            return x * f32(invocation_id.x);
+        }
+        fn unused() -> u32 {
+            return invocation_id.y;
+        }
+    ";
+    let parse_built_in_library = || crate::front::wgsl::parse_str(built_in_library).unwrap();
+    assert!(matches!(
+        link(vec![shader_input(), parse_built_in_library().into()]),
+        Err(LinkError::BuiltInConflict {
+            ref entry_point,
+            class: StorageClass::Input,
+            built_in: BuiltIn::GlobalInvocationId,
+        }) if entry_point == "main"
+    ));
+    let mut unused_input = LinkInput::from(parse_built_in_library());
+    unused_input.declarations.push(Handle::from_usize(0));
+    link(vec![shader_input(), unused_input, parse_library().into()]).unwrap();
+
+    let fragment = "
+        type T0 = vec4<f32>
+        global G0 \"color\" output T0 @location(0)
+        global G1 \"normal\" input T0 @location(0)
+
+        fn F0 \"shade\"() {
+        }
+
+        entry \"main\" fragment
+        fn \"main\"() {
+            %0 = global G0
+            %1 = global G1
+            call F0 ()
+            return
+        }
+    ";
+    let fragment_input = || LinkInput {
+        module: crate::front::ir::parse_str(fragment).unwrap(),
+        declarations: vec![Handle::from_usize(0)],
+    };
+    let shading = |location: u32| {
+        let source = format!(
+            "
+            type T0 = vec4<f32>
+            global G0 \"extra\" output T0 @location({})
+
+            fn F0 \"shade\"() {{
+                %0 = global G0
+                return
+            }}
+            ",
+            location,
+        );
+        crate::front::ir::parse_str(&source).unwrap()
+    };
+    let module = link(vec![fragment_input(), shading(1).into()]).unwrap();
+    assert_eq!(module.global_variables.len(), 3);
+    assert!(matches!(
+        link(vec![fragment_input(), shading(0).into()]),
+        Err(LinkError::LocationConflict {
+            class: StorageClass::Output,
+            location: 0,
+            ..
+        })
+    ));
+}
+
+/// The entry points of a module can reuse the same locations and built-ins.
+#[cfg(all(test, feature = "wgsl-in"))]
+#[test]
+fn link_single_module() {
+    for source in [
+        include_str!("../../tests/in/quad.wgsl"),
+        include_str!("../../tests/in/skybox.wgsl"),
+    ]
+    .iter()
+    {
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        let globals = module.global_variables.len();
+        let linked = link(vec![module]).unwrap();
+        assert_eq!(linked.global_variables.len(), globals);
+        super::Validator::new().validate(&linked).unwrap();
+    }
+}
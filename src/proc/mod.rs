@@ -1,17 +1,47 @@
 //! Module processing functionality.
 
 pub mod analyzer;
+mod builder;
+mod compactor;
+mod evaluator;
+mod inliner;
+mod interpreter;
 mod layouter;
+mod linker;
 mod namer;
+mod reflector;
+mod specializer;
 mod terminator;
 mod typifier;
 mod validator;
+pub mod visitor;
 
+pub use builder::{FunctionBuilder, ModuleBuilder, SwitchBuilder};
+pub use compactor::{compact, extract_entry_point};
+pub use evaluator::{fold_constants, ConstantEvaluator, ConstantEvaluatorError, FoldError};
+pub use inliner::{inline_calls, InlineError, InlineOptions};
+pub use interpreter::{Image, Interpreter, InterpreterError, Resource, Resources};
 pub use layouter::{Alignment, Layouter};
+pub use linker::{link, LinkError, LinkInput};
 pub use namer::{EntryPointIndex, NameKey, Namer};
+pub use reflector::{
+    reflect, BoundResource, EntryPointReflection, InterfaceFormat, InterfaceVariable,
+    PushConstantRange, ResourceType,
+};
+pub use specializer::{specialize, SpecializationError};
 pub use terminator::ensure_block_returns;
 pub use typifier::{ResolveContext, ResolveError, Typifier, TypifyError};
 pub use validator::{TypeFlags, ValidationError, Validator};
+pub use visitor::{Visitor, VisitorMut};
+
+/// Resource binding of a global variable.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct ResourceBinding {
+    pub group: u32,
+    pub binding: u32,
+}
 
 impl From<super::StorageFormat> for super::ScalarKind {
     fn from(format: super::StorageFormat) -> Self {
@@ -64,6 +94,20 @@ impl super::ScalarValue {
     }
 }
 
+impl super::Constant {
+    /// Returns `true` if the value of this constant can be overridden
+    /// by the pipeline, directly or through any of its components.
+    pub fn is_specializable(&self, constants: &super::Arena<super::Constant>) -> bool {
+        self.specialization.is_some()
+            || match self.inner {
+                super::ConstantInner::Scalar { .. } => false,
+                super::ConstantInner::Composite { ref components, .. } => components
+                    .iter()
+                    .any(|&handle| constants[handle].is_specializable(constants)),
+            }
+    }
+}
+
 impl super::TypeInner {
     pub fn scalar_kind(&self) -> Option<super::ScalarKind> {
         match *self {
@@ -137,6 +181,31 @@ impl super::MathFunction {
     }
 }
 
+impl super::GeometryInput {
+    /// Returns the number of vertices in an input primitive,
+    /// which is the size of the per-vertex input arrays.
+    pub fn vertex_count(&self) -> u32 {
+        match *self {
+            Self::Points => 1,
+            Self::Lines => 2,
+            Self::LinesAdjacency => 4,
+            Self::Triangles => 3,
+            Self::TrianglesAdjacency => 6,
+        }
+    }
+}
+
+impl super::BuiltIn {
+    /// Returns true if the built-in is a per-vertex value,
+    /// which geometry and tessellation stages access as an array.
+    pub fn is_per_vertex(&self) -> bool {
+        match *self {
+            Self::Position | Self::PointSize | Self::ClipDistance => true,
+            _ => false,
+        }
+    }
+}
+
 impl crate::Expression {
     /// Returns true if the expression is considered emitted at the start of a function.
     pub fn needs_pre_emit(&self) -> bool {
@@ -149,3 +218,81 @@ impl crate::Expression {
         }
     }
 }
+
+/// Converts a float to the bits of the nearest half-precision float.
+///
+/// Values out of range become infinities, and the rounding is to nearest even.
+pub fn f16_to_bits(value: f64) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 48) & 0x8000) as u16;
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    let mantissa = bits & 0xf_ffff_ffff_ffff;
+
+    if exponent == 0x7ff {
+        let nan_bit = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan_bit;
+    }
+    let half_exponent = exponent - 1023 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (half, round_bit, mantissa) = if half_exponent <= 0 {
+        // subnormal half, or zero
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x10_0000_0000_0000;
+        let shift = (43 - half_exponent) as u32;
+        (mantissa >> shift, 1 << (shift - 1), mantissa)
+    } else {
+        (
+            (half_exponent as u64) << 10 | mantissa >> 42,
+            1 << 41,
+            mantissa,
+        )
+    };
+    // round up if above the halfway point, or exactly on it with an odd result,
+    // a carry out of the mantissa correctly bumps the exponent
+    let round_up = mantissa & round_bit != 0 && mantissa & (3 * round_bit - 1) != 0;
+    sign | (half + round_up as u64) as u16
+}
+
+/// Converts the bits of a half-precision float to a float.
+pub fn f16_from_bits(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+#[test]
+fn f16_bits() {
+    for &(value, bits) in &[
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.0, 0xc000),
+        (0.5, 0x3800),
+        (65504.0, 0x7bff),
+        (2f64.powi(-24), 0x0001),
+        (2f64.powi(-14), 0x0400),
+        (f64::INFINITY, 0x7c00),
+    ] {
+        assert_eq!(f16_to_bits(value), bits, "{}", value);
+        assert_eq!(f16_from_bits(bits), value, "{:#x}", bits);
+    }
+    // rounding to nearest even
+    assert_eq!(f16_to_bits(1.0 + 2f64.powi(-11)), 0x3c00);
+    assert_eq!(f16_to_bits(1.0 + 3.0 * 2f64.powi(-11)), 0x3c02);
+    // just above the halfway point, which a single-precision value can't tell
+    assert_eq!(f16_to_bits(1.0 + 2f64.powi(-11) + 2f64.powi(-40)), 0x3c01);
+    assert_eq!(f16_to_bits(2f64.powi(-25)), 0x0000);
+    assert_eq!(f16_to_bits(3.0 * 2f64.powi(-25)), 0x0002);
+    assert_eq!(f16_to_bits(65520.0), 0x7c00);
+    assert!(f16_from_bits(f16_to_bits(f64::NAN)).is_nan());
+}
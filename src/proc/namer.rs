@@ -1,5 +1,4 @@
 use crate::{arena::Handle, FastHashMap};
-use std::collections::hash_map::Entry;
 
 pub type EntryPointIndex = u16;
 
@@ -13,6 +12,7 @@ pub enum NameKey {
     FunctionArgument(Handle<crate::Function>, u32),
     FunctionLocal(Handle<crate::Function>, Handle<crate::LocalVariable>),
     EntryPoint(EntryPointIndex),
+    EntryPointArgument(EntryPointIndex, u32),
     EntryPointLocal(EntryPointIndex, Handle<crate::LocalVariable>),
 }
 
@@ -25,36 +25,49 @@ pub struct Namer {
 
 impl Namer {
     fn sanitize(string: &str) -> String {
-        let mut base = string
+        let base = string
             .chars()
             .skip_while(|c| c.is_numeric())
             .filter(|&c| c.is_ascii_alphanumeric() || c == '_')
             .collect::<String>();
-        // close the name by '_' if the re is a number, so that
-        // we can have our own number!
-        match base.chars().next_back() {
-            Some(c) if !c.is_numeric() => {}
-            _ => base.push('_'),
-        };
-        base
+        if base.is_empty() {
+            "_".to_string()
+        } else {
+            base
+        }
     }
 
+    /// Returns a unique name based on the label.
+    ///
+    /// The label is kept as it is if it's not taken yet, so that the names
+    /// of a module stay the same once written and parsed again.
     pub fn call(&mut self, label_raw: &str) -> String {
         let base = Self::sanitize(label_raw);
-        match self.unique.entry(base) {
-            Entry::Occupied(mut e) => {
-                *e.get_mut() += 1;
-                format!("{}{}", e.key(), e.get())
+        let mut counter = match self.unique.get(&base) {
+            Some(&counter) => counter,
+            None => {
+                self.unique.insert(base.clone(), 0);
+                return base;
             }
-            Entry::Vacant(e) => {
-                let name = e.key().to_string();
-                e.insert(0);
-                name
+        };
+        // close the name by '_' if it ends with a number, so that
+        // we can have our own number!
+        let separator = match base.chars().next_back() {
+            Some(c) if c.is_numeric() => "_",
+            _ => "",
+        };
+        loop {
+            counter += 1;
+            let name = format!("{}{}{}", base, separator, counter);
+            if !self.unique.contains_key(&name) {
+                self.unique.insert(base, counter);
+                self.unique.insert(name.clone(), 0);
+                return name;
             }
         }
     }
 
-    fn call_or(&mut self, label: &Option<String>, fallback: &str) -> String {
+    pub fn call_or(&mut self, label: &Option<String>, fallback: &str) -> String {
         self.call(match *label {
             Some(ref name) => name,
             None => fallback,
@@ -160,6 +173,13 @@ impl Namer {
         for (ep_index, ep) in module.entry_points.iter().enumerate() {
             let ep_name = self.call(&ep.name);
             output.insert(NameKey::EntryPoint(ep_index as _), ep_name);
+            for (index, arg) in ep.function.arguments.iter().enumerate() {
+                let name = self.call_or(&arg.name, "param");
+                output.insert(
+                    NameKey::EntryPointArgument(ep_index as _, index as u32),
+                    name,
+                );
+            }
             for (handle, var) in ep.function.local_variables.iter() {
                 let name = self.call_or(&var.name, "local");
                 output.insert(NameKey::EntryPointLocal(ep_index as _, handle), name);
@@ -0,0 +1,464 @@
+/*! Reflection of the entry point interfaces.
+
+The resources are gathered from the global variable uses of the [`Analysis`],
+which include the uses of the called functions.
+!*/
+
+use super::{
+    analyzer::{Analysis, GlobalUse, SamplingKey},
+    visitor::{walk_expression, walk_function, Visitor},
+    Layouter, ResourceBinding,
+};
+use crate::{
+    arena::{Arena, Handle},
+    Binding, Bytes, EntryPoint, Expression, Function, GlobalVariable, ImageClass, ImageDimension,
+    Module, ScalarKind, ShaderStage, StorageAccess, StorageClass, Type, TypeInner, VectorSize,
+};
+
+/// Kind of a bound resource.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum ResourceType {
+    UniformBuffer,
+    StorageBuffer,
+    Image {
+        dim: ImageDimension,
+        arrayed: bool,
+        class: ImageClass,
+    },
+    Sampler {
+        comparison: bool,
+    },
+    AccelerationStructure,
+}
+
+/// Global variable bound to a resource, used by an entry point.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct BoundResource {
+    pub global: Handle<GlobalVariable>,
+    pub name: Option<String>,
+    pub binding: ResourceBinding,
+    pub ty: ResourceType,
+    /// Access declared for the storage buffers and images.
+    pub access: StorageAccess,
+    /// How the entry point actually uses the resource.
+    pub usage: GlobalUse,
+    /// Size of a buffer, counting a single element for a runtime-sized array,
+    /// which makes it the minimal binding size.
+    pub size: Option<u32>,
+}
+
+/// Range of the push constants used by an entry point.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct PushConstantRange {
+    pub global: Handle<GlobalVariable>,
+    /// Offset of the first member used, if only some members of a structure are.
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// Format of a value passed between the pipeline stages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct InterfaceFormat {
+    pub kind: ScalarKind,
+    pub width: Bytes,
+    /// Size of a vector, or `None` for a scalar.
+    pub size: Option<VectorSize>,
+}
+
+/// Input or output of an entry point at a location.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct InterfaceVariable {
+    pub name: Option<String>,
+    pub location: u32,
+    pub format: InterfaceFormat,
+}
+
+/// Interface of an entry point, as seen by the pipeline.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct EntryPointReflection {
+    pub name: String,
+    pub stage: ShaderStage,
+    /// Resources used by the entry point, sorted by binding.
+    pub resources: Vec<BoundResource>,
+    /// Images sampled together with samplers.
+    pub sampling_pairs: Vec<SamplingKey>,
+    pub push_constants: Vec<PushConstantRange>,
+    /// Vertex attributes of a vertex stage, sorted by location.
+    pub vertex_inputs: Vec<InterfaceVariable>,
+    /// Color outputs of a fragment stage, sorted by location.
+    pub fragment_outputs: Vec<InterfaceVariable>,
+    pub workgroup_size: [u32; 3],
+}
+
+fn interface_format(inner: &TypeInner) -> Option<InterfaceFormat> {
+    match *inner {
+        TypeInner::Scalar { kind, width } => Some(InterfaceFormat {
+            kind,
+            width,
+            size: None,
+        }),
+        TypeInner::Vector { size, kind, width } => Some(InterfaceFormat {
+            kind,
+            width,
+            size: Some(size),
+        }),
+        _ => None,
+    }
+}
+
+/// Collects the values bound to a location, including the members of a structure.
+fn collect_locations(
+    name: Option<&String>,
+    ty: Handle<Type>,
+    binding: Option<&Binding>,
+    types: &Arena<Type>,
+    variables: &mut Vec<InterfaceVariable>,
+) {
+    match (binding, &types[ty].inner) {
+        (Some(&Binding::Location(location)), inner) => {
+            if let Some(format) = interface_format(inner) {
+                variables.push(InterfaceVariable {
+                    name: name.cloned(),
+                    location,
+                    format,
+                });
+            }
+        }
+        (None, TypeInner::Struct { members, .. }) => {
+            for member in members.iter() {
+                collect_locations(
+                    member.name.as_ref(),
+                    member.ty,
+                    member.binding.as_ref(),
+                    types,
+                    variables,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects the members of a global variable accessed by the visited functions.
+struct MemberUsage {
+    global: Handle<GlobalVariable>,
+    /// Expressions of the current function referring to the variable.
+    pointers: Vec<Handle<Expression>>,
+    /// Indices of the accessed members, or `None` once the variable is used as a whole.
+    members: Option<Vec<u32>>,
+    calls: Vec<Handle<Function>>,
+}
+
+impl Visitor for MemberUsage {
+    fn visit_function(&mut self, function: &Function) {
+        self.pointers.clear();
+        walk_function(self, function);
+    }
+    fn visit_expression(&mut self, handle: Handle<Expression>, expression: &Expression) {
+        match *expression {
+            Expression::GlobalVariable(global) if global == self.global => {
+                self.pointers.push(handle)
+            }
+            Expression::AccessIndex { base, index } if self.pointers.contains(&base) => {
+                if let Some(ref mut members) = self.members {
+                    members.push(index);
+                }
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+    fn visit_expression_handle(&mut self, handle: &Handle<Expression>) {
+        if self.pointers.contains(handle) {
+            self.members = None;
+        }
+    }
+    fn visit_function_handle(&mut self, handle: &Handle<Function>) {
+        self.calls.push(*handle);
+    }
+}
+
+/// Returns the range of a push constant variable used by an entry point,
+/// which only covers the used members of a structure.
+fn push_constant_range(
+    module: &Module,
+    layouter: &Layouter,
+    entry_point: &EntryPoint,
+    handle: Handle<GlobalVariable>,
+) -> PushConstantRange {
+    let ty = module.global_variables[handle].ty;
+    let mut range = PushConstantRange {
+        global: handle,
+        offset: 0,
+        size: layouter.resolve(ty).size,
+    };
+    let members = match module.types[ty].inner {
+        TypeInner::Struct { ref members, .. } => members,
+        _ => return range,
+    };
+
+    let mut usage = MemberUsage {
+        global: handle,
+        pointers: Vec::new(),
+        members: Some(Vec::new()),
+        calls: Vec::new(),
+    };
+    usage.visit_function(&entry_point.function);
+    let mut visited = vec![false; module.functions.len()];
+    while let Some(function) = usage.calls.pop() {
+        if !std::mem::replace(&mut visited[function.index()], true) {
+            usage.visit_function(&module.functions[function]);
+        }
+    }
+    let used = match usage.members {
+        Some(ref used) if !used.is_empty() => used,
+        _ => return range,
+    };
+
+    // same layout as the structure members of the SPIR-V backend
+    let (mut start, mut end) = (u32::MAX, 0);
+    let mut offset = 0;
+    for (index, member) in members.iter().enumerate() {
+        let layout = layouter.resolve(member.ty);
+        offset += layout.pad(offset);
+        let size = match member.span {
+            Some(span) => span.get(),
+            None => layout.size,
+        };
+        if used.contains(&(index as u32)) {
+            start = start.min(offset);
+            end = end.max(offset + size);
+        }
+        offset += size;
+    }
+    range.offset = start;
+    range.size = end - start;
+    range
+}
+
+/// Describe the interface of each entry point of the module.
+///
+/// The analysis has to come from the validation of the same module.
+pub fn reflect(module: &Module, analysis: &Analysis) -> Vec<EntryPointReflection> {
+    let layouter = Layouter::new(&module.types, &module.constants);
+    module
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(index, entry_point)| {
+            let info = analysis.get_entry_point(index);
+            let mut reflection = EntryPointReflection {
+                name: entry_point.name.clone(),
+                stage: entry_point.stage,
+                resources: Vec::new(),
+                sampling_pairs: info.sampling_set.iter().cloned().collect(),
+                push_constants: Vec::new(),
+                vertex_inputs: Vec::new(),
+                fragment_outputs: Vec::new(),
+                workgroup_size: entry_point.workgroup_size,
+            };
+            reflection
+                .sampling_pairs
+                .sort_by_key(|key| (key.image, key.sampler));
+
+            let function = &entry_point.function;
+            match entry_point.stage {
+                ShaderStage::Vertex => {
+                    for argument in function.arguments.iter() {
+                        collect_locations(
+                            argument.name.as_ref(),
+                            argument.ty,
+                            argument.binding.as_ref(),
+                            &module.types,
+                            &mut reflection.vertex_inputs,
+                        );
+                    }
+                }
+                ShaderStage::Fragment => {
+                    if let Some(ref result) = function.result {
+                        collect_locations(
+                            None,
+                            result.ty,
+                            result.binding.as_ref(),
+                            &module.types,
+                            &mut reflection.fragment_outputs,
+                        );
+                    }
+                }
+                _ => {}
+            }
+
+            for (handle, global) in module.global_variables.iter() {
+                let usage = info[handle];
+                if usage.is_empty() {
+                    continue;
+                }
+                let inner = &module.types[global.ty].inner;
+                match (global.class, global.binding.as_ref()) {
+                    (StorageClass::Input, binding) if entry_point.stage == ShaderStage::Vertex => {
+                        collect_locations(
+                            global.name.as_ref(),
+                            global.ty,
+                            binding,
+                            &module.types,
+                            &mut reflection.vertex_inputs,
+                        );
+                    }
+                    (StorageClass::Output, binding)
+                        if entry_point.stage == ShaderStage::Fragment =>
+                    {
+                        collect_locations(
+                            global.name.as_ref(),
+                            global.ty,
+                            binding,
+                            &module.types,
+                            &mut reflection.fragment_outputs,
+                        );
+                    }
+                    (StorageClass::PushConstant, _) => {
+                        reflection.push_constants.push(push_constant_range(
+                            module,
+                            &layouter,
+                            entry_point,
+                            handle,
+                        ));
+                    }
+                    (class, Some(&Binding::Resource { group, binding })) => {
+                        let (ty, size) = match *inner {
+                            TypeInner::Image {
+                                dim,
+                                arrayed,
+                                class,
+                            } => (
+                                ResourceType::Image {
+                                    dim,
+                                    arrayed,
+                                    class,
+                                },
+                                None,
+                            ),
+                            TypeInner::Sampler { comparison } => {
+                                (ResourceType::Sampler { comparison }, None)
+                            }
+                            TypeInner::AccelerationStructure => {
+                                (ResourceType::AccelerationStructure, None)
+                            }
+                            _ => {
+                                let ty = if class == StorageClass::Uniform {
+                                    ResourceType::UniformBuffer
+                                } else {
+                                    ResourceType::StorageBuffer
+                                };
+                                (ty, Some(layouter.resolve(global.ty).size))
+                            }
+                        };
+                        reflection.resources.push(BoundResource {
+                            global: handle,
+                            name: global.name.clone(),
+                            binding: ResourceBinding { group, binding },
+                            ty,
+                            access: global.storage_access,
+                            usage,
+                            size,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+
+            reflection
+                .resources
+                .sort_by_key(|resource| resource.binding);
+            reflection
+                .vertex_inputs
+                .sort_by_key(|variable| variable.location);
+            reflection
+                .fragment_outputs
+                .sort_by_key(|variable| variable.location);
+            reflection
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+#[test]
+fn reflect_push_constants() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        [[block]]
+        struct Constants {
+            scale: f32;
+            bias: f32;
+            offset: vec2<f32>;
+            index: u32;
+        };
+        [[block]]
+        struct Data {
+            values: [[stride(4)]] array<f32>;
+        };
+        var<push_constant> pc: Constants;
+        [[group(1), binding(2)]]
+        var<storage> data: [[access(read)]] Data;
+
+        fn get_index() -> u32 {
+            return pc.index;
+        }
+
+        [[stage(vertex)]]
+        fn vs_main() -> [[builtin(position)]] vec4<f32> {
+            return vec4<f32>(pc.bias, f32(get_index()), data.values[0], 1.0);
+        }
+
+        [[stage(fragment)]]
+        fn fs_main() -> [[location(0)]] vec4<f32> {
+            const constants: Constants = pc;
+            return vec4<f32>(constants.offset, constants.scale, 1.0);
+        }
+        ",
+    )
+    .unwrap();
+    let analysis = super::Validator::new().validate(&module).unwrap();
+    let reflection = reflect(&module, &analysis);
+    let pc = module
+        .global_variables
+        .fetch_if(|var| var.name.as_deref() == Some("pc"));
+
+    // `bias` and `index`, through the call
+    let vs = &reflection[0];
+    assert_eq!(
+        vs.push_constants,
+        [PushConstantRange {
+            global: pc.unwrap(),
+            offset: 4,
+            size: 16,
+        }]
+    );
+    assert_eq!(vs.resources.len(), 1);
+    assert_eq!(
+        vs.resources[0].binding,
+        ResourceBinding {
+            group: 1,
+            binding: 2
+        }
+    );
+    assert_eq!(vs.resources[0].ty, ResourceType::StorageBuffer);
+    assert_eq!(vs.resources[0].size, Some(4));
+
+    // loaded as a whole
+    let fs = &reflection[1];
+    assert_eq!(fs.push_constants[0].offset, 0);
+    assert_eq!(fs.push_constants[0].size, 20);
+    assert!(fs.resources.is_empty());
+    assert_eq!(fs.fragment_outputs[0].location, 0);
+}
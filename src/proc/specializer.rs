@@ -0,0 +1,222 @@
+/*! Specialization constants.
+
+The new values are written into the scalar constants in place, so every handle
+of the module stays valid, and the composite constants and expressions referring
+to them see the new values without being touched. What depends on the values
+themselves is then brought up to date: the array sizes are checked again, and
+the spans of the struct members are adjusted to the new sizes of their types.
+!*/
+
+use super::Layouter;
+use crate::{
+    arena::Handle, ArraySize, Constant, ConstantInner, FastHashMap, Module, ScalarValue, TypeInner,
+};
+use std::num::NonZeroU32;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum SpecializationError {
+    #[error("Value {value:?} doesn't match the constant {handle:?} with specialization ID {id}")]
+    InvalidValue {
+        handle: Handle<Constant>,
+        id: u32,
+        value: ScalarValue,
+    },
+    #[error("Constant {0:?} is not a valid array size after specialization")]
+    InvalidArraySize(Handle<Constant>),
+}
+
+fn is_compatible(width: crate::Bytes, old: &ScalarValue, new: &ScalarValue) -> bool {
+    use std::convert::TryFrom;
+    match (old, new) {
+        (&ScalarValue::Sint(_), &ScalarValue::Sint(value)) => {
+            width == 8 || i32::try_from(value).is_ok()
+        }
+        (&ScalarValue::Uint(_), &ScalarValue::Uint(value)) => {
+            width == 8 || u32::try_from(value).is_ok()
+        }
+        (&ScalarValue::Float(_), &ScalarValue::Float(_))
+        | (&ScalarValue::Bool(_), &ScalarValue::Bool(_)) => true,
+        _ => false,
+    }
+}
+
+/// Freeze the specializable constants of a module.
+///
+/// Every constant with a specialization ID found in `values` gets the new value,
+/// and stops being specializable. Other specializable constants keep their defaults,
+/// and can still be overridden by the pipeline.
+///
+/// Composite constants refer to their components by handle, so they pick up the
+/// new values as is. Array sizes are checked again, and the spans of struct members
+/// holding arrays are adjusted to the new array lengths.
+pub fn specialize(
+    module: &mut Module,
+    values: &FastHashMap<u32, ScalarValue>,
+) -> Result<(), SpecializationError> {
+    let old_layouter = Layouter::new(&module.types, &module.constants);
+
+    let mut updates = Vec::new();
+    for (handle, constant) in module.constants.iter() {
+        let id = match constant.specialization {
+            Some(id) => id,
+            None => continue,
+        };
+        let new_value = match values.get(&id) {
+            Some(value) => value,
+            None => continue,
+        };
+        match constant.inner {
+            ConstantInner::Scalar { width, ref value }
+                if is_compatible(width, value, new_value) =>
+            {
+                updates.push((handle, new_value.clone()));
+            }
+            _ => {
+                return Err(SpecializationError::InvalidValue {
+                    handle,
+                    id,
+                    value: new_value.clone(),
+                })
+            }
+        }
+    }
+    if updates.is_empty() {
+        return Ok(());
+    }
+    for (handle, new_value) in updates {
+        let constant = module.constants.get_mut(handle);
+        if let ConstantInner::Scalar { ref mut value, .. } = constant.inner {
+            *value = new_value;
+        }
+        constant.specialization = None;
+    }
+
+    for (_, ty) in module.types.iter() {
+        if let TypeInner::Array {
+            size: ArraySize::Constant(handle),
+            ..
+        } = ty.inner
+        {
+            match module.constants[handle].inner {
+                ConstantInner::Scalar {
+                    width: _,
+                    value: ScalarValue::Uint(value),
+                } if value > 0 => {}
+                ConstantInner::Scalar {
+                    width: _,
+                    value: ScalarValue::Sint(value),
+                } if value > 0 => {}
+                _ => return Err(SpecializationError::InvalidArraySize(handle)),
+            }
+        }
+    }
+
+    // Struct member spans include the padding after the member, which is kept.
+    // Nested structs only see the new sizes of their members on the next
+    // iteration, so keep going until nothing changes.
+    let mut spans = Vec::new();
+    for (handle, ty) in module.types.iter() {
+        if let TypeInner::Struct { ref members, .. } = ty.inner {
+            for (index, member) in members.iter().enumerate() {
+                if let Some(span) = member.span {
+                    let old_size = old_layouter.resolve(member.ty).size;
+                    spans.push((handle, index, span.get(), old_size));
+                }
+            }
+        }
+    }
+    loop {
+        let layouter = Layouter::new(&module.types, &module.constants);
+        let mut changed = false;
+        for &(handle, index, old_span, old_size) in spans.iter() {
+            if let TypeInner::Struct {
+                ref mut members, ..
+            } = module.types.get_mut(handle).inner
+            {
+                let member = &mut members[index];
+                let new_size = layouter.resolve(member.ty).size;
+                let span = NonZeroU32::new((old_span + new_size).saturating_sub(old_size));
+                if member.span != span {
+                    member.span = span;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn specialize_array_size() {
+    use crate::Type;
+
+    let mut module = Module::default();
+    let length = module.constants.append(Constant {
+        name: Some("length".to_string()),
+        specialization: Some(3),
+        inner: ConstantInner::Scalar {
+            width: 4,
+            value: ScalarValue::Uint(2),
+        },
+    });
+    let float = module.types.append(Type {
+        name: None,
+        inner: TypeInner::Scalar {
+            kind: crate::ScalarKind::Float,
+            width: 4,
+        },
+    });
+    let array = module.types.append(Type {
+        name: None,
+        inner: TypeInner::Array {
+            base: float,
+            size: ArraySize::Constant(length),
+            stride: NonZeroU32::new(4),
+        },
+    });
+    let structure = module.types.append(Type {
+        name: None,
+        inner: TypeInner::Struct {
+            block: true,
+            members: vec![crate::StructMember {
+                name: None,
+                span: NonZeroU32::new(12),
+                ty: array,
+                binding: None,
+                interpolation: None,
+            }],
+        },
+    });
+
+    let mut values = FastHashMap::default();
+    values.insert(3, ScalarValue::Sint(5));
+    assert_eq!(
+        specialize(&mut module, &values),
+        Err(SpecializationError::InvalidValue {
+            handle: length,
+            id: 3,
+            value: ScalarValue::Sint(5),
+        })
+    );
+
+    values.insert(3, ScalarValue::Uint(5));
+    specialize(&mut module, &values).unwrap();
+    let constant = &module.constants[length];
+    assert_eq!(constant.specialization, None);
+    assert_eq!(
+        constant.inner,
+        ConstantInner::Scalar {
+            width: 4,
+            value: ScalarValue::Uint(5),
+        }
+    );
+    assert!(matches!(
+        module.types[structure].inner,
+        TypeInner::Struct { ref members, .. } if members[0].span == NonZeroU32::new(24)
+    ));
+}
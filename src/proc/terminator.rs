@@ -34,10 +34,19 @@ pub fn ensure_block_returns(block: &mut crate::Block) {
         | Some(&mut S::Break)
         | Some(&mut S::Continue)
         | Some(&mut S::Return { .. })
-        | Some(&mut S::Kill) => (),
+        | Some(&mut S::Kill)
+        | Some(&mut S::IgnoreIntersection)
+        | Some(&mut S::TerminateRay) => (),
         Some(&mut S::Loop { .. })
         | Some(&mut S::Store { .. })
         | Some(&mut S::ImageStore { .. })
+        | Some(&mut S::Atomic { .. })
+        | Some(&mut S::Barrier { .. })
+        | Some(&mut S::Subgroup { .. })
+        | Some(&mut S::EmitVertex)
+        | Some(&mut S::EndPrimitive)
+        | Some(&mut S::TraceRay { .. })
+        | Some(&mut S::ReportIntersection { .. })
         | Some(&mut S::Call { .. })
         | None => block.push(S::Return { value: None }),
     }
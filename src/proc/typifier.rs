@@ -335,7 +335,10 @@ impl Typifier {
                 })
             }
             crate::Expression::Load { pointer } => match *self.get(pointer, types) {
-                Ti::Pointer { base, class: _ } => Resolution::Handle(base),
+                Ti::Pointer { base, class: _ } => match types[base].inner {
+                    Ti::Atomic { kind, width } => Resolution::Value(Ti::Scalar { kind, width }),
+                    _ => Resolution::Handle(base),
+                },
                 Ti::ValuePointer {
                     size,
                     kind,
@@ -647,7 +650,9 @@ impl Typifier {
             },
             crate::Expression::Call(function) => {
                 let ty = ctx.functions[function]
-                    .return_type
+                    .result
+                    .as_ref()
+                    .map(|result| result.ty)
                     .ok_or(ResolveError::FunctionReturnsVoid)?;
                 Resolution::Handle(ty)
             }
@@ -655,6 +660,29 @@ impl Typifier {
                 kind: crate::ScalarKind::Uint,
                 width: 4,
             }),
+            crate::Expression::AtomicResult {
+                kind,
+                width,
+                comparison,
+            } => Resolution::Value(if comparison {
+                Ti::Vector {
+                    size: crate::VectorSize::Bi,
+                    kind,
+                    width,
+                }
+            } else {
+                Ti::Scalar { kind, width }
+            }),
+            crate::Expression::SubgroupResult { kind, width, size } => {
+                Resolution::Value(match size {
+                    Some(size) => Ti::Vector { size, kind, width },
+                    None => Ti::Scalar { kind, width },
+                })
+            }
+            crate::Expression::ReportIntersectionResult => Resolution::Value(Ti::Scalar {
+                kind: crate::ScalarKind::Bool,
+                width: crate::BOOL_WIDTH,
+            }),
         })
     }
 
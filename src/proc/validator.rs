@@ -1,15 +1,17 @@
 use super::{
-    analyzer::{Analysis, AnalysisError, FunctionInfo, GlobalUse},
+    analyzer::{Analysis, AnalysisError, FunctionInfo, GlobalUse, RayTracingOps},
     typifier::{ResolveContext, Typifier, TypifyError},
 };
 use crate::{
     arena::{Arena, Handle},
-    FastHashSet,
+    FastHashSet, Span,
 };
 use bit_set::BitSet;
 use thiserror::Error;
 
 const MAX_WORKGROUP_SIZE: u32 = 0x4000;
+const MAX_GEOMETRY_VERTICES: u32 = 0x100;
+const MAX_PATCH_VERTICES: u32 = 32;
 
 bitflags::bitflags! {
     #[repr(transparent)]
@@ -22,6 +24,8 @@ bitflags::bitflags! {
         const INTERFACE = 0x4;
         /// Can be used for host-shareable structures.
         const HOST_SHARED = 0x8;
+        /// Can be copied by value, i.e. doesn't contain atomics.
+        const COPY = 0x10;
     }
 }
 
@@ -72,10 +76,14 @@ pub struct Validator {
     type_flags: Vec<TypeFlags>,
     location_in_mask: BitSet,
     location_out_mask: BitSet,
+    /// Built-ins used by the entry point, and whether they are outputs.
+    built_in_set: FastHashSet<(crate::BuiltIn, bool)>,
     bind_group_masks: Vec<BitSet>,
     select_cases: FastHashSet<i32>,
     valid_expression_list: Vec<Handle<crate::Expression>>,
     valid_expression_set: BitSet,
+    /// Span of the statement being validated.
+    statement_span: Span,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -94,6 +102,8 @@ pub enum TypeError {
     InvalidArraySizeConstant(Handle<crate::Constant>),
     #[error("Field '{0}' can't be dynamically-sized, has type {1:?}")]
     InvalidDynamicArray(String, Handle<crate::Type>),
+    #[error("Atomic of {0:?} is not supported")]
+    InvalidAtomicKind(crate::ScalarKind),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -104,6 +114,8 @@ pub enum ConstantError {
     UnresolvedComponent(Handle<crate::Constant>),
     #[error("The array size handle {0:?} can not be resolved")]
     UnresolvedSize(Handle<crate::Constant>),
+    #[error("Only scalar constants can be specialized")]
+    InvalidSpecialization,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -132,6 +144,8 @@ pub enum GlobalVariableError {
 
 #[derive(Clone, Debug, Error)]
 pub enum LocalVariableError {
+    #[error("Local variable has a type {0:?} that can't be stored in a local")]
+    InvalidType(Handle<crate::Type>),
     #[error("Initializer doesn't match the variable type")]
     InitializerType,
 }
@@ -173,6 +187,44 @@ pub enum CallError {
     },
 }
 
+#[derive(Clone, Debug, Error)]
+pub enum AtomicError {
+    #[error("Pointer {0:?} to atomic is invalid.")]
+    InvalidPointer(Handle<crate::Expression>),
+    #[error("Operand {0:?} has invalid type.")]
+    InvalidOperand(Handle<crate::Expression>),
+    #[error("Result expression {0:?} has already been introduced earlier")]
+    ResultAlreadyInScope(Handle<crate::Expression>),
+    #[error("Result type for {0:?} doesn't match the statement")]
+    ResultTypeMismatch(Handle<crate::Expression>),
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum SubgroupError {
+    #[error("Operand {0:?} has invalid type.")]
+    InvalidOperand(Handle<crate::Expression>),
+    #[error("Operation {0:?} can't be used as {1:?} on the operand type")]
+    InvalidOperation(crate::SubgroupOperation, crate::CollectiveOperation),
+    #[error("Result expression {0:?} has already been introduced earlier")]
+    ResultAlreadyInScope(Handle<crate::Expression>),
+    #[error("Result type for {0:?} doesn't match the statement")]
+    ResultTypeMismatch(Handle<crate::Expression>),
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum RayTracingError {
+    #[error("Acceleration structure {0:?} is invalid.")]
+    InvalidAccelerationStructure(Handle<crate::Expression>),
+    #[error("Operand {0:?} has invalid type.")]
+    InvalidOperand(Handle<crate::Expression>),
+    #[error("Payload {0:?} doesn't point to a ray payload variable.")]
+    InvalidPayload(Handle<crate::Expression>),
+    #[error("Result expression {0:?} has already been introduced earlier")]
+    ResultAlreadyInScope(Handle<crate::Expression>),
+    #[error("Result type for {0:?} doesn't match the statement")]
+    ResultTypeMismatch(Handle<crate::Expression>),
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum FunctionError {
     #[error(transparent)]
@@ -229,6 +281,34 @@ pub enum FunctionError {
         #[source]
         error: CallError,
     },
+    #[error("Atomic operation is invalid")]
+    InvalidAtomic(#[from] AtomicError),
+    #[error("Subgroup operation is invalid")]
+    InvalidSubgroup(#[from] SubgroupError),
+    #[error("Ray tracing operation is invalid")]
+    InvalidRayTracing(#[from] RayTracingError),
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum VaryingError {
+    #[error("The type {0:?} does not match the varying")]
+    InvalidType(Handle<crate::Type>),
+    #[error("Interpolation is not valid")]
+    InvalidInterpolation,
+    #[error("Interpolation of an integer has to be flat")]
+    InvalidIntegerInterpolation,
+    #[error("Built-in {0:?} is not available at this stage")]
+    InvalidBuiltInStage(crate::BuiltIn),
+    #[error("Resource binding can't be used for a varying")]
+    InvalidResourceBinding,
+    #[error("Binding is missing")]
+    MissingBinding,
+    #[error("Struct member {index} is missing a binding")]
+    MissingMemberBinding { index: u32 },
+    #[error("Location {location} is bound more than once")]
+    LocationCollision { location: u32 },
+    #[error("Built-in {0:?} is present more than once")]
+    DuplicateBuiltIn(crate::BuiltIn),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -241,10 +321,34 @@ pub enum EntryPointError {
     UnexpectedWorkgroupSize,
     #[error("Workgroup size is out of range")]
     OutOfRangeWorkgroupSize,
+    #[error("Geometry primitives are missing")]
+    MissingGeometryInfo,
+    #[error("Geometry primitives are not applicable")]
+    UnexpectedGeometryInfo,
+    #[error("Maximum number of output vertices is out of range")]
+    OutOfRangeMaxVertices,
+    #[error("Vertices can only be emitted by geometry stages")]
+    UnexpectedVertexEmission,
+    #[error("Ray tracing operations {0:?} are not allowed in this stage")]
+    UnexpectedRayTracingOps(RayTracingOps),
+    #[error("Tessellation configuration is missing")]
+    MissingTessellationInfo,
+    #[error("Tessellation configuration is not applicable")]
+    UnexpectedTessellationInfo,
+    #[error("Number of output patch vertices is out of range")]
+    OutOfRangeOutputVertices,
+    #[error("Varying {0:?} doesn't have an element for each vertex of the primitive")]
+    InvalidPerVertexVarying(Handle<crate::GlobalVariable>),
+    #[error("Varying {0:?} can't be patch-constant in this stage")]
+    InvalidPatchVarying(Handle<crate::GlobalVariable>),
     #[error("Can't have arguments")]
     UnexpectedArguments,
     #[error("Can't have a return value")]
     UnexpectedReturnValue,
+    #[error("Argument {0} varying error")]
+    Argument(u32, #[source] VaryingError),
+    #[error("Result value varying error")]
+    Result(#[source] VaryingError),
     #[error("Global variable {0:?} is used incorrectly as {1:?}")]
     InvalidGlobalUsage(Handle<crate::GlobalVariable>, GlobalUse),
     #[error("Bindings for {0:?} conflict with other global variables")]
@@ -263,6 +367,7 @@ pub enum ValidationError {
     Type {
         handle: Handle<crate::Type>,
         name: String,
+        span: Span,
         #[source]
         error: TypeError,
     },
@@ -270,6 +375,7 @@ pub enum ValidationError {
     Constant {
         handle: Handle<crate::Constant>,
         name: String,
+        span: Span,
         #[source]
         error: ConstantError,
     },
@@ -277,6 +383,7 @@ pub enum ValidationError {
     GlobalVariable {
         handle: Handle<crate::GlobalVariable>,
         name: String,
+        span: Span,
         #[source]
         error: GlobalVariableError,
     },
@@ -284,6 +391,7 @@ pub enum ValidationError {
     Function {
         handle: Handle<crate::Function>,
         name: String,
+        span: Span,
         #[source]
         error: FunctionError,
     },
@@ -291,6 +399,7 @@ pub enum ValidationError {
     EntryPoint {
         stage: crate::ShaderStage,
         name: String,
+        span: Span,
         #[source]
         error: EntryPointError,
     },
@@ -300,6 +409,50 @@ pub enum ValidationError {
     Corrupted,
 }
 
+impl ValidationError {
+    /// Returns the source span of the offending item, if it's known.
+    pub fn span(&self) -> Span {
+        match *self {
+            ValidationError::Type { span, .. }
+            | ValidationError::Constant { span, .. }
+            | ValidationError::GlobalVariable { span, .. }
+            | ValidationError::Function { span, .. }
+            | ValidationError::EntryPoint { span, .. } => span,
+            ValidationError::Analysis(ref error) => error.span(),
+            ValidationError::Corrupted => Span::UNDEFINED,
+        }
+    }
+}
+
+impl FunctionError {
+    /// Returns the span of the expression or local variable that
+    /// this error is about, if it's known.
+    fn span(&self, fun: &crate::Function) -> Span {
+        match *self {
+            FunctionError::Expression { handle, .. }
+            | FunctionError::ExpressionAlreadyInScope(handle)
+            | FunctionError::InvalidExpression(handle)
+            | FunctionError::InvalidImage(handle)
+            | FunctionError::InvalidArrayIndex(handle) => fun.expressions.get_span(handle),
+            FunctionError::LocalVariable { handle, .. } => fun.local_variables.get_span(handle),
+            _ => Span::UNDEFINED,
+        }
+    }
+}
+
+impl EntryPointError {
+    /// Returns the span of the global variable that this error is about, if it's known.
+    fn span(&self, module: &crate::Module) -> Span {
+        match *self {
+            EntryPointError::InvalidPerVertexVarying(handle)
+            | EntryPointError::InvalidPatchVarying(handle)
+            | EntryPointError::InvalidGlobalUsage(handle, _)
+            | EntryPointError::BindingCollision(handle) => module.global_variables.get_span(handle),
+            _ => Span::UNDEFINED,
+        }
+    }
+}
+
 impl crate::GlobalVariable {
     fn forbid_interpolation(&self) -> Result<(), GlobalVariableError> {
         match self.interpolation {
@@ -319,6 +472,24 @@ impl crate::GlobalVariable {
     }
 
     fn check_varying(&self, types: &Arena<crate::Type>) -> Result<(), GlobalVariableError> {
+        let result = self.check_varying_type(self.ty, types);
+        // Per-vertex varyings of geometry and tessellation stages are arrays
+        // of the actual varyings. Their size is up to the entry point.
+        match types[self.ty].inner {
+            crate::TypeInner::Array {
+                base,
+                size: crate::ArraySize::Constant(_),
+                ..
+            } if result.is_err() && self.binding.is_some() => self.check_varying_type(base, types),
+            _ => result,
+        }
+    }
+
+    fn check_varying_type(
+        &self,
+        ty: Handle<crate::Type>,
+        types: &Arena<crate::Type>,
+    ) -> Result<(), GlobalVariableError> {
         match self.binding {
             Some(crate::Binding::BuiltIn(built_in)) => {
                 use crate::{BuiltIn as Bi, ScalarKind as Sk, TypeInner as Ti, VectorSize as Vs};
@@ -333,11 +504,27 @@ impl crate::GlobalVariable {
                     | Bi::SampleIndex
                     | Bi::SampleMaskIn
                     | Bi::SampleMaskOut
-                    | Bi::LocalInvocationIndex => Some(Ti::Scalar {
+                    | Bi::LocalInvocationIndex
+                    | Bi::SubgroupSize
+                    | Bi::SubgroupInvocationId
+                    | Bi::InvocationId
+                    | Bi::PatchVertices
+                    | Bi::InstanceCustomIndex
+                    | Bi::HitKind
+                    | Bi::IncomingRayFlags => Some(Ti::Scalar {
                         kind: Sk::Uint,
                         width,
                     }),
-                    Bi::PointSize | Bi::FragDepth => Some(Ti::Scalar {
+                    Bi::PointSize | Bi::FragDepth | Bi::RayTmin | Bi::RayTmax => Some(Ti::Scalar {
+                        kind: Sk::Float,
+                        width,
+                    }),
+                    Bi::TessCoord
+                    | Bi::WorldRayOrigin
+                    | Bi::WorldRayDirection
+                    | Bi::ObjectRayOrigin
+                    | Bi::ObjectRayDirection => Some(Ti::Vector {
+                        size: Vs::Tri,
                         kind: Sk::Float,
                         width,
                     }),
@@ -353,26 +540,37 @@ impl crate::GlobalVariable {
                     Bi::GlobalInvocationId
                     | Bi::LocalInvocationId
                     | Bi::WorkGroupId
-                    | Bi::WorkGroupSize => Some(Ti::Vector {
+                    | Bi::WorkGroupSize
+                    | Bi::LaunchId
+                    | Bi::LaunchSize => Some(Ti::Vector {
                         size: Vs::Tri,
                         kind: Sk::Uint,
                         width,
                     }),
-                    Bi::ClipDistance => None,
+                    Bi::ObjectToWorld | Bi::WorldToObject => Some(Ti::Matrix {
+                        columns: Vs::Quad,
+                        rows: Vs::Tri,
+                        width,
+                    }),
+                    Bi::ClipDistance | Bi::TessLevelInner | Bi::TessLevelOuter => None,
                 };
 
-                let ty_inner = &types[self.ty].inner;
+                let ty_inner = &types[ty].inner;
                 if Some(ty_inner) != expected_ty_inner.as_ref() {
-                    match (built_in, &types[self.ty].inner) {
-                        (Bi::ClipDistance, &Ti::Array { base, .. }) => match types[base].inner {
-                            Ti::Scalar {
-                                kind: Sk::Float, ..
-                            } => {}
-                            ref other => {
-                                log::warn!("Wrong array base type: {:?}", other);
-                                return Err(GlobalVariableError::InvalidBuiltInType(built_in));
+                    match (built_in, ty_inner) {
+                        (Bi::ClipDistance, &Ti::Array { base, .. })
+                        | (Bi::TessLevelInner, &Ti::Array { base, .. })
+                        | (Bi::TessLevelOuter, &Ti::Array { base, .. }) => {
+                            match types[base].inner {
+                                Ti::Scalar {
+                                    kind: Sk::Float, ..
+                                } => {}
+                                ref other => {
+                                    log::warn!("Wrong array base type: {:?}", other);
+                                    return Err(GlobalVariableError::InvalidBuiltInType(built_in));
+                                }
                             }
-                        },
+                        }
                         (_, other) => {
                             log::warn!("Wrong builtin type: {:?}", other);
                             return Err(GlobalVariableError::InvalidBuiltInType(built_in));
@@ -381,7 +579,7 @@ impl crate::GlobalVariable {
                 }
                 self.forbid_interpolation()?
             }
-            Some(crate::Binding::Location(_)) => match types[self.ty].inner {
+            Some(crate::Binding::Location(_)) => match types[ty].inner {
                 crate::TypeInner::Scalar { .. }
                 | crate::TypeInner::Vector { .. }
                 | crate::TypeInner::Matrix { .. } => {}
@@ -416,9 +614,88 @@ fn storage_usage(access: crate::StorageAccess) -> GlobalUse {
     storage_usage
 }
 
-fn built_in_usage(built_in: crate::BuiltIn) -> (crate::ShaderStage, GlobalUse) {
+/// Returns the size of the array holding a per-vertex varying, if it's one.
+fn per_vertex_array_size(
+    var: &crate::GlobalVariable,
+    module: &crate::Module,
+) -> Option<Option<u32>> {
+    match module.types[var.ty].inner {
+        crate::TypeInner::Array { base, size, .. } => {
+            // clip distances are arrays on their own
+            if var.binding == Some(crate::Binding::BuiltIn(crate::BuiltIn::ClipDistance)) {
+                match module.types[base].inner {
+                    crate::TypeInner::Array { .. } => {}
+                    _ => return None,
+                }
+            }
+            Some(match size {
+                crate::ArraySize::Constant(handle) => match module.constants[handle].inner {
+                    crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Uint(value),
+                        ..
+                    } => Some(value as u32),
+                    crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Sint(value),
+                        ..
+                    } => Some(value as u32),
+                    _ => None,
+                },
+                crate::ArraySize::Dynamic => None,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Ray tracing stages that can read the given built-in.
+fn ray_tracing_built_in_stages(built_in: crate::BuiltIn) -> &'static [crate::ShaderStage] {
     use crate::{BuiltIn as Bi, ShaderStage as Ss};
     match built_in {
+        Bi::LaunchId | Bi::LaunchSize => &[
+            Ss::RayGeneration,
+            Ss::Intersection,
+            Ss::AnyHit,
+            Ss::ClosestHit,
+            Ss::Miss,
+            Ss::Callable,
+        ],
+        Bi::WorldRayOrigin
+        | Bi::WorldRayDirection
+        | Bi::RayTmin
+        | Bi::RayTmax
+        | Bi::IncomingRayFlags => &[Ss::Intersection, Ss::AnyHit, Ss::ClosestHit, Ss::Miss],
+        Bi::ObjectRayOrigin
+        | Bi::ObjectRayDirection
+        | Bi::InstanceCustomIndex
+        | Bi::ObjectToWorld
+        | Bi::WorldToObject => &[Ss::Intersection, Ss::AnyHit, Ss::ClosestHit],
+        Bi::HitKind => &[Ss::AnyHit, Ss::ClosestHit],
+        _ => &[],
+    }
+}
+
+fn built_in_usage(built_in: crate::BuiltIn, stage: crate::ShaderStage) -> GlobalUse {
+    use crate::{BuiltIn as Bi, ShaderStage as Ss};
+    let (allowed_stage, usage) = match built_in {
+        // geometry and tessellation stages read the vertex outputs, and produce their own
+        Bi::Position
+            if stage == Ss::Geometry
+                || stage == Ss::TessellationControl
+                || stage == Ss::TessellationEvaluation =>
+        {
+            return GlobalUse::READ | GlobalUse::WRITE
+        }
+        Bi::ClipDistance | Bi::PointSize if stage == Ss::Geometry => return GlobalUse::WRITE,
+        Bi::ClipDistance | Bi::PointSize
+            if stage == Ss::TessellationControl || stage == Ss::TessellationEvaluation =>
+        {
+            return GlobalUse::READ | GlobalUse::WRITE
+        }
+        Bi::InvocationId if stage == Ss::Geometry => return GlobalUse::READ,
+        Bi::PatchVertices if stage == Ss::TessellationEvaluation => return GlobalUse::READ,
+        Bi::TessLevelInner | Bi::TessLevelOuter if stage == Ss::TessellationEvaluation => {
+            return GlobalUse::READ
+        }
         Bi::BaseInstance => (Ss::Vertex, GlobalUse::READ),
         Bi::BaseVertex => (Ss::Vertex, GlobalUse::READ),
         Bi::ClipDistance => (Ss::Vertex, GlobalUse::WRITE),
@@ -437,6 +714,37 @@ fn built_in_usage(built_in: crate::BuiltIn) -> (crate::ShaderStage, GlobalUse) {
         Bi::LocalInvocationIndex => (Ss::Compute, GlobalUse::READ),
         Bi::WorkGroupId => (Ss::Compute, GlobalUse::READ),
         Bi::WorkGroupSize => (Ss::Compute, GlobalUse::READ),
+        Bi::SubgroupSize => (Ss::Compute, GlobalUse::READ),
+        Bi::SubgroupInvocationId => (Ss::Compute, GlobalUse::READ),
+        Bi::InvocationId => (Ss::TessellationControl, GlobalUse::READ),
+        Bi::PatchVertices => (Ss::TessellationControl, GlobalUse::READ),
+        Bi::TessCoord => (Ss::TessellationEvaluation, GlobalUse::READ),
+        Bi::TessLevelInner => (Ss::TessellationControl, GlobalUse::WRITE),
+        Bi::TessLevelOuter => (Ss::TessellationControl, GlobalUse::WRITE),
+        Bi::LaunchId
+        | Bi::LaunchSize
+        | Bi::WorldRayOrigin
+        | Bi::WorldRayDirection
+        | Bi::ObjectRayOrigin
+        | Bi::ObjectRayDirection
+        | Bi::RayTmin
+        | Bi::RayTmax
+        | Bi::InstanceCustomIndex
+        | Bi::ObjectToWorld
+        | Bi::WorldToObject
+        | Bi::HitKind
+        | Bi::IncomingRayFlags => {
+            return if ray_tracing_built_in_stages(built_in).contains(&stage) {
+                GlobalUse::READ
+            } else {
+                GlobalUse::empty()
+            };
+        }
+    };
+    if allowed_stage == stage {
+        usage
+    } else {
+        GlobalUse::empty()
     }
 }
 
@@ -448,17 +756,20 @@ impl Validator {
             type_flags: Vec::new(),
             location_in_mask: BitSet::new(),
             location_out_mask: BitSet::new(),
+            built_in_set: FastHashSet::default(),
             bind_group_masks: Vec::new(),
             select_cases: FastHashSet::default(),
             valid_expression_list: Vec::new(),
             valid_expression_set: BitSet::new(),
+            statement_span: Span::UNDEFINED,
         }
     }
 
     fn check_width(kind: crate::ScalarKind, width: crate::Bytes) -> bool {
         match kind {
             crate::ScalarKind::Bool => width == crate::BOOL_WIDTH,
-            _ => width == 4,
+            crate::ScalarKind::Float => width == 2 || width == 4 || width == 8,
+            crate::ScalarKind::Sint | crate::ScalarKind::Uint => width == 4 || width == 8,
         }
     }
 
@@ -474,19 +785,38 @@ impl Validator {
                 if !Self::check_width(kind, width) {
                     return Err(TypeError::InvalidWidth(kind, width));
                 }
-                TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::INTERFACE | TypeFlags::HOST_SHARED
+                TypeFlags::DATA
+                    | TypeFlags::SIZED
+                    | TypeFlags::INTERFACE
+                    | TypeFlags::HOST_SHARED
+                    | TypeFlags::COPY
             }
             Ti::Matrix { width, .. } => {
                 if !Self::check_width(crate::ScalarKind::Float, width) {
                     return Err(TypeError::InvalidWidth(crate::ScalarKind::Float, width));
                 }
+                TypeFlags::DATA
+                    | TypeFlags::SIZED
+                    | TypeFlags::INTERFACE
+                    | TypeFlags::HOST_SHARED
+                    | TypeFlags::COPY
+            }
+            Ti::Atomic { kind, width } => {
+                match kind {
+                    crate::ScalarKind::Sint | crate::ScalarKind::Uint => {}
+                    _ => return Err(TypeError::InvalidAtomicKind(kind)),
+                }
+                // 64-bit atomics aren't supported by all the backends
+                if width != 4 {
+                    return Err(TypeError::InvalidWidth(kind, width));
+                }
                 TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::INTERFACE | TypeFlags::HOST_SHARED
             }
             Ti::Pointer { base, class: _ } => {
                 if base >= handle {
                     return Err(TypeError::UnresolvedBase(base));
                 }
-                TypeFlags::DATA | TypeFlags::SIZED
+                TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::COPY
             }
             Ti::ValuePointer {
                 size: _,
@@ -541,9 +871,9 @@ impl Validator {
                     crate::ArraySize::Dynamic => TypeFlags::empty(),
                 };
                 let base_mask = if stride.is_none() {
-                    TypeFlags::INTERFACE
+                    TypeFlags::INTERFACE | TypeFlags::COPY
                 } else {
-                    TypeFlags::HOST_SHARED | TypeFlags::INTERFACE
+                    TypeFlags::HOST_SHARED | TypeFlags::INTERFACE | TypeFlags::COPY
                 };
                 TypeFlags::DATA | (base_flags & base_mask) | sized_flag
             }
@@ -570,7 +900,7 @@ impl Validator {
                 //TODO: check the spans
                 flags
             }
-            Ti::Image { .. } | Ti::Sampler { .. } => TypeFlags::empty(),
+            Ti::Image { .. } | Ti::Sampler { .. } | Ti::AccelerationStructure => TypeFlags::empty(),
         })
     }
 
@@ -588,6 +918,9 @@ impl Validator {
                 }
             }
             crate::ConstantInner::Composite { ty, ref components } => {
+                if con.specialization.is_some() {
+                    return Err(ConstantError::InvalidSpecialization);
+                }
                 match types[ty].inner {
                     crate::TypeInner::Array {
                         size: crate::ArraySize::Dynamic,
@@ -625,7 +958,7 @@ impl Validator {
                 var.check_varying(types)?;
                 (
                     crate::StorageAccess::empty(),
-                    TypeFlags::DATA | TypeFlags::INTERFACE,
+                    TypeFlags::DATA | TypeFlags::INTERFACE | TypeFlags::COPY,
                 )
             }
             crate::StorageClass::Storage => {
@@ -647,7 +980,7 @@ impl Validator {
                 }
                 (
                     crate::StorageAccess::empty(),
-                    TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::HOST_SHARED,
+                    TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::HOST_SHARED | TypeFlags::COPY,
                 )
             }
             crate::StorageClass::Handle => {
@@ -657,9 +990,9 @@ impl Validator {
                         class: crate::ImageClass::Storage(_),
                         ..
                     } => crate::StorageAccess::all(),
-                    crate::TypeInner::Image { .. } | crate::TypeInner::Sampler { .. } => {
-                        crate::StorageAccess::empty()
-                    }
+                    crate::TypeInner::Image { .. }
+                    | crate::TypeInner::Sampler { .. }
+                    | crate::TypeInner::AccelerationStructure => crate::StorageAccess::empty(),
                     _ => return Err(GlobalVariableError::InvalidType),
                 };
                 (access, TypeFlags::empty())
@@ -669,12 +1002,30 @@ impl Validator {
                     return Err(GlobalVariableError::InvalidBinding);
                 }
                 var.forbid_interpolation()?;
-                (crate::StorageAccess::empty(), TypeFlags::DATA)
+                // atomics are only allowed in the workgroup memory
+                let required_flags = if var.class == crate::StorageClass::Private {
+                    TypeFlags::DATA | TypeFlags::COPY
+                } else {
+                    TypeFlags::DATA
+                };
+                (crate::StorageAccess::empty(), required_flags)
             }
             crate::StorageClass::PushConstant => (
                 crate::StorageAccess::LOAD,
-                TypeFlags::DATA | TypeFlags::HOST_SHARED,
+                TypeFlags::DATA | TypeFlags::HOST_SHARED | TypeFlags::COPY,
             ),
+            crate::StorageClass::RayPayload
+            | crate::StorageClass::IncomingRayPayload
+            | crate::StorageClass::HitAttribute => {
+                if var.binding.is_some() {
+                    return Err(GlobalVariableError::InvalidBinding);
+                }
+                var.forbid_interpolation()?;
+                (
+                    crate::StorageAccess::empty(),
+                    TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::COPY,
+                )
+            }
         };
 
         if !allowed_storage_access.contains(var.storage_access) {
@@ -702,6 +1053,9 @@ impl Validator {
         constants: &Arena<crate::Constant>,
     ) -> Result<(), LocalVariableError> {
         log::debug!("var {:?}", var);
+        if !self.type_flags[var.ty.index()].contains(TypeFlags::DATA | TypeFlags::COPY) {
+            return Err(LocalVariableError::InvalidType(var.ty));
+        }
         if let Some(const_handle) = var.init {
             match constants[const_handle].inner {
                 crate::ConstantInner::Scalar { width, ref value } => {
@@ -765,7 +1119,7 @@ impl Validator {
             .map(|expr| self.resolve_type_impl(expr, context.types))
             .transpose()
             .map_err(CallError::ResultValue)?;
-        let expected_ty = fun.return_type.map(|ty| &context.types[ty].inner);
+        let expected_ty = fun.result.as_ref().map(|fr| &context.types[fr.ty].inner);
         if result_ty != expected_ty {
             log::error!(
                 "Called function returns {:?} where {:?} is expected",
@@ -773,13 +1127,282 @@ impl Validator {
                 expected_ty
             );
             return Err(CallError::ResultType {
-                required: fun.return_type,
+                required: fun.result.as_ref().map(|fr| fr.ty),
                 seen_expression: result,
             });
         }
         Ok(())
     }
 
+    fn validate_atomic(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        fun: &crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), AtomicError> {
+        let pointer_inner = self
+            .resolve_type_impl(pointer, context.types)
+            .map_err(|_| AtomicError::InvalidPointer(pointer))?;
+        let (kind, width) = match *pointer_inner {
+            crate::TypeInner::Pointer {
+                base,
+                class: crate::StorageClass::Storage,
+            }
+            | crate::TypeInner::Pointer {
+                base,
+                class: crate::StorageClass::WorkGroup,
+            } => match context.types[base].inner {
+                crate::TypeInner::Atomic { kind, width } => (kind, width),
+                ref other => {
+                    log::error!("Atomic pointer to {:?}", other);
+                    return Err(AtomicError::InvalidPointer(pointer));
+                }
+            },
+            ref other => {
+                log::error!("Atomic pointer {:?}", other);
+                return Err(AtomicError::InvalidPointer(pointer));
+            }
+        };
+
+        let expected_operand = crate::TypeInner::Scalar { kind, width };
+        let mut operands = vec![value];
+        if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
+            operands.push(cmp);
+        }
+        for operand in operands {
+            let operand_inner = self
+                .resolve_type_impl(operand, context.types)
+                .map_err(|_| AtomicError::InvalidOperand(operand))?;
+            if *operand_inner != expected_operand {
+                log::error!("Atomic operand type {:?}", operand_inner);
+                return Err(AtomicError::InvalidOperand(operand));
+            }
+        }
+
+        if self.valid_expression_set.insert(result.index()) {
+            self.valid_expression_list.push(result);
+        } else {
+            return Err(AtomicError::ResultAlreadyInScope(result));
+        }
+        match context.expressions[result] {
+            crate::Expression::AtomicResult {
+                kind: result_kind,
+                width: result_width,
+                comparison,
+            } if result_kind == kind
+                && result_width == width
+                && comparison
+                    == matches!(*fun, crate::AtomicFunction::Exchange { compare: Some(_) }) => {}
+            _ => return Err(AtomicError::ResultTypeMismatch(result)),
+        }
+        Ok(())
+    }
+
+    fn resolve_subgroup_operand(
+        &self,
+        operand: Handle<crate::Expression>,
+        types: &Arena<crate::Type>,
+    ) -> Result<(crate::ScalarKind, crate::Bytes, Option<crate::VectorSize>), SubgroupError> {
+        match *self
+            .resolve_type_impl(operand, types)
+            .map_err(|_| SubgroupError::InvalidOperand(operand))?
+        {
+            crate::TypeInner::Scalar { kind, width } => Ok((kind, width, None)),
+            crate::TypeInner::Vector { size, kind, width } => Ok((kind, width, Some(size))),
+            ref other => {
+                log::error!("Subgroup operand type {:?}", other);
+                Err(SubgroupError::InvalidOperand(operand))
+            }
+        }
+    }
+
+    fn validate_subgroup(
+        &mut self,
+        fun: &crate::SubgroupFunction,
+        result: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), SubgroupError> {
+        use crate::{CollectiveOperation as Co, ScalarKind as Sk, SubgroupOperation as So};
+
+        let expected = match *fun {
+            crate::SubgroupFunction::Elect => (Sk::Bool, crate::BOOL_WIDTH, None),
+            crate::SubgroupFunction::Ballot { predicate } => {
+                if let Some(predicate) = predicate {
+                    match self.resolve_subgroup_operand(predicate, context.types)? {
+                        (Sk::Bool, _, None) => {}
+                        _ => return Err(SubgroupError::InvalidOperand(predicate)),
+                    }
+                }
+                (Sk::Uint, 4, Some(crate::VectorSize::Quad))
+            }
+            crate::SubgroupFunction::Gather { mode, argument } => {
+                match mode {
+                    crate::GatherMode::BroadcastFirst => {}
+                    crate::GatherMode::Broadcast(index)
+                    | crate::GatherMode::Shuffle(index)
+                    | crate::GatherMode::ShuffleDown(index)
+                    | crate::GatherMode::ShuffleUp(index)
+                    | crate::GatherMode::ShuffleXor(index) => {
+                        match self.resolve_subgroup_operand(index, context.types)? {
+                            (Sk::Uint, 4, None) => {}
+                            _ => return Err(SubgroupError::InvalidOperand(index)),
+                        }
+                    }
+                }
+                self.resolve_subgroup_operand(argument, context.types)?
+            }
+            crate::SubgroupFunction::Collective {
+                op,
+                collective,
+                argument,
+            } => {
+                let operand = self.resolve_subgroup_operand(argument, context.types)?;
+                let good = match op {
+                    So::All | So::Any => {
+                        operand == (Sk::Bool, crate::BOOL_WIDTH, None) && collective == Co::Reduce
+                    }
+                    So::Add | So::Multiply | So::Min | So::Max => operand.0 != Sk::Bool,
+                    So::And | So::InclusiveOr | So::ExclusiveOr => operand.0 != Sk::Float,
+                };
+                if !good {
+                    return Err(SubgroupError::InvalidOperation(op, collective));
+                }
+                operand
+            }
+        };
+
+        if self.valid_expression_set.insert(result.index()) {
+            self.valid_expression_list.push(result);
+        } else {
+            return Err(SubgroupError::ResultAlreadyInScope(result));
+        }
+        match context.expressions[result] {
+            crate::Expression::SubgroupResult { kind, width, size }
+                if (kind, width, size) == expected => {}
+            _ => return Err(SubgroupError::ResultTypeMismatch(result)),
+        }
+        Ok(())
+    }
+
+    fn validate_ray_tracing_operands(
+        &self,
+        operands: &[(Handle<crate::Expression>, &crate::TypeInner)],
+        context: &BlockContext,
+    ) -> Result<(), RayTracingError> {
+        for &(operand, expected) in operands {
+            let operand_inner = self
+                .resolve_type_impl(operand, context.types)
+                .map_err(|_| RayTracingError::InvalidOperand(operand))?;
+            if operand_inner != expected {
+                log::error!("Ray tracing operand type {:?}", operand_inner);
+                return Err(RayTracingError::InvalidOperand(operand));
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn validate_trace_ray(
+        &self,
+        acceleration_structure: Handle<crate::Expression>,
+        uint_operands: &[Handle<crate::Expression>],
+        origin: Handle<crate::Expression>,
+        t_min: Handle<crate::Expression>,
+        direction: Handle<crate::Expression>,
+        t_max: Handle<crate::Expression>,
+        payload: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), RayTracingError> {
+        use crate::{ScalarKind as Sk, TypeInner as Ti};
+        match self.resolve_type_impl(acceleration_structure, context.types) {
+            Ok(&Ti::AccelerationStructure) => {}
+            _ => {
+                return Err(RayTracingError::InvalidAccelerationStructure(
+                    acceleration_structure,
+                ))
+            }
+        }
+        // the payload has to be a whole variable
+        match (
+            &context.expressions[payload],
+            self.resolve_type_impl(payload, context.types),
+        ) {
+            (
+                &crate::Expression::GlobalVariable(_),
+                Ok(&Ti::Pointer {
+                    class: crate::StorageClass::RayPayload,
+                    ..
+                }),
+            ) => {}
+            _ => return Err(RayTracingError::InvalidPayload(payload)),
+        }
+
+        let width = 4;
+        let uint = Ti::Scalar {
+            kind: Sk::Uint,
+            width,
+        };
+        let float = Ti::Scalar {
+            kind: Sk::Float,
+            width,
+        };
+        let vec3 = Ti::Vector {
+            size: crate::VectorSize::Tri,
+            kind: Sk::Float,
+            width,
+        };
+        let mut operands = uint_operands
+            .iter()
+            .map(|&operand| (operand, &uint))
+            .collect::<Vec<_>>();
+        operands.push((origin, &vec3));
+        operands.push((t_min, &float));
+        operands.push((direction, &vec3));
+        operands.push((t_max, &float));
+        self.validate_ray_tracing_operands(&operands, context)
+    }
+
+    fn validate_report_intersection(
+        &mut self,
+        hit_t: Handle<crate::Expression>,
+        hit_kind: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), RayTracingError> {
+        let width = 4;
+        self.validate_ray_tracing_operands(
+            &[
+                (
+                    hit_t,
+                    &crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Float,
+                        width,
+                    },
+                ),
+                (
+                    hit_kind,
+                    &crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Uint,
+                        width,
+                    },
+                ),
+            ],
+            context,
+        )?;
+
+        if self.valid_expression_set.insert(result.index()) {
+            self.valid_expression_list.push(result);
+        } else {
+            return Err(RayTracingError::ResultAlreadyInScope(result));
+        }
+        match context.expressions[result] {
+            crate::Expression::ReportIntersectionResult => Ok(()),
+            _ => Err(RayTracingError::ResultTypeMismatch(result)),
+        }
+    }
+
     fn resolve_type_impl<'a>(
         &'a self,
         handle: Handle<crate::Expression>,
@@ -804,12 +1427,14 @@ impl Validator {
 
     fn validate_block_impl(
         &mut self,
-        statements: &[crate::Statement],
+        statements: &crate::Block,
         context: &BlockContext,
     ) -> Result<(), FunctionError> {
         use crate::{Statement as S, TypeInner as Ti};
+        let outer_span = self.statement_span;
         let mut finished = false;
-        for statement in statements {
+        for (statement, span) in statements.span_iter() {
+            self.statement_span = span;
             if finished {
                 return Err(FunctionError::InstructionsAfterReturn);
             }
@@ -902,7 +1527,7 @@ impl Validator {
                     }
                     finished = true;
                 }
-                S::Kill => {
+                S::Kill | S::IgnoreIntersection | S::TerminateRay => {
                     finished = true;
                 }
                 S::Store { pointer, value } => {
@@ -931,9 +1556,11 @@ impl Validator {
                         _ => {}
                     }
                     let good = match self.typifier.try_get(pointer, context.types) {
-                        Some(&Ti::Pointer { base, class: _ }) => {
-                            *value_ty == context.types[base].inner
-                        }
+                        Some(&Ti::Pointer { base, class: _ }) => match context.types[base].inner {
+                            // atomic values are stored as scalars
+                            Ti::Atomic { kind, width } => *value_ty == Ti::Scalar { kind, width },
+                            ref other => value_ty == other,
+                        },
                         Some(&Ti::ValuePointer {
                             size: Some(size),
                             kind,
@@ -988,14 +1615,60 @@ impl Validator {
                         return Err(FunctionError::InvalidCall { function, error });
                     }
                 }
+                S::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    self.validate_atomic(pointer, fun, value, result, context)?;
+                }
+                S::Barrier { .. } | S::EmitVertex | S::EndPrimitive => {}
+                S::Subgroup { ref fun, result } => {
+                    self.validate_subgroup(fun, result, context)?;
+                }
+                S::TraceRay {
+                    acceleration_structure,
+                    flags,
+                    cull_mask,
+                    sbt_offset,
+                    sbt_stride,
+                    miss_index,
+                    origin,
+                    t_min,
+                    direction,
+                    t_max,
+                    payload,
+                } => {
+                    self.validate_trace_ray(
+                        acceleration_structure,
+                        &[flags, cull_mask, sbt_offset, sbt_stride, miss_index],
+                        origin,
+                        t_min,
+                        direction,
+                        t_max,
+                        payload,
+                        context,
+                    )?;
+                }
+                S::ReportIntersection {
+                    hit_t,
+                    hit_kind,
+                    result,
+                } => {
+                    self.validate_report_intersection(hit_t, hit_kind, result, context)?;
+                }
             }
         }
+        // errors inside the block keep pointing at the inner statement,
+        // but after it the statement containing the block is current again
+        self.statement_span = outer_span;
         Ok(())
     }
 
     fn validate_block(
         &mut self,
-        statements: &[crate::Statement],
+        statements: &crate::Block,
         context: &BlockContext,
     ) -> Result<(), FunctionError> {
         let base_expression_count = self.valid_expression_list.len();
@@ -1006,12 +1679,30 @@ impl Validator {
         Ok(())
     }
 
+    /// Returns the most precise span known for an error in `fun`.
+    fn function_error_span(
+        &self,
+        error: &FunctionError,
+        fun: &crate::Function,
+        fallback: Span,
+    ) -> Span {
+        let span = error.span(fun);
+        if span.is_defined() {
+            span
+        } else if self.statement_span.is_defined() {
+            self.statement_span
+        } else {
+            fallback
+        }
+    }
+
     fn validate_function(
         &mut self,
         fun: &crate::Function,
         _info: &FunctionInfo,
         module: &crate::Module,
     ) -> Result<(), FunctionError> {
+        self.statement_span = Span::UNDEFINED;
         let resolve_ctx = ResolveContext {
             constants: &module.constants,
             global_vars: &module.global_variables,
@@ -1032,7 +1723,7 @@ impl Validator {
         }
 
         for (index, argument) in fun.arguments.iter().enumerate() {
-            if !self.type_flags[argument.ty.index()].contains(TypeFlags::DATA) {
+            if !self.type_flags[argument.ty.index()].contains(TypeFlags::DATA | TypeFlags::COPY) {
                 return Err(FunctionError::InvalidArgumentType {
                     index,
                     name: argument.name.clone().unwrap_or_default(),
@@ -1054,11 +1745,109 @@ impl Validator {
                 expressions: &fun.expressions,
                 types: &module.types,
                 functions: &module.functions,
-                return_type: fun.return_type,
+                return_type: fun.result.as_ref().map(|fr| fr.ty),
             },
         )
     }
 
+    /// Check a single input or output of an entry point, which has to be
+    /// bound, or be a structure with all of the members bound.
+    fn validate_varying(
+        &mut self,
+        ty: Handle<crate::Type>,
+        binding: Option<&crate::Binding>,
+        interpolation: Option<crate::Interpolation>,
+        stage: crate::ShaderStage,
+        is_output: bool,
+        types: &Arena<crate::Type>,
+    ) -> Result<(), VaryingError> {
+        match binding {
+            Some(binding) => {
+                self.validate_varying_binding(ty, binding, interpolation, stage, is_output, types)
+            }
+            None => {
+                match types[ty].inner {
+                    crate::TypeInner::Struct { ref members, .. } => {
+                        for (index, member) in members.iter().enumerate() {
+                            let binding = member.binding.as_ref().ok_or(
+                                VaryingError::MissingMemberBinding {
+                                    index: index as u32,
+                                },
+                            )?;
+                            self.validate_varying_binding(
+                                member.ty,
+                                binding,
+                                member.interpolation,
+                                stage,
+                                is_output,
+                                types,
+                            )?;
+                        }
+                        Ok(())
+                    }
+                    _ => Err(VaryingError::MissingBinding),
+                }
+            }
+        }
+    }
+
+    fn validate_varying_binding(
+        &mut self,
+        ty: Handle<crate::Type>,
+        binding: &crate::Binding,
+        interpolation: Option<crate::Interpolation>,
+        stage: crate::ShaderStage,
+        is_output: bool,
+        types: &Arena<crate::Type>,
+    ) -> Result<(), VaryingError> {
+        if !self.type_flags[ty.index()].contains(TypeFlags::INTERFACE) {
+            return Err(VaryingError::InvalidType(ty));
+        }
+        match *binding {
+            crate::Binding::BuiltIn(built_in) => {
+                let required = if is_output {
+                    GlobalUse::WRITE
+                } else {
+                    GlobalUse::READ
+                };
+                if !built_in_usage(built_in, stage).contains(required) {
+                    return Err(VaryingError::InvalidBuiltInStage(built_in));
+                }
+                if interpolation.is_some() {
+                    return Err(VaryingError::InvalidInterpolation);
+                }
+                if !self.built_in_set.insert((built_in, is_output)) {
+                    return Err(VaryingError::DuplicateBuiltIn(built_in));
+                }
+            }
+            crate::Binding::Location(location) => {
+                let mask = if is_output {
+                    &mut self.location_out_mask
+                } else {
+                    &mut self.location_in_mask
+                };
+                if !mask.insert(location as usize) {
+                    return Err(VaryingError::LocationCollision { location });
+                }
+                match interpolation {
+                    Some(crate::Interpolation::Patch) => {
+                        return Err(VaryingError::InvalidInterpolation)
+                    }
+                    Some(crate::Interpolation::Flat) => {}
+                    _ if stage == crate::ShaderStage::Fragment && !is_output => {
+                        match types[ty].inner.scalar_kind() {
+                            Some(crate::ScalarKind::Float) | None => {}
+                            Some(_) => return Err(VaryingError::InvalidIntegerInterpolation),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            crate::Binding::Resource { .. } => return Err(VaryingError::InvalidResourceBinding),
+        }
+        Ok(())
+    }
+
     fn validate_entry_point(
         &mut self,
         ep: &crate::EntryPoint,
@@ -1079,9 +1868,76 @@ impl Validator {
         } else if ep.workgroup_size != [0; 3] {
             return Err(EntryPointError::UnexpectedWorkgroupSize);
         }
+        match (ep.stage, ep.geometry) {
+            (crate::ShaderStage::Geometry, Some(geometry)) => {
+                if geometry.max_vertices == 0 || geometry.max_vertices > MAX_GEOMETRY_VERTICES {
+                    return Err(EntryPointError::OutOfRangeMaxVertices);
+                }
+            }
+            (crate::ShaderStage::Geometry, None) => {
+                return Err(EntryPointError::MissingGeometryInfo);
+            }
+            (_, Some(_)) => return Err(EntryPointError::UnexpectedGeometryInfo),
+            (_, None) => {
+                if info.may_emit_vertices {
+                    return Err(EntryPointError::UnexpectedVertexEmission);
+                }
+            }
+        }
+        match (ep.stage, ep.tessellation) {
+            (
+                crate::ShaderStage::TessellationControl,
+                Some(crate::TessellationInfo::Control { output_vertices }),
+            ) => {
+                if output_vertices == 0 || output_vertices > MAX_PATCH_VERTICES {
+                    return Err(EntryPointError::OutOfRangeOutputVertices);
+                }
+            }
+            (
+                crate::ShaderStage::TessellationEvaluation,
+                Some(crate::TessellationInfo::Evaluation { .. }),
+            ) => {}
+            (crate::ShaderStage::TessellationControl, None)
+            | (crate::ShaderStage::TessellationEvaluation, None) => {
+                return Err(EntryPointError::MissingTessellationInfo);
+            }
+            (_, Some(_)) => return Err(EntryPointError::UnexpectedTessellationInfo),
+            (_, None) => {}
+        }
+        let allowed_ray_tracing_ops = match ep.stage {
+            crate::ShaderStage::RayGeneration
+            | crate::ShaderStage::ClosestHit
+            | crate::ShaderStage::Miss => RayTracingOps::TRACE_RAY,
+            crate::ShaderStage::Intersection => RayTracingOps::REPORT_INTERSECTION,
+            crate::ShaderStage::AnyHit => RayTracingOps::RESOLVE_HIT,
+            _ => RayTracingOps::empty(),
+        };
+        if !allowed_ray_tracing_ops.contains(info.ray_tracing_ops) {
+            return Err(EntryPointError::UnexpectedRayTracingOps(
+                info.ray_tracing_ops - allowed_ray_tracing_ops,
+            ));
+        }
+
+        // Per-vertex varyings are arrays with an element for each vertex,
+        // where `Some(None)` means that the count isn't known by the stage.
+        let per_vertex_inputs = match ep.stage {
+            crate::ShaderStage::Geometry => ep
+                .geometry
+                .map(|geometry| Some(geometry.input.vertex_count())),
+            crate::ShaderStage::TessellationControl
+            | crate::ShaderStage::TessellationEvaluation => Some(None),
+            _ => None,
+        };
+        let per_vertex_outputs = match ep.tessellation {
+            Some(crate::TessellationInfo::Control { output_vertices }) => {
+                Some(Some(output_vertices))
+            }
+            _ => None,
+        };
 
         self.location_in_mask.clear();
         self.location_out_mask.clear();
+        self.built_in_set.clear();
         for bg in self.bind_group_masks.iter_mut() {
             bg.clear();
         }
@@ -1106,16 +1962,45 @@ impl Validator {
                 }
             }
 
+            let is_patch = var.interpolation == Some(crate::Interpolation::Patch);
+            if is_patch {
+                match (ep.stage, var.class) {
+                    (crate::ShaderStage::TessellationControl, crate::StorageClass::Output)
+                    | (crate::ShaderStage::TessellationEvaluation, crate::StorageClass::Input) => {}
+                    _ => return Err(EntryPointError::InvalidPatchVarying(var_handle)),
+                }
+            }
+            let is_per_vertex = match var.binding {
+                Some(crate::Binding::Location(_)) => !is_patch,
+                Some(crate::Binding::BuiltIn(built_in)) => built_in.is_per_vertex(),
+                _ => false,
+            };
+            if is_per_vertex {
+                let expected = match var.class {
+                    crate::StorageClass::Input => per_vertex_inputs,
+                    crate::StorageClass::Output => per_vertex_outputs,
+                    _ => None,
+                };
+                let valid = match (expected, per_vertex_array_size(var, module)) {
+                    (None, None) | (Some(None), Some(_)) => true,
+                    (Some(Some(count)), Some(size)) => size == Some(count),
+                    _ => false,
+                };
+                if !valid {
+                    return Err(EntryPointError::InvalidPerVertexVarying(var_handle));
+                }
+            }
+
             let allowed_usage = match var.class {
                 crate::StorageClass::Function => unreachable!(),
                 crate::StorageClass::Input => {
                     match var.binding {
                         Some(crate::Binding::BuiltIn(built_in)) => {
-                            let (allowed_stage, allowed_usage) = built_in_usage(built_in);
-                            if allowed_stage != ep.stage || !allowed_usage.contains(GlobalUse::READ)
-                            {
+                            let allowed_usage = built_in_usage(built_in, ep.stage);
+                            if !allowed_usage.contains(GlobalUse::READ) {
                                 return Err(EntryPointError::InvalidBuiltIn(built_in));
                             }
+                            self.built_in_set.insert((built_in, false));
                         }
                         Some(crate::Binding::Location(loc)) => {
                             if !self.location_in_mask.insert(loc as usize) {
@@ -1130,12 +2015,11 @@ impl Validator {
                 crate::StorageClass::Output => {
                     match var.binding {
                         Some(crate::Binding::BuiltIn(built_in)) => {
-                            let (allowed_stage, allowed_usage) = built_in_usage(built_in);
-                            if allowed_stage != ep.stage
-                                || !allowed_usage.contains(GlobalUse::WRITE)
-                            {
+                            let allowed_usage = built_in_usage(built_in, ep.stage);
+                            if !allowed_usage.contains(GlobalUse::WRITE) {
                                 return Err(EntryPointError::InvalidBuiltIn(built_in));
                             }
+                            self.built_in_set.insert((built_in, true));
                         }
                         Some(crate::Binding::Location(loc)) => {
                             if !self.location_out_mask.insert(loc as usize) {
@@ -1158,6 +2042,23 @@ impl Validator {
                 },
                 crate::StorageClass::Private | crate::StorageClass::WorkGroup => GlobalUse::all(),
                 crate::StorageClass::PushConstant => GlobalUse::READ,
+                crate::StorageClass::RayPayload => match ep.stage {
+                    crate::ShaderStage::RayGeneration
+                    | crate::ShaderStage::ClosestHit
+                    | crate::ShaderStage::Miss => GlobalUse::READ | GlobalUse::WRITE,
+                    _ => GlobalUse::empty(),
+                },
+                crate::StorageClass::IncomingRayPayload => match ep.stage {
+                    crate::ShaderStage::AnyHit
+                    | crate::ShaderStage::ClosestHit
+                    | crate::ShaderStage::Miss => GlobalUse::READ | GlobalUse::WRITE,
+                    _ => GlobalUse::empty(),
+                },
+                crate::StorageClass::HitAttribute => match ep.stage {
+                    crate::ShaderStage::Intersection => GlobalUse::READ | GlobalUse::WRITE,
+                    crate::ShaderStage::AnyHit | crate::ShaderStage::ClosestHit => GlobalUse::READ,
+                    _ => GlobalUse::empty(),
+                },
             };
             if !allowed_usage.contains(usage) {
                 log::warn!("\tUsage error for: {:?}", var);
@@ -1179,12 +2080,39 @@ impl Validator {
             }
         }
 
-        if !ep.function.arguments.is_empty() {
+        let (allow_arguments, allow_result) = match ep.stage {
+            crate::ShaderStage::Vertex | crate::ShaderStage::Fragment => (true, true),
+            crate::ShaderStage::Compute => (true, false),
+            _ => (false, false),
+        };
+        if !allow_arguments && !ep.function.arguments.is_empty() {
             return Err(EntryPointError::UnexpectedArguments);
         }
-        if ep.function.return_type.is_some() {
+        if !allow_result && ep.function.result.is_some() {
             return Err(EntryPointError::UnexpectedReturnValue);
         }
+        for (index, argument) in ep.function.arguments.iter().enumerate() {
+            self.validate_varying(
+                argument.ty,
+                argument.binding.as_ref(),
+                argument.interpolation,
+                ep.stage,
+                false,
+                &module.types,
+            )
+            .map_err(|e| EntryPointError::Argument(index as u32, e))?;
+        }
+        if let Some(ref fr) = ep.function.result {
+            self.validate_varying(
+                fr.ty,
+                fr.binding.as_ref(),
+                fr.interpolation,
+                ep.stage,
+                true,
+                &module.types,
+            )
+            .map_err(EntryPointError::Result)?;
+        }
 
         self.validate_function(&ep.function, info, module)?;
         Ok(())
@@ -1204,6 +2132,7 @@ impl Validator {
                 .map_err(|error| ValidationError::Constant {
                     handle,
                     name: constant.name.clone().unwrap_or_default(),
+                    span: module.constants.get_span(handle),
                     error,
                 })?;
         }
@@ -1215,6 +2144,7 @@ impl Validator {
                 .map_err(|error| ValidationError::Type {
                     handle,
                     name: ty.name.clone().unwrap_or_default(),
+                    span: module.types.get_span(handle),
                     error,
                 })?;
             self.type_flags[handle.index()] = ty_flags;
@@ -1225,6 +2155,7 @@ impl Validator {
                 .map_err(|error| ValidationError::GlobalVariable {
                     handle: var_handle,
                     name: var.name.clone().unwrap_or_default(),
+                    span: module.global_variables.get_span(var_handle),
                     error,
                 })?;
         }
@@ -1234,6 +2165,7 @@ impl Validator {
                 .map_err(|error| ValidationError::Function {
                     handle,
                     name: fun.name.clone().unwrap_or_default(),
+                    span: self.function_error_span(&error, fun, module.functions.get_span(handle)),
                     error,
                 })?;
         }
@@ -1244,6 +2176,7 @@ impl Validator {
                 return Err(ValidationError::EntryPoint {
                     stage: ep.stage,
                     name: ep.name.clone(),
+                    span: Span::UNDEFINED,
                     error: EntryPointError::Conflict,
                 });
             }
@@ -1252,6 +2185,12 @@ impl Validator {
                 .map_err(|error| ValidationError::EntryPoint {
                     stage: ep.stage,
                     name: ep.name.clone(),
+                    span: match error {
+                        EntryPointError::Function(ref error) => {
+                            self.function_error_span(error, &ep.function, Span::UNDEFINED)
+                        }
+                        ref other => other.span(module),
+                    },
                     error,
                 })?;
         }
@@ -0,0 +1,610 @@
+/*! Traversal of the IR.
+
+The [`Visitor`] and [`VisitorMut`] traits have a method for every kind of item,
+and every kind of handle an item can refer to. The default implementations walk
+into the contents with the matching `walk_*` function, so an implementation only
+overrides the methods it is interested in, and calls the `walk_*` function from
+them to keep going deeper.
+
+Handles are only reported, not followed: visiting an expression reports the
+handles of its operands, but doesn't visit the operands themselves.
+Statements visit their nested blocks.
+!*/
+
+use crate::{
+    arena::{Handle, Range},
+    ArraySize, AtomicFunction, Block, Constant, ConstantInner, EntryPoint, Expression, Function,
+    GatherMode, GlobalVariable, ImageQuery, LocalVariable, Module, SampleLevel, Statement,
+    SubgroupFunction, Type, TypeInner,
+};
+
+/// Adjusts a range of emitted expressions to the new handles of its first and
+/// last expressions.
+///
+/// The expressions in between are expected to stay contiguous.
+pub fn remap_range<F>(range: &Range<Expression>, mut map: F) -> Range<Expression>
+where
+    F: FnMut(Handle<Expression>) -> Handle<Expression>,
+{
+    let old = range.index_range();
+    if old.start == old.end {
+        return Range::from_index_range(0..0);
+    }
+    let start = map(Handle::from_usize(old.start as usize)).index() as u32;
+    let end = map(Handle::from_usize(old.end as usize - 1)).index() as u32 + 1;
+    Range::from_index_range(start..end)
+}
+
+// Both traits are generated from the same definition, so that they walk the
+// IR in the same way. The optional `mut` token selects the flavor.
+macro_rules! visitor {
+    (
+        $(#[$meta:meta])*
+        $visitor:ident, $iter:ident, [$($mut:tt)?];
+        $walk_module:ident,
+        $walk_type:ident,
+        $walk_constant:ident,
+        $walk_global_variable:ident,
+        $walk_function:ident,
+        $walk_local_variable:ident,
+        $walk_block:ident,
+        $walk_statement:ident,
+        $walk_expression:ident,
+    ) => {
+        $(#[$meta])*
+        pub trait $visitor {
+            fn visit_module(&mut self, module: &$($mut)? Module) {
+                $walk_module(self, module)
+            }
+            fn visit_type(&mut self, _handle: Handle<Type>, ty: &$($mut)? Type) {
+                $walk_type(self, ty)
+            }
+            fn visit_constant(&mut self, _handle: Handle<Constant>, constant: &$($mut)? Constant) {
+                $walk_constant(self, constant)
+            }
+            fn visit_global_variable(
+                &mut self,
+                _handle: Handle<GlobalVariable>,
+                variable: &$($mut)? GlobalVariable,
+            ) {
+                $walk_global_variable(self, variable)
+            }
+            fn visit_entry_point(&mut self, entry_point: &$($mut)? EntryPoint) {
+                self.visit_function(&$($mut)? entry_point.function)
+            }
+            fn visit_function(&mut self, function: &$($mut)? Function) {
+                $walk_function(self, function)
+            }
+            fn visit_local_variable(
+                &mut self,
+                _handle: Handle<LocalVariable>,
+                variable: &$($mut)? LocalVariable,
+            ) {
+                $walk_local_variable(self, variable)
+            }
+            fn visit_block(&mut self, block: &$($mut)? Block) {
+                $walk_block(self, block)
+            }
+            fn visit_statement(&mut self, statement: &$($mut)? Statement) {
+                $walk_statement(self, statement)
+            }
+            fn visit_expression(
+                &mut self,
+                _handle: Handle<Expression>,
+                expression: &$($mut)? Expression,
+            ) {
+                $walk_expression(self, expression)
+            }
+
+            fn visit_type_handle(&mut self, _handle: &$($mut)? Handle<Type>) {}
+            fn visit_constant_handle(&mut self, _handle: &$($mut)? Handle<Constant>) {}
+            fn visit_global_variable_handle(
+                &mut self,
+                _handle: &$($mut)? Handle<GlobalVariable>,
+            ) {
+            }
+            fn visit_function_handle(&mut self, _handle: &$($mut)? Handle<Function>) {}
+            fn visit_local_variable_handle(&mut self, _handle: &$($mut)? Handle<LocalVariable>) {}
+            fn visit_expression_handle(&mut self, _handle: &$($mut)? Handle<Expression>) {}
+            /// Visits the range of a `Statement::Emit`.
+            fn visit_expression_range(&mut self, _range: &$($mut)? Range<Expression>) {}
+        }
+
+        pub fn $walk_module<V: $visitor + ?Sized>(visitor: &mut V, module: &$($mut)? Module) {
+            for (handle, ty) in module.types.$iter() {
+                visitor.visit_type(handle, ty);
+            }
+            for (handle, constant) in module.constants.$iter() {
+                visitor.visit_constant(handle, constant);
+            }
+            for (handle, variable) in module.global_variables.$iter() {
+                visitor.visit_global_variable(handle, variable);
+            }
+            for (_, function) in module.functions.$iter() {
+                visitor.visit_function(function);
+            }
+            for entry_point in module.entry_points.$iter() {
+                visitor.visit_entry_point(entry_point);
+            }
+        }
+
+        pub fn $walk_type<V: $visitor + ?Sized>(visitor: &mut V, ty: &$($mut)? Type) {
+            match ty.inner {
+                TypeInner::Pointer { ref $($mut)? base, .. } => visitor.visit_type_handle(base),
+                TypeInner::Array {
+                    ref $($mut)? base,
+                    ref $($mut)? size,
+                    ..
+                } => {
+                    visitor.visit_type_handle(base);
+                    if let ArraySize::Constant(ref $($mut)? constant) = *size {
+                        visitor.visit_constant_handle(constant);
+                    }
+                }
+                TypeInner::Struct { ref $($mut)? members, .. } => {
+                    for member in members.$iter() {
+                        visitor.visit_type_handle(&$($mut)? member.ty);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        pub fn $walk_constant<V: $visitor + ?Sized>(visitor: &mut V, constant: &$($mut)? Constant) {
+            match constant.inner {
+                ConstantInner::Scalar { .. } => {}
+                ConstantInner::Composite {
+                    ref $($mut)? ty,
+                    ref $($mut)? components,
+                } => {
+                    visitor.visit_type_handle(ty);
+                    for component in components.$iter() {
+                        visitor.visit_constant_handle(component);
+                    }
+                }
+            }
+        }
+
+        pub fn $walk_global_variable<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            variable: &$($mut)? GlobalVariable,
+        ) {
+            visitor.visit_type_handle(&$($mut)? variable.ty);
+            if let Some(ref $($mut)? init) = variable.init {
+                visitor.visit_constant_handle(init);
+            }
+        }
+
+        pub fn $walk_function<V: $visitor + ?Sized>(visitor: &mut V, function: &$($mut)? Function) {
+            for argument in function.arguments.$iter() {
+                visitor.visit_type_handle(&$($mut)? argument.ty);
+            }
+            if let Some(ref $($mut)? result) = function.result {
+                visitor.visit_type_handle(&$($mut)? result.ty);
+            }
+            for (handle, variable) in function.local_variables.$iter() {
+                visitor.visit_local_variable(handle, variable);
+            }
+            for (handle, expression) in function.expressions.$iter() {
+                visitor.visit_expression(handle, expression);
+            }
+            visitor.visit_block(&$($mut)? function.body);
+        }
+
+        pub fn $walk_local_variable<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            variable: &$($mut)? LocalVariable,
+        ) {
+            visitor.visit_type_handle(&$($mut)? variable.ty);
+            if let Some(ref $($mut)? init) = variable.init {
+                visitor.visit_constant_handle(init);
+            }
+        }
+
+        pub fn $walk_block<V: $visitor + ?Sized>(visitor: &mut V, block: &$($mut)? Block) {
+            for statement in block.$iter() {
+                visitor.visit_statement(statement);
+            }
+        }
+
+        pub fn $walk_statement<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            statement: &$($mut)? Statement,
+        ) {
+            match *statement {
+                Statement::Emit(ref $($mut)? range) => visitor.visit_expression_range(range),
+                Statement::Block(ref $($mut)? block) => visitor.visit_block(block),
+                Statement::If {
+                    ref $($mut)? condition,
+                    ref $($mut)? accept,
+                    ref $($mut)? reject,
+                } => {
+                    visitor.visit_expression_handle(condition);
+                    visitor.visit_block(accept);
+                    visitor.visit_block(reject);
+                }
+                Statement::Switch {
+                    ref $($mut)? selector,
+                    ref $($mut)? cases,
+                    ref $($mut)? default,
+                } => {
+                    visitor.visit_expression_handle(selector);
+                    for case in cases.$iter() {
+                        visitor.visit_block(&$($mut)? case.body);
+                    }
+                    visitor.visit_block(default);
+                }
+                Statement::Loop {
+                    ref $($mut)? body,
+                    ref $($mut)? continuing,
+                } => {
+                    visitor.visit_block(body);
+                    visitor.visit_block(continuing);
+                }
+                Statement::Break
+                | Statement::Continue
+                | Statement::Kill
+                | Statement::Barrier { .. }
+                | Statement::EmitVertex
+                | Statement::EndPrimitive
+                | Statement::IgnoreIntersection
+                | Statement::TerminateRay => {}
+                Statement::Return { ref $($mut)? value } => {
+                    if let Some(ref $($mut)? value) = *value {
+                        visitor.visit_expression_handle(value);
+                    }
+                }
+                Statement::Store {
+                    ref $($mut)? pointer,
+                    ref $($mut)? value,
+                } => {
+                    visitor.visit_expression_handle(pointer);
+                    visitor.visit_expression_handle(value);
+                }
+                Statement::ImageStore {
+                    ref $($mut)? image,
+                    ref $($mut)? coordinate,
+                    ref $($mut)? array_index,
+                    ref $($mut)? value,
+                } => {
+                    visitor.visit_expression_handle(image);
+                    visitor.visit_expression_handle(coordinate);
+                    if let Some(ref $($mut)? array_index) = *array_index {
+                        visitor.visit_expression_handle(array_index);
+                    }
+                    visitor.visit_expression_handle(value);
+                }
+                Statement::Call {
+                    ref $($mut)? function,
+                    ref $($mut)? arguments,
+                    ref $($mut)? result,
+                } => {
+                    visitor.visit_function_handle(function);
+                    for argument in arguments.$iter() {
+                        visitor.visit_expression_handle(argument);
+                    }
+                    if let Some(ref $($mut)? result) = *result {
+                        visitor.visit_expression_handle(result);
+                    }
+                }
+                Statement::Atomic {
+                    ref $($mut)? pointer,
+                    ref $($mut)? fun,
+                    ref $($mut)? value,
+                    ref $($mut)? result,
+                } => {
+                    visitor.visit_expression_handle(pointer);
+                    if let AtomicFunction::Exchange {
+                        compare: Some(ref $($mut)? compare),
+                    } = *fun
+                    {
+                        visitor.visit_expression_handle(compare);
+                    }
+                    visitor.visit_expression_handle(value);
+                    visitor.visit_expression_handle(result);
+                }
+                Statement::Subgroup {
+                    ref $($mut)? fun,
+                    ref $($mut)? result,
+                } => {
+                    match *fun {
+                        SubgroupFunction::Elect => {}
+                        SubgroupFunction::Ballot { ref $($mut)? predicate } => {
+                            if let Some(ref $($mut)? predicate) = *predicate {
+                                visitor.visit_expression_handle(predicate);
+                            }
+                        }
+                        SubgroupFunction::Gather {
+                            ref $($mut)? mode,
+                            ref $($mut)? argument,
+                        } => {
+                            visitor.visit_expression_handle(argument);
+                            match *mode {
+                                GatherMode::BroadcastFirst => {}
+                                GatherMode::Broadcast(ref $($mut)? index)
+                                | GatherMode::Shuffle(ref $($mut)? index)
+                                | GatherMode::ShuffleDown(ref $($mut)? index)
+                                | GatherMode::ShuffleUp(ref $($mut)? index)
+                                | GatherMode::ShuffleXor(ref $($mut)? index) => {
+                                    visitor.visit_expression_handle(index)
+                                }
+                            }
+                        }
+                        SubgroupFunction::Collective { ref $($mut)? argument, .. } => {
+                            visitor.visit_expression_handle(argument)
+                        }
+                    }
+                    visitor.visit_expression_handle(result);
+                }
+                Statement::TraceRay {
+                    ref $($mut)? acceleration_structure,
+                    ref $($mut)? flags,
+                    ref $($mut)? cull_mask,
+                    ref $($mut)? sbt_offset,
+                    ref $($mut)? sbt_stride,
+                    ref $($mut)? miss_index,
+                    ref $($mut)? origin,
+                    ref $($mut)? t_min,
+                    ref $($mut)? direction,
+                    ref $($mut)? t_max,
+                    ref $($mut)? payload,
+                } => {
+                    visitor.visit_expression_handle(acceleration_structure);
+                    visitor.visit_expression_handle(flags);
+                    visitor.visit_expression_handle(cull_mask);
+                    visitor.visit_expression_handle(sbt_offset);
+                    visitor.visit_expression_handle(sbt_stride);
+                    visitor.visit_expression_handle(miss_index);
+                    visitor.visit_expression_handle(origin);
+                    visitor.visit_expression_handle(t_min);
+                    visitor.visit_expression_handle(direction);
+                    visitor.visit_expression_handle(t_max);
+                    visitor.visit_expression_handle(payload);
+                }
+                Statement::ReportIntersection {
+                    ref $($mut)? hit_t,
+                    ref $($mut)? hit_kind,
+                    ref $($mut)? result,
+                } => {
+                    visitor.visit_expression_handle(hit_t);
+                    visitor.visit_expression_handle(hit_kind);
+                    visitor.visit_expression_handle(result);
+                }
+            }
+        }
+
+        pub fn $walk_expression<V: $visitor + ?Sized>(
+            visitor: &mut V,
+            expression: &$($mut)? Expression,
+        ) {
+            match *expression {
+                Expression::Access {
+                    ref $($mut)? base,
+                    ref $($mut)? index,
+                } => {
+                    visitor.visit_expression_handle(base);
+                    visitor.visit_expression_handle(index);
+                }
+                Expression::AccessIndex { ref $($mut)? base, .. } => {
+                    visitor.visit_expression_handle(base)
+                }
+                Expression::Constant(ref $($mut)? constant) => {
+                    visitor.visit_constant_handle(constant)
+                }
+                Expression::Compose {
+                    ref $($mut)? ty,
+                    ref $($mut)? components,
+                } => {
+                    visitor.visit_type_handle(ty);
+                    for component in components.$iter() {
+                        visitor.visit_expression_handle(component);
+                    }
+                }
+                Expression::FunctionArgument(_)
+                | Expression::AtomicResult { .. }
+                | Expression::SubgroupResult { .. }
+                | Expression::ReportIntersectionResult => {}
+                Expression::GlobalVariable(ref $($mut)? variable) => {
+                    visitor.visit_global_variable_handle(variable)
+                }
+                Expression::LocalVariable(ref $($mut)? variable) => {
+                    visitor.visit_local_variable_handle(variable)
+                }
+                Expression::Load { ref $($mut)? pointer } => {
+                    visitor.visit_expression_handle(pointer)
+                }
+                Expression::ImageSample {
+                    ref $($mut)? image,
+                    ref $($mut)? sampler,
+                    ref $($mut)? coordinate,
+                    ref $($mut)? array_index,
+                    ref $($mut)? offset,
+                    ref $($mut)? level,
+                    ref $($mut)? depth_ref,
+                } => {
+                    visitor.visit_expression_handle(image);
+                    visitor.visit_expression_handle(sampler);
+                    visitor.visit_expression_handle(coordinate);
+                    if let Some(ref $($mut)? array_index) = *array_index {
+                        visitor.visit_expression_handle(array_index);
+                    }
+                    if let Some(ref $($mut)? offset) = *offset {
+                        visitor.visit_constant_handle(offset);
+                    }
+                    match *level {
+                        SampleLevel::Auto | SampleLevel::Zero => {}
+                        SampleLevel::Exact(ref $($mut)? handle)
+                        | SampleLevel::Bias(ref $($mut)? handle) => {
+                            visitor.visit_expression_handle(handle)
+                        }
+                        SampleLevel::Gradient {
+                            ref $($mut)? x,
+                            ref $($mut)? y,
+                        } => {
+                            visitor.visit_expression_handle(x);
+                            visitor.visit_expression_handle(y);
+                        }
+                    }
+                    if let Some(ref $($mut)? depth_ref) = *depth_ref {
+                        visitor.visit_expression_handle(depth_ref);
+                    }
+                }
+                Expression::ImageLoad {
+                    ref $($mut)? image,
+                    ref $($mut)? coordinate,
+                    ref $($mut)? array_index,
+                    ref $($mut)? index,
+                } => {
+                    visitor.visit_expression_handle(image);
+                    visitor.visit_expression_handle(coordinate);
+                    if let Some(ref $($mut)? array_index) = *array_index {
+                        visitor.visit_expression_handle(array_index);
+                    }
+                    if let Some(ref $($mut)? index) = *index {
+                        visitor.visit_expression_handle(index);
+                    }
+                }
+                Expression::ImageQuery {
+                    ref $($mut)? image,
+                    ref $($mut)? query,
+                } => {
+                    visitor.visit_expression_handle(image);
+                    if let ImageQuery::Size {
+                        level: Some(ref $($mut)? level),
+                    } = *query
+                    {
+                        visitor.visit_expression_handle(level);
+                    }
+                }
+                Expression::Unary { ref $($mut)? expr, .. }
+                | Expression::Derivative { ref $($mut)? expr, .. }
+                | Expression::As { ref $($mut)? expr, .. }
+                | Expression::ArrayLength(ref $($mut)? expr) => {
+                    visitor.visit_expression_handle(expr)
+                }
+                Expression::Binary {
+                    ref $($mut)? left,
+                    ref $($mut)? right,
+                    ..
+                } => {
+                    visitor.visit_expression_handle(left);
+                    visitor.visit_expression_handle(right);
+                }
+                Expression::Select {
+                    ref $($mut)? condition,
+                    ref $($mut)? accept,
+                    ref $($mut)? reject,
+                } => {
+                    visitor.visit_expression_handle(condition);
+                    visitor.visit_expression_handle(accept);
+                    visitor.visit_expression_handle(reject);
+                }
+                Expression::Relational { ref $($mut)? argument, .. } => {
+                    visitor.visit_expression_handle(argument)
+                }
+                Expression::Math {
+                    ref $($mut)? arg,
+                    ref $($mut)? arg1,
+                    ref $($mut)? arg2,
+                    ..
+                } => {
+                    visitor.visit_expression_handle(arg);
+                    if let Some(ref $($mut)? arg1) = *arg1 {
+                        visitor.visit_expression_handle(arg1);
+                    }
+                    if let Some(ref $($mut)? arg2) = *arg2 {
+                        visitor.visit_expression_handle(arg2);
+                    }
+                }
+                Expression::Call(ref $($mut)? function) => visitor.visit_function_handle(function),
+            }
+        }
+    };
+}
+
+visitor! {
+    /// Read-only traversal of the IR.
+    Visitor, iter, [];
+    walk_module,
+    walk_type,
+    walk_constant,
+    walk_global_variable,
+    walk_function,
+    walk_local_variable,
+    walk_block,
+    walk_statement,
+    walk_expression,
+}
+
+visitor! {
+    /// Traversal of the IR that can modify it in place,
+    /// for example to replace the handles.
+    VisitorMut, iter_mut, [mut];
+    walk_module_mut,
+    walk_type_mut,
+    walk_constant_mut,
+    walk_global_variable_mut,
+    walk_function_mut,
+    walk_local_variable_mut,
+    walk_block_mut,
+    walk_statement_mut,
+    walk_expression_mut,
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+#[test]
+fn visit_calls_and_globals() {
+    #[derive(Default)]
+    struct Counter {
+        calls: Vec<Handle<Function>>,
+        globals: usize,
+        blocks: usize,
+    }
+    impl Visitor for Counter {
+        fn visit_block(&mut self, block: &Block) {
+            self.blocks += 1;
+            walk_block(self, block);
+        }
+        fn visit_function_handle(&mut self, handle: &Handle<Function>) {
+            self.calls.push(*handle);
+        }
+        fn visit_global_variable_handle(&mut self, _handle: &Handle<GlobalVariable>) {
+            self.globals += 1;
+        }
+    }
+
+    /// Swaps the first two functions.
+    struct Swap;
+    impl VisitorMut for Swap {
+        fn visit_function_handle(&mut self, handle: &mut Handle<Function>) {
+            *handle = Handle::from_usize(1 - handle.index());
+        }
+    }
+
+    let mut module = crate::front::wgsl::parse_str(
+        "
+        var<private> counter: u32;
+        fn first() { counter = counter + 1u; }
+        fn second() { first(); }
+        [[stage(compute), workgroup_size(1)]]
+        fn main() {
+            if (counter > 2u) { second(); } else { first(); }
+            loop { first(); break; }
+        }
+        ",
+    )
+    .unwrap();
+    let mut counter = Counter::default();
+    counter.visit_module(&module);
+    let (first, second) = (Handle::from_usize(0), Handle::from_usize(1));
+    assert_eq!(counter.calls, vec![first, second, first, first]);
+    // the front-end adds an expression for each global to every function
+    assert_eq!(counter.globals, 3);
+    // function bodies, if branches, loop body and continuing
+    assert_eq!(counter.blocks, 3 + 2 + 2);
+
+    Swap.visit_module(&mut module);
+    let mut counter = Counter::default();
+    counter.visit_module(&module);
+    assert_eq!(counter.calls, vec![second, first, second, second]);
+}
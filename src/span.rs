@@ -0,0 +1,78 @@
+use std::ops::Range;
+
+/// A byte range in the source text a module was parsed from.
+///
+/// Front-ends attach spans to the items they produce, so that later
+/// passes (such as the validator) can point back into the original source.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct Span {
+    start: u32,
+    end: u32,
+}
+
+impl Span {
+    /// Span that doesn't point anywhere.
+    pub const UNDEFINED: Self = Span { start: 0, end: 0 };
+
+    /// Create a new span from the `start` and `end` byte offsets.
+    pub fn new(start: u32, end: u32) -> Self {
+        debug_assert!(start <= end);
+        Span { start, end }
+    }
+
+    /// Returns `true` if the span points into the source.
+    pub fn is_defined(&self) -> bool {
+        *self != Self::UNDEFINED
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        if !self.is_defined() {
+            *other
+        } else if !other.is_defined() {
+            *self
+        } else {
+            Span {
+                start: self.start.min(other.start),
+                end: self.end.max(other.end),
+            }
+        }
+    }
+
+    /// Returns the byte range of this span, if it's defined.
+    pub fn to_range(self) -> Option<Range<usize>> {
+        if self.is_defined() {
+            Some(self.start as usize..self.end as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the 1-based line number and column of the start of the span
+    /// within the `source` text.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let prefix = &source[..(self.start as usize).min(source.len())];
+        let line_start = prefix.rfind('\n').map_or(0, |pos| pos + 1);
+        let line = prefix.matches('\n').count() + 1;
+        let column = prefix[line_start..].chars().count() + 1;
+        (line, column)
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span::new(range.start as u32, range.end as u32)
+    }
+}
+
+#[test]
+fn span_location() {
+    let source = "fn main() {\n    let x = 1;\n}\n";
+    let span = Span::from(16..21);
+    assert_eq!(&source[span.to_range().unwrap()], "let x");
+    assert_eq!(span.location(source), (2, 5));
+    assert_eq!(Span::UNDEFINED.to_range(), None);
+    assert_eq!(Span::UNDEFINED.union(&span), span);
+}
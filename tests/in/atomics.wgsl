@@ -0,0 +1,33 @@
+[[builtin(global_invocation_id)]]
+var global_id: vec3<u32>;
+
+[[block]]
+struct Counters {
+    total: atomic<u32>;
+    bias: atomic<i32>;
+    bins: [[stride(4)]] array<atomic<u32>, 4u>;
+};
+
+[[group(0), binding(0)]]
+var<storage> counters: [[access(read_write)]] Counters;
+
+var<workgroup> workgroup_count: atomic<u32>;
+
+[[stage(compute), workgroup_size(64)]]
+fn main() {
+    atomicStore(&workgroup_count, 0u);
+    workgroupBarrier();
+    const old: u32 = atomicAdd(&counters.total, 1u);
+    atomicSub(&counters.bias, 2);
+    atomicMax(&counters.bias, -4);
+    atomicMin(&counters.bins[global_id.x % 4u], old);
+    atomicAnd(&counters.bins[0], 7u);
+    atomicOr(&counters.bins[1], 8u);
+    atomicXor(&counters.bins[2], 9u);
+    const exchanged: u32 = atomicExchange(&counters.bins[3], old);
+    const result: vec2<u32> = atomicCompareExchangeWeak(&workgroup_count, 0u, exchanged);
+    if (result.y == 1u) {
+        atomicAdd(&counters.total, atomicLoad(&workgroup_count));
+    }
+    storageBarrier();
+}
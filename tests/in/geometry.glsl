@@ -0,0 +1,26 @@
+#version 450
+
+layout(points) in;
+layout(triangle_strip, max_vertices = 3) out;
+
+layout(location = 0) out vec2 uv;
+
+const vec4 p0 = vec4(-1.0, -1.0, 0.0, 1.0);
+const vec4 p1 = vec4(3.0, -1.0, 0.0, 1.0);
+const vec4 p2 = vec4(-1.0, 3.0, 0.0, 1.0);
+const vec2 uv0 = vec2(0.0, 0.0);
+const vec2 uv1 = vec2(2.0, 0.0);
+const vec2 uv2 = vec2(0.0, 2.0);
+
+void main() {
+    gl_Position = p0;
+    uv = uv0;
+    EmitVertex();
+    gl_Position = p1;
+    uv = uv1;
+    EmitVertex();
+    gl_Position = p2;
+    uv = uv2;
+    EmitVertex();
+    EndPrimitive();
+}
@@ -0,0 +1,23 @@
+enable f16;
+
+[[block]]
+struct Data {
+    values: [[stride(2)]] array<f16, 64u>;
+    tint: vec4<f16>;
+};
+
+[[builtin(global_invocation_id)]]
+var global_id: vec3<u32>;
+
+[[group(0), binding(0)]]
+var<storage> data: [[access(read_write)]] Data;
+
+const scale: f16 = 0.5h;
+
+[[stage(compute), workgroup_size(64)]]
+fn main() {
+    const index: u32 = global_id.x;
+    const value: f16 = data.values[index] * scale;
+    const color: vec2<f16> = vec2<f16>(value, 2.0h) * data.tint.xy;
+    data.values[index] = color.x + color.y;
+}
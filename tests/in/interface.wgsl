@@ -0,0 +1,50 @@
+// Testing various parts of the pipeline interface: arguments, results, and structures of bindings
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(1)]] varying: f32;
+};
+
+[[stage(vertex)]]
+fn vertex(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[builtin(instance_index)]] instance_index: u32,
+    [[location(10)]] position: vec2<f32>
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>(position, 0.0, 1.0);
+    out.varying = f32(vertex_index + instance_index);
+    return out;
+}
+
+struct FragmentInput {
+    [[builtin(frag_coord)]] frag_coord: vec4<f32>;
+    [[location(1)]] varying: f32;
+};
+
+[[stage(fragment)]]
+fn fragment(
+    in: FragmentInput,
+    [[builtin(front_facing)]] front_facing: bool
+) -> [[location(0)]] vec4<f32> {
+    if (front_facing) {
+        return in.frag_coord;
+    }
+    return vec4<f32>(in.varying, 0.0, 0.0, 1.0);
+}
+
+[[block]]
+struct Output {
+    data: [[stride(4)]] array<u32>;
+};
+
+[[group(0), binding(0)]]
+var<storage> output: [[access(write)]] Output;
+
+[[stage(compute), workgroup_size(1)]]
+fn compute(
+    [[builtin(global_invocation_id)]] global_id: vec3<u32>,
+    [[builtin(local_invocation_id)]] local_id: vec3<u32>
+) {
+    output.data[global_id.x] = local_id.x;
+}
@@ -0,0 +1,10 @@
+#version 460
+
+hitAttributeEXT vec2 attribs;
+
+void main() {
+    if (gl_RayTmaxEXT > 100.0) {
+        ignoreIntersectionEXT;
+    }
+    terminateRayEXT;
+}
@@ -0,0 +1,9 @@
+#version 460
+
+layout(set = 0, binding = 0) uniform accelerationStructureEXT scene;
+
+layout(location = 0) rayPayloadEXT vec4 payload;
+
+void main() {
+    traceRayEXT(scene, 0u, 255u, 0u, 0u, 0u, vec3(gl_LaunchIDEXT), 0.001, vec3(0.0, 0.0, 1.0), 1000.0, 0);
+}
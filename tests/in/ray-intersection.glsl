@@ -0,0 +1,8 @@
+#version 460
+
+hitAttributeEXT vec2 attribs;
+
+void main() {
+    attribs = vec2(0.5, 0.5);
+    reportIntersectionEXT(gl_RayTminEXT, 0u);
+}
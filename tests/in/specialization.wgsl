@@ -0,0 +1,11 @@
+[[constant_id(0)]] const scale: f32 = 2.0;
+[[constant_id(1)]] const enabled: bool = true;
+const offset: vec2<f32> = vec2<f32>(scale, 0.5);
+
+[[stage(fragment)]]
+fn main([[location(0)]] uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    if (enabled) {
+        return vec4<f32>(uv + offset, scale, 1.0);
+    }
+    return vec4<f32>(uv, 0.0, 1.0);
+}
@@ -0,0 +1,36 @@
+[[builtin(subgroup_size)]]
+var subgroup_size: u32;
+[[builtin(subgroup_invocation_id)]]
+var subgroup_invocation_id: u32;
+
+[[block]]
+struct Data {
+    values: [[stride(4)]] array<f32, 64u>;
+};
+
+[[group(0), binding(0)]]
+var<storage> data: [[access(read_write)]] Data;
+
+[[stage(compute), workgroup_size(64)]]
+fn main() {
+    const value: f32 = data.values[subgroup_invocation_id];
+    const is_first: bool = subgroupElect();
+    const mask: vec4<u32> = subgroupBallot(value > 0.0);
+    const all_active: vec4<u32> = subgroupBallot();
+    const all_positive: bool = subgroupAll(value > 0.0);
+    const any_positive: bool = subgroupAny(value > 0.0);
+    const sum: f32 = subgroupAdd(value);
+    const prefix_sum: f32 = subgroupExclusiveAdd(value);
+    const product: f32 = subgroupInclusiveMul(value);
+    const minimum: f32 = subgroupMin(value);
+    const bits: u32 = subgroupXor(subgroup_invocation_id);
+    const first: f32 = subgroupBroadcastFirst(value);
+    const broadcast: f32 = subgroupBroadcast(value, 4u);
+    const shuffled: f32 = subgroupShuffle(value, subgroup_size - subgroup_invocation_id - 1u);
+    const down: f32 = subgroupShuffleDown(value, 1u);
+    const up: f32 = subgroupShuffleUp(value, 1u);
+    const xored: f32 = subgroupShuffleXor(value, 1u);
+    subgroupBarrier();
+    data.values[subgroup_invocation_id] = sum + prefix_sum + product + minimum + first
+        + broadcast + shuffled + down + up + xored + f32(bits + mask.x + all_active.y);
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(vertices = 3) out;
+
+const float outer = 4.0;
+const float inner = 2.0;
+
+void main() {
+    gl_TessLevelOuter[0] = outer;
+    gl_TessLevelOuter[1] = outer;
+    gl_TessLevelOuter[2] = outer;
+    gl_TessLevelInner[0] = inner;
+    gl_out[gl_InvocationID].gl_Position = gl_in[gl_InvocationID].gl_Position;
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(triangles, fractional_odd_spacing, ccw) in;
+
+layout(location = 0) patch in vec4 color;
+layout(location = 0) out vec4 v_color;
+
+void main() {
+    gl_Position = gl_in[0].gl_Position;
+    v_color = color;
+}
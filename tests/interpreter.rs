@@ -0,0 +1,175 @@
+#![cfg(feature = "wgsl-in")]
+
+use naga::proc::{Interpreter, Resource, ResourceBinding, Resources};
+
+fn load_module(name: &str) -> naga::Module {
+    let path = std::path::PathBuf::from("tests/in").join(name);
+    let source = std::fs::read_to_string(path).unwrap();
+    naga::front::wgsl::parse_str(&source).unwrap()
+}
+
+fn buffer(resources: &Resources, binding: u32) -> &[u8] {
+    match resources[&ResourceBinding { group: 0, binding }] {
+        Resource::Buffer(ref bytes) => bytes,
+        Resource::Image(_) => panic!("binding {} is not a buffer", binding),
+    }
+}
+
+fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+fn floats(bytes: &[u8]) -> Vec<f32> {
+    words(bytes).into_iter().map(f32::from_bits).collect()
+}
+
+fn to_bytes(words: impl IntoIterator<Item = u32>) -> Vec<u8> {
+    words
+        .into_iter()
+        .flat_map(|w| w.to_le_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn collatz() {
+    let module = load_module("collatz.wgsl");
+    let inputs = [1, 2, 3, 4, 5, 6, 7, 27];
+    let mut resources = Resources::default();
+    resources.insert(
+        ResourceBinding {
+            group: 0,
+            binding: 0,
+        },
+        Resource::Buffer(to_bytes(inputs.iter().cloned())),
+    );
+
+    let mut interpreter = Interpreter::new(&module);
+    interpreter.set_step_limit(Some(1_000_000));
+    interpreter
+        .dispatch("main", [inputs.len() as u32, 1, 1], &mut resources)
+        .unwrap();
+    assert_eq!(words(buffer(&resources, 0)), [0, 1, 7, 2, 5, 8, 16, 111]);
+}
+
+#[test]
+fn atomics() {
+    let module = load_module("atomics.wgsl");
+    let mut resources = Resources::default();
+    resources.insert(
+        ResourceBinding {
+            group: 0,
+            binding: 0,
+        },
+        Resource::Buffer(vec![0; 24]),
+    );
+
+    Interpreter::new(&module)
+        .dispatch("main", [1, 1, 1], &mut resources)
+        .unwrap();
+    let counters = words(buffer(&resources, 0));
+    // the invocations are executed in order between the barriers,
+    // so only the third one manages to change the workgroup counter
+    assert_eq!(counters[0], 65);
+    assert_eq!(counters[1] as i32, -4);
+}
+
+#[test]
+fn boids() {
+    const NUM_PARTICLES: usize = 1500;
+    const PARAMS: [f32; 7] = [0.04, 0.1, 0.025, 0.025, 0.02, 0.05, 0.005];
+
+    let module = load_module("boids.wgsl");
+    let mut particles = Vec::with_capacity(NUM_PARTICLES * 4);
+    let mut seed = 1u32;
+    for _ in 0..NUM_PARTICLES * 4 {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        particles.push((seed >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0);
+    }
+    let binding = |binding| ResourceBinding { group: 0, binding };
+    let mut resources = Resources::default();
+    resources.insert(
+        binding(0),
+        Resource::Buffer(to_bytes(PARAMS.iter().map(|p| p.to_bits()))),
+    );
+    resources.insert(
+        binding(1),
+        Resource::Buffer(to_bytes(particles.iter().map(|p| p.to_bits()))),
+    );
+    resources.insert(binding(2), Resource::Buffer(vec![0; NUM_PARTICLES * 16]));
+
+    // a single workgroup updates the first 64 particles
+    Interpreter::new(&module)
+        .dispatch("main", [1, 1, 1], &mut resources)
+        .unwrap();
+    let output = floats(buffer(&resources, 2));
+
+    let [delta_t, rule1_distance, rule2_distance, rule3_distance, rule1_scale, rule2_scale, rule3_scale] =
+        PARAMS;
+    let distance =
+        |a: [f32; 2], b: [f32; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+    for index in 0..64 {
+        let v_pos = [particles[index * 4], particles[index * 4 + 1]];
+        let mut v_vel = [particles[index * 4 + 2], particles[index * 4 + 3]];
+        let mut c_mass = [0.0; 2];
+        let mut c_vel = [0.0; 2];
+        let mut col_vel = [0.0; 2];
+        let (mut c_mass_count, mut c_vel_count) = (0, 0);
+        for i in (0..NUM_PARTICLES).filter(|&i| i != index) {
+            let pos = [particles[i * 4], particles[i * 4 + 1]];
+            let vel = [particles[i * 4 + 2], particles[i * 4 + 3]];
+            let d = distance(pos, v_pos);
+            for c in 0..2 {
+                if d < rule1_distance {
+                    c_mass[c] += pos[c];
+                }
+                if d < rule2_distance {
+                    col_vel[c] -= pos[c] - v_pos[c];
+                }
+                if d < rule3_distance {
+                    c_vel[c] += vel[c];
+                }
+            }
+            c_mass_count += (d < rule1_distance) as i32;
+            c_vel_count += (d < rule3_distance) as i32;
+        }
+        for c in 0..2 {
+            if c_mass_count > 0 {
+                c_mass[c] = c_mass[c] * (1.0 / c_mass_count as f32) - v_pos[c];
+            }
+            if c_vel_count > 0 {
+                c_vel[c] *= 1.0 / c_vel_count as f32;
+            }
+            v_vel[c] += c_mass[c] * rule1_scale + col_vel[c] * rule2_scale + c_vel[c] * rule3_scale;
+        }
+        let length = (v_vel[0] * v_vel[0] + v_vel[1] * v_vel[1]).sqrt();
+        let mut expected = [0.0; 4];
+        for c in 0..2 {
+            let vel = v_vel[c] / length * length.min(0.1);
+            let mut pos = v_pos[c] + vel * delta_t;
+            if pos < -1.0 {
+                pos = 1.0;
+            }
+            if pos > 1.0 {
+                pos = -1.0;
+            }
+            expected[c] = pos;
+            expected[c + 2] = vel;
+        }
+
+        let actual = &output[index * 4..index * 4 + 4];
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!(
+                (a - e).abs() < 1e-5,
+                "particle {}: {:?} != {:?}",
+                index,
+                actual,
+                expected
+            );
+        }
+    }
+    // the other particles are not touched
+    assert!(output[64 * 4..].iter().all(|&f| f == 0.0));
+}
@@ -10,6 +10,24 @@ fn check_glsl(name: &str) {
         naga::ShaderStage::Fragment
     } else if name.ends_with(".comp") {
         naga::ShaderStage::Compute
+    } else if name.ends_with(".geom") {
+        naga::ShaderStage::Geometry
+    } else if name.ends_with(".tesc") {
+        naga::ShaderStage::TessellationControl
+    } else if name.ends_with(".tese") {
+        naga::ShaderStage::TessellationEvaluation
+    } else if name.ends_with(".rgen") {
+        naga::ShaderStage::RayGeneration
+    } else if name.ends_with(".rint") {
+        naga::ShaderStage::Intersection
+    } else if name.ends_with(".rahit") {
+        naga::ShaderStage::AnyHit
+    } else if name.ends_with(".rchit") {
+        naga::ShaderStage::ClosestHit
+    } else if name.ends_with(".rmiss") {
+        naga::ShaderStage::Miss
+    } else if name.ends_with(".rcall") {
+        naga::ShaderStage::Callable
     } else {
         panic!("Unknown extension in {:?}", name)
     };
@@ -25,8 +43,7 @@ fn check_glsl(name: &str) {
     ) {
         Ok(m) => match naga::proc::Validator::new().validate(&m) {
             Ok(_analysis) => (),
-            //TODO: panic
-            Err(e) => log::error!("Unable to validate {}: {:?}", name, e),
+            Err(e) => panic!("Unable to validate {}: {:?}", name, e),
         },
         Err(e) => panic!("Unable to parse {}: {:?}", name, e),
     };
@@ -38,5 +55,11 @@ fn parse_glsl() {
     //check_glsl("glsl_constant_expression.vert"); //TODO
     //check_glsl("glsl_if_preprocessor.vert");
     check_glsl("glsl_preprocessor_abuse.vert");
+    check_glsl("glsl_geometry.geom");
+    check_glsl("glsl_tessellation.tesc");
+    check_glsl("glsl_tessellation.tese");
+    check_glsl("glsl_ray_tracing.rgen");
+    check_glsl("glsl_ray_tracing.rint");
+    check_glsl("glsl_ray_tracing.rahit");
     //check_glsl("glsl_vertex_test_shader.vert"); //TODO
 }
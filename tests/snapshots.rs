@@ -6,6 +6,12 @@ bitflags::bitflags! {
         const METAL = 0x8;
         const GLSL = 0x10;
         const DOT = 0x20;
+        const WGSL = 0x40;
+        const HLSL = 0x80;
+        const TEXT = 0x100;
+        const REFLECTION = 0x200;
+        /// GLSL written from the module parsed back from the SPIR-V output.
+        const GLSL_FROM_SPIRV = 0x400;
     }
 }
 
@@ -47,6 +53,18 @@ struct Parameters {
     spv_capabilities: naga::FastHashSet<spirv::Capability>,
     #[cfg_attr(not(feature = "msl-out"), allow(dead_code))]
     mtl_bindings: naga::FastHashMap<BindSource, BindTarget>,
+    #[cfg_attr(not(feature = "msl-out"), allow(dead_code))]
+    #[serde(default)]
+    mtl_version: Option<(u8, u8)>,
+    #[cfg_attr(not(feature = "glsl-out"), allow(dead_code))]
+    #[serde(default)]
+    glsl_specialization_constants: bool,
+    #[cfg_attr(not(feature = "glsl-out"), allow(dead_code))]
+    #[serde(default)]
+    glsl_desktop_version: Option<u16>,
+    #[cfg_attr(not(feature = "hlsl-out"), allow(dead_code))]
+    #[serde(default)]
+    hlsl_shader_model: Option<(u8, u8)>,
 }
 
 #[allow(dead_code)]
@@ -73,6 +91,8 @@ fn check_targets(module: &naga::Module, name: &str, targets: Targets) {
             with_snapshot_settings(|| {
                 insta::assert_snapshot!(format!("{}.ron", name), output);
             });
+            #[cfg(feature = "deserialize")]
+            check_deserialized_ir(&output, name);
         }
         if targets.contains(Targets::ANALYSIS) {
             let config = ron::ser::PrettyConfig::default().with_new_line("\n".to_string());
@@ -81,12 +101,40 @@ fn check_targets(module: &naga::Module, name: &str, targets: Targets) {
                 insta::assert_snapshot!(format!("{}.info.ron", name), output);
             });
         }
+        if targets.contains(Targets::REFLECTION) {
+            let reflection = naga::proc::reflect(module, &analysis);
+            let config = ron::ser::PrettyConfig::default().with_new_line("\n".to_string());
+            let output = ron::ser::to_string_pretty(&reflection, config).unwrap();
+            with_snapshot_settings(|| {
+                insta::assert_snapshot!(format!("{}.reflection.ron", name), output);
+            });
+        }
     }
 
+    #[cfg(feature = "ir-out")]
+    {
+        check_output_text(module, name, targets.contains(Targets::TEXT));
+    }
     #[cfg(feature = "spv-out")]
     {
         if targets.contains(Targets::SPIRV) {
-            check_output_spv(module, &analysis, name, &params);
+            let _spv = check_output_spv(module, &analysis, name, &params);
+            #[cfg(feature = "spv-in")]
+            {
+                let reparsed = check_spv_round_trip(module, &_spv, name);
+                #[cfg(feature = "glsl-out")]
+                {
+                    if targets.contains(Targets::GLSL_FROM_SPIRV) {
+                        let analysis = naga::proc::Validator::new().validate(&reparsed).unwrap();
+                        let name = format!("{}-spv", name);
+                        for ep in reparsed.entry_points.iter() {
+                            check_output_glsl(
+                                &reparsed, &analysis, &name, ep.stage, &ep.name, &params,
+                            );
+                        }
+                    }
+                }
+            }
         }
     }
     #[cfg(feature = "msl-out")]
@@ -99,10 +147,22 @@ fn check_targets(module: &naga::Module, name: &str, targets: Targets) {
     {
         if targets.contains(Targets::GLSL) {
             for ep in module.entry_points.iter() {
-                check_output_glsl(module, &analysis, name, ep.stage, &ep.name);
+                check_output_glsl(module, &analysis, name, ep.stage, &ep.name, &params);
             }
         }
     }
+    #[cfg(feature = "wgsl-out")]
+    {
+        if targets.contains(Targets::WGSL) {
+            check_output_wgsl(module, &analysis, name);
+        }
+    }
+    #[cfg(feature = "hlsl-out")]
+    {
+        if targets.contains(Targets::HLSL) {
+            check_output_hlsl(module, &analysis, name, &params);
+        }
+    }
     #[cfg(feature = "dot-out")]
     {
         if targets.contains(Targets::DOT) {
@@ -114,13 +174,32 @@ fn check_targets(module: &naga::Module, name: &str, targets: Targets) {
     }
 }
 
+#[cfg(feature = "ir-out")]
+fn check_output_text(module: &naga::Module, name: &str, snapshot: bool) {
+    let string = naga::back::ir::write_string(module).unwrap();
+
+    // parsing the text back has to give the same module
+    #[cfg(feature = "ir-in")]
+    {
+        let reparsed = naga::front::ir::parse_str(&string).unwrap();
+        let reprinted = naga::back::ir::write_string(&reparsed).unwrap();
+        assert_eq!(string, reprinted);
+    }
+
+    if snapshot {
+        with_snapshot_settings(|| {
+            insta::assert_snapshot!(format!("{}.ir", name), string);
+        });
+    }
+}
+
 #[cfg(feature = "spv-out")]
 fn check_output_spv(
     module: &naga::Module,
     analysis: &naga::proc::analyzer::Analysis,
     name: &str,
     params: &Parameters,
-) {
+) -> Vec<u32> {
     use naga::back::spv;
     use rspirv::binary::Disassemble;
 
@@ -132,12 +211,55 @@ fn check_output_spv(
 
     let spv = spv::write_vec(module, analysis, &options).unwrap();
 
-    let dis = rspirv::dr::load_words(spv)
+    let text = spv::disassemble(&spv).unwrap();
+    #[cfg(feature = "spv-in")]
+    assert_eq!(naga::front::spv::assemble(&text).unwrap(), spv);
+
+    let dis = rspirv::dr::load_words(spv.clone())
         .expect("Produced invalid SPIR-V")
         .disassemble();
     with_snapshot_settings(|| {
         insta::assert_snapshot!(format!("{}.spvasm", name), dis);
     });
+    spv
+}
+
+/// Loads the IR back, without any of the spans, and runs it through
+/// the validator and the passes rewriting function bodies.
+#[cfg(feature = "deserialize")]
+fn check_deserialized_ir(ron: &str, name: &str) {
+    let mut module: naga::Module = ron::de::from_str(ron).unwrap();
+    naga::proc::Validator::new()
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("Unable to validate the deserialized {}: {:?}", name, e));
+    naga::proc::inline_calls(&mut module, &Default::default()).unwrap();
+    naga::proc::Validator::new()
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("Unable to validate the inlined {}: {:?}", name, e));
+}
+
+/// Parses the written SPIR-V back, which has to give the same entry points.
+#[cfg(all(feature = "spv-out", feature = "spv-in"))]
+fn check_spv_round_trip(module: &naga::Module, spv: &[u32], name: &str) -> naga::Module {
+    let bytes = spv
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .collect::<Vec<_>>();
+    let reparsed = naga::front::spv::parse_u8_slice(&bytes, &Default::default())
+        .unwrap_or_else(|e| panic!("Unable to parse the SPIR-V of {}: {:?}", name, e));
+    naga::proc::Validator::new()
+        .validate(&reparsed)
+        .unwrap_or_else(|e| panic!("Unable to validate the SPIR-V of {}: {:?}", name, e));
+    for ep in module.entry_points.iter() {
+        let reparsed_ep = reparsed
+            .entry_points
+            .iter()
+            .find(|other| other.stage == ep.stage && other.name == ep.name)
+            .unwrap_or_else(|| panic!("Entry point {} of {} is missing", ep.name, name));
+        assert_eq!(ep.geometry, reparsed_ep.geometry, "{}", name);
+        assert_eq!(ep.tessellation, reparsed_ep.tessellation, "{}", name);
+    }
+    reparsed
 }
 
 #[cfg(feature = "msl-out")]
@@ -170,7 +292,7 @@ fn check_output_msl(
         );
     }
     let options = msl::Options {
-        lang_version: (1, 0),
+        lang_version: params.mtl_version.unwrap_or((1, 0)),
         binding_map,
         spirv_cross_compatibility: false,
         fake_missing_bindings: false,
@@ -190,13 +312,19 @@ fn check_output_glsl(
     name: &str,
     stage: naga::ShaderStage,
     ep_name: &str,
+    params: &Parameters,
 ) {
     use naga::back::glsl;
 
+    let version = match params.glsl_desktop_version {
+        Some(version) => glsl::Version::Desktop(version),
+        None => glsl::Version::Embedded(310),
+    };
     let options = glsl::Options {
-        version: glsl::Version::Embedded(310),
+        version,
         shader_stage: stage,
         entry_point: ep_name.to_string(),
+        specialization_constants: params.glsl_specialization_constants,
     };
 
     let mut buffer = Vec::new();
@@ -210,6 +338,60 @@ fn check_output_glsl(
     });
 }
 
+#[cfg(feature = "wgsl-out")]
+fn check_output_wgsl(module: &naga::Module, analysis: &naga::proc::analyzer::Analysis, name: &str) {
+    use naga::back::wgsl;
+
+    let (string, _) = wgsl::write_string(module, analysis, &wgsl::Options::default()).unwrap();
+
+    // the output has to be accepted by our own front-end,
+    // and written again without any change
+    #[cfg(feature = "wgsl-in")]
+    {
+        let reparsed = naga::front::wgsl::parse_str(&string).unwrap();
+        let analysis = naga::proc::Validator::new().validate(&reparsed).unwrap();
+        let (rewritten, _) =
+            wgsl::write_string(&reparsed, &analysis, &wgsl::Options::default()).unwrap();
+        assert_eq!(string, rewritten, "{} changes once parsed again", name);
+    }
+
+    with_snapshot_settings(|| {
+        insta::assert_snapshot!(format!("{}.wgsl", name), string);
+    });
+}
+
+#[cfg(feature = "hlsl-out")]
+fn check_output_hlsl(
+    module: &naga::Module,
+    analysis: &naga::proc::analyzer::Analysis,
+    name: &str,
+    params: &Parameters,
+) {
+    use naga::back::hlsl;
+
+    let shader_model = match params.hlsl_shader_model.unwrap_or((5, 1)) {
+        (5, 0) => hlsl::ShaderModel::V5_0,
+        (5, 1) => hlsl::ShaderModel::V5_1,
+        (6, 0) => hlsl::ShaderModel::V6_0,
+        (6, 1) => hlsl::ShaderModel::V6_1,
+        (6, 2) => hlsl::ShaderModel::V6_2,
+        (6, 3) => hlsl::ShaderModel::V6_3,
+        (6, 4) => hlsl::ShaderModel::V6_4,
+        (6, 5) => hlsl::ShaderModel::V6_5,
+        other => panic!("Unknown shader model {:?}", other),
+    };
+    let options = hlsl::Options {
+        shader_model,
+        ..Default::default()
+    };
+
+    let (string, _) = hlsl::write_string(module, analysis, &options).unwrap();
+
+    with_snapshot_settings(|| {
+        insta::assert_snapshot!(format!("{}.hlsl", name), string);
+    });
+}
+
 #[cfg(feature = "wgsl-in")]
 fn convert_wgsl(name: &str, targets: Targets) {
     let module = naga::front::wgsl::parse_str(
@@ -225,26 +407,41 @@ fn convert_wgsl(name: &str, targets: Targets) {
 fn convert_wgsl_quad() {
     convert_wgsl(
         "quad",
-        Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::DOT,
+        Targets::WGSL
+            | Targets::HLSL
+            | Targets::SPIRV
+            | Targets::METAL
+            | Targets::GLSL
+            | Targets::DOT
+            | Targets::REFLECTION,
     );
 }
 
 #[cfg(feature = "wgsl-in")]
 #[test]
 fn convert_wgsl_empty() {
-    convert_wgsl("empty", Targets::SPIRV | Targets::METAL | Targets::GLSL);
+    convert_wgsl(
+        "empty",
+        Targets::WGSL | Targets::HLSL | Targets::SPIRV | Targets::METAL | Targets::GLSL,
+    );
 }
 
 #[cfg(feature = "wgsl-in")]
 #[test]
 fn convert_wgsl_boids() {
-    convert_wgsl("boids", Targets::SPIRV | Targets::METAL);
+    convert_wgsl(
+        "boids",
+        Targets::WGSL | Targets::HLSL | Targets::SPIRV | Targets::METAL | Targets::REFLECTION,
+    );
 }
 
 #[cfg(feature = "wgsl-in")]
 #[test]
 fn convert_wgsl_skybox() {
-    convert_wgsl("skybox", Targets::SPIRV | Targets::METAL | Targets::GLSL);
+    convert_wgsl(
+        "skybox",
+        Targets::WGSL | Targets::HLSL | Targets::SPIRV | Targets::METAL | Targets::GLSL,
+    );
 }
 
 #[cfg(feature = "wgsl-in")]
@@ -252,20 +449,154 @@ fn convert_wgsl_skybox() {
 fn convert_wgsl_collatz() {
     convert_wgsl(
         "collatz",
-        Targets::SPIRV | Targets::METAL | Targets::IR | Targets::ANALYSIS,
+        Targets::WGSL
+            | Targets::HLSL
+            | Targets::SPIRV
+            | Targets::METAL
+            | Targets::IR
+            | Targets::ANALYSIS
+            | Targets::TEXT,
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn convert_wgsl_atomics() {
+    convert_wgsl(
+        "atomics",
+        Targets::WGSL
+            | Targets::HLSL
+            | Targets::SPIRV
+            | Targets::METAL
+            | Targets::GLSL
+            | Targets::IR,
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn convert_wgsl_subgroup_operations() {
+    convert_wgsl(
+        "subgroup-operations",
+        Targets::WGSL
+            | Targets::HLSL
+            | Targets::SPIRV
+            | Targets::METAL
+            | Targets::GLSL
+            | Targets::IR,
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn convert_wgsl_half_float() {
+    convert_wgsl(
+        "half-float",
+        Targets::WGSL
+            | Targets::HLSL
+            | Targets::SPIRV
+            | Targets::METAL
+            | Targets::GLSL
+            | Targets::IR,
     );
 }
 
 #[cfg(feature = "wgsl-in")]
 #[test]
 fn convert_wgsl_shadow() {
-    convert_wgsl("shadow", Targets::SPIRV | Targets::METAL);
+    convert_wgsl(
+        "shadow",
+        Targets::WGSL | Targets::HLSL | Targets::SPIRV | Targets::METAL | Targets::REFLECTION,
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn convert_wgsl_interface() {
+    convert_wgsl(
+        "interface",
+        Targets::WGSL
+            | Targets::HLSL
+            | Targets::SPIRV
+            | Targets::METAL
+            | Targets::GLSL
+            | Targets::IR
+            | Targets::TEXT
+            | Targets::REFLECTION,
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn convert_wgsl_specialization() {
+    convert_wgsl(
+        "specialization",
+        Targets::WGSL | Targets::HLSL | Targets::METAL | Targets::GLSL | Targets::IR,
+    );
 }
 
 #[cfg(feature = "wgsl-in")]
 #[test]
 fn convert_wgsl_texture_array() {
-    convert_wgsl("texture-array", Targets::SPIRV);
+    convert_wgsl(
+        "texture-array",
+        Targets::WGSL | Targets::HLSL | Targets::SPIRV,
+    );
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn compact_wgsl() {
+    for &name in &[
+        "quad",
+        "boids",
+        "skybox",
+        "shadow",
+        "interface",
+        "texture-array",
+    ] {
+        let source = std::fs::read_to_string(format!("tests/in/{}.wgsl", name)).unwrap();
+        let original = naga::front::wgsl::parse_str(&source).unwrap();
+        // keep each entry point on its own
+        for ep in original.entry_points.iter() {
+            let (stage, entry_name) = (ep.stage, ep.name.clone());
+            let mut module = original.clone();
+            naga::proc::compact(&mut module, |ep| ep.stage == stage && ep.name == entry_name);
+            // extracting leaves the original module alone, but gives the same result
+            let extracted = naga::proc::extract_entry_point(&original, stage, &entry_name).unwrap();
+            assert_eq!(format!("{:?}", extracted), format!("{:?}", module));
+            let _analysis = naga::proc::Validator::new()
+                .validate(&module)
+                .unwrap_or_else(|e| panic!("{} ({}): {:?}", name, entry_name, e));
+            #[cfg(feature = "spv-out")]
+            naga::back::spv::write_vec(&module, &_analysis, &Default::default()).unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn inline_wgsl() {
+    for &name in &[
+        "quad",
+        "boids",
+        "skybox",
+        "shadow",
+        "collatz",
+        "interface",
+        "texture-array",
+    ] {
+        let source = std::fs::read_to_string(format!("tests/in/{}.wgsl", name)).unwrap();
+        let mut module = naga::front::wgsl::parse_str(&source).unwrap();
+        naga::proc::inline_calls(&mut module, &Default::default()).unwrap();
+        naga::proc::compact(&mut module, |_| true);
+        assert_eq!(module.functions.len(), 0, "{}", name);
+        let _analysis = naga::proc::Validator::new()
+            .validate(&module)
+            .unwrap_or_else(|e| panic!("{}: {:?}", name, e));
+        #[cfg(feature = "spv-out")]
+        naga::back::spv::write_vec(&module, &_analysis, &Default::default()).unwrap();
+    }
 }
 
 #[cfg(feature = "spv-in")]
@@ -285,6 +616,54 @@ fn convert_spv_shadow() {
     convert_spv("shadow", Targets::IR | Targets::ANALYSIS);
 }
 
+#[cfg(feature = "ir-in")]
+fn convert_ir(name: &str, targets: Targets) {
+    let module = naga::front::ir::parse_str(
+        &std::fs::read_to_string(format!("tests/in/{}{}", name, ".ir"))
+            .expect("Couldn't find ir file"),
+    )
+    .unwrap();
+    check_targets(&module, name, targets);
+}
+
+#[cfg(feature = "ir-in")]
+#[test]
+fn convert_ir_clamp() {
+    convert_ir(
+        "clamp",
+        Targets::WGSL | Targets::HLSL | Targets::SPIRV | Targets::METAL,
+    );
+}
+
+#[cfg(feature = "ir-in")]
+#[test]
+fn convert_ir_int64() {
+    convert_ir("int64", Targets::METAL | Targets::GLSL);
+}
+
+#[cfg(feature = "ir-in")]
+#[test]
+fn convert_ir_double() {
+    convert_ir("double", Targets::GLSL);
+}
+
+#[cfg(all(feature = "ir-in", feature = "msl-out"))]
+#[test]
+fn convert_ir_double_msl() {
+    use naga::back::msl;
+
+    let module =
+        naga::front::ir::parse_str(&std::fs::read_to_string("tests/in/double.ir").unwrap())
+            .unwrap();
+    let analysis = naga::proc::Validator::new().validate(&module).unwrap();
+    // Metal has no 64-bit floats
+    match msl::write_string(&module, &analysis, &msl::Options::default()) {
+        Err(msl::Error::UnsupportedScalar(naga::ScalarKind::Float, 8)) => (),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+        Ok(_) => panic!("Doubles were written to MSL"),
+    }
+}
+
 #[cfg(feature = "glsl-in")]
 fn convert_glsl(
     name: &str,
@@ -304,6 +683,58 @@ fn convert_glsl(
     //check_targets(&module, name, targets);
 }
 
+/// Translates the `main` function of a shader with a single stage.
+#[cfg(feature = "glsl-in")]
+fn convert_glsl_stage(name: &str, stage: naga::ShaderStage, targets: Targets) {
+    let mut entry_points = naga::FastHashMap::default();
+    entry_points.insert("main".to_string(), stage);
+    let module = naga::front::glsl::parse_str(
+        &std::fs::read_to_string(format!("tests/in/{}{}", name, ".glsl"))
+            .expect("Couldn't find glsl file"),
+        &naga::front::glsl::Options {
+            entry_points,
+            defines: Default::default(),
+        },
+    )
+    .unwrap();
+    check_targets(&module, name, targets);
+}
+
+#[cfg(feature = "glsl-in")]
+#[test]
+fn convert_glsl_geometry() {
+    convert_glsl_stage(
+        "geometry",
+        naga::ShaderStage::Geometry,
+        Targets::SPIRV | Targets::GLSL | Targets::GLSL_FROM_SPIRV,
+    );
+}
+
+#[cfg(feature = "glsl-in")]
+#[test]
+fn convert_glsl_tessellation() {
+    let targets = Targets::SPIRV | Targets::GLSL | Targets::GLSL_FROM_SPIRV;
+    convert_glsl_stage(
+        "tess-control",
+        naga::ShaderStage::TessellationControl,
+        targets,
+    );
+    convert_glsl_stage(
+        "tess-evaluation",
+        naga::ShaderStage::TessellationEvaluation,
+        targets,
+    );
+}
+
+#[cfg(feature = "glsl-in")]
+#[test]
+fn convert_glsl_ray_tracing() {
+    let targets = Targets::SPIRV | Targets::GLSL | Targets::GLSL_FROM_SPIRV;
+    convert_glsl_stage("ray-generation", naga::ShaderStage::RayGeneration, targets);
+    convert_glsl_stage("ray-intersection", naga::ShaderStage::Intersection, targets);
+    convert_glsl_stage("ray-any-hit", naga::ShaderStage::AnyHit, targets);
+}
+
 #[cfg(feature = "glsl-in")]
 #[test]
 fn convert_glsl_quad() {